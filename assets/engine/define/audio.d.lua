@@ -1,72 +1,128 @@
+--- every effect is a table with a `type`, unknown fields and parameters out of range are errors,
+--- e.g. `{type = "compressor", ratio = 4, mix = 0.5}`
 ---@class Compressor
----@field attack_duration number|nil
----@field  makeup_gain number|nil
----@field  mix number|nil
----@field  ratio number|nil
----@field  release_duration number|nil
----@field  threshold number|nil
+---@field type "compressor"
+---@field attack_duration number|nil milliseconds
+---@field makeup_gain number|nil
+---@field mix number|nil 0..1
+---@field ratio number|nil >= 1
+---@field release_duration number|nil milliseconds
+---@field threshold number|nil
 
+---@class Delay
+---@field type "delay"
+---@field delay_time number|nil milliseconds
+---@field feedback number|nil
+---@field mix number|nil 0..1
 
----@class  Delay
----@field  delay_time number|nil
----@field  feedback number|nil
----@field  mix number|nil
-
----@class  Distortion
----@field  drive number|nil
----@field  kind "HardClip" | "SoftClip" | nil
----@field  mix number|nil
-
-
----@class  EqFilter
----@field  kind "Bell" | "LowShelf" | "HighShelf" | nil
----@field  frequency number|nil
----@field  gain number|nil
----@field  q number|nil
+---@class Distortion
+---@field type "distortion"
+---@field drive number|nil
+---@field kind "HardClip" | "SoftClip" | nil
+---@field mix number|nil 0..1
 
+---@class EqFilter
+---@field type "eq_filter"
+---@field kind "Bell" | "LowShelf" | "HighShelf" | nil
+---@field frequency number|nil > 0
+---@field gain number|nil
+---@field q number|nil > 0
 
 ---@class Filter
----@field cutoff number|nil
----@field mix number|nil
+---@field type "filter"
+---@field cutoff number|nil > 0
+---@field mix number|nil 0..1
 ---@field mode "LowPass" | "BandPass" | "HighPass" | "Notch" | nil
----@field resonance number|nil
+---@field resonance number|nil 0..1
 
 ---@class PanningControl
----@field panning number
+---@field type "panning_control"
+---@field panning number -1..1
 
----@class  Reverb
----@field  damping number|nil
----@field  feedback number|nil
----@field  mix number|nil
----@field  stereo_width number|nil
----@class  VolumeControl 
----@field  volume number
+---@class Reverb
+---@field type "reverb"
+---@field damping number|nil 0..1
+---@field feedback number|nil 0..1
+---@field mix number|nil 0..1
+---@field stereo_width number|nil 0..1
 
----@class EffectConfig
----@field Compressor Compressor| nil
----@field Delay Delay | nil
----@field EqFilter EqFilter | nil
----@field Filter Filter | nil
----@field PanningControl PanningControl | nil
----@field Reverb Reverb | nil
+---@class VolumeControl
+---@field type "volume_control"
+---@field volume number
 
+---@alias EffectConfig Compressor|Delay|Distortion|EqFilter|Filter|PanningControl|Reverb|VolumeControl
 
+---@class PlayOptions
+---@field volume number|nil
+---@field panning number|nil
+---@field position number|nil
+---@field rate number|nil 0.25..4.0, defaults to 1.0
 
 ---@class Audio
 local Audio = {}
----@param name string
+---groups form a tree under the master, "sfx/weapons" is mixed through "sfx",
+---the groups missing above it are added with volume 0 and no effects.
+---adding a group that exists replaces it and the groups under it
+---@param name string a group path like "music" or "sfx/weapons"
 ---@param volume number
 ---@param persist boolean
----@param effects EffectConfig|nil
+---@param effects table<string, EffectConfig>|nil by effect name
 function Audio:add_group(name, volume, persist, effects)
 end
 
+---removes the group and the groups under it, their sounds are cut off
+---unless their group was added with persist
+---@param group string
+function Audio:remove_group(group)
+end
+
+---@class AudioGroup
+---@field name string
+---@field path string
+---@field children AudioGroup[]
+
+---the groups right under the master, sorted by name
+---@return AudioGroup[]
+function Audio:list_groups()
+    return {}
+end
+
+---the volume of a group, the groups under it follow
+---@param group string
+---@param volume number
+---@param duration number
+function Audio:set_group_volume(group, volume, duration)
+end
+
+---pause every sound of a group and the groups under it
+---@param group string
+---@param duration number
+function Audio:pause_group(group, duration)
+end
+
+---@param group string
+---@param duration number
+function Audio:resume_group(group, duration)
+end
+
+---an options table, or volume, panning and position as before
 ---@param group string
 ---@param audio string
----@param volume number | nil
----@param panning number | nil
----@param position number | nil
-function Audio:play(group, audio, volume, panning, position)
+---@param options PlayOptions | number | nil
+---@param panning number | nil with a volume
+---@param position number | nil with a volume
+function Audio:play(group, audio, options, panning, position)
+end
+
+---play generated samples, -1..1, interleaved left and right with 2 channels
+---@param group string
+---@param samples number[]
+---@param sample_rate number
+---@param channels 1|2|nil 1 by default
+---@param options PlayOptions | nil
+---@return string audio the name pause, stop and the others take for it
+function Audio:play_pcm(group, samples, sample_rate, channels, options)
+    return ""
 end
 
 ---@param group string
@@ -113,12 +169,139 @@ end
 function Audio:set_panning(group, audio, panning, duration)
 end
 
+---@param group string
+---@param audio string
+---@param rate number 0.25..4.0
+---@param duration number
+function Audio:set_playback_rate(group, audio, rate, duration)
+end
+
+---scale the rate of all sounds in time scaled groups
+---@param scale number 0.25..4.0
+---@param duration number
+function Audio:set_time_scale(scale, duration)
+end
+
+---@return number
+function Audio:time_scale()
+    return 1.0
+end
+
+---levels of everything playing after the master volume, they fall off within about a second
+---@return number peak 0..1, 1 is full scale
+---@return number rms 0..1
+function Audio:master_levels()
+    return 0.0, 0.0
+end
+
+---@param group string
+---@param scaled boolean
+function Audio:set_time_scaled(group, scaled)
+end
+
+---decode ahead of time so the first play does not hitch, e.g. behind a loading screen
+---@param audio string|string[]
+function Audio:preload(audio)
+end
+
+---drop a decoded sound, the next play decodes it again
+---@param audio string
+function Audio:unload(audio)
+end
+
+---@class AudioCacheStats
+---@field hits number plays and preloads that did not decode
+---@field misses number
+---@field sounds number decoded sounds kept
+---@field bytes number
+---@field max_bytes number
+
+---@return AudioCacheStats
+function Audio:stats()
+    return {}
+end
+
+---bytes of decoded sounds kept, least recently played ones are dropped first
+---@param max_bytes number
+function Audio:set_cache_limit(max_bytes)
+end
+
+---silence a group and the groups under it, unmuting restores its volume
+---@param group string
+---@param muted boolean
+function Audio:set_mute(group, muted)
+end
+
+---while any group is soloed all other groups are silenced, except the ones
+---above and under a soloed group
+---@param group string
+---@param solo boolean
+function Audio:set_solo(group, solo)
+end
+
+---@param group string
+function Audio:mute_group(group)
+end
+
+---@param group string
+function Audio:unmute_group(group)
+end
+
+---set_solo on, groups added while it is soloed start silenced too
+---@param group string
+function Audio:solo_group(group)
+end
+
+---clear every solo
+function Audio:unsolo()
+end
+
+---@param group string
+---@return boolean|nil muted itself, nil when the group does not exist
+function Audio:is_group_muted(group)
+    return false
+end
+
+---@param group string
+---@return boolean|nil
+function Audio:is_group_soloed(group)
+    return false
+end
+
+---silence a sound, it keeps playing so unmuting picks up where it is by then
+---@param group string
+---@param audio string
+function Audio:mute(group, audio)
+end
+
+---back at the last volume set, also one set while it was muted
+---@param group string
+---@param audio string
+function Audio:unmute(group, audio)
+end
+
+---@param group string
+---@param audio string
+---@return boolean|nil muted the sound itself, nil when it is not playing
+function Audio:is_muted(group, audio)
+    return false
+end
+
 ---@param group string
 ---@param audio string
 ---"Playing"|"Pausing" | "Paused" | "WaitingToResume" | "Resuming" | "Stopping"| "Stopped"
----@return string | nil
+---@return string | nil state
+---@return number | nil rate current rate with the time scale applied
 function Audio:state(group, audio)
-    return ""
+    return "", 1.0
+end
+
+---@param group string
+---@param audio string
+---@return number | nil position in seconds
+---@return number | nil rate current rate with the time scale applied
+function Audio:position(group, audio)
+    return 0.0, 1.0
 end
 
 ---@param group string
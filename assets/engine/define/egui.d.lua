@@ -226,6 +226,38 @@ function UIContext:set_row_height(height) end
 
 function UIContext:end_row() end
 
+---@class VirtualList
+---@field id string selection and scroll position are kept by id
+---@field total number
+---@field row_height number? default 24
+---@field select "none" | "single" | "multi" | nil shift selects a range, ctrl toggles
+---@field render fun(ui: UIContext, index: number) only called for visible rows
+
+---@param args VirtualList
+function UIContext:virtual_list(args) end
+
+---@class TableColumn
+---@field title string
+---@field width number? remaining width if nil
+
+---@class VirtualTable
+---@field id string
+---@field total number
+---@field row_height number? default 24
+---@field select "none" | "single" | "multi" | nil
+---@field columns TableColumn[]
+---@field cell fun(ui: UIContext, row: number, col: number)
+---@field sort fun(col: number, ascending: boolean)? headers are clickable when set
+
+---@param args VirtualTable
+function UIContext:virtual_table(args) end
+
+---@param id string
+---@return number[] selected rows, 1 based
+function UIContext:list_selection(id)
+    return {}
+end
+
 ---@class Rotate
 ---@field angle number 0.0 - 2π
 ---@field origin Point 0.0 - 1.0
@@ -271,6 +303,7 @@ end
 ---@field active_fg_color? Color8
 ---@field inactive_fg_color? Color8,
 ---@field open_fg_color? Color8
+---@field toast? ToastTheme
 ---@param style LuaGuiStyle
 ---@diagnostic disable-next-line: lowercase-global
 function UIContext:set_style(style)
@@ -305,6 +338,7 @@ end
 ---@field stroke_width? number
 ---@field stroke_color? Color8
 ---@class UiConfig
+---@field id? string keeps the window where the player left it between runs
 ---@field title string
 ---@field collapsible? boolean
 ---@field constrain? boolean
@@ -325,4 +359,108 @@ end
 ---@diagnostic disable-next-line: lowercase-global
 function UIContext:draw_window(config, body)
 
-end
\ No newline at end of file
+end
+---load a declarative ui form (.toml or .json) with a [window] UiConfig and a widgets tree,
+---not a window layout preset like load_layout
+---@param path string
+---@return UIForm
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:load_form(path)
+    return {}
+end
+
+---forget where the windows with an id were moved, they open where the config puts them
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:reset_layout() end
+
+---keep the current window layout as a preset
+---@param name string
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:save_layout(name) end
+
+---move the windows with an id to a saved preset
+---@param name string
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:load_layout(name) end
+
+---keep screen edges for tool windows, the scene is centered in what is left
+---@param left number
+---@param top number
+---@param right number
+---@param bottom number
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:set_viewport_margin(left, top, right, bottom) end
+
+---true while a text field has focus, gameplay should skip the keys then
+---@return boolean
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:wants_keyboard() return false end
+
+---true while the pointer is over a window or drags a widget
+---@return boolean
+---@diagnostic disable-next-line: lowercase-global
+function UIContext:wants_pointer() return false end
+
+---@class UIForm
+local UIForm = {}
+
+---render the form, fires the registered callbacks
+---@param ctx UIContext
+---@diagnostic disable-next-line: lowercase-global
+function UIForm:show(ctx) end
+
+---@param id string
+---@param event "click" | "change"
+---@param callback fun(form: UIForm, value: string|number|boolean|nil)
+---@diagnostic disable-next-line: lowercase-global
+function UIForm:on(id, event, callback) end
+
+---@param id string
+---@param prop "text" | "value" | "checked" | "visible" | "enabled"
+---@param value string|number|boolean
+---@diagnostic disable-next-line: lowercase-global
+function UIForm:set(id, prop, value) end
+
+---@param id string
+---@return string|number|boolean|nil
+---@diagnostic disable-next-line: lowercase-global
+function UIForm:get(id) end
+
+---@diagnostic disable-next-line: lowercase-global
+function UIForm:reload() end
+
+---fill per kind, the rest wait in a queue while max_visible are up
+---@class ToastTheme
+---@field info? Color8
+---@field warning? Color8
+---@field error? Color8
+---@field text_color? Color8
+---@field font_size? number
+---@field max_width? number
+---@field max_visible? integer
+---@field spacing? number
+
+---@class ToastOptions
+---@field text string
+---@field icon? string ui texture shown before the text
+---@field duration? number seconds fully shown, 3 by default
+---@field kind? "info"|"warning"|"error"
+---@field position? "top_left"|"top"|"top_right"|"bottom_left"|"bottom"|"bottom_right"
+---@field persistent? boolean stays until clicked or dismissed
+---@field on_click? fun(id: integer) runs on the next frame, clicking also dismisses it
+
+---notifications over the game, fading with the style's animation_time,
+---still drawn while the game is paused
+---@class Toasts
+local Toasts = {}
+---the same text and kind still up counts up instead of showing another
+---@param options ToastOptions
+---@return integer id
+function Toasts:show(options) return 0 end
+---@param id integer
+---@return boolean found
+function Toasts:dismiss(id) return false end
+function Toasts:dismiss_all() end
+---shown and queued
+---@return integer
+function Toasts:count() return 0 end
@@ -1,10 +1,23 @@
 --- Window
 ---@class Engine
 ---@field ui_ctx UIContext
+---@field toast Toasts
 ---@field window Window
 ---@field graphics Graphics
 ---@field audio Audio
 ---@field save SaveManager
+---@field actions ActionMap
+---@field mods ModManager
+---@field entities EntityRegistry
+---@field postfx PostFx
+---@field netplay Netplay
+---@field video Videos
+---@field image ImageOps
+---@field rng Rng
+---@field accessibility Accessibility
+---@field achievements Achievements
+---@field bus EventBus
+---@field resource ResourceScopes
 local Engine = {}
 
 ---@class DSLModule
@@ -44,7 +57,481 @@ function Engine:is_pause() end
 ---@diagnostic disable-next-line: lowercase-global
 function Engine:is_exiting() end
 
+---@return "init" | "running" | "paused" | "exiting"
+---@diagnostic disable-next-line: lowercase-global
+function Engine:status() end
+
+---called with the new status whenever it changes, "exiting" runs before shutdown
+---@param callback fun(status: "init" | "running" | "paused" | "exiting")
+---@diagnostic disable-next-line: lowercase-global
+function Engine:on_status_change(callback) end
+
+---mouse position in scene coordinates, undoing the scene centering and scale.
+---nil while the cursor is outside the window, see Event:cursor_pos for screen space.
+---with views set it goes through the camera of the view under the cursor, nil between them
+---@return Point | nil
+function Engine:mouse_world() return {} end
+
+---@class Camera
+---@field x number scene position in the center of the views showing through it
+---@field y number
+---@field zoom number
+---@field rotation number radians, the scene turns the other way
+local Camera = {}
+---@param x number
+---@param y number
+function Camera:look_at(x, y) end
+
+---a camera for set_views, moved by changing its fields, zoom 1 when left out
+---@param params? {x?: number, y?: number, zoom?: number, rotation?: number}
+---@return Camera
+function Engine:camera(params) return Camera end
+
+---@class ViewDef
+---@field rect number[] x, y, width and height in 0..1 of the window, kept on resize
+---@field camera? Camera a default one when left out
+
+---split screen, each view draws the scene through its camera clipped to its rect,
+---later views over earlier ones. nil goes back to the single full window camera,
+---scripts reloading reset it
+---@param views ViewDef[] | nil
+function Engine:set_views(views) end
+
+---the view under a window position, the cursor when left out, nil outside every view
+---@param pos? Point
+---@return {view: integer, x: number, y: number} | nil view is 1 based, x and y in scene coordinates
+function Engine:view_at(pos) end
+
+---true when the asset can be loaded, never logs an error, nothing is loaded
+---@param name string
+---@return boolean
+function Engine:asset_exists(name) return true end
+
+--- the same index lookup as asset_exists
+---@param name string
+---@return boolean
+function Engine:has_asset(name) return true end
+
+---@class Time
+---@field total number real seconds since init
+---@field delta number real seconds the last frame took
+---@field fps number smoothed frames per second
+local Time = {}
+---scaled seconds of this frame, 0 while paused or in a hitstop
+---@return number
+function Time:dt() return 0 end
+---@return number
+function Time:real_dt() return 0 end
+---@return number
+function Time:scaled_total() return 0 end
+---@return number
+function Time:scale() return 1 end
+---applies from the next frame: `run` gets the scaled dt and fixed updates
+---keep their dt but run less often, pause and exit stay on real time
+---@param scale number 0 freezes, 0.2 is slow motion
+function Time:set_scale(scale) end
+---freezes scaled time for a moment from the next frame
+---@param ms number
+function Time:hitstop(ms) end
+---forward the scale to audio groups marked with `Audio:set_time_scaled`,
+---clamped to the playback rate range, hitstops are not forwarded
+---@param enabled boolean
+function Time:scale_audio(enabled) end
+
+---clock of the rendered frames, shared by every hook
+---@return Time
+function Engine:time() return Time end
+
+---shows or hides the fps, entity, body and cache stats drawn over the game,
+---F3 does the same unless `debug_overlay` is off in the config
+---@return boolean visible
+function Engine:toggle_debug_overlay() return true end
+
+---pause the audio and the fixed updates while the window is in the background,
+---audio paused before it lost focus stays paused, starts as `pause_on_focus_loss` in the config
+---@param enabled boolean
+function Engine:set_pause_on_focus_loss(enabled) end
+---@return boolean
+function Engine:pause_on_focus_loss() return false end
+
+---after the current frame main.lua and init run again in a new lua state
+---with the mods as set now, the old state stays when main.lua fails to load
+function Engine:reload_scripts() end
+
+
+--- ties a physics body, a scene node key and sounds to one id
+---@class EntityRegistry
+local EntityRegistry = {}
+---@param tag string
+---@return number id
+function EntityRegistry:create(tag) return 0 end
+---@param id number
+---@param body LuaRigidBodyHandle
+---@return boolean false for unknown ids
+function EntityRegistry:attach_body(id, body) return true end
+--- any key the script draws the node by, handed back to on_destroy
+---@param id number
+---@param node any
+---@return boolean
+function EntityRegistry:attach_node(id, node) return true end
+---@param id number
+---@param group string
+---@param music string
+---@return boolean
+function EntityRegistry:attach_sound(id, group, music) return true end
+--- removes the body, stops the sounds and calls on_destroy,
+--- safe inside collision callbacks, the body is removed once event_update returns
+---@param id number
+---@return boolean
+function EntityRegistry:destroy(id) return true end
+---@param tag string
+---@return number[]
+function EntityRegistry:find_by_tag(tag) return {} end
+---@param body LuaRigidBodyHandle
+---@return number|nil
+function EntityRegistry:from_body(body) return 0 end
+---@param id number
+---@return string|nil
+function EntityRegistry:tag(id) return "" end
+--- the world attached bodies belong to
+---@param physics Physics
+function EntityRegistry:set_physics(physics) end
+---@param callback fun(id: number, tag: string, node: any)|nil
+function EntityRegistry:on_destroy(callback) end
+
+--- full screen effects, applied in the order
+--- shake, chromatic_aberration, color_grade, vignette, fade
+---@class PostFx
+local PostFx = {}
+---@alias Rgba number[] {r, g, b, a} in 0..1
+--- params by effect, missing fields keep their defaults, nil or false turns it off
+--- chromatic_aberration: {amount = 2.0} in pixels at the screen edge
+--- color_grade: {brightness = 0.0, contrast = 1.0, saturation = 1.0, tint = Rgba}
+--- vignette: {intensity = 0.5, radius = 0.6, softness = 0.4, color = Rgba}
+--- fade: {amount = 0.0, color = Rgba}
+---@param name "chromatic_aberration"|"color_grade"|"vignette"|"fade"
+---@param params table|boolean|nil
+function PostFx:set(name, params) end
+--- fades the fade color in (1.0) or out (0.0)
+---@param target number 0..1
+---@param duration number milliseconds
+---@param on_done fun()|nil called once the fade finished
+function PostFx:fade_to(target, duration, on_done) end
+---@return boolean
+function PostFx:is_fading() return false end
+---@param amplitude number pixels
+---@param frequency number hz
+---@param duration number milliseconds
+function PostFx:shake(amplitude, frequency, duration) end
+function PostFx:clear() end
+
+--- two player lockstep over udp or tcp, a frame plays once both inputs are in,
+--- late packets stall it instead of rolling back
+---@class Netplay
+local Netplay = {}
+---@class NetOptions
+---@field delay number|nil frames between submitting and playing an input, 2, the host's is used
+---@field hash_interval number|nil frames between state hash checks, 60, 0 turns them off
+---@field tcp boolean|nil false
+---@field timeout_ms number|nil the peer is dropped after this long without a packet, 5000
+
+--- waits for a player, 0 picks a free port
+---@param port number
+---@param options NetOptions|nil
+---@return number|nil port the bound port
+function Netplay:host(port, options) end
+---@param addr string "host:port"
+---@param options NetOptions|nil
+function Netplay:join(addr, options) end
+function Netplay:close() end
+---@return "idle"|"connecting"|"connected"|"disconnected"
+function Netplay:state() return "idle" end
+---@return number|nil
+function Netplay:delay() return nil end
+--- queues this frame's input, sent right away
+---@param input any serializable like save data
+---@return number|nil frame the frame it plays on, nil while not connected
+function Netplay:submit_local_input(input) end
+--- nil while the remote input is missing, the frame stalls until then and the
+--- inputs not acknowledged yet are sent again, the first delay frames have no input
+---@param frame number
+---@return {local: any, remote: any}|nil
+function Netplay:inputs_for_frame(frame) end
+---@param callback fun(state: string, reason: string|nil)|nil
+function Netplay:on_state(callback) end
+---@param callback fun(frame: number, local_hash: string, remote_hash: string)|nil
+function Netplay:on_desync(callback) end
+--- called with a frame due for a check once it is played, when the inputs of the next
+--- one are asked for, the hashes of both players are compared
+---@param callback fun(frame: number): number|string|nil
+function Netplay:set_state_hash(callback) end
+
+---@class ImageOps
+--- derived images are cached under a key made from the source and the operation,
+--- e.g. "icons/sword.png#grayscale", and can be drawn or used as textures by that key.
+--- with a callback the work runs on a worker thread, nil is returned and
+--- callback(key, err) is called once it is done
+local ImageOps = {}
+---@param key string
+---@param w number
+---@param h number
+---@param filter "nearest"|"triangle"|"catmull_rom"|"gaussian"|"lanczos3"|nil "triangle"
+---@param callback fun(key: string|nil, err: string|nil)|nil
+---@return string|nil key
+function ImageOps:resize(key, w, h, filter, callback) end
+---@param callback fun(key: string|nil, err: string|nil)|nil
+---@return string|nil key
+function ImageOps:crop(key, x, y, w, h, callback) end
+---@param callback fun(key: string|nil, err: string|nil)|nil
+---@return string|nil key
+function ImageOps:grayscale(key, callback) end
+--- multiplied into every pixel, a is 0 when left out like every color
+---@param color {r: number, g: number, b: number, a: number}
+---@param callback fun(key: string|nil, err: string|nil)|nil
+---@return string|nil key
+function ImageOps:tint(key, color, callback) end
+---@param horizontal boolean false flips vertically
+---@param callback fun(key: string|nil, err: string|nil)|nil
+---@return string|nil key
+function ImageOps:flip(key, horizontal, callback) end
+---@class AtlasRect
+---@field x number
+---@field y number
+---@field w number
+---@field h number
+---@field u0 number
+---@field v0 number
+---@field u1 number
+---@field v1 number
+---@class Atlas
+---@field atlas_key string
+---@field width number
+---@field height number
+---@field uv_rects table<string, AtlasRect>
+--- packs the images into one of at most max_size x max_size
+---@param keys string[]
+---@param max_size number
+---@param callback fun(atlas: Atlas|nil, err: string|nil)|nil
+---@return Atlas|nil
+function ImageOps:pack_atlas(keys, max_size, callback) end
+
+---@class Videos
+local Videos = {}
+---@class VideoOptions
+---@field audio string|nil played with the video and kept on its clock
+---@field group string|nil the audio group, "music"
+
+--- a folder with a `video.toml`:
+--- `fps = 24.0` and `frames = ["0001.jpg", ...]`, or `pack = "frames.bin"` with
+--- `offsets = [...]` where each frame starts in it, png or jpeg frames,
+--- or an av1 `.webm` file when the engine is built with the webm feature
+---@param path string
+---@param options VideoOptions|nil
+---@return Video
+function Videos:open(path, options) end
+
+---@class Video
+local Video = {}
+--- the current frame stretched over a w x h rect centered on x, y, keeps
+--- showing the new frames once added to the scene
+---@param x number
+---@param y number
+---@param w number
+---@param h number
+---@return Scene
+function Video:node(x, y, w, h) end
+--- from the start again when it played to the end
+function Video:play() end
+function Video:pause() end
+---@param position number seconds
+function Video:seek(position) end
+--- jumps to the end, on_finished runs as if it played through
+function Video:skip() end
+---@param looping boolean
+function Video:set_loop(looping) end
+---@param callback fun()|nil
+function Video:on_finished(callback) end
+---@return boolean
+function Video:is_playing() return false end
+---@return number seconds
+function Video:position() return 0 end
+---@return number seconds
+function Video:duration() return 0 end
+--- frames skipped because they were decoded too late
+---@return number
+function Video:dropped_frames() return 0 end
+--- stops it and its audio
+function Video:close() end
+
+---@class ActionMap
+local ActionMap = {}
+
+---bind an action to one or more inputs, replacing the previous ones.
+---inputs are key codes (see Event:key_pressed) or MouseLeft, MouseRight, MouseMiddle, MouseBack, MouseForward.
+---key codes bind where the key sits, e.g. "KeyW" for movement, a single character or "logical:<key>"
+---binds what the key types, e.g. "s" for a save shortcut, it is saved as "logical:s"
+---@param action string
+---@param inputs string | string[]
+function ActionMap:bind(action, inputs) end
+
+---@param action string
+---@return boolean
+function ActionMap:unbind(action) return true end
+
+---@param action string
+---@return string[]
+function ActionMap:bindings(action) return {} end
+
+---@return table<string, string[]>
+function ActionMap:all() return {} end
+
+---store the bindings with the save system, name defaults to "bindings"
+---@param name string | nil
+function ActionMap:save(name) end
 
+---replace all bindings with saved ones, name defaults to "bindings"
+---@param name string | nil
+function ActionMap:load(name) end
+
+---whether the action follows the player's hold mode, e.g. sprint or aim,
+---in "toggle" mode Event:action_held reads as on from one press to the next
+---@param action string
+---@param toggleable boolean|nil defaults to true
+function ActionMap:set_toggleable(action, toggleable) end
+
+---@class AccessibilitySettings
+---@field colorblind "none" | "protanopia" | "deuteranopia" | "tritanopia"
+---@field color_filter "correct" | "simulate" simulate shows how the game is seen
+---@field ui_scale number 0.5-4.0, scales the gui, Graphics:ui_scale for world space ui
+---@field repeat_delay number seconds before Event:action_repeat repeats
+---@field repeat_rate number repeats per second, 0 for none
+---@field hold_mode "hold" | "toggle"
+
+---the player's settings, saved and applied before init runs
+---@class Accessibility
+local Accessibility = {}
+
+---changes only the given options, effective right away and saved
+---e.g. accessibility:set{colorblind="deuteranopia", ui_scale=1.25, hold_mode="toggle"}
+---@param options AccessibilitySettings
+function Accessibility:set(options) end
+---@return AccessibilitySettings
+function Accessibility:get() return {} end
+function Accessibility:reset() end
+---@return number
+function Accessibility:ui_scale() return 1 end
+
+---@class AchievementInfo
+---@field id string
+---@field name string
+---@field description string
+---@field icon string|nil asset key
+---@field hidden boolean
+---@field unlocked boolean only in Achievements:list
+
+---defined in achievements.toml in the assets, kept in a reserved save slot,
+---or steam when built with the steam feature and it is running
+---@class Achievements
+local Achievements = {}
+---unlocking it again does nothing, it can be called every frame
+---@param id string
+---@return boolean newly_unlocked
+function Achievements:unlock(id) return true end
+---@param id string
+---@return boolean
+function Achievements:is_unlocked(id) return false end
+---int stats drop the fraction, the achievements linked to it unlock at their threshold
+---@param id string
+---@param value number
+---@return number value
+function Achievements:set_stat(id, value) return 0 end
+---@param id string
+---@param delta number
+---@return number value
+function Achievements:add_stat(id, delta) return 0 end
+---@param id string
+---@return number
+function Achievements:get_stat(id) return 0 end
+---shows how far along it is, it is not unlocked by this
+---@param id string
+---@param current number
+---@param max number
+function Achievements:progress(id, current, max) end
+---@return AchievementInfo[]
+function Achievements:list() return {} end
+---runs after the frame it was unlocked in, e.g. to show a toast
+---@param callback fun(id: string, info: AchievementInfo)|nil
+function Achievements:on_unlock(callback) end
+---@return "local" | "steam"
+function Achievements:backend() return "local" end
+
+---named events between the scripts and the engine, published any time and
+---delivered once per frame before update. Payloads are copied, like saves
+---@class EventBus
+local EventBus = {}
+---events published by a subscriber wait for the next frame
+---@param name string
+---@param payload any|nil plain data
+function EventBus:publish(name, payload) end
+---called in the order they subscribed
+---@param name string
+---@param callback fun(payload: any, name: string)
+---@return integer id
+function EventBus:subscribe(name, callback) return 0 end
+---@param id integer
+---@return boolean subscribed
+function EventBus:unsubscribe(id) return true end
+---@return integer events waiting for the next frame
+function EventBus:pending() return 0 end
+
+---@class ModInfo
+---@field id string
+---@field name string
+---@field version string
+---@field author string
+---@field dependencies string[] "id" or "id >= version"
+---@field min_engine_version string|nil
+---@field path string the folder or pak in the mods folder
+---@field enabled boolean
+---@field loaded boolean mounted right now
+---@field errors string[] why it won't load on the next reload, missing dependencies, cycles..
+
+---@class ModConflict
+---@field path string
+---@field mods string[] in load order
+---@field winner string the last one
+
+---every folder or pak with a mod.toml in the mods folder, a later mod in the load order
+---overrides the assets of the game and of the mods before it, its scripts under
+---mods/<id>/ run sandboxed, the changes are saved and take effect on Engine:reload_scripts
+---@class ModManager
+local ModManager = {}
+
+---in the preferred order, lowest priority first
+---@return ModInfo[]
+function ModManager:list() return {} end
+---@param id string
+---@return ModInfo|nil
+function ModManager:info(id) return {} end
+---@param id string
+function ModManager:enable(id) end
+---@param id string
+function ModManager:disable(id) end
+---moves the mod to index of the list, a mod still loads after its dependencies
+---@param id string
+---@param index integer
+function ModManager:reorder(id, index) end
+---the mounted mods in load order
+---@return string[]
+function ModManager:loaded() return {} end
+---whether a reload would mount something else
+---@return boolean
+function ModManager:pending() return false end
+---assets more than one loaded mod ships
+---@return ModConflict[]
+function ModManager:conflicts() return {} end
+
+---slots starting with "__" are kept by the engine and can't be used
 ---@class SaveManager
 local SaveManager = {}
 
@@ -75,4 +562,105 @@ end
 ---@diagnostic disable-next-line: lowercase-global
 function SaveManager:load(name)
     return {}
-end
\ No newline at end of file
+end
+
+---a save split into sections, each one read and written on its own,
+---`list` shows the "meta" section of it and `load` all sections by name,
+---an old save reads as a single "data" section
+---@param name string
+---@param sections table<string, any> section name to data
+function SaveManager:save_sections(name, sections) end
+
+---replaces one section, e.g. a dirty "world.chunks.3_4", the others stay untouched
+---@param name string
+---@param section string
+---@param data any
+function SaveManager:save_section(name, section, data) end
+
+---@param name string
+---@param section string
+---@return any nil when missing or broken
+function SaveManager:load_section(name, section) return nil end
+
+---@param name string
+---@return string[]
+function SaveManager:list_sections(name) return {} end
+
+---the folder the saves are in, e.g. for an "open save folder" button
+---@return string
+function SaveManager:path() return "" end
+
+---what loads inside a scope is released with it, unless an outer scope
+---or a pin holds it too
+---@class ResourceScopes
+local ResourceScopes = {}
+---@param name string
+function ResourceScopes:push_scope(name) end
+---pops and releases the innermost scope
+---@return string | nil name
+function ResourceScopes:pop_scope() return nil end
+---@param name string
+---@return number released entries
+function ResourceScopes:release_scope(name) return 0 end
+---@return string[] outermost first
+function ResourceScopes:scopes() return {} end
+---kept whatever scope loads it
+---@param name string
+function ResourceScopes:pin(name) end
+
+---@class MemoryEntry
+---@field cache string raw, image, ui_texture, cursor, icon, texture, svg or font
+---@field name string
+---@field bytes number
+---@field shared boolean also held by another scope
+
+---@class ScopeMemory
+---@field bytes number
+---@field entries MemoryEntry[] biggest first
+
+---@class MemoryReport
+---@field scopes table<string, ScopeMemory> entries loaded outside any scope are under "global"
+
+---@return MemoryReport
+function ResourceScopes:memory_report() return {} end
+
+--- deterministic generator apart from `math.random`, seeded from the clock
+--- every run, reseed it to replay a run
+---@class Rng
+local Rng = {}
+---restarts the sequence
+---@param seed integer
+function Rng:seed(seed) end
+---@return integer seed the seed the current sequence started from
+function Rng:get_seed() return 0 end
+---@param min integer
+---@param max integer
+---@return integer value in min..max, both included
+function Rng:int(min, max) return min end
+---@return number value in 0..1, 1 excluded
+function Rng:float() return 0 end
+---shuffles in place
+---@generic T
+---@param array T[]
+---@return T[] array
+function Rng:shuffle(array) return array end
+
+---debug builds only, every module required so far with the modules it required
+---@return table<string, string[]>
+function package.graph() return {} end
+
+---every loaded module with the searcher that found it: "memory", "user" or "builtin"
+---@return table<string, string>
+function debug_modules() return {} end
+
+---@class Boot
+local Boot = {}
+---read into the caches behind the splash, a missing one ends on the error screen
+---@param keys string|string[]
+function Boot:prefetch(keys) end
+---@param text string shown under the progress bar
+function Boot:stage(text) end
+
+---optional, called once main.lua is loaded and before init while the splash is up
+---@param boot Boot
+function preload(boot) end
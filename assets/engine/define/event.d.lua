@@ -42,6 +42,85 @@ function Event:key_held(key)
     return true
 end
 
+---the key codes name where a key sits, "KeyW" is W on US and Z on AZERTY keyboards,
+---these are the same as key_pressed/key_released/key_held
+---@param key string
+---@return boolean
+function Event:key_pressed_physical(key) return true end
+---@param key string
+---@return boolean
+function Event:key_released_physical(key) return true end
+---@param key string
+---@return boolean
+function Event:key_held_physical(key) return true end
+
+---what the key types in the player's layout, a character like "z" in either case
+---or a named key like "Escape"
+---@param key string
+---@return boolean
+function Event:key_pressed_logical(key) return true end
+---@param key string
+---@return boolean
+function Event:key_released_logical(key) return true end
+---@param key string
+---@return boolean
+function Event:key_held_logical(key) return true end
+
+---the label of a key code in the player's layout for binding menus, "Z" for "KeyW" on AZERTY,
+---the US label until the key was pressed once
+---@param key string
+---@return string
+function Event:key_display_name(key) return "" end
+
+---@class QueuedEvent
+---@field type "key" | "mouse" | "wheel"
+---@field key string|nil key code of a key event, nil for keys without one
+---@field logical string|nil what the key types, "a" or a named key like "Escape"
+---@field button string|nil "Left", "Right", "Middle"..
+---@field pressed boolean|nil false for a release
+---@field repeat boolean|nil a key held down repeating
+---@field x number|nil wheel lines
+---@field y number|nil wheel lines
+
+---every press, release and scroll since the last frame in the order they came,
+---nothing is merged, two taps between frames are two presses. In update it is
+---since the last update step, each event shows up in one step
+---@return QueuedEvent[]
+function Event:events() return {} end
+
+---how often the key went down since the last frame, or update step in update,
+---repeats not counted,
+---key_pressed is true once however many taps there were
+---@param key string
+---@return integer
+function Event:key_press_count(key) return 0 end
+
+---true when any input bound to the action was pressed this frame
+---@param action string
+---@return boolean
+function Event:action_pressed(action)
+    return true
+end
+
+---@param action string
+---@return boolean
+function Event:action_released(action)
+    return true
+end
+
+---@param action string
+---@return boolean
+function Event:action_held(action)
+    return true
+end
+
+---true on the press and then at the accessibility repeat rate while held, for menus
+---@param action string
+---@return boolean
+function Event:action_repeat(action)
+    return true
+end
+
 ---@return table {x = number, y = number}
 function Event:cursor_pos()
     return {}
@@ -115,10 +194,41 @@ end
 ---@field content string
 ---@field pos nil| Point
 ---@class State
----@field state string "enabled" | "disabled" | nil
----@field preedit Preedit | nil
----@field commit string | nil
+---@field state string "enabled" | "disabled" | nil, nil before the first event
+---@field preedit Preedit | nil the composition, kept until it is committed or cleared
+---@field commit string | nil everything committed this frame
 ---@return State
 function Event:ime_state()
     return {}
 end
+
+---starts a text input session, typing, ime commits and ctrl+v paste edit it until end_text
+---@param initial string | nil
+---@param max_len number | nil max characters
+function Event:start_text(initial, max_len)
+end
+
+---@class TextSelection
+---@field start number
+---@field end number
+---@class TextState
+---@field text string
+---@field cursor number character index
+---@field selection TextSelection | nil
+---@field preedit Preedit | nil
+---@return TextState | nil nil without a running session
+function Event:text_state()
+    return {}
+end
+
+---where the ime candidate window shows, in window pixels
+---@param x number
+---@param y number
+function Event:set_ime_position(x, y)
+end
+
+---stops the session
+---@return string | nil the final text
+function Event:end_text()
+    return ""
+end
@@ -5,6 +5,7 @@ local Graphics = {}
 ---@field apply_parent_style? boolean
 ---@field drawable? SceneNodeKind
 ---@field children? Scene[]
+---errors name a bad parameter, e.g. a negative radius
 ---@param node Scene
 ---@diagnostic disable-next-line: lowercase-global
 function Graphics:draw_shape(node)
@@ -18,11 +19,48 @@ function Graphics:create_sprite(image, frame_size, num_frame)
     return{}
 end
 
+---draws an svg centered on x, y, it stays sharp at any scale
+---@param name string svg resource path
+---@param x number
+---@param y number
+---@param scale number|nil defaults to 1
+---@diagnostic disable-next-line: lowercase-global
+function Graphics:svg(name, x, y, scale)
+end
+
+---the svg viewport size before scaling
+---@param name string svg resource path
+---@return Size
+---@diagnostic disable-next-line: lowercase-global
+function Graphics:svg_size(name)
+    return {}
+end
+
 ---@param scale number float number
 ---@diagnostic disable-next-line: lowercase-global
 function Graphics:set_scale(scale)
     return{}
 end
+
+---the accessibility ui scale, for ui drawn in the scene
+---@return number
+---@diagnostic disable-next-line: lowercase-global
+function Graphics:ui_scale()
+    return 1
+end
+
+---@class GpuInfo
+---@field name string
+---@field backend string "vulkan" | "metal" | "dx12" | "gl" | "webgpu"
+---@field driver string
+---@field driver_info string
+---@field device_type string "DiscreteGpu" | "IntegratedGpu" | "VirtualGpu" | "Cpu" | "Other"
+
+---@return GpuInfo
+---@diagnostic disable-next-line: lowercase-global
+function Graphics:gpu_info()
+    return {}
+end
 ---@class Vec2
 ---@field x number
 ---@field y number
@@ -127,10 +165,27 @@ end
 ---@field sweep_angle number
 ---@field rotation number
 
+---filled it is a pie slice or, with an inner radius, a cooldown donut,
+---angles in radians clockwise from +x, a sweep past a full turn draws the full turn
+---@class Ring
+---@field center Point
+---@field outer_radius number
+---@field inner_radius number 0 for a pie slice, at most outer_radius
+---@field start_angle number
+---@field sweep_angle number
+
+---filled even-odd when closed, only stroked when open
+---@class Polygon
+---@field points Point[] at least 3 when closed, 2 when open
+---@field closed boolean
+
 ---@class Image
 ---@field position Point
 ---@field image string
 
+---@class Svg
+---@field svg string resource path, drawn centered, use the style translation to place it
+
 ---@class PointLight
 ---@field center Point
 ---@field radius number
@@ -159,10 +214,13 @@ end
 ---@field BezPath BezPath|nil
 ---@field Point Point|nil
 ---@field Arc Arc|nil
+---@field Ring Ring|nil
+---@field Polygon Polygon|nil
 ---@field Image Image|nil
 ---@field PointLight PointLight|nil
 ---@field LightMask LightMask|nil
 ---@field Text Text|nil
+---@field Svg Svg|nil
 
 ---@class Join 
 --- Bevel,
@@ -206,6 +264,19 @@ end
 ---@field Solid Solid
 ---@class Affine number[] 6
 
+---@class TextOutline
+--- how far the outline reaches past the glyph edge
+---@field width number
+---@field color? Color
+
+---@class TextShadow
+--- offset in text units
+---@field dx? number
+---@field dy? number
+--- spread of the shadow, 0 for a hard one
+---@field blur? number
+---@field color? Color
+
 ---@class Style
 ---@field translation? Affine
 ---@field fill? Brush,
@@ -221,4 +292,8 @@ end
 ---@field hint? boolean | nil
 ---@field align? "Left"| "Center" | "Right" | nil
 ---@field line_spacing? number | nil
----@field vertical? boolean | nil
\ No newline at end of file
+---@field vertical? boolean | nil
+--- stroked beneath the fill
+---@field outline? TextOutline | nil
+--- drawn beneath the outline and the fill
+---@field shadow? TextShadow | nil
\ No newline at end of file
@@ -0,0 +1,220 @@
+--- require("fool.math"), implemented in rust
+---@class FoolMath
+local FoolMath = {}
+
+---@class Vec2
+---@field x number
+---@field y number
+---@operator add(Vec2): Vec2
+---@operator sub(Vec2): Vec2
+---@operator mul(number): Vec2
+---@operator div(number): Vec2
+---@operator unm: Vec2
+local Vec2 = {}
+---@param other Vec2|Point
+---@return Vec2
+function Vec2:add(other) return {} end
+---@param other Vec2|Point
+---@return Vec2
+function Vec2:sub(other) return {} end
+---@param s number
+---@return Vec2
+function Vec2:scale(s) return {} end
+---@param other Vec2|Point
+---@return number
+function Vec2:dot(other) return 0 end
+---@param other Vec2|Point
+---@return number
+function Vec2:cross(other) return 0 end
+---@return number
+function Vec2:length() return 0 end
+---@return number
+function Vec2:length_sq() return 0 end
+---zero vectors stay zero
+---@return Vec2
+function Vec2:normalize() return {} end
+---@param angle number radians
+---@return Vec2
+function Vec2:rotate(angle) return {} end
+---@return number radians
+function Vec2:angle() return 0 end
+---@param other Vec2|Point
+---@return number
+function Vec2:distance(other) return 0 end
+---@param other Vec2|Point
+---@param t number
+---@return Vec2
+function Vec2:lerp(other, t) return {} end
+---@return number, number
+function Vec2:unpack() return 0, 0 end
+---@return Point
+function Vec2:to_point() return {} end
+
+---@class Vec3
+---@field x number
+---@field y number
+---@field z number
+---@operator add(Vec3): Vec3
+---@operator sub(Vec3): Vec3
+---@operator mul(number): Vec3
+---@operator div(number): Vec3
+---@operator unm: Vec3
+local Vec3 = {}
+---@param other Vec3
+---@return Vec3
+function Vec3:add(other) return {} end
+---@param other Vec3
+---@return Vec3
+function Vec3:sub(other) return {} end
+---@param s number
+---@return Vec3
+function Vec3:scale(s) return {} end
+---@param other Vec3
+---@return number
+function Vec3:dot(other) return 0 end
+---@param other Vec3
+---@return Vec3
+function Vec3:cross(other) return {} end
+---@return number
+function Vec3:length() return 0 end
+---@return number
+function Vec3:length_sq() return 0 end
+---zero vectors stay zero
+---@return Vec3
+function Vec3:normalize() return {} end
+---@param other Vec3
+---@return number
+function Vec3:distance(other) return 0 end
+---@param other Vec3
+---@param t number
+---@return Vec3
+function Vec3:lerp(other, t) return {} end
+---@return number, number, number
+function Vec3:unpack() return 0, 0, 0 end
+
+---2d affine transform, `m * v` transforms a Vec2, `a * b` applies b first
+---@class Mat
+---@operator mul(Mat): Mat
+---@operator mul(Vec2): Vec2
+local Mat = {}
+---@param other Mat
+---@return Mat
+function Mat:mul(other) return {} end
+---@param p Vec2|Point
+---@return Vec2
+function Mat:transform(p) return {} end
+---without the translation
+---@param v Vec2|Point
+---@return Vec2
+function Mat:transform_vector(v) return {} end
+---@return Mat|nil nil when it can't be inverted
+function Mat:inverse() return nil end
+---@return number
+function Mat:determinant() return 0 end
+---@return Vec2
+function Mat:translation() return {} end
+---@return number[] a, b, c, d, e, f
+function Mat:coeffs() return {} end
+---@return Mat
+function Mat:then_translate(x, y) return {} end
+---@param angle number radians
+---@return Mat
+function Mat:then_rotate(angle) return {} end
+---@param sx number
+---@param sy number|nil defaults to sx
+---@return Mat
+function Mat:then_scale(sx, sy) return {} end
+
+---@class MatConstructors
+---@field new fun(a: number, b: number, c: number, d: number, e: number, f: number): Mat
+---@field identity fun(): Mat
+---@field translate fun(x: number, y: number): Mat
+---@field rotate fun(angle: number): Mat
+---@field scale fun(sx: number, sy: number|nil): Mat
+
+---@class Rect
+---@field x number
+---@field y number
+---@field width number
+---@field height number
+local Rect = {}
+---@param p Vec2|Point
+---@return boolean
+function Rect:contains(p) return false end
+---touching edges don't intersect
+---@param other Rect|table
+---@return boolean
+function Rect:intersects(other) return false end
+---@param other Rect|table
+---@return Rect|nil
+function Rect:intersection(other) return nil end
+---@param other Rect|table
+---@return Rect
+function Rect:union(other) return {} end
+---@return Vec2
+function Rect:center() return {} end
+
+---@param x number|nil
+---@param y number|nil
+---@return Vec2
+function FoolMath.vec2(x, y) return {} end
+---@param x number|nil
+---@param y number|nil
+---@param z number|nil
+---@return Vec3
+function FoolMath.vec3(x, y, z) return {} end
+---@type MatConstructors
+FoolMath.mat = {}
+---@param x number
+---@param y number
+---@param width number
+---@param height number
+---@return Rect
+function FoolMath.rect(x, y, width, height) return {} end
+---@return number
+function FoolMath.lerp(a, b, t) return 0 end
+---@return number
+function FoolMath.clamp(v, min, max) return 0 end
+---map v from in_min..in_max to out_min..out_max
+---@return number
+function FoolMath.remap(v, in_min, in_max, out_min, out_max) return 0 end
+---@return number
+function FoolMath.smoothstep(edge0, edge1, x) return 0 end
+---wrap radians into -pi..pi
+---@return number
+function FoolMath.wrap_angle(angle) return 0 end
+---signed shortest rotation in radians
+---@return number
+function FoolMath.shortest_delta(from, to) return 0 end
+
+---t is clamped to 0..1
+---@class Ease
+---@field linear fun(t: number): number
+---@field in_quad fun(t: number): number
+---@field out_quad fun(t: number): number
+---@field in_out_quad fun(t: number): number
+---@field in_cubic fun(t: number): number
+---@field out_cubic fun(t: number): number
+---@field in_out_cubic fun(t: number): number
+---@field in_quart fun(t: number): number
+---@field out_quart fun(t: number): number
+---@field in_out_quart fun(t: number): number
+---@field in_sine fun(t: number): number
+---@field out_sine fun(t: number): number
+---@field in_out_sine fun(t: number): number
+---@field in_expo fun(t: number): number
+---@field out_expo fun(t: number): number
+---@field in_out_expo fun(t: number): number
+---@field in_back fun(t: number): number
+---@field out_back fun(t: number): number
+---@field out_elastic fun(t: number): number
+---@field out_bounce fun(t: number): number
+---@field apply fun(name: string, t: number): number
+FoolMath.ease = {}
+
+---@class Noise
+---@field perlin2 fun(x: number, y: number, seed: integer|nil): number about -1..1
+---@field simplex2 fun(x: number, y: number, seed: integer|nil): number about -1..1
+FoolMath.noise = {}
+
+return FoolMath
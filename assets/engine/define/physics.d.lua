@@ -60,17 +60,75 @@ end
 function Physics:update()
 end
 
+---solver substeps per update, more keeps stacks and fast bodies stable under large forces
+---at the cost of time, 4 by default
+---@param substeps integer at least 1
+function Physics:set_substeps(substeps) end
+
+---seconds one update advances, 1/60 by default. A fixed timestep driven from the fixed
+---update keeps runs repeatable: the same bodies added in the same order with the same
+---inputs step the same way on the same build. Steps with a varying dt, or across
+---platforms, may drift apart.
+---@param dt number above 0
+function Physics:set_timestep(dt) end
+
+---continuous collision detection, keeps fast bodies from passing through thin ones,
+---for the bodies there are and the ones added later, on by default
+---@param enabled boolean
+function Physics:set_ccd(enabled) end
+
 ---@param config PhysicsBodyConfig
 ---@return LuaRigidBodyHandle
 function Physics:add_body(config)
     return {} -- placeholder for a real body handle
 end
 
+--- inside collision callbacks the body is removed once event_update returns,
+--- its data goes along unless another body has the same user_data
 ---@param handle LuaRigidBodyHandle
 function Physics:remove_body(handle)
     return {} -- placeholder for a real body handle
 end
 
+---attach game data to the user_data of a body, handed to collision callbacks as
+---data1 and data2. The table is copied, changing it afterwards or the copies from
+---get_data and events changes nothing until it is set again. Plain data only, at
+---most 4KiB as json unless set_data_limit says otherwise, nil removes it
+---@param key LuaRigidBodyHandle|integer a body or its user_data
+---@param table table|nil
+function Physics:set_data(key, table)
+end
+
+---@param key LuaRigidBodyHandle|integer
+---@return table|nil
+function Physics:get_data(key)
+    return {}
+end
+
+---@param key LuaRigidBodyHandle|integer
+---@return boolean removed false when nothing was attached
+function Physics:remove_data(key)
+    return true
+end
+
+---json bytes one set_data may take, for the tables set from now on
+---@param bytes integer
+function Physics:set_data_limit(bytes)
+end
+
+---the bodies, colliders, joints and their set_data tables in one binary string,
+---storable with SaveManager. The step and query pipelines are rebuilt on restore,
+---not serialized
+---@return string
+function Physics:serialize()
+end
+
+---replaces the world with one from serialize, handles taken before serialize find
+---the same bodies again. Callbacks and contact groups are kept as they are
+---@param bytes string
+function Physics:deserialize(bytes)
+end
+
 ---@return LuaRigidBody[]
 function Physics:get_bodies()
     return {} -- return list of rigid bodies
@@ -106,6 +164,81 @@ function Physics:set_angvel(handle, angvel)
     -- set angular velocity
 end
 
+---the collider properties are set on every collider of the body and read from the first,
+---the setters return false and the getters nil for a removed body
+---@param handle LuaRigidBodyHandle
+---@param friction number averaged with the friction of what it touches
+---@return boolean
+function Physics:set_friction(handle, friction) return true end
+---@param handle LuaRigidBodyHandle
+---@return number?
+function Physics:get_friction(handle) return 0 end
+---@param handle LuaRigidBodyHandle
+---@param restitution number
+---@return boolean
+function Physics:set_restitution(handle, restitution) return true end
+---@param handle LuaRigidBodyHandle
+---@return number?
+function Physics:get_restitution(handle) return 0 end
+---mass and inertia follow right away
+---@param handle LuaRigidBodyHandle
+---@param density number
+---@return boolean
+function Physics:set_density(handle, density) return true end
+---@param handle LuaRigidBodyHandle
+---@return number?
+function Physics:get_density(handle) return 0 end
+---@param handle LuaRigidBodyHandle
+---@param sensor boolean
+---@return boolean
+function Physics:set_sensor(handle, sensor) return true end
+---@param handle LuaRigidBodyHandle
+---@return boolean?
+function Physics:is_sensor(handle) return false end
+---@param handle LuaRigidBodyHandle
+---@param scale number
+---@return boolean
+function Physics:set_gravity_scale(handle, scale) return true end
+---@param handle LuaRigidBodyHandle
+---@return number?
+function Physics:get_gravity_scale(handle) return 1 end
+---@param handle LuaRigidBodyHandle
+---@param damping number
+---@return boolean
+function Physics:set_linear_damping(handle, damping) return true end
+---@param handle LuaRigidBodyHandle
+---@return number?
+function Physics:get_linear_damping(handle) return 0 end
+---@param handle LuaRigidBodyHandle
+---@param damping number
+---@return boolean
+function Physics:set_angular_damping(handle, damping) return true end
+---@param handle LuaRigidBodyHandle
+---@return number?
+function Physics:get_angular_damping(handle) return 0 end
+---a disabled body keeps its velocity and joints but is left out of the simulation
+---@param handle LuaRigidBodyHandle
+---@param enabled boolean
+---@return boolean
+function Physics:set_enabled(handle, enabled) return true end
+---@param handle LuaRigidBodyHandle
+---@return boolean?
+function Physics:is_enabled(handle) return true end
+---@param handle LuaRigidBodyHandle
+---@param locked boolean
+---@return boolean
+function Physics:lock_rotations(handle, locked) return true end
+---@param handle LuaRigidBodyHandle
+---@return boolean?
+function Physics:is_rotation_locked(handle) return false end
+---@param handle LuaRigidBodyHandle
+---@param locked boolean
+---@return boolean
+function Physics:lock_translations(handle, locked) return true end
+---@param handle LuaRigidBodyHandle
+---@return boolean?
+function Physics:is_translation_locked(handle) return false end
+
 ---@param handle LuaRigidBodyHandle
 ---@param strong boolean
 function Physics:wake_up(handle, strong)
@@ -134,6 +267,51 @@ function Physics:cast_ray(origin, dir, max_toi)
     return {}
 end
 
+---@class PhysicsDebugDraw
+---@field colliders? boolean
+---@field aabbs? boolean
+---@field contacts? boolean
+---@field joints? boolean
+---@field sleep? boolean dims the sleeping bodies
+---@field line_width? number in screen pixels, 1 by default
+---drawn over the scene each frame for the world set with entities:set_physics,
+---unlisted categories are turned off, nil or {} turns it all off
+---@param options PhysicsDebugDraw|nil
+function Physics:debug_draw(options)
+end
+
+---same fields as add_body takes, nil for removed bodies
+---@param handle LuaRigidBodyHandle
+---@return PhysicsBodyConfig|nil
+function Physics:get_collider_info(handle)
+    return {}
+end
+
+---@class Aabb
+---@field min Point
+---@field max Point
+---world space bounds of the body colliders, nil for removed bodies
+---@param handle LuaRigidBodyHandle
+---@return Aabb|nil
+function Physics:get_aabb(handle)
+    return {}
+end
+
+---@param handle LuaRigidBodyHandle
+---@param point Point
+---@return boolean|nil
+function Physics:contains_point(handle, point)
+    return true
+end
+
+---bodies intersecting the region, as of the last update
+---@param min Point
+---@param max Point
+---@return LuaRigidBodyHandle[]
+function Physics:colliders_in_aabb(min, max)
+    return {}
+end
+
 ---only for sensor
 ---@return string[]
 function Physics:list_ignore_intersection_group()
@@ -178,11 +356,35 @@ end
 ---@class LuaCollisionEvent
 ---@field started? CollisionEvent
 ---@field stopped? CollisionEvent
+---@field b1 LuaRigidBodyHandle
+---@field b2 LuaRigidBodyHandle
+---@field kind "started" | "stopped"
+---@field sensor boolean
+---@field removed boolean
+---@field normal? Point world space, from b1 towards b2, only when two solid bodies start touching
+---@field points Point[] world space contact points on b1, empty without a normal
+---@field data1? table a copy of what set_data attached to b1
+---@field data2? table
 
 ---@param call_back fun(LuaCollisionEvent)
 function Physics:register_collision_event_callback(call_back)
 end
 
+---@class CollisionPayload
+---@field started boolean
+---@field sensor boolean
+---@field removed boolean
+---@field user_data1 integer
+---@field user_data2 integer
+---@field data1? table a copy of what set_data attached to b1
+---@field data2? table
+
+---publishes every collision as "collision" with a CollisionPayload from event_update,
+---e.g. phy:publish_collisions(engine.bus), nil stops it
+---@param bus EventBus|nil
+function Physics:publish_collisions(bus)
+end
+
 ---@class LuaContactForceEvent
 ---@field b1 LuaRigidBodyHandle
 ---@field b2 LuaRigidBodyHandle
@@ -0,0 +1,18 @@
+--- require("resource"), loads off the main thread, implemented in rust
+---@class ResourceModule
+local ResourceModule = {}
+
+--- starts loading key in the background, the callback or coroutine gets
+--- (data, err) on a later frame, data is the raw bytes as a string
+---@param key string
+---@param callback fun(data: string?, err: string?)|thread
+function ResourceModule.load_async(key, callback) end
+--- load_async for the running coroutine, yields until the data is there
+---@param key string
+---@return string? data
+---@return string? err
+function ResourceModule.load(key) end
+--- true once key is in the cache
+---@param key string
+---@return boolean
+function ResourceModule.is_loaded(key) return false end
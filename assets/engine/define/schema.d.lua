@@ -0,0 +1,19 @@
+--- require("schema"), checks untrusted tables like configs and saves, implemented in rust
+---@class SchemaModule
+local SchemaModule = {}
+
+--- a type name: "string", "number", "integer", "boolean", "table", "function", "userdata"
+--- or "any", ending in "?" it also takes nil. `{ schema }` checks every item of an array,
+--- any other table checks the fields it names, fields it doesn't name are allowed:
+--- `{ name = "string", hp = "number", tags = { "string" }, pet = { name = "string?" } }`
+---@alias Schema string | table
+
+--- true, or false and the first mismatch with its path:
+--- "tags[2]: expected string, got number". A malformed schema raises an error
+---@param value any
+---@param schema Schema
+---@return boolean ok
+---@return string | nil mismatch
+function SchemaModule.validate(value, schema) return true end
+
+return SchemaModule
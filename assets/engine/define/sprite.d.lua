@@ -23,6 +23,17 @@ function Sprite:list_animation()
     return {}
 end
 
+---a state machine over the animations of this sprite, from a .toml or .json file:
+---initial, [states.<name>] with animation and speed, [[transitions]] with from
+---(a state or "any"), to, conditions ({ param, op, value }), duration and exit_time.
+---fails listing every state and transition naming something missing
+---@param path string
+---@return AnimationController
+---@diagnostic disable-next-line: lowercase-global
+function Sprite:create_controller(path)
+    return {}
+end
+
 ---@class Animation
 local Animation = {}
 
@@ -38,11 +49,54 @@ function Animation:current()
     return 0
 end
 
+---moves on by the scaled time since the last call, see `Time:set_scale`
 ---@diagnostic disable-next-line: lowercase-global
 function Animation:next()
 end
 
+---follow real time, slow motion and hitstops do not slow it down
+---@param unscaled boolean
+---@diagnostic disable-next-line: lowercase-global
+function Animation:set_unscaled(unscaled)
+end
+
 ---@param position Point
 ---@diagnostic disable-next-line: lowercase-global
 function Animation:draw(position)
-end
\ No newline at end of file
+end
+---@class AnimationController
+local AnimationController = {}
+
+---@param name string
+---@param value number
+function AnimationController:set(name, value) end
+
+---@param name string
+---@param value boolean
+function AnimationController:set_bool(name, value) end
+
+---true until a transition checking it is taken
+---@param name string
+function AnimationController:trigger(name) end
+
+---moves on by dt seconds, then takes at most one transition
+---@param dt number
+function AnimationController:step(dt) end
+
+---@return string
+function AnimationController:state() return "" end
+
+---passes through the current animation, 1.5 is halfway through the second
+---@return number
+function AnimationController:normalized_time() return 0 end
+
+---0..1 through the crossfade, nil while not blending
+---@return number|nil
+function AnimationController:blend() return nil end
+
+---the states entered and left since the last call, in order
+---@return { kind: "enter" | "exit", state: string }[]
+function AnimationController:events() return {} end
+
+---@param position Point
+function AnimationController:draw(position) end
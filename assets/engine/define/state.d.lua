@@ -0,0 +1,45 @@
+--- require("state"), a table that reports every write, implemented in rust
+---@class StateModule
+local StateModule = {}
+
+--- paths are dot separated, numeric parts index arrays: "player.items.1.name"
+---@class StateHandle
+local StateHandle = {}
+---@param path string?
+---@return any
+function StateHandle:get(path) end
+---@param path string
+---@param value any
+---@return any old
+function StateHandle:set(path, value) end
+---@param callback fun(path: string, value: any, old: any)
+---@return number id
+function StateHandle:subscribe(callback) return 0 end
+---@param id number
+---@return boolean
+function StateHandle:unsubscribe(id) return false end
+
+--- the initial table is copied
+---@param initial table?
+---@return StateHandle
+function StateModule.create(initial) return {} end
+--- get returns live tables, change them through set so subscribers are called
+---@param handle StateHandle
+---@param path string?
+---@return any
+function StateModule.get(handle, path) end
+---@param handle StateHandle
+---@param path string
+---@param value any
+---@return any old
+function StateModule.set(handle, path, value) end
+---@param handle StateHandle
+---@param callback fun(path: string, value: any, old: any)
+---@return number id
+function StateModule.subscribe(handle, callback) return 0 end
+---@param handle StateHandle
+---@param id number
+---@return boolean
+function StateModule.unsubscribe(handle, id) return false end
+
+return StateModule
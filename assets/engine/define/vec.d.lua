@@ -0,0 +1,15 @@
+--- require("vec"), the vector types of fool.math on their own
+---@class VecModule
+---@field vec2 fun(x: number|nil, y: number|nil): Vec2
+---@field vec3 fun(x: number|nil, y: number|nil, z: number|nil): Vec3
+---@field mat MatConstructors
+local Vec = {}
+
+---a Vec2, or a Vec3 when z is given
+---@param x number|nil
+---@param y number|nil
+---@param z number|nil
+---@return Vec2|Vec3
+function Vec.new(x, y, z) return {} end
+
+return Vec
@@ -83,6 +83,12 @@ end
 function Window:monitor()
 end
 
+---false while the window is minimized or fully covered, rendering is paused
+---@return boolean
+function Window:is_visible()
+    return true
+end
+
 ---@return boolean
 function Window:is_fullscreen()
     return false
@@ -93,7 +99,48 @@ end
 function Window:capture()
 end
 
----@param fps number
+---applies from the next frame, a mode the renderer lacks falls back to a supported one
+---@param mode "area"|"msaa8"|"msaa16" -- msaa16 by default, area is the cheapest
+function Window:set_antialiasing(mode)
+end
+
+---for pixel art, from the next frame: images sample their nearest pixel, the nodes land
+---on whole pixels and the cheapest antialiasing is used, the mode set before comes back
+---when turned off
+---@param enabled boolean
+function Window:set_pixel_perfect(enabled)
+end
+
+---@alias RenderQualityName "msaa16"|"msaa8"|"area"|"area@0.85"|"area@0.75"
+
+---@class RenderQuality
+---@field name RenderQualityName
+---@field aa "area"|"msaa8"|"msaa16"
+---@field scale number -- the scene is rendered at this fraction of the window size
+---@field adaptive boolean -- [render] adaptive_quality in config.toml
+---@field floor RenderQualityName -- the lowest it steps down to
+
+---the rung of the quality ladder in use, with adaptive_quality it steps down while
+---frames take longer than the fps allows and back up once there is headroom
+---@return RenderQuality
+function Window:render_quality()
+end
+
+---the lowest quality adaptive_quality may pick, a lower current one is raised on the next frame
+---@param name RenderQualityName
+function Window:set_quality_floor(name)
+end
+
+---what the scene is drawn over from the next frame on, black by default
+---@param color string -- any css color, e.g. "#204080" or "rebeccapurple"
+function Window:set_background(color)
+end
+
+---forgets the remembered position and size, the next start opens the window as configured
+function Window:reset_layout()
+end
+
+---@param fps number|nil -- nil renders at the display rate
 ---@diagnostic disable-next-line: lowercase-global
 function Window:set_fps(fps)
 end
\ No newline at end of file
@@ -65,4 +65,12 @@ function LOG:log(level, fmt, ...)
     __logger(level, full_msg)
 end
 
+--- Logs message with fields as key=value pairs after it, e.g. `hit damage=5`
+---@param level string
+---@param message string
+---@param fields table|nil
+function LOG:event(level, message, fields)
+    log.event(level, string.format("%s: %s", self.name, message), fields)
+end
+
 return LOG
\ No newline at end of file
@@ -8,6 +8,14 @@ local shape      = require("shape")
 local lua_thread = require('lua_thread')
 register_threaded_module(lua_thread)
 
+-- checked before init runs, missing ones are reported on an error screen
+---@diagnostic disable-next-line: lowercase-global
+required_assets = {
+    "image/cursor.png",
+    "image/linux.png",
+    "fonts/SarasaTermSCNerd-Regular.ttf",
+}
+
 ---@param engine Engine
 ---@diagnostic disable-next-line: lowercase-global
 function init(engine)
@@ -49,8 +57,9 @@ end
 ---@param engine Engine
 ---@param event Event
 ---@param dt number -- delay time
+---@param alpha number -- interpolation fraction between the last two updates
 ---@diagnostic disable-next-line: lowercase-global
-function run(engine, event, dt)
+function run(engine, event, dt, alpha)
     local window = engine.window
     if event:key_pressed("Insert") then
         logger:debug("Insert pressed")
@@ -90,6 +99,13 @@ function exit(engine, event, dt)
 end
 
 
+---called once on shutdown before the renderer is dropped, bounded by exit_timeout
+---@param engine Engine
+---@diagnostic disable-next-line: lowercase-global
+function on_exit(engine)
+    logger:debug("on_exit")
+end
+
 ---@param engine Engine
 ---@param event Event
 ---@param dt number -- delay time
@@ -49,7 +49,10 @@ function shape:init(engine)
     shape.left_run_animation = shape.player_sprite:create_animation("run_left", { 16, 17, 18, 19, 20, 21, 22, 23 }, 5)
     shape.right_run_animation = shape.player_sprite:create_animation("run_right", { 24, 25, 26, 27, 28, 29, 30, 31 }, 5)
     engine.audio:add_group("default", 0.0, true, nil)
-    engine.audio:play("default", "audio/bgm.mp3", -10.0)
+    engine.audio:play("default", "audio/bgm.mp3", { volume = -10.0 })
+    engine.actions:bind("left", { "ArrowLeft", "KeyA" })
+    engine.actions:bind("right", { "ArrowRight", "KeyD" })
+    engine.actions:bind("jump", { "ArrowUp", "Space" })
 end
 
 ---@param engine Engine
@@ -58,7 +61,7 @@ function shape:view(engine, event)
     engine.graphics:set_scale(0.9)
     local state = engine.audio:state("default", "audio/bgm.mp3")
     if state ~= nil and state ~= "Playing" then
-        engine.audio:play("default", "audio/bgm.mp3", -10.0)
+        engine.audio:play("default", "audio/bgm.mp3", { volume = -10.0 })
     end
     engine.graphics:draw_shape({
         style = self.style,
@@ -139,20 +142,20 @@ function shape:view(engine, event)
         shape.up_run_animation:next()
     end
     shape.orc_last_direction = ""
-    if event:key_held("ArrowLeft") then
+    if event:action_held("left") then
         shape.orc_last_direction = "left"
         lua_thread.shared_state.orc_force.x = -100
     end
-    if event:key_held("ArrowRight") then
+    if event:action_held("right") then
         shape.orc_last_direction = "right"
         lua_thread.shared_state.orc_force.x = 100
     end
-    if event:key_held("ArrowUp") then
+    if event:action_held("jump") then
         local state = engine.audio:state("default", "audio/jump.mp3")
         if state == "Playing" then
             engine.audio:stop("default", "audio/jump.mp3", 1)
         end
-        engine.audio:play("default", "audio/jump.mp3", -8.0)
+        engine.audio:play("default", "audio/jump.mp3", { volume = -8.0, rate = math.random() * 0.2 + 0.9 })
         lua_thread.shared_state.orc_force.y = -400
     end
 end
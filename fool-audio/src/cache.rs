@@ -0,0 +1,164 @@
+use kira::{Frame, sound::static_sound::StaticSoundData};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// decoded sounds are kept up to this many bytes of samples
+pub const SOUND_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+fn data_size(data: &StaticSoundData) -> usize {
+    data.frames.len() * size_of::<Frame>()
+}
+
+/// how well the decoded sounds are reused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct CacheStats {
+    /// plays and preloads served without decoding
+    pub hits: usize,
+    pub misses: usize,
+    pub sounds: usize,
+    pub bytes: usize,
+    pub max_bytes: usize,
+}
+
+/// decoded sounds by resource path, the least recently used are dropped past `max_bytes`
+#[derive(Debug)]
+pub struct SoundCache {
+    max_bytes: usize,
+    bytes: usize,
+    tick: u64,
+    // (data, last use)
+    entries: HashMap<String, (StaticSoundData, u64)>,
+    decodes: usize,
+    hits: usize,
+    misses: usize,
+}
+
+impl SoundCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            bytes: 0,
+            tick: 0,
+            entries: Default::default(),
+            decodes: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+    pub fn get(&mut self, key: &str) -> Option<StaticSoundData> {
+        self.tick += 1;
+        let (data, used) = self.entries.get_mut(key)?;
+        *used = self.tick;
+        Some(data.clone())
+    }
+    /// the newest entry is kept even when it alone is over the cap
+    pub fn insert(&mut self, key: impl Into<String>, data: StaticSoundData) {
+        self.tick += 1;
+        self.bytes += data_size(&data);
+        if let Some((old, _)) = self.entries.insert(key.into(), (data, self.tick)) {
+            self.bytes -= data_size(&old);
+        }
+        while self.bytes > self.max_bytes && self.entries.len() > 1 {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+    pub fn remove(&mut self, key: &str) {
+        if let Some((data, _)) = self.entries.remove(key) {
+            self.bytes -= data_size(&data);
+        }
+    }
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+    }
+    /// bytes of decoded samples held
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+    /// how many sounds went through `decode` in `cached_decode`
+    pub fn decodes(&self) -> usize {
+        self.decodes
+    }
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            sounds: self.entries.len(),
+            bytes: self.bytes,
+            max_bytes: self.max_bytes,
+        }
+    }
+}
+
+/// the cached data, or `decode` run without holding the lock
+pub fn cached_decode(
+    cache: &Mutex<SoundCache>,
+    key: &str,
+    decode: impl FnOnce() -> anyhow::Result<StaticSoundData>,
+) -> anyhow::Result<StaticSoundData> {
+    {
+        let mut cache = cache.lock();
+        if let Some(data) = cache.get(key) {
+            cache.hits += 1;
+            return Ok(data);
+        }
+        cache.misses += 1;
+    }
+    let data = decode()?;
+    let mut cache = cache.lock();
+    cache.decodes += 1;
+    cache.insert(key, data.clone());
+    Ok(data)
+}
+
+#[test]
+fn test_sound_cache() -> anyhow::Result<()> {
+    let sound = |frames: usize| StaticSoundData {
+        sample_rate: 48000,
+        frames: vec![Frame::ZERO; frames].into(),
+        settings: Default::default(),
+        slice: None,
+    };
+    // room for two of the 100 frame sounds
+    let cache = Mutex::new(SoundCache::new(200 * size_of::<Frame>()));
+    let decoded = std::cell::RefCell::new(Vec::<String>::new());
+    let play = |key: &str| {
+        cached_decode(&cache, key, || {
+            decoded.borrow_mut().push(key.to_owned());
+            Ok(sound(100))
+        })
+    };
+    // preloaded, then played without decoding again
+    play("hit.ogg")?;
+    play("hit.ogg")?;
+    assert_eq!(*decoded.borrow(), ["hit.ogg"]);
+
+    play("jump.ogg")?;
+    play("hit.ogg")?;
+    // jump.ogg is the least recently used
+    play("step.ogg")?;
+    play("hit.ogg")?;
+    play("jump.ogg")?;
+    assert_eq!(
+        *decoded.borrow(),
+        ["hit.ogg", "jump.ogg", "step.ogg", "jump.ogg"]
+    );
+    let cache = cache.lock();
+    assert_eq!(cache.decodes(), 4);
+    assert_eq!(cache.bytes(), 200 * size_of::<Frame>());
+    assert!(cache.contains("hit.ogg") && !cache.contains("step.ogg"));
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.sounds), (3, 4, 2));
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use super::EffectConfig;
+use std::fmt::Display;
+
+/// the accepted range of a parameter
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    min: f64,
+    max: f64,
+    // `min` itself is out of range
+    exclusive: bool,
+    expect: &'static str,
+}
+
+impl Limit {
+    const ANY: Self = Self::new(f64::MIN, f64::MAX, "a finite number");
+    const UNIT: Self = Self::new(0.0, 1.0, "0..1");
+    const PANNING: Self = Self::new(-1.0, 1.0, "-1..1");
+    const RATIO: Self = Self::new(1.0, f64::MAX, ">= 1");
+    const POSITIVE: Self = Self {
+        exclusive: true,
+        ..Self::new(0.0, f64::MAX, "> 0")
+    };
+    const fn new(min: f64, max: f64, expect: &'static str) -> Self {
+        Self {
+            min,
+            max,
+            exclusive: false,
+            expect,
+        }
+    }
+    fn contains(&self, value: f64) -> bool {
+        let above = match self.exclusive {
+            true => value > self.min,
+            false => value >= self.min,
+        };
+        value.is_finite() && above && value <= self.max
+    }
+    /// None falls back to the default, there is no closest valid value to nan or an open bound
+    fn fix(&self, value: f64) -> Option<f64> {
+        match self.contains(value) {
+            true => Some(value),
+            false if !value.is_finite() || (self.exclusive && value <= self.min) => None,
+            false => Some(value.clamp(self.min, self.max)),
+        }
+    }
+}
+
+/// a parameter out of its range
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectParamError {
+    pub effect: &'static str,
+    pub field: &'static str,
+    pub value: f64,
+    pub expect: &'static str,
+}
+impl Display for EffectParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{} = {}, expect {}",
+            self.effect, self.field, self.value, self.expect
+        )
+    }
+}
+impl std::error::Error for EffectParamError {}
+
+trait Param: Copy {
+    fn get(self) -> f64;
+    fn put(value: f64) -> Self;
+}
+impl Param for f32 {
+    fn get(self) -> f64 {
+        self as f64
+    }
+    fn put(value: f64) -> Self {
+        value as f32
+    }
+}
+impl Param for f64 {
+    fn get(self) -> f64 {
+        self
+    }
+    fn put(value: f64) -> Self {
+        value
+    }
+}
+
+/// what is done with every parameter in range of `visit`
+trait Visitor {
+    fn optional<T: Param>(&mut self, field: &'static str, value: &mut Option<T>, limit: Limit);
+    fn required<T: Param>(&mut self, field: &'static str, value: &mut T, limit: Limit);
+}
+
+struct Validate {
+    effect: &'static str,
+    error: Option<EffectParamError>,
+}
+impl Validate {
+    fn check(&mut self, field: &'static str, value: f64, limit: Limit) {
+        if self.error.is_none() && !limit.contains(value) {
+            self.error = Some(EffectParamError {
+                effect: self.effect,
+                field,
+                value,
+                expect: limit.expect,
+            });
+        }
+    }
+}
+impl Visitor for Validate {
+    fn optional<T: Param>(&mut self, field: &'static str, value: &mut Option<T>, limit: Limit) {
+        if let Some(value) = value {
+            self.check(field, value.get(), limit);
+        }
+    }
+    fn required<T: Param>(&mut self, field: &'static str, value: &mut T, limit: Limit) {
+        self.check(field, value.get(), limit);
+    }
+}
+
+struct Clamp;
+impl Visitor for Clamp {
+    fn optional<T: Param>(&mut self, _field: &'static str, value: &mut Option<T>, limit: Limit) {
+        *value = value.and_then(|value| limit.fix(value.get())).map(T::put);
+    }
+    fn required<T: Param>(&mut self, _field: &'static str, value: &mut T, limit: Limit) {
+        // centered and unchanged volume
+        *value = T::put(limit.fix(value.get()).unwrap_or(0.0));
+    }
+}
+
+impl EffectConfig {
+    /// the `type` of every effect
+    pub const TYPES: [&'static str; 8] = [
+        "compressor",
+        "delay",
+        "distortion",
+        "eq_filter",
+        "filter",
+        "panning_control",
+        "reverb",
+        "volume_control",
+    ];
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Compressor { .. } => "compressor",
+            Self::Delay { .. } => "delay",
+            Self::Distortion { .. } => "distortion",
+            Self::EqFilter { .. } => "eq_filter",
+            Self::Filter { .. } => "filter",
+            Self::PanningControl { .. } => "panning_control",
+            Self::Reverb { .. } => "reverb",
+            Self::VolumeControl { .. } => "volume_control",
+        }
+    }
+    fn visit(&mut self, v: &mut impl Visitor) {
+        match self {
+            Self::Compressor {
+                makeup_gain,
+                mix,
+                ratio,
+                threshold,
+                ..
+            } => {
+                v.optional("makeup_gain", makeup_gain, Limit::ANY);
+                v.optional("mix", mix, Limit::UNIT);
+                v.optional("ratio", ratio, Limit::RATIO);
+                v.optional("threshold", threshold, Limit::ANY);
+            }
+            Self::Delay { feedback, mix, .. } => {
+                v.optional("feedback", feedback, Limit::ANY);
+                v.optional("mix", mix, Limit::UNIT);
+            }
+            Self::Distortion { drive, mix, .. } => {
+                v.optional("drive", drive, Limit::ANY);
+                v.optional("mix", mix, Limit::UNIT);
+            }
+            Self::EqFilter {
+                frequency, gain, q, ..
+            } => {
+                v.optional("frequency", frequency, Limit::POSITIVE);
+                v.optional("gain", gain, Limit::ANY);
+                v.optional("q", q, Limit::POSITIVE);
+            }
+            Self::Filter {
+                cutoff,
+                mix,
+                resonance,
+                ..
+            } => {
+                v.optional("cutoff", cutoff, Limit::POSITIVE);
+                v.optional("mix", mix, Limit::UNIT);
+                v.optional("resonance", resonance, Limit::UNIT);
+            }
+            Self::PanningControl { panning } => v.required("panning", panning, Limit::PANNING),
+            Self::Reverb {
+                damping,
+                feedback,
+                mix,
+                stereo_width,
+            } => {
+                v.optional("damping", damping, Limit::UNIT);
+                v.optional("feedback", feedback, Limit::UNIT);
+                v.optional("mix", mix, Limit::UNIT);
+                v.optional("stereo_width", stereo_width, Limit::UNIT);
+            }
+            Self::VolumeControl { volume } => v.required("volume", volume, Limit::ANY),
+        }
+    }
+    /// the first parameter out of its range
+    pub fn validate(&self) -> Result<(), EffectParamError> {
+        let mut validate = Validate {
+            effect: self.type_name(),
+            error: None,
+        };
+        self.clone().visit(&mut validate);
+        validate.error.map_or(Ok(()), Err)
+    }
+    /// pulls parameters into range, nan, infinities and frequencies <= 0 fall back to the defaults
+    pub fn clamped(&self) -> Self {
+        let mut config = self.clone();
+        config.visit(&mut Clamp);
+        config
+    }
+}
+
+#[test]
+fn test_effect_limits() {
+    let config = EffectConfig::Filter {
+        cutoff: Some(-20.0),
+        mix: Some(1.5),
+        mode: None,
+        resonance: Some(f64::NAN),
+    };
+    let err = config.validate().unwrap_err();
+    assert_eq!(err.to_string(), "filter.cutoff = -20, expect > 0");
+    let EffectConfig::Filter {
+        cutoff,
+        mix,
+        resonance,
+        ..
+    } = config.clamped()
+    else {
+        unreachable!()
+    };
+    assert_eq!((cutoff, mix, resonance), (None, Some(1.0), None));
+
+    let panning = EffectConfig::PanningControl { panning: -3.0 };
+    assert_eq!(panning.validate().unwrap_err().expect, "-1..1");
+    assert_eq!(
+        panning.clamped(),
+        EffectConfig::PanningControl { panning: -1.0 }
+    );
+    let volume = EffectConfig::VolumeControl {
+        volume: f32::INFINITY,
+    };
+    assert_eq!(
+        volume.clamped(),
+        EffectConfig::VolumeControl { volume: 0.0 }
+    );
+    assert!(volume.clamped().validate().is_ok());
+    let compressor = EffectConfig::Compressor {
+        attack_duration: Some(10),
+        makeup_gain: None,
+        mix: Some(0.5),
+        ratio: Some(0.5),
+        release_duration: None,
+        threshold: Some(-12.0),
+    };
+    assert_eq!(compressor.validate().unwrap_err().field, "ratio");
+    assert!(compressor.clamped().validate().is_ok());
+}
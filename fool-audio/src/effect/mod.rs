@@ -14,6 +14,8 @@ use kira::{
 };
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+mod check;
+pub use check::EffectParamError;
 
 #[derive(Debug)]
 pub enum EffectHandle {
@@ -29,7 +31,7 @@ pub enum EffectHandle {
 impl EffectHandle {
     pub fn set(&mut self, config: EffectConfig, tween: Option<u64>) {
         let tween = tween.unwrap_or(100);
-        match config {
+        match config.clamped() {
             EffectConfig::Compressor {
                 attack_duration,
                 makeup_gain,
@@ -251,7 +253,9 @@ macro_rules! apply_if_some {
         }
     };
 }
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// `{type = "compressor", mix = 0.5}`, unknown fields are an error
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
 pub enum EffectConfig {
     Compressor {
         attack_duration: Option<u64>,
@@ -298,8 +302,9 @@ pub enum EffectConfig {
 }
 
 impl EffectConfig {
+    /// parameters out of range are clamped first, see `clamped`
     pub fn build(&self) -> (Box<dyn Effect>, EffectHandle) {
-        match self {
+        match &self.clamped() {
             Self::Compressor {
                 attack_duration,
                 makeup_gain,
@@ -1,8 +1,90 @@
-use super::effect::EffectHandle;
-use kira::track::TrackHandle;
+use super::effect::{EffectConfig, EffectHandle};
+use kira::{Decibels, track::TrackHandle};
+use serde::Serialize;
 use std::collections::HashMap;
 #[derive(Debug)]
 pub struct Track {
     pub handle: TrackHandle,
     pub effects: HashMap<String, EffectHandle>,
+    // what the track was built with, to tell whether adding it again has to rebuild it
+    pub configs: HashMap<String, EffectConfig>,
+    pub persist: bool,
+    // follows `AudioSystem::set_time_scale`
+    pub time_scaled: bool,
+    pub mix: GroupMix,
+}
+
+/// debug mixer toggles, `volume` is kept untouched so clearing them restores it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupMix {
+    pub volume: f32,
+    pub muted: bool,
+    pub solo: bool,
+}
+
+impl GroupMix {
+    pub fn new(volume: f32) -> Self {
+        Self {
+            volume,
+            ..Default::default()
+        }
+    }
+    /// `any_solo` is whether some group is soloed, the others are silenced then
+    pub fn effective_volume(&self, any_solo: bool) -> f32 {
+        if self.muted || (any_solo && !self.solo) {
+            Decibels::SILENCE.0
+        } else {
+            self.volume
+        }
+    }
+}
+
+/// checks a slash separated group path like `sfx/weapons`
+pub fn check_path(path: &str) -> anyhow::Result<()> {
+    if path.split('/').any(str::is_empty) {
+        return Err(anyhow::anyhow!(
+            "invalid group path {:?}, expect names separated by /",
+            path
+        ));
+    }
+    Ok(())
+}
+
+/// `sfx` for `sfx/weapons`, None for a group right under the master
+pub fn parent_path(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// whether `path` is `group` or a group under it
+pub fn in_subtree(path: &str, group: &str) -> bool {
+    path.strip_prefix(group)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// a group and the groups under it, by name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupNode {
+    pub name: String,
+    pub path: String,
+    pub children: Vec<GroupNode>,
+}
+
+impl GroupNode {
+    /// the trees of `paths`, every parent of a path has to be in it too
+    pub fn tree(paths: &[String]) -> Vec<GroupNode> {
+        Self::children_of(paths, None)
+    }
+    fn children_of(paths: &[String], parent: Option<&str>) -> Vec<GroupNode> {
+        let mut children: Vec<GroupNode> = paths
+            .iter()
+            .filter(|path| parent_path(path) == parent)
+            .map(|path| GroupNode {
+                name: path.rsplit('/').next().unwrap_or(path).to_owned(),
+                path: path.clone(),
+                children: Self::children_of(paths, Some(path)),
+            })
+            .collect();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        children
+    }
 }
@@ -1,27 +1,112 @@
 use fool_resource::{Resource, SharedData};
 use kira::{
-    AudioManager, AudioManagerSettings, DefaultBackend, Tween,
+    AudioManager, AudioManagerSettings, DefaultBackend, Frame, Tween,
     sound::{
         PlaybackState,
         static_sound::{StaticSoundData, StaticSoundHandle},
     },
     track::TrackBuilder,
 };
+mod cache;
 mod effect;
 mod group;
+mod meter;
+#[cfg(any(test, feature = "testing"))]
+mod mock;
+pub use cache::{CacheStats, SOUND_CACHE_BYTES, SoundCache};
 use dashmap::DashMap;
-pub use effect::{EffectConfig, EffectHandle};
-pub use group::Track;
+pub use effect::{EffectConfig, EffectHandle, EffectParamError};
+pub use group::{GroupMix, GroupNode, Track};
+use group::{check_path, in_subtree, parent_path};
+pub use kira::backend::Backend;
+pub use meter::{Levels, MeterBuilder};
+#[cfg(any(test, feature = "testing"))]
+pub use mock::{MOCK_SAMPLE_RATE, MockBackend, MockBackendSettings};
 use parking_lot::Mutex;
-use std::{collections::HashMap, io::Cursor, sync::Arc, time::Duration};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::{collections::HashMap, fmt::Debug, io::Cursor, sync::Arc, time::Duration};
 
-#[derive(Clone)]
-pub struct AudioSystem {
-    pub manager: Arc<AudioManager>,
+pub const MIN_PLAYBACK_RATE: f64 = 0.25;
+pub const MAX_PLAYBACK_RATE: f64 = 4.0;
+
+/// generic over the kira backend so tests can run without an audio device
+pub struct AudioSystem<B: Backend = DefaultBackend> {
+    pub manager: Arc<Mutex<AudioManager<B>>>,
     pub groups: Arc<DashMap<String, Track>>,
     pub master: Arc<Mutex<Track>>,
     pub musics: Arc<DashMap<MusicId, StaticSoundHandle>>,
+    // rate requested per sound, before the time scale is applied
+    pub rates: Arc<DashMap<MusicId, f64>>,
+    // volume and mute per sound, solo is only for groups
+    pub sound_mix: Arc<DashMap<MusicId, GroupMix>>,
+    pub time_scale: Arc<Mutex<f64>>,
     pub resource: Resource<String, SharedData>,
+    // decoded once, `play` reuses it
+    pub sounds: Arc<Mutex<SoundCache>>,
+    // names the sounds from `play_pcm`
+    pcm_count: Arc<AtomicU64>,
+    // metered after the master volume
+    pub levels: Arc<Levels>,
+    // set by `pause_all`, the master track only reports it once the backend ran
+    paused: Arc<AtomicBool>,
+}
+
+impl<B: Backend> Clone for AudioSystem<B> {
+    fn clone(&self) -> Self {
+        Self {
+            manager: self.manager.clone(),
+            groups: self.groups.clone(),
+            master: self.master.clone(),
+            musics: self.musics.clone(),
+            rates: self.rates.clone(),
+            sound_mix: self.sound_mix.clone(),
+            time_scale: self.time_scale.clone(),
+            resource: self.resource.clone(),
+            sounds: self.sounds.clone(),
+            pcm_count: self.pcm_count.clone(),
+            levels: self.levels.clone(),
+            paused: self.paused.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PlayOptions {
+    pub volume: Option<f32>,
+    pub panning: Option<f32>,
+    pub position: Option<f64>,
+    pub rate: Option<f64>,
+}
+
+fn check_rate(rate: f64) -> anyhow::Result<f64> {
+    if !(MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+        return Err(anyhow::anyhow!(
+            "playback rate {} out of range {}..={}",
+            rate,
+            MIN_PLAYBACK_RATE,
+            MAX_PLAYBACK_RATE
+        ));
+    }
+    Ok(rate)
+}
+
+/// a linear fade over `duration` milliseconds
+fn linear_tween(duration: u64) -> Tween {
+    Tween {
+        start_time: Default::default(),
+        duration: Duration::from_millis(duration),
+        easing: kira::Easing::Linear,
+    }
+}
+
+fn scaled_rate(rate: f64, scale: f64, scaled: bool) -> f64 {
+    if scaled {
+        (rate * scale).clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE)
+    } else {
+        rate
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -31,41 +116,384 @@ pub struct MusicId {
 }
 
 impl AudioSystem {
+    /// plays on the default output device
     pub fn new(resource: Resource<String, SharedData>) -> anyhow::Result<Self> {
-        let mut manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())?;
+        Self::new_with_backend(resource, Default::default())
+    }
+}
+
+impl<B: Backend> AudioSystem<B> {
+    pub fn new_with_backend(
+        resource: Resource<String, SharedData>,
+        mut settings: AudioManagerSettings<B>,
+    ) -> anyhow::Result<Self>
+    where
+        B::Error: Debug,
+    {
+        let levels = settings
+            .main_track_builder
+            .add_effect(MeterBuilder::default());
+        let mut manager = AudioManager::new(settings)
+            .map_err(|err| anyhow::anyhow!("audio backend setup failed: {:?}", err))?;
         let master = manager.add_sub_track(TrackBuilder::default())?;
         Ok(Self {
-            manager: Arc::new(manager),
+            manager: Arc::new(Mutex::new(manager)),
             groups: Default::default(),
             master: Arc::new(Mutex::new(Track {
                 handle: master,
                 effects: Default::default(),
+                configs: Default::default(),
+                persist: false,
+                time_scaled: false,
+                mix: GroupMix::new(0.0),
             })),
             resource,
             musics: Default::default(),
+            rates: Default::default(),
+            sound_mix: Default::default(),
+            time_scale: Arc::new(Mutex::new(1.0)),
+            sounds: Arc::new(Mutex::new(SoundCache::new(SOUND_CACHE_BYTES))),
+            pcm_count: Default::default(),
+            levels,
+            paused: Default::default(),
         })
     }
+    /// recent (peak, rms) of everything that plays, for meters and visualizers
+    pub fn master_levels(&self) -> (f32, f32) {
+        self.levels.get()
+    }
+    fn sound_data(&self, music: &str) -> anyhow::Result<StaticSoundData> {
+        cache::cached_decode(&self.sounds, music, || {
+            let audio = self
+                .resource
+                .get(music)
+                .map_err(|_| anyhow::anyhow!("audio {} Not Found!", music))?;
+            Ok(StaticSoundData::from_cursor(Cursor::new(audio))?)
+        })
+    }
+    /// decodes `musics` ahead of time so the first `play` does not stall the frame,
+    /// e.g. while a loading screen is up
+    pub fn preload(&self, musics: &[impl AsRef<str>]) -> anyhow::Result<()> {
+        for music in musics {
+            self.sound_data(music.as_ref())?;
+        }
+        Ok(())
+    }
+    /// drops the decoded `music`, the next play decodes it again
+    pub fn unload(&self, music: &str) {
+        self.sounds.lock().remove(music);
+    }
+    /// bytes of decoded sounds kept, least recently played ones are dropped first
+    pub fn set_cache_limit(&self, max_bytes: usize) {
+        self.sounds.lock().set_max_bytes(max_bytes);
+    }
+    pub fn cache_stats(&self) -> CacheStats {
+        self.sounds.lock().stats()
+    }
+    /// `name` is a path like `sfx/weapons`, the groups missing above it are added with
+    /// default settings. Adding a group that exists keeps the groups under it, with the
+    /// same persist and effects only its volume changes, otherwise its track is built
+    /// again with the groups under it and the sounds playing in them are cut off
     pub fn add_group(
         &self,
         name: impl Into<String>,
         volume: f32,
         persist: bool,
         effects: HashMap<impl Into<String>, EffectConfig>,
+    ) -> anyhow::Result<()> {
+        let name = name.into();
+        check_path(&name)?;
+        let effects: HashMap<String, EffectConfig> = effects
+            .into_iter()
+            .map(|(n, effect)| (n.into(), effect))
+            .collect();
+        let same_track = self
+            .groups
+            .get(&name)
+            .map(|track| track.persist == persist && track.configs == effects);
+        match same_track {
+            Some(true) => {
+                return self.update_mix(name, Tween::default(), |mix| mix.volume = volume);
+            }
+            // a built track can't change its effects, the subtree is built again
+            Some(false) => {
+                let paths: Vec<String> = self
+                    .groups
+                    .iter()
+                    .map(|track| track.key().clone())
+                    .filter(|path| in_subtree(path, &name))
+                    .collect();
+                let mut subtree: Vec<(String, Track)> = paths
+                    .iter()
+                    .filter_map(|path| self.groups.remove(path))
+                    .collect();
+                // a parent sorts before the groups under it
+                subtree.sort_by(|a, b| a.0.cmp(&b.0));
+                self.forget_sounds(&name);
+                for (path, track) in subtree {
+                    let (mix, persist, configs) = match path == name {
+                        true => (
+                            GroupMix {
+                                volume,
+                                ..track.mix
+                            },
+                            persist,
+                            effects.clone(),
+                        ),
+                        false => (track.mix, track.persist, track.configs),
+                    };
+                    self.build_group(path, mix, persist, configs, track.time_scaled)?;
+                }
+            }
+            None => {
+                if let Some(parent) =
+                    parent_path(&name).filter(|parent| !self.groups.contains_key(*parent))
+                {
+                    self.add_group(parent, 0.0, false, HashMap::<String, _>::new())?;
+                }
+                self.build_group(name, GroupMix::new(volume), persist, effects, false)?;
+            }
+        }
+        // a group added while another is soloed starts silenced
+        self.apply_mix(Tween::default());
+        Ok(())
+    }
+    // adds the track of `name` under its parent, which has to exist
+    fn build_group(
+        &self,
+        name: String,
+        mix: GroupMix,
+        persist: bool,
+        configs: HashMap<String, EffectConfig>,
+        time_scaled: bool,
     ) -> anyhow::Result<()> {
         let mut track = TrackBuilder::new()
-            .volume(volume)
+            .volume(mix.volume)
             .persist_until_sounds_finish(persist);
         let mut e = HashMap::default();
-        for (n, effect) in effects {
+        for (n, effect) in &configs {
             let (ef, ha) = effect.build();
             track.add_built_effect(ef);
-            e.insert(n.into(), ha);
+            e.insert(n.clone(), ha);
+        }
+        // routed through the parent, its volume and effects apply to this one too
+        let handle = match parent_path(&name) {
+            Some(parent) => match self.groups.get_mut(parent) {
+                Some(mut parent) => parent.handle.add_sub_track(track)?,
+                None => return Err(self.missing_group(parent)),
+            },
+            None => self.master.lock().handle.add_sub_track(track)?,
+        };
+        self.groups.insert(
+            name,
+            Track {
+                handle,
+                effects: e,
+                configs,
+                persist,
+                time_scaled,
+                mix,
+            },
+        );
+        Ok(())
+    }
+    // tells a missing group from a missing parent of it
+    fn missing_group(&self, group: &str) -> anyhow::Error {
+        if let Err(err) = check_path(group) {
+            return err;
+        }
+        match parent_path(group) {
+            Some(parent) if !self.groups.contains_key(parent) => {
+                anyhow::anyhow!("parent group {} of {} Not Found!", parent, group)
+            }
+            _ => anyhow::anyhow!("group {} Not Found!", group),
+        }
+    }
+    /// drops the group and the groups under it with their effects, sounds still playing
+    /// in them are cut off unless their group was added with `persist`
+    pub fn remove_group(&self, group: impl Into<String>) -> anyhow::Result<()> {
+        let group = group.into();
+        if !self.groups.contains_key(&group) {
+            return Err(self.missing_group(&group));
+        }
+        self.groups.retain(|path, _| !in_subtree(path, &group));
+        self.forget_sounds(&group);
+        self.apply_mix(Tween::default());
+        Ok(())
+    }
+    // the sounds of `group` and the groups under it, their tracks are gone
+    fn forget_sounds(&self, group: &str) {
+        self.musics.retain(|id, _| !in_subtree(&id.track, group));
+        self.rates.retain(|id, _| !in_subtree(&id.track, group));
+        self.sound_mix.retain(|id, _| !in_subtree(&id.track, group));
+    }
+    /// the groups as trees under the master, sorted by name
+    pub fn list_groups(&self) -> Vec<GroupNode> {
+        let paths: Vec<String> = self
+            .groups
+            .iter()
+            .map(|track| track.key().clone())
+            .collect();
+        GroupNode::tree(&paths)
+    }
+    /// forgets the sounds that finished playing, returns how many
+    pub fn gc(&self) -> usize {
+        let before = self.musics.len();
+        self.musics
+            .retain(|_, handle| handle.state() != PlaybackState::Stopped);
+        self.rates.retain(|id, _| self.musics.contains_key(id));
+        self.sound_mix.retain(|id, _| self.musics.contains_key(id));
+        before - self.musics.len()
+    }
+    // a soloed group keeps the groups above and under it audible, muting a group
+    // silences the ones under it through its track
+    fn apply_mix(&self, tween: Tween) {
+        let soloed: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|track| track.mix.solo)
+            .map(|track| track.key().clone())
+            .collect();
+        for mut track in self.groups.iter_mut() {
+            let path = track.key();
+            let on_solo_path = soloed
+                .iter()
+                .any(|solo| in_subtree(path, solo) || in_subtree(solo, path));
+            let volume = track
+                .mix
+                .effective_volume(!soloed.is_empty() && !on_solo_path);
+            track.handle.set_volume(volume, tween);
+        }
+    }
+    fn update_mix(
+        &self,
+        group: String,
+        tween: Tween,
+        update: impl FnOnce(&mut GroupMix),
+    ) -> anyhow::Result<()> {
+        match self.groups.get_mut(&group) {
+            Some(mut track) => update(&mut track.mix),
+            None => return Err(self.missing_group(&group)),
+        }
+        self.apply_mix(tween);
+        Ok(())
+    }
+    /// silences `group` and the groups under it, unmuting restores its volume
+    pub fn set_mute(&self, group: impl Into<String>, muted: bool) -> anyhow::Result<()> {
+        self.update_mix(group.into(), Tween::default(), |mix| mix.muted = muted)
+    }
+    /// while any group is soloed every group that is not is silenced, except the
+    /// groups above and under a soloed one
+    pub fn set_solo(&self, group: impl Into<String>, solo: bool) -> anyhow::Result<()> {
+        self.update_mix(group.into(), Tween::default(), |mix| mix.solo = solo)
+    }
+    pub fn mute_group(&self, group: impl Into<String>) -> anyhow::Result<()> {
+        self.set_mute(group, true)
+    }
+    pub fn unmute_group(&self, group: impl Into<String>) -> anyhow::Result<()> {
+        self.set_mute(group, false)
+    }
+    /// `set_solo` on, groups added while it is soloed start silenced too
+    pub fn solo_group(&self, group: impl Into<String>) -> anyhow::Result<()> {
+        self.set_solo(group, true)
+    }
+    /// clears every solo
+    pub fn unsolo(&self) {
+        for mut track in self.groups.iter_mut() {
+            track.mix.solo = false;
+        }
+        self.apply_mix(Tween::default());
+    }
+    /// muted itself, a group above it being muted is not counted, None when it does
+    /// not exist
+    pub fn is_group_muted(&self, group: &str) -> Option<bool> {
+        self.groups.get(group).map(|track| track.mix.muted)
+    }
+    pub fn is_group_soloed(&self, group: &str) -> Option<bool> {
+        self.groups.get(group).map(|track| track.mix.solo)
+    }
+    /// the volume of `group`, the groups under it are mixed through it
+    pub fn set_group_volume(
+        &self,
+        group: impl Into<String>,
+        volume: f32,
+        duration: u64,
+    ) -> anyhow::Result<()> {
+        let tween = linear_tween(duration);
+        self.update_mix(group.into(), tween, |mix| mix.volume = volume)
+    }
+    /// pauses every sound in `group` and the groups under it
+    pub fn pause_group(&self, group: impl Into<String>, duration: u64) -> anyhow::Result<()> {
+        let group = group.into();
+        let tween = linear_tween(duration);
+        match self.groups.get_mut(&group) {
+            Some(mut track) => {
+                track.handle.pause(tween);
+                Ok(())
+            }
+            None => Err(self.missing_group(&group)),
+        }
+    }
+    pub fn resume_group(&self, group: impl Into<String>, duration: u64) -> anyhow::Result<()> {
+        let group = group.into();
+        let tween = linear_tween(duration);
+        match self.groups.get_mut(&group) {
+            Some(mut track) => {
+                track.handle.resume(tween);
+                Ok(())
+            }
+            None => Err(self.missing_group(&group)),
+        }
+    }
+    /// whether sounds of `group` and the groups under it follow the global time scale
+    pub fn set_time_scaled(&self, group: impl Into<String>, scaled: bool) -> anyhow::Result<()> {
+        let group = group.into();
+        match self.groups.get_mut(&group) {
+            Some(mut track) => {
+                track.time_scaled = scaled;
+            }
+            None => return Err(self.missing_group(&group)),
+        }
+        let scale = *self.time_scale.lock();
+        let tween = Tween::default();
+        for mut music in self.musics.iter_mut() {
+            let (id, handle) = music.pair_mut();
+            if in_subtree(&id.track, &group) {
+                let rate = self.rates.get(id).map(|r| *r).unwrap_or(1.0);
+                let scaled = self.is_time_scaled(&id.track);
+                handle.set_playback_rate(scaled_rate(rate, scale, scaled), tween);
+            }
         }
-        let handle = self.master.lock().handle.add_sub_track(track)?;
-        self.groups
-            .insert(name.into(), Track { handle, effects: e });
         Ok(())
     }
+    // when the group or one above it is
+    fn is_time_scaled(&self, group: &str) -> bool {
+        let mut path = Some(group);
+        while let Some(group) = path {
+            if self.groups.get(group).is_some_and(|t| t.time_scaled) {
+                return true;
+            }
+            path = parent_path(group);
+        }
+        false
+    }
+    /// scales the rate of every sound in time scaled groups, e.g. for bullet time
+    pub fn set_time_scale(&self, scale: f64, duration: u64) -> anyhow::Result<()> {
+        let scale = check_rate(scale)?;
+        *self.time_scale.lock() = scale;
+        let tween = linear_tween(duration);
+        for mut music in self.musics.iter_mut() {
+            let (id, handle) = music.pair_mut();
+            if self.is_time_scaled(&id.track) {
+                let rate = self.rates.get(id).map(|r| *r).unwrap_or(1.0);
+                handle.set_playback_rate(scaled_rate(rate, scale, true), tween);
+            }
+        }
+        Ok(())
+    }
+    pub fn time_scale(&self) -> f64 {
+        *self.time_scale.lock()
+    }
     pub fn set_effect(
         &self,
         group: impl Into<String>,
@@ -83,39 +511,29 @@ impl AudioSystem {
                 Err(anyhow::anyhow!("effect {} Not Found!", effect))
             }
         } else {
-            Err(anyhow::anyhow!("group {} Not Found!", effect))
+            Err(self.missing_group(&group))
         }
     }
     pub fn pause_all(&self, duration: u64) {
-        let tween = Tween {
-            start_time: Default::default(),
-            duration: Duration::from_millis(duration),
-            easing: kira::Easing::Linear,
-        };
+        let tween = linear_tween(duration);
         self.master.lock().handle.pause(tween);
+        self.paused.store(true, Ordering::Relaxed);
     }
     pub fn resume_all(&self, duration: u64) {
-        let tween = Tween {
-            start_time: Default::default(),
-            duration: Duration::from_millis(duration),
-            easing: kira::Easing::Linear,
-        };
+        let tween = linear_tween(duration);
         self.master.lock().handle.resume(tween);
+        self.paused.store(false, Ordering::Relaxed);
+    }
+    /// paused by `pause_all` and not resumed since
+    pub fn is_paused_all(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
     }
     pub fn set_volume_all(&self, volume: f32, duration: u64) {
-        let tween = Tween {
-            start_time: Default::default(),
-            duration: Duration::from_millis(duration),
-            easing: kira::Easing::Linear,
-        };
+        let tween = linear_tween(duration);
         self.master.lock().handle.set_volume(volume, tween);
     }
     pub fn stop_all(&self, duration: u64) {
-        let tween = Tween {
-            start_time: Default::default(),
-            duration: Duration::from_millis(duration),
-            easing: kira::Easing::Linear,
-        };
+        let tween = linear_tween(duration);
         for mut music in self.musics.iter_mut() {
             let (_name, handle) = music.pair_mut();
             handle.stop(tween);
@@ -125,39 +543,84 @@ impl AudioSystem {
         &self,
         group: impl Into<String>,
         music: impl Into<String>,
-        volume: Option<f32>,
-        panning: Option<f32>,
-        position: Option<f64>,
+        options: PlayOptions,
     ) -> anyhow::Result<()> {
-        let track = group.into();
         let music = music.into();
+        self.play_with(group.into(), music.clone(), options, || {
+            self.sound_data(&music)
+        })
+    }
+    /// plays generated audio, `samples` interleaved when there are 2 `channels`,
+    /// returns the name the control methods take for it
+    pub fn play_pcm(
+        &self,
+        group: impl Into<String>,
+        samples: Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+        options: PlayOptions,
+    ) -> anyhow::Result<String> {
+        let frames: Arc<[Frame]> = match channels {
+            1 => samples.into_iter().map(Frame::from_mono).collect(),
+            2 if samples.len().is_multiple_of(2) => samples
+                .chunks_exact(2)
+                .map(|lr| Frame::new(lr[0], lr[1]))
+                .collect(),
+            2 => return Err(anyhow::anyhow!("odd sample count for 2 channels")),
+            _ => {
+                return Err(anyhow::anyhow!("{} channels, expect 1 or 2", channels));
+            }
+        };
+        if sample_rate == 0 {
+            return Err(anyhow::anyhow!("sample rate must not be 0"));
+        }
+        let music = format!("pcm:{}", self.pcm_count.fetch_add(1, Ordering::Relaxed));
+        self.play_with(group.into(), music.clone(), options, || {
+            Ok(StaticSoundData {
+                sample_rate,
+                frames,
+                settings: Default::default(),
+                slice: None,
+            })
+        })?;
+        Ok(music)
+    }
+    fn play_with(
+        &self,
+        track: String,
+        music: String,
+        options: PlayOptions,
+        sound_data: impl FnOnce() -> anyhow::Result<StaticSoundData>,
+    ) -> anyhow::Result<()> {
+        let rate = check_rate(options.rate.unwrap_or(1.0))?;
         match self.groups.get_mut(&track) {
             Some(mut t) => {
-                if let Ok(audio) = self.resource.get(&music) {
-                    let mut sound_data = StaticSoundData::from_cursor(Cursor::new(audio.clone()))?;
-                    if let Some(v) = volume {
-                        sound_data = sound_data.volume(v);
-                    }
-                    if let Some(v) = panning {
-                        sound_data = sound_data.panning(v);
-                    }
-                    if let Some(v) = position {
-                        sound_data = sound_data.start_position(v);
-                    }
-                    let handle = t.handle.play(sound_data)?;
-                    self.musics.insert(
-                        MusicId {
-                            track: track.clone(),
-                            music: music.clone(),
-                        },
-                        handle,
-                    );
-                    Ok(())
-                } else {
-                    Err(anyhow::anyhow!("audio {} Not Found!", music))
+                let mut sound_data = sound_data()?;
+                if let Some(v) = options.volume {
+                    sound_data = sound_data.volume(v);
+                }
+                if let Some(v) = options.panning {
+                    sound_data = sound_data.panning(v);
+                }
+                if let Some(v) = options.position {
+                    sound_data = sound_data.start_position(v);
                 }
+                let scale = *self.time_scale.lock();
+                sound_data = sound_data.playback_rate(scaled_rate(rate, scale, t.time_scaled));
+                let handle = t.handle.play(sound_data)?;
+                // finished sounds are only dropped here, the map stays as big as what plays
+                self.gc();
+                let id = MusicId {
+                    track: track.clone(),
+                    music: music.clone(),
+                };
+                self.rates.insert(id.clone(), rate);
+                self.sound_mix
+                    .insert(id.clone(), GroupMix::new(options.volume.unwrap_or(0.0)));
+                self.musics.insert(id, handle);
+                Ok(())
             }
-            None => Err(anyhow::anyhow!("group {} Not Found!", track)),
+            None => Err(self.missing_group(&track)),
         }
     }
     pub fn pause(
@@ -173,11 +636,7 @@ impl AudioSystem {
             music: music.clone(),
         }) {
             Some(mut t) => {
-                let tween = Tween {
-                    start_time: Default::default(),
-                    duration: Duration::from_millis(duration),
-                    easing: kira::Easing::Linear,
-                };
+                let tween = linear_tween(duration);
                 t.pause(tween);
                 Ok(())
             }
@@ -201,11 +660,7 @@ impl AudioSystem {
             music: music.clone(),
         }) {
             Some(mut t) => {
-                let tween = Tween {
-                    start_time: Default::default(),
-                    duration: Duration::from_millis(duration),
-                    easing: kira::Easing::Linear,
-                };
+                let tween = linear_tween(duration);
                 t.resume(tween);
                 Ok(())
             }
@@ -230,11 +685,7 @@ impl AudioSystem {
             music: music.clone(),
         }) {
             Some(mut t) => {
-                let tween = Tween {
-                    start_time: Default::default(),
-                    duration: Duration::from_millis(duration),
-                    easing: kira::Easing::Linear,
-                };
+                let tween = linear_tween(duration);
                 t.stop(tween);
                 Ok(())
             }
@@ -291,12 +742,65 @@ impl AudioSystem {
             )),
         }
     }
+    fn update_sound_mix(
+        &self,
+        track: String,
+        music: String,
+        tween: Tween,
+        update: impl FnOnce(&mut GroupMix),
+    ) -> anyhow::Result<()> {
+        let id = MusicId { track, music };
+        match self.musics.get_mut(&id) {
+            Some(mut t) => {
+                let mut mix = self.sound_mix.entry(id).or_default();
+                update(&mut mix);
+                t.set_volume(mix.effective_volume(false), tween);
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!(
+                "audio {} of group {} Not Found!",
+                id.music,
+                id.track
+            )),
+        }
+    }
+    /// while the sound is muted only the volume it comes back at is changed
     pub fn set_volume(
         &self,
         group: impl Into<String>,
         music: impl Into<String>,
         duration: u64,
         volume: f32,
+    ) -> anyhow::Result<()> {
+        let tween = linear_tween(duration);
+        self.update_sound_mix(group.into(), music.into(), tween, |mix| mix.volume = volume)
+    }
+    /// silences a sound, it keeps playing so unmuting picks up where it is by then
+    pub fn mute(&self, group: impl Into<String>, music: impl Into<String>) -> anyhow::Result<()> {
+        self.update_sound_mix(group.into(), music.into(), Tween::default(), |mix| {
+            mix.muted = true
+        })
+    }
+    /// back at the last volume set, also one set while it was muted
+    pub fn unmute(&self, group: impl Into<String>, music: impl Into<String>) -> anyhow::Result<()> {
+        self.update_sound_mix(group.into(), music.into(), Tween::default(), |mix| {
+            mix.muted = false
+        })
+    }
+    /// the sound itself, not its groups, None when it is not playing
+    pub fn is_muted(&self, group: impl Into<String>, music: impl Into<String>) -> Option<bool> {
+        let id = MusicId {
+            track: group.into(),
+            music: music.into(),
+        };
+        self.sound_mix.get(&id).map(|mix| mix.muted)
+    }
+    pub fn set_panning(
+        &self,
+        group: impl Into<String>,
+        music: impl Into<String>,
+        duration: u64,
+        panning: f32,
     ) -> anyhow::Result<()> {
         let track = group.into();
         let music = music.into();
@@ -305,12 +809,8 @@ impl AudioSystem {
             music: music.clone(),
         }) {
             Some(mut t) => {
-                let tween = Tween {
-                    start_time: Default::default(),
-                    duration: Duration::from_millis(duration),
-                    easing: kira::Easing::Linear,
-                };
-                t.set_volume(volume, tween);
+                let tween = linear_tween(duration);
+                t.set_panning(panning, tween);
                 Ok(())
             }
             None => Err(anyhow::anyhow!(
@@ -320,26 +820,27 @@ impl AudioSystem {
             )),
         }
     }
-    pub fn set_panning(
+    pub fn set_playback_rate(
         &self,
         group: impl Into<String>,
         music: impl Into<String>,
+        rate: f64,
         duration: u64,
-        panning: f32,
     ) -> anyhow::Result<()> {
         let track = group.into();
         let music = music.into();
-        match self.musics.get_mut(&MusicId {
+        let rate = check_rate(rate)?;
+        let id = MusicId {
             track: track.clone(),
             music: music.clone(),
-        }) {
+        };
+        let scaled = self.is_time_scaled(&track);
+        match self.musics.get_mut(&id) {
             Some(mut t) => {
-                let tween = Tween {
-                    start_time: Default::default(),
-                    duration: Duration::from_millis(duration),
-                    easing: kira::Easing::Linear,
-                };
-                t.set_panning(panning, tween);
+                let tween = linear_tween(duration);
+                let scale = *self.time_scale.lock();
+                t.set_playback_rate(scaled_rate(rate, scale, scaled), tween);
+                self.rates.insert(id, rate);
                 Ok(())
             }
             None => Err(anyhow::anyhow!(
@@ -349,6 +850,27 @@ impl AudioSystem {
             )),
         }
     }
+    /// effective playback rate with the time scale applied
+    pub fn playback_rate(&self, group: impl Into<String>, music: impl Into<String>) -> Option<f64> {
+        let id = MusicId {
+            track: group.into(),
+            music: music.into(),
+        };
+        let rate = *self.rates.get(&id)?;
+        Some(scaled_rate(
+            rate,
+            *self.time_scale.lock(),
+            self.is_time_scaled(&id.track),
+        ))
+    }
+    pub fn position(&self, group: impl Into<String>, music: impl Into<String>) -> Option<f64> {
+        self.musics
+            .get(&MusicId {
+                track: group.into(),
+                music: music.into(),
+            })
+            .map(|t| t.position())
+    }
     pub fn state(
         &self,
         group: impl Into<String>,
@@ -368,3 +890,36 @@ impl AudioSystem {
         }
     }
 }
+
+#[test]
+fn test_playback_rate() {
+    assert!(check_rate(0.25).is_ok());
+    assert!(check_rate(4.0).is_ok());
+    assert!(check_rate(0.2).is_err());
+    assert!(check_rate(4.5).is_err());
+    assert_eq!(scaled_rate(1.1, 0.5, true), 0.55);
+    assert_eq!(scaled_rate(1.1, 0.5, false), 1.1);
+    // the combined rate stays in range
+    assert_eq!(scaled_rate(0.5, 0.25, true), MIN_PLAYBACK_RATE);
+    assert_eq!(scaled_rate(4.0, 2.0, true), MAX_PLAYBACK_RATE);
+}
+
+#[test]
+fn test_solo_mute() {
+    let silence = kira::Decibels::SILENCE.0;
+    let mut music = GroupMix::new(-6.0);
+    let mut sfx = GroupMix::new(-3.5);
+    sfx.solo = true;
+    assert_eq!(music.effective_volume(true), silence);
+    assert_eq!(sfx.effective_volume(true), -3.5);
+    sfx.solo = false;
+    assert_eq!(music.effective_volume(false), -6.0);
+    assert_eq!(sfx.effective_volume(false), -3.5);
+
+    music.muted = true;
+    music.solo = true;
+    // mute wins over solo
+    assert_eq!(music.effective_volume(true), silence);
+    music.muted = false;
+    assert_eq!(music.effective_volume(true), -6.0);
+}
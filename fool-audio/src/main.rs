@@ -1,7 +1,7 @@
 use fool_resource::Resource;
 use std::collections::HashMap;
 
-use fool_audio::{AudioSystem, EffectConfig};
+use fool_audio::{AudioSystem, EffectConfig, PlayOptions};
 
 const OGG0: &[u8] = include_bytes!("../../assets/audio/jump.mp3");
 const OGG1: &[u8] = include_bytes!("../../assets/audio/bgm.mp3");
@@ -24,8 +24,23 @@ fn main() -> anyhow::Result<()> {
     );
     a.add_group("test", 0.5, true, effects)?;
     a.set_volume_all(0.1, 0);
-    a.play("test", "000", Some(-6.8), None, None)?;
-    a.play("test", "001", Some(-6.8), None, None)?;
+    a.play(
+        "test",
+        "000",
+        PlayOptions {
+            volume: Some(-6.8),
+            ..Default::default()
+        },
+    )?;
+    a.play(
+        "test",
+        "001",
+        PlayOptions {
+            volume: Some(-6.8),
+            rate: Some(1.2),
+            ..Default::default()
+        },
+    )?;
     loop {
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
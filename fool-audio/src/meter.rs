@@ -0,0 +1,126 @@
+use kira::{
+    Frame,
+    effect::{Effect, EffectBuilder},
+    info::Info,
+};
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+// seconds for the levels to fall to about a third once the signal stops
+const RELEASE: f64 = 0.3;
+
+/// peak and rms amplitude of a track, written from the audio thread
+#[derive(Debug, Default)]
+pub struct Levels {
+    peak: AtomicU32,
+    rms: AtomicU32,
+}
+
+impl Levels {
+    /// (peak, rms), 1.0 is full scale
+    pub fn get(&self) -> (f32, f32) {
+        (
+            f32::from_bits(self.peak.load(Ordering::Relaxed)),
+            f32::from_bits(self.rms.load(Ordering::Relaxed)),
+        )
+    }
+    fn set(&self, peak: f32, rms: f32) {
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// measures the signal without changing it, levels fall off over `release` seconds
+#[derive(Debug, Clone, Copy)]
+pub struct MeterBuilder {
+    pub release: f64,
+}
+impl Default for MeterBuilder {
+    fn default() -> Self {
+        Self { release: RELEASE }
+    }
+}
+
+impl EffectBuilder for MeterBuilder {
+    type Handle = Arc<Levels>;
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        let levels = Arc::new(Levels::default());
+        let meter = Meter {
+            levels: levels.clone(),
+            release: self.release.max(f64::EPSILON),
+            peak: 0.0,
+            mean_square: 0.0,
+        };
+        (Box::new(meter), levels)
+    }
+}
+
+struct Meter {
+    levels: Arc<Levels>,
+    release: f64,
+    peak: f32,
+    mean_square: f32,
+}
+
+impl Effect for Meter {
+    fn process(&mut self, input: &mut [Frame], dt: f64, _info: &Info) {
+        if input.is_empty() {
+            return;
+        }
+        let (mut peak, mut sum) = (0.0f32, 0.0f32);
+        for frame in input.iter() {
+            peak = peak.max(frame.left.abs()).max(frame.right.abs());
+            sum += (frame.left * frame.left + frame.right * frame.right) / 2.0;
+        }
+        let mean_square = sum / input.len() as f32;
+        let decay = (-dt * input.len() as f64 / self.release).exp() as f32;
+        self.peak = peak.max(self.peak * decay);
+        self.mean_square = mean_square + (self.mean_square - mean_square) * decay;
+        self.levels.set(self.peak, self.mean_square.sqrt());
+    }
+}
+
+#[test]
+fn test_meter() -> anyhow::Result<()> {
+    use kira::{
+        AudioManager, AudioManagerSettings, Tween,
+        backend::mock::{MockBackend, MockBackendSettings},
+        sound::static_sound::StaticSoundData,
+    };
+    let mut settings = AudioManagerSettings::<MockBackend> {
+        backend_settings: MockBackendSettings { sample_rate: 1000 },
+        ..Default::default()
+    };
+    let levels = settings
+        .main_track_builder
+        .add_effect(MeterBuilder::default());
+    let mut manager = AudioManager::new(settings).unwrap();
+    let process = |manager: &mut AudioManager<MockBackend>, blocks: usize| {
+        for _ in 0..blocks {
+            manager.backend_mut().on_start_processing();
+            manager.backend_mut().process();
+        }
+    };
+    assert_eq!(levels.get(), (0.0, 0.0));
+    let loud = StaticSoundData {
+        sample_rate: 1000,
+        frames: (0..2000)
+            .map(|i| Frame::from_mono(if i % 2 == 0 { 0.8 } else { -0.8 }))
+            .collect(),
+        settings: Default::default(),
+        slice: None,
+    };
+    let mut sound = manager.play(loud)?;
+    process(&mut manager, 4);
+    let (peak, rms) = levels.get();
+    assert!(peak > 0.5 && rms > 0.3, "playing {} {}", peak, rms);
+    assert!(rms <= peak);
+    sound.stop(Tween::default());
+    // 40 blocks of 128 frames is about 5 seconds
+    process(&mut manager, 40);
+    let (peak, rms) = levels.get();
+    assert!(peak < 0.001 && rms < 0.001, "stopped {} {}", peak, rms);
+    Ok(())
+}
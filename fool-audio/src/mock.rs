@@ -0,0 +1,430 @@
+use super::AudioSystem;
+use fool_resource::{Resource, SharedData};
+use kira::AudioManagerSettings;
+pub use kira::backend::mock::{MockBackend, MockBackendSettings};
+use std::time::Duration;
+
+/// the rate the mock backend renders at
+pub const MOCK_SAMPLE_RATE: u32 = 48_000;
+
+impl AudioSystem<MockBackend> {
+    /// without an audio device, time only passes in `advance`
+    pub fn new_mock(resource: Resource<String, SharedData>) -> anyhow::Result<Self> {
+        let settings = AudioManagerSettings::<MockBackend> {
+            backend_settings: MockBackendSettings {
+                sample_rate: MOCK_SAMPLE_RATE,
+            },
+            ..Default::default()
+        };
+        Self::new_with_backend(resource, settings)
+    }
+    /// renders at least `duration` of audio, for a system made by `new_mock`
+    pub fn advance(&self, duration: Duration) {
+        let block = AudioManagerSettings::<MockBackend>::default().internal_buffer_size;
+        let frames = duration.as_secs_f64() * MOCK_SAMPLE_RATE as f64;
+        let blocks = (frames / block as f64).ceil() as usize;
+        let mut manager = self.manager.lock();
+        let backend = manager.backend_mut();
+        for _ in 0..blocks {
+            backend.on_start_processing();
+            backend.process();
+        }
+    }
+}
+
+#[cfg(test)]
+fn mock_system() -> AudioSystem<MockBackend> {
+    let resource = Resource::empty();
+    resource.load(
+        "jump",
+        SharedData::from_static(include_bytes!("../../assets/audio/jump.mp3")),
+    );
+    let audio = AudioSystem::new_mock(resource).unwrap();
+    let effects = std::collections::HashMap::<String, super::EffectConfig>::new();
+    audio.add_group("sfx", 0.0, false, effects.clone()).unwrap();
+    audio.add_group("music", 0.0, false, effects).unwrap();
+    audio
+}
+
+#[test]
+fn test_mock_playback() -> anyhow::Result<()> {
+    use kira::sound::PlaybackState;
+    let audio = mock_system();
+    let step = Duration::from_millis(100);
+    audio.play("sfx", "jump", Default::default())?;
+    audio.advance(step);
+    assert_eq!(audio.state("sfx", "jump"), Some(PlaybackState::Playing));
+    let playing = audio.position("sfx", "jump").unwrap();
+    assert!(playing > 0.05, "position {}", playing);
+
+    audio.pause("sfx", "jump", 0)?;
+    audio.advance(step);
+    assert_eq!(audio.state("sfx", "jump"), Some(PlaybackState::Paused));
+    let paused = audio.position("sfx", "jump").unwrap();
+    audio.advance(step);
+    assert_eq!(audio.position("sfx", "jump"), Some(paused));
+    audio.resume("sfx", "jump", 0)?;
+    audio.advance(step);
+    assert_eq!(audio.state("sfx", "jump"), Some(PlaybackState::Playing));
+    assert!(audio.position("sfx", "jump").unwrap() > paused);
+
+    audio.seek_to("sfx", "jump", 1.0)?;
+    audio.advance(step);
+    assert!(audio.position("sfx", "jump").unwrap() >= 1.0);
+    audio.seek_by("sfx", "jump", -0.5)?;
+    audio.advance(step);
+    let sought = audio.position("sfx", "jump").unwrap();
+    assert!((0.5..1.0).contains(&sought), "position {}", sought);
+
+    audio.stop("sfx", "jump", 0)?;
+    audio.advance(step);
+    assert_eq!(audio.state("sfx", "jump"), Some(PlaybackState::Stopped));
+
+    // what can't be played is an error, not a panic
+    assert!(audio.play("ui", "jump", Default::default()).is_err());
+    let err = audio.play("sfx", "land", Default::default()).unwrap_err();
+    assert!(err.to_string().contains("land"));
+    assert!(audio.pause("music", "jump", 0).is_err());
+    assert!(audio.set_playback_rate("sfx", "jump", 10.0, 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_mock_groups() -> anyhow::Result<()> {
+    use kira::sound::PlaybackState;
+    let audio = mock_system();
+    let step = Duration::from_millis(100);
+    audio.play("sfx", "jump", Default::default())?;
+    audio.play("music", "jump", Default::default())?;
+    audio.advance(step);
+    assert_eq!(audio.musics.len(), 2);
+
+    // the same sound played again in a group replaces the tracked one
+    audio.advance(step * 3);
+    audio.play("sfx", "jump", Default::default())?;
+    audio.advance(step);
+    assert_eq!(audio.musics.len(), 2);
+    let replayed = audio.position("sfx", "jump").unwrap();
+    assert!(replayed < audio.position("music", "jump").unwrap());
+
+    audio.remove_group("music")?;
+    assert!(audio.state("music", "jump").is_none());
+    assert!(audio.play("music", "jump", Default::default()).is_err());
+    assert!(audio.remove_group("music").is_err());
+    assert_eq!(audio.musics.len(), 1);
+
+    // stopped sounds stay queryable until the next play collects them
+    audio.stop("sfx", "jump", 0)?;
+    audio.advance(step);
+    assert_eq!(audio.state("sfx", "jump"), Some(PlaybackState::Stopped));
+    assert_eq!(audio.gc(), 1);
+    assert!(audio.musics.is_empty() && audio.rates.is_empty());
+    assert_eq!(audio.state("sfx", "jump"), None);
+    Ok(())
+}
+
+#[test]
+fn test_decoded_once() -> anyhow::Result<()> {
+    use fool_resource::Fallback;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[derive(Debug, Clone, Default)]
+    struct Counting(Arc<AtomicUsize>);
+    impl Fallback for Counting {
+        type K = String;
+        type V = SharedData;
+        fn get(&self, _key: &Self::K) -> anyhow::Result<Self::V> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(SharedData::from_static(include_bytes!(
+                "../../assets/audio/jump.mp3"
+            )))
+        }
+    }
+    let reads = Counting::default();
+    let resource = Resource::from_fallback(reads.clone());
+    let audio = AudioSystem::new_mock(resource.clone())?;
+    let effects = std::collections::HashMap::<String, super::EffectConfig>::new();
+    audio.add_group("sfx", 0.0, false, effects)?;
+    audio.preload(&["jump"])?;
+    // the raw bytes are gone, only the decoded sound is left to play from
+    resource.remove("jump");
+    audio.play("sfx", "jump", Default::default())?;
+    let quiet = super::PlayOptions {
+        volume: Some(-6.0),
+        position: Some(0.5),
+        ..Default::default()
+    };
+    audio.play("sfx", "jump", quiet)?;
+    audio.advance(Duration::from_millis(100));
+    assert_eq!(reads.0.load(Ordering::Relaxed), 1);
+    // the options of the last play applied to its own handle only
+    assert!(audio.position("sfx", "jump").unwrap() >= 0.5);
+    let stats = audio.cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.sounds), (2, 1, 1));
+
+    audio.unload("jump");
+    audio.play("sfx", "jump", Default::default())?;
+    assert_eq!(reads.0.load(Ordering::Relaxed), 2);
+    assert_eq!(audio.cache_stats().misses, 2);
+    Ok(())
+}
+
+#[test]
+fn test_play_pcm() -> anyhow::Result<()> {
+    use kira::sound::PlaybackState;
+    use std::f32::consts::TAU;
+    let audio = mock_system();
+    let sine: Vec<f32> = (0..MOCK_SAMPLE_RATE)
+        .map(|i| (TAU * 440.0 * i as f32 / MOCK_SAMPLE_RATE as f32).sin() * 0.5)
+        .collect();
+    let tone = audio.play_pcm("sfx", sine.clone(), MOCK_SAMPLE_RATE, 1, Default::default())?;
+    audio.advance(Duration::from_millis(100));
+    assert_eq!(audio.state("sfx", &tone), Some(PlaybackState::Playing));
+    assert!(audio.position("sfx", &tone).unwrap() > 0.05);
+    // each buffer gets its own name, the first one keeps playing
+    let other = audio.play_pcm("sfx", sine, MOCK_SAMPLE_RATE, 2, Default::default())?;
+    assert_ne!(tone, other);
+    audio.stop("sfx", &tone, 0)?;
+    audio.advance(Duration::from_millis(100));
+    assert_eq!(audio.state("sfx", &tone), Some(PlaybackState::Stopped));
+    assert_eq!(audio.state("sfx", &other), Some(PlaybackState::Playing));
+
+    let fails = |group: &str, samples: usize, channels: u16| {
+        audio
+            .play_pcm(
+                group,
+                vec![0.0; samples],
+                48_000,
+                channels,
+                Default::default(),
+            )
+            .is_err()
+    };
+    assert!(fails("sfx", 3, 2));
+    assert!(fails("sfx", 4, 6));
+    assert!(fails("ui", 4, 1));
+    Ok(())
+}
+
+#[test]
+fn test_group_tree() -> anyhow::Result<()> {
+    use super::GroupNode;
+    use kira::sound::PlaybackState;
+    let audio = mock_system();
+    let effects = std::collections::HashMap::<String, super::EffectConfig>::new();
+    audio.add_group("sfx/weapons", 0.0, false, effects.clone())?;
+    audio.add_group("sfx/footsteps", 0.0, false, effects.clone())?;
+    // the missing parents are added too
+    audio.add_group("voice/npc/guard", 0.0, false, effects.clone())?;
+    let node = |path: &str, children: Vec<GroupNode>| GroupNode {
+        name: path.rsplit('/').next().unwrap().to_owned(),
+        path: path.to_owned(),
+        children,
+    };
+    assert_eq!(
+        audio.list_groups(),
+        vec![
+            node("music", vec![]),
+            node(
+                "sfx",
+                vec![node("sfx/footsteps", vec![]), node("sfx/weapons", vec![])]
+            ),
+            node(
+                "voice",
+                vec![node("voice/npc", vec![node("voice/npc/guard", vec![])])]
+            ),
+        ]
+    );
+
+    let step = Duration::from_millis(100);
+    let tone: Vec<f32> = vec![0.5; MOCK_SAMPLE_RATE as usize * 10];
+    let shot = audio.play_pcm(
+        "sfx/weapons",
+        tone.clone(),
+        MOCK_SAMPLE_RATE,
+        1,
+        Default::default(),
+    )?;
+    audio.advance(step);
+    assert_eq!(
+        audio.state("sfx/weapons", &shot),
+        Some(PlaybackState::Playing)
+    );
+    let loud = |audio: &AudioSystem<MockBackend>| audio.master_levels().1 > 0.1;
+    assert!(loud(&audio));
+    // the parent silences and pauses what is under it
+    audio.set_mute("sfx", true)?;
+    audio.advance(step * 30);
+    assert!(!loud(&audio));
+    audio.set_mute("sfx", false)?;
+    audio.advance(step);
+    assert!(loud(&audio));
+    audio.pause_group("sfx", 0)?;
+    audio.advance(step);
+    let paused = audio.position("sfx/weapons", &shot).unwrap();
+    audio.advance(step);
+    assert_eq!(audio.position("sfx/weapons", &shot), Some(paused));
+    audio.resume_group("sfx", 0)?;
+    audio.advance(step);
+    assert!(audio.position("sfx/weapons", &shot).unwrap() > paused);
+    // soloing a child keeps its parent audible, music is silenced
+    audio.play_pcm("music", tone, MOCK_SAMPLE_RATE, 1, Default::default())?;
+    audio.set_solo("sfx/weapons", true)?;
+    audio.set_mute("sfx/weapons", true)?;
+    audio.advance(step * 30);
+    assert!(!loud(&audio));
+    audio.set_mute("sfx/weapons", false)?;
+    audio.advance(step);
+    assert!(loud(&audio));
+
+    // a missing parent is told from a missing group
+    let err = audio
+        .play("ui/menu", "jump", Default::default())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "parent group ui of ui/menu Not Found!");
+    let err = audio
+        .play("sfx/ui", "jump", Default::default())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "group sfx/ui Not Found!");
+    assert!(audio.add_group("sfx//ui", 0.0, false, effects).is_err());
+
+    // removing a group takes the ones under it and their sounds along
+    audio.remove_group("sfx")?;
+    assert_eq!(audio.state("sfx/weapons", &shot), None);
+    assert!(
+        audio
+            .play("sfx/weapons", "jump", Default::default())
+            .is_err()
+    );
+    let paths: Vec<_> = audio
+        .list_groups()
+        .into_iter()
+        .map(|node| node.path)
+        .collect();
+    assert_eq!(paths, ["music", "voice"]);
+    assert!(
+        audio
+            .musics
+            .iter()
+            .all(|music| music.key().track == "music")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mute_solo() -> anyhow::Result<()> {
+    let audio = mock_system();
+    let step = Duration::from_millis(100);
+    let rms = |audio: &AudioSystem<MockBackend>| {
+        audio.advance(step * 30);
+        audio.master_levels().1
+    };
+    let tone: Vec<f32> = vec![0.5; MOCK_SAMPLE_RATE as usize * 40];
+    let ambience = audio.play_pcm("sfx", tone.clone(), MOCK_SAMPLE_RATE, 1, Default::default())?;
+    let full = rms(&audio);
+    assert!(full > 0.1);
+
+    // a volume set while muted is the one it comes back at
+    audio.mute("sfx", &ambience)?;
+    assert_eq!(audio.is_muted("sfx", &ambience), Some(true));
+    assert!(rms(&audio) < 0.01);
+    let muted_at = audio.position("sfx", &ambience).unwrap();
+    audio.set_volume("sfx", &ambience, 0, -6.0)?;
+    assert!(rms(&audio) < 0.01);
+    assert!(audio.position("sfx", &ambience).unwrap() > muted_at);
+    audio.unmute("sfx", &ambience)?;
+    assert_eq!(audio.is_muted("sfx", &ambience), Some(false));
+    let quieter = rms(&audio);
+    assert!(quieter > 0.1 && quieter < full * 0.6);
+    assert_eq!(audio.is_muted("sfx", "missing"), None);
+    assert!(audio.mute("sfx", "missing").is_err());
+
+    // the same for groups
+    audio.mute_group("sfx")?;
+    audio.set_group_volume("sfx", -6.0, 0)?;
+    assert!(rms(&audio) < 0.01);
+    assert_eq!(audio.is_group_muted("sfx"), Some(true));
+    audio.unmute_group("sfx")?;
+    let group_quieter = rms(&audio);
+    assert!(group_quieter < quieter * 0.6 && group_quieter > 0.05);
+    assert_eq!(audio.is_group_muted("ui"), None);
+
+    // soloing music silences sfx, also a group added while it is soloed
+    audio.solo_group("music")?;
+    assert_eq!(audio.is_group_soloed("music"), Some(true));
+    assert!(rms(&audio) < 0.01);
+    let effects = std::collections::HashMap::<String, super::EffectConfig>::new();
+    audio.add_group("ui", 0.0, false, effects)?;
+    audio.play_pcm("ui", tone, MOCK_SAMPLE_RATE, 1, Default::default())?;
+    assert!(rms(&audio) < 0.01);
+    audio.unsolo();
+    assert_eq!(audio.is_group_soloed("music"), Some(false));
+    assert!(rms(&audio) > group_quieter);
+    Ok(())
+}
+
+#[test]
+fn test_parent_added_after_child() -> anyhow::Result<()> {
+    use super::EffectConfig;
+    use std::collections::HashMap;
+    let audio = mock_system();
+    let step = Duration::from_millis(100);
+    let rms = |audio: &AudioSystem<MockBackend>| {
+        audio.advance(step * 30);
+        audio.master_levels().1
+    };
+    let none = HashMap::<String, EffectConfig>::new();
+    audio.add_group("voice/npc", 0.0, false, none.clone())?;
+    let tone: Vec<f32> = vec![0.5; MOCK_SAMPLE_RATE as usize * 40];
+    let line = audio.play_pcm("voice/npc", tone, MOCK_SAMPLE_RATE, 1, Default::default())?;
+    let full = rms(&audio);
+    assert!(full > 0.1);
+
+    // configuring the parent afterwards only sets its volume, the child keeps playing
+    audio.mute_group("voice/npc")?;
+    audio.add_group("voice", -6.0, false, none.clone())?;
+    assert_eq!(audio.is_group_muted("voice/npc"), Some(true));
+    audio.unmute_group("voice/npc")?;
+    let quieter = rms(&audio);
+    assert!(quieter > 0.05 && quieter < full * 0.6);
+    let played = audio.position("voice/npc", &line).unwrap();
+    audio.advance(step);
+    assert!(audio.position("voice/npc", &line).unwrap() > played);
+
+    // new effects build the parent again, the child comes along with its settings
+    audio.set_time_scaled("voice/npc", true)?;
+    let effects = HashMap::from([(
+        "echo".to_owned(),
+        EffectConfig::Delay {
+            delay_time: None,
+            feedback: None,
+            mix: None,
+        },
+    )]);
+    audio.add_group("voice", 0.0, false, effects)?;
+    let paths: Vec<_> = audio.list_groups()[2]
+        .children
+        .iter()
+        .map(|node| node.path.clone())
+        .collect();
+    assert_eq!(paths, ["voice/npc"]);
+    assert!(
+        audio
+            .groups
+            .get("voice")
+            .unwrap()
+            .effects
+            .contains_key("echo")
+    );
+    assert!(audio.groups.get("voice/npc").unwrap().time_scaled);
+    assert_eq!(audio.state("voice/npc", &line), None);
+    audio.play_pcm(
+        "voice/npc",
+        vec![0.5; MOCK_SAMPLE_RATE as usize * 10],
+        MOCK_SAMPLE_RATE,
+        1,
+        Default::default(),
+    )?;
+    assert!(rms(&audio) > 0.1);
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use crate::event::{ActionMap, HoldMode, RepeatSettings};
+use crate::map2lua_error;
+use crate::save::SaveManager;
+use fool_graphics::canvas::SceneGraph;
+use fool_graphics::EffectStack;
+use mlua::{LuaSerdeExt, Table, UserData, UserDataMethods, Value};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// reserved, scripts can't overwrite it through the save manager
+const SAVE_SLOT: &str = "__accessibility";
+const MIN_UI_SCALE: f32 = 0.5;
+const MAX_UI_SCALE: f32 = 4.0;
+
+type Matrix = [[f32; 3]; 3];
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorBlindness {
+    #[default]
+    None,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// how the colors look with it, Machado et al. 2009 at full severity
+    pub fn simulation(&self) -> Matrix {
+        match self {
+            Self::None => IDENTITY,
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+    /// daltonization, what can't be told apart is moved into the channels that still can
+    pub fn correction(&self) -> Matrix {
+        let shift = match self {
+            Self::None => return IDENTITY,
+            Self::Protanopia | Self::Deuteranopia => {
+                [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]]
+            }
+            Self::Tritanopia => [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]],
+        };
+        let simulation = self.simulation();
+        // color + shift * (color - simulated color)
+        let lost: Matrix =
+            std::array::from_fn(|i| std::array::from_fn(|j| IDENTITY[i][j] - simulation[i][j]));
+        let moved = mul(&shift, &lost);
+        std::array::from_fn(|i| std::array::from_fn(|j| IDENTITY[i][j] + moved[i][j]))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorFilter {
+    #[default]
+    Correct,
+    /// shows the game the way it is seen, for testing a palette
+    Simulate,
+}
+
+/// the player's settings, kept in a reserved save slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub colorblind: ColorBlindness,
+    pub color_filter: ColorFilter,
+    /// multiplies the gui size, world space ui can read it from the scene graph
+    pub ui_scale: f32,
+    /// seconds a menu key is held before it repeats
+    pub repeat_delay: f32,
+    /// repeats per second, 0 turns repeating off
+    pub repeat_rate: f32,
+    pub hold_mode: HoldMode,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        let repeat = RepeatSettings::default();
+        Self {
+            colorblind: ColorBlindness::None,
+            color_filter: ColorFilter::Correct,
+            ui_scale: 1.0,
+            repeat_delay: repeat.delay,
+            repeat_rate: repeat.rate,
+            hold_mode: repeat.hold_mode,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    /// out of range values clamped
+    fn sanitized(mut self) -> Self {
+        self.ui_scale = match self.ui_scale.is_finite() {
+            true => self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE),
+            false => 1.0,
+        };
+        self.repeat_delay = self.repeat_delay.max(0.0);
+        self.repeat_rate = self.repeat_rate.max(0.0);
+        self
+    }
+    /// None when the screen is left as it is
+    pub fn color_matrix(&self) -> Option<Matrix> {
+        match (self.colorblind, self.color_filter) {
+            (ColorBlindness::None, _) => None,
+            (kind, ColorFilter::Correct) => Some(kind.correction()),
+            (kind, ColorFilter::Simulate) => Some(kind.simulation()),
+        }
+    }
+    pub fn repeat(&self) -> RepeatSettings {
+        RepeatSettings {
+            delay: self.repeat_delay,
+            rate: self.repeat_rate,
+            hold_mode: self.hold_mode,
+        }
+    }
+}
+
+/// applies the settings to the render, the gui and the input, and saves them
+#[derive(Clone)]
+pub struct Accessibility {
+    settings: Arc<RwLock<AccessibilitySettings>>,
+    save: SaveManager,
+    effects: Arc<Mutex<EffectStack>>,
+    actions: ActionMap,
+    scene_graph: Arc<RwLock<SceneGraph>>,
+}
+
+impl Accessibility {
+    pub fn new(
+        save: SaveManager,
+        effects: Arc<Mutex<EffectStack>>,
+        actions: ActionMap,
+        scene_graph: Arc<RwLock<SceneGraph>>,
+    ) -> Self {
+        Self {
+            settings: Default::default(),
+            save,
+            effects,
+            actions,
+            scene_graph,
+        }
+    }
+    /// applies the saved settings, the defaults when there are none
+    pub fn load(&self) {
+        let saved = self
+            .save
+            .load(SAVE_SLOT)
+            .and_then(|entry| Ok(bson::from_bson::<AccessibilitySettings>(entry.data)?));
+        let settings = match saved {
+            Ok(settings) => settings,
+            Err(err) => {
+                log::debug!("no saved accessibility settings, using defaults: {}", err);
+                Default::default()
+            }
+        };
+        self.apply(settings);
+    }
+    fn apply(&self, settings: AccessibilitySettings) {
+        let settings = settings.sanitized();
+        self.effects.lock().color_matrix = settings.color_matrix();
+        self.actions.set_repeat(settings.repeat());
+        self.scene_graph
+            .write()
+            .set_ui_scale(Some(settings.ui_scale as f64));
+        *self.settings.write() = settings;
+    }
+    /// takes effect right away and is saved for the next start
+    pub fn set(&self, settings: AccessibilitySettings) -> anyhow::Result<()> {
+        self.apply(settings);
+        let data = bson::to_bson(&self.settings())?;
+        self.save.save(Some(SAVE_SLOT), true, data)
+    }
+    pub fn settings(&self) -> AccessibilitySettings {
+        self.settings.read().clone()
+    }
+    pub fn ui_scale(&self) -> f32 {
+        self.settings.read().ui_scale
+    }
+}
+
+impl UserData for Accessibility {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // only the given options change
+        methods.add_method("set", |lua, this, options: Table| {
+            let current = lua.to_value(&this.settings())?;
+            let Value::Table(current) = current else {
+                return Err(mlua::Error::RuntimeError(
+                    "accessibility settings are not a table".into(),
+                ));
+            };
+            for pair in options.pairs::<String, Value>() {
+                let (name, value) = pair?;
+                if current.get::<Value>(name.as_str())?.is_nil() {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "unknown accessibility option {}",
+                        name
+                    )));
+                }
+                current.set(name, value)?;
+            }
+            let settings = lua.from_value(Value::Table(current))?;
+            map2lua_error!(this.set(settings), "Accessibility::set")
+        });
+        methods.add_method("get", |lua, this, ()| lua.to_value(&this.settings()));
+        methods.add_method("reset", |_, this, ()| {
+            map2lua_error!(this.set(Default::default()), "Accessibility::reset")
+        });
+        methods.add_method("ui_scale", |_, this, ()| Ok(this.ui_scale()));
+    }
+}
+
+#[test]
+fn test_color_matrices() {
+    let apply = |m: &Matrix, c: [f32; 3]| -> [f32; 3] {
+        std::array::from_fn(|i| (0..3).map(|k| m[i][k] * c[k]).sum())
+    };
+    let near = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 0.01);
+    for kind in [
+        ColorBlindness::Protanopia,
+        ColorBlindness::Deuteranopia,
+        ColorBlindness::Tritanopia,
+    ] {
+        // greys stay grey
+        for m in [kind.simulation(), kind.correction()] {
+            assert!(near(apply(&m, [0.5; 3]), [0.5; 3]), "{:?}", kind);
+        }
+    }
+    // red and green look alike to a deuteranope, less so once corrected
+    let kind = ColorBlindness::Deuteranopia;
+    let (red, green) = ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+    let distance = |a: [f32; 3], b: [f32; 3]| -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let seen = |c| apply(&kind.simulation(), c);
+    let corrected = |c| seen(apply(&kind.correction(), c));
+    assert!(distance(corrected(red), corrected(green)) > distance(seen(red), seen(green)));
+    assert_eq!(AccessibilitySettings::default().color_matrix(), None);
+}
+
+#[test]
+fn test_accessibility_settings() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("test_accessibility_settings");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let save = SaveManager::new(&dir);
+    let effects = Arc::new(Mutex::new(EffectStack::default()));
+    let actions = ActionMap::new(save.clone());
+    let scene_graph = Arc::new(RwLock::new(SceneGraph::default()));
+    let accessibility = Accessibility::new(
+        save.clone(),
+        effects.clone(),
+        actions.clone(),
+        scene_graph.clone(),
+    );
+    let lua = mlua::Lua::new();
+    lua.globals().set("accessibility", accessibility.clone())?;
+    lua.load(r#"accessibility:set{colorblind="deuteranopia", ui_scale=1.25, hold_mode="toggle"}"#)
+        .exec()?;
+    assert!(effects.lock().color_matrix.is_some());
+    assert_eq!(actions.repeat().hold_mode, HoldMode::Toggle);
+    assert_eq!(scene_graph.read().ui_scale(), 1.25);
+    assert!(lua
+        .load(r#"accessibility:set{colour="red"}"#)
+        .exec()
+        .is_err());
+    assert!(lua
+        .load(r#"accessibility:set{colorblind="purple"}"#)
+        .exec()
+        .is_err());
+    // out of range is clamped, the rest is kept
+    lua.load("accessibility:set{ui_scale=100}").exec()?;
+    assert_eq!(accessibility.ui_scale(), MAX_UI_SCALE);
+
+    // the next start gets them back
+    let effects = Arc::new(Mutex::new(EffectStack::default()));
+    let restored = Accessibility::new(save.clone(), effects.clone(), actions, scene_graph);
+    restored.load();
+    assert_eq!(restored.settings(), accessibility.settings());
+    assert!(effects.lock().color_matrix.is_some());
+    // hidden from the scripts' saves
+    assert!(save
+        .list()?
+        .iter()
+        .any(|e| e.name.as_deref() == Some(SAVE_SLOT)));
+    lua.globals().set("save", save)?;
+    let listed: usize = lua.load("return #save:list()").eval()?;
+    assert_eq!(listed, 0);
+    assert!(lua
+        .load(r#"save:delete("__accessibility")"#)
+        .exec()
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use super::{AchievementBackend, StatDef};
+use crate::save::SaveManager;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// reserved, scripts can't overwrite it through the save manager
+const SAVE_SLOT: &str = "__achievements";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LocalData {
+    unlocked: BTreeSet<String>,
+    stats: BTreeMap<String, f64>,
+    /// the last progress shown, current and max
+    progress: BTreeMap<String, (u32, u32)>,
+}
+
+/// keeps everything in a reserved save slot, for development and stores without
+/// an achievement service
+pub struct LocalBackend {
+    save: SaveManager,
+    data: LocalData,
+}
+
+impl LocalBackend {
+    /// what was saved, nothing unlocked when there is no save
+    pub fn load(save: SaveManager) -> Self {
+        let saved = save
+            .load(SAVE_SLOT)
+            .and_then(|entry| Ok(bson::from_bson::<LocalData>(entry.data)?));
+        let data = match saved {
+            Ok(data) => data,
+            Err(err) => {
+                log::debug!("no saved achievements: {}", err);
+                Default::default()
+            }
+        };
+        Self { save, data }
+    }
+}
+
+impl AchievementBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+    fn is_unlocked(&self, id: &str) -> bool {
+        self.data.unlocked.contains(id)
+    }
+    fn unlock(&mut self, id: &str) -> anyhow::Result<()> {
+        self.data.unlocked.insert(id.to_owned());
+        self.data.progress.remove(id);
+        Ok(())
+    }
+    fn get_stat(&self, stat: &StatDef) -> f64 {
+        self.data.stats.get(&stat.id).copied().unwrap_or_default()
+    }
+    fn set_stat(&mut self, stat: &StatDef, value: f64) -> anyhow::Result<()> {
+        self.data.stats.insert(stat.id.clone(), value);
+        Ok(())
+    }
+    fn progress(&mut self, id: &str, current: u32, max: u32) -> anyhow::Result<()> {
+        log::debug!("achievement {} at {}/{}", id, current, max);
+        self.data.progress.insert(id.to_owned(), (current, max));
+        Ok(())
+    }
+    fn store(&mut self) -> anyhow::Result<()> {
+        let data = bson::to_bson(&self.data)?;
+        self.save.save(Some(SAVE_SLOT), true, data)
+    }
+}
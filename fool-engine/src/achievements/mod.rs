@@ -0,0 +1,426 @@
+use crate::map2anyhow_error;
+use crate::map2lua_error;
+use crate::save::SaveManager;
+use mlua::{Function, IntoLua, Lua, UserData, UserDataMethods, Value};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+mod local;
+#[cfg(feature = "steam")]
+mod steam;
+pub use local::LocalBackend;
+#[cfg(feature = "steam")]
+pub use steam::SteamBackend;
+
+/// the definitions in the assets, the same file can seed the steam partner config
+pub const DEFINITIONS_FILE: &str = "achievements.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatKind {
+    #[default]
+    Int,
+    Float,
+}
+
+impl StatKind {
+    /// int stats drop the fraction, the way steam stores them
+    pub fn normalize(&self, value: f64) -> f64 {
+        match self {
+            Self::Int => value.trunc(),
+            Self::Float => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatDef {
+    pub id: String,
+    #[serde(default)]
+    pub kind: StatKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AchievementDef {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// asset key of the icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// name and description are kept from the player until it is unlocked
+    #[serde(default)]
+    pub hidden: bool,
+    /// unlocked once this stat reaches `threshold`
+    #[serde(default)]
+    pub stat: Option<String>,
+    #[serde(default)]
+    pub threshold: Option<f64>,
+}
+
+impl IntoLua for AchievementDef {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        let table = lua.create_table()?;
+        table.set("id", self.id)?;
+        table.set("name", self.name)?;
+        table.set("description", self.description)?;
+        table.set("icon", self.icon)?;
+        table.set("hidden", self.hidden)?;
+        Ok(Value::Table(table))
+    }
+}
+
+/// `achievements.toml`, `[[achievement]]` and `[[stat]]` tables
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Definitions {
+    #[serde(default, rename = "achievement")]
+    pub achievements: Vec<AchievementDef>,
+    #[serde(default, rename = "stat")]
+    pub stats: Vec<StatDef>,
+}
+
+impl Definitions {
+    pub fn parse(toml: &str) -> anyhow::Result<Self> {
+        let defs: Self = toml::from_str(toml)?;
+        let mut ids = HashSet::new();
+        for id in defs.stats.iter().map(|s| &s.id) {
+            if !ids.insert(id) {
+                return Err(anyhow::anyhow!("stat {} is defined twice", id));
+            }
+        }
+        let mut ids = HashSet::new();
+        for achievement in &defs.achievements {
+            if !ids.insert(&achievement.id) {
+                return Err(anyhow::anyhow!(
+                    "achievement {} is defined twice",
+                    achievement.id
+                ));
+            }
+            match (&achievement.stat, achievement.threshold) {
+                (None, None) => {}
+                (Some(stat), Some(_)) if defs.stat(stat).is_some() => {}
+                (Some(stat), Some(_)) => {
+                    return Err(anyhow::anyhow!(
+                        "achievement {} unlocks on stat {} which is not defined",
+                        achievement.id,
+                        stat
+                    ))
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "achievement {} needs both stat and threshold",
+                        achievement.id
+                    ))
+                }
+            }
+        }
+        Ok(defs)
+    }
+    pub fn achievement(&self, id: &str) -> Option<&AchievementDef> {
+        self.achievements.iter().find(|a| a.id == id)
+    }
+    pub fn stat(&self, id: &str) -> Option<&StatDef> {
+        self.stats.iter().find(|s| s.id == id)
+    }
+}
+
+/// where the unlocks and stats are kept, the ids are checked against the definitions
+/// before they get here
+pub trait AchievementBackend: Send {
+    fn name(&self) -> &'static str;
+    fn is_unlocked(&self, id: &str) -> bool;
+    fn unlock(&mut self, id: &str) -> anyhow::Result<()>;
+    fn get_stat(&self, stat: &StatDef) -> f64;
+    fn set_stat(&mut self, stat: &StatDef, value: f64) -> anyhow::Result<()>;
+    /// shows how far along `id` is, it isn't unlocked by this
+    fn progress(&mut self, id: &str, current: u32, max: u32) -> anyhow::Result<()>;
+    /// writes what changed since the last store
+    fn store(&mut self) -> anyhow::Result<()>;
+    /// runs the platform callbacks, once per frame
+    fn pump(&mut self) {}
+}
+
+/// unlocks and stats through the backend in use, a local one unless steam is running
+#[derive(Clone)]
+pub struct Achievements {
+    defs: Arc<RwLock<Definitions>>,
+    backend: Arc<Mutex<Box<dyn AchievementBackend>>>,
+    // unlocked since the last poll, `on_unlock` runs there
+    unlocked: Arc<Mutex<Vec<String>>>,
+    changed: Arc<AtomicBool>,
+    on_unlock: Arc<Mutex<Option<Function>>>,
+}
+
+impl Achievements {
+    pub fn new(backend: impl AchievementBackend + 'static) -> Self {
+        Self {
+            defs: Default::default(),
+            backend: Arc::new(Mutex::new(Box::new(backend))),
+            unlocked: Default::default(),
+            changed: Default::default(),
+            on_unlock: Default::default(),
+        }
+    }
+    /// kept in a reserved save slot
+    pub fn local(save: SaveManager) -> Self {
+        Self::new(LocalBackend::load(save))
+    }
+    /// steam when it is running, the local backend otherwise
+    #[cfg(feature = "steam")]
+    pub fn connect_steam(&self, app_id: u32) {
+        match SteamBackend::init(app_id) {
+            Ok(steam) => {
+                log::info!("achievements go to steam app {}", app_id);
+                *self.backend.lock() = Box::new(steam);
+            }
+            Err(err) => log::warn!("steam is not running, achievements stay local: {}", err),
+        }
+    }
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.lock().name()
+    }
+    pub fn set_definitions(&self, defs: Definitions) {
+        *self.defs.write() = defs;
+    }
+    /// `achievements.toml` from the assets, none is no achievements
+    pub fn load_definitions(&self, toml: Option<&str>) -> anyhow::Result<()> {
+        let defs = match toml {
+            Some(toml) => Definitions::parse(toml)?,
+            None => Definitions::default(),
+        };
+        log::debug!(
+            "{} achievements and {} stats defined",
+            defs.achievements.len(),
+            defs.stats.len()
+        );
+        self.set_definitions(defs);
+        Ok(())
+    }
+    pub fn definitions(&self) -> Definitions {
+        self.defs.read().clone()
+    }
+    fn check_achievement(&self, id: &str) -> anyhow::Result<()> {
+        match self.defs.read().achievement(id) {
+            Some(_) => Ok(()),
+            None => Err(anyhow::anyhow!("achievement {} is not defined", id)),
+        }
+    }
+    fn stat_def(&self, id: &str) -> anyhow::Result<StatDef> {
+        match self.defs.read().stat(id) {
+            Some(stat) => Ok(stat.clone()),
+            None => Err(anyhow::anyhow!("stat {} is not defined", id)),
+        }
+    }
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.backend.lock().is_unlocked(id)
+    }
+    /// true when it was locked, unlocking it again does nothing so it can be
+    /// called every frame
+    pub fn unlock(&self, id: &str) -> anyhow::Result<bool> {
+        self.check_achievement(id)?;
+        let mut backend = self.backend.lock();
+        if backend.is_unlocked(id) {
+            return Ok(false);
+        }
+        backend.unlock(id)?;
+        log::info!("achievement {} unlocked", id);
+        self.changed.store(true, Ordering::Relaxed);
+        self.unlocked.lock().push(id.to_owned());
+        Ok(true)
+    }
+    pub fn get_stat(&self, id: &str) -> anyhow::Result<f64> {
+        let stat = self.stat_def(id)?;
+        Ok(self.backend.lock().get_stat(&stat))
+    }
+    /// the achievements linked to the stat unlock once it reaches their threshold
+    pub fn set_stat(&self, id: &str, value: f64) -> anyhow::Result<f64> {
+        let stat = self.stat_def(id)?;
+        if !value.is_finite() {
+            return Err(anyhow::anyhow!("stat {} can't be set to {}", id, value));
+        }
+        let value = stat.kind.normalize(value);
+        {
+            let mut backend = self.backend.lock();
+            if backend.get_stat(&stat) == value {
+                return Ok(value);
+            }
+            backend.set_stat(&stat, value)?;
+        }
+        self.changed.store(true, Ordering::Relaxed);
+        let reached: Vec<String> = self
+            .defs
+            .read()
+            .achievements
+            .iter()
+            .filter(|a| a.stat.as_deref() == Some(id))
+            .filter(|a| a.threshold.is_some_and(|threshold| value >= threshold))
+            .map(|a| a.id.clone())
+            .collect();
+        for achievement in reached {
+            self.unlock(&achievement)?;
+        }
+        Ok(value)
+    }
+    pub fn add_stat(&self, id: &str, delta: f64) -> anyhow::Result<f64> {
+        let current = self.get_stat(id)?;
+        self.set_stat(id, current + delta)
+    }
+    pub fn progress(&self, id: &str, current: u32, max: u32) -> anyhow::Result<()> {
+        self.check_achievement(id)?;
+        let mut backend = self.backend.lock();
+        if backend.is_unlocked(id) {
+            return Ok(());
+        }
+        backend.progress(id, current.min(max), max)?;
+        self.changed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+    /// stores what changed and runs `on_unlock` for the new unlocks, once per frame
+    pub fn poll(&self) -> anyhow::Result<()> {
+        {
+            let mut backend = self.backend.lock();
+            backend.pump();
+            if self.changed.swap(false, Ordering::Relaxed) {
+                if let Err(err) = backend.store() {
+                    log::error!("store achievements failed: {}", err);
+                }
+            }
+        }
+        let unlocked = std::mem::take(&mut *self.unlocked.lock());
+        // no lock is held, the callback may unlock more
+        let callback = self.on_unlock.lock().clone();
+        let Some(callback) = callback else {
+            return Ok(());
+        };
+        for id in unlocked {
+            let def = self.defs.read().achievement(&id).cloned();
+            map2anyhow_error!(
+                callback.call::<()>((id.as_str(), def)),
+                "run achievements on_unlock"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl UserData for Achievements {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("unlock", |_, this, id: String| {
+            map2lua_error!(this.unlock(&id), "Achievements::unlock")
+        });
+        methods.add_method("is_unlocked", |_, this, id: String| {
+            Ok(this.is_unlocked(&id))
+        });
+        methods.add_method("set_stat", |_, this, (id, value): (String, f64)| {
+            map2lua_error!(this.set_stat(&id, value), "Achievements::set_stat")
+        });
+        methods.add_method("add_stat", |_, this, (id, delta): (String, f64)| {
+            map2lua_error!(this.add_stat(&id, delta), "Achievements::add_stat")
+        });
+        methods.add_method("get_stat", |_, this, id: String| {
+            map2lua_error!(this.get_stat(&id), "Achievements::get_stat")
+        });
+        methods.add_method(
+            "progress",
+            |_, this, (id, current, max): (String, u32, u32)| {
+                map2lua_error!(this.progress(&id, current, max), "Achievements::progress")
+            },
+        );
+        // every definition with `unlocked` set, in the order of the file
+        methods.add_method("list", |lua, this, ()| {
+            let list = lua.create_table()?;
+            for def in this.definitions().achievements {
+                let unlocked = this.is_unlocked(&def.id);
+                let Value::Table(entry) = def.into_lua(lua)? else {
+                    unreachable!()
+                };
+                entry.set("unlocked", unlocked)?;
+                list.push(entry)?;
+            }
+            Ok(list)
+        });
+        // `callback(id, definition)` after the frame it was unlocked in
+        methods.add_method("on_unlock", |_, this, callback: Option<Function>| {
+            *this.on_unlock.lock() = callback;
+            Ok(())
+        });
+        methods.add_method("backend", |_, this, ()| Ok(this.backend_name()));
+    }
+}
+
+#[test]
+fn test_achievements() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("test_achievements");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let save = SaveManager::new(&dir);
+    let defs = r#"
+        [[stat]]
+        id = "kills"
+
+        [[achievement]]
+        id = "first_blood"
+        name = "First Blood"
+        icon = "image/first_blood.png"
+
+        [[achievement]]
+        id = "hundred"
+        name = "Hundred"
+        hidden = true
+        stat = "kills"
+        threshold = 100
+    "#;
+    let achievements = Achievements::local(save.clone());
+    achievements.load_definitions(Some(defs))?;
+    let lua = Lua::new();
+    lua.globals().set("achievements", achievements.clone())?;
+    lua.load(
+        r#"
+        unlocks = {}
+        achievements:on_unlock(function(id, def) table.insert(unlocks, def.name) end)
+        "#,
+    )
+    .exec()?;
+    let unlocks = || -> anyhow::Result<Vec<String>> { Ok(lua.load("return unlocks").eval()?) };
+    // every frame is harmless, the callback runs once
+    for _ in 0..3 {
+        lua.load(r#"achievements:unlock("first_blood")"#).exec()?;
+        achievements.poll()?;
+    }
+    assert_eq!(unlocks()?, ["First Blood"]);
+    assert!(achievements.unlock("nope").is_err());
+
+    // the stat unlocks the linked one at its threshold
+    assert_eq!(achievements.add_stat("kills", 99.7)?, 99.0);
+    assert!(!achievements.is_unlocked("hundred"));
+    lua.load(r#"achievements:add_stat("kills", 1)"#).exec()?;
+    achievements.poll()?;
+    assert!(achievements.is_unlocked("hundred"));
+    assert_eq!(unlocks()?, ["First Blood", "Hundred"]);
+    assert!(achievements.set_stat("deaths", 1.0).is_err());
+
+    // kept for the next start, out of the scripts' saves
+    let restored = Achievements::local(save.clone());
+    restored.load_definitions(Some(defs))?;
+    assert!(restored.is_unlocked("first_blood") && restored.is_unlocked("hundred"));
+    assert_eq!(restored.get_stat("kills")?, 100.0);
+    assert!(save
+        .list()?
+        .iter()
+        .all(|e| e.name.as_deref().is_some_and(crate::save::is_reserved)));
+
+    assert!(Definitions::parse(
+        r#"
+        [[achievement]]
+        id = "a"
+        name = "A"
+        stat = "missing"
+        threshold = 1
+        "#
+    )
+    .is_err());
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use super::{AchievementBackend, StatDef, StatKind};
+use std::ffi::CString;
+use steamworks::{sys, AppId, Client};
+
+/// the unlocks and stats of the logged in steam user, set up in the partner config
+/// from the same ids as `achievements.toml`
+pub struct SteamBackend {
+    client: Client,
+}
+
+impl SteamBackend {
+    /// fails when the steam client isn't running or doesn't own `app_id`
+    pub fn init(app_id: u32) -> anyhow::Result<Self> {
+        let client = Client::init_app(AppId(app_id))
+            .map_err(|err| anyhow::anyhow!("steam init failed: {}", err))?;
+        Ok(Self { client })
+    }
+}
+
+impl AchievementBackend for SteamBackend {
+    fn name(&self) -> &'static str {
+        "steam"
+    }
+    fn is_unlocked(&self, id: &str) -> bool {
+        self.client
+            .user_stats()
+            .achievement(id)
+            .get()
+            .unwrap_or(false)
+    }
+    fn unlock(&mut self, id: &str) -> anyhow::Result<()> {
+        self.client
+            .user_stats()
+            .achievement(id)
+            .set()
+            .map_err(|_| anyhow::anyhow!("steam has no achievement {}", id))
+    }
+    fn get_stat(&self, stat: &StatDef) -> f64 {
+        let stats = self.client.user_stats();
+        let value = match stat.kind {
+            StatKind::Int => stats.get_stat_i32(&stat.id).map(f64::from),
+            StatKind::Float => stats.get_stat_f32(&stat.id).map(f64::from),
+        };
+        value.unwrap_or_default()
+    }
+    fn set_stat(&mut self, stat: &StatDef, value: f64) -> anyhow::Result<()> {
+        let stats = self.client.user_stats();
+        let set = match stat.kind {
+            StatKind::Int => stats.set_stat_i32(&stat.id, value as i32),
+            StatKind::Float => stats.set_stat_f32(&stat.id, value as f32),
+        };
+        set.map_err(|_| anyhow::anyhow!("steam has no {:?} stat {}", stat.kind, stat.id))
+    }
+    fn progress(&mut self, id: &str, current: u32, max: u32) -> anyhow::Result<()> {
+        // the safe wrapper has no progress call, the flat api does, its accessor is
+        // versioned by the sdk so steamworks is pinned in Cargo.toml
+        let name = CString::new(id)?;
+        // SAFETY: `self.client` keeps the steam api initialized for the whole call,
+        // the interface is checked for null before use and `name` is a nul terminated
+        // string that outlives the call, steam doesn't keep the pointer
+        let shown = unsafe {
+            let stats = sys::SteamAPI_SteamUserStats_v013();
+            if stats.is_null() {
+                anyhow::bail!("steam user stats interface is not available");
+            }
+            sys::SteamAPI_ISteamUserStats_IndicateAchievementProgress(
+                stats,
+                name.as_ptr(),
+                current,
+                max,
+            )
+        };
+        if !shown {
+            anyhow::bail!("steam can't show the progress of {}", id);
+        }
+        Ok(())
+    }
+    fn store(&mut self) -> anyhow::Result<()> {
+        self.client
+            .user_stats()
+            .store_stats()
+            .map_err(|_| anyhow::anyhow!("steam store stats failed"))
+    }
+    fn pump(&mut self) {
+        self.client.run_callbacks();
+    }
+}
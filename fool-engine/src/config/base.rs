@@ -1,4 +1,6 @@
 use crate::create_if_not_exists;
+use crate::resource::{AssetSource, IntegrityCheck};
+use crate::save::SaveManager;
 use crate::utils::dir;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -6,11 +8,73 @@ use std::path::PathBuf;
 pub struct BaseConfig {
     pub name: String,
     pub capture_path: PathBuf,
+    // relative to the documents dir, empty puts the saves in the per-user data dir
+    #[serde(default)]
     pub save_path: PathBuf,
     pub assets_path: PathBuf,
-    pub fps: u32,
+    // folder or pak regardless of the build, unset reads `assets_path` the way the build does
+    #[serde(default)]
+    pub assets_source: Option<AssetSource>,
+    // render rate, None renders at the display rate
+    #[serde(default)]
+    pub fps: Option<u32>,
+    // fixed logic update rate
+    #[serde(default = "default_update_hz")]
+    pub update_hz: u32,
+    // max time in ms the lua on_exit hook may run
+    #[serde(default = "default_exit_timeout")]
+    pub exit_timeout: u64,
+    // how much of the assets is verified at startup
+    #[serde(default)]
+    pub integrity_check: IntegrityCheck,
+    // lets text input read and write the system clipboard
+    #[serde(default = "default_clipboard")]
+    pub clipboard: bool,
+    // script folders run sandboxed, they share data through `mods.shared` only
+    #[serde(default)]
+    pub mod_mounts: Vec<String>,
+    // folders and paks with a mod.toml, next to the executable
+    #[serde(default = "default_mods_path")]
+    pub mods_path: PathBuf,
+    // F3 toggles the debug overlay, scripts can toggle it either way
+    #[serde(default = "default_debug_overlay")]
+    pub debug_overlay: bool,
+    // pauses the audio and the updates while the window is in the background
+    #[serde(default)]
+    pub pause_on_focus_loss: bool,
+    // achievements go to steam when it runs, built with the steam feature
+    #[serde(default)]
+    pub steam_app_id: Option<u32>,
+}
+const fn default_exit_timeout() -> u64 {
+    3000
+}
+const fn default_clipboard() -> bool {
+    true
+}
+const fn default_debug_overlay() -> bool {
+    cfg!(debug_assertions)
+}
+fn default_mods_path() -> PathBuf {
+    PathBuf::from("mods")
+}
+const fn default_update_hz() -> u32 {
+    60
 }
 impl BaseConfig {
+    pub fn assets_source(&self) -> AssetSource {
+        self.assets_source
+            .clone()
+            .unwrap_or_else(|| AssetSource::from_feature(&self.assets_path))
+    }
+    /// $HOME/Document/$name/window.toml, next to the saves
+    pub fn window_state_path(&self) -> anyhow::Result<PathBuf> {
+        let current_dir = dir::current_exe_path()?;
+        let doc_dir = directories::UserDirs::new()
+            .and_then(|d| d.document_dir().map(|p| p.to_path_buf()))
+            .unwrap_or(current_dir);
+        Ok(doc_dir.join(&self.name).join("window.toml"))
+    }
     pub fn build(&self) -> anyhow::Result<Self> {
         let usr_dir = directories::UserDirs::new();
         let current_dir = dir::current_exe_path()?;
@@ -21,12 +85,17 @@ impl BaseConfig {
                 .join(&self.name)
                 .join(self.capture_path.clone())
                 .to_path_buf();
-            let save_dir = d
-                .document_dir()
-                .unwrap_or(current_dir.as_path())
-                .join(&self.name)
-                .join(self.save_path.clone())
-                .to_path_buf();
+            let save_dir = match self.save_path.as_os_str().is_empty() {
+                true => SaveManager::default_for_app(&self.name)
+                    .path()
+                    .to_path_buf(),
+                false => d
+                    .document_dir()
+                    .unwrap_or(current_dir.as_path())
+                    .join(&self.name)
+                    .join(self.save_path.clone())
+                    .to_path_buf(),
+            };
             let assets_dir = current_dir
                 .to_path_buf()
                 .join(self.assets_path.to_path_buf());
@@ -36,8 +105,18 @@ impl BaseConfig {
                 name: self.name.clone(),
                 capture_path: pic_dir,
                 save_path: save_dir,
+                assets_source: Some(self.assets_source().relative_to(&current_dir)),
                 assets_path: assets_dir,
                 fps: self.fps,
+                update_hz: self.update_hz,
+                exit_timeout: self.exit_timeout,
+                integrity_check: self.integrity_check,
+                clipboard: self.clipboard,
+                mod_mounts: self.mod_mounts.clone(),
+                mods_path: current_dir.join(&self.mods_path),
+                debug_overlay: self.debug_overlay,
+                pause_on_focus_loss: self.pause_on_focus_loss,
+                steam_app_id: self.steam_app_id,
             })
         } else {
             Err(anyhow::anyhow!("failed to get base path for Fool Engine!"))
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// the splash shown while the assets are checked and the scripts loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BootConfig {
+    // false does the boot work before the first frame without drawing anything, for headless runs
+    pub splash: bool,
+    // asset key of the image in the middle of the splash
+    pub splash_image: Option<String>,
+    // any css color
+    pub background: String,
+    // the splash stays up at least this long, even when the boot is quicker
+    pub min_splash_ms: u64,
+}
+
+impl Default for BootConfig {
+    fn default() -> Self {
+        Self {
+            splash: true,
+            splash_image: None,
+            background: "black".to_owned(),
+            min_splash_ms: 0,
+        }
+    }
+}
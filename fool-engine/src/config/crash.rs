@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// what is written when the game panics and offered on the next launch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashConfig {
+    // false leaves panics to the default hook
+    pub enabled: bool,
+    // copy the newest save slot into the bundle when it is at most this many MiB, 0 never does
+    pub save_limit_mb: u64,
+    // run with the bundle folder appended when the player sends a report, empty hides the button
+    pub upload_command: Vec<String>,
+}
+
+impl Default for CrashConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            save_limit_mb: 16,
+            upload_command: Vec::new(),
+        }
+    }
+}
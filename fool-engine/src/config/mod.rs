@@ -1,14 +1,26 @@
 pub use crate::utils::dir::{current_exe_path, current_run_path, load_from_current};
 mod base;
+mod boot;
+mod crash;
+mod render;
 mod window;
 use serde::{Deserialize, Serialize};
 
 pub use base::BaseConfig;
+pub use boot::BootConfig;
+pub use crash::CrashConfig;
+pub use render::RenderConfig;
 pub use window::WindowConfig;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub base: BaseConfig,
     pub window: WindowConfig,
+    #[serde(default)]
+    pub boot: BootConfig,
+    #[serde(default)]
+    pub render: RenderConfig,
+    #[serde(default)]
+    pub crash: CrashConfig,
 }
 
 impl Config {
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderConfig {
+    // steps the anti-aliasing and render scale down when frames take longer than the fps allows
+    pub adaptive_quality: bool,
+    // the lowest quality it may step down to, "msaa16", "msaa8", "area", "area@0.85" or "area@0.75"
+    pub quality_floor: Option<String>,
+}
@@ -3,8 +3,9 @@ use crate::{
     apply_if_some,
     script::types::{LuaPoint, LuaSize},
 };
+use fool_window::{LoopMode, WindowState};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 use winit::{
     dpi::{LogicalPosition, LogicalSize, Position, Size},
     event_loop::EventLoop,
@@ -30,6 +31,27 @@ impl Into<WindowLevel> for WinLevel {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum WinControlFlow {
+    #[default]
+    Wait,
+    Poll,
+    WaitUntil,
+}
+
+impl WinControlFlow {
+    /// `WaitUntil` wakes up once per frame at `fps`, without a limit it polls
+    pub fn loop_mode(self, fps: Option<u32>) -> LoopMode {
+        match (self, fps) {
+            (Self::Wait, _) => LoopMode::Wait,
+            (Self::WaitUntil, Some(fps)) if fps > 0 => {
+                LoopMode::WaitUntil(Duration::from_secs_f64(1.0 / fps as f64))
+            }
+            _ => LoopMode::Poll,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WinButtons(pub Vec<String>);
 impl Default for WinButtons {
@@ -81,10 +103,22 @@ pub struct WindowConfig {
     pub active: Option<bool>,
     pub cursor: Option<String>,
     pub fullscreen: Option<bool>,
+    // restore the position and size the window had when the game closed
+    pub remember_window: Option<bool>,
+    // how the event loop waits between frames
+    pub control_flow: Option<WinControlFlow>,
 }
 
 impl WindowConfig {
-    pub fn build<T>(&self, event_loop: &EventLoop<T>) -> anyhow::Result<WindowAttributes> {
+    pub fn remember_window(&self) -> bool {
+        self.remember_window.unwrap_or(true)
+    }
+    /// `state` from the last session wins over the configured position and size
+    pub fn build<T>(
+        &self,
+        event_loop: &EventLoop<T>,
+        state: Option<&WindowState>,
+    ) -> anyhow::Result<WindowAttributes> {
         let mut attributes = WindowAttributes::default()
             .with_active(self.active.unwrap_or(true))
             .with_window_level(
@@ -143,6 +177,9 @@ impl WindowConfig {
             attributes =
                 attributes.with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)))
         }
+        if let Some(state) = state {
+            attributes = state.apply(attributes);
+        }
         Ok(attributes)
     }
 }
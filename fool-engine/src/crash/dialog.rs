@@ -0,0 +1,112 @@
+use super::{mark_seen, pending_bundles, REPORT_FILE};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// asks about the bundles left by earlier runs, one at a time, over the game
+#[derive(Debug)]
+pub struct CrashDialog {
+    // newest first, with the panic line of their report
+    bundles: Vec<(PathBuf, String)>,
+    // run with the bundle path appended, the game's own upload step
+    upload_command: Vec<String>,
+}
+
+impl CrashDialog {
+    /// None when there is nothing to ask about
+    pub fn pending(dir: &Path, upload_command: Vec<String>) -> Option<Self> {
+        let bundles = pending_bundles(dir)
+            .into_iter()
+            .map(|bundle| {
+                let summary = std::fs::read_to_string(bundle.join(REPORT_FILE))
+                    .ok()
+                    .and_then(|report| {
+                        report
+                            .lines()
+                            .find_map(|line| line.strip_prefix("panic: "))
+                            .map(str::to_owned)
+                    })
+                    .unwrap_or_default();
+                (bundle, summary)
+            })
+            .collect::<Vec<_>>();
+        if bundles.is_empty() {
+            return None;
+        }
+        Some(Self {
+            bundles,
+            upload_command,
+        })
+    }
+    /// draws into the running egui pass, returns whether bundles are left to ask about
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let Some((bundle, summary)) = self.bundles.first() else {
+            return false;
+        };
+        let mut answered = false;
+        egui::Window::new("The game closed unexpectedly")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("A crash report was saved, it helps the developers fix the problem.");
+                if !summary.is_empty() {
+                    ui.monospace(summary);
+                }
+                ui.label(bundle.display().to_string());
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Open folder").clicked() {
+                        if let Err(err) = open_folder(bundle) {
+                            log::warn!("open {} failed: {}", bundle.display(), err);
+                        }
+                    }
+                    if !self.upload_command.is_empty() && ui.button("Send report").clicked() {
+                        if let Err(err) = upload(&self.upload_command, bundle) {
+                            log::warn!("send crash report failed: {}", err);
+                        }
+                        answered = true;
+                    }
+                    if ui.button("Delete").clicked() {
+                        if let Err(err) = std::fs::remove_dir_all(bundle) {
+                            log::warn!("delete {} failed: {}", bundle.display(), err);
+                        }
+                        answered = true;
+                    }
+                    if ui.button("Close").clicked() {
+                        answered = true;
+                    }
+                });
+            });
+        if answered {
+            let (bundle, _) = self.bundles.remove(0);
+            // a deleted one is gone already
+            if bundle.exists() {
+                if let Err(err) = mark_seen(&bundle) {
+                    log::warn!("mark {} seen failed: {}", bundle.display(), err);
+                }
+            }
+        }
+        !self.bundles.is_empty()
+    }
+}
+
+fn open_folder(path: &Path) -> std::io::Result<()> {
+    let opener = match std::env::consts::OS {
+        "windows" => "explorer",
+        "macos" => "open",
+        _ => "xdg-open",
+    };
+    Command::new(opener).arg(path).spawn().map(|_| ())
+}
+
+// not waited for, the game goes on while it uploads
+fn upload(command: &[String], bundle: &Path) -> std::io::Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(());
+    };
+    Command::new(program)
+        .args(args)
+        .arg(bundle)
+        .spawn()
+        .map(|_| ())
+}
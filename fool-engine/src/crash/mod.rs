@@ -0,0 +1,318 @@
+use crate::config::Config;
+use crate::save::SaveManager;
+use fool_script::ScriptError;
+use parking_lot::{const_mutex, Mutex};
+use std::fmt::{Debug, Write as _};
+use std::fs::File;
+use std::io::{self, Write};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+mod dialog;
+pub use dialog::CrashDialog;
+
+const STAGING: &str = ".staging";
+const BUNDLE_PREFIX: &str = "crash-";
+pub const REPORT_FILE: &str = "report.txt";
+pub const LOG_FILE: &str = "log.txt";
+// left in a bundle once the player answered the dialog for it
+const SEEN_FILE: &str = "seen";
+
+// what the engine knew before the panic, the hook only tries the lock
+struct CrashContext {
+    gpu: Option<String>,
+    script_error: Option<String>,
+    capture: Option<PathBuf>,
+    save_dir: Option<PathBuf>,
+}
+
+static CONTEXT: Mutex<CrashContext> = const_mutex(CrashContext {
+    gpu: None,
+    script_error: None,
+    capture: None,
+    save_dir: None,
+});
+// one bundle per run, a panic while unwinding the first keeps it
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_gpu_info(info: &impl Debug) {
+    CONTEXT.lock().gpu = Some(format!("{:#?}", info));
+}
+/// the last script error wins, with its causes and tracebacks
+pub fn record_script_error(err: &ScriptError) {
+    CONTEXT.lock().script_error = Some(format!("{:#}", err));
+}
+/// copied into the bundle when it was written by then
+pub fn record_capture(path: &Path) {
+    CONTEXT.lock().capture = Some(path.to_path_buf());
+}
+/// the newest slot in it goes into the bundle
+pub fn set_save_dir(path: &Path) {
+    CONTEXT.lock().save_dir = Some(path.to_path_buf());
+}
+
+/// `crashes` next to the per-user saves of the game
+pub fn crash_dir(app_name: &str) -> PathBuf {
+    SaveManager::default_for_app(app_name)
+        .path()
+        .join("crashes")
+}
+
+/// replaces the panic hook with one writing a bundle before the default hook runs,
+/// to be called before the window opens. Only panics of the main thread are reported,
+/// the workers recover from theirs
+pub fn install(config: &Config) -> anyhow::Result<()> {
+    let mut staging = Staging::create(
+        &crash_dir(&config.base.name),
+        about(config),
+        config.crash.save_limit_mb * 1024 * 1024,
+    )?;
+    staging.config = toml::to_string(config).unwrap_or_else(|err| err.to_string());
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let main = std::thread::current().name() == Some("main");
+        if main && !PANICKED.swap(true, Ordering::SeqCst) {
+            // no log macros, the panic may have happened inside the logger
+            match staging.write(&PanicReport::new(info)) {
+                Ok(bundle) => eprintln!("crash report written to {}", bundle.display()),
+                Err(err) => eprintln!("write crash report failed: {}", err),
+            }
+        }
+        previous(info);
+    }));
+    Ok(())
+}
+
+/// bundles the player was not asked about yet, newest first
+pub fn pending_bundles(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut bundles = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(BUNDLE_PREFIX))
+                && path.join(REPORT_FILE).is_file()
+                && !path.join(SEEN_FILE).exists()
+        })
+        .collect::<Vec<_>>();
+    // named by the time they were written
+    bundles.sort();
+    bundles.reverse();
+    bundles
+}
+pub fn mark_seen(bundle: &Path) -> io::Result<()> {
+    File::create(bundle.join(SEEN_FILE)).map(|_| ())
+}
+
+struct PanicReport {
+    message: String,
+    location: String,
+    thread: String,
+    backtrace: String,
+}
+
+impl PanicReport {
+    fn new(info: &PanicHookInfo) -> Self {
+        let payload = info.payload();
+        let message = match (
+            payload.downcast_ref::<&str>(),
+            payload.downcast_ref::<String>(),
+        ) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => "unknown panic payload".to_owned(),
+        };
+        Self {
+            message,
+            location: info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_default(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("unnamed")
+                .to_owned(),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        }
+    }
+}
+
+/// the next bundle, created with its files open at install so a panic only
+/// writes them and renames the folder
+struct Staging {
+    dir: PathBuf,
+    path: PathBuf,
+    report: File,
+    log: File,
+    // engine and system details, they don't change while it runs
+    about: String,
+    // as it was parsed
+    config: String,
+    save_limit: u64,
+}
+
+impl Staging {
+    fn create(dir: &Path, about: String, save_limit: u64) -> io::Result<Self> {
+        let path = dir.join(STAGING);
+        // left by the last run that didn't crash
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            report: File::create(path.join(REPORT_FILE))?,
+            log: File::create(path.join(LOG_FILE))?,
+            path,
+            about,
+            config: String::new(),
+            save_limit,
+        })
+    }
+    fn write(&self, panic: &PanicReport) -> io::Result<PathBuf> {
+        let now = chrono::Utc::now();
+        let mut report = String::new();
+        let _ = writeln!(report, "{}", self.about);
+        let _ = writeln!(report, "time: {}", now.to_rfc3339());
+        let _ = writeln!(report, "thread: {}", panic.thread);
+        let _ = writeln!(report, "panic: {}", panic.message);
+        let _ = writeln!(report, "location: {}", panic.location);
+        // whatever was set before, a lock held by the panicking code is skipped
+        let (gpu, script_error, capture, save_dir) = match CONTEXT.try_lock() {
+            Some(context) => (
+                context.gpu.clone(),
+                context.script_error.clone(),
+                context.capture.clone(),
+                context.save_dir.clone(),
+            ),
+            None => Default::default(),
+        };
+        let sections = [
+            ("gpu", gpu),
+            ("last script error", script_error),
+            ("backtrace", Some(panic.backtrace.clone())),
+            ("config", Some(self.config.clone())),
+        ];
+        for (title, text) in sections {
+            let _ = writeln!(report, "\n== {} ==", title);
+            let _ = writeln!(report, "{}", text.as_deref().unwrap_or("none"));
+        }
+        (&self.report).write_all(report.as_bytes())?;
+        self.report.sync_all()?;
+        let mut log = rolllog::recent_lines().lines().join("\n");
+        log.push('\n');
+        (&self.log).write_all(log.as_bytes())?;
+        self.log.sync_all()?;
+
+        // cheap extras, a failure leaves them out
+        if let Some(save) = save_dir.as_deref().and_then(newest_save) {
+            let small = std::fs::metadata(&save).is_ok_and(|meta| meta.len() <= self.save_limit);
+            if let (true, Some(name)) = (small, save.file_name()) {
+                let _ = std::fs::copy(&save, self.path.join(name));
+            }
+        }
+        if let Some(capture) = &capture {
+            if let Some(name) = capture.file_name() {
+                let _ = std::fs::copy(capture, self.path.join(name));
+            }
+        }
+
+        let bundle = self.dir.join(format!(
+            "{}{}",
+            BUNDLE_PREFIX,
+            now.format("%Y%m%d-%H%M%S-%3f")
+        ));
+        std::fs::rename(&self.path, &bundle)?;
+        Ok(bundle)
+    }
+}
+
+fn newest_save(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "save"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+// gathered once at install
+fn about(config: &Config) -> String {
+    let mut about = format!(
+        "{} on fool-engine {} ({})\n",
+        config.base.name,
+        env!("CARGO_PKG_VERSION"),
+        match cfg!(debug_assertions) {
+            true => "debug",
+            false => "release",
+        }
+    );
+    let _ = writeln!(
+        about,
+        "os: {} {}, arch: {}, cpus: {}",
+        std::env::consts::FAMILY,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::thread::available_parallelism().map_or(0, |cpus| cpus.get())
+    );
+    if let Some(cpu) = cpu_model() {
+        let _ = write!(about, "cpu: {}", cpu);
+    }
+    about
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_owned())
+}
+
+#[test]
+fn test_crash_bundle() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("fool_engine_test_crash_bundle");
+    let _ = std::fs::remove_dir_all(&dir);
+    let saves = dir.join("saves");
+    std::fs::create_dir_all(&saves)?;
+    std::fs::write(saves.join("slot1.save"), "progress")?;
+    set_save_dir(&saves);
+    record_script_error(&ScriptError::from_message(
+        "main.lua:3: boom\nstack traceback:\n\tmain.lua:3: in function 'update'",
+    ));
+    let staging = Staging::create(&dir, "about the game".to_owned(), 1024)?;
+    let bundle = staging.write(&PanicReport {
+        message: "index out of bounds".to_owned(),
+        location: "src/engine/mod.rs:1:1".to_owned(),
+        thread: "main".to_owned(),
+        backtrace: "0: fool_engine::main".to_owned(),
+    })?;
+    assert!(!dir.join(STAGING).exists());
+    let report = std::fs::read_to_string(bundle.join(REPORT_FILE))?;
+    for part in [
+        "about the game",
+        "panic: index out of bounds",
+        "main.lua:3: boom",
+        "in function 'update'",
+        "0: fool_engine::main",
+    ] {
+        assert!(report.contains(part), "{} not in {}", part, report);
+    }
+    assert!(bundle.join(LOG_FILE).is_file());
+    assert_eq!(std::fs::read(bundle.join("slot1.save"))?, b"progress");
+
+    assert_eq!(pending_bundles(&dir), std::slice::from_ref(&bundle));
+    mark_seen(&bundle)?;
+    assert!(pending_bundles(&dir).is_empty());
+    // a new staging folder is not a bundle
+    Staging::create(&dir, String::new(), 0)?;
+    assert!(pending_bundles(&dir).is_empty());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,256 @@
+use crate::config::BootConfig;
+use crate::resource::{AssetReport, IntegrityCheck, ResourceManager};
+use crate::script::{required_assets, run_preload_fn};
+use egui::{Color32, Context, Frame, Image, ProgressBar};
+use fool_script::FoolScript;
+use mlua::{UserData, UserDataMethods};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use vello::peniko::color::{parse_color, Srgb};
+
+// checking the assets, compiling, loading main.lua and running preload
+const BOOT_STEPS: usize = 4;
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    stage: String,
+    done: usize,
+    // the boot steps and the prefetches
+    total: usize,
+    prefetch: Vec<String>,
+}
+
+/// how far the boot is, `preload(boot)` in main.lua queues prefetches on it
+#[derive(Debug, Clone)]
+pub struct BootProgress {
+    state: Arc<Mutex<ProgressState>>,
+}
+
+impl Default for BootProgress {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ProgressState {
+                total: BOOT_STEPS,
+                ..Default::default()
+            })),
+        }
+    }
+}
+
+impl BootProgress {
+    /// finishes the running stage
+    pub fn begin(&self, stage: impl Into<String>) {
+        let mut state = self.state.lock();
+        if !state.stage.is_empty() {
+            state.done += 1;
+        }
+        state.stage = stage.into();
+    }
+    /// 0..=1 and what runs now
+    pub fn get(&self) -> (f32, String) {
+        let state = self.state.lock();
+        let fraction = state.done as f32 / state.total.max(1) as f32;
+        (fraction.min(1.0), state.stage.clone())
+    }
+    pub fn prefetch(&self, keys: impl IntoIterator<Item = String>) {
+        let mut state = self.state.lock();
+        for key in keys {
+            if !state.prefetch.contains(&key) {
+                state.prefetch.push(key);
+                state.total += 1;
+            }
+        }
+    }
+    fn take_prefetch(&self) -> Vec<String> {
+        std::mem::take(&mut self.state.lock().prefetch)
+    }
+}
+
+impl UserData for BootProgress {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "prefetch",
+            |_, this, keys: mlua::Either<String, Vec<String>>| {
+                match keys {
+                    mlua::Either::Left(key) => this.prefetch([key]),
+                    mlua::Either::Right(keys) => this.prefetch(keys),
+                }
+                Ok(())
+            },
+        );
+        methods.add_method("stage", |_, this, stage: String| {
+            this.state.lock().stage = stage;
+            Ok(())
+        });
+    }
+}
+
+/// the work done behind the splash, what goes wrong ends up on the error screen
+pub fn run(
+    resource: &ResourceManager,
+    script: &FoolScript,
+    check: IntegrityCheck,
+    progress: &BootProgress,
+) -> AssetReport {
+    progress.begin("checking assets");
+    let mut report = resource.check_integrity(check);
+    report.merge(resource.verify_manifest());
+    progress.begin("compiling scripts");
+    for (module, err) in script.modules.mem_mod.precompile(script) {
+        log::warn!("compile {} failed: {}", module, err);
+    }
+    progress.begin("loading main.lua");
+    let loaded = script
+        .load_main()
+        .map_err(anyhow::Error::from)
+        .and_then(|_| required_assets(script))
+        .map(|required| report.merge(resource.check_required(&required)));
+    if let Err(err) = loaded {
+        report.add_error("main.lua", err);
+    }
+    if report.is_ok() {
+        progress.begin("preloading");
+        if let Err(err) = run_preload_fn(script, progress) {
+            report.add_error("main.lua", err);
+        }
+        for key in progress.take_prefetch() {
+            progress.begin(format!("loading {}", key));
+            if let Err(err) = resource.raw_resource.get(&key) {
+                log::error!("prefetch {} failed: {}", key, err);
+                report.add_missing(key);
+            }
+        }
+    }
+    progress.begin("starting");
+    report.log();
+    report
+}
+
+/// the splash drawn while `run` works on a thread of its own
+pub struct Boot {
+    progress: BootProgress,
+    worker: Option<JoinHandle<AssetReport>>,
+    report: Option<AssetReport>,
+    started: Instant,
+    min_splash: Duration,
+    image: Option<String>,
+    background: Color32,
+    image_failed: bool,
+}
+
+impl Boot {
+    pub fn start(
+        config: &BootConfig,
+        resource: ResourceManager,
+        script: FoolScript,
+        check: IntegrityCheck,
+    ) -> Self {
+        let background = match parse_color(&config.background) {
+            Ok(color) => {
+                let [r, g, b, a] = color.to_alpha_color::<Srgb>().to_rgba8().to_u8_array();
+                Color32::from_rgba_unmultiplied(r, g, b, a)
+            }
+            Err(err) => {
+                log::warn!("splash background {}: {}", config.background, err);
+                Color32::BLACK
+            }
+        };
+        let progress = BootProgress::default();
+        let worker = {
+            let progress = progress.clone();
+            std::thread::spawn(move || run(&resource, &script, check, &progress))
+        };
+        Self {
+            progress,
+            worker: Some(worker),
+            report: None,
+            started: Instant::now(),
+            min_splash: Duration::from_millis(config.min_splash_ms),
+            image: config.splash_image.clone(),
+            background,
+            image_failed: false,
+        }
+    }
+    /// the report once the work is done and the splash was up long enough
+    pub fn poll(&mut self) -> Option<AssetReport> {
+        if let Some(worker) = self.worker.take_if(|worker| worker.is_finished()) {
+            let report = worker.join().unwrap_or_else(|_| {
+                let mut report = AssetReport::default();
+                report.add_corrupt("main.lua", "the boot thread panicked");
+                report
+            });
+            self.report = Some(report);
+        }
+        if self.started.elapsed() < self.min_splash {
+            return None;
+        }
+        self.report.take()
+    }
+    pub fn show(&mut self, ctx: &Context, resource: &ResourceManager) {
+        let (fraction, stage) = self.progress.get();
+        let frame = Frame::NONE.fill(self.background).inner_margin(16.0);
+        egui::TopBottomPanel::bottom("fool_boot_progress")
+            .frame(frame)
+            .show_separator_line(false)
+            .show(ctx, |ui| {
+                ui.add(ProgressBar::new(fraction).text(stage));
+            });
+        egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
+            let Some(image) = self.image.as_ref().filter(|_| !self.image_failed) else {
+                return;
+            };
+            match resource.get_ui_texture(image) {
+                Ok(texture) => {
+                    ui.centered_and_justified(|ui| {
+                        ui.add(Image::from_texture(&texture).shrink_to_fit());
+                    });
+                }
+                // drawn without it from here on
+                Err(err) => {
+                    log::error!("load splash image {} failed: {}", image, err);
+                    self.image_failed = true;
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_boot_progress() -> anyhow::Result<()> {
+    use crate::resource::AssetSource;
+    let dir = std::env::temp_dir().join("test_boot_progress");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("image"))?;
+    std::fs::write(
+        dir.join("main.lua"),
+        r#"
+        function preload(boot)
+            boot:prefetch({"image/a.png", "image/b.png"})
+            boot:prefetch("image/a.png")
+        end
+        "#,
+    )?;
+    std::fs::write(dir.join("image/a.png"), b"a")?;
+    let resource = ResourceManager::new(AssetSource::Folder(dir.clone()))?;
+    let mut script = FoolScript::new(resource.raw_resource.clone())?;
+    script.setup()?;
+    let progress = BootProgress::default();
+    let report = run(&resource, &script, IntegrityCheck::None, &progress);
+    // the missing prefetch is on the error screen instead of failing later
+    assert_eq!(report.missing, ["image/b.png"]);
+    assert_eq!(progress.get(), (1.0, "starting".to_owned()));
+    assert!(resource.raw_resource.contains("image/a.png"));
+
+    std::fs::write(dir.join("main.lua"), "return {")?;
+    let resource = ResourceManager::new(AssetSource::Folder(dir.clone()))?;
+    let mut script = FoolScript::new(resource.raw_resource.clone())?;
+    script.setup()?;
+    let report = run(&resource, &script, IntegrityCheck::None, &progress);
+    // a syntax error is a script error, with where it is, not a corrupt file
+    assert!(report.corrupt.is_empty());
+    assert_eq!(report.scripts.len(), 1);
+    assert_eq!(report.scripts[0].line, Some(1));
+    Ok(())
+}
@@ -1,18 +1,29 @@
 use crate::engine::EngineStatus;
 
-use super::Engine;
+use super::{frame_budget, refresh_rate, Engine};
+use fool_graphics::render::aa_name;
+use fool_graphics::AaConfig;
 use fool_window::{Application, CustomEvent, EventProxy, WinEvent};
-use std::{path::PathBuf, sync::Arc};
-use winit::{event::WindowEvent, window::Window};
+use std::{
+    path::PathBuf,
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+use vello::peniko::Color;
+use winit::{event::WindowEvent, event_loop::ControlFlow, window::Window};
 impl Engine {
     fn window_event(&mut self, event: &WinEvent, raw_event: &WindowEvent) {
         if !self.scheduler.running {
             return;
         }
         if event.close_requested() {
-            *self.status.write() = EngineStatus::Exiting
+            self.status.set(EngineStatus::Exiting)
         }
-        if let Some(size) = event.window_resized() {
+        // a minimized window reports a zero size, keep the last surface
+        if let Some(size) = event
+            .window_resized()
+            .filter(|size| size.width > 0 && size.height > 0)
+        {
             if let (Some(render), Some(window), Some(lua_engine)) =
                 (&mut self.render, &self.window, &mut self.lua_engine)
             {
@@ -25,6 +36,18 @@ impl Engine {
                 window.request_redraw();
             }
         }
+        if let Some(lua_engine) = &self.lua_engine {
+            match raw_event {
+                WindowEvent::CursorMoved { position, .. } => {
+                    *lua_engine.cursor.write() = Some((position.x, position.y))
+                }
+                WindowEvent::CursorLeft { .. } => *lua_engine.cursor.write() = None,
+                WindowEvent::Focused(focused) => lua_engine
+                    .focus_pause
+                    .focus_changed(*focused, &lua_engine.audio.0),
+                _ => {}
+            }
+        }
         self.event(event, raw_event);
     }
 }
@@ -42,30 +65,71 @@ impl Application for Engine {
         }
         self.window_event(event, raw_event);
     }
-    fn update(&mut self) {
+    fn update(&mut self) -> ControlFlow {
+        // paused, sleep until an event resumes it
         if !self.scheduler.running {
-            return;
+            return ControlFlow::Wait;
         }
-        if let (Some(proxy), Some(window)) = (&self.event_proxy, &self.window) {
-            if self.scheduler.trigger_redraw(proxy) {
+        let (redraw, flow) = self.scheduler.next_frame(Instant::now());
+        if redraw {
+            self.frame_id.advance();
+            if let Some(window) = &self.window {
                 window.request_redraw();
             }
         }
+        flow
     }
     fn exiting(&mut self) {
         self.exiting();
     }
+    fn visibility_changed(&mut self, visible: bool) {
+        if let Some(lua_engine) = &self.lua_engine {
+            lua_engine.window.visible.store(visible, Ordering::Relaxed);
+        }
+        if visible {
+            // skip the updates missed while hidden
+            self.scheduler.reset();
+        }
+    }
     fn user_event(&mut self, event: Box<dyn CustomEvent>) {
         if let Ok(event) = event.downcast::<EngineEvent>() {
             match *event {
                 EngineEvent::Capture(p) => {
                     let full_path = self.base_config.capture_path.clone().join(p);
                     log::trace!("Capture current screen to {}", full_path.display());
+                    crate::crash::record_capture(&full_path);
                     self.frame_capture.push_back(full_path);
                 }
                 EngineEvent::FPS(fps) => {
-                    log::trace!("set current fps to {}", fps);
-                    self.scheduler.set_fps(fps)
+                    log::trace!("set current fps to {:?}", fps);
+                    self.scheduler.set_fps(fps);
+                    if let (Some(render), Some(window)) = (&self.render, &self.window) {
+                        let budget = frame_budget(fps, refresh_rate(window));
+                        render.quality().lock().set_budget(budget);
+                    }
+                }
+                EngineEvent::Antialiasing(aa) => {
+                    if let Some(render) = &mut self.render {
+                        let used = render.set_aa(aa);
+                        log::trace!("set antialiasing to {}", aa_name(used));
+                    }
+                }
+                EngineEvent::PixelPerfect(enabled) => {
+                    if let Some(render) = &mut self.render {
+                        render.set_pixel_perfect(enabled);
+                        self.scene_graph.write().set_pixel_perfect(enabled);
+                        log::trace!("set pixel perfect to {}", enabled);
+                    }
+                }
+                EngineEvent::ClearColor(color) => {
+                    if let Some(render) = &mut self.render {
+                        render.set_clear_color(color);
+                    }
+                }
+                EngineEvent::ReloadScripts => {
+                    if let Err(err) = self.reload_scripts() {
+                        log::error!("reload scripts failed: {}", err);
+                    }
                 }
             }
         }
@@ -75,5 +139,9 @@ impl Application for Engine {
 #[derive(Debug, Clone)]
 pub enum EngineEvent {
     Capture(PathBuf),
-    FPS(u32),
+    FPS(Option<u32>),
+    Antialiasing(AaConfig),
+    PixelPerfect(bool),
+    ClearColor(Color),
+    ReloadScripts,
 }
@@ -0,0 +1,90 @@
+use fool_audio::{AudioSystem, Backend};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// ms the audio fades out and back in over
+const FOCUS_FADE: u64 = 150;
+
+/// pauses the audio and the fixed updates while the window is in the background,
+/// off unless `BaseConfig::pause_on_focus_loss` or a script turns it on
+#[derive(Debug, Clone, Default)]
+pub struct FocusPause {
+    enabled: Arc<AtomicBool>,
+    // Some while the window is in the background, true when the audio was paused here
+    held: Arc<Mutex<Option<bool>>>,
+}
+
+impl FocusPause {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+    /// turned off in the background, what it paused resumes right away
+    pub fn set_enabled<B: Backend>(&self, enabled: bool, audio: &AudioSystem<B>) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.release(audio);
+        }
+    }
+    /// the fixed updates don't run while true
+    pub fn is_holding(&self) -> bool {
+        self.held.lock().is_some()
+    }
+    /// audio paused before the window lost focus, e.g. by a pause menu, stays paused
+    /// when it comes back
+    pub fn focus_changed<B: Backend>(&self, focused: bool, audio: &AudioSystem<B>) {
+        if focused {
+            self.release(audio);
+            return;
+        }
+        let mut held = self.held.lock();
+        if !self.is_enabled() || held.is_some() {
+            return;
+        }
+        let pause_audio = !audio.is_paused_all();
+        if pause_audio {
+            audio.pause_all(FOCUS_FADE);
+        }
+        log::debug!("window lost focus, pause audio: {}", pause_audio);
+        *held = Some(pause_audio);
+    }
+    fn release<B: Backend>(&self, audio: &AudioSystem<B>) {
+        let Some(paused_audio) = self.held.lock().take() else {
+            return;
+        };
+        log::debug!("window focused, resume audio: {}", paused_audio);
+        if paused_audio {
+            audio.resume_all(FOCUS_FADE);
+        }
+    }
+}
+
+#[test]
+fn test_focus_pause() -> anyhow::Result<()> {
+    let audio = AudioSystem::new_mock(fool_resource::Resource::empty())?;
+    let focus = FocusPause::default();
+    // off by default
+    focus.focus_changed(false, &audio);
+    assert!(!audio.is_paused_all() && !focus.is_holding());
+    focus.focus_changed(true, &audio);
+
+    focus.set_enabled(true, &audio);
+    focus.focus_changed(false, &audio);
+    assert!(audio.is_paused_all() && focus.is_holding());
+    focus.focus_changed(true, &audio);
+    assert!(!audio.is_paused_all() && !focus.is_holding());
+
+    // paused by the game before, still paused after
+    audio.pause_all(0);
+    focus.focus_changed(false, &audio);
+    assert!(focus.is_holding());
+    focus.focus_changed(true, &audio);
+    assert!(audio.is_paused_all() && !focus.is_holding());
+    audio.resume_all(0);
+
+    // turned off in the background lets go at once
+    focus.focus_changed(false, &audio);
+    focus.set_enabled(false, &audio);
+    assert!(!audio.is_paused_all() && !focus.is_holding());
+    Ok(())
+}
@@ -1,11 +1,16 @@
-use crate::config::BaseConfig;
+use crate::achievements::DEFINITIONS_FILE;
+use crate::config::{BaseConfig, BootConfig, RenderConfig};
+use crate::crash::{self, CrashDialog};
 use crate::map2anyhow_error;
+use crate::mods::ModManager;
+use crate::resource::AssetReport;
 pub use crate::resource::ResourceManager;
-use crate::scheduler::FrameScheduler;
-use crate::script::LuaEngine;
+use crate::save::SaveManager;
+use crate::scheduler::FrameID;
 use crate::script::{run_init_fn, setup_modules};
+use crate::script::{ExitHook, LuaEngine, LuaEngineServices};
 use fool_graphics::canvas::SceneGraph;
-use fool_graphics::GraphRender;
+use fool_graphics::{parse_quality, AdaptiveQuality, GraphRender, Scheduler};
 use fool_script::{thread::AsyncScheduler, FoolScript};
 use fool_window::EventProxy;
 use fool_window::WinEvent;
@@ -13,94 +18,260 @@ use parking_lot::RwLock;
 use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use winit::window::Window;
+mod boot;
 pub mod event;
+mod focus;
+mod overlay;
 pub mod script;
 mod status;
-pub use status::EngineStatus;
+pub use boot::{Boot, BootProgress};
+pub use focus::FocusPause;
+pub use overlay::{DebugOverlay, DebugStats};
+pub use status::{EngineStatus, SharedStatus};
 pub struct Engine {
     resource: ResourceManager,
     script: FoolScript,
+    mods: ModManager,
     window: Option<Arc<Window>>,
     render: Option<GraphRender>,
     event_proxy: Option<EventProxy>,
-    scheduler: FrameScheduler,
+    scheduler: Scheduler,
+    frame_id: FrameID,
     script_scheduler: AsyncScheduler,
     lua_engine: Option<LuaEngine>,
     scene_graph: Arc<RwLock<SceneGraph>>,
     events_current_frame: Vec<WinEvent>,
+    // not seen by an update step yet, frames may run several steps or none
+    events_since_update: Vec<WinEvent>,
     frame_capture: VecDeque<PathBuf>,
-    status: Arc<RwLock<EngineStatus>>,
+    status: SharedStatus,
+    exit_hook: ExitHook,
+    // not ok shows an error screen instead of running the game
+    asset_report: AssetReport,
     base_config: BaseConfig,
+    boot_config: BootConfig,
+    render_config: RenderConfig,
+    // Some while the splash is up, the game starts once it is done
+    boot: Option<Boot>,
+    // init ran, only a game that started is owed its on_exit
+    started: bool,
+    // Some while earlier crash reports are asked about
+    crash_dialog: Option<CrashDialog>,
+    crash_test: bool,
 }
 
 impl Engine {
-    pub fn new(base_config: BaseConfig) -> anyhow::Result<Self> {
+    /// the assets are checked and main.lua loaded once the window is up, see `init`
+    pub fn new(
+        base_config: BaseConfig,
+        boot_config: BootConfig,
+        render_config: RenderConfig,
+    ) -> anyhow::Result<Self> {
         let base_config = base_config.build()?;
         log::debug!("engine base config: {:?}", base_config);
-        let resource = ResourceManager::new(base_config.assets_path.clone())?;
-        let mut script = FoolScript::new(resource.raw_resource.clone())?;
-        script.setup()?;
-        setup_modules(&script)?;
+        crash::set_save_dir(&base_config.save_path);
+        let resource = ResourceManager::new(base_config.assets_source())?;
+        let mods = ModManager::new(
+            &base_config.mods_path,
+            SaveManager::new(&base_config.save_path),
+        );
+        resource.raw_resource.prepend_fallback(mods.fallback());
+        mods.apply();
+        let script = create_script(&resource, &base_config, &mods)?;
         let scene_graph = Arc::new(RwLock::new(SceneGraph {
             font_mgr: resource.graphics_font.clone(),
             img_mgr: resource.graphics_img.clone(),
+            svg_mgr: resource.graphics_svg.clone(),
             ..Default::default()
         }));
-        map2anyhow_error!(script.load_main(), "load main.lua failed: ")?;
         Ok(Engine {
             resource,
             script: script.clone(),
+            mods,
             window: None,
             event_proxy: None,
             render: None,
-            scheduler: FrameScheduler::new(base_config.fps),
+            scheduler: Scheduler::new(base_config.update_hz, base_config.fps),
+            frame_id: FrameID::new(),
             script_scheduler: AsyncScheduler::new(script.modules.clone()),
             lua_engine: None,
             events_current_frame: Vec::new(),
+            events_since_update: Vec::new(),
             frame_capture: Default::default(),
-            status: Arc::new(RwLock::new(EngineStatus::Init)),
+            status: SharedStatus::new(EngineStatus::Init),
+            exit_hook: ExitHook::new(Duration::from_millis(base_config.exit_timeout)),
+            asset_report: Default::default(),
             base_config,
+            boot_config,
+            render_config,
+            boot: None,
+            started: false,
+            crash_dialog: None,
+            crash_test: false,
             scene_graph,
         })
     }
+    /// shown over the game until every report in it was answered
+    pub fn set_crash_dialog(&mut self, dialog: Option<CrashDialog>) {
+        self.crash_dialog = dialog;
+    }
+    /// panics on the first frame of the game
+    pub fn set_crash_test(&mut self, crash_test: bool) {
+        self.crash_test = crash_test;
+    }
 
     pub fn init(&mut self, window: Arc<Window>, proxy: &EventProxy) -> anyhow::Result<()> {
         self.window.replace(window.clone());
-        let render = GraphRender::new(window.clone())?;
+        let mut render = GraphRender::new(window.clone())?;
         self.resource
             .setup_egui_texture_fallback(render.gui_context());
         egui_extras::install_image_loaders(render.gui_context());
+        let egui_texture = self.resource.egui_texture.clone();
+        let gui_context = render.gui_context().clone();
+        render.on_device_recreated(move || {
+            // cached handles point at textures of the lost device, load them again on next use
+            egui_texture.clear();
+            gui_context.forget_all_images();
+            gui_context.request_repaint();
+        });
         let size = window.inner_size();
+        let gpu_info = render.gpu_info();
+        log::info!("gpu: {:?}", gpu_info);
+        crash::set_gpu_info(&gpu_info);
+        let quality = render.quality();
+        self.setup_quality(&mut quality.lock(), &window);
+        let services = LuaEngineServices {
+            resource: self.resource.clone(),
+            scene_graph: self.scene_graph.clone(),
+            status: self.status.clone(),
+            save_path: self.base_config.save_path.clone(),
+            gpu_info,
+            effects: render.effects(),
+            quality,
+            clipboard: self.base_config.clipboard,
+            mods: self.mods.clone(),
+        };
         let lua_engine = LuaEngine::new(
             window,
             render.gui_context().clone(),
             proxy.clone(),
-            self.resource.clone(),
-            self.scene_graph.clone(),
-            self.status.clone(),
-            &self.base_config.save_path,
+            services,
         )?;
         self.event_proxy.replace(proxy.clone());
-        run_init_fn(&self.script, &lua_engine)?;
+        // the player's settings are in place before the game sees anything
+        lua_engine.accessibility.load();
+        lua_engine
+            .focus_pause
+            .set_enabled(self.base_config.pause_on_focus_loss, &lua_engine.audio.0);
+        let achievements = self
+            .resource
+            .raw_resource
+            .try_get(DEFINITIONS_FILE)
+            .map(|defs| defs.to_string())
+            .transpose()?;
+        lua_engine
+            .achievements
+            .load_definitions(achievements.as_deref())?;
+        #[cfg(feature = "steam")]
+        if let Some(app_id) = self.base_config.steam_app_id {
+            lua_engine.achievements.connect_steam(app_id);
+        }
+        render.set_ui_scale(lua_engine.accessibility.ui_scale());
+        lua_engine.ui_ctx.workspace.load();
         self.lua_engine.replace(lua_engine);
         self.render.replace(render);
-        self.script_scheduler.init()?;
         self.scene_graph
             .write()
             .center_with_screen_size(size.width as f64, size.height as f64);
-        *self.status.write() = EngineStatus::Running;
+        let check = self.base_config.integrity_check;
+        if self.boot_config.splash {
+            self.boot = Some(Boot::start(
+                &self.boot_config,
+                self.resource.clone(),
+                self.script.clone(),
+                check,
+            ));
+            return Ok(());
+        }
+        let report = boot::run(&self.resource, &self.script, check, &Default::default());
+        self.finish_boot(report)
+    }
+    fn setup_quality(&self, quality: &mut AdaptiveQuality, window: &Window) {
+        quality.set_budget(frame_budget(self.base_config.fps, refresh_rate(window)));
+        if let Some(floor) = &self.render_config.quality_floor {
+            match parse_quality(floor) {
+                Some(floor) => {
+                    quality.set_floor(floor);
+                }
+                None => log::warn!("unknown render quality_floor {}, ignored", floor),
+            }
+        }
+        quality.set_enabled(self.render_config.adaptive_quality);
+    }
+    /// hands over to the game, or to the error screen with a bad `report`
+    fn finish_boot(&mut self, report: AssetReport) -> anyhow::Result<()> {
+        self.asset_report = report;
+        if let (true, Some(lua_engine)) = (self.asset_report.is_ok(), &self.lua_engine) {
+            run_init_fn(&self.script, lua_engine)?;
+        }
+        self.started = self.asset_report.is_ok();
+        self.script_scheduler.init()?;
+        self.status.set(EngineStatus::Running);
+        Ok(())
+    }
+    /// mounts the mods as they are set now and runs main.lua and `init` again
+    /// in a new lua state, the old one is kept when main.lua fails to load
+    fn reload_scripts(&mut self) -> anyhow::Result<()> {
+        let Some(lua_engine) = &self.lua_engine else {
+            return Ok(());
+        };
+        log::info!("reload scripts");
+        self.mods.apply();
+        self.resource.clear_assets();
+        let script = create_script(&self.resource, &self.base_config, &self.mods)?;
+        script.load_main()?;
+        self.script_scheduler.stop_all();
+        self.status.clear_callbacks();
+        lua_engine.bus.unsubscribe_lua();
+        lua_engine.graph.views.clear();
+        self.script = script;
+        self.script_scheduler = AsyncScheduler::new(self.script.modules.clone());
+        run_init_fn(&self.script, lua_engine)?;
+        self.script_scheduler.init()?;
         Ok(())
     }
     pub fn stop(&mut self) {
         log::info!("stop engine");
         self.scheduler.pause();
-        *self.status.write() = EngineStatus::Exiting;
+        self.status.set(EngineStatus::Exiting);
         if let Some(proxy) = &self.event_proxy {
             let _ = proxy.exit();
         }
     }
     fn exiting(&mut self) {
+        // let scripts finish their exit work before anything is torn down,
+        // a game closed during the splash never started
+        self.status.set(EngineStatus::Exiting);
+        if self.started {
+            let engine = self
+                .lua_engine
+                .as_ref()
+                .map(|lua_engine| self.script.create_userdata(lua_engine.clone()))
+                .transpose();
+            let result = map2anyhow_error!(engine, "create engine userdata")
+                .and_then(|engine| self.exit_hook.run(&self.script, engine));
+            if let Err(err) = result {
+                log::error!("run exit hook failed: {}", err);
+            }
+            if let Some(lua_engine) = &self.lua_engine {
+                if let Err(err) = lua_engine.ui_ctx.workspace.save_layout(None) {
+                    log::error!("save gui layout failed: {}", err);
+                }
+            }
+        }
+        self.status.clear_callbacks();
         self.script_scheduler.stop_all();
         if let (Some(render), Some(window), Some(lua_engine)) = (
             self.render.take(),
@@ -114,3 +285,84 @@ impl Engine {
         log::debug!("exiting engine");
     }
 }
+
+// a lua state with the engine modules, the mod scripts run sandboxed
+fn create_script(
+    resource: &ResourceManager,
+    base_config: &BaseConfig,
+    mods: &ModManager,
+) -> anyhow::Result<FoolScript> {
+    let mut script = FoolScript::new(resource.raw_resource.clone())?;
+    for dir in &base_config.mod_mounts {
+        script.mount_untrusted(dir);
+    }
+    mods.mount_scripts(&script);
+    script.setup()?;
+    setup_modules(&script)?;
+    Ok(script)
+}
+
+fn refresh_rate(window: &Window) -> Option<f64> {
+    window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+        .map(|mhz| mhz as f64 / 1000.0)
+}
+
+// the time a frame may take, the set fps or the display rate when rendering unlimited
+fn frame_budget(fps: Option<u32>, refresh_rate: Option<f64>) -> Duration {
+    let rate = fps
+        .map(f64::from)
+        .or(refresh_rate)
+        .filter(|rate| *rate > 0.0)
+        .unwrap_or(60.0);
+    Duration::from_secs_f64(1.0 / rate)
+}
+
+#[test]
+fn test_exit_hook_once() -> anyhow::Result<()> {
+    use crate::resource::{AssetSource, IntegrityCheck};
+    use fool_window::Application;
+    let dir = std::env::temp_dir().join(format!("test_exit_hook_once_{}", std::process::id()));
+    let assets = dir.join("assets");
+    std::fs::create_dir_all(&assets)?;
+    std::fs::write(
+        assets.join("main.lua"),
+        "exits = 0 function on_exit() exits = exits + 1 end",
+    )?;
+    let engine = |started: bool| -> anyhow::Result<Engine> {
+        let mut base: BaseConfig = toml::from_str(&format!(
+            "name = 'test'\ncapture_path = '{0}/capture'\nsave_path = '{0}/save'\n\
+             assets_path = '{0}/assets'\nmods_path = '{0}/mods'",
+            dir.display()
+        ))?;
+        base.assets_source = Some(AssetSource::Folder(assets.clone()));
+        let mut engine = Engine::new(base, Default::default(), Default::default())?;
+        if started {
+            let report = boot::run(
+                &engine.resource,
+                &engine.script,
+                IntegrityCheck::None,
+                &Default::default(),
+            );
+            engine.finish_boot(report)?;
+        }
+        Ok(engine)
+    };
+    let exits = |engine: &Engine| engine.script.globals().get::<u32>("exits");
+
+    // stopped by the game, the window then closes and winit may report it again
+    let mut started = engine(true)?;
+    started.stop();
+    Application::exiting(&mut started);
+    Application::exiting(&mut started);
+    assert_eq!(exits(&started)?, 1);
+    assert_eq!(started.status.get(), EngineStatus::Exiting);
+
+    // closed before the game started, main.lua never ran either
+    let mut booting = engine(false)?;
+    Application::exiting(&mut booting);
+    assert!(exits(&booting).is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
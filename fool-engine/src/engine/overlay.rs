@@ -0,0 +1,105 @@
+use egui::{Align2, Area, Context, Frame, Id, Order};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use winit::keyboard::KeyCode;
+
+/// toggles the overlay when `BaseConfig::debug_overlay` allows it
+pub const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+/// what the overlay shows, gathered only on the frames it is drawn
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugStats {
+    pub fps: f64,
+    /// seconds the last frame took
+    pub frame_time: f64,
+    /// fixed updates run this frame
+    pub updates: u32,
+    pub entities: usize,
+    /// None without a physics world attached to the entities
+    pub bodies: Option<usize>,
+    /// bytes held by the resource caches
+    pub cache_bytes: usize,
+    /// frames not presented because nothing changed
+    pub skipped_frames: u64,
+}
+
+/// engine stats drawn over the game and its gui, off until toggled
+#[derive(Debug, Clone, Default)]
+pub struct DebugOverlay {
+    visible: Arc<AtomicBool>,
+}
+
+impl DebugOverlay {
+    /// returns whether it is shown now
+    pub fn toggle(&self) -> bool {
+        !self.visible.fetch_xor(true, Ordering::Relaxed)
+    }
+    pub fn is_visible(&self) -> bool {
+        self.visible.load(Ordering::Relaxed)
+    }
+    /// draws into the running egui pass, `stats` is only called while visible,
+    /// returns whether it was drawn
+    pub fn show(&self, ctx: &Context, stats: impl FnOnce() -> DebugStats) -> bool {
+        if !self.is_visible() {
+            return false;
+        }
+        let stats = stats();
+        // above every window of the game, never takes the mouse
+        Area::new(Id::new("fool_debug_overlay"))
+            .order(Order::Debug)
+            .anchor(Align2::RIGHT_TOP, [-8.0, 8.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.monospace(format!(
+                        "fps {:.0} ({:.2} ms)",
+                        stats.fps,
+                        stats.frame_time * 1000.0
+                    ));
+                    ui.monospace(format!("updates {}", stats.updates));
+                    ui.monospace(format!("entities {}", stats.entities));
+                    match stats.bodies {
+                        Some(bodies) => ui.monospace(format!("bodies {}", bodies)),
+                        None => ui.monospace("bodies -"),
+                    };
+                    ui.monospace(format!(
+                        "caches {:.1} MiB",
+                        stats.cache_bytes as f64 / (1024.0 * 1024.0)
+                    ));
+                    ui.monospace(format!("skipped frames {}", stats.skipped_frames));
+                });
+            });
+        true
+    }
+}
+
+#[test]
+fn test_debug_overlay() {
+    let ctx = Context::default();
+    let overlay = DebugOverlay::default();
+    let mut gathered = 0;
+    let mut frame = |overlay: &DebugOverlay| {
+        let mut drawn = false;
+        let _ = ctx.run(Default::default(), |ctx| {
+            drawn = overlay.show(ctx, || {
+                gathered += 1;
+                DebugStats {
+                    fps: 60.0,
+                    bodies: Some(3),
+                    ..Default::default()
+                }
+            });
+        });
+        drawn
+    };
+    assert!(!frame(&overlay));
+    // the lua handle is a clone sharing the flag
+    assert!(overlay.clone().toggle());
+    assert!(overlay.is_visible());
+    assert!(frame(&overlay));
+    assert!(!overlay.toggle());
+    assert!(!frame(&overlay));
+    assert_eq!(gathered, 1);
+    let area = ctx.memory(|memory| memory.area_rect(Id::new("fool_debug_overlay")));
+    assert!(area.is_some_and(|rect| rect.width() > 0.0));
+}
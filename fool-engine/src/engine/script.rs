@@ -1,29 +1,158 @@
 pub use super::Engine;
 use crate::{
-    engine::EngineStatus,
-    script::{exit_fn, pause_fn, run_fn},
+    engine::{overlay::TOGGLE_KEY, DebugStats, EngineStatus},
+    event::InputEvent,
+    script::{
+        exit_fn, pause_fn, run_fn, take_audio_time_scale, time_peer_frame, update_fn, LuaEngine,
+    },
 };
+use fool_audio::{MAX_PLAYBACK_RATE, MIN_PLAYBACK_RATE};
 use fool_graphics::canvas::Scene;
+use fool_script::thread::WorkerPanic;
 use fool_window::WinEvent;
+use mlua::Lua;
+use std::{path::PathBuf, time::Instant};
 use winit::event::WindowEvent;
+
+/// the events since the last update step go to the next one only, a frame
+/// without a step keeps them for the following frame, the input queue too
+fn run_updates(
+    lua: &Lua,
+    lua_engine: &LuaEngine,
+    events: &mut Vec<WinEvent>,
+    steps: u32,
+    dt: f64,
+) -> anyhow::Result<()> {
+    for _ in 0..steps {
+        lua_engine.input_queue.next_step();
+        update_fn(lua, lua_engine, events, dt)?;
+        events.clear();
+    }
+    Ok(())
+}
+
 impl Engine {
     pub fn run_frame(&mut self) {
+        if !self.asset_report.is_ok() {
+            self.run_report_frame();
+            return;
+        }
+        if self.crash_test {
+            panic!("crash test requested with --crash-test");
+        }
         let scene_graph = self.scene_graph.clone();
         let events = &self.events_current_frame;
+        let status = self.status.get();
+        // the window in the background holds the updates back like a pause
+        let held = self
+            .lua_engine
+            .as_ref()
+            .is_some_and(|lua_engine| lua_engine.focus_pause.is_holding());
+        let running = status.running() && !held;
+        // a scale set last frame applies from here, pause wins over it
+        let time = time_peer_frame(!running);
+        let steps = if running {
+            self.scheduler
+                .should_update_scaled(Instant::now(), time.scale)
+        } else {
+            // don't catch up on time spent paused, nor on the input sent meanwhile
+            self.scheduler.update.reset();
+            self.events_since_update.clear();
+            if let Some(lua_engine) = &self.lua_engine {
+                lua_engine.input_queue.clear_steps();
+            }
+            0
+        };
+        let update_events = &mut self.events_since_update;
+        let (dt, alpha) = (self.scheduler.update_dt(), self.scheduler.alpha());
         if let (Some(render), Some(lua_engine)) = (&mut self.render, &mut self.lua_engine) {
-            crate::try_or_return!(render.begin_frame(), "begin_frame", self.stop());
+            // the text typed this frame is in place before any script reads it
+            lua_engine.text_input.feed(events);
+            let input = InputEvent {
+                events,
+                actions: &lua_engine.actions,
+                text: &lua_engine.text_input,
+                queue: &lua_engine.input_queue,
+            };
+            lua_engine.actions.update(&input, time.delta as f32);
+            if self.base_config.debug_overlay && input.key_pressed(TOGGLE_KEY) {
+                lua_engine.debug_overlay.toggle();
+            }
+            render.set_ui_scale(lua_engine.accessibility.ui_scale());
+            if let Some(scale) = take_audio_time_scale() {
+                let scale = scale.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE);
+                if let Err(err) = lua_engine.audio.0.set_time_scale(scale, 0) {
+                    log::warn!("forward time scale to audio failed: {}", err);
+                }
+            }
+            let began = render.begin_frame();
+            crate::try_or_return!(&began, "begin_frame", self.stop());
+            if let Ok(false) = began {
+                // nothing to draw to, keep the simulation going
+                log::trace!("skip frame, surface is unavailable");
+                let update_result = run_updates(&self.script, lua_engine, update_events, steps, dt);
+                crate::try_or_return!(update_result, "run lua update", self.stop());
+                return;
+            }
 
-            let status = { self.status.read().clone() };
-            let frame_result = match status {
+            // begin_frame moved the fades forward, finished ones and the network call back first
+            let polled = lua_engine.postfx.poll();
+            let polled = polled.and_then(|_| lua_engine.netplay.poll());
+            let polled = polled.and_then(|_| lua_engine.video.update(time.delta));
+            let polled = polled.and_then(|_| lua_engine.image.poll());
+            let polled = polled.and_then(|_| lua_engine.achievements.poll());
+            let polled = polled.and_then(|_| lua_engine.ui_ctx.toasts.poll());
+            let polled = polled.and_then(|_| lua_engine.bus.dispatch());
+            let frame_result = polled.and_then(|_| match status {
                 EngineStatus::Pause => pause_fn(&self.script, lua_engine, events),
                 EngineStatus::Exiting => exit_fn(&self.script, lua_engine, events),
-                _ => run_fn(&self.script, lua_engine, events),
-            };
+                _ => run_updates(&self.script, lua_engine, update_events, steps, dt)
+                    .and_then(|_| run_fn(&self.script, lua_engine, events, alpha)),
+            });
             let mut graph = scene_graph.write();
-            let mut scene = Scene::new();
-            let graph_result = graph.draw(&mut scene);
-            let scene_result = render.draw_scene(&scene);
+            let (graph_result, scene_result) = match lua_engine.graph.views.is_empty() {
+                true => {
+                    let mut scene = Scene::new();
+                    let graph_result = graph.draw(&mut scene);
+                    // on top of the game, in the same world space
+                    lua_engine
+                        .entities
+                        .draw_physics_debug(&mut scene, graph.transform());
+                    (graph_result, render.draw_scene(&scene))
+                }
+                // split screen, the debug shapes in each view
+                false => {
+                    let views = lua_engine.graph.views.build(&graph);
+                    let entities = &lua_engine.entities;
+                    let result = render.render_views_with(&views, |_, scene, transform| {
+                        entities.draw_physics_debug(scene, transform)
+                    });
+                    (result, Ok(()))
+                }
+            };
             graph.reset();
+            // whatever the status, a paused game still shows and times them out
+            lua_engine
+                .ui_ctx
+                .toasts
+                .draw(render.gui_context(), &self.resource);
+            // over whatever gui the scripts drew
+            lua_engine
+                .debug_overlay
+                .show(render.gui_context(), || DebugStats {
+                    fps: time.fps,
+                    frame_time: time.delta,
+                    updates: steps,
+                    entities: lua_engine.entities.count(),
+                    bodies: lua_engine.entities.body_count(),
+                    cache_bytes: self.resource.cache_bytes(),
+                    skipped_frames: render.skipped_frames(),
+                });
+            if let Some(dialog) = &mut self.crash_dialog {
+                if !dialog.show(render.gui_context()) {
+                    self.crash_dialog = None;
+                }
+            }
             crate::try_or_return!(
                 render.end_frame(self.frame_capture.pop_front()),
                 "end_frame",
@@ -35,30 +164,94 @@ impl Engine {
             crate::try_or_return!(scene_result, "run lua draw_scene", self.stop());
         }
     }
+    // the game doesn't run with broken assets, only the report is shown
+    fn run_report_frame(&mut self) {
+        let Some(render) = &mut self.render else {
+            return;
+        };
+        let began = render.begin_frame();
+        crate::try_or_return!(&began, "begin_frame", self.stop());
+        if let Ok(false) = began {
+            return;
+        }
+        let scene_result = render.draw_scene(&Scene::new());
+        let quit = self.asset_report.show(render.gui_context());
+        crate::try_or_return!(render.end_frame(None::<PathBuf>), "end_frame", self.stop());
+        crate::try_or_return!(scene_result, "draw_scene", self.stop());
+        if quit || self.status.get() == EngineStatus::Exiting {
+            self.stop();
+        }
+    }
+    // drawn until the boot work is done, the window stays responsive meanwhile
+    fn run_splash_frame(&mut self) {
+        if self.status.get() == EngineStatus::Exiting {
+            self.stop();
+            return;
+        }
+        let Some(boot) = &mut self.boot else {
+            return;
+        };
+        if let Some(report) = boot.poll() {
+            self.boot = None;
+            crate::try_or_return!(self.finish_boot(report), "finish boot", self.stop());
+            return;
+        }
+        let Some(render) = &mut self.render else {
+            return;
+        };
+        let began = render.begin_frame();
+        crate::try_or_return!(&began, "begin_frame", self.stop());
+        if let Ok(false) = began {
+            return;
+        }
+        let scene_result = render.draw_scene(&Scene::new());
+        boot.show(render.gui_context(), &self.resource);
+        crate::try_or_return!(render.end_frame(None::<PathBuf>), "end_frame", self.stop());
+        crate::try_or_return!(scene_result, "draw_scene", self.stop());
+    }
     pub fn event(&mut self, event: &WinEvent, raw_event: &WindowEvent) {
         if let Some(render) = &mut self.render {
             render.gui_event(&raw_event);
         }
         self.events_current_frame.push(event.clone());
+        self.events_since_update.push(event.clone());
+        if let Some(lua_engine) = &self.lua_engine {
+            lua_engine.input_queue.push(raw_event);
+        }
         if !event.must_redraw() {
             return;
         }
+        // the scripts are busy behind the splash
+        if self.boot.is_some() {
+            self.run_splash_frame();
+            self.events_current_frame.clear();
+            self.events_since_update.clear();
+            if let Some(lua_engine) = &self.lua_engine {
+                lua_engine.input_queue.clear();
+            }
+            return;
+        }
+        if let Some(lua_engine) = &self.lua_engine {
+            lua_engine.input_queue.next_frame();
+        }
         if let Err(err) = self
             .script_scheduler
-            .fetch_result(&self.script, self.scheduler.frame_id.into())
+            .fetch_result(&self.script, self.frame_id.into())
         {
             log::error!("run lua script_scheduler failed: {}", err);
-            self.stop();
-            return;
+            // the panicked worker restarted itself, the game goes on
+            if !err.is::<WorkerPanic>() {
+                self.stop();
+                return;
+            }
         }
         self.run_frame();
-        self.script_scheduler
-            .start_update(self.scheduler.frame_id.into());
+        self.script_scheduler.start_update(self.frame_id.into());
         self.events_current_frame.clear();
         log::trace!(
             "Frame: {}, elapsed: {:?}",
-            self.scheduler.frame_id,
-            self.scheduler.frame_id.elapsed()
+            self.frame_id,
+            self.frame_id.elapsed()
         );
     }
 }
@@ -66,7 +259,14 @@ impl Engine {
 macro_rules! try_or_return {
     ($result:expr, $ctx:expr, $run: expr) => {
         if let Err(err) = $result {
-            log::error!("{} failed: {}", $ctx, err);
+            // a script error with its causes and tracebacks
+            match err.downcast_ref::<::fool_script::ScriptError>() {
+                Some(err) => {
+                    log::error!("{} failed: {:#}", $ctx, err);
+                    $crate::crash::record_script_error(err);
+                }
+                None => log::error!("{} failed: {:#}", $ctx, err),
+            }
             $run;
             return;
         }
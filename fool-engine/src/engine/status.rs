@@ -1,4 +1,7 @@
+use mlua::{Function, UserDataMethods};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Deserialize)]
 pub enum EngineStatus {
@@ -22,3 +25,133 @@ impl EngineStatus {
         *self == Self::Exiting
     }
 }
+impl EngineStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Init => "init",
+            Self::Running => "running",
+            Self::Pause => "paused",
+            Self::Exiting => "exiting",
+        }
+    }
+}
+
+/// engine status shared with lua, notifies `on_status_change` callbacks
+#[derive(Debug, Clone)]
+pub struct SharedStatus {
+    status: Arc<RwLock<EngineStatus>>,
+    callbacks: Arc<Mutex<Vec<Function>>>,
+}
+impl SharedStatus {
+    pub fn new(status: EngineStatus) -> Self {
+        Self {
+            status: Arc::new(RwLock::new(status)),
+            callbacks: Default::default(),
+        }
+    }
+    pub fn get(&self) -> EngineStatus {
+        *self.status.read()
+    }
+    pub fn set(&self, status: EngineStatus) {
+        let old = std::mem::replace(&mut *self.status.write(), status);
+        if old == status {
+            return;
+        }
+        log::trace!("engine status {:?} -> {:?}", old, status);
+        // taken out so callbacks can query or change the status again
+        let callbacks = self.callbacks.lock().clone();
+        for callback in callbacks {
+            if let Err(err) = callback.call::<()>(status.as_str()) {
+                log::error!("run on_status_change callback failed: {}", err);
+            }
+        }
+    }
+    pub fn on_change(&self, callback: Function) {
+        self.callbacks.lock().push(callback);
+    }
+    pub fn clear_callbacks(&self) {
+        self.callbacks.lock().clear();
+    }
+    /// the status methods of the lua `engine`, `status` picks it out of the userdata
+    pub fn add_lua_methods<T: 'static, M: UserDataMethods<T>>(
+        methods: &mut M,
+        status: fn(&T) -> &Self,
+    ) {
+        methods.add_method("set_running", move |_, this, ()| {
+            status(this).set(EngineStatus::Running);
+            Ok(())
+        });
+        methods.add_method("set_pause", move |_, this, ()| {
+            status(this).set(EngineStatus::Pause);
+            Ok(())
+        });
+        methods.add_method("set_exiting", move |_, this, ()| {
+            status(this).set(EngineStatus::Exiting);
+            Ok(())
+        });
+        methods.add_method("is_running", move |_, this, ()| {
+            Ok(status(this).get() == EngineStatus::Running)
+        });
+        methods.add_method("is_pause", move |_, this, ()| {
+            Ok(status(this).get() == EngineStatus::Pause)
+        });
+        methods.add_method("is_exiting", move |_, this, ()| {
+            Ok(status(this).get() == EngineStatus::Exiting)
+        });
+        methods.add_method("status", move |_, this, ()| Ok(status(this).get().as_str()));
+        methods.add_method("on_status_change", move |_, this, callback: Function| {
+            status(this).on_change(callback);
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn test_status_change() -> anyhow::Result<()> {
+    let lua = mlua::Lua::new();
+    let status = SharedStatus::new(EngineStatus::Running);
+    let callback = lua
+        .load("return function(status) last_status = status end")
+        .eval::<Function>()?;
+    status.on_change(callback);
+    status.set(EngineStatus::Pause);
+    assert_eq!(lua.globals().get::<String>("last_status")?, "paused");
+    assert_eq!(status.get(), EngineStatus::Pause);
+    Ok(())
+}
+
+#[test]
+fn test_status_change_from_lua() -> anyhow::Result<()> {
+    use mlua::UserData;
+    // LuaEngine needs a window, this holds just its status with the same methods
+    struct Engine(SharedStatus);
+    impl UserData for Engine {
+        fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+            SharedStatus::add_lua_methods(methods, |this: &Self| &this.0);
+        }
+    }
+    let lua = mlua::Lua::new();
+    let status = SharedStatus::new(EngineStatus::Running);
+    lua.globals().set("engine", Engine(status.clone()))?;
+    lua.load(
+        r#"
+        changes = {}
+        engine:on_status_change(function(status)
+            changes[#changes + 1] = status
+            -- a callback may change the status again
+            if status == "exiting" then engine:set_pause() end
+        end)
+        engine:set_pause()
+        assert(engine:is_pause() and engine:status() == "paused")
+        "#,
+    )
+    .exec()?;
+    status.set(EngineStatus::Exiting);
+    let changes: Vec<String> = lua.load("return changes").eval()?;
+    assert_eq!(changes, ["paused", "exiting", "paused"]);
+    assert_eq!(status.get(), EngineStatus::Pause);
+    lua.load("engine:set_running()").exec()?;
+    assert_eq!(status.get(), EngineStatus::Running);
+    status.clear_callbacks();
+    Ok(())
+}
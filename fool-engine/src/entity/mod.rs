@@ -0,0 +1,247 @@
+use crate::physics::{types::LuaRigidBodyHandle, LuaPhysics};
+use fool_audio::AudioSystem;
+use mlua::{AnyUserData, Function, UserData, UserDataMethods, Value};
+use parking_lot::Mutex;
+use rapier2d::prelude::RigidBodyHandle;
+use std::{collections::HashMap, sync::Arc};
+use vello::{kurbo::Affine, Scene};
+
+pub type EntityId = u64;
+
+#[derive(Debug, Default)]
+pub struct Entity {
+    pub tag: String,
+    pub body: Option<RigidBodyHandle>,
+    // scene nodes are rebuilt by the script every frame,
+    // the key is handed back to `on_destroy` so the script stops drawing it
+    pub node: Option<Value>,
+    // (group, music)
+    pub sounds: Vec<(String, String)>,
+}
+
+/// the bookkeeping part, side effects of destroying live in `EntityRegistry`
+#[derive(Default)]
+struct Registry {
+    next_id: EntityId,
+    entities: HashMap<EntityId, Entity>,
+    bodies: HashMap<RigidBodyHandle, EntityId>,
+    physics: Option<AnyUserData>,
+    on_destroy: Option<Function>,
+}
+
+impl Registry {
+    fn create(&mut self, tag: String) -> EntityId {
+        self.next_id += 1;
+        self.entities.insert(
+            self.next_id,
+            Entity {
+                tag,
+                ..Default::default()
+            },
+        );
+        self.next_id
+    }
+    fn attach_body(&mut self, id: EntityId, body: RigidBodyHandle) -> bool {
+        let Some(entity) = self.entities.get_mut(&id) else {
+            return false;
+        };
+        if let Some(old) = entity.body.replace(body) {
+            self.bodies.remove(&old);
+        }
+        self.bodies.insert(body, id);
+        true
+    }
+    fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        if let Some(body) = entity.body {
+            self.bodies.remove(&body);
+        }
+        Some(entity)
+    }
+    fn find_by_tag(&self, tag: &str) -> Vec<EntityId> {
+        let mut ids = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.tag == tag)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+}
+
+/// one id tying together a physics body, a scene node key and playing sounds,
+/// so destroying the entity cleans all of them up
+#[derive(Clone)]
+pub struct EntityRegistry {
+    registry: Arc<Mutex<Registry>>,
+    audio: AudioSystem,
+}
+
+impl EntityRegistry {
+    pub fn new(audio: AudioSystem) -> Self {
+        Self {
+            registry: Default::default(),
+            audio,
+        }
+    }
+    pub fn create(&self, tag: impl Into<String>) -> EntityId {
+        self.registry.lock().create(tag.into())
+    }
+    pub fn attach_body(&self, id: EntityId, body: RigidBodyHandle) -> bool {
+        self.registry.lock().attach_body(id, body)
+    }
+    pub fn attach_node(&self, id: EntityId, node: Value) -> bool {
+        let mut registry = self.registry.lock();
+        let Some(entity) = registry.entities.get_mut(&id) else {
+            return false;
+        };
+        entity.node = Some(node);
+        true
+    }
+    pub fn attach_sound(
+        &self,
+        id: EntityId,
+        group: impl Into<String>,
+        music: impl Into<String>,
+    ) -> bool {
+        let mut registry = self.registry.lock();
+        let Some(entity) = registry.entities.get_mut(&id) else {
+            return false;
+        };
+        entity.sounds.push((group.into(), music.into()));
+        true
+    }
+    pub fn find_by_tag(&self, tag: &str) -> Vec<EntityId> {
+        self.registry.lock().find_by_tag(tag)
+    }
+    /// live entities
+    pub fn count(&self) -> usize {
+        self.registry.lock().entities.len()
+    }
+    /// bodies in the attached physics world, None without one or while it is being stepped
+    pub fn body_count(&self) -> Option<usize> {
+        let physics = self.registry.lock().physics.clone()?;
+        let physics = physics.borrow::<LuaPhysics>().ok()?;
+        Some(physics.physics.body_count())
+    }
+    /// the attached world's `debug_draw` overlay, skipped while it is being stepped
+    pub fn draw_physics_debug(&self, scene: &mut Scene, transform: Affine) {
+        let Some(physics) = self.registry.lock().physics.clone() else {
+            return;
+        };
+        if let Ok(physics) = physics.borrow::<LuaPhysics>() {
+            physics.draw_debug(scene, transform);
+        }
+    }
+    pub fn from_body(&self, body: RigidBodyHandle) -> Option<EntityId> {
+        self.registry.lock().bodies.get(&body).copied()
+    }
+    pub fn tag(&self, id: EntityId) -> Option<String> {
+        self.registry
+            .lock()
+            .entities
+            .get(&id)
+            .map(|entity| entity.tag.clone())
+    }
+    /// the world attached bodies are removed from
+    pub fn set_physics(&self, physics: AnyUserData) -> mlua::Result<()> {
+        if !physics.is::<LuaPhysics>() {
+            return Err(mlua::Error::RuntimeError(
+                "set_physics expects a Physics world".to_owned(),
+            ));
+        }
+        self.registry.lock().physics = Some(physics);
+        Ok(())
+    }
+    pub fn on_destroy(&self, callback: Option<Function>) {
+        self.registry.lock().on_destroy = callback;
+    }
+    /// removes the body (deferred while physics callbacks run), stops the sounds,
+    /// then calls `on_destroy(id, tag, node)`, false for unknown ids
+    pub fn destroy(&self, id: EntityId) -> mlua::Result<bool> {
+        // released before calling out, the callback may use the registry
+        let (entity, physics, on_destroy) = {
+            let mut registry = self.registry.lock();
+            let Some(entity) = registry.remove(id) else {
+                return Ok(false);
+            };
+            (
+                entity,
+                registry.physics.clone(),
+                registry.on_destroy.clone(),
+            )
+        };
+        match (entity.body, &physics) {
+            (Some(body), Some(physics)) => LuaPhysics::remove_body(physics, body)?,
+            (Some(_), None) => log::warn!("entity {} has a body but no physics world is set", id),
+            _ => {}
+        }
+        for (group, music) in &entity.sounds {
+            // the sound may already be finished
+            if let Err(err) = self.audio.stop(group, music, 0) {
+                log::debug!("entity {} stop {}: {}", id, music, err);
+            }
+        }
+        if let Some(callback) = on_destroy {
+            callback.call::<()>((id, entity.tag, entity.node))?;
+        }
+        Ok(true)
+    }
+}
+
+impl UserData for EntityRegistry {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("create", |_, this, tag: String| Ok(this.create(tag)));
+        methods.add_method(
+            "attach_body",
+            |_, this, (id, body): (EntityId, LuaRigidBodyHandle)| Ok(this.attach_body(id, body.0)),
+        );
+        methods.add_method("attach_node", |_, this, (id, node): (EntityId, Value)| {
+            Ok(this.attach_node(id, node))
+        });
+        methods.add_method(
+            "attach_sound",
+            |_, this, (id, group, music): (EntityId, String, String)| {
+                Ok(this.attach_sound(id, group, music))
+            },
+        );
+        methods.add_method("destroy", |_, this, id: EntityId| this.destroy(id));
+        methods.add_method("find_by_tag", |_, this, tag: String| {
+            Ok(this.find_by_tag(&tag))
+        });
+        methods.add_method("from_body", |_, this, body: LuaRigidBodyHandle| {
+            Ok(this.from_body(body.0))
+        });
+        methods.add_method("tag", |_, this, id: EntityId| Ok(this.tag(id)));
+        methods.add_method("set_physics", |_, this, physics: AnyUserData| {
+            this.set_physics(physics)
+        });
+        methods.add_method("on_destroy", |_, this, callback: Option<Function>| {
+            this.on_destroy(callback);
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn test_entity_registry() {
+    let mut registry = Registry::default();
+    let player = registry.create("player".into());
+    let orc = registry.create("enemy".into());
+    let goblin = registry.create("enemy".into());
+    let body = RigidBodyHandle::from_raw_parts(3, 0);
+    assert!(registry.attach_body(orc, body));
+    assert!(!registry.attach_body(42, body));
+    assert_eq!(registry.bodies.get(&body), Some(&orc));
+    assert_eq!(registry.find_by_tag("enemy"), [orc, goblin]);
+    assert_eq!(registry.find_by_tag("player"), [player]);
+
+    let removed = registry.remove(orc).unwrap();
+    assert_eq!(removed.body, Some(body));
+    assert!(registry.bodies.is_empty());
+    assert!(registry.remove(orc).is_none());
+    assert_eq!(registry.find_by_tag("enemy"), [goblin]);
+    // ids are never reused
+    assert!(registry.create("enemy".into()) > goblin);
+}
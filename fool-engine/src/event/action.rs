@@ -0,0 +1,492 @@
+use super::InputEvent;
+use crate::map2lua_error;
+use crate::save::SaveManager;
+use fool_window::WinEvent;
+use mlua::{UserData, UserDataMethods};
+use parking_lot::RwLock;
+use serde::de::{value::StrDeserializer, IntoDeserializer};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
+use winit::{
+    event::MouseButton,
+    keyboard::{Key, KeyCode, NamedKey},
+};
+
+const DEFAULT_SAVE_NAME: &str = "bindings";
+// bindings to what the layout types are saved with it
+const LOGICAL_PREFIX: &str = "logical:";
+const PHYSICAL_PREFIX: &str = "physical:";
+
+/// an input an action can be bound to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Binding {
+    /// where the key sits, the same on every layout, what movement wants
+    Key(KeyCode),
+    /// what the key types, for mnemonic shortcuts like ctrl+s
+    Logical(Key),
+    Mouse(MouseButton),
+}
+
+/// "Escape" is a named key, anything else the characters it types
+pub fn parse_logical(s: &str) -> Key {
+    let de: StrDeserializer<serde::de::value::Error> = s.into_deserializer();
+    match NamedKey::deserialize(de) {
+        Ok(named) => Key::Named(named),
+        Err(_) => Key::Character(s.to_lowercase().into()),
+    }
+}
+
+/// the US label of a physical key, "KeyW" is "W"
+fn us_label(code: KeyCode) -> String {
+    let name = format!("{:?}", code);
+    for prefix in ["Key", "Digit"] {
+        if let Some(label) = name.strip_prefix(prefix) {
+            return label.to_owned();
+        }
+    }
+    name
+}
+
+impl FromStr for Binding {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(key) = s.strip_prefix(LOGICAL_PREFIX) {
+            return Ok(Binding::Logical(parse_logical(key)));
+        }
+        let s = s.strip_prefix(PHYSICAL_PREFIX).unwrap_or(s);
+        let mouse = match s {
+            "MouseLeft" => Some(MouseButton::Left),
+            "MouseRight" => Some(MouseButton::Right),
+            "MouseMiddle" => Some(MouseButton::Middle),
+            "MouseBack" => Some(MouseButton::Back),
+            "MouseForward" => Some(MouseButton::Forward),
+            _ => None,
+        };
+        if let Some(button) = mouse {
+            return Ok(Binding::Mouse(button));
+        }
+        let de: StrDeserializer<serde::de::value::Error> = s.into_deserializer();
+        match KeyCode::deserialize(de) {
+            Ok(code) => Ok(Binding::Key(code)),
+            // key codes are never a single character, "s" means the key typing s
+            Err(_) if s.chars().count() == 1 => Ok(Binding::Logical(parse_logical(s))),
+            Err(_) => Err(anyhow::anyhow!("unknown input {}", s)),
+        }
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(code) => write!(f, "{:?}", code),
+            Binding::Logical(Key::Character(c)) => write!(f, "{}{}", LOGICAL_PREFIX, c),
+            Binding::Logical(Key::Named(named)) => write!(f, "{}{:?}", LOGICAL_PREFIX, named),
+            Binding::Logical(key) => write!(f, "{}{:?}", LOGICAL_PREFIX, key),
+            Binding::Mouse(button) => write!(f, "Mouse{:?}", button),
+        }
+    }
+}
+
+impl Binding {
+    // characters match either case, shift changes what the key types
+    fn logical(
+        key: &Key,
+        events: &[WinEvent],
+        check: impl Fn(&WinEvent, Key<&str>) -> bool,
+    ) -> bool {
+        events.iter().any(|event| match key {
+            Key::Character(c) => {
+                check(event, Key::Character(&c.to_lowercase()))
+                    || check(event, Key::Character(&c.to_uppercase()))
+            }
+            key => check(event, key.as_ref()),
+        })
+    }
+    pub fn pressed(&self, events: &[WinEvent]) -> bool {
+        match self {
+            Binding::Key(code) => events.iter().any(|e| e.key_pressed(*code)),
+            Binding::Logical(key) => Self::logical(key, events, |e, k| e.key_pressed_logical(k)),
+            Binding::Mouse(button) => events.iter().any(|e| e.mouse_pressed(*button)),
+        }
+    }
+    pub fn released(&self, events: &[WinEvent]) -> bool {
+        match self {
+            Binding::Key(code) => events.iter().any(|e| e.key_released(*code)),
+            Binding::Logical(key) => Self::logical(key, events, |e, k| e.key_released_logical(k)),
+            Binding::Mouse(button) => events.iter().any(|e| e.mouse_released(*button)),
+        }
+    }
+    pub fn held(&self, events: &[WinEvent]) -> bool {
+        match self {
+            Binding::Key(code) => events.iter().any(|e| e.key_held(*code)),
+            Binding::Logical(key) => Self::logical(key, events, |e, k| e.key_held_logical(k)),
+            Binding::Mouse(button) => events.iter().any(|e| e.mouse_held(*button)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Action {
+    // names as given, unsupported inputs (e.g. gamepad) are kept so saving round trips
+    names: Vec<String>,
+    bindings: Vec<Binding>,
+}
+
+/// how the actions marked toggleable read as held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HoldMode {
+    /// held while an input is held
+    #[default]
+    Hold,
+    /// a press turns it on, the next one off
+    Toggle,
+}
+
+/// key repeat for menu navigation and how held actions behave
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RepeatSettings {
+    /// seconds held before the first repeat
+    pub delay: f32,
+    /// repeats per second after that, 0 for none
+    pub rate: f32,
+    pub hold_mode: HoldMode,
+}
+impl Default for RepeatSettings {
+    fn default() -> Self {
+        Self {
+            delay: 0.4,
+            rate: 12.0,
+            hold_mode: HoldMode::Hold,
+        }
+    }
+}
+
+/// what an action did over the frames so far, see `ActionMap::update`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ActionState {
+    toggleable: bool,
+    toggled: bool,
+    held_for: f32,
+    next_repeat: f32,
+    repeated: bool,
+}
+
+impl ActionState {
+    fn advance(&mut self, pressed: bool, held: bool, dt: f32, settings: &RepeatSettings) {
+        self.repeated = pressed;
+        if pressed {
+            self.held_for = 0.0;
+            self.next_repeat = settings.delay;
+            self.toggled = !self.toggled;
+        } else if held {
+            self.held_for += dt;
+            if settings.rate > 0.0 && self.held_for >= self.next_repeat {
+                self.repeated = true;
+                // one repeat a frame at most, slow frames don't burst
+                let interval = 1.0 / settings.rate;
+                while self.next_repeat <= self.held_for {
+                    self.next_repeat += interval;
+                }
+            }
+        }
+        if !self.toggleable || settings.hold_mode == HoldMode::Hold {
+            self.toggled = false;
+        }
+    }
+}
+
+/// named actions resolved against the per frame input,
+/// each action can be bound to several physical inputs
+#[derive(Debug, Clone)]
+pub struct ActionMap {
+    actions: Arc<RwLock<HashMap<String, Action>>>,
+    states: Arc<RwLock<HashMap<String, ActionState>>>,
+    repeat: Arc<RwLock<RepeatSettings>>,
+    // what the physical keys typed, kept for the frames without input
+    labels: Arc<RwLock<HashMap<KeyCode, Key>>>,
+    save: SaveManager,
+}
+
+impl ActionMap {
+    pub fn new(save: SaveManager) -> Self {
+        Self {
+            actions: Default::default(),
+            states: Default::default(),
+            repeat: Default::default(),
+            labels: Default::default(),
+            save,
+        }
+    }
+    pub fn set_repeat(&self, settings: RepeatSettings) {
+        *self.repeat.write() = settings;
+    }
+    pub fn repeat(&self) -> RepeatSettings {
+        *self.repeat.read()
+    }
+    /// whether `action` follows the hold mode, e.g. sprint or aim,
+    /// the others always read as held while an input is
+    pub fn set_toggleable(&self, action: &str, toggleable: bool) {
+        let mut states = self.states.write();
+        let state = states.entry(action.to_owned()).or_default();
+        state.toggleable = toggleable;
+        state.toggled &= toggleable;
+    }
+    /// the window keeps every label it saw, the newest event has them all
+    fn learn_labels(&self, events: &[WinEvent]) {
+        if let Some(event) = events.last() {
+            self.labels.write().clone_from(event.key_labels());
+        }
+    }
+    /// what the key at `code` types in the player's layout, e.g. "Z" for KeyW on AZERTY,
+    /// the US label until the key was pressed once, winit can't ask the layout
+    pub fn key_display_name(&self, code: KeyCode) -> String {
+        match self.labels.read().get(&code) {
+            Some(Key::Character(c)) => c.to_uppercase(),
+            Some(Key::Named(named)) => format!("{:?}", named),
+            _ => us_label(code),
+        }
+    }
+    /// moves repeats and toggles forward, once per frame before scripts read the input
+    pub fn update(&self, input: &InputEvent, dt: f32) {
+        self.learn_labels(input.events);
+        let settings = self.repeat();
+        let actions = self.actions.read();
+        let mut states = self.states.write();
+        for (name, action) in actions.iter() {
+            let pressed = action.bindings.iter().any(|b| b.pressed(input.events));
+            let held = action.bindings.iter().any(|b| b.held(input.events));
+            states
+                .entry(name.clone())
+                .or_default()
+                .advance(pressed, held, dt, &settings);
+        }
+    }
+    /// replaces the inputs bound to `action`, "KeyW" binds where the key sits
+    /// and "s" or "logical:s" what the key types, the saved names say which
+    pub fn bind(&self, action: impl Into<String>, inputs: &[impl AsRef<str>]) {
+        let action = action.into();
+        let mut bound = Action::default();
+        for name in inputs {
+            let name = name.as_ref();
+            match Binding::from_str(name) {
+                Ok(binding) => {
+                    bound.names.push(binding.to_string());
+                    bound.bindings.push(binding);
+                }
+                Err(err) => {
+                    log::warn!("action {}: {}, binding ignored", action, err);
+                    bound.names.push(name.to_owned());
+                }
+            }
+        }
+        self.actions.write().insert(action, bound);
+    }
+    pub fn unbind(&self, action: &str) -> bool {
+        self.actions.write().remove(action).is_some()
+    }
+    pub fn bindings(&self, action: &str) -> Vec<String> {
+        self.actions
+            .read()
+            .get(action)
+            .map(|a| a.names.clone())
+            .unwrap_or_default()
+    }
+    pub fn to_map(&self) -> HashMap<String, Vec<String>> {
+        self.actions
+            .read()
+            .iter()
+            .map(|(name, action)| (name.clone(), action.names.clone()))
+            .collect()
+    }
+    /// true when any input bound to `action` satisfies `active`
+    pub fn resolve(&self, action: &str, mut active: impl FnMut(&Binding) -> bool) -> bool {
+        self.actions
+            .read()
+            .get(action)
+            .is_some_and(|a| a.bindings.iter().any(&mut active))
+    }
+    pub fn pressed(&self, action: &str, input: &InputEvent) -> bool {
+        self.resolve(action, |b| b.pressed(input.events))
+    }
+    pub fn released(&self, action: &str, input: &InputEvent) -> bool {
+        self.resolve(action, |b| b.released(input.events))
+    }
+    /// toggleable actions read as toggled in the toggle hold mode
+    pub fn held(&self, action: &str, input: &InputEvent) -> bool {
+        if self.repeat().hold_mode == HoldMode::Toggle {
+            if let Some(state) = self.states.read().get(action).filter(|s| s.toggleable) {
+                return state.toggled;
+            }
+        }
+        self.resolve(action, |b| b.held(input.events))
+    }
+    /// true on the press and then at the repeat rate while held
+    pub fn repeated(&self, action: &str) -> bool {
+        self.states.read().get(action).is_some_and(|s| s.repeated)
+    }
+    pub fn save(&self, name: Option<String>) -> anyhow::Result<()> {
+        let data = bson::to_bson(&self.to_map())?;
+        let name = name.unwrap_or(DEFAULT_SAVE_NAME.to_owned());
+        self.save.save(Some(name), true, data)
+    }
+    /// replaces every binding with the saved ones
+    pub fn load(&self, name: Option<String>) -> anyhow::Result<()> {
+        let name = name.unwrap_or(DEFAULT_SAVE_NAME.to_owned());
+        let entry = self.save.load(name)?;
+        let saved: HashMap<String, Vec<String>> = bson::from_bson(entry.data)?;
+        self.actions.write().clear();
+        for (action, inputs) in saved {
+            self.bind(action, &inputs);
+        }
+        Ok(())
+    }
+}
+
+impl UserData for ActionMap {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "bind",
+            |_, this, (action, inputs): (String, mlua::Either<String, Vec<String>>)| {
+                match inputs {
+                    mlua::Either::Left(input) => this.bind(action, &[input]),
+                    mlua::Either::Right(inputs) => this.bind(action, &inputs),
+                }
+                Ok(())
+            },
+        );
+        methods.add_method("unbind", |_, this, action: String| Ok(this.unbind(&action)));
+        methods.add_method("bindings", |_, this, action: String| {
+            Ok(this.bindings(&action))
+        });
+        methods.add_method("all", |_, this, ()| Ok(this.to_map()));
+        methods.add_method(
+            "set_toggleable",
+            |_, this, (action, toggleable): (String, Option<bool>)| {
+                this.set_toggleable(&action, toggleable.unwrap_or(true));
+                Ok(())
+            },
+        );
+        methods.add_method("save", |_, this, name: Option<String>| {
+            map2lua_error!(this.save(name), "ActionMap::save")
+        });
+        methods.add_method("load", |_, this, name: Option<String>| {
+            map2lua_error!(this.load(name), "ActionMap::load")
+        });
+    }
+}
+
+#[test]
+fn test_action_map() -> anyhow::Result<()> {
+    // a folder of its own, other runs and tests don't share the saved slot
+    let dir = std::env::temp_dir().join(format!("test_action_map_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let map = ActionMap::new(SaveManager::new(&dir));
+    map.bind("jump", &["Space", "KeyW", "GamepadA"]);
+    for key in [KeyCode::Space, KeyCode::KeyW] {
+        assert!(map.resolve("jump", |b| *b == Binding::Key(key)));
+    }
+    assert!(!map.resolve("jump", |b| *b == Binding::Key(KeyCode::KeyA)));
+    assert!(!map.resolve("fire", |_| true));
+    assert_eq!(map.bindings("jump"), ["Space", "KeyW", "GamepadA"]);
+    assert_eq!(Binding::from_str("MouseLeft")?.to_string(), "MouseLeft");
+    assert_eq!(Binding::from_str("KeyA")?, Binding::Key(KeyCode::KeyA));
+
+    map.save(Some("test_action_map".into()))?;
+    map.unbind("jump");
+    map.load(Some("test_action_map".into()))?;
+    assert!(map.resolve("jump", |b| *b == Binding::Key(KeyCode::KeyW)));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_physical_logical_bindings() -> anyhow::Result<()> {
+    use winit::event::ElementState;
+    use winit::keyboard::PhysicalKey;
+    // a folder of its own, other runs and tests don't share the saved slot
+    let dir = std::env::temp_dir().join(format!(
+        "test_physical_logical_bindings_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir)?;
+    let map = ActionMap::new(SaveManager::new(&dir));
+    map.bind("forward", &["KeyW"]);
+    map.bind("save", &["s"]);
+    map.bind("pause", &["logical:Escape", "physical:KeyP"]);
+    assert_eq!(map.bindings("save"), ["logical:s"]);
+    assert_eq!(map.bindings("pause"), ["logical:Escape", "KeyP"]);
+    assert_eq!(
+        Binding::from_str("logical:Escape")?,
+        Binding::Logical(Key::Named(NamedKey::Escape))
+    );
+    assert!(Binding::from_str("Sprint").is_err());
+
+    // an AZERTY keyboard, the W position types z and the S position s
+    let press = |physical, logical: &str| {
+        let mut event = WinEvent::new();
+        event.step();
+        event.process_key(
+            PhysicalKey::Code(physical),
+            Key::Character(logical.into()),
+            ElementState::Pressed,
+        );
+        event.end_step();
+        vec![event]
+    };
+    let events = press(KeyCode::KeyW, "z");
+    assert!(map.resolve("forward", |b| b.pressed(&events)));
+    assert!(!map.resolve("save", |b| b.pressed(&events)));
+    map.learn_labels(&events);
+    map.learn_labels(&[]);
+    assert_eq!(map.key_display_name(KeyCode::KeyW), "Z");
+    assert_eq!(map.key_display_name(KeyCode::KeyA), "A");
+    assert_eq!(map.key_display_name(KeyCode::Digit1), "1");
+    // dvorak, the S key sits where the US ; is
+    let events = press(KeyCode::Semicolon, "S");
+    assert!(map.resolve("save", |b| b.pressed(&events) && b.held(&events)));
+    assert!(!map.resolve("forward", |b| b.held(&events)));
+
+    map.save(Some("test_physical_logical_bindings".into()))?;
+    map.unbind("save");
+    map.load(Some("test_physical_logical_bindings".into()))?;
+    assert!(map.resolve("save", |b| *b
+        == Binding::Logical(Key::Character("s".into()))));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_action_state() {
+    let settings = RepeatSettings {
+        delay: 0.5,
+        rate: 8.0,
+        hold_mode: HoldMode::Toggle,
+    };
+    let mut state = ActionState::default();
+    state.advance(true, true, 0.1, &settings);
+    assert!(state.repeated);
+    let mut repeats = 0;
+    // held for another second at 32 fps
+    for _ in 0..32 {
+        state.advance(false, true, 0.03125, &settings);
+        repeats += state.repeated as u32;
+    }
+    // at 0.5s, then every 0.125s up to 1.0s
+    assert_eq!(repeats, 5);
+    // a long frame repeats once
+    state.advance(false, true, 0.375, &settings);
+    assert!(state.repeated);
+    state.advance(false, false, 0.02, &settings);
+    assert!(!state.repeated);
+    // only toggleable actions latch
+    assert!(!state.toggled);
+    state.toggleable = true;
+    state.advance(true, true, 0.02, &settings);
+    state.advance(false, false, 0.02, &settings);
+    assert!(state.toggled);
+    state.advance(true, true, 0.02, &settings);
+    assert!(!state.toggled);
+    state.advance(true, true, 0.02, &RepeatSettings::default());
+    assert!(!state.toggled);
+}
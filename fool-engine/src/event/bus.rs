@@ -0,0 +1,193 @@
+use crate::{map2anyhow_error, map2lua_error};
+use bson::Bson;
+use fool_script::modules::ser::{bson_to_lua_value, lua_value_to_bson};
+use mlua::{Function, IntoLua, Lua, UserData, UserDataMethods, Value};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+pub type SubscriptionId = u64;
+type RustHandler = Arc<dyn Fn(&Bson) -> anyhow::Result<()> + Send + Sync>;
+// by event name, in the order they subscribed
+type Subscribers = HashMap<String, Vec<(SubscriptionId, Handler)>>;
+
+#[derive(Clone)]
+enum Handler {
+    Rust(RustHandler),
+    Lua(Function),
+}
+
+// converted for the lua subscribers when they are called
+struct LuaBson<'a>(&'a Bson);
+
+impl IntoLua for LuaBson<'_> {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        bson_to_lua_value(lua, self.0)
+    }
+}
+
+/// named events with a bson payload between the subsystems and the scripts,
+/// published any time and delivered in order by `dispatch`, once per frame
+#[derive(Clone, Default)]
+pub struct EventBus {
+    queue: Arc<Mutex<Vec<(String, Bson)>>>,
+    subscribers: Arc<RwLock<Subscribers>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl EventBus {
+    pub fn publish(&self, name: impl Into<String>, payload: Bson) {
+        self.queue.lock().push((name.into(), payload));
+    }
+    fn add(&self, name: impl Into<String>, handler: Handler) -> SubscriptionId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.subscribers
+            .write()
+            .entry(name.into())
+            .or_default()
+            .push((id, handler));
+        id
+    }
+    pub fn subscribe(
+        &self,
+        name: impl Into<String>,
+        handler: impl Fn(&Bson) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.add(name, Handler::Rust(Arc::new(handler)))
+    }
+    /// `callback(payload, name)`
+    pub fn subscribe_lua(&self, name: impl Into<String>, callback: Function) -> SubscriptionId {
+        self.add(name, Handler::Lua(callback))
+    }
+    /// false when it was not subscribed
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.subscribers.write();
+        let mut found = false;
+        for handlers in subscribers.values_mut() {
+            let len = handlers.len();
+            handlers.retain(|(handler_id, _)| *handler_id != id);
+            found |= handlers.len() != len;
+        }
+        subscribers.retain(|_, handlers| !handlers.is_empty());
+        found
+    }
+    /// drops the subscribers of a lua state that is replaced, the rust ones stay
+    pub fn unsubscribe_lua(&self) {
+        let mut subscribers = self.subscribers.write();
+        for handlers in subscribers.values_mut() {
+            handlers.retain(|(_, handler)| matches!(handler, Handler::Rust(_)));
+        }
+        subscribers.retain(|_, handlers| !handlers.is_empty());
+    }
+    pub fn pending(&self) -> usize {
+        self.queue.lock().len()
+    }
+    /// delivers the events published so far to their subscribers, in the order they
+    /// subscribed. The events the subscribers publish wait for the next dispatch
+    pub fn dispatch(&self) -> anyhow::Result<()> {
+        let events = std::mem::take(&mut *self.queue.lock());
+        for (name, payload) in events {
+            // no lock is held, a subscriber may subscribe or unsubscribe
+            let handlers = match self.subscribers.read().get(&name) {
+                Some(handlers) => handlers.clone(),
+                None => continue,
+            };
+            for (_, handler) in handlers {
+                match handler {
+                    Handler::Rust(handler) => handler(&payload)?,
+                    Handler::Lua(callback) => map2anyhow_error!(
+                        callback.call::<()>((LuaBson(&payload), name.as_str())),
+                        format!("run event bus subscriber of {}", name)
+                    )?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl UserData for EventBus {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "publish",
+            |_, this, (name, payload): (String, Option<Value>)| {
+                let payload = map2lua_error!(
+                    lua_value_to_bson(payload.unwrap_or(Value::Nil)),
+                    "EventBus::publish"
+                )?;
+                this.publish(name, payload);
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "subscribe",
+            |_, this, (name, callback): (String, Function)| Ok(this.subscribe_lua(name, callback)),
+        );
+        methods.add_method("unsubscribe", |_, this, id: SubscriptionId| {
+            Ok(this.unsubscribe(id))
+        });
+        methods.add_method("pending", |_, this, ()| Ok(this.pending()));
+    }
+}
+
+#[test]
+fn test_event_bus() -> anyhow::Result<()> {
+    let bus = EventBus::default();
+    let lua = Lua::new();
+    lua.globals().set("bus", bus.clone())?;
+    lua.load(
+        r#"
+        hits = {}
+        id = bus:subscribe("collision", function(payload, name)
+            table.insert(hits, { name = name, payload = payload })
+        end)
+        "#,
+    )
+    .exec()?;
+    let heard = Arc::new(Mutex::new(Vec::new()));
+    let sink = heard.clone();
+    bus.subscribe("collision", move |payload| {
+        sink.lock().push(payload.clone());
+        Ok(())
+    });
+    let payload = bson::bson!({ "started": true, "force": 2.5, "tags": ["wall", "ball"] });
+    bus.publish("collision", payload.clone());
+    bus.publish("nobody listens", Bson::Null);
+    // nothing is delivered before the dispatch
+    assert_eq!(lua.load("return #hits").eval::<usize>()?, 0);
+    bus.dispatch()?;
+    assert_eq!(bus.pending(), 0);
+    let (name, started, force, tag): (String, bool, f64, String) = lua
+        .load("local hit = hits[1] return hit.name, hit.payload.started, hit.payload.force, hit.payload.tags[2]")
+        .eval()?;
+    assert_eq!(
+        (name.as_str(), started, force, tag.as_str()),
+        ("collision", true, 2.5, "ball")
+    );
+    assert_eq!(*heard.lock(), [payload]);
+
+    // published from lua, an event published by a subscriber waits a frame
+    lua.load(
+        r#"
+        bus:subscribe("echo", function(payload) bus:publish("collision", payload) end)
+        bus:publish("echo", { n = 1 })
+        "#,
+    )
+    .exec()?;
+    bus.dispatch()?;
+    assert_eq!(lua.load("return #hits").eval::<usize>()?, 1);
+    bus.dispatch()?;
+    assert_eq!(lua.load("return hits[2].payload.n").eval::<i64>()?, 1);
+
+    assert!(lua.load("return bus:unsubscribe(id)").eval::<bool>()?);
+    assert!(!lua.load("return bus:unsubscribe(id)").eval::<bool>()?);
+    bus.unsubscribe_lua();
+    bus.publish("echo", Bson::Null);
+    bus.publish("collision", Bson::Null);
+    bus.dispatch()?;
+    assert_eq!(lua.load("return #hits").eval::<usize>()?, 2);
+    // the rust subscriber stays
+    assert_eq!(heard.lock().len(), 3);
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use super::action::parse_logical;
+use super::{ActionMap, Binding, EventQueue, TextInput};
 use crate::lua_create_table;
 use fool_window::WinEvent;
 use mlua::{
@@ -5,12 +7,16 @@ use mlua::{
     Value::{self},
 };
 use winit::{
-    event::{Ime, MouseButton},
+    event::MouseButton,
     keyboard::{Key, KeyCode},
 };
 #[allow(dead_code)]
 pub struct InputEvent<'a> {
     pub events: &'a Vec<WinEvent>,
+    pub actions: &'a ActionMap,
+    pub text: &'a TextInput,
+    // every press and release since the last frame, `events` only keeps the edges
+    pub queue: &'a EventQueue,
 }
 
 impl<'a> InputEvent<'a> {
@@ -76,6 +82,14 @@ impl<'a> InputEvent<'a> {
         }
         false
     }
+    pub fn mouse_held(&self, key: MouseButton) -> bool {
+        for event in self.events {
+            if event.mouse_held(key) {
+                return true;
+            }
+        }
+        false
+    }
     pub fn scroll_diff(&self) -> (f32, f32) {
         if let Some(e) = self.events.last() {
             e.scroll_diff()
@@ -115,37 +129,13 @@ impl<'a> InputEvent<'a> {
         let commit = self
             .events
             .iter()
-            .filter_map(|e| match e.ime() {
-                Some(Ime::Commit(c)) => Some(c),
-                _ => None,
-            })
-            .collect::<Vec<String>>()
-            .join("");
-        let preedit = self
-            .events
-            .iter()
-            .filter_map(|e| match e.ime() {
-                Some(Ime::Preedit(c, p)) => {
-                    if c.is_empty() && p.is_none() {
-                        None
-                    } else {
-                        Some((c, p))
-                    }
-                }
-                _ => None,
-            })
-            .last()
-            .clone();
-        let enable = self
-            .events
-            .iter()
-            .filter_map(|e| match e.ime() {
-                Some(Ime::Enabled) => Some(true),
-                Some(Ime::Disabled) => Some(false),
-                _ => None,
-            })
-            .last();
-        (commit, preedit, enable)
+            .map(|e| e.ime_state().commit.as_str())
+            .collect::<String>();
+        // the composition is kept across steps, the newest one is what shows
+        let Some(state) = self.events.last().map(|e| e.ime_state()) else {
+            return (commit, None, None);
+        };
+        (commit, state.preedit.clone(), Some(state.enabled))
     }
 }
 
@@ -163,6 +153,52 @@ impl<'a> UserData for InputEvent<'a> {
             let key: KeyCode = lua.from_value(key)?;
             Ok(this.key_held(key))
         });
+        // where the key sits, the same as key_pressed, "KeyW" is W on US and Z on AZERTY
+        methods.add_method("key_pressed_physical", |lua, this, key: Value| {
+            let key: KeyCode = lua.from_value(key)?;
+            Ok(this.key_pressed(key))
+        });
+        methods.add_method("key_released_physical", |lua, this, key: Value| {
+            let key: KeyCode = lua.from_value(key)?;
+            Ok(this.key_released(key))
+        });
+        methods.add_method("key_held_physical", |lua, this, key: Value| {
+            let key: KeyCode = lua.from_value(key)?;
+            Ok(this.key_held(key))
+        });
+        // what the key types, "z" or a named key like "Escape"
+        methods.add_method("key_pressed_logical", |_, this, key: String| {
+            Ok(Binding::Logical(parse_logical(&key)).pressed(this.events))
+        });
+        methods.add_method("key_released_logical", |_, this, key: String| {
+            Ok(Binding::Logical(parse_logical(&key)).released(this.events))
+        });
+        methods.add_method("key_held_logical", |_, this, key: String| {
+            Ok(Binding::Logical(parse_logical(&key)).held(this.events))
+        });
+        // the presses, releases and scrolls since the last frame in the order they came
+        methods.add_method("events", |lua, this, ()| this.queue.to_lua(lua));
+        // counts every tap, key_pressed is true once however many there were
+        methods.add_method("key_press_count", |lua, this, key: Value| {
+            let key: KeyCode = lua.from_value(key)?;
+            Ok(this.queue.press_count(key))
+        });
+        methods.add_method("key_display_name", |lua, this, key: Value| {
+            let key: KeyCode = lua.from_value(key)?;
+            Ok(this.actions.key_display_name(key))
+        });
+        methods.add_method("action_pressed", |_, this, action: String| {
+            Ok(this.actions.pressed(&action, this))
+        });
+        methods.add_method("action_released", |_, this, action: String| {
+            Ok(this.actions.released(&action, this))
+        });
+        methods.add_method("action_held", |_, this, action: String| {
+            Ok(this.actions.held(&action, this))
+        });
+        methods.add_method("action_repeat", |_, this, action: String| {
+            Ok(this.actions.repeated(&action))
+        });
         methods.add_method("cursor_pos", |lua, this, ()| {
             let diff = this.cursor();
             let table = lua_create_table!(lua, [x = diff.0, y = diff.1]);
@@ -234,6 +270,36 @@ impl<'a> UserData for InputEvent<'a> {
             }
             Ok(Value::Table(table))
         });
+        methods.add_method(
+            "start_text",
+            |_lua, this, (initial, max_len): (Option<String>, Option<usize>)| {
+                this.text.start(&initial.unwrap_or_default(), max_len);
+                Ok(())
+            },
+        );
+        methods.add_method("text_state", |lua, this, ()| {
+            let Some(edit) = this.text.state() else {
+                return Ok(Value::Nil);
+            };
+            let table = lua_create_table!(lua, [text = edit.text(), cursor = edit.cursor()]);
+            if let Some((start, end)) = edit.selection() {
+                let selection = lua_create_table!(lua, [start = start, end = end]);
+                table.set("selection", selection)?;
+            }
+            if let Some((content, pos)) = edit.preedit {
+                let preedit = lua_create_table!(lua, [content = content]);
+                if let Some(p) = pos {
+                    preedit.set("pos", lua_create_table!(lua, [x = p.0, y = p.1]))?;
+                }
+                table.set("preedit", preedit)?;
+            }
+            Ok(Value::Table(table))
+        });
+        methods.add_method("set_ime_position", |_lua, this, (x, y): (f64, f64)| {
+            this.text.set_ime_position(x, y);
+            Ok(())
+        });
+        methods.add_method("end_text", |_lua, this, ()| Ok(this.text.end()));
     }
 }
 
@@ -1,2 +1,10 @@
+mod action;
+mod bus;
 mod input;
+mod queue;
+mod text;
+pub use action::{ActionMap, Binding, HoldMode, RepeatSettings};
+pub use bus::{EventBus, SubscriptionId};
 pub use input::InputEvent;
+pub use queue::{EventQueue, QueuedEvent};
+pub use text::{TextEdit, TextInput};
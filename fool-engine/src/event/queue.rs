@@ -0,0 +1,280 @@
+use mlua::{Lua, LuaSerdeExt, Table};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{Key, KeyCode, PhysicalKey};
+
+// a line of the mouse wheel in pixels, for wheels reporting pixels
+const PIXELS_PER_LINE: f32 = 40.0;
+
+/// one press, release or scroll, as it arrived
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueuedEvent {
+    Key {
+        code: Option<KeyCode>,
+        logical: Key,
+        pressed: bool,
+        repeat: bool,
+    },
+    Mouse {
+        button: MouseButton,
+        pressed: bool,
+    },
+    Wheel {
+        x: f32,
+        y: f32,
+    },
+}
+
+impl QueuedEvent {
+    fn to_lua(&self, lua: &Lua) -> mlua::Result<Table> {
+        let table = lua.create_table()?;
+        match self {
+            QueuedEvent::Key {
+                code,
+                logical,
+                pressed,
+                repeat,
+            } => {
+                table.set("type", "key")?;
+                if let Some(code) = code {
+                    table.set("key", lua.to_value(code)?)?;
+                }
+                match logical {
+                    Key::Character(c) => table.set("logical", c.as_str())?,
+                    Key::Named(named) => table.set("logical", format!("{:?}", named))?,
+                    _ => {}
+                }
+                table.set("pressed", *pressed)?;
+                table.set("repeat", *repeat)?;
+            }
+            QueuedEvent::Mouse { button, pressed } => {
+                table.set("type", "mouse")?;
+                table.set("button", format!("{:?}", button))?;
+                table.set("pressed", *pressed)?;
+            }
+            QueuedEvent::Wheel { x, y } => {
+                table.set("type", "wheel")?;
+                table.set("x", *x)?;
+                table.set("y", *y)?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    pending: Vec<QueuedEvent>,
+    frame: Vec<QueuedEvent>,
+    // the same for the update steps, a frame runs several or none
+    step_pending: Vec<QueuedEvent>,
+    step: Vec<QueuedEvent>,
+}
+
+impl Queue {
+    fn push(&mut self, event: QueuedEvent) {
+        self.step_pending.push(event.clone());
+        self.pending.push(event);
+    }
+    fn clear_steps(&mut self) {
+        self.step_pending.clear();
+        self.step.clear();
+    }
+}
+
+/// every press and release since the last frame, in order, two taps of a key
+/// between frames are two presses here where `key_pressed` only tells there was one
+#[derive(Debug, Clone, Default)]
+pub struct EventQueue {
+    queue: Arc<Mutex<Queue>>,
+    // reads what arrived before the current update step instead of the frame
+    stepped: bool,
+}
+
+impl EventQueue {
+    pub fn push(&self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => self.push_key(
+                &event.physical_key,
+                &event.logical_key,
+                event.state,
+                event.repeat,
+            ),
+            WindowEvent::MouseInput { button, state, .. } => self.push_mouse(*button, *state),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(pos) => (
+                        pos.x as f32 / PIXELS_PER_LINE,
+                        pos.y as f32 / PIXELS_PER_LINE,
+                    ),
+                };
+                self.queue.lock().push(QueuedEvent::Wheel { x, y });
+            }
+            _ => {}
+        }
+    }
+    /// what `push` does for a key, `KeyEvent` can't be built outside winit
+    pub fn push_key(
+        &self,
+        physical: &PhysicalKey,
+        logical: &Key,
+        state: ElementState,
+        repeat: bool,
+    ) {
+        let code = match physical {
+            PhysicalKey::Code(code) => Some(*code),
+            PhysicalKey::Unidentified(_) => None,
+        };
+        self.queue.lock().push(QueuedEvent::Key {
+            code,
+            logical: logical.clone(),
+            pressed: state.is_pressed(),
+            repeat,
+        });
+    }
+    pub fn push_mouse(&self, button: MouseButton, state: ElementState) {
+        self.queue.lock().push(QueuedEvent::Mouse {
+            button,
+            pressed: state.is_pressed(),
+        });
+    }
+    /// what was queued since the last call becomes the current frame's
+    pub fn next_frame(&self) {
+        let mut queue = self.queue.lock();
+        queue.frame = std::mem::take(&mut queue.pending);
+    }
+    /// what was queued since the last step becomes the current step's, each event
+    /// is seen by one step, they wait through frames that run none
+    pub fn next_step(&self) {
+        let mut queue = self.queue.lock();
+        queue.step = std::mem::take(&mut queue.step_pending);
+    }
+    /// the same queue as the update steps see it
+    pub fn stepped(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            stepped: true,
+        }
+    }
+    /// drops everything, e.g. the input sent to the splash
+    pub fn clear(&self) {
+        let mut queue = self.queue.lock();
+        queue.pending.clear();
+        queue.frame.clear();
+        queue.clear_steps();
+    }
+    /// drops what the update steps haven't seen, e.g. while they are paused
+    pub fn clear_steps(&self) {
+        self.queue.lock().clear_steps();
+    }
+    fn current<R>(&self, f: impl FnOnce(&[QueuedEvent]) -> R) -> R {
+        let queue = self.queue.lock();
+        match self.stepped {
+            true => f(&queue.step),
+            false => f(&queue.frame),
+        }
+    }
+    pub fn events(&self) -> Vec<QueuedEvent> {
+        self.current(|events| events.to_vec())
+    }
+    /// presses of `code` this frame or step, key repeats not counted
+    pub fn press_count(&self, code: KeyCode) -> usize {
+        self.current(|events| {
+            events
+                .iter()
+                .filter(|event| match event {
+                    QueuedEvent::Key {
+                        code: Some(c),
+                        pressed: true,
+                        repeat: false,
+                        ..
+                    } => *c == code,
+                    _ => false,
+                })
+                .count()
+        })
+    }
+    pub fn to_lua(&self, lua: &Lua) -> mlua::Result<Table> {
+        let list = lua.create_table()?;
+        for event in self.events() {
+            list.push(event.to_lua(lua)?)?;
+        }
+        Ok(list)
+    }
+}
+
+#[test]
+fn test_event_queue() -> anyhow::Result<()> {
+    let queue = EventQueue::default();
+    let tap = |code: KeyCode, c: &str| {
+        let logical = Key::Character(c.into());
+        let physical = PhysicalKey::Code(code);
+        queue.push_key(&physical, &logical, ElementState::Pressed, false);
+        queue.push_key(&physical, &logical, ElementState::Released, false);
+    };
+    // two taps of space and one of A between frames, at a low frame rate
+    tap(KeyCode::Space, " ");
+    tap(KeyCode::KeyA, "a");
+    tap(KeyCode::Space, " ");
+    queue.push_mouse(MouseButton::Left, ElementState::Pressed);
+    queue.next_frame();
+    assert_eq!(queue.press_count(KeyCode::Space), 2);
+    assert_eq!(queue.events().len(), 7);
+
+    let lua = Lua::new();
+    lua.globals().set("events", queue.to_lua(&lua)?)?;
+    let (jumps, order): (i64, String) = lua
+        .load(
+            r#"
+            local jumps, order = 0, ""
+            for _, e in ipairs(events) do
+                if e.type == "key" and e.pressed then
+                    order = order .. e.logical
+                    if e.key == "Space" then jumps = jumps + 1 end
+                end
+            end
+            assert(events[7].type == "mouse" and events[7].button == "Left")
+            return jumps, order
+            "#,
+        )
+        .eval()?;
+    assert_eq!(jumps, 2);
+    assert_eq!(order, " a ");
+    // the next frame starts empty
+    queue.next_frame();
+    assert!(queue.events().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_step_queue() {
+    let queue = EventQueue::default();
+    let steps = queue.stepped();
+    let tap = || {
+        let physical = PhysicalKey::Code(KeyCode::Space);
+        let logical = Key::Character(" ".into());
+        queue.push_key(&physical, &logical, ElementState::Pressed, false);
+        queue.push_key(&physical, &logical, ElementState::Released, false);
+    };
+    // a frame running two update steps, only the first sees the press
+    tap();
+    queue.next_frame();
+    steps.next_step();
+    assert_eq!(steps.press_count(KeyCode::Space), 1);
+    steps.next_step();
+    assert_eq!(steps.press_count(KeyCode::Space), 0);
+    assert_eq!(queue.press_count(KeyCode::Space), 1);
+    // a frame running none, the press waits for the next step
+    tap();
+    queue.next_frame();
+    queue.next_frame();
+    assert!(queue.events().is_empty());
+    steps.next_step();
+    assert_eq!(steps.events().len(), 2);
+    tap();
+    steps.clear_steps();
+    steps.next_step();
+    assert!(steps.events().is_empty());
+}
@@ -0,0 +1,304 @@
+use fool_window::WinEvent;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::Ime,
+    keyboard::{Key, NamedKey},
+    window::Window,
+};
+
+fn word_start(chars: &[char], mut pos: usize) -> usize {
+    while pos > 0 && chars[pos - 1].is_whitespace() {
+        pos -= 1;
+    }
+    while pos > 0 && !chars[pos - 1].is_whitespace() {
+        pos -= 1;
+    }
+    pos
+}
+fn word_end(chars: &[char], mut pos: usize) -> usize {
+    while pos < chars.len() && chars[pos].is_whitespace() {
+        pos += 1;
+    }
+    while pos < chars.len() && !chars[pos].is_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// a single line being edited, positions are char indices between characters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextEdit {
+    chars: Vec<char>,
+    cursor: usize,
+    // the other end of the selection
+    anchor: Option<usize>,
+    max_len: Option<usize>,
+    /// ime composition not committed yet, (content, cursor byte range)
+    pub preedit: Option<(String, Option<(usize, usize)>)>,
+}
+
+impl TextEdit {
+    pub fn new(initial: &str, max_len: Option<usize>) -> Self {
+        let mut chars = initial.chars().collect::<Vec<_>>();
+        if let Some(max_len) = max_len {
+            chars.truncate(max_len);
+        }
+        Self {
+            cursor: chars.len(),
+            chars,
+            max_len,
+            ..Default::default()
+        }
+    }
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    /// (start, end), None when nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor.filter(|anchor| *anchor != self.cursor)?;
+        Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.chars[start..end].iter().collect())
+    }
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        match selection {
+            Some((start, end)) => {
+                self.chars.drain(start..end);
+                self.cursor = start;
+                true
+            }
+            None => false,
+        }
+    }
+    /// replaces the selection, control characters are dropped and so is
+    /// whatever does not fit `max_len`
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let room = self
+            .max_len
+            .map_or(usize::MAX, |max| max.saturating_sub(self.chars.len()));
+        let new = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(room)
+            .collect::<Vec<_>>();
+        let len = new.len();
+        self.chars.splice(self.cursor..self.cursor, new);
+        self.cursor += len;
+    }
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+    /// removes the word before the cursor
+    pub fn delete_word(&mut self) {
+        if !self.delete_selection() {
+            let start = word_start(&self.chars, self.cursor);
+            self.chars.drain(start..self.cursor);
+            self.cursor = start;
+        }
+    }
+    fn move_to(&mut self, pos: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = pos.min(self.chars.len());
+    }
+    pub fn left(&mut self, select: bool, word: bool) {
+        let pos = match (self.selection(), select, word) {
+            // collapses to the start of the selection
+            (Some((start, _)), false, false) => start,
+            (_, _, true) => word_start(&self.chars, self.cursor),
+            _ => self.cursor.saturating_sub(1),
+        };
+        self.move_to(pos, select);
+    }
+    pub fn right(&mut self, select: bool, word: bool) {
+        let pos = match (self.selection(), select, word) {
+            (Some((_, end)), false, false) => end,
+            (_, _, true) => word_end(&self.chars, self.cursor),
+            _ => self.cursor + 1,
+        };
+        self.move_to(pos, select);
+    }
+    pub fn home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+    pub fn end(&mut self, select: bool) {
+        self.move_to(self.chars.len(), select);
+    }
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.chars.len();
+    }
+}
+
+#[derive(Debug)]
+struct TextSession {
+    edit: TextEdit,
+    // text comes in as ime commits while composing, key characters are ignored then
+    composing: bool,
+}
+
+/// a text input session fed from the frame's window events,
+/// only active between `start` and `end`
+#[derive(Clone)]
+pub struct TextInput {
+    window: Arc<Window>,
+    session: Arc<Mutex<Option<TextSession>>>,
+    clipboard: bool,
+}
+
+impl TextInput {
+    pub fn new(window: Arc<Window>, clipboard: bool) -> Self {
+        Self {
+            window,
+            session: Default::default(),
+            clipboard,
+        }
+    }
+    pub fn start(&self, initial: &str, max_len: Option<usize>) {
+        self.window.set_ime_allowed(true);
+        *self.session.lock() = Some(TextSession {
+            edit: TextEdit::new(initial, max_len),
+            composing: false,
+        });
+    }
+    /// the final text, None when no session was running
+    pub fn end(&self) -> Option<String> {
+        let session = self.session.lock().take()?;
+        self.window.set_ime_allowed(false);
+        Some(session.edit.text())
+    }
+    pub fn is_active(&self) -> bool {
+        self.session.lock().is_some()
+    }
+    pub fn state(&self) -> Option<TextEdit> {
+        self.session
+            .lock()
+            .as_ref()
+            .map(|session| session.edit.clone())
+    }
+    /// where the ime candidate window shows, in window pixels
+    pub fn set_ime_position(&self, x: f64, y: f64) {
+        self.window
+            .set_ime_cursor_area(PhysicalPosition::new(x, y), PhysicalSize::new(1.0, 1.0));
+    }
+    fn paste(&self, edit: &mut TextEdit) {
+        if !self.clipboard {
+            return;
+        }
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            // a single line, line breaks become spaces
+            Ok(text) => edit.insert(&text.replace(['\r', '\n'], " ")),
+            Err(err) => log::debug!("clipboard paste failed: {}", err),
+        }
+    }
+    fn copy(&self, edit: &TextEdit) {
+        let Some(text) = edit.selected_text().filter(|_| self.clipboard) else {
+            return;
+        };
+        if let Err(err) = arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+            log::debug!("clipboard copy failed: {}", err);
+        }
+    }
+    /// applies the edits of one frame
+    pub fn feed(&self, events: &[WinEvent]) {
+        let mut session = self.session.lock();
+        let Some(session) = session.as_mut() else {
+            return;
+        };
+        for event in events {
+            for ime in event.ime_state().events() {
+                match ime {
+                    Ime::Enabled => session.composing = true,
+                    Ime::Disabled => {
+                        session.composing = false;
+                        session.edit.preedit = None;
+                    }
+                    Ime::Preedit(content, cursor) => {
+                        session.edit.preedit =
+                            (!content.is_empty()).then(|| (content.clone(), *cursor));
+                    }
+                    Ime::Commit(text) => {
+                        session.edit.preedit = None;
+                        session.edit.insert(text);
+                    }
+                }
+            }
+            let (ctrl, shift) = (event.held_control(), event.held_shift());
+            let edit = &mut session.edit;
+            for key in event.text() {
+                match key {
+                    Key::Character(c) if ctrl => match c.to_lowercase().as_str() {
+                        "a" => edit.select_all(),
+                        "c" => self.copy(edit),
+                        "x" => {
+                            self.copy(edit);
+                            edit.delete_selection();
+                        }
+                        "v" => self.paste(edit),
+                        _ => {}
+                    },
+                    Key::Character(c) if !session.composing => edit.insert(c),
+                    Key::Named(NamedKey::Space) if !session.composing => edit.insert(" "),
+                    Key::Named(NamedKey::Backspace) if ctrl => edit.delete_word(),
+                    Key::Named(NamedKey::Backspace) => edit.backspace(),
+                    Key::Named(NamedKey::Delete) => edit.delete(),
+                    Key::Named(NamedKey::ArrowLeft) => edit.left(shift, ctrl),
+                    Key::Named(NamedKey::ArrowRight) => edit.right(shift, ctrl),
+                    Key::Named(NamedKey::Home) => edit.home(shift),
+                    Key::Named(NamedKey::End) => edit.end(shift),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_text_edit() {
+    let mut edit = TextEdit::new("héllo world", Some(14));
+    assert_eq!(edit.cursor(), 11);
+    edit.delete_word();
+    assert_eq!(edit.text(), "héllo ");
+    edit.insert("wörld!!!!!!");
+    // capped at 14 chars
+    assert_eq!(edit.text(), "héllo wörld!!!");
+    edit.home(false);
+    edit.right(true, true);
+    assert_eq!(edit.selected_text().as_deref(), Some("héllo"));
+    edit.insert("hi");
+    assert_eq!((edit.text().as_str(), edit.cursor()), ("hi wörld!!!", 2));
+    edit.end(false);
+    edit.left(true, false);
+    edit.left(true, false);
+    assert_eq!(edit.selection(), Some((9, 11)));
+    // left without shift collapses to the selection start
+    edit.left(false, false);
+    assert_eq!((edit.cursor(), edit.selection()), (9, None));
+    edit.backspace();
+    edit.delete();
+    assert_eq!(edit.text(), "hi wörld!");
+    edit.select_all();
+    edit.insert("a\nb");
+    assert_eq!(edit.text(), "ab");
+}
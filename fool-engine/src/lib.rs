@@ -1,21 +1,58 @@
+pub mod accessibility;
+pub mod achievements;
 pub mod config;
+pub mod crash;
 pub mod engine;
+pub mod entity;
 pub mod event;
+pub mod mods;
+pub mod netplay;
 pub mod physics;
 pub mod resource;
 pub mod save;
 mod scheduler;
 pub mod script;
 pub mod utils;
-use fool_window::{AppEvent, FoolWindow};
+use fool_window::{AppEvent, FoolWindow, WindowState};
 use winit::event_loop::EventLoopBuilder;
 
-pub fn init_engine() -> anyhow::Result<()> {
-    let config = config::Config::from_file()?;
+/// `assets` overrides the assets source of the config, `crash_test` panics on the
+/// first frame to check the crash reports
+pub fn init_engine(assets: Option<resource::AssetSource>, crash_test: bool) -> anyhow::Result<()> {
+    let mut config = config::Config::from_file()?;
+    if assets.is_some() {
+        config.base.assets_source = assets;
+    }
+    // before anything that could panic
+    if config.crash.enabled {
+        if let Err(err) = crash::install(&config) {
+            log::warn!("install crash reporter failed: {}", err);
+        }
+    }
+    let crash_dialog = crash::CrashDialog::pending(
+        &crash::crash_dir(&config.base.name),
+        config.crash.upload_command.clone(),
+    );
     let event_loop = EventLoopBuilder::<AppEvent>::default().build()?;
-    let window_attr = config.window.build(&event_loop)?;
-    let engine = engine::Engine::new(config.base)?;
+    let state_path = match config.window.remember_window() {
+        true => Some(config.base.window_state_path()?),
+        false => None,
+    };
+    let state = state_path.as_deref().and_then(WindowState::load);
+    let window_attr = config.window.build(&event_loop, state.as_ref())?;
+    let loop_mode = config
+        .window
+        .control_flow
+        .unwrap_or_default()
+        .loop_mode(config.base.fps);
+    let mut engine = engine::Engine::new(config.base, config.boot, config.render)?;
+    engine.set_crash_dialog(crash_dialog);
+    engine.set_crash_test(crash_test);
     let mut window = FoolWindow::new(window_attr, engine, event_loop)?;
+    window.set_loop_mode(loop_mode);
+    if let Some(path) = state_path {
+        window.remember_state(path, state.as_ref());
+    }
     window.run()?;
     Ok(())
 }
@@ -1,6 +1,6 @@
 #![cfg_attr(not(feature = "debug"), windows_subsystem = "windows")]
 use clap::Parser;
-use fool_engine::init_engine;
+use fool_engine::{init_engine, resource::AssetSource};
 use log::LevelFilter;
 use std::str::FromStr;
 #[derive(Parser, Debug, Clone)]
@@ -15,6 +15,12 @@ pub struct Args {
     /// The log is output to the console
     #[arg(short, long, default_value_t = true)]
     verbose: bool,
+    /// read the assets from folder:<path> or pak:<path> instead of the config
+    #[arg(long)]
+    assets: Option<String>,
+    /// panic on the first frame to check the crash reports
+    #[arg(long, hide = true)]
+    crash_test: bool,
 }
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
@@ -26,5 +32,10 @@ fn main() -> anyhow::Result<()> {
         &args.file_log,
         &["fool_engine", "fool_script", "fool_resource"],
     )?;
-    init_engine()
+    // relative to where it was started from, not to the executable
+    let assets = match &args.assets {
+        Some(assets) => Some(AssetSource::parse(assets)?.relative_to(&std::env::current_dir()?)),
+        None => None,
+    };
+    init_engine(assets, args.crash_test)
 }
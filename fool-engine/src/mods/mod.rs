@@ -0,0 +1,500 @@
+use crate::resource::fallback::{FSFallBack, PakFallBack};
+use crate::save::SaveManager;
+use crate::{lua_create_table, map2lua_error};
+use fool_resource::{Fallback, SharedData};
+use fool_script::FoolScript;
+use mlua::{Lua, LuaSerdeExt, Table, UserData, UserDataMethods};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+mod order;
+pub use order::{load_order, Dependency, LoadPlan, Version};
+
+const SAVE_SLOT: &str = "__mods";
+// read at discovery, never served as an asset
+const MOD_INFO: &str = "mod.toml";
+
+/// the `mod.toml` at the root of every mod
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    // `id` or `id >= version`
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub min_engine_version: Option<String>,
+}
+
+type RawFallback = Box<dyn Fallback<K = String, V = SharedData>>;
+
+/// what a mod may serve. Its scripts only load from its own `mods/<id>/`, which
+/// `mount_scripts` sandboxes, so it can't replace main.lua or a trusted module
+fn provides(id: &str, key: &str) -> bool {
+    if key == MOD_INFO || key.split(['/', '\\']).any(|part| part == "..") {
+        return false;
+    }
+    let script = key
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("lua"));
+    !script || key.starts_with(&format!("mods/{}/", id))
+}
+
+/// a folder or pak of the mods folder with a readable mod.toml
+#[derive(Debug, Clone)]
+pub struct ModEntry {
+    pub info: ModInfo,
+    pub path: PathBuf,
+    source: RawFallback,
+}
+
+impl ModEntry {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let source: RawFallback = if path.is_dir() {
+            Box::new(FSFallBack {
+                asset_path: path.to_path_buf(),
+            })
+        } else {
+            let pak = packtool::ResourcePackage::open_mapped(path)?;
+            Box::new(PakFallBack { pak: Arc::new(pak) })
+        };
+        let data = source.get(&MOD_INFO.to_owned())?;
+        let info: ModInfo = toml::from_str(std::str::from_utf8(&data)?)?;
+        Ok(Self {
+            info,
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// the mounted mods in front of the game's assets, the last one in load order is tried first
+#[derive(Debug, Clone, Default)]
+pub struct ModFallBack {
+    // with the id of their mod
+    layers: Arc<RwLock<Vec<(String, RawFallback)>>>,
+}
+
+impl Fallback for ModFallBack {
+    type K = String;
+    type V = SharedData;
+    fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+        for (id, layer) in self.layers.read().iter().rev() {
+            if provides(id, key) && layer.exists(key) {
+                return layer.get(key);
+            }
+        }
+        Err(anyhow::anyhow!("{} is in no mod", key))
+    }
+    fn exists(&self, key: &Self::K) -> bool {
+        self.layers
+            .read()
+            .iter()
+            .any(|(id, layer)| provides(id, key) && layer.exists(key))
+    }
+    fn keys(&self) -> Vec<Self::K> {
+        let mut keys: Vec<String> = self
+            .layers
+            .read()
+            .iter()
+            .flat_map(|(id, layer)| {
+                let keys = layer.keys().into_iter();
+                keys.filter(move |key| provides(id, key))
+            })
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+/// an asset more than one loaded mod ships, `mods` in load order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    pub path: String,
+    pub mods: Vec<String>,
+    pub winner: String,
+}
+
+// what the player chose, mods not listed are enabled and load last
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ModConfig {
+    order: Vec<String>,
+    disabled: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct ModState {
+    mods: Vec<ModEntry>,
+    config: ModConfig,
+    // mounted by the last `apply`
+    applied: LoadPlan,
+    conflicts: Vec<Conflict>,
+}
+
+impl ModState {
+    fn get(&self, id: &str) -> anyhow::Result<&ModEntry> {
+        self.mods
+            .iter()
+            .find(|entry| entry.info.id == id)
+            .ok_or(anyhow::anyhow!("mod {} is not installed", id))
+    }
+    fn plan(&self) -> LoadPlan {
+        let infos: Vec<ModInfo> = self.mods.iter().map(|entry| entry.info.clone()).collect();
+        let enabled = infos
+            .iter()
+            .map(|info| info.id.clone())
+            .filter(|id| !self.config.disabled.contains(id))
+            .collect();
+        load_order(&infos, &enabled, &self.config.order, engine_version())
+    }
+}
+
+pub fn engine_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).unwrap_or(Version(0, 0, 0))
+}
+
+/// the mods folder, enabling, disabling and reordering are saved right away
+/// and take effect with the next `apply`, which the engine runs on a script reload
+#[derive(Debug, Clone)]
+pub struct ModManager {
+    dir: PathBuf,
+    save: SaveManager,
+    fallback: ModFallBack,
+    state: Arc<RwLock<ModState>>,
+}
+
+impl ModManager {
+    pub fn new(dir: impl Into<PathBuf>, save: SaveManager) -> Self {
+        let config = save
+            .load(SAVE_SLOT)
+            .and_then(|entry| Ok(bson::from_bson::<ModConfig>(entry.data)?));
+        let config = match config {
+            Ok(config) => config,
+            Err(err) => {
+                log::debug!("no saved mod settings, enabling every mod: {}", err);
+                Default::default()
+            }
+        };
+        let manager = Self {
+            dir: dir.into(),
+            save,
+            fallback: Default::default(),
+            state: Arc::new(RwLock::new(ModState {
+                config,
+                ..Default::default()
+            })),
+        };
+        manager.scan();
+        manager
+    }
+    /// what the resource has to try before the game's assets
+    pub fn fallback(&self) -> ModFallBack {
+        self.fallback.clone()
+    }
+    /// finds the folders and paks with a mod.toml, a broken one is logged and skipped
+    pub fn scan(&self) {
+        let mut mods: Vec<ModEntry> = Vec::new();
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(err) => {
+                log::debug!("no mods in {}: {}", self.dir.display(), err);
+                Vec::new()
+            }
+        };
+        paths.retain(|path| path.is_dir() || path.extension().is_some_and(|ext| ext == "pak"));
+        paths.sort();
+        for path in paths {
+            match ModEntry::open(&path) {
+                Ok(entry) if mods.iter().any(|m| m.info.id == entry.info.id) => log::error!(
+                    "mod {} skipped, id {} is taken",
+                    path.display(),
+                    entry.info.id
+                ),
+                Ok(entry) => mods.push(entry),
+                Err(err) => log::error!("mod {} skipped: {}", path.display(), err),
+            }
+        }
+        let mut state = self.state.write();
+        // new mods go last, in name order
+        let mut order: Vec<String> = state
+            .config
+            .order
+            .iter()
+            .filter(|id| mods.iter().any(|m| &m.info.id == *id))
+            .cloned()
+            .collect();
+        for entry in &mods {
+            if !order.contains(&entry.info.id) {
+                order.push(entry.info.id.clone());
+            }
+        }
+        state.config.order = order;
+        state.mods = mods;
+    }
+    /// what `apply` would mount now
+    pub fn plan(&self) -> LoadPlan {
+        self.state.read().plan()
+    }
+    /// mounts the planned mods in place of the ones before, the caches
+    /// holding assets of the old ones have to be cleared by the caller
+    pub fn apply(&self) -> LoadPlan {
+        let mut state = self.state.write();
+        let plan = state.plan();
+        for (id, errors) in &plan.errors {
+            log::error!("mod {} not loaded: {}", id, errors.join(", "));
+        }
+        let mut layers = Vec::new();
+        let mut provided: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for id in &plan.order {
+            let Ok(entry) = state.get(id) else {
+                continue;
+            };
+            for key in entry.source.keys() {
+                if provides(id, &key) {
+                    provided.entry(key).or_default().push(id.clone());
+                } else if key != MOD_INFO {
+                    log::warn!(
+                        "{} of mod {} ignored, its scripts go in mods/{}/",
+                        key,
+                        id,
+                        id
+                    );
+                }
+            }
+            layers.push((id.clone(), entry.source.clone()));
+        }
+        let conflicts: Vec<Conflict> = provided
+            .into_iter()
+            .filter(|(_, mods)| mods.len() > 1)
+            .map(|(path, mods)| Conflict {
+                winner: mods[mods.len() - 1].clone(),
+                path,
+                mods,
+            })
+            .collect();
+        for conflict in &conflicts {
+            log::warn!(
+                "{} is in mods {}, {} wins",
+                conflict.path,
+                conflict.mods.join(", "),
+                conflict.winner
+            );
+        }
+        log::info!("mods loaded: {}", plan.order.join(", "));
+        *self.fallback.layers.write() = layers;
+        state.conflicts = conflicts;
+        state.applied = plan.clone();
+        plan
+    }
+    /// the scripts under `mods/<id>/` of every loaded mod run sandboxed
+    pub fn mount_scripts(&self, script: &FoolScript) {
+        for id in &self.state.read().applied.order {
+            script.mount_untrusted(&format!("mods/{}", id));
+        }
+    }
+    /// the mounted mods, lowest priority first
+    pub fn loaded(&self) -> Vec<String> {
+        self.state.read().applied.order.clone()
+    }
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.state.read().conflicts.clone()
+    }
+    /// whether `apply` would mount something else than what is mounted
+    pub fn pending(&self) -> bool {
+        let state = self.state.read();
+        state.plan().order != state.applied.order
+    }
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> anyhow::Result<()> {
+        let mut state = self.state.write();
+        state.get(id)?;
+        state.config.disabled.retain(|disabled| disabled != id);
+        if !enabled {
+            state.config.disabled.push(id.to_owned());
+        }
+        self.save_config(&state.config)
+    }
+    /// moves `id` to `index` of the preferred order, past the end puts it last
+    pub fn reorder(&self, id: &str, index: usize) -> anyhow::Result<()> {
+        let mut state = self.state.write();
+        state.get(id)?;
+        state.config.order.retain(|other| other != id);
+        let index = index.min(state.config.order.len());
+        state.config.order.insert(index, id.to_owned());
+        self.save_config(&state.config)
+    }
+    fn save_config(&self, config: &ModConfig) -> anyhow::Result<()> {
+        self.save
+            .save(Some(SAVE_SLOT), true, bson::to_bson(config)?)
+    }
+    fn info_table(
+        &self,
+        lua: &Lua,
+        state: &ModState,
+        plan: &LoadPlan,
+        id: &str,
+    ) -> mlua::Result<Table> {
+        let entry = map2lua_error!(state.get(id), "mods info")?;
+        let info = &entry.info;
+        let errors = plan.errors.get(id).cloned().unwrap_or_default();
+        Ok(lua_create_table!(
+            lua,
+            [
+                id = info.id.clone(),
+                name = info.name.clone(),
+                version = info.version.clone(),
+                author = info.author.clone(),
+                dependencies = info.dependencies.clone(),
+                min_engine_version = info.min_engine_version.clone(),
+                path = entry.path.display().to_string(),
+                enabled = !state.config.disabled.contains(&info.id),
+                loaded = state.applied.order.contains(&info.id),
+                errors = errors
+            ]
+        ))
+    }
+}
+
+impl UserData for ModManager {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // in the preferred order, `errors` tells why an enabled mod would not load
+        methods.add_method("list", |lua, this, ()| {
+            let state = this.state.read();
+            let plan = state.plan();
+            let list = lua.create_table()?;
+            for id in &state.config.order {
+                list.push(this.info_table(lua, &state, &plan, id)?)?;
+            }
+            Ok(list)
+        });
+        methods.add_method("info", |lua, this, id: String| {
+            let state = this.state.read();
+            match state.get(&id) {
+                Ok(_) => Ok(Some(this.info_table(lua, &state, &state.plan(), &id)?)),
+                Err(_) => Ok(None),
+            }
+        });
+        methods.add_method("enable", |_, this, id: String| {
+            map2lua_error!(this.set_enabled(&id, true), "mods enable")
+        });
+        methods.add_method("disable", |_, this, id: String| {
+            map2lua_error!(this.set_enabled(&id, false), "mods disable")
+        });
+        // 1 is the lowest priority, the same as the list
+        methods.add_method("reorder", |_, this, (id, index): (String, usize)| {
+            map2lua_error!(this.reorder(&id, index.saturating_sub(1)), "mods reorder")
+        });
+        methods.add_method("loaded", |_, this, ()| Ok(this.loaded()));
+        methods.add_method("pending", |_, this, ()| Ok(this.pending()));
+        methods.add_method("conflicts", |lua, this, ()| lua.to_value(&this.conflicts()));
+    }
+}
+
+#[test]
+fn test_mod_manager() -> anyhow::Result<()> {
+    use fool_resource::Resource;
+    let dir = std::env::temp_dir().join("test_mod_manager");
+    let _ = std::fs::remove_dir_all(&dir);
+    let write = |path: &str, content: &str| -> anyhow::Result<()> {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        Ok(std::fs::write(path, content)?)
+    };
+    write("assets/image/hero.png", "base")?;
+    write("assets/main.lua", "return {}")?;
+    let mod_toml = |id: &str, deps: &str| {
+        format!(
+            "id = \"{}\"\nname = \"{}\"\nversion = \"1.0\"\ndependencies = [{}]\n",
+            id, id, deps
+        )
+    };
+    write("mods/hd/mod.toml", &mod_toml("hd", ""))?;
+    write("mods/hd/image/hero.png", "hd")?;
+    write("mods/hd/image/sky.png", "hd")?;
+    write("mods/retro/mod.toml", &mod_toml("retro", "\"hd >= 1\""))?;
+    write("mods/retro/image/hero.png", "retro")?;
+    write("mods/retro/mods/retro/init.lua", "return { palette = 4 }")?;
+    // scripts outside their own folder would run unsandboxed
+    write("mods/retro/main.lua", "return { hijacked = true }")?;
+    write("mods/hd/mods/retro/extra.lua", "return {}")?;
+    write("mods/broken/mod.toml", "id = ")?;
+    write("mods/loop/mod.toml", &mod_toml("loop", "\"loop\""))?;
+    let save = SaveManager::new(dir.join("save"));
+    std::fs::create_dir_all(dir.join("save"))?;
+
+    let resource = Resource::from_fallback(FSFallBack {
+        asset_path: dir.join("assets"),
+    });
+    let mods = ModManager::new(dir.join("mods"), save.clone());
+    resource.prepend_fallback(mods.fallback());
+    // the player wants retro under hd, it needs hd so it still loads after it
+    mods.reorder("retro", 0)?;
+    let plan = mods.apply();
+    assert_eq!(plan.order, ["hd", "retro"]);
+    assert!(plan.errors["loop"][0].contains("dependency cycle loop -> loop"));
+    assert_eq!(&*resource.get("image/hero.png")?, b"retro");
+    assert_eq!(&*resource.get("image/sky.png")?, b"hd");
+    assert_eq!(&*resource.get("main.lua")?, b"return {}");
+    assert!(!mods.fallback().exists(&"main.lua".to_owned()));
+    assert!(resource.try_get("mods/retro/extra.lua").is_none());
+    assert!(resource.try_get("mod.toml").is_none());
+    assert_eq!(
+        mods.conflicts(),
+        [Conflict {
+            path: "image/hero.png".into(),
+            mods: vec!["hd".into(), "retro".into()],
+            winner: "retro".into(),
+        }]
+    );
+
+    let lua = Lua::new();
+    lua.globals().set("mods", mods.clone())?;
+    let (count, errors): (usize, String) = lua
+        .load(
+            r#"
+            local list = mods:list()
+            assert(list[1].id == "retro" and list[1].loaded and list[1].enabled)
+            assert(mods:info("missing") == nil)
+            mods:disable("hd")
+            assert(mods:pending())
+            local retro = mods:info("retro")
+            return #list, retro.errors[1]
+            "#,
+        )
+        .eval()?;
+    assert_eq!(count, 3);
+    assert!(errors.contains("hd, which is disabled"), "{}", errors);
+    assert!(lua.load("mods:enable('nope')").exec().is_err());
+
+    // the choice survives a restart and applies on reload
+    let mods = ModManager::new(dir.join("mods"), save.clone());
+    resource.set_fall_back(FSFallBack {
+        asset_path: dir.join("assets"),
+    });
+    resource.prepend_fallback(mods.fallback());
+    resource.clear();
+    assert!(mods.apply().order.is_empty());
+    assert_eq!(&*resource.get("image/hero.png")?, b"base");
+    mods.set_enabled("hd", true)?;
+    mods.reorder("hd", 10)?;
+    assert_eq!(mods.plan().order, ["hd", "retro"]);
+    mods.apply();
+    resource.clear();
+    assert_eq!(&*resource.get("image/hero.png")?, b"retro");
+
+    // the mod's own scripts are sandboxed
+    let mut script = FoolScript::new(resource.clone())?;
+    mods.mount_scripts(&script);
+    script.setup()?;
+    let palette: i64 = script
+        .load("return require('mods.retro.init').palette")
+        .eval()?;
+    assert_eq!(palette, 4);
+    assert!(script.modules.mem_mod.is_untrusted("mods.retro.init"));
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use super::ModInfo;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+/// `major.minor.patch`, missing parts are 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u64, pub u64, pub u64);
+
+impl Version {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut parts = [0u64; 3];
+        for (count, part) in s.trim().split('.').enumerate() {
+            if count == 3 {
+                anyhow::bail!("version {} has more than 3 parts", s);
+            }
+            parts[count] = part
+                .parse()
+                .map_err(|_| anyhow::anyhow!("version {} is not major.minor.patch", s))?;
+        }
+        Ok(Self(parts[0], parts[1], parts[2]))
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// `id` or `id >= version`, as listed in `dependencies` of mod.toml
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub id: String,
+    pub min_version: Option<Version>,
+}
+
+impl Dependency {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s.split_once(">=") {
+            Some((id, version)) => Ok(Self {
+                id: id.trim().to_owned(),
+                min_version: Some(Version::parse(version)?),
+            }),
+            None if s.contains(['<', '>', '=']) => {
+                anyhow::bail!("dependency {} is not `id` or `id >= version`", s)
+            }
+            None => Ok(Self {
+                id: s.trim().to_owned(),
+                min_version: None,
+            }),
+        }
+    }
+}
+
+/// what loads and in which order, with why the rest doesn't
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadPlan {
+    // lowest priority first, a later mod wins over the ones before it
+    pub order: Vec<String>,
+    // mod id -> the problems that keep it from loading
+    pub errors: HashMap<String, Vec<String>>,
+}
+
+impl LoadPlan {
+    fn reject(&mut self, id: &str, err: impl Into<String>) {
+        self.errors
+            .entry(id.to_owned())
+            .or_default()
+            .push(err.into());
+    }
+}
+
+/// orders the enabled mods by `preferred`, moving a mod after its dependencies,
+/// mods with missing or too old dependencies, a too new `min_engine_version`
+/// or a dependency cycle are left out, and so is whatever depends on them
+pub fn load_order(
+    mods: &[ModInfo],
+    enabled: &HashSet<String>,
+    preferred: &[String],
+    engine_version: Version,
+) -> LoadPlan {
+    let mut plan = LoadPlan::default();
+    let by_id: HashMap<&str, &ModInfo> = mods.iter().map(|m| (m.id.as_str(), m)).collect();
+    let rank = |id: &str| preferred.iter().position(|p| p == id).unwrap_or(usize::MAX);
+    let mut candidates: Vec<&ModInfo> = mods.iter().filter(|m| enabled.contains(&m.id)).collect();
+    candidates.sort_by(|a, b| rank(&a.id).cmp(&rank(&b.id)).then(a.id.cmp(&b.id)));
+
+    let mut deps: HashMap<&str, Vec<Dependency>> = HashMap::new();
+    for info in &candidates {
+        if let Some(min) = &info.min_engine_version {
+            match Version::parse(min) {
+                Ok(min) if min > engine_version => plan.reject(
+                    &info.id,
+                    format!("needs engine {}, this is {}", min, engine_version),
+                ),
+                Ok(_) => {}
+                Err(err) => plan.reject(&info.id, err.to_string()),
+            }
+        }
+        if let Err(err) = Version::parse(&info.version) {
+            plan.reject(&info.id, err.to_string());
+        }
+        let mut parsed = Vec::new();
+        for dep in &info.dependencies {
+            match Dependency::parse(dep) {
+                Ok(dep) => parsed.push(dep),
+                Err(err) => plan.reject(&info.id, err.to_string()),
+            }
+        }
+        for dep in &parsed {
+            let Some(found) = by_id.get(dep.id.as_str()) else {
+                plan.reject(
+                    &info.id,
+                    format!("depends on {}, which is not installed", dep.id),
+                );
+                continue;
+            };
+            if !enabled.contains(&dep.id) {
+                plan.reject(
+                    &info.id,
+                    format!("depends on {}, which is disabled", dep.id),
+                );
+            }
+            if let (Some(min), Ok(version)) = (dep.min_version, Version::parse(&found.version)) {
+                if version < min {
+                    plan.reject(
+                        &info.id,
+                        format!("depends on {} >= {}, found {}", dep.id, min, version),
+                    );
+                }
+            }
+        }
+        deps.insert(info.id.as_str(), parsed);
+    }
+
+    // the preferred order wherever the dependencies allow it
+    let mut remaining: Vec<&str> = candidates.iter().map(|m| m.id.as_str()).collect();
+    let mut placed: HashSet<&str> = HashSet::new();
+    loop {
+        // a rejected mod takes whatever depends on it along
+        let mut rejected = Vec::new();
+        for id in remaining
+            .iter()
+            .filter(|id| !plan.errors.contains_key(**id))
+        {
+            if let Some(dep) = deps[id]
+                .iter()
+                .find(|dep| plan.errors.contains_key(&dep.id))
+            {
+                rejected.push((*id, dep.id.clone()));
+            }
+        }
+        for (id, dep) in &rejected {
+            plan.reject(id, format!("depends on {}, which can't load", dep));
+        }
+        remaining.retain(|id| !plan.errors.contains_key(*id));
+        let next = remaining
+            .iter()
+            .position(|id| deps[id].iter().all(|dep| placed.contains(dep.id.as_str())));
+        match next {
+            Some(index) => {
+                let id = remaining.remove(index);
+                placed.insert(id);
+                plan.order.push(id.to_owned());
+            }
+            None if !rejected.is_empty() => continue,
+            None if remaining.is_empty() => break,
+            None => {
+                let cycle = find_cycle(&remaining, &deps);
+                let path = cycle.join(" -> ");
+                for id in &cycle[1..] {
+                    plan.reject(id, format!("dependency cycle {}", path));
+                }
+            }
+        }
+    }
+    plan
+}
+
+// every mod left waits on another one left, following the first such
+// dependency has to come back to a mod already visited
+fn find_cycle(remaining: &[&str], deps: &HashMap<&str, Vec<Dependency>>) -> Vec<String> {
+    let mut path: Vec<&str> = vec![remaining[0]];
+    loop {
+        let current = path[path.len() - 1];
+        let next = deps[current]
+            .iter()
+            .map(|dep| dep.id.as_str())
+            .find(|id| remaining.contains(id))
+            .unwrap_or(current);
+        if let Some(start) = path.iter().position(|id| *id == next) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|id| id.to_string()).collect();
+            cycle.push(next.to_owned());
+            return cycle;
+        }
+        path.push(next);
+    }
+}
+
+#[test]
+fn test_load_order() {
+    let info = |id: &str, version: &str, deps: &[&str]| ModInfo {
+        id: id.to_owned(),
+        name: id.to_owned(),
+        version: version.to_owned(),
+        author: String::new(),
+        dependencies: deps.iter().map(|d| d.to_string()).collect(),
+        min_engine_version: None,
+    };
+    let mut mods = vec![
+        info("ui", "1.0", &["lib >= 1.2"]),
+        info("lib", "1.2.1", &[]),
+        info("maps", "0.3", &["lib"]),
+        info("a", "1", &["b"]),
+        info("b", "1", &["c"]),
+        info("c", "1", &["a"]),
+        info("needs_c", "1", &["c"]),
+        info("old", "1", &["lib >= 2"]),
+    ];
+    mods.push(ModInfo {
+        min_engine_version: Some("99.0".into()),
+        ..info("future", "1", &[])
+    });
+    let enabled: HashSet<String> = mods.iter().map(|m| m.id.clone()).collect();
+    let preferred: Vec<String> = ["ui", "maps", "lib"].map(String::from).into();
+    let plan = load_order(&mods, &enabled, &preferred, Version(0, 1, 0));
+    // the player wants ui first, it still loads after the lib it needs
+    assert_eq!(plan.order, ["lib", "ui", "maps"]);
+    assert!(
+        plan.errors["a"][0].contains("dependency cycle"),
+        "{:?}",
+        plan.errors
+    );
+    assert!(plan.errors["needs_c"][0].contains("c, which can't load"));
+    assert!(plan.errors["old"][0].contains("lib >= 2.0.0, found 1.2.1"));
+    assert!(plan.errors["future"][0].contains("needs engine 99.0.0"));
+
+    let enabled: HashSet<String> = ["ui", "maps"].map(String::from).into();
+    let plan = load_order(&mods, &enabled, &preferred, Version(0, 1, 0));
+    assert!(plan.order.is_empty());
+    assert!(plan.errors["ui"][0].contains("lib, which is disabled"));
+    assert!(Dependency::parse("lib < 2").is_err());
+    assert_eq!(Version::parse("1.2").unwrap(), Version(1, 2, 0));
+}
@@ -0,0 +1,383 @@
+use super::NetOptions;
+use bson::Bson;
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+// how long a receive waits, the thread checks its commands in between
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(200);
+// a datagram never comes close to this
+const MAX_PACKET: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Packet {
+    Hello {
+        version: u32,
+    },
+    Welcome {
+        version: u32,
+        delay: u64,
+        hash_interval: u64,
+    },
+    /// local inputs from frame `first` on, `ack` is the first remote frame still missing
+    Inputs {
+        first: u64,
+        ack: u64,
+        inputs: Vec<Bson>,
+    },
+    Hash {
+        frame: u64,
+        hash: i64,
+    },
+    Ping,
+    Bye,
+}
+
+impl Packet {
+    pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bson::to_vec(self)?)
+    }
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        Ok(bson::from_slice(data)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Role {
+    Host { port: u16 },
+    Join { addr: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetEvent {
+    Connected,
+    Packet(Packet),
+    Disconnected(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Send(Packet),
+    Close,
+}
+
+/// the main loop side of the network thread, both queues never block
+#[derive(Debug)]
+pub struct LinkHandle {
+    pub commands: Sender<Command>,
+    pub events: Receiver<NetEvent>,
+    /// the bound address, for hosting on port 0
+    pub local_addr: Option<SocketAddr>,
+}
+
+enum Pending {
+    Udp(UdpSocket, Option<SocketAddr>),
+    TcpListen(TcpListener),
+    TcpConnect(SocketAddr),
+}
+
+enum Link {
+    Udp {
+        socket: UdpSocket,
+        peer: Option<SocketAddr>,
+    },
+    Tcp {
+        stream: TcpStream,
+        buffer: Vec<u8>,
+    },
+}
+
+impl Link {
+    fn send(&mut self, packet: &Packet) -> anyhow::Result<()> {
+        let data = packet.encode()?;
+        match self {
+            Self::Udp { socket, peer } => {
+                if let Some(peer) = peer {
+                    socket.send_to(&data, *peer)?;
+                }
+            }
+            Self::Tcp { stream, .. } => {
+                stream.write_all(&(data.len() as u32).to_le_bytes())?;
+                stream.write_all(&data)?;
+            }
+        }
+        Ok(())
+    }
+    /// None when nothing arrived within `POLL_INTERVAL`, garbage is skipped
+    fn recv(&mut self) -> anyhow::Result<Option<Packet>> {
+        let timeout = |err: &std::io::Error| {
+            matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+        };
+        match self {
+            Self::Udp { socket, peer } => {
+                let mut data = vec![0; MAX_PACKET];
+                let (len, from) = match socket.recv_from(&mut data) {
+                    Ok(received) => received,
+                    Err(err) if timeout(&err) => return Ok(None),
+                    // a closed port on the other side shows up as a reset on some systems
+                    Err(err) if err.kind() == ErrorKind::ConnectionReset => return Ok(None),
+                    Err(err) => return Err(err.into()),
+                };
+                let packet = match Packet::decode(&data[..len]) {
+                    Ok(packet) => packet,
+                    Err(err) => {
+                        log::debug!("netplay drop packet from {}: {}", from, err);
+                        return Ok(None);
+                    }
+                };
+                match peer {
+                    Some(peer) if *peer != from => Ok(None),
+                    Some(_) => Ok(Some(packet)),
+                    // the host takes the first player that says hello
+                    None => {
+                        if !matches!(packet, Packet::Hello { .. }) {
+                            return Ok(None);
+                        }
+                        *peer = Some(from);
+                        Ok(Some(packet))
+                    }
+                }
+            }
+            Self::Tcp { stream, buffer } => {
+                if buffer.len() < 4 {
+                    let mut data = [0; 4096];
+                    match stream.read(&mut data) {
+                        Ok(0) => return Err(anyhow::anyhow!("connection closed")),
+                        Ok(len) => buffer.extend_from_slice(&data[..len]),
+                        Err(err) if timeout(&err) => return Ok(None),
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                let Some(len) = buffer.get(..4) else {
+                    return Ok(None);
+                };
+                let len = u32::from_le_bytes(len.try_into()?) as usize;
+                if len > MAX_PACKET {
+                    return Err(anyhow::anyhow!("packet of {} bytes is too large", len));
+                }
+                if buffer.len() < 4 + len {
+                    let mut data = [0; 4096];
+                    match stream.read(&mut data) {
+                        Ok(0) => return Err(anyhow::anyhow!("connection closed")),
+                        Ok(read) => buffer.extend_from_slice(&data[..read]),
+                        Err(err) if timeout(&err) => {}
+                        Err(err) => return Err(err.into()),
+                    }
+                    if buffer.len() < 4 + len {
+                        return Ok(None);
+                    }
+                }
+                let packet = Packet::decode(&buffer[4..4 + len]);
+                buffer.drain(..4 + len);
+                Ok(packet.ok())
+            }
+        }
+    }
+}
+
+fn resolve(addr: &str) -> anyhow::Result<SocketAddr> {
+    addr.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no address for {}", addr))
+}
+
+/// binds right away so a taken port fails the call, the rest happens on the thread
+pub fn spawn(role: Role, options: NetOptions) -> anyhow::Result<LinkHandle> {
+    let pending = match (&role, options.tcp) {
+        (Role::Host { port }, false) => Pending::Udp(UdpSocket::bind(("0.0.0.0", *port))?, None),
+        (Role::Join { addr }, false) => {
+            let peer = resolve(addr)?;
+            let unspecified = match peer {
+                SocketAddr::V4(_) => "0.0.0.0:0",
+                SocketAddr::V6(_) => "[::]:0",
+            };
+            Pending::Udp(UdpSocket::bind(unspecified)?, Some(peer))
+        }
+        (Role::Host { port }, true) => Pending::TcpListen(TcpListener::bind(("0.0.0.0", *port))?),
+        (Role::Join { addr }, true) => Pending::TcpConnect(resolve(addr)?),
+    };
+    let local_addr = match &pending {
+        Pending::Udp(socket, _) => socket.local_addr().ok(),
+        Pending::TcpListen(listener) => listener.local_addr().ok(),
+        Pending::TcpConnect(_) => None,
+    };
+    let (commands, command_rx) = crossbeam_channel::unbounded();
+    let (event_tx, events) = crossbeam_channel::unbounded();
+    let host = matches!(role, Role::Host { .. });
+    std::thread::Builder::new()
+        .name("netplay".to_owned())
+        .spawn(move || {
+            let result = connect(pending, options, &command_rx)
+                .and_then(|link| run(link, host, options, &command_rx, &event_tx));
+            if let Err(err) = result {
+                log::warn!("netplay stopped: {}", err);
+                let _ = event_tx.send(NetEvent::Disconnected(err.to_string()));
+            }
+        })?;
+    Ok(LinkHandle {
+        commands,
+        events,
+        local_addr,
+    })
+}
+
+fn connect(
+    pending: Pending,
+    options: NetOptions,
+    commands: &Receiver<Command>,
+) -> anyhow::Result<Link> {
+    let stream = match pending {
+        Pending::Udp(socket, peer) => {
+            socket.set_read_timeout(Some(POLL_INTERVAL))?;
+            return Ok(Link::Udp { socket, peer });
+        }
+        Pending::TcpConnect(addr) => TcpStream::connect_timeout(&addr, options.timeout())?,
+        Pending::TcpListen(listener) => {
+            listener.set_nonblocking(true)?;
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => break stream,
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => return Err(err.into()),
+                }
+                if !matches!(commands.try_recv(), Err(TryRecvError::Empty)) {
+                    return Err(anyhow::anyhow!("closed while waiting for a player"));
+                }
+                std::thread::sleep(HELLO_INTERVAL / 10);
+            }
+        }
+    };
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    stream.set_write_timeout(Some(options.timeout()))?;
+    Ok(Link::Tcp {
+        stream,
+        buffer: Vec::new(),
+    })
+}
+
+fn run(
+    mut link: Link,
+    host: bool,
+    options: NetOptions,
+    commands: &Receiver<Command>,
+    events: &Sender<NetEvent>,
+) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let mut connected = false;
+    let mut last_recv = Instant::now();
+    let mut last_send: Option<Instant> = None;
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Send(packet)) if connected => {
+                    link.send(&packet)?;
+                    last_send = Some(Instant::now());
+                }
+                Ok(Command::Send(_)) => {}
+                Err(TryRecvError::Empty) => break,
+                // closed or the main loop dropped the session
+                Ok(Command::Close) | Err(TryRecvError::Disconnected) => {
+                    if connected {
+                        let _ = link.send(&Packet::Bye);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        let idle = last_send.is_none_or(|sent| sent.elapsed() >= KEEPALIVE_INTERVAL);
+        if connected && idle {
+            link.send(&Packet::Ping)?;
+            last_send = Some(Instant::now());
+        } else if !connected && !host && last_send.is_none_or(|s| s.elapsed() >= HELLO_INTERVAL) {
+            link.send(&Packet::Hello {
+                version: PROTOCOL_VERSION,
+            })?;
+            last_send = Some(Instant::now());
+        }
+        if let Some(packet) = link.recv()? {
+            last_recv = Instant::now();
+            match packet {
+                Packet::Hello { version } if host => {
+                    if version != PROTOCOL_VERSION {
+                        let _ = link.send(&Packet::Bye);
+                        return Err(anyhow::anyhow!("peer speaks protocol {}", version));
+                    }
+                    // resent for every hello in case the first welcome got lost
+                    link.send(&Packet::Welcome {
+                        version: PROTOCOL_VERSION,
+                        delay: options.delay,
+                        hash_interval: options.hash_interval,
+                    })?;
+                    if !connected {
+                        connected = true;
+                        events.send(NetEvent::Connected)?;
+                    }
+                }
+                Packet::Welcome { version, .. } if !host && !connected => {
+                    if version != PROTOCOL_VERSION {
+                        return Err(anyhow::anyhow!("host speaks protocol {}", version));
+                    }
+                    connected = true;
+                    events.send(NetEvent::Packet(packet))?;
+                    events.send(NetEvent::Connected)?;
+                }
+                Packet::Bye => {
+                    events.send(NetEvent::Disconnected("peer left".to_owned()))?;
+                    return Ok(());
+                }
+                Packet::Inputs { .. } | Packet::Hash { .. } if connected => {
+                    events.send(NetEvent::Packet(packet))?;
+                }
+                _ => {}
+            }
+        }
+        if connected && last_recv.elapsed() > options.timeout() {
+            return Err(anyhow::anyhow!("peer timed out"));
+        }
+        // the host waits for a player as long as it takes
+        if !connected && !host && start.elapsed() > options.timeout() {
+            return Err(anyhow::anyhow!("no answer from the host"));
+        }
+    }
+}
+
+#[test]
+fn test_udp_link() -> anyhow::Result<()> {
+    let options = NetOptions::default();
+    let host = spawn(Role::Host { port: 0 }, options)?;
+    let port = host.local_addr.unwrap().port();
+    let join = spawn(
+        Role::Join {
+            addr: format!("127.0.0.1:{}", port),
+        },
+        options,
+    )?;
+    let wait = Duration::from_secs(2);
+    assert_eq!(host.events.recv_timeout(wait)?, NetEvent::Connected);
+    assert!(matches!(
+        join.events.recv_timeout(wait)?,
+        NetEvent::Packet(Packet::Welcome { delay: 2, .. })
+    ));
+    assert_eq!(join.events.recv_timeout(wait)?, NetEvent::Connected);
+    let inputs = Packet::Inputs {
+        first: 2,
+        ack: 2,
+        inputs: vec![Bson::Int64(3)],
+    };
+    join.commands.send(Command::Send(inputs.clone()))?;
+    assert_eq!(host.events.recv_timeout(wait)?, NetEvent::Packet(inputs));
+    join.commands.send(Command::Close)?;
+    assert_eq!(
+        host.events.recv_timeout(wait)?,
+        NetEvent::Disconnected("peer left".to_owned())
+    );
+    Ok(())
+}
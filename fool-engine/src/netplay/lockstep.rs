@@ -0,0 +1,172 @@
+use bson::Bson;
+use std::collections::BTreeMap;
+
+/// the state hashes of a frame differ between the players
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Desync {
+    pub frame: u64,
+    pub local: u64,
+    pub remote: u64,
+}
+
+/// the inputs of both players by frame, a frame is playable once both are in,
+/// late inputs stall the game instead of rolling it back
+#[derive(Debug, Clone)]
+pub struct Lockstep {
+    delay: u64,
+    hash_interval: u64,
+    local: BTreeMap<u64, Bson>,
+    remote: BTreeMap<u64, Bson>,
+    // the frame the next local input is for
+    next_local: u64,
+    // the first remote frame not received yet
+    next_remote: u64,
+    // the peer has every local input before this frame
+    acked: u64,
+    // the latest frame handed out, earlier inputs are dropped
+    played: u64,
+    // (local, remote) hash by frame until both are known
+    hashes: BTreeMap<u64, (Option<u64>, Option<u64>)>,
+}
+
+impl Lockstep {
+    /// `delay` frames pass between submitting an input and playing it,
+    /// the first `delay` frames are played without input
+    pub fn new(delay: u64, hash_interval: u64) -> Self {
+        Self {
+            delay,
+            hash_interval,
+            local: Default::default(),
+            remote: Default::default(),
+            next_local: delay,
+            next_remote: delay,
+            acked: delay,
+            played: 0,
+            hashes: Default::default(),
+        }
+    }
+    pub fn delay(&self) -> u64 {
+        self.delay
+    }
+    /// takes the settings of the host, only before any input was submitted
+    pub fn set_params(&mut self, delay: u64, hash_interval: u64) {
+        if self.local.is_empty() && self.next_local == self.delay {
+            *self = Self::new(delay, hash_interval);
+        }
+    }
+    /// queues the local input, returns the frame it plays on
+    pub fn submit(&mut self, input: Bson) -> u64 {
+        let frame = self.next_local;
+        self.local.insert(frame, input);
+        self.next_local += 1;
+        frame
+    }
+    /// (local, remote), None while either is missing
+    pub fn inputs(&mut self, frame: u64) -> Option<(Bson, Bson)> {
+        if frame < self.delay {
+            return Some((Bson::Null, Bson::Null));
+        }
+        let local = self.local.get(&frame)?.clone();
+        let remote = self.remote.get(&frame)?.clone();
+        self.played = self.played.max(frame);
+        self.prune();
+        Some((local, remote))
+    }
+    fn prune(&mut self) {
+        // unacknowledged inputs are kept for resending
+        let keep = self.played.min(self.acked);
+        self.local = self.local.split_off(&keep);
+        self.remote = self.remote.split_off(&self.played);
+    }
+    /// the local inputs the peer has not acknowledged, (first frame, inputs)
+    pub fn outgoing(&self, max: usize) -> (u64, Vec<Bson>) {
+        let inputs = self.local.range(self.acked..).take(max);
+        (self.acked, inputs.map(|(_, input)| input.clone()).collect())
+    }
+    /// the first remote frame still missing, what the peer resends from
+    pub fn ack(&self) -> u64 {
+        self.next_remote
+    }
+    pub fn receive(&mut self, first: u64, inputs: Vec<Bson>, ack: u64) {
+        self.acked = self.acked.max(ack.min(self.next_local));
+        for (frame, input) in (first..).zip(inputs) {
+            if frame >= self.next_remote {
+                self.remote.insert(frame, input);
+            }
+        }
+        while self.remote.contains_key(&self.next_remote) {
+            self.next_remote += 1;
+        }
+        self.prune();
+    }
+    /// whether the state hash after `frame` is due and not taken yet
+    pub fn wants_hash(&self, frame: u64) -> bool {
+        self.hash_interval > 0
+            && frame > 0
+            && frame.is_multiple_of(self.hash_interval)
+            && self
+                .hashes
+                .get(&frame)
+                .is_none_or(|(local, _)| local.is_none())
+    }
+    pub fn local_hash(&mut self, frame: u64, hash: u64) -> Option<Desync> {
+        self.hashes.entry(frame).or_default().0 = Some(hash);
+        self.compare(frame)
+    }
+    pub fn remote_hash(&mut self, frame: u64, hash: u64) -> Option<Desync> {
+        self.hashes.entry(frame).or_default().1 = Some(hash);
+        self.compare(frame)
+    }
+    fn compare(&mut self, frame: u64) -> Option<Desync> {
+        let (Some(local), Some(remote)) = *self.hashes.get(&frame)? else {
+            return None;
+        };
+        self.hashes.remove(&frame);
+        (local != remote).then_some(Desync {
+            frame,
+            local,
+            remote,
+        })
+    }
+}
+
+#[test]
+fn test_lockstep() {
+    let input = |n: i64| Bson::Int64(n);
+    let (mut a, mut b) = (Lockstep::new(2, 4), Lockstep::new(2, 4));
+    // the delay frames play without input
+    assert_eq!(a.inputs(0), Some((Bson::Null, Bson::Null)));
+    assert_eq!(a.submit(input(1)), 2);
+    assert_eq!(a.submit(input(2)), 3);
+    b.submit(input(10));
+    // nothing from the peer yet, frame 2 stalls
+    assert_eq!(a.inputs(2), None);
+    let (first, inputs) = b.outgoing(64);
+    a.receive(first, inputs, b.ack());
+    assert_eq!(a.inputs(2), Some((input(1), input(10))));
+    assert_eq!(a.inputs(3), None);
+    // a lost packet is covered by the next one carrying everything unacknowledged
+    let _lost = a.outgoing(64);
+    b.submit(input(11));
+    let (first, inputs) = a.outgoing(64);
+    assert_eq!((first, inputs.len()), (2, 2));
+    b.receive(first, inputs.clone(), a.ack());
+    // a duplicate changes nothing
+    b.receive(first, inputs, a.ack());
+    assert_eq!(b.inputs(3), Some((input(11), input(2))));
+    let (first, inputs) = b.outgoing(64);
+    a.receive(first, inputs, b.ack());
+    assert_eq!(a.outgoing(64), (4, vec![]));
+    assert_eq!(a.inputs(3), Some((input(2), input(11))));
+
+    assert!(a.wants_hash(4) && !a.wants_hash(3));
+    assert_eq!(a.local_hash(4, 7), None);
+    assert!(!a.wants_hash(4));
+    assert_eq!(a.remote_hash(4, 7), None);
+    assert_eq!(a.remote_hash(8, 9), None);
+    let desync = a.local_hash(8, 1);
+    assert_eq!(
+        desync.map(|d| (d.frame, d.local, d.remote)),
+        Some((8, 1, 9))
+    );
+}
@@ -0,0 +1,406 @@
+use crate::{lua_create_table, map2anyhow_error, map2lua_error};
+use fool_script::modules::ser::{bson_to_lua_value, lua_value_to_bson};
+use link::{Command, LinkHandle, NetEvent, Packet, Role};
+use mlua::{Function, LuaSerdeExt, UserData, Value};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+pub mod link;
+mod lockstep;
+pub use lockstep::{Desync, Lockstep};
+
+// inputs per packet, more than the delay window ever holds unless the peer stalls
+const MAX_INPUTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct NetOptions {
+    /// frames between submitting an input and playing it, the host's value is used
+    pub delay: u64,
+    /// frames between state hash checks, 0 turns them off
+    pub hash_interval: u64,
+    /// tcp instead of udp, for networks that drop datagrams
+    pub tcp: bool,
+    /// the peer is dropped after this long without a packet
+    pub timeout_ms: u64,
+}
+impl Default for NetOptions {
+    fn default() -> Self {
+        Self {
+            delay: 2,
+            hash_interval: 60,
+            tcp: false,
+            timeout_ms: 5000,
+        }
+    }
+}
+impl NetOptions {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+impl ConnState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connecting => "connecting",
+            Self::Connected => "connected",
+            Self::Disconnected => "disconnected",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Notice {
+    State(ConnState, Option<String>),
+    Desync(Desync),
+}
+
+/// one peer, fed from the network thread once per frame
+#[derive(Debug)]
+pub struct Session {
+    lockstep: Lockstep,
+    link: LinkHandle,
+    state: ConnState,
+    notices: Vec<Notice>,
+}
+
+impl Session {
+    pub fn start(role: Role, options: NetOptions) -> anyhow::Result<Self> {
+        let link = link::spawn(role, options)?;
+        Ok(Self {
+            lockstep: Lockstep::new(options.delay, options.hash_interval),
+            link,
+            state: ConnState::Connecting,
+            notices: vec![Notice::State(ConnState::Connecting, None)],
+        })
+    }
+    fn set_state(&mut self, state: ConnState, reason: Option<String>) {
+        if self.state != state {
+            self.state = state;
+            self.notices.push(Notice::State(state, reason));
+        }
+    }
+    /// applies whatever the network thread received, never blocks
+    pub fn pump(&mut self) {
+        while let Ok(event) = self.link.events.try_recv() {
+            match event {
+                NetEvent::Connected => self.set_state(ConnState::Connected, None),
+                NetEvent::Disconnected(reason) => {
+                    self.set_state(ConnState::Disconnected, Some(reason))
+                }
+                NetEvent::Packet(Packet::Welcome {
+                    delay,
+                    hash_interval,
+                    ..
+                }) => self.lockstep.set_params(delay, hash_interval),
+                NetEvent::Packet(Packet::Inputs { first, ack, inputs }) => {
+                    self.lockstep.receive(first, inputs, ack)
+                }
+                NetEvent::Packet(Packet::Hash { frame, hash }) => {
+                    if let Some(desync) = self.lockstep.remote_hash(frame, hash as u64) {
+                        self.notices.push(Notice::Desync(desync));
+                    }
+                }
+                NetEvent::Packet(_) => {}
+            }
+        }
+    }
+    fn send(&self, packet: Packet) {
+        // the thread is gone once disconnected, that is reported through the events
+        let _ = self.link.commands.send(Command::Send(packet));
+    }
+    /// everything not acknowledged yet, so a lost packet costs one frame
+    pub fn send_inputs(&self) {
+        let (first, inputs) = self.lockstep.outgoing(MAX_INPUTS);
+        self.send(Packet::Inputs {
+            first,
+            ack: self.lockstep.ack(),
+            inputs,
+        });
+    }
+    pub fn local_hash(&mut self, frame: u64, hash: u64) {
+        self.send(Packet::Hash {
+            frame,
+            hash: hash as i64,
+        });
+        if let Some(desync) = self.lockstep.local_hash(frame, hash) {
+            self.notices.push(Notice::Desync(desync));
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.link.commands.send(Command::Close);
+    }
+}
+
+/// a two player lockstep session, at most one at a time
+#[derive(Clone, Default)]
+pub struct Netplay {
+    session: Arc<Mutex<Option<Session>>>,
+    on_state: Arc<Mutex<Option<Function>>>,
+    on_desync: Arc<Mutex<Option<Function>>>,
+    state_hash: Arc<Mutex<Option<Function>>>,
+}
+
+impl Netplay {
+    fn start(&self, role: Role, options: NetOptions) -> anyhow::Result<Option<u16>> {
+        let session = Session::start(role, options)?;
+        let port = session.link.local_addr.map(|addr| addr.port());
+        // replacing a session closes it
+        *self.session.lock() = Some(session);
+        Ok(port)
+    }
+    /// pumps the network events and runs the callbacks, once per frame
+    pub fn poll(&self) -> anyhow::Result<()> {
+        let notices = match self.session.lock().as_mut() {
+            Some(session) => {
+                session.pump();
+                std::mem::take(&mut session.notices)
+            }
+            None => return Ok(()),
+        };
+        // no lock is held, the callbacks may call back into netplay
+        for notice in notices {
+            match notice {
+                Notice::State(state, reason) => {
+                    let callback = self.on_state.lock().clone();
+                    if let Some(callback) = callback {
+                        map2anyhow_error!(
+                            callback.call::<()>((state.as_str(), reason)),
+                            "run netplay on_state callback"
+                        )?;
+                    }
+                }
+                Notice::Desync(desync) => {
+                    log::warn!("netplay desync at frame {}", desync.frame);
+                    let callback = self.on_desync.lock().clone();
+                    if let Some(callback) = callback {
+                        map2anyhow_error!(
+                            callback.call::<()>((
+                                desync.frame,
+                                format!("{:016x}", desync.local),
+                                format!("{:016x}", desync.remote)
+                            )),
+                            "run netplay on_desync callback"
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// asks the script for the state after `frame` when a check is due
+    fn check_hash(&self, frame: u64) -> mlua::Result<()> {
+        let due = self
+            .session
+            .lock()
+            .as_ref()
+            .is_some_and(|session| session.lockstep.wants_hash(frame));
+        let callback = self.state_hash.lock().clone();
+        let Some(callback) = callback.filter(|_| due) else {
+            return Ok(());
+        };
+        let hash = match callback.call::<Value>(frame)? {
+            Value::Integer(hash) => hash as u64,
+            Value::String(hash) => fnv1a(hash.as_bytes()),
+            other => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "state hash must be an integer or a string, got {}",
+                    other.type_name()
+                )));
+            }
+        };
+        if let Some(session) = self.session.lock().as_mut() {
+            session.local_hash(frame, hash);
+        }
+        Ok(())
+    }
+}
+
+/// 64 bit FNV-1a, the peer may be built with another toolchain so the
+/// algorithm can't be left to std
+fn fnv1a(bytes: impl AsRef<[u8]>) -> u64 {
+    bytes
+        .as_ref()
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn options(lua: &mlua::Lua, value: Option<Value>) -> mlua::Result<NetOptions> {
+    match value {
+        None | Some(Value::Nil) => Ok(NetOptions::default()),
+        Some(value) => lua.from_value(value),
+    }
+}
+
+impl UserData for Netplay {
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("host", |lua, this, (port, opts): (u16, Option<Value>)| {
+            let options = options(lua, opts)?;
+            map2lua_error!(this.start(Role::Host { port }, options), "netplay host")
+        });
+        methods.add_method(
+            "join",
+            |lua, this, (addr, opts): (String, Option<Value>)| {
+                let options = options(lua, opts)?;
+                map2lua_error!(this.start(Role::Join { addr }, options), "netplay join")?;
+                Ok(())
+            },
+        );
+        methods.add_method("close", |_, this, ()| {
+            this.session.lock().take();
+            Ok(())
+        });
+        methods.add_method("state", |_, this, ()| {
+            Ok(match this.session.lock().as_ref() {
+                Some(session) => session.state.as_str(),
+                None => "idle",
+            })
+        });
+        methods.add_method("delay", |_, this, ()| {
+            Ok(this
+                .session
+                .lock()
+                .as_ref()
+                .map(|session| session.lockstep.delay()))
+        });
+        methods.add_method("submit_local_input", |_, this, input: Value| {
+            let input = lua_value_to_bson(input)?;
+            let mut session = this.session.lock();
+            let Some(session) = session
+                .as_mut()
+                .filter(|session| session.state == ConnState::Connected)
+            else {
+                return Ok(None);
+            };
+            let frame = session.lockstep.submit(input);
+            session.send_inputs();
+            Ok(Some(frame))
+        });
+        methods.add_method("inputs_for_frame", |lua, this, frame: u64| {
+            // asked for the next frame, the one before is simulated by now
+            if let Some(played) = frame.checked_sub(1) {
+                this.check_hash(played)?;
+            }
+            let inputs = match this.session.lock().as_mut() {
+                Some(session) => {
+                    // a stalled frame is asked for again, keep the peer's view fresh
+                    session.pump();
+                    let inputs = session.lockstep.inputs(frame);
+                    // the last inputs sent may be lost and nothing new is submitted
+                    // while stalled, so they go out again until the peer has them
+                    if inputs.is_none() && session.state == ConnState::Connected {
+                        session.send_inputs();
+                    }
+                    inputs
+                }
+                None => None,
+            };
+            let Some((local, remote)) = inputs else {
+                return Ok(None);
+            };
+            let local = bson_to_lua_value(lua, &local)?;
+            let remote = bson_to_lua_value(lua, &remote)?;
+            Ok(Some(lua_create_table!(
+                lua,
+                [local = local, remote = remote]
+            )))
+        });
+        methods.add_method("on_state", |_, this, callback: Option<Function>| {
+            *this.on_state.lock() = callback;
+            Ok(())
+        });
+        methods.add_method("on_desync", |_, this, callback: Option<Function>| {
+            *this.on_desync.lock() = callback;
+            Ok(())
+        });
+        methods.add_method("set_state_hash", |_, this, callback: Option<Function>| {
+            *this.state_hash.lock() = callback;
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn test_stalled_resend() -> anyhow::Result<()> {
+    use std::time::Instant;
+    let lua = mlua::Lua::new();
+    let options = NetOptions {
+        delay: 1,
+        hash_interval: 1,
+        ..Default::default()
+    };
+    let (host, join) = (Netplay::default(), Netplay::default());
+    let port = host.start(Role::Host { port: 0 }, options)?.unwrap();
+    join.start(
+        Role::Join {
+            addr: format!("127.0.0.1:{}", port),
+        },
+        options,
+    )?;
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let connected = |netplay: &Netplay| {
+        let mut session = netplay.session.lock();
+        let session = session.as_mut().unwrap();
+        session.pump();
+        session.state == ConnState::Connected
+    };
+    while !(connected(&host) && connected(&join)) {
+        assert!(Instant::now() < deadline, "never connected");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    // both inputs for frame 1 are lost on the way
+    for netplay in [&host, &join] {
+        if let Some(session) = netplay.session.lock().as_mut() {
+            session.lockstep.submit(bson::Bson::Int64(1));
+        }
+    }
+    lua.globals().set("host", host.clone())?;
+    lua.globals().set("join", join.clone())?;
+    lua.load(
+        r#"
+        hashed = {}
+        host:set_state_hash(function(frame)
+            table.insert(hashed, frame)
+            return frame
+        end)
+        "#,
+    )
+    .exec()?;
+    let playable = |name: &str| {
+        lua.load(format!("return {}:inputs_for_frame(1) ~= nil", name))
+            .eval::<bool>()
+    };
+    let (mut host_ready, mut join_ready) = (false, false);
+    while !(host_ready && join_ready) {
+        assert!(Instant::now() < deadline, "stalled for good");
+        host_ready = host_ready || playable("host")?;
+        join_ready = join_ready || playable("join")?;
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    // frame 1 is hashed once it is played, when frame 2 is asked for
+    assert_eq!(lua.load("return #hashed").eval::<i64>()?, 0);
+    lua.load("host:inputs_for_frame(2)").exec()?;
+    assert_eq!(lua.load("return hashed[1]").eval::<i64>()?, 1);
+    Ok(())
+}
+
+#[test]
+fn test_fnv1a() {
+    // the published FNV-1a test vectors
+    assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+}
@@ -0,0 +1,151 @@
+use mlua::{Lua, LuaSerdeExt, Value};
+use std::collections::HashMap;
+
+/// json bytes the data of one user_data may take by default
+pub const DATA_LIMIT: usize = 4 * 1024;
+
+/// lua tables attached to bodies by their user_data, so collision handlers find the
+/// game data without packing it into the integer. Tables are deep copied in and out,
+/// changing one got back from `get_data` or an event changes nothing until it is set again
+#[derive(Debug)]
+pub struct PhysicsData {
+    tables: HashMap<u128, serde_json::Value>,
+    // keeps scripts from attaching megabytes to every bullet
+    limit: usize,
+}
+
+impl Default for PhysicsData {
+    fn default() -> Self {
+        Self {
+            tables: Default::default(),
+            limit: DATA_LIMIT,
+        }
+    }
+}
+
+impl PhysicsData {
+    /// only plain data, functions and userdata in the table are refused
+    pub fn set(&mut self, lua: &Lua, user_data: u128, table: Value) -> mlua::Result<()> {
+        let value: serde_json::Value = lua.from_value(table)?;
+        let size = serde_json::to_vec(&value)
+            .map_err(|err| mlua::Error::RuntimeError(err.to_string()))?
+            .len();
+        if size > self.limit {
+            return Err(mlua::Error::RuntimeError(format!(
+                "physics data of {} is {} bytes, more than the limit of {}",
+                user_data, size, self.limit
+            )));
+        }
+        self.tables.insert(user_data, value);
+        Ok(())
+    }
+    /// a fresh copy, nil when nothing is attached
+    pub fn get(&self, lua: &Lua, user_data: u128) -> mlua::Result<Value> {
+        match self.tables.get(&user_data) {
+            Some(value) => lua.to_value(value),
+            None => Ok(Value::Nil),
+        }
+    }
+    /// for the event bus, null when nothing is attached
+    pub fn get_bson(&self, user_data: u128) -> bson::Bson {
+        self.tables
+            .get(&user_data)
+            .and_then(|value| bson::to_bson(value).ok())
+            .unwrap_or(bson::Bson::Null)
+    }
+    pub fn remove(&mut self, user_data: u128) -> bool {
+        self.tables.remove(&user_data).is_some()
+    }
+    pub fn contains(&self, user_data: u128) -> bool {
+        self.tables.contains_key(&user_data)
+    }
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+    /// applies to the tables set from now on
+    pub fn set_limit(&mut self, bytes: usize) {
+        self.limit = bytes;
+    }
+    pub(crate) fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self.tables)
+    }
+    /// replaces all tables, the limit is kept
+    pub(crate) fn load_json(&mut self, json: &str) -> serde_json::Result<()> {
+        self.tables = serde_json::from_str(json)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_physics_data() -> anyhow::Result<()> {
+    use super::LuaPhysics;
+    let lua = mlua::Lua::new();
+    let physics = lua.create_userdata(LuaPhysics::new(0.0, 10.0))?;
+    lua.globals().set("phy", &physics)?;
+    lua.load(
+        r#"
+        ground = phy:add_body({
+            user_data = 1,
+            position = { x = 0, y = 10 },
+            shape = { Cuboid = { width = 40, height = 2 } },
+            body_type = "Fixed",
+        })
+        bullet = phy:add_body({
+            user_data = 2,
+            position = { x = 0, y = 5 },
+            shape = { Ball = { radius = 1 } },
+            body_type = "Dynamic",
+            active_events = "collision_events",
+        })
+        phy:set_data(ground, { kind = "wall" })
+        -- by user_data works the same
+        phy:set_data(2, { kind = "bullet", damage = 3 })
+        local copy = phy:get_data(bullet)
+        copy.damage = 100
+        hits = {}
+        phy:register_collision_event_callback(function(event)
+            if event.kind == "started" then
+                local walls = { event.data1.kind, event.data2.kind }
+                table.sort(walls)
+                table.insert(hits, walls[1] .. "," .. walls[2])
+                local bullet_data = event.data1.damage and event.data1 or event.data2
+                damage = bullet_data.damage
+            end
+        end)
+        for _ = 1, 120 do
+            phy:update()
+            phy:event_update()
+            if #hits > 0 then break end
+        end
+        phy:remove_body(bullet)
+        "#,
+    )
+    .exec()?;
+    let hits: Vec<String> = lua.globals().get("hits")?;
+    assert_eq!(hits, ["bullet,wall"]);
+    // deep copied, changing the copy left the stored table alone
+    assert_eq!(lua.globals().get::<i64>("damage")?, 3);
+    {
+        // removing the bullet took its data along
+        let this = physics.borrow::<LuaPhysics>()?;
+        assert!(!this.data.contains(2) && this.data.contains(1));
+    }
+    assert!(lua.load("return phy:get_data(2)").eval::<Value>()?.is_nil());
+    // attached to a removed body is refused, by user_data it is still allowed
+    assert!(lua.load("phy:set_data(bullet, {})").exec().is_err());
+    assert!(lua.load("phy:remove_data(1)").eval::<bool>()?);
+
+    // the size guard and plain data only
+    lua.load("phy:set_data_limit(64)").exec()?;
+    assert!(lua
+        .load("phy:set_data(1, { name = string.rep('x', 100) })")
+        .exec()
+        .is_err());
+    assert!(lua.load("phy:set_data(1, { f = print })").exec().is_err());
+    lua.load("phy:set_data(1, { name = 'ok' })").exec()?;
+    assert_eq!(physics.borrow::<LuaPhysics>()?.data.len(), 1);
+    Ok(())
+}
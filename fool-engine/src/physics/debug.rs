@@ -0,0 +1,224 @@
+use super::LuaPhysics;
+use rapier2d::pipeline::{
+    DebugColor, DebugRenderBackend, DebugRenderMode, DebugRenderObject, DebugRenderPipeline,
+    DebugRenderStyle,
+};
+use rapier2d::prelude::*;
+use serde::Deserialize;
+use vello::kurbo::{Affine, BezPath, Stroke};
+use vello::peniko::Color;
+use vello::Scene;
+
+/// what `physics:debug_draw` shows, everything off by default
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DebugDrawOptions {
+    pub colliders: bool,
+    pub aabbs: bool,
+    pub contacts: bool,
+    pub joints: bool,
+    /// dims the bodies that are asleep
+    pub sleep: bool,
+    /// in screen pixels, the same at any camera zoom
+    pub line_width: f64,
+}
+
+impl Default for DebugDrawOptions {
+    fn default() -> Self {
+        Self {
+            colliders: false,
+            aabbs: false,
+            contacts: false,
+            joints: false,
+            sleep: false,
+            line_width: 1.0,
+        }
+    }
+}
+
+impl DebugDrawOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.colliders || self.aabbs || self.contacts || self.joints
+    }
+    fn mode(&self) -> DebugRenderMode {
+        let mut mode = DebugRenderMode::empty();
+        mode.set(DebugRenderMode::COLLIDER_SHAPES, self.colliders);
+        mode.set(DebugRenderMode::COLLIDER_AABBS, self.aabbs);
+        mode.set(DebugRenderMode::CONTACTS, self.contacts);
+        mode.set(DebugRenderMode::JOINTS, self.joints);
+        mode
+    }
+    fn style(&self) -> DebugRenderStyle {
+        let mut style = DebugRenderStyle::default();
+        if !self.sleep {
+            style.sleep_color_multiplier = [1.0; 4];
+        }
+        style
+    }
+    /// None while nothing is shown, so a world that never draws never builds one
+    pub(super) fn pipeline(&self) -> Option<DebugRenderPipeline> {
+        self.is_enabled()
+            .then(|| DebugRenderPipeline::new(self.style(), self.mode()))
+    }
+}
+
+/// rapier colors are hsla
+fn to_color([h, s, l, a]: DebugColor) -> Color {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    Color::new([r + m, g + m, b + m, a])
+}
+
+/// collects the segments rapier's debug pipeline emits, in world space
+#[derive(Debug, Default)]
+pub struct PhysicsDebugRenderer {
+    // runs of segments sharing a color, one stroke each
+    runs: Vec<(DebugColor, BezPath)>,
+    segments: usize,
+}
+
+impl DebugRenderBackend for PhysicsDebugRenderer {
+    fn draw_line(
+        &mut self,
+        _object: DebugRenderObject,
+        a: Point<Real>,
+        b: Point<Real>,
+        color: DebugColor,
+    ) {
+        let path = match self.runs.last_mut() {
+            Some((last, path)) if *last == color => path,
+            _ => {
+                self.runs.push((color, BezPath::new()));
+                &mut self.runs.last_mut().unwrap().1
+            }
+        };
+        path.move_to((a.x as f64, a.y as f64));
+        path.line_to((b.x as f64, b.y as f64));
+        self.segments += 1;
+    }
+}
+
+impl PhysicsDebugRenderer {
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+    /// strokes the segments with the world to screen `transform`,
+    /// the width is divided by its zoom to stay `line_width` pixels wide
+    pub fn draw(&self, scene: &mut Scene, transform: Affine, line_width: f64) {
+        let zoom = transform.determinant().abs().sqrt();
+        if zoom <= f64::EPSILON {
+            return;
+        }
+        let stroke = Stroke::new(line_width / zoom);
+        for (color, path) in &self.runs {
+            scene.stroke(&stroke, transform, to_color(*color), None, path);
+        }
+    }
+}
+
+impl LuaPhysics {
+    /// collects the enabled categories, false without any
+    pub fn debug_render(&self, renderer: &mut PhysicsDebugRenderer) -> bool {
+        let mut pipeline = self.debug_pipeline.lock();
+        let Some(pipeline) = pipeline.as_mut() else {
+            return false;
+        };
+        let physics = &self.physics;
+        pipeline.render(
+            renderer,
+            &physics.bodies,
+            &physics.colliders,
+            &physics.impulse_joints,
+            &physics.multibody_joints,
+            &physics.narrow_phase,
+        );
+        true
+    }
+    pub fn debug_draw_options(&self) -> DebugDrawOptions {
+        self.debug_draw
+    }
+    pub fn set_debug_draw(&mut self, options: DebugDrawOptions) {
+        *self.debug_pipeline.get_mut() = options.pipeline();
+        self.debug_draw = options;
+    }
+    /// appends the overlay for the enabled categories, nothing while all are off
+    pub fn draw_debug(&self, scene: &mut Scene, transform: Affine) {
+        if !self.debug_draw.is_enabled() {
+            return;
+        }
+        let mut renderer = PhysicsDebugRenderer::default();
+        if self.debug_render(&mut renderer) {
+            renderer.draw(scene, transform, self.debug_draw.line_width);
+        }
+    }
+}
+
+#[test]
+fn test_debug_render() -> anyhow::Result<()> {
+    let lua = mlua::Lua::new();
+    let physics = lua.create_userdata(LuaPhysics::new(0.0, 10.0))?;
+    lua.globals().set("phy", &physics)?;
+    lua.load(
+        r#"
+        phy:add_body({
+            user_data = 1,
+            position = { x = 0, y = 10 },
+            shape = { Cuboid = { width = 40, height = 2 } },
+            body_type = "Fixed",
+        })
+        for i = 1, 3 do
+            phy:add_body({
+                user_data = i + 1,
+                position = { x = i * 4 - 8, y = 8 },
+                shape = { Ball = { radius = 1 } },
+                body_type = "Dynamic",
+            })
+        end
+        for _ = 1, 30 do phy:update() end
+        "#,
+    )
+    .exec()?;
+    let segments = |lua_options: &str| -> anyhow::Result<usize> {
+        lua.load(format!("phy:debug_draw{}", lua_options)).exec()?;
+        let mut renderer = PhysicsDebugRenderer::default();
+        physics.borrow::<LuaPhysics>()?.debug_render(&mut renderer);
+        Ok(renderer.segments())
+    };
+    assert_eq!(segments("{}")?, 0);
+    // a box outline and three ball outlines
+    let colliders = segments("{colliders=true}")?;
+    let outlines = 4 + 3 * 8..=4 + 3 * 64;
+    assert!(outlines.contains(&colliders), "{}", colliders);
+    assert_eq!(segments("{aabbs=true}")?, 4 * 4);
+    // the balls rest on the ground by now
+    let contacts = segments("{contacts=true}")?;
+    assert!(contacts >= 3, "{}", contacts);
+    assert_eq!(
+        segments("{colliders=true, aabbs=true, contacts=true}")?,
+        colliders + 4 * 4 + contacts
+    );
+    assert!(lua.load("phy:debug_draw{collider=true}").exec().is_err());
+
+    let mut scene = Scene::new();
+    let this = physics.borrow::<LuaPhysics>()?;
+    this.draw_debug(&mut scene, Affine::scale(20.0));
+    assert!(scene.encoding().n_paths > 0);
+    drop(this);
+    lua.load("phy:debug_draw{}").exec()?;
+    let mut scene = Scene::new();
+    physics
+        .borrow::<LuaPhysics>()?
+        .draw_debug(&mut scene, Affine::scale(20.0));
+    assert_eq!(scene.encoding().n_paths, 0);
+    Ok(())
+}
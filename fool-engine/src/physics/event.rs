@@ -1,5 +1,6 @@
-use super::LuaRigidBodyHandle;
-use mlua::{FromLua, LuaSerdeExt, UserData, Value};
+use super::{LuaPoint, LuaRigidBodyHandle, PhysicsData};
+use bson::{doc, Bson};
+use mlua::{AnyUserData, FromLua, LuaSerdeExt, UserData, Value};
 use parking_lot::Mutex;
 use rapier2d::{
     geometry::CollisionEventFlags,
@@ -9,19 +10,59 @@ use rapier2d::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+/// where two solid colliders touch, in world space
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LuaContact {
+    /// from the first body towards the second
+    pub normal: LuaPoint<f32>,
+    /// on the first body's surface
+    pub points: Vec<LuaPoint<f32>>,
+}
+
+impl LuaContact {
+    /// None for sensors and pairs not touching yet
+    fn from_pair(colliders: &ColliderSet, pair: &ContactPair) -> Option<Self> {
+        let collider = colliders.get(pair.collider1)?;
+        let manifold = pair
+            .manifolds
+            .iter()
+            .find(|manifold| !manifold.points.is_empty())?;
+        let normal = manifold.data.normal;
+        let points = manifold
+            .points
+            .iter()
+            .map(|point| LuaPoint::from(collider.position() * point.local_p1))
+            .collect();
+        Some(Self {
+            normal: LuaPoint {
+                x: normal.x,
+                y: normal.y,
+            },
+            points,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum LuaCollisionEvent {
     Started {
         b1: LuaRigidBodyHandle,
         b2: LuaRigidBodyHandle,
         sensor: bool,
         removed: bool,
+        #[serde(default)]
+        contact: Option<LuaContact>,
+        // of the two colliders, the bodies may be gone by the time it is handled
+        #[serde(skip)]
+        user_data: (u128, u128),
     },
     Stopped {
         b1: LuaRigidBodyHandle,
         b2: LuaRigidBodyHandle,
         sensor: bool,
         removed: bool,
+        #[serde(skip)]
+        user_data: (u128, u128),
     },
 }
 impl FromLua for LuaCollisionEvent {
@@ -30,14 +71,91 @@ impl FromLua for LuaCollisionEvent {
     }
 }
 
+impl LuaCollisionEvent {
+    pub fn bodies(&self) -> (LuaRigidBodyHandle, LuaRigidBodyHandle) {
+        match self {
+            LuaCollisionEvent::Started { b1, b2, .. }
+            | LuaCollisionEvent::Stopped { b1, b2, .. } => (*b1, *b2),
+        }
+    }
+    pub fn is_started(&self) -> bool {
+        matches!(self, LuaCollisionEvent::Started { .. })
+    }
+    pub fn is_sensor(&self) -> bool {
+        match self {
+            LuaCollisionEvent::Started { sensor, .. }
+            | LuaCollisionEvent::Stopped { sensor, .. } => *sensor,
+        }
+    }
+    pub fn is_removed(&self) -> bool {
+        match self {
+            LuaCollisionEvent::Started { removed, .. }
+            | LuaCollisionEvent::Stopped { removed, .. } => *removed,
+        }
+    }
+    pub fn user_data(&self) -> (u128, u128) {
+        match self {
+            LuaCollisionEvent::Started { user_data, .. }
+            | LuaCollisionEvent::Stopped { user_data, .. } => *user_data,
+        }
+    }
+    /// only for starting contacts between solid colliders
+    pub fn contact(&self) -> Option<&LuaContact> {
+        match self {
+            LuaCollisionEvent::Started { contact, .. } => contact.as_ref(),
+            LuaCollisionEvent::Stopped { .. } => None,
+        }
+    }
+    /// the payload of the `collision` event on the bus
+    pub fn to_bson(&self, data: &PhysicsData) -> Bson {
+        let (user_data1, user_data2) = self.user_data();
+        Bson::Document(doc! {
+            "started": self.is_started(),
+            "sensor": self.is_sensor(),
+            "removed": self.is_removed(),
+            "user_data1": user_data1 as i64,
+            "user_data2": user_data2 as i64,
+            "data1": data.get_bson(user_data1),
+            "data2": data.get_bson(user_data2),
+        })
+    }
+}
+
 impl UserData for LuaCollisionEvent {
     fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("b1", |_lua, this| Ok(this.bodies().0));
+        fields.add_field_method_get("b2", |_lua, this| Ok(this.bodies().1));
+        fields.add_field_method_get("kind", |_lua, this| {
+            Ok(match this.is_started() {
+                true => "started",
+                false => "stopped",
+            })
+        });
+        fields.add_field_method_get("sensor", |_lua, this| Ok(this.is_sensor()));
+        // the physics data of b1 and b2, set as user values by `event_update`
+        fields.add_field_function_get("data1", |_lua, this: AnyUserData| {
+            this.nth_user_value::<Value>(1)
+        });
+        fields.add_field_function_get("data2", |_lua, this: AnyUserData| {
+            this.nth_user_value::<Value>(2)
+        });
+        fields.add_field_method_get("removed", |_lua, this| Ok(this.is_removed()));
+        fields.add_field_method_get("normal", |_lua, this| {
+            Ok(this.contact().map(|contact| contact.normal.clone()))
+        });
+        fields.add_field_method_get("points", |_lua, this| {
+            Ok(this
+                .contact()
+                .map(|contact| contact.points.clone())
+                .unwrap_or_default())
+        });
         fields.add_field_method_get("started", |lua, this| match this {
             LuaCollisionEvent::Started {
                 b1,
                 b2,
                 sensor,
                 removed,
+                ..
             } => {
                 let table = lua.create_table()?;
                 table.set("b1", *b1)?;
@@ -55,6 +173,7 @@ impl UserData for LuaCollisionEvent {
                 b2,
                 sensor,
                 removed,
+                ..
             } => {
                 let table = lua.create_table()?;
                 table.set("b1", *b1)?;
@@ -120,14 +239,15 @@ impl EventHandler for LuaPhyEventHandler {
         _bodies: &rapier2d::prelude::RigidBodySet,
         colliders: &rapier2d::prelude::ColliderSet,
         event: rapier2d::prelude::CollisionEvent,
-        _contact_pair: Option<&rapier2d::prelude::ContactPair>,
+        contact_pair: Option<&rapier2d::prelude::ContactPair>,
     ) {
         match event {
             CollisionEvent::Started(handle1, handle2, flags) => {
-                if let (Some(rb1), Some(rb2)) = (
-                    colliders.get(handle1).and_then(|col| col.parent()),
-                    colliders.get(handle2).and_then(|col| col.parent()),
-                ) {
+                let (Some(col1), Some(col2)) = (colliders.get(handle1), colliders.get(handle2))
+                else {
+                    return;
+                };
+                if let (Some(rb1), Some(rb2)) = (col1.parent(), col2.parent()) {
                     self.collision_event
                         .lock()
                         .push(LuaCollisionEvent::Started {
@@ -135,14 +255,18 @@ impl EventHandler for LuaPhyEventHandler {
                             b2: LuaRigidBodyHandle(rb2),
                             sensor: flags.contains(CollisionEventFlags::SENSOR),
                             removed: flags.contains(CollisionEventFlags::REMOVED),
+                            contact: contact_pair
+                                .and_then(|pair| LuaContact::from_pair(colliders, pair)),
+                            user_data: (col1.user_data, col2.user_data),
                         })
                 }
             }
             CollisionEvent::Stopped(handle1, handle2, flags) => {
-                if let (Some(rb1), Some(rb2)) = (
-                    colliders.get(handle1).and_then(|col| col.parent()),
-                    colliders.get(handle2).and_then(|col| col.parent()),
-                ) {
+                let (Some(col1), Some(col2)) = (colliders.get(handle1), colliders.get(handle2))
+                else {
+                    return;
+                };
+                if let (Some(rb1), Some(rb2)) = (col1.parent(), col2.parent()) {
                     self.collision_event
                         .lock()
                         .push(LuaCollisionEvent::Stopped {
@@ -150,6 +274,7 @@ impl EventHandler for LuaPhyEventHandler {
                             b2: LuaRigidBodyHandle(rb2),
                             sensor: flags.contains(CollisionEventFlags::SENSOR),
                             removed: flags.contains(CollisionEventFlags::REMOVED),
+                            user_data: (col1.user_data, col2.user_data),
                         })
                 }
             }
@@ -180,3 +305,128 @@ impl EventHandler for LuaPhyEventHandler {
         }
     }
 }
+
+#[test]
+fn test_collision_event_details() -> anyhow::Result<()> {
+    use super::LuaPhysics;
+    let lua = mlua::Lua::new();
+    let physics = lua.create_userdata(LuaPhysics::new(0.0, 10.0))?;
+    lua.globals().set("phy", &physics)?;
+    lua.load(
+        r#"
+        ground = phy:add_body({
+            user_data = 1,
+            position = { x = 0, y = 10 },
+            shape = { Cuboid = { width = 40, height = 2 } },
+            body_type = "Fixed",
+        })
+        ball = phy:add_body({
+            user_data = 2,
+            position = { x = 0, y = 5 },
+            shape = { Ball = { radius = 1 } },
+            body_type = "Dynamic",
+            active_events = "collision_events",
+        })
+        events = {}
+        phy:register_collision_event_callback(function(event)
+            local points = {}
+            for i, point in ipairs(event.points) do
+                points[i] = { x = point.x, y = point.y }
+            end
+            table.insert(events, {
+                kind = event.kind,
+                b1 = event.b1,
+                b2 = event.b2,
+                sensor = event.sensor,
+                normal = event.normal and { x = event.normal.x, y = event.normal.y },
+                points = points,
+                started = event.started ~= nil,
+            })
+        end)
+        for _ = 1, 120 do
+            phy:update()
+            phy:event_update()
+            if #events > 0 then break end
+        end
+        -- thrown back up, off the ground
+        phy:set_linvel(ball, { x = 0, y = -20 })
+        for _ = 1, 30 do
+            phy:update()
+            phy:event_update()
+            if #events > 1 then break end
+        end
+        "#,
+    )
+    .exec()?;
+    let events: mlua::Table = lua.globals().get("events")?;
+    assert_eq!(events.len()?, 2);
+    let started: mlua::Table = events.get(1)?;
+    assert_eq!(started.get::<String>("kind")?, "started");
+    assert!(started.get::<bool>("started")?);
+    assert!(!started.get::<bool>("sensor")?);
+    let (ground, ball): (LuaRigidBodyHandle, LuaRigidBodyHandle) =
+        (lua.globals().get("ground")?, lua.globals().get("ball")?);
+    let bodies = (
+        started.get::<LuaRigidBodyHandle>("b1")?.0,
+        started.get::<LuaRigidBodyHandle>("b2")?.0,
+    );
+    assert!(bodies == (ground.0, ball.0) || bodies == (ball.0, ground.0));
+    // the ball lands on the top of the ground, y grows downwards
+    let normal: mlua::Table = started.get("normal")?;
+    assert!(normal.get::<f32>("y")?.abs() > 0.9);
+    let points: Vec<mlua::Table> = started.get("points")?;
+    assert!(!points.is_empty());
+    for point in points {
+        let (x, y) = (point.get::<f32>("x")?, point.get::<f32>("y")?);
+        assert!(x.abs() < 0.1 && (y - 9.0).abs() < 0.1, "{} {}", x, y);
+    }
+    let stopped: mlua::Table = events.get(2)?;
+    assert_eq!(stopped.get::<String>("kind")?, "stopped");
+    assert!(stopped.get::<Option<mlua::Table>>("normal")?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_publish_collisions() -> anyhow::Result<()> {
+    use super::LuaPhysics;
+    use crate::event::EventBus;
+    let lua = mlua::Lua::new();
+    let bus = EventBus::default();
+    lua.globals().set("bus", bus.clone())?;
+    lua.globals()
+        .set("phy", lua.create_userdata(LuaPhysics::new(0.0, 10.0))?)?;
+    lua.load(
+        r#"
+        phy:add_body({
+            user_data = 1,
+            position = { x = 0, y = 10 },
+            shape = { Cuboid = { width = 40, height = 2 } },
+            body_type = "Fixed",
+        })
+        ball = phy:add_body({
+            user_data = 2,
+            position = { x = 0, y = 5 },
+            shape = { Ball = { radius = 1 } },
+            body_type = "Dynamic",
+            active_events = "collision_events",
+        })
+        phy:set_data(ball, { sound = "hit" })
+        hits = {}
+        bus:subscribe("collision", function(hit) table.insert(hits, hit) end)
+        phy:publish_collisions(bus)
+        for _ = 1, 120 do
+            phy:update()
+            phy:event_update()
+            if bus:pending() > 0 then break end
+        end
+        "#,
+    )
+    .exec()?;
+    bus.dispatch()?;
+    let (started, sound): (bool, String) = lua
+        .load("local hit = hits[1] return hit.started, (hit.data1 or hit.data2).sound")
+        .eval()?;
+    assert!(started);
+    assert_eq!(sound, "hit");
+    Ok(())
+}
@@ -1,9 +1,21 @@
 use rapier2d::prelude::*;
+mod data;
+mod debug;
 mod event;
 mod hooks;
+mod props;
+mod snapshot;
 pub mod types;
-use mlua::{Function, UserData, UserDataMethods, Value};
+use crate::event::EventBus;
+use crate::map2lua_error;
+pub use data::{PhysicsData, DATA_LIMIT};
+pub use debug::{DebugDrawOptions, PhysicsDebugRenderer};
+use mlua::{
+    AnyUserData, Either, Function, Lua, LuaSerdeExt, UserData, UserDataMethods, UserDataRef, Value,
+};
+use parking_lot::Mutex;
 use rapier2d::na::Vector2;
+use std::sync::Arc;
 use types::{BodyData, LuaPoint, LuaRigidBody, LuaRigidBodyHandle, Shape2D};
 pub struct Physics {
     pipeline: PhysicsPipeline,
@@ -18,6 +30,8 @@ pub struct Physics {
     multibody_joints: MultibodyJointSet,
     ccd_solver: CCDSolver,
     query_pipeline: QueryPipeline,
+    // given to the bodies added from now on
+    ccd: bool,
 }
 
 impl Physics {
@@ -38,6 +52,37 @@ impl Physics {
             multibody_joints: MultibodyJointSet::new(),
             ccd_solver: CCDSolver::new(),
             query_pipeline: QueryPipeline::new(),
+            ccd: true,
+        }
+    }
+    pub fn integration_parameters(&self) -> &IntegrationParameters {
+        &self.integration_parameters
+    }
+    /// solver substeps run per step, more keeps stacks and fast bodies stable
+    /// under large forces at the cost of time, false for 0
+    pub fn set_substeps(&mut self, substeps: usize) -> bool {
+        match std::num::NonZeroUsize::new(substeps) {
+            Some(substeps) => {
+                self.integration_parameters.num_solver_iterations = substeps;
+                true
+            }
+            None => false,
+        }
+    }
+    /// seconds one `update` advances, 1/60 by default,
+    /// false unless it is finite and above 0
+    pub fn set_timestep(&mut self, dt: Real) -> bool {
+        if !(dt.is_finite() && dt > 0.0) {
+            return false;
+        }
+        self.integration_parameters.dt = dt;
+        true
+    }
+    /// continuous collision detection for the bodies there are and the ones added later
+    pub fn set_ccd(&mut self, enabled: bool) {
+        self.ccd = enabled;
+        for (_, body) in self.bodies.iter_mut() {
+            body.enable_ccd(enabled);
         }
     }
     pub fn update<E, H>(&mut self, event_handler: &E, physics_hooks: &H)
@@ -93,7 +138,7 @@ impl Physics {
             .linear_damping(linear_damping)
             .angular_damping(angular_damping)
             .user_data(user_data)
-            .ccd_enabled(true);
+            .ccd_enabled(self.ccd);
         let body = if let Some(r) = rotation {
             body.rotation(r)
         } else {
@@ -119,6 +164,18 @@ impl Physics {
             .insert_with_parent(collider, handle, &mut self.bodies);
         handle
     }
+    pub fn remove_body(&mut self, handle: RigidBodyHandle) -> bool {
+        self.bodies
+            .remove(
+                handle,
+                &mut self.island_manager,
+                &mut self.colliders,
+                &mut self.impulse_joints,
+                &mut self.multibody_joints,
+                true,
+            )
+            .is_some()
+    }
     pub fn build_collider(&self, shape: Shape2D) -> ColliderBuilder {
         match shape {
             Shape2D::Cuboid { width, height } => ColliderBuilder::cuboid(width / 2.0, height / 2.0),
@@ -141,6 +198,76 @@ impl Physics {
             }
         }
     }
+    pub fn body_count(&self) -> usize {
+        self.bodies.len()
+    }
+    pub fn body_data(&self, handle: RigidBodyHandle) -> Option<BodyData> {
+        let body = self.bodies.get(handle)?;
+        let collider = body
+            .colliders()
+            .first()
+            .and_then(|h| self.colliders.get(*h))?;
+        let additional_mass = match body.mass_properties().additional_local_mprops.as_deref() {
+            Some(RigidBodyAdditionalMassProps::Mass(mass)) => *mass,
+            Some(RigidBodyAdditionalMassProps::MassProps(mprops)) => mprops.mass(),
+            None => 0.0,
+        };
+        Some(BodyData {
+            user_data: body.user_data,
+            position: LuaPoint {
+                x: body.translation().x,
+                y: body.translation().y,
+            },
+            shape: Shape2D::from_shape(collider.shape())?,
+            body_type: body.body_type(),
+            rotation: (!body.is_rotation_locked()).then(|| body.rotation().angle()),
+            linear_damping: body.linear_damping(),
+            angular_damping: body.angular_damping(),
+            gravity_scale: body.gravity_scale(),
+            additional_mass,
+            mass: collider.mass(),
+            can_sleep: body.activation().normalized_linear_threshold >= 0.0,
+            sleeping: body.is_sleeping(),
+            restitution: collider.restitution(),
+            friction: collider.friction(),
+            density: collider.density(),
+            is_sensor: collider.is_sensor(),
+            active_events: collider.active_events().into(),
+            active_hooks: collider.active_hooks().into(),
+        })
+    }
+    /// world space aabb of all colliders attached to the body
+    pub fn body_aabb(&self, handle: RigidBodyHandle) -> Option<Aabb> {
+        let body = self.bodies.get(handle)?;
+        body.colliders()
+            .iter()
+            .filter_map(|h| self.colliders.get(*h))
+            .map(|c| c.compute_aabb())
+            .reduce(|a, b| a.merged(&b))
+    }
+    pub fn contains_point(&self, handle: RigidBodyHandle, point: Point<Real>) -> Option<bool> {
+        let body = self.bodies.get(handle)?;
+        Some(
+            body.colliders()
+                .iter()
+                .filter_map(|h| self.colliders.get(*h))
+                .any(|c| c.shape().contains_point(c.position(), &point)),
+        )
+    }
+    /// bodies whose colliders intersect the region, as of the last update
+    pub fn bodies_in_aabb(&self, aabb: Aabb) -> Vec<RigidBodyHandle> {
+        let mut handles = Vec::new();
+        self.query_pipeline
+            .colliders_with_aabb_intersecting_aabb(&aabb, |handle| {
+                if let Some(parent) = self.colliders.get(*handle).and_then(|c| c.parent()) {
+                    if !handles.contains(&parent) {
+                        handles.push(parent);
+                    }
+                }
+                true
+            });
+        handles
+    }
     pub fn cast_ray(
         &self,
         origin: Vector<Real>,
@@ -161,12 +288,25 @@ impl Physics {
     }
 }
 
+// reachable from the userdata without borrowing the world
+#[derive(Clone, Default)]
+struct RemovalQueue(Arc<Mutex<Vec<RigidBodyHandle>>>);
+impl UserData for RemovalQueue {}
+
 pub struct LuaPhysics {
     pub physics: Physics,
     pub collision_event: Option<Function>,
     pub contact_force_event: Option<Function>,
     pub event: event::LuaPhyEventHandler,
     pub hooks: hooks::LuaPhysicsHooks,
+    // bodies removed while the world was borrowed, e.g. from an event callback
+    removals: RemovalQueue,
+    pub data: PhysicsData,
+    // collisions are published as `collision` when set
+    bus: Option<EventBus>,
+    debug_draw: DebugDrawOptions,
+    // rendering needs it mutable while the world is only borrowed
+    debug_pipeline: Mutex<Option<DebugRenderPipeline>>,
 }
 
 impl LuaPhysics {
@@ -178,6 +318,71 @@ impl LuaPhysics {
             contact_force_event: None,
             event: Default::default(),
             hooks: Default::default(),
+            removals: Default::default(),
+            data: Default::default(),
+            bus: None,
+            debug_draw: Default::default(),
+            debug_pipeline: Default::default(),
+        }
+    }
+    /// the userdata the scripts get, it carries the removal queue of `remove_body`
+    pub fn create(lua: &Lua, x: f32, y: f32) -> mlua::Result<AnyUserData> {
+        let this = Self::new(x, y);
+        let removals = this.removals.clone();
+        let physics = lua.create_userdata(this)?;
+        physics.set_named_user_value("removals", removals)?;
+        Ok(physics)
+    }
+    /// removes the body right away, or once the running `event_update` returns
+    /// when called from inside a collision callback
+    pub fn remove_body(physics: &AnyUserData, handle: RigidBodyHandle) -> mlua::Result<()> {
+        match physics.borrow_mut::<LuaPhysics>() {
+            Ok(mut this) => {
+                this.remove_body_now(handle);
+                Ok(())
+            }
+            Err(mlua::Error::UserDataBorrowMutError) => {
+                // any borrow is exclusive, the queue is taken from the user value
+                let removals = physics.named_user_value::<UserDataRef<RemovalQueue>>("removals")?;
+                removals.0.lock().push(handle);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+    fn flush_removals(&mut self) {
+        let removals = std::mem::take(&mut *self.removals.0.lock());
+        for handle in removals {
+            self.remove_body_now(handle);
+        }
+    }
+    // the data goes with the last body of its user_data
+    fn remove_body_now(&mut self, handle: RigidBodyHandle) {
+        let Some(user_data) = self.physics.bodies.get(handle).map(|body| body.user_data) else {
+            return;
+        };
+        self.physics.remove_body(handle);
+        if self.data.contains(user_data)
+            && !self
+                .physics
+                .bodies
+                .iter()
+                .any(|(_, body)| body.user_data == user_data)
+        {
+            self.data.remove(user_data);
+        }
+    }
+    /// a body handle is looked up, a number is taken as the user_data itself
+    fn data_key(&self, key: Either<LuaRigidBodyHandle, u128>) -> mlua::Result<u128> {
+        match key {
+            Either::Left(handle) => match self.physics.bodies.get(handle.0) {
+                Some(body) => Ok(body.user_data),
+                None => Err(mlua::Error::RuntimeError(format!(
+                    "body {:?} not found",
+                    handle.0
+                ))),
+            },
+            Either::Right(user_data) => Ok(user_data),
         }
     }
 
@@ -192,20 +397,47 @@ impl LuaPhysics {
 
 impl UserData for LuaPhysics {
     fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        Self::add_property_methods(methods);
         methods.add_method_mut("set_gravity", |_lua, this, (x, y): (f32, f32)| {
             this.physics.gravity = vector![x, y];
             Ok(())
         });
+        methods.add_method_mut("set_substeps", |_lua, this, substeps: usize| {
+            match this.physics.set_substeps(substeps) {
+                true => Ok(()),
+                false => Err(mlua::Error::RuntimeError(
+                    "physics substeps must be at least 1".into(),
+                )),
+            }
+        });
+        methods.add_method_mut("set_timestep", |_lua, this, dt: Real| {
+            match this.physics.set_timestep(dt) {
+                true => Ok(()),
+                false => Err(mlua::Error::RuntimeError(format!(
+                    "physics timestep must be above 0, got {}",
+                    dt
+                ))),
+            }
+        });
+        methods.add_method_mut("set_ccd", |_lua, this, enabled: bool| {
+            this.physics.set_ccd(enabled);
+            Ok(())
+        });
         methods.add_method_mut("update", |_lua, this, ()| {
+            this.flush_removals();
             this.physics.update(&this.event, &this.hooks);
             Ok(())
         });
-        methods.add_method("event_update", |lua, this, (): ()| {
+        methods.add_function("event_update", |lua, physics: AnyUserData| {
+            let this = physics.borrow::<LuaPhysics>()?;
             let collision_event = this.collision_event.clone();
             if let Some(func) = collision_event {
                 this.event.handle_collision_event(|event| {
                     match lua.scope(|scope| {
-                        let args = scope.create_userdata(*event)?;
+                        let args = scope.create_userdata(event.clone())?;
+                        let (user_data1, user_data2) = event.user_data();
+                        args.set_nth_user_value(1, this.data.get(lua, user_data1)?)?;
+                        args.set_nth_user_value(2, this.data.get(lua, user_data2)?)?;
                         func.call::<()>(args)
                     }) {
                         Ok(_) => {}
@@ -213,6 +445,11 @@ impl UserData for LuaPhysics {
                     }
                 })
             }
+            if let Some(bus) = &this.bus {
+                this.event.handle_collision_event(|event| {
+                    bus.publish("collision", event.to_bson(&this.data))
+                })
+            }
             let contact_force_event = this.contact_force_event.clone();
             if let Some(func) = contact_force_event {
                 this.event.handle_contact_force_event(|event| {
@@ -226,6 +463,16 @@ impl UserData for LuaPhysics {
                 })
             }
             this.event.reset_all();
+            drop(this);
+            physics.borrow_mut::<LuaPhysics>()?.flush_removals();
+            Ok(())
+        });
+        methods.add_method_mut("debug_draw", |lua, this, options: Option<Value>| {
+            let options = match options {
+                Some(options) => lua.from_value(options)?,
+                None => DebugDrawOptions::default(),
+            };
+            this.set_debug_draw(options);
             Ok(())
         });
         methods.add_method("get_bodies", |lua, this, ()| {
@@ -282,17 +529,52 @@ impl UserData for LuaPhysics {
             );
             Ok(LuaRigidBodyHandle(handle))
         });
-        methods.add_method_mut("remove_body", |_, this, handle: LuaRigidBodyHandle| {
-            this.physics.bodies.remove(
-                handle.0,
-                &mut this.physics.island_manager,
-                &mut this.physics.colliders,
-                &mut this.physics.impulse_joints,
-                &mut this.physics.multibody_joints,
-                true,
-            );
+        methods.add_function(
+            "remove_body",
+            |_, (physics, handle): (AnyUserData, LuaRigidBodyHandle)| {
+                LuaPhysics::remove_body(&physics, handle.0)
+            },
+        );
+        methods.add_method_mut(
+            "set_data",
+            |lua, this, (key, table): (Either<LuaRigidBodyHandle, u128>, Value)| {
+                let user_data = this.data_key(key)?;
+                match table {
+                    Value::Nil => {
+                        this.data.remove(user_data);
+                        Ok(())
+                    }
+                    table => this.data.set(lua, user_data, table),
+                }
+            },
+        );
+        methods.add_method(
+            "get_data",
+            |lua, this, key: Either<LuaRigidBodyHandle, u128>| {
+                this.data.get(lua, this.data_key(key)?)
+            },
+        );
+        methods.add_method_mut(
+            "remove_data",
+            |_lua, this, key: Either<LuaRigidBodyHandle, u128>| {
+                let user_data = this.data_key(key)?;
+                Ok(this.data.remove(user_data))
+            },
+        );
+        methods.add_method_mut("set_data_limit", |_lua, this, bytes: usize| {
+            this.data.set_limit(bytes);
             Ok(())
         });
+        methods.add_method("serialize", |lua, this, ()| {
+            let bytes = map2lua_error!(this.serialize(), "LuaPhysics::serialize")?;
+            lua.create_string(bytes)
+        });
+        methods.add_method_mut("deserialize", |_lua, this, bytes: mlua::String| {
+            map2lua_error!(
+                this.deserialize(&bytes.as_bytes()),
+                "LuaPhysics::deserialize"
+            )
+        });
         methods.add_method_mut(
             "set_linvel",
             |_, this, (handle, linvel): (LuaRigidBodyHandle, LuaPoint<f32>)| {
@@ -366,6 +648,40 @@ impl UserData for LuaPhysics {
                 None => Ok(Value::Nil),
             },
         );
+        methods.add_method(
+            "get_collider_info",
+            |_lua, this, handle: LuaRigidBodyHandle| Ok(this.physics.body_data(handle.0)),
+        );
+        methods.add_method(
+            "get_aabb",
+            |lua, this, handle: LuaRigidBodyHandle| match this.physics.body_aabb(handle.0) {
+                Some(aabb) => {
+                    let table = lua.create_table()?;
+                    table.set("min", LuaPoint::from(aabb.mins))?;
+                    table.set("max", LuaPoint::from(aabb.maxs))?;
+                    Ok(Value::Table(table))
+                }
+                None => Ok(Value::Nil),
+            },
+        );
+        methods.add_method(
+            "contains_point",
+            |_lua, this, (handle, point): (LuaRigidBodyHandle, LuaPoint<f32>)| {
+                Ok(this.physics.contains_point(handle.0, point.into()))
+            },
+        );
+        methods.add_method(
+            "colliders_in_aabb",
+            |_lua, this, (min, max): (LuaPoint<f32>, LuaPoint<f32>)| {
+                let aabb = Aabb::new(min.into(), max.into());
+                Ok(this
+                    .physics
+                    .bodies_in_aabb(aabb)
+                    .into_iter()
+                    .map(LuaRigidBodyHandle)
+                    .collect::<Vec<_>>())
+            },
+        );
         methods.add_method_mut(
             "register_collision_event_callback",
             |_lua, this, func: Function| {
@@ -373,6 +689,14 @@ impl UserData for LuaPhysics {
                 Ok(())
             },
         );
+        // nil stops publishing
+        methods.add_method_mut(
+            "publish_collisions",
+            |_lua, this, bus: Option<mlua::UserDataRef<EventBus>>| {
+                this.bus = bus.map(|bus| bus.clone());
+                Ok(())
+            },
+        );
         methods.add_method_mut(
             "register_contact_force_event_callback",
             |_lua, this, func: Function| {
@@ -416,3 +740,131 @@ impl UserData for LuaPhysics {
         });
     }
 }
+
+#[test]
+fn test_collider_info() {
+    let mut physics = Physics::new(0.0, 0.0);
+    let shape = Shape2D::Cuboid {
+        width: 4.0,
+        height: 2.0,
+    };
+    let handle = physics.add_body(
+        1,
+        LuaPoint { x: 10.0, y: 10.0 },
+        shape,
+        RigidBodyType::Fixed,
+        None,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+        1.0,
+        true,
+        false,
+        0.5,
+        0.5,
+        1.0,
+        false,
+        ActiveEvents::empty(),
+        ActiveHooks::empty(),
+    );
+    let data = physics.body_data(handle).unwrap();
+    assert!(matches!(
+        data.shape,
+        Shape2D::Cuboid { width, height } if width == 4.0 && height == 2.0
+    ));
+    let aabb = physics.body_aabb(handle).unwrap();
+    assert_eq!((aabb.mins.x, aabb.maxs.y), (8.0, 11.0));
+    assert_eq!(
+        physics.contains_point(handle, point![9.0, 10.5]),
+        Some(true)
+    );
+    assert_eq!(
+        physics.contains_point(handle, point![7.0, 10.0]),
+        Some(false)
+    );
+    physics.bodies.remove(
+        handle,
+        &mut physics.island_manager,
+        &mut physics.colliders,
+        &mut physics.impulse_joints,
+        &mut physics.multibody_joints,
+        true,
+    );
+    assert!(physics.body_data(handle).is_none());
+    assert!(physics.contains_point(handle, point![9.0, 10.5]).is_none());
+}
+
+#[test]
+fn test_deferred_remove_body() -> anyhow::Result<()> {
+    let lua = mlua::Lua::new();
+    let physics = LuaPhysics::create(&lua, 0.0, 0.0)?;
+    lua.globals().set("phy", &physics)?;
+    let handle: LuaRigidBodyHandle = lua
+        .load(
+            r#"
+            return phy:add_body({
+                user_data = 1,
+                position = { x = 0, y = 0 },
+                shape = { Ball = { radius = 1 } },
+            })
+            "#,
+        )
+        .eval()?;
+    {
+        // held the same way while event_update runs the callbacks
+        let busy = physics.borrow::<LuaPhysics>()?;
+        LuaPhysics::remove_body(&physics, handle.0)?;
+        assert!(busy.physics.bodies.get(handle.0).is_some());
+    }
+    lua.load("phy:update()").exec()?;
+    assert!(physics
+        .borrow::<LuaPhysics>()?
+        .physics
+        .bodies
+        .get(handle.0)
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn test_step_parameters() -> anyhow::Result<()> {
+    let lua = mlua::Lua::new();
+    let physics = lua.create_userdata(LuaPhysics::new(0.0, 10.0))?;
+    lua.globals().set("phy", &physics)?;
+    let handle: LuaRigidBodyHandle = lua
+        .load(
+            r#"
+            phy:set_substeps(8)
+            phy:set_timestep(1 / 120)
+            phy:set_ccd(false)
+            local handle = phy:add_body({
+                user_data = 1,
+                position = { x = 0, y = 0 },
+                shape = { Ball = { radius = 1 } },
+                body_type = "Dynamic",
+            })
+            phy:update()
+            return handle
+            "#,
+        )
+        .eval()?;
+    {
+        let this = physics.borrow::<LuaPhysics>()?;
+        let params = this.physics.integration_parameters();
+        assert_eq!(params.num_solver_iterations.get(), 8);
+        assert_eq!(params.dt, 1.0 / 120.0);
+        let body = &this.physics.bodies[handle.0];
+        assert!(!body.is_ccd_enabled());
+        // the step ran with them, falling under gravity
+        assert!(body.linvel().y > 0.0);
+    }
+    lua.load("phy:set_ccd(true)").exec()?;
+    assert!(physics.borrow::<LuaPhysics>()?.physics.bodies[handle.0].is_ccd_enabled());
+    assert!(lua.load("phy:set_substeps(0)").exec().is_err());
+    assert!(lua.load("phy:set_timestep(0)").exec().is_err());
+    assert!(lua.load("phy:set_timestep(-1)").exec().is_err());
+    let this = physics.borrow::<LuaPhysics>()?;
+    assert_eq!(this.physics.integration_parameters().dt, 1.0 / 120.0);
+    Ok(())
+}
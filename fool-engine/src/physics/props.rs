@@ -0,0 +1,268 @@
+use super::types::LuaRigidBodyHandle;
+use super::{LuaPhysics, Physics};
+use mlua::UserDataMethods;
+use rapier2d::prelude::*;
+
+// the collider properties are set on every collider of the body,
+// read back from the first one
+impl Physics {
+    fn with_body(&mut self, handle: RigidBodyHandle, f: impl FnOnce(&mut RigidBody)) -> bool {
+        match self.bodies.get_mut(handle) {
+            Some(body) => {
+                f(body);
+                true
+            }
+            None => false,
+        }
+    }
+    fn with_colliders(
+        &mut self,
+        handle: RigidBodyHandle,
+        mut f: impl FnMut(&mut Collider),
+    ) -> bool {
+        let Some(body) = self.bodies.get(handle) else {
+            return false;
+        };
+        for collider in body.colliders() {
+            if let Some(collider) = self.colliders.get_mut(*collider) {
+                f(collider);
+            }
+        }
+        true
+    }
+    fn first_collider(&self, handle: RigidBodyHandle) -> Option<&Collider> {
+        let body = self.bodies.get(handle)?;
+        body.colliders()
+            .first()
+            .and_then(|collider| self.colliders.get(*collider))
+    }
+    pub fn set_friction(&mut self, handle: RigidBodyHandle, friction: Real) -> bool {
+        self.with_colliders(handle, |collider| collider.set_friction(friction))
+    }
+    pub fn friction(&self, handle: RigidBodyHandle) -> Option<Real> {
+        self.first_collider(handle)
+            .map(|collider| collider.friction())
+    }
+    pub fn set_restitution(&mut self, handle: RigidBodyHandle, restitution: Real) -> bool {
+        self.with_colliders(handle, |collider| collider.set_restitution(restitution))
+    }
+    pub fn restitution(&self, handle: RigidBodyHandle) -> Option<Real> {
+        self.first_collider(handle)
+            .map(|collider| collider.restitution())
+    }
+    /// the body's mass and inertia follow right away, not on the next step
+    pub fn set_density(&mut self, handle: RigidBodyHandle, density: Real) -> bool {
+        if !self.with_colliders(handle, |collider| collider.set_density(density)) {
+            return false;
+        }
+        match self.bodies.get_mut(handle) {
+            Some(body) => {
+                body.recompute_mass_properties_from_colliders(&self.colliders);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn density(&self, handle: RigidBodyHandle) -> Option<Real> {
+        self.first_collider(handle)
+            .map(|collider| collider.density())
+    }
+    pub fn set_sensor(&mut self, handle: RigidBodyHandle, sensor: bool) -> bool {
+        self.with_colliders(handle, |collider| collider.set_sensor(sensor))
+    }
+    pub fn is_sensor(&self, handle: RigidBodyHandle) -> Option<bool> {
+        self.first_collider(handle)
+            .map(|collider| collider.is_sensor())
+    }
+    pub fn set_gravity_scale(&mut self, handle: RigidBodyHandle, scale: Real) -> bool {
+        self.with_body(handle, |body| body.set_gravity_scale(scale, true))
+    }
+    pub fn set_linear_damping(&mut self, handle: RigidBodyHandle, damping: Real) -> bool {
+        self.with_body(handle, |body| body.set_linear_damping(damping))
+    }
+    pub fn set_angular_damping(&mut self, handle: RigidBodyHandle, damping: Real) -> bool {
+        self.with_body(handle, |body| body.set_angular_damping(damping))
+    }
+    /// a disabled body keeps its velocity and joints but is left out of the simulation
+    pub fn set_enabled(&mut self, handle: RigidBodyHandle, enabled: bool) -> bool {
+        self.with_body(handle, |body| body.set_enabled(enabled))
+    }
+    pub fn lock_rotations(&mut self, handle: RigidBodyHandle, locked: bool) -> bool {
+        self.with_body(handle, |body| body.lock_rotations(locked, true))
+    }
+    pub fn lock_translations(&mut self, handle: RigidBodyHandle, locked: bool) -> bool {
+        self.with_body(handle, |body| body.lock_translations(locked, true))
+    }
+}
+
+impl LuaPhysics {
+    /// the setters return false for a removed body, the getters nil
+    pub(super) fn add_property_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        type Args<T> = (LuaRigidBodyHandle, T);
+        methods.add_method_mut("set_friction", |_, this, (handle, v): Args<Real>| {
+            Ok(this.physics.set_friction(handle.0, v))
+        });
+        methods.add_method("get_friction", |_, this, handle: LuaRigidBodyHandle| {
+            Ok(this.physics.friction(handle.0))
+        });
+        methods.add_method_mut("set_restitution", |_, this, (handle, v): Args<Real>| {
+            Ok(this.physics.set_restitution(handle.0, v))
+        });
+        methods.add_method("get_restitution", |_, this, handle: LuaRigidBodyHandle| {
+            Ok(this.physics.restitution(handle.0))
+        });
+        methods.add_method_mut("set_density", |_, this, (handle, v): Args<Real>| {
+            Ok(this.physics.set_density(handle.0, v))
+        });
+        methods.add_method("get_density", |_, this, handle: LuaRigidBodyHandle| {
+            Ok(this.physics.density(handle.0))
+        });
+        methods.add_method_mut("set_sensor", |_, this, (handle, v): Args<bool>| {
+            Ok(this.physics.set_sensor(handle.0, v))
+        });
+        methods.add_method("is_sensor", |_, this, handle: LuaRigidBodyHandle| {
+            Ok(this.physics.is_sensor(handle.0))
+        });
+        methods.add_method_mut("set_gravity_scale", |_, this, (handle, v): Args<Real>| {
+            Ok(this.physics.set_gravity_scale(handle.0, v))
+        });
+        methods.add_method(
+            "get_gravity_scale",
+            |_, this, handle: LuaRigidBodyHandle| {
+                Ok(this.physics.bodies.get(handle.0).map(|b| b.gravity_scale()))
+            },
+        );
+        methods.add_method_mut("set_linear_damping", |_, this, (handle, v): Args<Real>| {
+            Ok(this.physics.set_linear_damping(handle.0, v))
+        });
+        methods.add_method(
+            "get_linear_damping",
+            |_, this, handle: LuaRigidBodyHandle| {
+                Ok(this
+                    .physics
+                    .bodies
+                    .get(handle.0)
+                    .map(|b| b.linear_damping()))
+            },
+        );
+        methods.add_method_mut("set_angular_damping", |_, this, (handle, v): Args<Real>| {
+            Ok(this.physics.set_angular_damping(handle.0, v))
+        });
+        methods.add_method(
+            "get_angular_damping",
+            |_, this, handle: LuaRigidBodyHandle| {
+                Ok(this
+                    .physics
+                    .bodies
+                    .get(handle.0)
+                    .map(|b| b.angular_damping()))
+            },
+        );
+        methods.add_method_mut("set_enabled", |_, this, (handle, v): Args<bool>| {
+            Ok(this.physics.set_enabled(handle.0, v))
+        });
+        methods.add_method("is_enabled", |_, this, handle: LuaRigidBodyHandle| {
+            Ok(this.physics.bodies.get(handle.0).map(|b| b.is_enabled()))
+        });
+        methods.add_method_mut("lock_rotations", |_, this, (handle, v): Args<bool>| {
+            Ok(this.physics.lock_rotations(handle.0, v))
+        });
+        methods.add_method(
+            "is_rotation_locked",
+            |_, this, handle: LuaRigidBodyHandle| {
+                Ok(this
+                    .physics
+                    .bodies
+                    .get(handle.0)
+                    .map(|b| b.is_rotation_locked()))
+            },
+        );
+        methods.add_method_mut("lock_translations", |_, this, (handle, v): Args<bool>| {
+            Ok(this.physics.lock_translations(handle.0, v))
+        });
+        methods.add_method(
+            "is_translation_locked",
+            |_, this, handle: LuaRigidBodyHandle| {
+                Ok(this
+                    .physics
+                    .bodies
+                    .get(handle.0)
+                    .map(|b| b.locked_axes().contains(LockedAxes::TRANSLATION_LOCKED)))
+            },
+        );
+    }
+}
+
+#[test]
+fn test_body_properties() -> anyhow::Result<()> {
+    let lua = mlua::Lua::new();
+    let physics = lua.create_userdata(LuaPhysics::new(0.0, 10.0))?;
+    lua.globals().set("phy", &physics)?;
+    // two boxes pushed along the ground, one of them turned to ice,
+    // friction is averaged with the ground's
+    let (ice, rough): (f32, f32) = lua
+        .load(
+            r#"
+            phy:add_body({
+                user_data = 1,
+                position = { x = 0, y = 10 },
+                shape = { Cuboid = { width = 400, height = 2 } },
+                body_type = "Fixed",
+                friction = 0,
+            })
+            local function crate(x)
+                local handle = phy:add_body({
+                    user_data = 2,
+                    position = { x = x, y = 8 },
+                    shape = { Cuboid = { width = 2, height = 2 } },
+                    body_type = "Dynamic",
+                    friction = 2,
+                })
+                return handle
+            end
+            ice = crate(-50)
+            rough = crate(50)
+            assert(phy:set_friction(ice, 0))
+            assert(phy:get_friction(ice) == 0 and phy:get_friction(rough) == 2)
+            for _ = 1, 10 do phy:update() end
+            phy:set_linvel(ice, { x = 10, y = 0 })
+            phy:set_linvel(rough, { x = 10, y = 0 })
+            for _ = 1, 30 do phy:update() end
+            return phy:find_body(ice).pos.x + 50, phy:find_body(rough).pos.x - 50
+            "#,
+        )
+        .eval()?;
+    assert!(ice > rough + 1.0, "ice {} rough {}", ice, rough);
+
+    let handle: LuaRigidBodyHandle = lua.load("return ice").eval()?;
+    let mass = || physics.borrow::<LuaPhysics>().unwrap().physics.bodies[handle.0].mass();
+    lua.load("assert(phy:set_density(ice, 4) and phy:get_density(ice) == 4)")
+        .exec()?;
+    // the default additional mass of 10 and the 2x2 box at the new density,
+    // before any step ran
+    assert!((mass() - (10.0 + 4.0 * 4.0)).abs() < 1e-3, "{}", mass());
+    lua.load(
+        r#"
+        phy:set_gravity_scale(ice, 0.5)
+        phy:set_linear_damping(ice, 2)
+        phy:set_angular_damping(ice, 3)
+        phy:set_restitution(ice, 0.25)
+        phy:set_sensor(ice, true)
+        phy:lock_translations(ice, true)
+        phy:lock_rotations(ice, false)
+        assert(phy:get_gravity_scale(ice) == 0.5)
+        assert(phy:get_linear_damping(ice) == 2 and phy:get_angular_damping(ice) == 3)
+        assert(phy:get_restitution(ice) == 0.25 and phy:is_sensor(ice))
+        assert(phy:is_translation_locked(ice) and not phy:is_rotation_locked(ice))
+        phy:set_enabled(ice, false)
+        assert(not phy:is_enabled(ice))
+        phy:remove_body(rough)
+        assert(not phy:set_friction(rough, 1) and phy:get_friction(rough) == nil)
+        "#,
+    )
+    .exec()?;
+    // disabled, not destroyed
+    let this = physics.borrow::<LuaPhysics>()?;
+    assert!(!this.physics.bodies[handle.0].is_enabled());
+    Ok(())
+}
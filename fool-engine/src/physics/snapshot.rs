@@ -0,0 +1,149 @@
+use super::LuaPhysics;
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+// bumped whenever the layout changes, older snapshots are refused
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// the world as rapier serializes it, borrowed when saving and owned when restoring
+#[derive(Serialize, Deserialize)]
+struct Snapshot<'a> {
+    version: u32,
+    gravity: Vector<Real>,
+    integration_parameters: IntegrationParameters,
+    ccd: bool,
+    island_manager: Cow<'a, IslandManager>,
+    broad_phase: Cow<'a, DefaultBroadPhase>,
+    narrow_phase: Cow<'a, NarrowPhase>,
+    bodies: Cow<'a, RigidBodySet>,
+    colliders: Cow<'a, ColliderSet>,
+    impulse_joints: Cow<'a, ImpulseJointSet>,
+    multibody_joints: Cow<'a, MultibodyJointSet>,
+    ccd_solver: Cow<'a, CCDSolver>,
+    // as json, bincode can't read the data tables back on their own
+    data: String,
+}
+
+impl LuaPhysics {
+    /// the bodies, colliders, joints and the data attached to them in one buffer.
+    /// The step pipeline and the query pipeline are not in it, they are rebuilt on restore
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let physics = &self.physics;
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            gravity: physics.gravity,
+            integration_parameters: physics.integration_parameters,
+            ccd: physics.ccd,
+            island_manager: Cow::Borrowed(&physics.island_manager),
+            broad_phase: Cow::Borrowed(&physics.broad_phase),
+            narrow_phase: Cow::Borrowed(&physics.narrow_phase),
+            bodies: Cow::Borrowed(&physics.bodies),
+            colliders: Cow::Borrowed(&physics.colliders),
+            impulse_joints: Cow::Borrowed(&physics.impulse_joints),
+            multibody_joints: Cow::Borrowed(&physics.multibody_joints),
+            ccd_solver: Cow::Borrowed(&physics.ccd_solver),
+            data: self.data.to_json()?,
+        };
+        Ok(bincode::serde::encode_to_vec(
+            &snapshot,
+            bincode::config::standard(),
+        )?)
+    }
+    /// replaces the world with a serialized one. Handles keep their index and generation,
+    /// the ones taken before `serialize` find the same bodies and the data is found
+    /// by the same user_data again. Callbacks, hooks and pending events are left alone
+    pub fn deserialize(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        let (snapshot, _): (Snapshot, _) =
+            bincode::serde::decode_from_slice(bytes, bincode::config::standard())?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            anyhow::bail!(
+                "physics snapshot version {} is not supported, expected {}",
+                snapshot.version,
+                SNAPSHOT_VERSION
+            );
+        }
+        self.data.load_json(&snapshot.data)?;
+        let physics = &mut self.physics;
+        physics.gravity = snapshot.gravity;
+        physics.integration_parameters = snapshot.integration_parameters;
+        physics.ccd = snapshot.ccd;
+        physics.island_manager = snapshot.island_manager.into_owned();
+        physics.broad_phase = snapshot.broad_phase.into_owned();
+        physics.narrow_phase = snapshot.narrow_phase.into_owned();
+        physics.bodies = snapshot.bodies.into_owned();
+        physics.colliders = snapshot.colliders.into_owned();
+        physics.impulse_joints = snapshot.impulse_joints.into_owned();
+        physics.multibody_joints = snapshot.multibody_joints.into_owned();
+        physics.ccd_solver = snapshot.ccd_solver.into_owned();
+        physics.pipeline = PhysicsPipeline::new();
+        // queries answer before the next update
+        physics.query_pipeline = QueryPipeline::new();
+        physics.query_pipeline.update(&physics.colliders);
+        // removals asked for the replaced world would hit the restored bodies
+        self.removals.0.lock().clear();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_serialize() -> anyhow::Result<()> {
+    use super::types::LuaRigidBodyHandle;
+    let lua = mlua::Lua::new();
+    let physics = lua.create_userdata(LuaPhysics::new(0.0, 10.0))?;
+    lua.globals().set("phy", &physics)?;
+    lua.load(
+        r#"
+        ground = phy:add_body({
+            user_data = 1,
+            position = { x = 0, y = 10 },
+            shape = { Cuboid = { width = 40, height = 2 } },
+            body_type = "Fixed",
+        })
+        box = phy:add_body({
+            user_data = 2,
+            position = { x = 3, y = 0 },
+            shape = { Ball = { radius = 1 } },
+            body_type = "Dynamic",
+        })
+        phy:set_data(box, { hp = 5 })
+        for _ = 1, 10 do phy:update() end
+        saved = phy:serialize()
+        function position(handle)
+            local body = phy:find_body(handle)
+            return body and body.pos
+        end
+        before = position(box)
+        phy:remove_body(ground)
+        phy:remove_body(box)
+        "#,
+    )
+    .exec()?;
+    assert_eq!(physics.borrow::<LuaPhysics>()?.physics.body_count(), 0);
+    // storable through the save manager as a plain lua string
+    assert_eq!(lua.load("return type(saved)").eval::<String>()?, "string");
+
+    lua.load("phy:deserialize(saved)").exec()?;
+    {
+        let this = physics.borrow::<LuaPhysics>()?;
+        assert_eq!(this.physics.body_count(), 2);
+        assert!(this.data.contains(2));
+    }
+    let (x, y, before_y): (f32, f32, f32) = lua
+        .load("local after = position(box) return after.x, after.y, before.y")
+        .eval()?;
+    assert_eq!(x, 3.0);
+    assert_eq!(y, before_y);
+    // it had fallen some before the snapshot
+    assert!(y > 0.0);
+    let ground: LuaRigidBodyHandle = lua.globals().get("ground")?;
+    let this = physics.borrow::<LuaPhysics>()?;
+    assert_eq!(this.physics.bodies[ground.0].translation().y, 10.0);
+    // the query pipeline was rebuilt with the colliders
+    let aabb = Aabb::new(point![2.0, -1.0], point![4.0, 5.0]);
+    assert_eq!(this.physics.bodies_in_aabb(aabb).len(), 1);
+    drop(this);
+    assert_eq!(lua.load("return phy:get_data(box).hp").eval::<i64>()?, 5);
+    assert!(lua.load("phy:deserialize('not a world')").exec().is_err());
+    Ok(())
+}
@@ -34,6 +34,62 @@ pub enum Shape2D {
         points: Vec<LuaPoint<f32>>,
     },
 }
+impl Shape2D {
+    /// describe a collider shape, None for shapes add_body can't build
+    pub fn from_shape(shape: &dyn Shape) -> Option<Self> {
+        if let Some(round) = shape.as_round_cuboid() {
+            let half = round.inner_shape.half_extents;
+            return Some(Shape2D::RoundCuboid {
+                width: half.x * 2.0,
+                height: half.y * 2.0,
+                border_radius: round.border_radius,
+            });
+        }
+        if let Some(cuboid) = shape.as_cuboid() {
+            return Some(Shape2D::Cuboid {
+                width: cuboid.half_extents.x * 2.0,
+                height: cuboid.half_extents.y * 2.0,
+            });
+        }
+        if let Some(ball) = shape.as_ball() {
+            return Some(Shape2D::Ball {
+                radius: ball.radius,
+            });
+        }
+        if let Some(capsule) = shape.as_capsule() {
+            let (a, b) = (capsule.segment.a, capsule.segment.b);
+            return Some(if a.x == b.x {
+                Shape2D::CapsuleY {
+                    height: (b.y - a.y).abs(),
+                    radius: capsule.radius,
+                }
+            } else {
+                Shape2D::CapsuleX {
+                    width: (b.x - a.x).abs(),
+                    radius: capsule.radius,
+                }
+            });
+        }
+        if let Some(triangle) = shape.as_triangle() {
+            return Some(Shape2D::Triangle {
+                a: triangle.a.into(),
+                b: triangle.b.into(),
+                c: triangle.c.into(),
+            });
+        }
+        if let Some(convex) = shape.as_convex_polygon() {
+            return Some(Shape2D::Convex {
+                points: convex.points().iter().map(|p| (*p).into()).collect(),
+            });
+        }
+        None
+    }
+}
+impl IntoLua for Shape2D {
+    fn into_lua(self, lua: &Lua) -> LuaResult<Value> {
+        lua.to_value(&self)
+    }
+}
 impl Default for Shape2D {
     fn default() -> Self {
         Shape2D::Cuboid {
@@ -140,6 +196,22 @@ impl Into<ActiveHooks> for LuaActiveHooks {
     }
 }
 
+impl From<ActiveHooks> for LuaActiveHooks {
+    fn from(hooks: ActiveHooks) -> Self {
+        let both = ActiveHooks::FILTER_CONTACT_PAIRS | ActiveHooks::FILTER_INTERSECTION_PAIR;
+        let name = if hooks.contains(both) {
+            "all"
+        } else if hooks.contains(ActiveHooks::FILTER_CONTACT_PAIRS) {
+            "filter_contact_pairs"
+        } else if hooks.contains(ActiveHooks::FILTER_INTERSECTION_PAIR) {
+            "filter_intersection_pair"
+        } else {
+            ""
+        };
+        Self(name.to_string())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[repr(transparent)]
 pub struct LuaActiveEvents(String);
@@ -153,6 +225,20 @@ impl Into<ActiveEvents> for LuaActiveEvents {
         }
     }
 }
+impl From<ActiveEvents> for LuaActiveEvents {
+    fn from(events: ActiveEvents) -> Self {
+        let name = if events.contains(ActiveEvents::all()) {
+            "all"
+        } else if events.contains(ActiveEvents::COLLISION_EVENTS) {
+            "collision_events"
+        } else if events.contains(ActiveEvents::CONTACT_FORCE_EVENTS) {
+            "contact_force_events"
+        } else {
+            ""
+        };
+        Self(name.to_string())
+    }
+}
 impl FromLua for Shape2D {
     fn from_lua(value: Value, lua: &Lua) -> LuaResult<Self> {
         let shape: Shape2D = lua.from_value(value)?;
@@ -165,6 +251,11 @@ impl FromLua for BodyData {
         lua.from_value(value)
     }
 }
+impl IntoLua for BodyData {
+    fn into_lua(self, lua: &Lua) -> LuaResult<Value> {
+        lua.to_value(&self)
+    }
+}
 
 const fn default_body_type() -> RigidBodyType {
     RigidBodyType::Dynamic
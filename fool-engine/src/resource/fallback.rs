@@ -7,6 +7,7 @@ use image::DynamicImage;
 use image::GenericImageView;
 use std::fmt::Debug;
 use std::io::Read;
+use std::path::Path;
 use std::sync::Arc;
 use winit::window::Icon;
 #[derive(Debug, Clone)]
@@ -24,6 +25,61 @@ impl Fallback for FSFallBack {
         fd.read_to_end(&mut buffer)?;
         Ok(SharedData::from(buffer))
     }
+    fn exists(&self, key: &Self::K) -> bool {
+        self.asset_path.join(key).is_file()
+    }
+    fn keys(&self) -> Vec<Self::K> {
+        fn walk(dir: &Path, prefix: &str, keys: &mut Vec<String>) {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                match entry.file_type() {
+                    Ok(kind) if kind.is_dir() => walk(&entry.path(), &(name + "/"), keys),
+                    Ok(_) => keys.push(name),
+                    Err(_) => {}
+                }
+            }
+        }
+        let mut keys = Vec::new();
+        walk(&self.asset_path, "", &mut keys);
+        keys
+    }
+}
+
+/// reads entries out of a memory mapped pak on first access,
+/// uncompressed entries are views into the mapping
+#[derive(Debug, Clone)]
+pub struct PakFallBack {
+    pub pak: Arc<packtool::MappedPackage>,
+}
+
+impl Fallback for PakFallBack {
+    type K = String;
+    type V = SharedData;
+    fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+        let entry = self
+            .pak
+            .entry(key)
+            .ok_or_else(|| anyhow::anyhow!("{} not found in pak", key))?;
+        if self.pak.compressed() {
+            Ok(SharedData::from_vec(self.pak.decompress(entry)?))
+        } else {
+            self.pak.verify(entry)?;
+            match self.pak.mapped_range(entry) {
+                Some((mmap, range)) => SharedData::from_mmap(mmap, range),
+                // spans volumes of a split pak, copied out once
+                None => Ok(SharedData::from_vec(self.pak.raw(entry).into_owned())),
+            }
+        }
+    }
+    fn exists(&self, key: &Self::K) -> bool {
+        self.pak.entry(key).is_some()
+    }
+    fn keys(&self) -> Vec<Self::K> {
+        self.pak.entries().map(|entry| entry.path.clone()).collect()
+    }
 }
 
 #[derive(Clone)]
@@ -141,3 +197,35 @@ impl Fallback for VelloImageFallBack {
         Ok(Arc::new(image))
     }
 }
+
+#[test]
+fn test_pak_fallback() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("fool_test_pak_fallback_{}", std::process::id()));
+    let input = dir.join("assets");
+    std::fs::create_dir_all(input.join("image"))?;
+    let big: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    std::fs::write(input.join("image/big.bin"), &big)?;
+    std::fs::write(input.join("main.lua"), "return {}")?;
+    for compress in [false, true] {
+        let output = dir.join(format!("assets_{}.pak", compress));
+        packtool::ResourcePackage::create_pak(&input, &output, compress, 3, None).pack()?;
+        let pak = Arc::new(packtool::ResourcePackage::open_mapped(&output)?);
+        let fallback = PakFallBack { pak };
+        assert!(fallback.exists(&"main.lua".to_string()));
+        assert!(!fallback.exists(&"missing.lua".to_string()));
+        let mut keys = fallback.keys();
+        keys.sort();
+        assert_eq!(keys, ["image/big.bin", "main.lua"]);
+
+        let resource = Resource::from_fallback(fallback);
+        let data = resource.get("image/big.bin")?;
+        assert_eq!(*data, *big);
+        // stored entries are views into the mapping, compressed ones are unpacked once
+        assert_eq!(data.is_mapped(), !compress, "compress: {}", compress);
+        let again = resource.get("image/big.bin")?;
+        assert!(std::ptr::eq(data.as_ptr(), again.as_ptr()));
+        assert!(resource.get("missing.lua").is_err());
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,334 @@
+use super::ResourceManager;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImage, RgbaImage};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+// between the images of an atlas, so linear sampling does not bleed into the neighbours
+const ATLAS_PADDING: u32 = 1;
+
+/// an image derived from another, the result is always rgba8
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageOp {
+    Resize {
+        width: u32,
+        height: u32,
+        filter: FilterType,
+    },
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Grayscale,
+    /// multiplied into every pixel, alpha included
+    Tint([u8; 4]),
+    Flip {
+        horizontal: bool,
+    },
+}
+
+/// `nearest`, `triangle`, `catmull_rom`, `gaussian` or `lanczos3`
+pub fn parse_filter(name: &str) -> anyhow::Result<FilterType> {
+    Ok(match name {
+        "nearest" => FilterType::Nearest,
+        "triangle" => FilterType::Triangle,
+        "catmull_rom" => FilterType::CatmullRom,
+        "gaussian" => FilterType::Gaussian,
+        "lanczos3" => FilterType::Lanczos3,
+        other => {
+            return Err(anyhow::anyhow!(
+                "unknown filter {}, expect nearest, triangle, catmull_rom, gaussian or lanczos3",
+                other
+            ));
+        }
+    })
+}
+
+impl ImageOp {
+    /// the key the result is cached under, the same source and op always give the same key
+    pub fn key(&self, source: &str) -> String {
+        match self {
+            Self::Resize {
+                width,
+                height,
+                filter,
+            } => format!("{}#resize({}x{},{:?})", source, width, height, filter),
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => format!("{}#crop({},{},{}x{})", source, x, y, width, height),
+            Self::Grayscale => format!("{}#grayscale", source),
+            Self::Tint([r, g, b, a]) => format!("{}#tint({},{},{},{})", source, r, g, b, a),
+            Self::Flip { horizontal: true } => format!("{}#flip_h", source),
+            Self::Flip { horizontal: false } => format!("{}#flip_v", source),
+        }
+    }
+    pub fn apply(&self, img: &DynamicImage) -> anyhow::Result<DynamicImage> {
+        let rgba = match self {
+            Self::Resize {
+                width,
+                height,
+                filter,
+            } => {
+                if *width == 0 || *height == 0 {
+                    return Err(anyhow::anyhow!("resize to {}x{}", width, height));
+                }
+                img.resize_exact(*width, *height, *filter).to_rgba8()
+            }
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let inside = x.checked_add(*width).is_some_and(|x1| x1 <= img.width())
+                    && y.checked_add(*height).is_some_and(|y1| y1 <= img.height());
+                if !inside || *width == 0 || *height == 0 {
+                    return Err(anyhow::anyhow!(
+                        "crop {},{} {}x{} outside the {}x{} image",
+                        x,
+                        y,
+                        width,
+                        height,
+                        img.width(),
+                        img.height()
+                    ));
+                }
+                img.crop_imm(*x, *y, *width, *height).to_rgba8()
+            }
+            Self::Grayscale => img.grayscale().to_rgba8(),
+            Self::Tint(tint) => {
+                let mut rgba = img.to_rgba8();
+                for pixel in rgba.pixels_mut() {
+                    for (channel, tint) in pixel.0.iter_mut().zip(tint) {
+                        *channel = (*channel as u16 * *tint as u16 / 255) as u8;
+                    }
+                }
+                rgba
+            }
+            Self::Flip { horizontal: true } => img.fliph().to_rgba8(),
+            Self::Flip { horizontal: false } => img.flipv().to_rgba8(),
+        };
+        Ok(DynamicImage::ImageRgba8(rgba))
+    }
+}
+
+/// where an image sits in an atlas, in pixels and 0..1 uv
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Atlas {
+    pub atlas_key: String,
+    pub width: u32,
+    pub height: u32,
+    pub uv_rects: BTreeMap<String, AtlasRect>,
+}
+
+/// the same keys and size always give the same atlas key
+pub fn atlas_key(keys: &[String], max_size: u32) -> String {
+    let mut hasher = DefaultHasher::new();
+    keys.hash(&mut hasher);
+    max_size.hash(&mut hasher);
+    format!("atlas#{:016x}", hasher.finish())
+}
+
+/// shelf packing, tallest first, a new shelf starts when a row reaches `max_size`,
+/// returns the top left corner of every image in the given order
+pub fn pack_shelves(sizes: &[(u32, u32)], max_size: u32) -> anyhow::Result<Vec<(u32, u32)>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(sizes[index].1));
+    let mut places = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0u32, 0u32, 0u32);
+    for index in order {
+        let (w, h) = sizes[index];
+        if w > max_size || h > max_size {
+            return Err(anyhow::anyhow!(
+                "a {}x{} image does not fit in {}x{}",
+                w,
+                h,
+                max_size,
+                max_size
+            ));
+        }
+        if x + w > max_size {
+            (x, y, shelf_height) = (0, y + shelf_height + ATLAS_PADDING, 0);
+        }
+        if y + h > max_size {
+            return Err(anyhow::anyhow!(
+                "{} images do not fit in {}x{}",
+                sizes.len(),
+                max_size,
+                max_size
+            ));
+        }
+        places[index] = (x, y);
+        shelf_height = shelf_height.max(h);
+        x += w + ATLAS_PADDING;
+    }
+    Ok(places)
+}
+
+impl ResourceManager {
+    /// `op` applied to the image `source`, loaded into `raw_image` under the derived key
+    /// so textures and canvas images find it, it is only computed once
+    pub fn derive_image(&self, source: &str, op: &ImageOp) -> anyhow::Result<String> {
+        let key = op.key(source);
+        if self.raw_image.exists(key.clone()) {
+            return Ok(key);
+        }
+        let img = self.raw_image.get(source)?;
+        let derived = op
+            .apply(&img)
+            .map_err(|err| anyhow::anyhow!("{} of {}: {}", key, source, err))?;
+        self.raw_image.load(key.clone(), Arc::new(derived));
+        Ok(key)
+    }
+    /// packs the images `keys` into one, loaded into `raw_image` like `derive_image`
+    pub fn pack_atlas(&self, keys: &[String], max_size: u32) -> anyhow::Result<Atlas> {
+        let atlas_key = atlas_key(keys, max_size);
+        let images = keys
+            .iter()
+            .map(|key| self.raw_image.get(key))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let sizes: Vec<_> = images
+            .iter()
+            .map(|img| (img.width(), img.height()))
+            .collect();
+        let places = pack_shelves(&sizes, max_size)
+            .map_err(|err| anyhow::anyhow!("pack atlas {}: {}", atlas_key, err))?;
+        let (width, height) = sizes
+            .iter()
+            .zip(&places)
+            .fold((1, 1), |(width, height), ((w, h), (x, y))| {
+                (width.max(x + w), height.max(y + h))
+            });
+        if !self.raw_image.exists(atlas_key.clone()) {
+            let mut atlas = RgbaImage::new(width, height);
+            for (img, (x, y)) in images.iter().zip(&places) {
+                atlas.copy_from(&img.to_rgba8(), *x, *y)?;
+            }
+            self.raw_image
+                .load(atlas_key.clone(), Arc::new(DynamicImage::ImageRgba8(atlas)));
+        }
+        let uv_rects = keys
+            .iter()
+            .zip(sizes.iter().zip(&places))
+            .map(|(key, (&(w, h), &(x, y)))| {
+                let rect = AtlasRect {
+                    x,
+                    y,
+                    w,
+                    h,
+                    u0: x as f32 / width as f32,
+                    v0: y as f32 / height as f32,
+                    u1: (x + w) as f32 / width as f32,
+                    v1: (y + h) as f32 / height as f32,
+                };
+                (key.clone(), rect)
+            })
+            .collect();
+        Ok(Atlas {
+            atlas_key,
+            width,
+            height,
+            uv_rects,
+        })
+    }
+}
+
+#[test]
+fn test_image_ops() -> anyhow::Result<()> {
+    use super::AssetSource;
+    use image::Rgba;
+    let resource = ResourceManager::new(AssetSource::Folder(std::env::temp_dir()))?;
+    let mut sheet = RgbaImage::from_pixel(8, 4, Rgba([200, 100, 50, 255]));
+    sheet.put_pixel(7, 0, Rgba([10, 20, 30, 255]));
+    resource
+        .raw_image
+        .load("sheet.png", Arc::new(DynamicImage::ImageRgba8(sheet)));
+
+    let crop = ImageOp::Crop {
+        x: 4,
+        y: 0,
+        width: 4,
+        height: 2,
+    };
+    let key = resource.derive_image("sheet.png", &crop)?;
+    assert_eq!(key, "sheet.png#crop(4,0,4x2)");
+    let cropped = resource.raw_image.get(&key)?;
+    assert_eq!((cropped.width(), cropped.height()), (4, 2));
+    assert_eq!(cropped.to_rgba8().get_pixel(3, 0), &Rgba([10, 20, 30, 255]));
+    // cached, the same image comes back
+    assert_eq!(resource.derive_image("sheet.png", &crop)?, key);
+    assert!(Arc::ptr_eq(&cropped, &resource.raw_image.get(&key)?));
+
+    let flipped = resource.derive_image(&key, &ImageOp::Flip { horizontal: true })?;
+    let flipped = resource.raw_image.get(&flipped)?.to_rgba8();
+    assert_eq!(flipped.get_pixel(0, 0), &Rgba([10, 20, 30, 255]));
+    let tinted = resource.derive_image("sheet.png", &ImageOp::Tint([255, 0, 255, 128]))?;
+    let tinted = resource.raw_image.get(&tinted)?.to_rgba8();
+    assert_eq!(tinted.get_pixel(0, 0), &Rgba([200, 0, 50, 128]));
+    let gray = resource.derive_image("sheet.png", &ImageOp::Grayscale)?;
+    let gray = resource.raw_image.get(&gray)?.to_rgba8();
+    let [r, g, b, a] = gray.get_pixel(0, 0).0;
+    assert!(r == g && g == b && a == 255);
+    let resized = ImageOp::Resize {
+        width: 16,
+        height: 2,
+        filter: parse_filter("nearest")?,
+    };
+    let resized = resource.derive_image("sheet.png", &resized)?;
+    assert_eq!(resource.raw_image.get(&resized)?.width(), 16);
+
+    // the error names the key and what was asked for
+    let outside = ImageOp::Crop {
+        x: 6,
+        y: 0,
+        width: 4,
+        height: 2,
+    };
+    let err = resource.derive_image("sheet.png", &outside).unwrap_err();
+    assert!(
+        err.to_string().contains("sheet.png#crop(6,0,4x2)"),
+        "{}",
+        err
+    );
+    assert!(resource
+        .derive_image("missing.png", &ImageOp::Grayscale)
+        .is_err());
+    assert!(parse_filter("bicubic").is_err());
+
+    let icon = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 3, Rgba([0, 0, 255, 255])));
+    resource.raw_image.load("icon.png", Arc::new(icon));
+    let keys = vec!["sheet.png".to_owned(), "icon.png".to_owned()];
+    let atlas = resource.pack_atlas(&keys, 16)?;
+    assert_eq!(atlas.atlas_key, atlas_key(&keys, 16));
+    assert_eq!((atlas.width, atlas.height), (12, 4));
+    let icon = atlas.uv_rects["icon.png"];
+    assert_eq!((icon.x, icon.y, icon.w, icon.h), (9, 0, 3, 3));
+    assert_eq!((icon.u1, icon.v1), (1.0, 0.75));
+    let packed = resource.raw_image.get(&atlas.atlas_key)?.to_rgba8();
+    assert_eq!(packed.get_pixel(9, 0), &Rgba([0, 0, 255, 255]));
+    assert_eq!(packed.get_pixel(7, 0), &Rgba([10, 20, 30, 255]));
+    // the second image starts a shelf below the first
+    let atlas = resource.pack_atlas(&keys, 10)?;
+    assert_eq!(atlas.uv_rects["icon.png"].y, 5);
+    assert!(resource.pack_atlas(&keys, 6).is_err());
+    Ok(())
+}
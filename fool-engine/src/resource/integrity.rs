@@ -0,0 +1,155 @@
+use fool_script::ScriptError;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+// entries checked by `IntegrityCheck::Sampled`
+pub const SAMPLE_COUNT: usize = 16;
+
+/// how much of assets.pak is verified at startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrityCheck {
+    None,
+    // header and footer only
+    #[default]
+    Header,
+    // header plus the checksum of a few entries
+    Sampled,
+    // header plus every checksum, slow for big paks
+    Full,
+}
+
+/// every missing or corrupt asset and failed script found at startup
+#[derive(Debug, Clone, Default)]
+pub struct AssetReport {
+    pub missing: Vec<String>,
+    pub corrupt: Vec<(String, String)>,
+    pub scripts: Vec<ScriptError>,
+}
+
+impl AssetReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.scripts.is_empty()
+    }
+    /// a script error is kept with its location and traceback, anything else
+    /// marks `name` corrupt
+    pub fn add_error(&mut self, name: impl Into<String>, err: anyhow::Error) {
+        match err.downcast_ref::<ScriptError>() {
+            Some(script_err) => self.scripts.push(script_err.clone()),
+            None => self.add_corrupt(name, format!("{:#}", err)),
+        }
+    }
+    pub fn add_missing(&mut self, name: impl Into<String>) {
+        self.missing.push(name.into());
+    }
+    pub fn add_corrupt(&mut self, name: impl Into<String>, err: impl Display) {
+        self.corrupt.push((name.into(), err.to_string()));
+    }
+    pub fn merge(&mut self, other: AssetReport) {
+        self.missing.extend(other.missing);
+        self.corrupt.extend(other.corrupt);
+        self.scripts.extend(other.scripts);
+    }
+    /// error screen listing the report, returns true when quit is clicked
+    pub fn show(&self, ctx: &egui::Context) -> bool {
+        let mut quit = false;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            match self.missing.is_empty() && self.corrupt.is_empty() {
+                true => ui.heading("A game script failed"),
+                false => ui.heading("Some game assets are missing or corrupt"),
+            };
+            ui.label("Please verify or reinstall the game.");
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(ui.available_height() - 40.0)
+                .show(ui, |ui| {
+                    for name in &self.missing {
+                        ui.label(format!("missing: {}", name));
+                    }
+                    for (name, err) in &self.corrupt {
+                        ui.label(format!("corrupt: {}, {}", name, err));
+                    }
+                    for (index, err) in self.scripts.iter().enumerate() {
+                        show_script_error(ui, index, err);
+                    }
+                });
+            ui.separator();
+            quit = ui.button("Quit").clicked();
+        });
+        quit
+    }
+    pub fn log(&self) {
+        if self.is_ok() {
+            log::debug!("asset check passed");
+        } else {
+            log::error!("asset check failed:\n{}", self);
+        }
+    }
+}
+
+// every error of the chain with where it was raised, the tracebacks folded
+fn show_script_error(ui: &mut egui::Ui, index: usize, err: &ScriptError) {
+    for (depth, err) in err.chain().enumerate() {
+        let prefix = match depth {
+            0 => "script error",
+            _ => "caused by",
+        };
+        match err.location() {
+            Some(location) => ui.label(format!("{} in {}: {}", prefix, location, err.message)),
+            None => ui.label(format!("{}: {}", prefix, err.message)),
+        };
+        if let Some(traceback) = &err.traceback {
+            egui::CollapsingHeader::new("traceback")
+                .id_salt((index, depth))
+                .show(ui, |ui| ui.monospace(traceback));
+        }
+    }
+}
+
+impl Display for AssetReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for name in &self.missing {
+            writeln!(f, "missing: {}", name)?;
+        }
+        for (name, err) in &self.corrupt {
+            writeln!(f, "corrupt: {}, {}", name, err)?;
+        }
+        for err in &self.scripts {
+            writeln!(f, "script error: {:#}", err)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_asset_report() {
+    let mut report = AssetReport::default();
+    assert!(report.is_ok());
+    report.add_missing("image/a.png");
+    let mut other = AssetReport::default();
+    other.add_corrupt("audio/b.mp3", "SHA256 checksum mismatch");
+    report.merge(other);
+    assert!(!report.is_ok());
+    assert_eq!(
+        report.to_string(),
+        "missing: image/a.png\ncorrupt: audio/b.mp3, SHA256 checksum mismatch\n"
+    );
+    // script errors keep their fields, other errors are a corrupt entry
+    let mut scripts = AssetReport::default();
+    let err =
+        ScriptError::from_message("[string \"<@MemModule>/main.lua\"]:4: boom").in_module("main");
+    scripts.add_error(
+        "main.lua",
+        anyhow::Error::new(err).context("run_preload_fn"),
+    );
+    scripts.add_error("main.lua", anyhow::anyhow!("read required_assets"));
+    assert!(!scripts.is_ok());
+    assert_eq!(scripts.scripts[0].location().as_deref(), Some("main:4"));
+    assert_eq!(scripts.corrupt[0].1, "read required_assets");
+    #[derive(Deserialize)]
+    struct Config {
+        check: IntegrityCheck,
+    }
+    let config: Config = toml::from_str("check = \"sampled\"").unwrap();
+    assert_eq!(config.check, IntegrityCheck::Sampled);
+}
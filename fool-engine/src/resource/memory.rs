@@ -0,0 +1,52 @@
+use fool_resource::EntryUsage;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryEntry {
+    /// the cache holding it, raw data, decoded images, gpu textures and so on
+    pub cache: &'static str,
+    pub name: String,
+    pub bytes: usize,
+    /// held by another scope too, releasing this one does not free it
+    pub shared: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScopeMemory {
+    pub bytes: usize,
+    pub entries: Vec<MemoryEntry>,
+}
+
+/// live cache entries by the scope holding them, an entry several scopes
+/// hold is listed under each
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryReport {
+    pub scopes: BTreeMap<String, ScopeMemory>,
+}
+
+impl MemoryReport {
+    pub fn add(&mut self, cache: &'static str, usage: Vec<EntryUsage<String>>) {
+        for entry in usage {
+            let shared = entry.scopes.len() > 1;
+            for scope in entry.scopes {
+                let memory = self.scopes.entry(scope).or_default();
+                memory.bytes += entry.bytes;
+                memory.entries.push(MemoryEntry {
+                    cache,
+                    name: entry.name.clone(),
+                    bytes: entry.bytes,
+                    shared,
+                });
+            }
+        }
+    }
+    /// biggest first in every scope
+    pub fn sort(&mut self) {
+        for memory in self.scopes.values_mut() {
+            memory
+                .entries
+                .sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        }
+    }
+}
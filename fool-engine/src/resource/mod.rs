@@ -1,13 +1,21 @@
 use egui::{Context, FontData, FontDefinitions};
 use image::DynamicImage;
 use std::{path::PathBuf, sync::Arc};
-mod fallback;
+pub(crate) mod fallback;
+pub mod imageops;
+mod integrity;
+mod memory;
+mod source;
 pub mod types;
 pub mod utils;
 use egui::epaint::TextureHandle;
-pub use fool_graphics::canvas::{FontManager, ImageManager, VelloFontFallback};
-pub use fool_resource::{Resource, SharedData};
+pub use fool_graphics::canvas::{FontManager, ImageManager, SvgManager, VelloFontFallback};
+pub use fool_resource::{EntryUsage, Resource, ScopeStack, SharedData};
+pub use integrity::{AssetReport, IntegrityCheck};
+pub use memory::{MemoryEntry, MemoryReport, ScopeMemory};
+use packtool::Manifest;
 use parking_lot::RwLock;
+pub use source::AssetSource;
 pub use utils::{create_cursor, texture_from_image};
 use winit::{
     event_loop::ActiveEventLoop,
@@ -24,36 +32,47 @@ pub struct ResourceManager {
     pub window_icon: Resource<String, Arc<Icon>>,
     pub graphics_font: FontManager,
     pub graphics_img: ImageManager,
+    pub graphics_svg: SvgManager,
+    // shared by every cache, what loads inside a scope is released with it
+    scopes: ScopeStack,
+    // None when reading from a folder
+    pak: Option<Result<Arc<packtool::MappedPackage>, String>>,
+    // None when no manifest ships next to the assets
+    manifest: Option<Result<Arc<Manifest>, String>>,
 }
 
 impl ResourceManager {
-    pub fn new(assets_path: impl Into<PathBuf>) -> anyhow::Result<Self> {
-        // let assets_path = utils::resource_path()?;
-        let assets_path = assets_path.into();
-        #[cfg(feature = "debug")]
-        let raw_resource = {
-            log::debug!(
-                "init resource manager assets_path from {}",
-                assets_path.display()
-            );
-            let fs_fallback = fallback::FSFallBack {
-                asset_path: assets_path.clone(),
-            };
-            Resource::from_fallback(fs_fallback)
-        };
-
-        #[cfg(not(feature = "debug"))]
-        let raw_resource: Resource<String, SharedData> = {
-            log::debug!(
-                "init resource manager assets_path from {}",
-                assets_path.display()
-            );
-            let resource_pack =
-                packtool::ResourcePackage::from_pak(&assets_path)?.unpack2memory()?;
-            let raw = Resource::empty();
-            raw.load_from_map(resource_pack);
-            raw
+    pub fn new(source: AssetSource) -> anyhow::Result<Self> {
+        let assets_path = source.path().to_path_buf();
+        log::debug!("init resource manager from {:?}", source);
+        let (raw_resource, pak): (Resource<String, SharedData>, _) = match &source {
+            AssetSource::Folder(path) => {
+                let fs_fallback = fallback::FSFallBack {
+                    asset_path: path.clone(),
+                };
+                (Resource::from_fallback(fs_fallback), None)
+            }
+            // a broken pak is reported by `check_integrity` instead of failing here
+            AssetSource::Pak(path) => match packtool::ResourcePackage::open_mapped(path) {
+                Ok(pak) => {
+                    let pak = Arc::new(pak);
+                    (
+                        Resource::from_fallback(fallback::PakFallBack { pak: pak.clone() }),
+                        Some(Ok(pak)),
+                    )
+                }
+                Err(err) => {
+                    log::error!("open {} failed: {}", path.display(), err);
+                    (Resource::empty(), Some(Err(err.to_string())))
+                }
+            },
         };
+        let manifest_path = Manifest::path_for(&assets_path);
+        let manifest = manifest_path.is_file().then(|| {
+            Manifest::load(&manifest_path)
+                .map(Arc::new)
+                .map_err(|err| err.to_string())
+        });
         let raw_image = Resource::<String, Arc<DynamicImage>>::empty();
         raw_image.set_fall_back(fallback::RawImageFallBack {
             raw_data: raw_resource.clone(),
@@ -70,18 +89,108 @@ impl ResourceManager {
         };
         let graphics_img = ImageManager::empty();
         graphics_img.set_fall_back(graphics_img_fall_back);
+        let graphics_svg = SvgManager::from_resource(raw_resource.clone());
+        let window_cursor = Resource::<String, Arc<CustomCursor>>::empty();
+        let scopes = ScopeStack::default();
+        raw_resource.set_scopes(scopes.clone());
+        raw_image.set_scopes(scopes.clone());
+        egui_texture.set_scopes(scopes.clone());
+        window_cursor.set_scopes(scopes.clone());
+        window_icon.set_scopes(scopes.clone());
+        graphics_font.set_scopes(scopes.clone());
+        graphics_img.set_scopes(scopes.clone());
+        graphics_svg.set_scopes(scopes.clone());
         Ok(Self {
             raw_image: raw_image,
             raw_resource,
             assets_path,
             egui_font: Arc::new(RwLock::new(FontDefinitions::empty())),
-            window_cursor: Default::default(),
+            window_cursor,
             window_icon,
             egui_texture,
             graphics_font: graphics_font.clone(),
             graphics_img,
+            graphics_svg,
+            scopes,
+            pak,
+            manifest,
         })
     }
+    /// verifies the asset source, collecting every problem into one report
+    pub fn check_integrity(&self, check: IntegrityCheck) -> AssetReport {
+        let mut report = AssetReport::default();
+        if check == IntegrityCheck::None {
+            return report;
+        }
+        match &self.pak {
+            None => {
+                if !self.assets_path.is_dir() {
+                    report.add_missing(self.assets_path.display().to_string());
+                }
+            }
+            Some(Err(_)) if !self.assets_path.exists() => {
+                report.add_missing(self.assets_path.display().to_string());
+            }
+            Some(Err(err)) => report.add_corrupt(self.assets_path.display().to_string(), err),
+            Some(Ok(pak)) => {
+                if let Err(err) = pak.check_header() {
+                    report.add_corrupt(self.assets_path.display().to_string(), err);
+                    return report;
+                }
+                let sample = match check {
+                    IntegrityCheck::Sampled => Some(integrity::SAMPLE_COUNT),
+                    IntegrityCheck::Full => None,
+                    _ => return report,
+                };
+                for (path, err) in pak.verify_entries(sample) {
+                    report.add_corrupt(path, err);
+                }
+            }
+        }
+        report
+    }
+    /// existence check without loading, for the `required_assets` of main.lua
+    pub fn check_required(&self, names: &[String]) -> AssetReport {
+        let mut report = AssetReport::default();
+        for name in names {
+            if !self.has_asset(name) {
+                report.add_missing(name.clone());
+            }
+        }
+        report
+    }
+    /// cross checks the manifest written by packtool against the open assets,
+    /// only the index is compared, entry data is still verified when loaded
+    pub fn verify_manifest(&self) -> AssetReport {
+        let mut report = AssetReport::default();
+        let manifest = match &self.manifest {
+            None => return report,
+            Some(Ok(manifest)) => manifest,
+            Some(Err(err)) => {
+                let path = Manifest::path_for(&self.assets_path);
+                report.add_corrupt(path.display().to_string(), err);
+                return report;
+            }
+        };
+        for path in manifest.entries.keys() {
+            if !self.has_asset(path) {
+                report.add_missing(path.clone());
+            }
+        }
+        // a pak repacked without updating the manifest
+        if let Some(Ok(pak)) = &self.pak {
+            for (path, hash) in &manifest.entries {
+                if pak.entry(path).is_some_and(|entry| entry.hash != *hash) {
+                    report.add_corrupt(path.clone(), "checksum differs from the manifest");
+                }
+            }
+        }
+        report
+    }
+    /// index lookup only, nothing is loaded
+    pub fn has_asset(&self, name: &str) -> bool {
+        self.raw_resource.contains(name.to_owned())
+    }
     pub fn setup_egui_texture_fallback(&mut self, egui_ctx: &Context) {
         let egui_texture_fallback = fallback::EguiTextureFallBack {
             ctx: egui_ctx.clone(),
@@ -133,4 +242,143 @@ impl ResourceManager {
     pub fn get_ui_texture(&self, path: &String) -> anyhow::Result<TextureHandle> {
         self.egui_texture.get(path)
     }
+    /// everything loaded from now on is tagged with `name` until it is popped
+    pub fn push_scope(&self, name: impl Into<String>) {
+        let name = name.into();
+        log::debug!("push resource scope {}", name);
+        self.scopes.push(name);
+    }
+    /// pops the innermost scope and releases it, unless an outer scope has the same name
+    pub fn pop_scope(&self) -> Option<String> {
+        let name = self.scopes.pop()?;
+        if !self.scopes.contains(&name) {
+            self.release_scope(&name);
+        }
+        Some(name)
+    }
+    pub fn scopes(&self) -> Vec<String> {
+        self.scopes.names()
+    }
+    /// `name` and whatever is built from it survive every scope release
+    pub fn pin(&self, name: &str) {
+        let name = name.to_owned();
+        self.raw_resource.pin(name.clone());
+        self.raw_image.pin(name.clone());
+        self.egui_texture.pin(name.clone());
+        self.window_cursor.pin(name.clone());
+        self.window_icon.pin(name.clone());
+        self.graphics_img.pin(name);
+    }
+    /// drops what only `name` holds, derived caches before the ones they
+    /// are built from so gpu textures go with their images, returns the count
+    pub fn release_scope(&self, name: &str) -> usize {
+        [
+            self.egui_texture.release_scope(name),
+            self.window_cursor.release_scope(name),
+            self.window_icon.release_scope(name),
+            self.graphics_img.release_scope(name),
+            self.graphics_svg.release_scope(name),
+            self.graphics_font.release_scope(name),
+            self.raw_image.release_scope(name),
+            self.raw_resource.release_scope(name),
+        ]
+        .iter()
+        .map(Vec::len)
+        .sum()
+    }
+    /// drops the loaded assets and the images built from them, for when the mods
+    /// change, fonts and svgs already parsed stay as they are
+    pub fn clear_assets(&self) {
+        self.egui_texture.clear();
+        self.graphics_img.clear();
+        self.raw_image.clear();
+        self.raw_resource.clear();
+    }
+    // cursors and icons are opaque and count as 0 bytes
+    fn usage(&self) -> Vec<(&'static str, Vec<EntryUsage<String>>)> {
+        vec![
+            ("raw", self.raw_resource.usage(|data| data.len())),
+            ("image", self.raw_image.usage(|img| img.as_bytes().len())),
+            (
+                "ui_texture",
+                self.egui_texture.usage(|texture| texture.byte_size()),
+            ),
+            ("cursor", self.window_cursor.usage(|_| 0)),
+            ("icon", self.window_icon.usage(|_| 0)),
+            ("texture", self.graphics_img.usage(|img| img.data.len())),
+            ("svg", self.graphics_svg.usage()),
+            ("font", self.graphics_font.usage()),
+        ]
+    }
+    /// live entries grouped by scope
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        for (cache, usage) in self.usage() {
+            report.add(cache, usage);
+        }
+        report.sort();
+        report
+    }
+    /// bytes held by all caches, an entry several scopes hold counts once
+    pub fn cache_bytes(&self) -> usize {
+        self.usage()
+            .iter()
+            .flat_map(|(_, usage)| usage.iter().map(|entry| entry.bytes))
+            .sum()
+    }
+}
+
+#[test]
+fn test_verify_manifest() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("fool_test_verify_manifest");
+    let input = dir.join("assets");
+    std::fs::create_dir_all(input.join("image"))?;
+    std::fs::write(input.join("main.lua"), "return {}")?;
+    std::fs::write(input.join("image/a.png"), [0u8; 16])?;
+    let output = dir.join("assets.pak");
+    packtool::ResourcePackage::create_pak(&input, &output, true, 3, None).pack()?;
+    let manifest_path = Manifest::path_for(&output);
+    let mut manifest = Manifest::load(&manifest_path)?;
+    assert!(manifest.contains("main.lua") && manifest.contains("image/a.png"));
+    manifest.insert("image/missing.png", [0; 32]);
+    manifest.save(&manifest_path)?;
+
+    let resource = ResourceManager::new(AssetSource::Pak(output.clone()))?;
+    assert!(resource.has_asset("image/a.png"));
+    assert!(!resource.has_asset("image/missing.png"));
+    let report = resource.verify_manifest();
+    assert_eq!(report.missing, ["image/missing.png"]);
+    assert!(report.corrupt.is_empty());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_asset_sources() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("fool_test_asset_sources");
+    let input = dir.join("assets");
+    std::fs::create_dir_all(&input)?;
+    std::fs::write(input.join("main.lua"), "return {}")?;
+    let output = dir.join("assets.pak");
+    packtool::ResourcePackage::create_pak(&input, &output, true, 3, None).pack()?;
+    // either source works whatever the build features
+    for source in [
+        AssetSource::Folder(input.clone()),
+        AssetSource::Pak(output.clone()),
+    ] {
+        let resource = ResourceManager::new(source.clone())?;
+        let main = resource.raw_resource.get("main.lua")?;
+        assert_eq!(main.to_string()?, "return {}", "{:?}", source);
+        assert!(resource.check_integrity(IntegrityCheck::Full).is_ok());
+    }
+    // a folder path given as a pak is reported, not a panic
+    let resource = ResourceManager::new(AssetSource::Pak(input.clone()))?;
+    assert!(!resource.check_integrity(IntegrityCheck::Header).is_ok());
+    assert_eq!(
+        AssetSource::parse("pak:build/assets.pak")?,
+        AssetSource::Pak("build/assets.pak".into())
+    );
+    assert!(AssetSource::parse("assets").is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
 }
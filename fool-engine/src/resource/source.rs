@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// where the assets are read from, `{ folder = "assets" }` or `{ pak = "assets.pak" }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetSource {
+    Folder(PathBuf),
+    Pak(PathBuf),
+}
+
+impl AssetSource {
+    /// what the build defaults to, the folder with the `debug` feature and the pak without
+    pub fn from_feature(path: impl Into<PathBuf>) -> Self {
+        #[cfg(feature = "debug")]
+        return Self::Folder(path.into());
+        #[cfg(not(feature = "debug"))]
+        return Self::Pak(path.into());
+    }
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Folder(path) | Self::Pak(path) => path,
+        }
+    }
+    /// the same kind of source with `base` joined in front of a relative path
+    pub fn relative_to(&self, base: &Path) -> Self {
+        match self {
+            Self::Folder(path) => Self::Folder(base.join(path)),
+            Self::Pak(path) => Self::Pak(base.join(path)),
+        }
+    }
+    pub fn is_pak(&self) -> bool {
+        matches!(self, Self::Pak(_))
+    }
+    /// `folder:<path>` or `pak:<path>`, as given on the command line
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value.split_once(':') {
+            Some(("folder", path)) => Ok(Self::Folder(path.into())),
+            Some(("pak", path)) => Ok(Self::Pak(path.into())),
+            _ => Err(anyhow::anyhow!(
+                "assets source {} is not folder:<path> or pak:<path>",
+                value
+            )),
+        }
+    }
+}
@@ -7,12 +7,33 @@ use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use crate::{lua_create_table, map2lua_error};
+mod section;
+pub use section::{DATA_SECTION, META_SECTION};
 const MAGIC: [u8; 4] = [b'f', b'o', b'o', b'l'];
 const VERSION: [u8; 4] = [0, 0, 0, 1];
+// the save split into sections, see section.rs
+const VERSION_SECTIONS: [u8; 4] = [0, 0, 0, 2];
+// slots starting with this hold engine settings, scripts can't see or touch them
+const RESERVED_PREFIX: &str = "__";
+
+/// whether `name` is a slot the engine keeps for itself
+pub fn is_reserved(name: &str) -> bool {
+    name.starts_with(RESERVED_PREFIX)
+}
+
+fn check_slot(name: &str) -> mlua::Result<()> {
+    match is_reserved(name) {
+        true => Err(mlua::Error::RuntimeError(format!(
+            "save slot {} is reserved for the engine",
+            name
+        ))),
+        false => Ok(()),
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct Header {
@@ -23,7 +44,10 @@ pub struct Header {
 
 impl Header {
     pub fn is_vaild(&self) -> bool {
-        self.magic == MAGIC && self.version == VERSION
+        self.magic == MAGIC && (self.version == VERSION || self.version == VERSION_SECTIONS)
+    }
+    pub fn is_sectioned(&self) -> bool {
+        self.version == VERSION_SECTIONS
     }
     pub fn read<R: Read>(f: &mut R) -> anyhow::Result<Self> {
         Ok(bincode::decode_from_std_read(
@@ -45,6 +69,13 @@ impl Header {
             compress,
         }
     }
+    fn sectioned(compress: bool) -> Self {
+        Self {
+            magic: MAGIC,
+            version: VERSION_SECTIONS,
+            compress,
+        }
+    }
 }
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Entry {
@@ -61,6 +92,14 @@ impl Entry {
             Ok(bson::from_reader(r)?)
         }
     }
+    /// a sectioned save only reads its meta section unless `full`
+    fn read(fd: &mut std::fs::File, header: &Header, full: bool) -> anyhow::Result<Self> {
+        if header.is_sectioned() {
+            section::load_entry(fd, header.compress, full)
+        } else {
+            Self::load(fd, header.compress)
+        }
+    }
     pub fn save(
         path: impl Into<PathBuf>,
         name: Option<impl Into<String> + Clone>,
@@ -130,6 +169,30 @@ impl SaveManager {
         log::debug!("SaveManager init from {}", path.display());
         Self { path: path }
     }
+    /// saves in the per-user data dir of `app_name`, `%APPDATA%/app_name` on windows,
+    /// `~/.local/share/app_name` on linux, created if missing
+    pub fn default_for_app(app_name: &str) -> Self {
+        let path = match directories::BaseDirs::new() {
+            Some(dirs) => dirs.data_dir().join(app_name),
+            None => {
+                log::warn!(
+                    "no home directory, saves of {} go next to the game",
+                    app_name
+                );
+                PathBuf::from(app_name)
+            }
+        };
+        if let Err(err) = std::fs::create_dir_all(&path) {
+            log::error!("create save dir {} failed: {}", path.display(), err);
+        }
+        Self::new(path)
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    fn slot_path(&self, name: &str) -> PathBuf {
+        self.path.join(format!("{}.save", name))
+    }
 
     pub fn list(&self) -> anyhow::Result<Vec<Entry>> {
         let mut entrys = Vec::new();
@@ -141,7 +204,7 @@ impl SaveManager {
                 let mut fd = std::fs::File::open(entry.path())?;
                 let header = Header::read(&mut fd)?;
                 if header.is_vaild() {
-                    match Entry::load(&mut fd, header.compress) {
+                    match Entry::read(&mut fd, &header, false) {
                         Ok(e) => {
                             log::debug!("save file {} loaded!", entry.path().display());
                             entrys.push(e)
@@ -157,11 +220,10 @@ impl SaveManager {
     }
     pub fn load(&self, name: impl Into<String> + Clone) -> anyhow::Result<Entry> {
         let name = name.into();
-        let full_path = self.path.join(format!("{}.save", &name));
-        let mut fd = std::fs::File::open(full_path)?;
+        let mut fd = std::fs::File::open(self.slot_path(&name))?;
         let header = Header::read(&mut fd)?;
         if header.is_vaild() {
-            Ok(Entry::load(&mut fd, header.compress)?)
+            Ok(Entry::read(&mut fd, &header, true)?)
         } else {
             Err(anyhow::anyhow!("{} not found!", name))
         }
@@ -175,8 +237,7 @@ impl SaveManager {
         Entry::save(&self.path, name, data, compress)
     }
     pub fn delete(&self, name: &str) -> anyhow::Result<()> {
-        let path = self.path.join(format!("{}.save", name));
-        std::fs::remove_file(path)?;
+        std::fs::remove_file(self.slot_path(name))?;
         Ok(())
     }
 }
@@ -186,19 +247,74 @@ impl UserData for SaveManager {
         methods.add_method(
             "save",
             |_lua, this, (name, data): (Option<String>, Value)| {
+                if let Some(name) = &name {
+                    check_slot(name)?;
+                }
                 let data = lua_value_to_bson(data)?;
                 map2lua_error!(this.save(name, true, data), "SaveManager::save")?;
                 Ok(())
             },
         );
+        methods.add_method(
+            "save_section",
+            |_lua, this, (name, section, data): (String, String, Value)| {
+                check_slot(&name)?;
+                let data = lua_value_to_bson(data)?;
+                map2lua_error!(
+                    this.save_section(&name, &section, data),
+                    "SaveManager::save_section"
+                )?;
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "save_sections",
+            |_lua, this, (name, sections): (String, mlua::Table)| {
+                check_slot(&name)?;
+                let sections = sections
+                    .pairs::<String, Value>()
+                    .map(|pair| pair.and_then(|(k, v)| Ok((k, lua_value_to_bson(v)?))))
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                map2lua_error!(
+                    this.save_sections(&name, true, sections),
+                    "SaveManager::save_sections"
+                )?;
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "load_section",
+            |lua, this, (name, section): (String, String)| {
+                check_slot(&name)?;
+                match this.load_section(&name, &section) {
+                    Ok(data) => bson_to_lua_value(lua, &data),
+                    Err(err) => {
+                        log::error!("SaveManager load {}/{} failed: {}", name, section, err);
+                        Ok(Value::Nil)
+                    }
+                }
+            },
+        );
+        methods.add_method("list_sections", |_lua, this, name: String| {
+            check_slot(&name)?;
+            map2lua_error!(this.list_sections(&name), "SaveManager::list_sections")
+        });
         methods.add_method("delete", |_lua, this, name: String| {
+            check_slot(&name)?;
             map2lua_error!(this.delete(&name), "SaveManager::delete")?;
             Ok(())
         });
+        // for an "open save folder" button
+        methods.add_method("path", |_lua, this, ()| {
+            Ok(this.path.to_string_lossy().into_owned())
+        });
         methods.add_method("list", |lua, this, ()| {
             let entrys = map2lua_error!(this.list(), "SaveManager::list")?;
             let lua_entrys = lua_create_table!(lua, []);
-            for (index, entry) in entrys.iter().enumerate() {
+            let entrys = entrys
+                .iter()
+                .filter(|entry| !entry.name.as_deref().is_some_and(is_reserved));
+            for (index, entry) in entrys.enumerate() {
                 let local_str = entry
                     .create_at
                     .with_timezone(&Local)
@@ -213,6 +329,7 @@ impl UserData for SaveManager {
             Ok(lua_entrys)
         });
         methods.add_method("load", |lua, this, name: String| {
+            check_slot(&name)?;
             match map2lua_error!(this.load(&name), "SaveManager::load") {
                 Ok(entry) => {
                     let local_str = entry
@@ -250,3 +367,17 @@ fn test_save() -> anyhow::Result<()> {
     println!("{:?}", saves);
     Ok(())
 }
+
+#[test]
+fn test_default_for_app() {
+    let app_name = format!("fool-engine-test-{}", std::process::id());
+    let save = SaveManager::default_for_app(&app_name);
+    let data_dir = directories::BaseDirs::new()
+        .unwrap()
+        .data_dir()
+        .to_path_buf();
+    assert_eq!(save.path(), data_dir.join(&app_name));
+    assert!(save.path().is_dir());
+    assert!(save.list().unwrap().is_empty());
+    std::fs::remove_dir_all(save.path()).unwrap();
+}
@@ -0,0 +1,272 @@
+use super::{Entry, Header, SaveManager};
+use bincode::{Decode, Encode};
+use bson::{Bson, Document};
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+
+// what the old single blob saves read as
+pub const DATA_SECTION: &str = "data";
+// what `list` shows of a sectioned save
+pub const META_SECTION: &str = "meta";
+
+// a sectioned save is
+//   header | table offset: u64 le | section.. | table
+// every section is a bson document of its own, compressed on its own,
+// an update appends the section and a new table and only then moves the offset
+#[derive(Debug, Clone, Encode, Decode)]
+struct SectionInfo {
+    name: String,
+    offset: u64,
+    len: u64,
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct SectionTable {
+    name: Option<String>,
+    // millis, chrono has no bincode support
+    create_at: i64,
+    sections: Vec<SectionInfo>,
+}
+
+impl SectionTable {
+    fn new(name: &str) -> Self {
+        Self {
+            name: Some(name.to_owned()),
+            create_at: Utc::now().timestamp_millis(),
+            sections: Vec::new(),
+        }
+    }
+    /// reads the offset right after the header and the table it points at
+    fn read(fd: &mut File) -> anyhow::Result<Self> {
+        let mut offset = [0u8; 8];
+        fd.read_exact(&mut offset)?;
+        fd.seek(SeekFrom::Start(u64::from_le_bytes(offset)))?;
+        Ok(bincode::decode_from_std_read(
+            fd,
+            bincode::config::standard(),
+        )?)
+    }
+    fn write<W: Write>(&self, w: &mut W) -> anyhow::Result<()> {
+        bincode::encode_into_std_write(self, w, bincode::config::standard())?;
+        Ok(())
+    }
+    /// a section saved again replaces the old one, its bytes stay unused until the next full save
+    fn insert(&mut self, name: String, offset: u64, len: u64) {
+        self.sections.retain(|section| section.name != name);
+        self.sections.push(SectionInfo { name, offset, len });
+    }
+    fn get(&self, name: &str) -> Option<&SectionInfo> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+    fn create_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.create_at).unwrap_or_default()
+    }
+}
+
+fn write_section<W: Write>(w: &mut W, data: Bson, compress: bool) -> anyhow::Result<u64> {
+    let mut doc = Document::new();
+    doc.insert("data", data);
+    let mut bytes = bson::to_vec(&doc)?;
+    if compress {
+        bytes = zstd::encode_all(bytes.as_slice(), 10)?;
+    }
+    w.write_all(&bytes)?;
+    Ok(bytes.len() as u64)
+}
+
+fn read_section(fd: &mut File, info: &SectionInfo, compress: bool) -> anyhow::Result<Bson> {
+    fd.seek(SeekFrom::Start(info.offset))?;
+    let mut bytes = vec![0u8; info.len as usize];
+    fd.read_exact(&mut bytes)?;
+    if compress {
+        bytes = zstd::decode_all(bytes.as_slice())?;
+    }
+    let mut doc: Document = bson::from_slice(&bytes)?;
+    doc.remove("data")
+        .ok_or(anyhow::anyhow!("section {} has no data", info.name))
+}
+
+/// `full` reads every section into one document, otherwise only the meta section is read
+pub fn load_entry(fd: &mut File, compress: bool, full: bool) -> anyhow::Result<Entry> {
+    let table = SectionTable::read(fd)?;
+    let data = if full {
+        let mut doc = Document::new();
+        for info in &table.sections {
+            match read_section(fd, info, compress) {
+                Ok(data) => {
+                    doc.insert(info.name.clone(), data);
+                }
+                Err(err) => log::error!("save section {} load failed: {}", info.name, err),
+            }
+        }
+        Bson::Document(doc)
+    } else {
+        match table.get(META_SECTION) {
+            Some(info) => read_section(fd, info, compress)?,
+            None => Bson::Null,
+        }
+    };
+    Ok(Entry {
+        name: table.name.clone(),
+        create_at: table.create_at(),
+        data,
+    })
+}
+
+impl SaveManager {
+    fn open_slot(&self, name: &str) -> anyhow::Result<(File, Header)> {
+        let mut fd = File::options()
+            .read(true)
+            .write(true)
+            .open(self.slot_path(name))?;
+        let header = Header::read(&mut fd)?;
+        if !header.is_vaild() {
+            anyhow::bail!("{} not found!", name);
+        }
+        Ok((fd, header))
+    }
+    /// writes the sections one after the other, the slot is replaced once all are on disk
+    pub fn save_sections(
+        &self,
+        name: &str,
+        compress: bool,
+        sections: impl IntoIterator<Item = (String, Bson)>,
+    ) -> anyhow::Result<()> {
+        let path = self.slot_path(name);
+        let tmp = path.with_extension("save.tmp");
+        let mut fd = BufWriter::new(File::create(&tmp)?);
+        Header::sectioned(compress).write(&mut fd)?;
+        let pointer = fd.stream_position()?;
+        fd.write_all(&0u64.to_le_bytes())?;
+        let mut table = SectionTable::new(name);
+        for (section, data) in sections {
+            let offset = fd.stream_position()?;
+            let len = write_section(&mut fd, data, compress)?;
+            table.insert(section, offset, len);
+        }
+        let table_offset = fd.stream_position()?;
+        table.write(&mut fd)?;
+        fd.seek(SeekFrom::Start(pointer))?;
+        fd.write_all(&table_offset.to_le_bytes())?;
+        fd.into_inner()?.sync_all()?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+    /// updates one section in place, an old single blob save is turned into its "data" section
+    pub fn save_section(&self, name: &str, section: &str, data: Bson) -> anyhow::Result<()> {
+        if !self.slot_path(name).exists() {
+            return self.save_sections(name, true, [(section.to_owned(), data)]);
+        }
+        let (mut fd, header) = self.open_slot(name)?;
+        if !header.is_sectioned() {
+            let entry = Entry::load(&mut fd, header.compress)?;
+            drop(fd);
+            let sections = [
+                (DATA_SECTION.to_owned(), entry.data),
+                (section.to_owned(), data),
+            ];
+            return self.save_sections(name, header.compress, sections);
+        }
+        let pointer = fd.stream_position()?;
+        let mut table = SectionTable::read(&mut fd)?;
+        // after the current table, a failed write leaves the save as it was
+        let offset = fd.seek(SeekFrom::End(0))?;
+        let len = write_section(&mut fd, data, header.compress)?;
+        table.insert(section.to_owned(), offset, len);
+        table.write(&mut fd)?;
+        fd.sync_data()?;
+        fd.seek(SeekFrom::Start(pointer))?;
+        fd.write_all(&(offset + len).to_le_bytes())?;
+        fd.sync_data()?;
+        Ok(())
+    }
+    pub fn load_section(&self, name: &str, section: &str) -> anyhow::Result<Bson> {
+        let (mut fd, header) = self.open_slot(name)?;
+        if !header.is_sectioned() {
+            if section != DATA_SECTION {
+                anyhow::bail!("save {} has no section {}", name, section);
+            }
+            return Ok(Entry::load(&mut fd, header.compress)?.data);
+        }
+        let table = SectionTable::read(&mut fd)?;
+        let info = table.get(section).ok_or(anyhow::anyhow!(
+            "save {} has no section {}",
+            name,
+            section
+        ))?;
+        read_section(&mut fd, info, header.compress)
+    }
+    pub fn list_sections(&self, name: &str) -> anyhow::Result<Vec<String>> {
+        let (mut fd, header) = self.open_slot(name)?;
+        if !header.is_sectioned() {
+            return Ok(vec![DATA_SECTION.to_owned()]);
+        }
+        let table = SectionTable::read(&mut fd)?;
+        Ok(table.sections.into_iter().map(|info| info.name).collect())
+    }
+}
+
+#[test]
+fn test_save_sections() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("test_save_sections");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let sm = SaveManager::new(&dir);
+    let chunks = (0..4).map(|x| {
+        let tiles = Bson::Array((0..256).map(|t| Bson::Int32(t * x)).collect());
+        (format!("world.chunks.{}_0", x), tiles)
+    });
+    let sections = [
+        (META_SECTION.to_owned(), Bson::String("day 3".into())),
+        ("player".to_owned(), Bson::Int32(100)),
+    ];
+    sm.save_sections("world", true, sections.into_iter().chain(chunks))?;
+    assert_eq!(sm.list_sections("world")?.len(), 6);
+    assert_eq!(sm.load_section("world", "player")?, Bson::Int32(100));
+    assert!(sm.load_section("world", "inventory").is_err());
+    // the menu only reads the meta section
+    let listed = sm.list()?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].data, Bson::String("day 3".into()));
+
+    // a dirty chunk saved on its own
+    sm.save_section("world", "world.chunks.2_0", Bson::Boolean(true))?;
+    sm.save_section("world", "world.chunks.9_9", Bson::Boolean(false))?;
+    assert_eq!(
+        sm.load_section("world", "world.chunks.2_0")?,
+        Bson::Boolean(true)
+    );
+    assert_eq!(sm.list_sections("world")?.len(), 7);
+
+    // a broken player section doesn't take the world with it
+    let path = sm.slot_path("world");
+    let info = {
+        let (mut fd, _) = sm.open_slot("world")?;
+        SectionTable::read(&mut fd)?.get("player").cloned().unwrap()
+    };
+    let mut bytes = std::fs::read(&path)?;
+    for byte in &mut bytes[info.offset as usize..(info.offset + info.len) as usize] {
+        *byte = 0xff;
+    }
+    std::fs::write(&path, bytes)?;
+    assert!(sm.load_section("world", "player").is_err());
+    assert_eq!(
+        sm.load_section("world", "meta")?,
+        Bson::String("day 3".into())
+    );
+    let entry = sm.load("world")?;
+    let doc = entry.data.as_document().unwrap();
+    assert!(doc.get("player").is_none());
+    assert_eq!(doc.get("world.chunks.9_9"), Some(&Bson::Boolean(false)));
+    assert_eq!(entry.name.as_deref(), Some("world"));
+
+    // the old format reads as a single section
+    sm.save(Some("old"), true, Bson::Int64(7))?;
+    assert_eq!(sm.list_sections("old")?, ["data"]);
+    assert_eq!(sm.load_section("old", DATA_SECTION)?, Bson::Int64(7));
+    sm.save_section("old", "player", Bson::Int32(1))?;
+    assert_eq!(sm.list_sections("old")?, ["data", "player"]);
+    assert_eq!(sm.load_section("old", DATA_SECTION)?, Bson::Int64(7));
+    Ok(())
+}
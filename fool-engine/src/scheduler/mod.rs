@@ -1,4 +1,2 @@
 mod frameid;
-mod scheduler;
 pub use frameid::FrameID;
-pub use scheduler::FrameScheduler;
@@ -1,13 +1,42 @@
 use crate::map2lua_error;
-use fool_audio::{AudioSystem, EffectConfig};
+use fool_audio::{AudioSystem, EffectConfig, PlayOptions};
 use mlua::{FromLua, IntoLua, LuaSerdeExt, UserData, Value};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LuaEffectConfig(EffectConfig);
+impl LuaEffectConfig {
+    /// `{type = "compressor", ...}` with every parameter in range
+    pub fn parse(lua: &mlua::Lua, value: Value) -> Result<Self, String> {
+        let types = EffectConfig::TYPES.join(", ");
+        let Value::Table(table) = &value else {
+            return Err(format!("expect a table, got {}", value.type_name()));
+        };
+        let kind = match table.raw_get::<Value>("type").map_err(|e| e.to_string())? {
+            Value::String(kind) => kind.to_string_lossy(),
+            Value::Nil => return Err(format!("missing type, expect one of {}", types)),
+            other => return Err(format!("type must be a string, got {}", other.type_name())),
+        };
+        if !EffectConfig::TYPES.contains(&kind.as_str()) {
+            return Err(format!("unknown type {}, expect one of {}", kind, types));
+        }
+        let config: EffectConfig = lua.from_value(value).map_err(|err| match err {
+            mlua::Error::DeserializeError(err) => format!("{}: {}", kind, err),
+            err => err.to_string(),
+        })?;
+        config.validate().map_err(|err| err.to_string())?;
+        Ok(Self(config))
+    }
+    /// errors name the group and effect the config is for
+    fn parse_for(lua: &mlua::Lua, group: &str, effect: &str, value: Value) -> mlua::Result<Self> {
+        Self::parse(lua, value).map_err(|err| {
+            mlua::Error::RuntimeError(format!("group {} effect {}: {}", group, effect, err))
+        })
+    }
+}
 impl FromLua for LuaEffectConfig {
     fn from_lua(value: Value, lua: &mlua::Lua) -> mlua::Result<Self> {
-        lua.from_value(value)
+        Self::parse(lua, value).map_err(mlua::Error::RuntimeError)
     }
 }
 impl IntoLua for LuaEffectConfig {
@@ -15,6 +44,37 @@ impl IntoLua for LuaEffectConfig {
         lua.to_value(&self)
     }
 }
+#[derive(Debug, Clone, Default)]
+pub struct LuaPlayOptions(PlayOptions);
+impl FromLua for LuaPlayOptions {
+    fn from_lua(value: Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+        Ok(Self(lua.from_value(value)?))
+    }
+}
+impl LuaPlayOptions {
+    /// an options table, or the volume, panning and position `play` took before
+    fn from_args(
+        lua: &mlua::Lua,
+        options: Value,
+        panning: Option<f32>,
+        position: Option<f64>,
+    ) -> mlua::Result<Self> {
+        match options {
+            Value::Table(_) if panning.is_some() || position.is_some() => {
+                Err(mlua::Error::RuntimeError(
+                    "expect an options table or volume, panning and position".into(),
+                ))
+            }
+            Value::Table(_) => Self::from_lua(options, lua),
+            volume => Ok(Self(PlayOptions {
+                volume: FromLua::from_lua(volume, lua)?,
+                panning,
+                position,
+                ..Default::default()
+            })),
+        }
+    }
+}
 #[derive(Clone)]
 pub struct LuaAudio(pub AudioSystem);
 
@@ -22,44 +82,96 @@ impl UserData for LuaAudio {
     fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
         methods.add_method(
             "add_group",
-            |_lua,
+            |lua,
              this,
              (name, volume, persist, effects): (
                 String,
                 f32,
                 bool,
-                Option<HashMap<String, LuaEffectConfig>>,
+                Option<HashMap<String, Value>>,
             )| {
-                let effects = if let Some(effects) = &effects {
-                    effects.iter().map(|(k, v)| (k, v.0.clone())).collect()
-                } else {
-                    Default::default()
-                };
+                let mut configs = HashMap::new();
+                for (effect, value) in effects.unwrap_or_default() {
+                    let config = LuaEffectConfig::parse_for(lua, &name, &effect, value)?;
+                    configs.insert(effect, config.0);
+                }
                 map2lua_error!(
-                    this.0.add_group(name, volume, persist, effects),
+                    this.0.add_group(name, volume, persist, configs),
                     "LuaAudio add_group"
                 )?;
                 Ok(())
             },
         );
+        methods.add_method("remove_group", |_lua, this, group: String| {
+            map2lua_error!(this.0.remove_group(group), "LuaAudio remove_group")?;
+            Ok(())
+        });
+        methods.add_method("list_groups", |lua, this, ()| {
+            lua.to_value(&this.0.list_groups())
+        });
+        methods.add_method(
+            "set_group_volume",
+            |_lua, this, (group, volume, duration): (String, f32, u64)| {
+                map2lua_error!(
+                    this.0.set_group_volume(group, volume, duration),
+                    "LuaAudio set_group_volume"
+                )?;
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "pause_group",
+            |_lua, this, (group, duration): (String, u64)| {
+                map2lua_error!(this.0.pause_group(group, duration), "LuaAudio pause_group")?;
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "resume_group",
+            |_lua, this, (group, duration): (String, u64)| {
+                map2lua_error!(
+                    this.0.resume_group(group, duration),
+                    "LuaAudio resume_group"
+                )?;
+                Ok(())
+            },
+        );
         methods.add_method(
             "play",
-            |_lua,
+            |lua,
              this,
-             (group, audio, volume, panning, position): (
+             (group, audio, options, panning, position): (
                 String,
                 String,
-                Option<f32>,
+                Value,
                 Option<f32>,
                 Option<f64>,
             )| {
-                map2lua_error!(
-                    this.0.play(group, audio, volume, panning, position),
-                    "LuaAudio play"
-                )?;
+                let options = LuaPlayOptions::from_args(lua, options, panning, position)?.0;
+                map2lua_error!(this.0.play(group, audio, options), "LuaAudio play")?;
                 Ok(())
             },
         );
+        methods.add_method(
+            "play_pcm",
+            |_lua,
+             this,
+             (group, samples, sample_rate, channels, options): (
+                String,
+                Vec<f32>,
+                u32,
+                Option<u16>,
+                Option<LuaPlayOptions>,
+            )| {
+                let options = options.unwrap_or_default().0;
+                let channels = channels.unwrap_or(1);
+                map2lua_error!(
+                    this.0
+                        .play_pcm(group, samples, sample_rate, channels, options),
+                    "LuaAudio play_pcm"
+                )
+            },
+        );
         methods.add_method(
             "pause",
             |_lua, this, (group, audio, duration): (String, String, u64)| {
@@ -120,26 +232,122 @@ impl UserData for LuaAudio {
             },
         );
 
+        methods.add_method(
+            "set_playback_rate",
+            |_lua, this, (group, audio, rate, duration): (String, String, f64, u64)| {
+                map2lua_error!(
+                    this.0.set_playback_rate(group, audio, rate, duration),
+                    "LuaAudio set_playback_rate"
+                )?;
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "set_time_scale",
+            |_lua, this, (scale, duration): (f64, u64)| {
+                map2lua_error!(
+                    this.0.set_time_scale(scale, duration),
+                    "LuaAudio set_time_scale"
+                )?;
+                Ok(())
+            },
+        );
+        methods.add_method("time_scale", |_lua, this, ()| Ok(this.0.time_scale()));
+        methods.add_method("master_levels", |_lua, this, ()| Ok(this.0.master_levels()));
+        methods.add_method(
+            "set_time_scaled",
+            |_lua, this, (group, scaled): (String, bool)| {
+                map2lua_error!(
+                    this.0.set_time_scaled(group, scaled),
+                    "LuaAudio set_time_scaled"
+                )?;
+                Ok(())
+            },
+        );
+        methods.add_method(
+            "preload",
+            |_lua, this, audio: mlua::Either<String, Vec<String>>| {
+                let result = match audio {
+                    mlua::Either::Left(audio) => this.0.preload(&[audio]),
+                    mlua::Either::Right(audios) => this.0.preload(&audios),
+                };
+                map2lua_error!(result, "LuaAudio preload")?;
+                Ok(())
+            },
+        );
+        methods.add_method("unload", |_lua, this, audio: String| {
+            this.0.unload(&audio);
+            Ok(())
+        });
+        methods.add_method("stats", |lua, this, ()| lua.to_value(&this.0.cache_stats()));
+        methods.add_method("set_cache_limit", |_lua, this, max_bytes: usize| {
+            this.0.set_cache_limit(max_bytes);
+            Ok(())
+        });
+        methods.add_method("set_mute", |_lua, this, (group, muted): (String, bool)| {
+            map2lua_error!(this.0.set_mute(group, muted), "LuaAudio set_mute")?;
+            Ok(())
+        });
+        methods.add_method("set_solo", |_lua, this, (group, solo): (String, bool)| {
+            map2lua_error!(this.0.set_solo(group, solo), "LuaAudio set_solo")?;
+            Ok(())
+        });
+        methods.add_method("mute_group", |_lua, this, group: String| {
+            map2lua_error!(this.0.mute_group(group), "LuaAudio mute_group")?;
+            Ok(())
+        });
+        methods.add_method("unmute_group", |_lua, this, group: String| {
+            map2lua_error!(this.0.unmute_group(group), "LuaAudio unmute_group")?;
+            Ok(())
+        });
+        methods.add_method("solo_group", |_lua, this, group: String| {
+            map2lua_error!(this.0.solo_group(group), "LuaAudio solo_group")?;
+            Ok(())
+        });
+        methods.add_method("unsolo", |_lua, this, ()| {
+            this.0.unsolo();
+            Ok(())
+        });
+        methods.add_method("is_group_muted", |_lua, this, group: String| {
+            Ok(this.0.is_group_muted(&group))
+        });
+        methods.add_method("is_group_soloed", |_lua, this, group: String| {
+            Ok(this.0.is_group_soloed(&group))
+        });
+        methods.add_method("mute", |_lua, this, (group, audio): (String, String)| {
+            map2lua_error!(this.0.mute(group, audio), "LuaAudio mute")?;
+            Ok(())
+        });
+        methods.add_method("unmute", |_lua, this, (group, audio): (String, String)| {
+            map2lua_error!(this.0.unmute(group, audio), "LuaAudio unmute")?;
+            Ok(())
+        });
+        methods.add_method(
+            "is_muted",
+            |_lua, this, (group, audio): (String, String)| Ok(this.0.is_muted(group, audio)),
+        );
         methods.add_method("state", |_lua, this, (group, audio): (String, String)| {
-            if let Some(state) = this.0.state(group, audio) {
-                Ok(Some(format!("{:?}", state)))
+            if let Some(state) = this.0.state(&group, &audio) {
+                let rate = this.0.playback_rate(group, audio);
+                Ok((Some(format!("{:?}", state)), rate))
             } else {
-                Ok(None)
+                Ok((None, None))
             }
         });
+        methods.add_method(
+            "position",
+            |_lua, this, (group, audio): (String, String)| {
+                let position = this.0.position(&group, &audio);
+                Ok((position, this.0.playback_rate(group, audio)))
+            },
+        );
 
         methods.add_method(
             "set_effect",
-            |_lua,
-             this,
-             (group,effect, config,tween): (
-                String,
-                String,
-                LuaEffectConfig,
-                Option<u64>,
-            )| {
+            |lua, this, (group, effect, config, tween): (String, String, Value, Option<u64>)| {
+                let config = LuaEffectConfig::parse_for(lua, &group, &effect, config)?;
                 map2lua_error!(
-                    this.0.set_effect(group, effect, config.0,tween),
+                    this.0.set_effect(group, effect, config.0, tween),
                     "LuaAudio set_effect"
                 )?;
                 Ok(())
@@ -167,3 +375,79 @@ impl UserData for LuaAudio {
         });
     }
 }
+
+#[test]
+fn test_lua_effect_config() -> mlua::Result<()> {
+    let lua = mlua::Lua::new();
+    // one per type, in the order of `EffectConfig::TYPES`
+    let configs: Vec<Value> = lua
+        .load(
+            r#"return {
+                {type = "compressor", attack_duration = 10, ratio = 4, mix = 0.5, threshold = -12},
+                {type = "delay", delay_time = 250, feedback = -6, mix = 0.3},
+                {type = "distortion", drive = 12, kind = "SoftClip", mix = 1},
+                {type = "eq_filter", kind = "LowShelf", frequency = 200, gain = 3, q = 0.7},
+                {type = "filter", cutoff = 800, mode = "HighPass", resonance = 0.2},
+                {type = "panning_control", panning = -0.5},
+                {type = "reverb", damping = 0.8, feedback = 0.9, stereo_width = 1},
+                {type = "volume_control", volume = -3},
+            }"#,
+        )
+        .eval()?;
+    assert_eq!(configs.len(), EffectConfig::TYPES.len());
+    for (value, kind) in configs.into_iter().zip(EffectConfig::TYPES) {
+        let config = LuaEffectConfig::from_lua(value, &lua)?;
+        assert_eq!(config.0.type_name(), kind);
+        let back = LuaEffectConfig::from_lua(config.clone().into_lua(&lua)?, &lua)?;
+        assert_eq!(back.0, config.0);
+    }
+
+    let parse = |code: &str| {
+        let value = lua.load(code).eval::<Value>().unwrap();
+        LuaEffectConfig::parse_for(&lua, "sfx", "echo", value)
+            .unwrap_err()
+            .to_string()
+    };
+    let types = EffectConfig::TYPES.join(", ");
+    assert_eq!(
+        parse(r#"return {type = "comprossor"}"#),
+        format!(
+            "runtime error: group sfx effect echo: unknown type comprossor, expect one of {}",
+            types
+        )
+    );
+    assert!(parse(r#"return {Compressor = {}}"#)
+        .ends_with(&format!("missing type, expect one of {}", types)));
+    assert!(parse(r#"return {type = "compressor", atack_duration = 5}"#)
+        .contains("compressor: unknown field `atack_duration`"));
+    assert!(
+        parse(r#"return {type = "delay", mix = -0.5}"#).ends_with("delay.mix = -0.5, expect 0..1")
+    );
+    assert!(parse(r#"return {type = "compressor", ratio = 0.5}"#)
+        .ends_with("compressor.ratio = 0.5, expect >= 1"));
+    assert!(parse(r#"return {type = "eq_filter", frequency = 0}"#)
+        .ends_with("eq_filter.frequency = 0, expect > 0"));
+    assert!(parse(r#"return {type = "panning_control", panning = 2}"#)
+        .ends_with("panning_control.panning = 2, expect -1..1"));
+    Ok(())
+}
+
+#[test]
+fn test_play_args() -> mlua::Result<()> {
+    let lua = mlua::Lua::new();
+    let parse = |code: &str| {
+        let (options, panning, position) = lua.load(code).eval()?;
+        LuaPlayOptions::from_args(&lua, options, panning, position).map(|options| options.0)
+    };
+    let table = parse("return {volume = 0.5, rate = 2}, nil, nil")?;
+    assert_eq!((table.volume, table.rate), (Some(0.5), Some(2.0)));
+    // the positional form of older scripts
+    let positional = parse("return 0.5, -1, 3")?;
+    assert_eq!(
+        (positional.volume, positional.panning, positional.position),
+        (Some(0.5), Some(-1.0), Some(3.0))
+    );
+    assert_eq!(parse("return nil, nil, nil")?.volume, None);
+    assert!(parse("return {volume = 0.5}, -1, nil").is_err());
+    Ok(())
+}
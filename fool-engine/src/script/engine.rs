@@ -1,22 +1,37 @@
-use super::super::engine::EngineStatus;
+use super::super::engine::SharedStatus;
 use super::audio::LuaAudio;
 use super::graphics::draw::LuaScene;
+use super::graphics::postfx::LuaPostFx;
 use super::graphics::sprite::{LuaSrpite, Sprite};
-use super::gui::EguiContext;
+use super::graphics::video::LuaVideos;
+use super::graphics::view::{LuaCamera, LuaViews};
+use super::gui::{EguiContext, GuiWorkspace};
+use super::resource::{LuaImageOps, LuaResource};
+use super::rng::LuaRng;
 use super::types::{LuaPoint, LuaSize};
+use crate::accessibility::Accessibility;
+use crate::achievements::Achievements;
 use crate::engine::event::EngineEvent;
 use crate::engine::ResourceManager;
-use crate::map2lua_error;
+use crate::engine::{DebugOverlay, FocusPause};
+use crate::entity::EntityRegistry;
+use crate::event::{ActionMap, EventBus, EventQueue, TextInput};
+use crate::mods::ModManager;
+use crate::netplay::Netplay;
 use crate::save::SaveManager;
+use crate::{lua_create_table, map2lua_error};
 use chrono::{Local, Utc};
 use egui::Context;
 use fool_audio::AudioSystem;
-use fool_graphics::canvas::SceneGraph;
+use fool_graphics::canvas::{Affine, SceneGraph, SceneNode};
+use fool_graphics::{parse_quality, AdaptiveQuality, EffectStack, GpuInfo, QUALITY_LADDER};
 use fool_window::{AppEvent, CustomEvent, EventProxy, WindowCursor};
-use mlua::{UserData, UserDataMethods};
-use parking_lot::RwLock;
+use mlua::{LuaSerdeExt, UserData, UserDataMethods};
+use parking_lot::{Mutex, RwLock};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{str::FromStr, sync::Arc};
+use vello::peniko::color::{parse_color, Srgb};
 use winit::{
     dpi::{LogicalPosition, LogicalSize, PhysicalSize, Position, Size},
     window::{CursorGrabMode, CursorIcon, Fullscreen, Window},
@@ -28,25 +43,65 @@ pub struct LuaEngine {
     pub graph: LuaGraphics,
     pub audio: LuaAudio,
     pub save: SaveManager,
-    pub status: Arc<RwLock<EngineStatus>>,
+    pub actions: ActionMap,
+    pub mods: ModManager,
+    pub entities: EntityRegistry,
+    pub postfx: LuaPostFx,
+    pub netplay: Netplay,
+    pub video: LuaVideos,
+    pub image: LuaImageOps,
+    pub rng: LuaRng,
+    pub accessibility: Accessibility,
+    pub achievements: Achievements,
+    pub bus: EventBus,
+    pub text_input: TextInput,
+    pub input_queue: EventQueue,
+    pub debug_overlay: DebugOverlay,
+    pub focus_pause: FocusPause,
+    pub status: SharedStatus,
+    /// last known mouse position in window pixels
+    pub cursor: Arc<RwLock<Option<(f64, f64)>>>,
 }
 
 #[derive(Clone)]
 pub struct LuaGraphics {
     pub scene_graph: Arc<RwLock<SceneGraph>>,
     pub resource: ResourceManager,
+    pub gpu_info: GpuInfo,
+    /// the split screen, drawn instead of the single camera when set
+    pub views: LuaViews,
 }
 impl UserData for LuaGraphics {
     fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
         methods.add_method("draw_shape", |_lua, this, scene: LuaScene| {
             let node = scene.0;
+            map2lua_error!(node.validate(), "draw_shape")?;
             this.scene_graph.write().root.add_child(&node);
             Ok(())
         });
+        methods.add_method(
+            "svg",
+            |_lua, this, (name, x, y, scale): (String, f64, f64, Option<f64>)| {
+                let mut node = SceneNode::svg(name);
+                node.style.translation =
+                    Affine::translate((x, y)) * Affine::scale(scale.unwrap_or(1.0));
+                this.scene_graph.write().root.add_child(&node);
+                Ok(())
+            },
+        );
+        methods.add_method("svg_size", |lua, this, name: String| {
+            let size = map2lua_error!(this.resource.graphics_svg.size(&name), "svg_size")?;
+            let table = lua_create_table!(lua, [width = size.width, height = size.height]);
+            Ok(table)
+        });
+        methods.add_method("gpu_info", |lua, this, ()| lua.to_value(&this.gpu_info));
         methods.add_method("set_scale", |_lua, this, scale: Option<f64>| {
             this.scene_graph.write().set_scale(scale);
             Ok(())
         });
+        methods.add_method("ui_scale", |_lua, this, ()| {
+            Ok(this.scene_graph.read().ui_scale())
+        });
         methods.add_method(
             "create_sprite",
             |_lua, this, (image, frame_size, num): (String, LuaSize<u32>, usize)| {
@@ -55,44 +110,98 @@ impl UserData for LuaGraphics {
                 Ok(LuaSrpite {
                     sprite: sprite,
                     scene_graph: this.scene_graph.clone(),
+                    resource: this.resource.clone(),
                 })
             },
         );
     }
 }
+/// the engine parts the lua engine is built on, shared with the render and the engine
+pub struct LuaEngineServices {
+    pub resource: ResourceManager,
+    pub scene_graph: Arc<RwLock<SceneGraph>>,
+    pub status: SharedStatus,
+    pub save_path: PathBuf,
+    pub gpu_info: GpuInfo,
+    pub effects: Arc<Mutex<EffectStack>>,
+    pub quality: Arc<Mutex<AdaptiveQuality>>,
+    pub clipboard: bool,
+    pub mods: ModManager,
+}
 impl LuaEngine {
     pub fn new(
         window: Arc<Window>,
         context: Context,
         proxy: EventProxy,
-        resource: ResourceManager,
-        scene_graph: Arc<RwLock<SceneGraph>>,
-        status: Arc<RwLock<EngineStatus>>,
-        save_path: impl Into<PathBuf>,
+        services: LuaEngineServices,
     ) -> anyhow::Result<Self> {
+        let LuaEngineServices {
+            resource,
+            scene_graph,
+            status,
+            save_path,
+            gpu_info,
+            effects,
+            quality,
+            clipboard,
+            mods,
+        } = services;
         let size = window.inner_size();
+        let save = SaveManager::new(save_path);
         let ui_ctx = EguiContext {
             context: context,
             width: size.width as _,
             heigth: size.height as _,
             resource: resource.clone(),
+            workspace: GuiWorkspace::new(save.clone(), scene_graph.clone()),
+            toasts: Default::default(),
         };
+        let text_input = TextInput::new(window.clone(), clipboard);
         let window = LuaWindow {
             window: window,
             resource: resource.clone(),
             proxy: proxy,
+            visible: Arc::new(AtomicBool::new(true)),
+            quality,
         };
         let audio = AudioSystem::new(resource.raw_resource.clone())?;
+        let actions = ActionMap::new(save.clone());
+        let accessibility = Accessibility::new(
+            save.clone(),
+            effects.clone(),
+            actions.clone(),
+            scene_graph.clone(),
+        );
+        let video = LuaVideos::new(resource.clone(), audio.clone());
+        let image = LuaImageOps::new(resource.clone());
         Ok(Self {
             window,
             ui_ctx,
             graph: LuaGraphics {
                 scene_graph,
                 resource: resource,
+                gpu_info,
+                views: Default::default(),
             },
+            entities: EntityRegistry::new(audio.clone()),
+            postfx: LuaPostFx::new(effects),
+            netplay: Netplay::default(),
+            video,
+            image,
+            rng: LuaRng::default(),
+            accessibility,
+            achievements: Achievements::local(save.clone()),
+            bus: Default::default(),
+            text_input,
+            input_queue: Default::default(),
+            debug_overlay: Default::default(),
+            focus_pause: Default::default(),
             audio: LuaAudio(audio),
-            save: SaveManager::new(save_path),
+            actions,
+            mods,
+            save,
             status,
+            cursor: Default::default(),
         })
     }
     pub fn resize(&mut self, w: u32, h: u32) {
@@ -102,33 +211,89 @@ impl LuaEngine {
 impl UserData for LuaEngine {
     fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
         fields.add_field_method_get("ui_ctx", |_, this| Ok(this.ui_ctx.clone()));
+        fields.add_field_method_get("toast", |_, this| Ok(this.ui_ctx.toasts.clone()));
         fields.add_field_method_get("window", |_, this| Ok(this.window.clone()));
         fields.add_field_method_get("audio", |_, this| Ok(this.audio.clone()));
         fields.add_field_method_get("graphics", |_, this| Ok(this.graph.clone()));
         fields.add_field_method_get("save", |_, this| Ok(this.save.clone()));
+        fields.add_field_method_get("actions", |_, this| Ok(this.actions.clone()));
+        fields.add_field_method_get("mods", |_, this| Ok(this.mods.clone()));
+        fields.add_field_method_get("entities", |_, this| Ok(this.entities.clone()));
+        fields.add_field_method_get("postfx", |_, this| Ok(this.postfx.clone()));
+        fields.add_field_method_get("netplay", |_, this| Ok(this.netplay.clone()));
+        fields.add_field_method_get("video", |_, this| Ok(this.video.clone()));
+        fields.add_field_method_get("image", |_, this| Ok(this.image.clone()));
+        fields.add_field_method_get("rng", |_, this| Ok(this.rng.clone()));
+        fields.add_field_method_get("accessibility", |_, this| Ok(this.accessibility.clone()));
+        fields.add_field_method_get("achievements", |_, this| Ok(this.achievements.clone()));
+        fields.add_field_method_get("bus", |_, this| Ok(this.bus.clone()));
+        fields.add_field_method_get("resource", |_, this| {
+            Ok(LuaResource(this.graph.resource.clone()))
+        });
     }
     fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
-        methods.add_method("set_running", |_, this, ()| {
-            *this.status.write() = EngineStatus::Running;
-            Ok(())
+        SharedStatus::add_lua_methods(methods, |this: &Self| &this.status);
+        // nil while the cursor is outside the window, or outside every view
+        methods.add_method("mouse_world", |lua, this, ()| {
+            let Some((x, y)) = *this.cursor.read() else {
+                return Ok(mlua::Value::Nil);
+            };
+            let graph = this.graph.scene_graph.read();
+            let (x, y) = match this.graph.views.is_empty() {
+                true => graph.screen_to_world(x, y),
+                false => match this.graph.views.view_at(&graph, x, y) {
+                    Some((_, world)) => world,
+                    None => return Ok(mlua::Value::Nil),
+                },
+            };
+            Ok(mlua::Value::Table(lua_create_table!(lua, [x = x, y = y])))
         });
-        methods.add_method("set_pause", |_, this, ()| {
-            *this.status.write() = EngineStatus::Pause;
-            Ok(())
+        methods.add_method("camera", |_, _this, params: Option<mlua::Table>| {
+            LuaCamera::from_table(params)
         });
-        methods.add_method("set_exiting", |_, this, ()| {
-            *this.status.write() = EngineStatus::Exiting;
-            Ok(())
+        methods.add_method("set_views", |_, this, views: Option<Vec<mlua::Table>>| {
+            this.graph.views.set(views)
+        });
+        // the 1 based view under a window position, the cursor without one,
+        // and the scene position it shows there
+        methods.add_method("view_at", |lua, this, pos: Option<LuaPoint<f64>>| {
+            let Some((x, y)) = pos.map(|pos| (pos.x, pos.y)).or(*this.cursor.read()) else {
+                return Ok(mlua::Value::Nil);
+            };
+            let graph = this.graph.scene_graph.read();
+            let Some((index, (x, y))) = this.graph.views.view_at(&graph, x, y) else {
+                return Ok(mlua::Value::Nil);
+            };
+            Ok(mlua::Value::Table(lua_create_table!(
+                lua,
+                [view = index + 1, x = x, y = y]
+            )))
         });
-        methods.add_method("is_running", |_, this, ()| {
-            Ok(*this.status.read() == EngineStatus::Running)
+        // probe optional assets without logging an error or loading them
+        methods.add_method("asset_exists", |_, this, name: String| {
+            Ok(this.graph.resource.has_asset(&name))
         });
-
-        methods.add_method("is_pause", |_, this, ()| {
-            Ok(*this.status.read() == EngineStatus::Pause)
+        methods.add_method("has_asset", |_, this, name: String| {
+            Ok(this.graph.resource.has_asset(&name))
+        });
+        methods.add_method("time", |_, _this, ()| Ok(super::time::LuaTime));
+        methods.add_method("toggle_debug_overlay", |_, this, ()| {
+            Ok(this.debug_overlay.toggle())
+        });
+        methods.add_method("set_pause_on_focus_loss", |_, this, enabled: bool| {
+            this.focus_pause.set_enabled(enabled, &this.audio.0);
+            Ok(())
         });
-        methods.add_method("is_exiting", |_, this, ()| {
-            Ok(*this.status.read() == EngineStatus::Exiting)
+        methods.add_method("pause_on_focus_loss", |_, this, ()| {
+            Ok(this.focus_pause.is_enabled())
+        });
+        // after the current frame, main.lua and `init` run again with the mods as set now
+        methods.add_method("reload_scripts", |_, this, ()| {
+            let event: Box<dyn CustomEvent> = Box::new(EngineEvent::ReloadScripts);
+            map2lua_error!(
+                this.window.proxy.send(AppEvent::CustomEvent(event)),
+                "LuaEngine reload_scripts"
+            )
         });
     }
 }
@@ -138,12 +303,17 @@ pub struct LuaWindow {
     pub window: Arc<Window>,
     pub resource: ResourceManager,
     pub proxy: EventProxy,
+    pub visible: Arc<AtomicBool>,
+    pub quality: Arc<Mutex<AdaptiveQuality>>,
 }
 
 impl UserData for LuaWindow {
     fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
-        methods.add_method("set_fps", |_lua, this, fps: u32| {
-            log::trace!("set_fps to: {}", fps);
+        methods.add_method("is_visible", |_lua, this, ()| {
+            Ok(this.visible.load(Ordering::Relaxed))
+        });
+        methods.add_method("set_fps", |_lua, this, fps: Option<u32>| {
+            log::trace!("set_fps to: {:?}", fps);
             let event: Box<dyn CustomEvent> = Box::new(EngineEvent::FPS(fps));
             map2lua_error!(
                 this.proxy.send(AppEvent::CustomEvent(event)),
@@ -151,6 +321,70 @@ impl UserData for LuaWindow {
             )?;
             Ok(())
         });
+        methods.add_method("set_antialiasing", |_lua, this, mode: String| {
+            let aa = fool_graphics::render::parse_aa(&mode).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!(
+                    "unknown antialiasing {}, expect area, msaa8 or msaa16",
+                    mode
+                ))
+            })?;
+            let event: Box<dyn CustomEvent> = Box::new(EngineEvent::Antialiasing(aa));
+            map2lua_error!(
+                this.proxy.send(AppEvent::CustomEvent(event)),
+                "LuaWindow set_antialiasing"
+            )?;
+            Ok(())
+        });
+        methods.add_method("set_pixel_perfect", |_lua, this, enabled: bool| {
+            let event: Box<dyn CustomEvent> = Box::new(EngineEvent::PixelPerfect(enabled));
+            map2lua_error!(
+                this.proxy.send(AppEvent::CustomEvent(event)),
+                "LuaWindow set_pixel_perfect"
+            )?;
+            Ok(())
+        });
+        methods.add_method("render_quality", |lua, this, ()| {
+            let quality = this.quality.lock();
+            let level = quality.level();
+            Ok(lua_create_table!(
+                lua,
+                [
+                    name = level.name(),
+                    aa = fool_graphics::render::aa_name(level.aa),
+                    scale = level.scale,
+                    adaptive = quality.enabled(),
+                    floor = QUALITY_LADDER[quality.floor()].name()
+                ]
+            ))
+        });
+        methods.add_method("set_quality_floor", |_lua, this, name: String| {
+            let floor = parse_quality(&name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!(
+                    "unknown render quality {}, expect msaa16, msaa8, area, area@0.85 or area@0.75",
+                    name
+                ))
+            })?;
+            // applied with the next presented frame
+            this.quality.lock().set_floor(floor);
+            Ok(())
+        });
+        methods.add_method("set_background", |_lua, this, color: String| {
+            let color = parse_color(&color)
+                .map_err(|err| {
+                    mlua::Error::RuntimeError(format!("invalid color {}: {}", color, err))
+                })?
+                .to_alpha_color::<Srgb>();
+            let event: Box<dyn CustomEvent> = Box::new(EngineEvent::ClearColor(color));
+            map2lua_error!(
+                this.proxy.send(AppEvent::CustomEvent(event)),
+                "LuaWindow set_background"
+            )?;
+            Ok(())
+        });
+        methods.add_method("reset_layout", |_lua, this, ()| {
+            map2lua_error!(this.proxy.reset_window_state(), "LuaWindow reset_layout")?;
+            Ok(())
+        });
         methods.add_method("capture", |_lua, this, ()| {
             let capture_path = PathBuf::from(format!(
                 "{}.png",
@@ -1,3 +1,6 @@
 pub mod draw;
+pub mod postfx;
 pub mod sprite;
 pub mod types;
+pub mod video;
+pub mod view;
@@ -0,0 +1,90 @@
+use crate::map2anyhow_error;
+use fool_graphics::EffectStack;
+use mlua::{Function, Lua, LuaSerdeExt, UserData, UserDataMethods, Value};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// nil or false turns an effect off
+fn params<T: DeserializeOwned>(lua: &Lua, value: Value) -> mlua::Result<Option<T>> {
+    match value {
+        Value::Nil | Value::Boolean(false) => Ok(None),
+        Value::Boolean(true) => Ok(Some(lua.from_value(Value::Table(lua.create_table()?))?)),
+        value => Ok(Some(lua.from_value(value)?)),
+    }
+}
+
+#[derive(Clone)]
+pub struct LuaPostFx {
+    pub effects: Arc<Mutex<EffectStack>>,
+    // called once the running `fade_to` finished, replaced by the next one
+    on_faded: Arc<Mutex<Option<Function>>>,
+}
+
+impl LuaPostFx {
+    pub fn new(effects: Arc<Mutex<EffectStack>>) -> Self {
+        Self {
+            effects,
+            on_faded: Default::default(),
+        }
+    }
+    /// runs the `fade_to` callback after the render advanced the fade to its end
+    pub fn poll(&self) -> anyhow::Result<()> {
+        if self.effects.lock().take_fades_done() == 0 {
+            return Ok(());
+        }
+        let callback = self.on_faded.lock().take();
+        if let Some(callback) = callback {
+            map2anyhow_error!(callback.call::<()>(()), "run fade_to callback")?;
+        }
+        Ok(())
+    }
+}
+
+impl UserData for LuaPostFx {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("set", |lua, this, (name, value): (String, Value)| {
+            let mut effects = this.effects.lock();
+            match name.as_str() {
+                "chromatic_aberration" => effects.chromatic_aberration = params(lua, value)?,
+                "color_grade" => effects.color_grade = params(lua, value)?,
+                "vignette" => effects.vignette = params(lua, value)?,
+                "fade" => effects.fade = params(lua, value)?,
+                _ => {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "unknown effect {}, expect chromatic_aberration, color_grade, vignette or fade",
+                        name
+                    )));
+                }
+            }
+            Ok(())
+        });
+        methods.add_method(
+            "fade_to",
+            |_, this, (target, duration, callback): (f32, u64, Option<Function>)| {
+                this.effects
+                    .lock()
+                    .fade_to(target, duration as f32 / 1000.0);
+                *this.on_faded.lock() = callback;
+                Ok(())
+            },
+        );
+        methods.add_method("is_fading", |_, this, ()| {
+            Ok(this.effects.lock().is_fading())
+        });
+        methods.add_method(
+            "shake",
+            |_, this, (amplitude, frequency, duration): (f32, f32, u64)| {
+                this.effects
+                    .lock()
+                    .shake(amplitude, frequency, duration as f32 / 1000.0);
+                Ok(())
+            },
+        );
+        methods.add_method("clear", |_, this, ()| {
+            this.effects.lock().clear();
+            this.on_faded.lock().take();
+            Ok(())
+        });
+    }
+}
@@ -1,11 +1,42 @@
-use crate::{map2lua_error, script::types::LuaPoint};
-pub use fool_graphics::canvas::{Animation, Frame, SceneGraph, Sprite};
+use crate::{
+    lua_create_table, map2lua_error,
+    resource::ResourceManager,
+    script::{frame_time, types::LuaPoint},
+};
+pub use fool_graphics::canvas::{
+    Animation, AnimationController, ControllerDef, ControllerEvent, Frame, SceneGraph, Sprite,
+};
 use mlua::UserData;
 use parking_lot::RwLock;
+use std::path::Path;
 use std::sync::Arc;
 pub struct LuaSrpite {
     pub scene_graph: Arc<RwLock<SceneGraph>>,
     pub sprite: Sprite<usize>,
+    pub resource: ResourceManager,
+}
+
+/// a controller graph from a `.toml` or `.json` data file
+pub fn parse_controller(path: &str, content: &str) -> anyhow::Result<ControllerDef> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(content)
+            .map_err(|err| anyhow::anyhow!("parse animation controller {} failed: {}", path, err)),
+        Some("json") => serde_json::from_str(content)
+            .map_err(|err| anyhow::anyhow!("parse animation controller {} failed: {}", path, err)),
+        _ => anyhow::bail!(
+            "unsupported animation controller {}, expected .toml or .json",
+            path
+        ),
+    }
+}
+
+impl LuaSrpite {
+    fn create_controller(&self, path: &str) -> anyhow::Result<AnimationController> {
+        let content = self.resource.raw_resource.get(path)?.to_string()?;
+        let def = parse_controller(path, &content)?;
+        AnimationController::new(def, &self.sprite)
+            .map_err(|err| anyhow::anyhow!("{}: {}", path, err))
+    }
 }
 
 impl UserData for LuaSrpite {
@@ -18,36 +49,67 @@ impl UserData for LuaSrpite {
                         .create_animation(name, frames_id.into_iter(), fps),
                     "create_animation"
                 )?;
-                Ok(LuaAnimation {
-                    animation,
-                    scene_graph: this.scene_graph.clone(),
-                })
+                Ok(LuaAnimation::new(animation, this.scene_graph.clone()))
             },
         );
         methods.add_method_mut("get_animation", |_lua, this, name: String| {
             let animation = map2lua_error!(this.sprite.get_animation(name), "get_animation")?;
-            Ok(LuaAnimation {
-                animation,
-                scene_graph: this.scene_graph.clone(),
-            })
+            Ok(LuaAnimation::new(animation, this.scene_graph.clone()))
         });
         methods.add_method_mut("list_animation", |_lua, this, ()| {
             let animations = this.sprite.list_animation();
             Ok(animations)
         });
+        methods.add_method("create_controller", |_lua, this, path: String| {
+            let controller = map2lua_error!(this.create_controller(&path), "create_controller")?;
+            Ok(LuaAnimationController {
+                controller,
+                scene_graph: this.scene_graph.clone(),
+            })
+        });
     }
 }
 
 pub struct LuaAnimation {
     animation: Animation,
     scene_graph: Arc<RwLock<SceneGraph>>,
+    // follow real time instead of the scaled frame clock
+    unscaled: bool,
+    // the clock reading at the last `next`
+    last: Option<f64>,
+}
+
+impl LuaAnimation {
+    fn new(animation: Animation, scene_graph: Arc<RwLock<SceneGraph>>) -> Self {
+        Self {
+            animation,
+            scene_graph,
+            unscaled: false,
+            last: None,
+        }
+    }
+    fn next(&mut self) {
+        let time = frame_time();
+        let now = match self.unscaled {
+            true => time.total,
+            false => time.scaled_total,
+        };
+        let dt = now - self.last.unwrap_or(now);
+        self.last = Some(now);
+        self.animation.advance(dt);
+    }
 }
 
 impl UserData for LuaAnimation {
     fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
         methods.add_method_mut("count", |_lua, this, ()| Ok(this.animation.count()));
         methods.add_method_mut("current", |_lua, this, ()| Ok(this.animation.current()));
-        methods.add_method_mut("next", |_lua, this, ()| Ok(this.animation.next()));
+        methods.add_method_mut("next", |_lua, this, ()| Ok(this.next()));
+        methods.add_method_mut("set_unscaled", |_lua, this, unscaled: bool| {
+            this.unscaled = unscaled;
+            this.last = None;
+            Ok(())
+        });
         methods.add_method_mut("draw", |_lua, this, pos: LuaPoint<f64>| {
             let node = this.animation.to_node(pos.x, pos.y);
             this.scene_graph.write().root.add_child(&node);
@@ -55,3 +117,84 @@ impl UserData for LuaAnimation {
         });
     }
 }
+
+pub struct LuaAnimationController {
+    controller: AnimationController,
+    scene_graph: Arc<RwLock<SceneGraph>>,
+}
+
+impl UserData for LuaAnimationController {
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method_mut("set", |_lua, this, (name, value): (String, f64)| {
+            this.controller.set(name, value);
+            Ok(())
+        });
+        methods.add_method_mut("set_bool", |_lua, this, (name, value): (String, bool)| {
+            this.controller.set_bool(name, value);
+            Ok(())
+        });
+        methods.add_method_mut("trigger", |_lua, this, name: String| {
+            this.controller.trigger(name);
+            Ok(())
+        });
+        methods.add_method_mut("step", |_lua, this, dt: f64| {
+            this.controller.step(dt);
+            Ok(())
+        });
+        methods.add_method("state", |_lua, this, ()| {
+            Ok(this.controller.state().to_owned())
+        });
+        methods.add_method("normalized_time", |_lua, this, ()| {
+            Ok(this.controller.normalized_time())
+        });
+        methods.add_method("blend", |_lua, this, ()| Ok(this.controller.blend()));
+        methods.add_method_mut("events", |lua, this, ()| {
+            let events = lua.create_table()?;
+            for event in this.controller.take_events() {
+                let (kind, state) = match event {
+                    ControllerEvent::Enter(state) => ("enter", state),
+                    ControllerEvent::Exit(state) => ("exit", state),
+                };
+                events.push(lua_create_table!(lua, [kind = kind, state = state]))?;
+            }
+            Ok(events)
+        });
+        methods.add_method("draw", |_lua, this, pos: LuaPoint<f64>| {
+            let node = this.controller.to_node(pos.x, pos.y);
+            this.scene_graph.write().root.add_child(&node);
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn test_parse_controller() -> anyhow::Result<()> {
+    let def = parse_controller(
+        "anim/player.toml",
+        r#"
+        initial = "idle"
+        [states.idle]
+        animation = "idle"
+        [states.hurt]
+        animation = "hurt"
+        speed = 1.5
+        [[transitions]]
+        from = "any"
+        to = "hurt"
+        duration = 0.1
+        conditions = [{ param = "hurt" }, { param = "health", op = "<", value = 10 }]
+        "#,
+    )?;
+    assert_eq!(def.initial, "idle");
+    assert_eq!(def.states["hurt"].speed, 1.5);
+    assert_eq!(def.transitions[0].conditions.len(), 2);
+    let err = parse_controller(
+        "anim/player.toml",
+        "initial = \"idle\"\n[states.idle]\nclip = \"idle\"",
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("anim/player.toml"), "{}", err);
+    assert!(parse_controller("anim/player.yaml", "").is_err());
+    Ok(())
+}
@@ -0,0 +1,355 @@
+use super::draw::LuaScene;
+use crate::engine::ResourceManager;
+use crate::{map2anyhow_error, map2lua_error};
+use fool_audio::{AudioSystem, PlayOptions};
+use fool_graphics::canvas::{Blob, Image, ImageFormat, ImageManager, SceneNode};
+#[cfg(feature = "webm")]
+use fool_graphics::video::WebmVideo;
+use fool_graphics::video::{ImageSequence, ReadFrameFile, VideoManifest, VideoPlayer};
+use mlua::{Function, Table, UserData, UserDataMethods};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use vello::kurbo::{Point, Rect, Size};
+
+// audio further off the video clock than this is moved back onto it
+const AUDIO_DRIFT: f64 = 0.1;
+// the group the audio of a video plays in unless it names one
+const DEFAULT_AUDIO_GROUP: &str = "music";
+
+/// where the audio has to seek to, None while it is close enough to the video
+pub fn audio_resync(video: f64, audio: f64) -> Option<f64> {
+    ((audio - video).abs() > AUDIO_DRIFT).then_some(video)
+}
+
+struct VideoAudio {
+    group: String,
+    music: String,
+    started: bool,
+}
+
+struct VideoState {
+    player: VideoPlayer,
+    // the name the current frame is loaded under in `images`
+    key: String,
+    images: ImageManager,
+    audio_system: AudioSystem,
+    audio: Option<VideoAudio>,
+    // where the clock was last frame, it going back is a loop or a seek
+    last_position: f64,
+    on_finished: Option<Function>,
+}
+
+impl VideoState {
+    // plays the audio from where the video is
+    fn restart_audio(&mut self) {
+        let position = self.player.position();
+        let Some(audio) = &mut self.audio else {
+            return;
+        };
+        if audio.started {
+            let _ = self.audio_system.stop(&audio.group, &audio.music, 0);
+        }
+        let options = PlayOptions {
+            position: Some(position),
+            ..Default::default()
+        };
+        audio.started = match self.audio_system.play(&audio.group, &audio.music, options) {
+            Ok(_) => true,
+            Err(err) => {
+                log::error!("play video audio {} failed: {}", audio.music, err);
+                false
+            }
+        };
+    }
+    fn pause_audio(&mut self) {
+        if let Some(audio) = self.audio.as_ref().filter(|audio| audio.started) {
+            let _ = self.audio_system.pause(&audio.group, &audio.music, 0);
+        }
+    }
+    fn stop_audio(&mut self) {
+        if let Some(audio) = self.audio.as_mut().filter(|audio| audio.started) {
+            let _ = self.audio_system.stop(&audio.group, &audio.music, 0);
+            audio.started = false;
+        }
+    }
+    fn play(&mut self) {
+        self.player.play();
+        match &self.audio {
+            Some(audio) if audio.started => {
+                let _ = self.audio_system.resume(&audio.group, &audio.music, 0);
+            }
+            Some(_) => self.restart_audio(),
+            None => {}
+        }
+        self.last_position = self.player.position();
+    }
+    fn seek(&mut self, position: f64) {
+        self.player.seek(position);
+        match self.player.is_playing() {
+            true => self.restart_audio(),
+            false => self.stop_audio(),
+        }
+        self.last_position = self.player.position();
+    }
+    // the audio follows the video clock, the frames can't follow the audio
+    fn sync_audio(&mut self) {
+        if !self.player.is_playing() {
+            return;
+        }
+        let position = self.player.position();
+        let looped = position < self.last_position;
+        self.last_position = position;
+        if !self.audio.as_ref().is_some_and(|audio| audio.started) {
+            return;
+        }
+        if looped {
+            self.restart_audio();
+            return;
+        }
+        let Some(audio) = &self.audio else {
+            return;
+        };
+        let Some(audio_position) = self.audio_system.position(&audio.group, &audio.music) else {
+            return;
+        };
+        if let Some(target) = audio_resync(position, audio_position) {
+            log::debug!(
+                "video audio {} drifted to {:.3}s, video at {:.3}s",
+                audio.music,
+                audio_position,
+                position
+            );
+            let _ = self
+                .audio_system
+                .seek_to(&audio.group, &audio.music, target);
+        }
+    }
+    // the callback to run when it finished
+    fn update(&mut self, dt: f64) -> Option<Function> {
+        if let Some(image) = self.player.update(dt) {
+            self.images.load(self.key.clone(), Arc::new(image));
+        }
+        self.sync_audio();
+        if !self.player.take_finished() {
+            return None;
+        }
+        self.stop_audio();
+        self.on_finished.clone()
+    }
+}
+
+impl Drop for VideoState {
+    fn drop(&mut self) {
+        self.stop_audio();
+        self.images.remove(self.key.clone());
+    }
+}
+
+/// a video opened by `LuaVideos::open`, it stops once the script drops it
+#[derive(Clone)]
+pub struct LuaVideo(Arc<Mutex<VideoState>>);
+
+impl UserData for LuaVideo {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // the current frame stretched over a w x h rect centered on x, y
+        methods.add_method("node", |_, this, (x, y, w, h): (f64, f64, f64, f64)| {
+            let size = Size::new(w, h);
+            let dest = Rect::from_center_size(Point::new(x, y), size);
+            let key = this.0.lock().key.clone();
+            Ok(LuaScene(SceneNode::tiled_image(key, dest, size)))
+        });
+        methods.add_method("play", |_, this, ()| {
+            this.0.lock().play();
+            Ok(())
+        });
+        methods.add_method("pause", |_, this, ()| {
+            let mut state = this.0.lock();
+            state.player.pause();
+            state.pause_audio();
+            Ok(())
+        });
+        methods.add_method("seek", |_, this, position: f64| {
+            this.0.lock().seek(position);
+            Ok(())
+        });
+        // jumps to the end, `on_finished` runs as if it played through
+        methods.add_method("skip", |_, this, ()| {
+            let mut state = this.0.lock();
+            state.player.skip();
+            state.stop_audio();
+            Ok(())
+        });
+        methods.add_method("set_loop", |_, this, looping: bool| {
+            this.0.lock().player.set_looping(looping);
+            Ok(())
+        });
+        methods.add_method("on_finished", |_, this, callback: Option<Function>| {
+            this.0.lock().on_finished = callback;
+            Ok(())
+        });
+        methods.add_method("is_playing", |_, this, ()| {
+            Ok(this.0.lock().player.is_playing())
+        });
+        methods.add_method("position", |_, this, ()| {
+            Ok(this.0.lock().player.position())
+        });
+        methods.add_method("duration", |_, this, ()| {
+            Ok(this.0.lock().player.duration())
+        });
+        methods.add_method("dropped_frames", |_, this, ()| {
+            Ok(this.0.lock().player.dropped_frames())
+        });
+        // stops it and its audio, the callback is let go too
+        methods.add_method("close", |_, this, ()| {
+            let mut state = this.0.lock();
+            state.player.pause();
+            state.stop_audio();
+            state.on_finished = None;
+            Ok(())
+        });
+    }
+}
+
+/// opens videos and moves the ones still referenced forward every frame
+#[derive(Clone)]
+pub struct LuaVideos {
+    resource: ResourceManager,
+    audio: AudioSystem,
+    videos: Arc<Mutex<Vec<Weak<Mutex<VideoState>>>>>,
+    opened: Arc<AtomicUsize>,
+}
+
+impl LuaVideos {
+    pub fn new(resource: ResourceManager, audio: AudioSystem) -> Self {
+        Self {
+            resource,
+            audio,
+            videos: Default::default(),
+            opened: Default::default(),
+        }
+    }
+    /// the folder `path` with a `video.toml` or a `.webm` file,
+    /// `audio` is played in `group` in sync with it
+    pub fn open(&self, path: &str, audio: Option<(String, String)>) -> anyhow::Result<LuaVideo> {
+        let dir = path.trim_end_matches('/').to_owned();
+        let player = match dir.ends_with(".webm") {
+            true => self.open_webm(&dir)?,
+            false => self.open_sequence(&dir)?,
+        };
+        let key = format!(
+            "video:{}#{}",
+            dir,
+            self.opened.fetch_add(1, Ordering::Relaxed)
+        );
+        // drawn transparent until the first frame is decoded
+        let blank = Image::new(Blob::new(Arc::new(vec![0u8; 4])), ImageFormat::Rgba8, 1, 1);
+        let images = self.resource.graphics_img.clone();
+        images.load(key.clone(), Arc::new(blank));
+        let state = Arc::new(Mutex::new(VideoState {
+            player,
+            key,
+            images,
+            audio_system: self.audio.clone(),
+            audio: audio.map(|(group, music)| VideoAudio {
+                group,
+                music,
+                started: false,
+            }),
+            last_position: 0.0,
+            on_finished: None,
+        }));
+        self.videos.lock().push(Arc::downgrade(&state));
+        Ok(LuaVideo(state))
+    }
+    fn open_sequence(&self, dir: &str) -> anyhow::Result<VideoPlayer> {
+        let manifest = self
+            .resource
+            .raw_resource
+            .get(format!("{}/video.toml", dir))?;
+        let manifest: VideoManifest = toml::from_str(&manifest.to_string()?)?;
+        let raw = self.resource.raw_resource.clone();
+        let read: ReadFrameFile = {
+            let dir = dir.to_owned();
+            Box::new(move |name| raw.get(format!("{}/{}", dir, name)))
+        };
+        VideoPlayer::new(ImageSequence::new(manifest, read))
+    }
+    #[cfg(feature = "webm")]
+    fn open_webm(&self, path: &str) -> anyhow::Result<VideoPlayer> {
+        VideoPlayer::new(WebmVideo::open(self.resource.raw_resource.get(path)?)?)
+    }
+    #[cfg(not(feature = "webm"))]
+    fn open_webm(&self, path: &str) -> anyhow::Result<VideoPlayer> {
+        Err(anyhow::anyhow!(
+            "{} needs the engine built with the webm feature",
+            path
+        ))
+    }
+    /// moves every video forward by `dt` seconds, loads the frames that are due,
+    /// keeps the audio on the video clock and runs `on_finished` of the ones that ended
+    pub fn update(&self, dt: f64) -> anyhow::Result<()> {
+        let videos: Vec<_> = {
+            let mut videos = self.videos.lock();
+            videos.retain(|video| video.strong_count() > 0);
+            videos.iter().filter_map(Weak::upgrade).collect()
+        };
+        // called with no video locked, the callback may use it
+        let finished: Vec<Function> = videos
+            .iter()
+            .filter_map(|video| video.lock().update(dt))
+            .collect();
+        for callback in finished {
+            map2anyhow_error!(callback.call::<()>(()), "run video on_finished")?;
+        }
+        Ok(())
+    }
+}
+
+impl UserData for LuaVideos {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // `{audio = "cutscenes/intro.ogg", group = "music"}`, both optional
+        methods.add_method(
+            "open",
+            |_, this, (path, options): (String, Option<Table>)| {
+                let audio = match options {
+                    Some(options) => match options.get::<Option<String>>("audio")? {
+                        Some(music) => {
+                            let group = options.get::<Option<String>>("group")?;
+                            Some((group.unwrap_or(DEFAULT_AUDIO_GROUP.to_owned()), music))
+                        }
+                        None => None,
+                    },
+                    None => None,
+                };
+                map2lua_error!(this.open(&path, audio), format!("open video {}", path))
+            },
+        );
+    }
+}
+
+#[test]
+fn test_video_manifest() -> anyhow::Result<()> {
+    let manifest: VideoManifest = toml::from_str(
+        r#"
+        fps = 24.0
+        pack = "frames.bin"
+        offsets = [0, 1200, 2500]
+        "#,
+    )?;
+    assert_eq!(manifest.frame_count(), 3);
+    assert_eq!(manifest.duration(), 0.125);
+    let manifest: VideoManifest = toml::from_str(
+        r#"
+        fps = 2.0
+        frames = ["0001.jpg", "0002.jpg"]
+        "#,
+    )?;
+    assert_eq!(manifest.duration(), 1.0);
+
+    // a little off is left alone, past the threshold it goes back to the video
+    assert_eq!(audio_resync(1.0, 1.05), None);
+    assert_eq!(audio_resync(1.0, 1.3), Some(1.0));
+    assert_eq!(audio_resync(2.0, 1.7), Some(2.0));
+    Ok(())
+}
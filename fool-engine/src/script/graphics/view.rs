@@ -0,0 +1,149 @@
+use fool_graphics::canvas::{view_at, Camera, SceneGraph, View};
+use mlua::{Table, UserData, UserDataFields, UserDataMethods, UserDataRef};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// shared with the scripts, moving it moves every view showing through it
+#[derive(Clone, Default)]
+pub struct LuaCamera(pub Arc<RwLock<Camera>>);
+
+impl LuaCamera {
+    /// `{x, y, zoom, rotation}`, each optional
+    pub fn from_table(table: Option<Table>) -> mlua::Result<Self> {
+        let mut camera = Camera::default();
+        if let Some(table) = table {
+            camera.x = table.get::<Option<f64>>("x")?.unwrap_or(camera.x);
+            camera.y = table.get::<Option<f64>>("y")?.unwrap_or(camera.y);
+            camera.zoom = table.get::<Option<f64>>("zoom")?.unwrap_or(camera.zoom);
+            camera.rotation = table
+                .get::<Option<f64>>("rotation")?
+                .unwrap_or(camera.rotation);
+        }
+        Ok(Self(Arc::new(RwLock::new(camera))))
+    }
+}
+
+impl UserData for LuaCamera {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("x", |_, this| Ok(this.0.read().x));
+        fields.add_field_method_get("y", |_, this| Ok(this.0.read().y));
+        fields.add_field_method_get("zoom", |_, this| Ok(this.0.read().zoom));
+        fields.add_field_method_get("rotation", |_, this| Ok(this.0.read().rotation));
+        fields.add_field_method_set("x", |_, this, x: f64| {
+            this.0.write().x = x;
+            Ok(())
+        });
+        fields.add_field_method_set("y", |_, this, y: f64| {
+            this.0.write().y = y;
+            Ok(())
+        });
+        fields.add_field_method_set("zoom", |_, this, zoom: f64| {
+            this.0.write().zoom = zoom;
+            Ok(())
+        });
+        fields.add_field_method_set("rotation", |_, this, rotation: f64| {
+            this.0.write().rotation = rotation;
+            Ok(())
+        });
+    }
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("look_at", |_, this, (x, y): (f64, f64)| {
+            let mut camera = this.0.write();
+            camera.x = x;
+            camera.y = y;
+            Ok(())
+        });
+    }
+}
+
+#[derive(Clone)]
+struct ViewDef {
+    // x, y, width and height in 0..1 of the window
+    rect: (f64, f64, f64, f64),
+    camera: LuaCamera,
+}
+
+/// the split screen set by the scripts, empty for the single full window camera
+#[derive(Clone, Default)]
+pub struct LuaViews(Arc<RwLock<Vec<ViewDef>>>);
+
+impl LuaViews {
+    /// `{{rect = {x, y, w, h}, camera = cam}, ...}`, nil goes back to the single camera
+    pub fn set(&self, views: Option<Vec<Table>>) -> mlua::Result<()> {
+        let views = views
+            .unwrap_or_default()
+            .into_iter()
+            .map(|view| {
+                let [x, y, w, h]: [f64; 4] = view.get("rect")?;
+                if w <= 0.0 || h <= 0.0 {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "view rect {}x{} is empty",
+                        w, h
+                    )));
+                }
+                let camera = match view.get::<Option<UserDataRef<LuaCamera>>>("camera")? {
+                    Some(camera) => camera.clone(),
+                    None => LuaCamera::default(),
+                };
+                Ok(ViewDef {
+                    rect: (x, y, w, h),
+                    camera,
+                })
+            })
+            .collect::<mlua::Result<Vec<_>>>()?;
+        *self.0.write() = views;
+        Ok(())
+    }
+    pub fn clear(&self) {
+        self.0.write().clear();
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.read().is_empty()
+    }
+    /// in pixels of the window size the graph was last resized to
+    pub fn build<'a>(&self, graph: &'a SceneGraph) -> Vec<View<'a>> {
+        self.0
+            .read()
+            .iter()
+            .map(|view| {
+                View::normalized(view.rect, graph.default_size, *view.camera.0.read(), graph)
+            })
+            .collect()
+    }
+    /// the 0 based view under a window position and the scene position it shows there
+    pub fn view_at(&self, graph: &SceneGraph, x: f64, y: f64) -> Option<(usize, (f64, f64))> {
+        let views = self.build(graph);
+        let index = view_at(&views, x, y)?;
+        Some((index, views[index].screen_to_world(x, y)))
+    }
+}
+
+#[test]
+fn test_lua_views() -> anyhow::Result<()> {
+    let lua = mlua::Lua::new();
+    let views = LuaViews::default();
+    let cam1 = LuaCamera::from_table(None)?;
+    let cam2 = LuaCamera::default();
+    lua.globals().set("cam1", cam1.clone())?;
+    lua.globals().set("cam2", cam2.clone())?;
+    let defs: Vec<Table> = lua
+        .load("return {{rect = {0, 0, 0.5, 1}, camera = cam1}, {rect = {0.5, 0, 0.5, 1}, camera = cam2}}")
+        .eval()?;
+    views.set(Some(defs))?;
+    let mut graph = SceneGraph::default();
+    graph.center_with_screen_size(800.0, 600.0);
+    assert_eq!(views.view_at(&graph, 200.0, 300.0), Some((0, (0.0, 0.0))));
+    // the cameras are followed as the scripts move them
+    lua.load("cam2:look_at(50, 10) cam2.zoom = 2").exec()?;
+    assert_eq!(views.view_at(&graph, 620.0, 300.0), Some((1, (60.0, 10.0))));
+    // and the rects follow the window
+    graph.center_with_screen_size(400.0, 300.0);
+    assert_eq!(views.build(&graph)[1].rect.x0, 200.0);
+
+    let empty: Vec<Table> = lua.load("return {{rect = {0, 0, 0, 1}}}").eval()?;
+    assert!(views.set(Some(empty)).is_err());
+    views.clear();
+    assert!(views.is_empty());
+    assert_eq!(views.view_at(&graph, 10.0, 10.0), None);
+    Ok(())
+}
@@ -1,5 +1,6 @@
 // use super::LuaTextureHandle;
 use super::super::{graphics::types::LuaColor, types::LuaSize};
+use super::list::{self, VirtualListConfig, VirtualTableConfig};
 use super::types::ImageButtonConfig;
 use crate::engine::ResourceManager;
 use crate::script::gui::types::UV;
@@ -519,6 +520,17 @@ impl<'lua> UserData for LuaUiContext<'lua> {
             },
         );
 
+        methods.add_method_mut("virtual_list", |lua, this, config: VirtualListConfig| {
+            list::virtual_list(lua, this.ui, &this.resource, config)
+        });
+        methods.add_method_mut("virtual_table", |lua, this, config: VirtualTableConfig| {
+            list::virtual_table(lua, this.ui, &this.resource, config)
+        });
+        // 1 based row indices, in ascending order
+        methods.add_method("list_selection", |_lua, this, id: String| {
+            let selection = list::list_selection(this.ui, &id);
+            Ok(selection.selected.iter().map(|i| i + 1).collect::<Vec<_>>())
+        });
         methods.add_method_mut("end_row", |_lua, this, (): ()| {
             this.ui.end_row();
             Ok(())
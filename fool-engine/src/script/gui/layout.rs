@@ -0,0 +1,531 @@
+use super::{show_window, EguiContext, LuaUIConfig};
+use crate::engine::ResourceManager;
+use crate::map2lua_error;
+use crate::script::types::LuaSize;
+use egui::{vec2, Button, Checkbox, Grid, ImageSource, Sense, Slider, Ui, Vec2};
+use mlua::{AnyUserData, Function, IntoLua, Lua, UserData, UserDataMethods, UserDataRef, Value};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+const WIDGET_TYPES: &[&str] = &[
+    "label",
+    "button",
+    "image",
+    "slider",
+    "checkbox",
+    "horizontal",
+    "vertical",
+    "grid",
+    "space",
+];
+// containers and spacing can be left anonymous
+const ID_OPTIONAL: &[&str] = &["horizontal", "vertical", "space"];
+
+const fn default_slider_max() -> f64 {
+    100.0
+}
+const fn default_grid_columns() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UIWidget {
+    Label {
+        id: String,
+        #[serde(default)]
+        text: String,
+    },
+    Button {
+        id: String,
+        #[serde(default)]
+        text: String,
+    },
+    Image {
+        id: String,
+        img: String,
+        #[serde(default)]
+        size: Option<LuaSize<f32>>,
+    },
+    Slider {
+        id: String,
+        #[serde(default)]
+        label: String,
+        #[serde(default)]
+        min: f64,
+        #[serde(default = "default_slider_max")]
+        max: f64,
+        #[serde(default)]
+        value: f64,
+    },
+    Checkbox {
+        id: String,
+        #[serde(default)]
+        label: String,
+        #[serde(default)]
+        checked: bool,
+    },
+    Horizontal {
+        #[serde(default)]
+        children: Vec<UIWidget>,
+    },
+    Vertical {
+        #[serde(default)]
+        children: Vec<UIWidget>,
+    },
+    Grid {
+        id: String,
+        #[serde(default = "default_grid_columns")]
+        columns: usize,
+        #[serde(default)]
+        spacing: LuaSize<f32>,
+        #[serde(default)]
+        children: Vec<UIWidget>,
+    },
+    Space {
+        #[serde(default)]
+        width: f32,
+        #[serde(default)]
+        height: f32,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UILayout {
+    pub window: LuaUIConfig,
+    #[serde(default)]
+    pub widgets: Vec<UIWidget>,
+}
+
+impl UILayout {
+    pub fn parse(path: &str, content: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(content)
+                .map_err(|err| anyhow::anyhow!("parse ui layout {} failed: {}", path, err))?,
+            Some("json") => serde_json::from_str(content)
+                .map_err(|err| anyhow::anyhow!("parse ui layout {} failed: {}", path, err))?,
+            _ => anyhow::bail!("unsupported ui layout {}, expected .toml or .json", path),
+        };
+        if value.get("window").is_none() {
+            anyhow::bail!("{}: missing [window] section", path);
+        }
+        let mut ids = HashSet::new();
+        validate_widgets(value.get("widgets"), "widgets", &mut ids)
+            .map_err(|err| anyhow::anyhow!("{}: {}", path, err))?;
+        serde_json::from_value(value)
+            .map_err(|err| anyhow::anyhow!("invalid ui layout {}: {}", path, err))
+    }
+    fn images(widgets: &[UIWidget], images: &mut Vec<String>) {
+        for widget in widgets {
+            match widget {
+                UIWidget::Image { img, .. } => images.push(img.clone()),
+                UIWidget::Horizontal { children }
+                | UIWidget::Vertical { children }
+                | UIWidget::Grid { children, .. } => Self::images(children, images),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn validate_widgets(
+    widgets: Option<&serde_json::Value>,
+    path: &str,
+    ids: &mut HashSet<String>,
+) -> anyhow::Result<()> {
+    let Some(widgets) = widgets else {
+        return Ok(());
+    };
+    let widgets = widgets
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("{} must be an array of widgets", path))?;
+    for (i, widget) in widgets.iter().enumerate() {
+        let path = format!("{}[{}]", path, i);
+        let kind = widget
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} is missing the widget type", path))?;
+        if !WIDGET_TYPES.contains(&kind) {
+            anyhow::bail!(
+                "{} has unknown widget type `{}`, expected one of {:?}",
+                path,
+                kind,
+                WIDGET_TYPES
+            );
+        }
+        match widget.get("id") {
+            Some(id) => {
+                let id = id
+                    .as_str()
+                    .ok_or_else(|| anyhow::anyhow!("{}: id must be a string", path))?;
+                if !ids.insert(id.to_string()) {
+                    anyhow::bail!("{}: duplicate widget id `{}`", path, id);
+                }
+            }
+            None if !ID_OPTIONAL.contains(&kind) => {
+                anyhow::bail!("{}: {} widget is missing an id", path, kind)
+            }
+            None => {}
+        }
+        validate_widgets(widget.get("children"), &format!("{}.children", path), ids)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum WidgetValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+impl IntoLua for WidgetValue {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        match self {
+            Self::Text(text) => text.into_lua(lua),
+            Self::Number(number) => number.into_lua(lua),
+            Self::Bool(b) => b.into_lua(lua),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FormState {
+    values: HashMap<String, WidgetValue>,
+    hidden: HashSet<String>,
+    disabled: HashSet<String>,
+    fired: Vec<(String, &'static str)>,
+}
+impl FormState {
+    fn from_widgets(widgets: &[UIWidget], values: &mut HashMap<String, WidgetValue>) {
+        for widget in widgets {
+            match widget {
+                UIWidget::Label { id, text } | UIWidget::Button { id, text } => {
+                    values.insert(id.clone(), WidgetValue::Text(text.clone()));
+                }
+                UIWidget::Slider { id, value, .. } => {
+                    values.insert(id.clone(), WidgetValue::Number(*value));
+                }
+                UIWidget::Checkbox { id, checked, .. } => {
+                    values.insert(id.clone(), WidgetValue::Bool(*checked));
+                }
+                UIWidget::Horizontal { children }
+                | UIWidget::Vertical { children }
+                | UIWidget::Grid { children, .. } => Self::from_widgets(children, values),
+                _ => {}
+            }
+        }
+    }
+    fn text(&self, id: &str) -> String {
+        match self.values.get(id) {
+            Some(WidgetValue::Text(text)) => text.clone(),
+            _ => String::new(),
+        }
+    }
+    fn show_widgets(&mut self, ui: &mut Ui, widgets: &[UIWidget], resource: &ResourceManager) {
+        for widget in widgets {
+            self.show_widget(ui, widget, resource);
+        }
+    }
+    fn show_widget(&mut self, ui: &mut Ui, widget: &UIWidget, resource: &ResourceManager) {
+        match widget {
+            UIWidget::Label { id, .. } if !self.hidden.contains(id) => {
+                ui.label(self.text(id));
+            }
+            UIWidget::Button { id, .. } if !self.hidden.contains(id) => {
+                let button = Button::new(self.text(id));
+                let clicked = ui
+                    .add_enabled(!self.disabled.contains(id), button)
+                    .clicked();
+                if clicked {
+                    self.fired.push((id.clone(), "click"));
+                }
+            }
+            UIWidget::Image { id, img, size } if !self.hidden.contains(id) => {
+                match resource.get_ui_texture(img) {
+                    Ok(texture) => {
+                        let mut image =
+                            egui::Image::from(ImageSource::from(&texture)).sense(Sense::CLICK);
+                        if let Some(size) = size {
+                            image = image.fit_to_exact_size(vec2(size.width, size.height));
+                        }
+                        if ui.add_enabled(!self.disabled.contains(id), image).clicked() {
+                            self.fired.push((id.clone(), "click"));
+                        }
+                    }
+                    Err(err) => log::error!("load ui texture {} failed: {}", img, err),
+                }
+            }
+            UIWidget::Slider {
+                id,
+                label,
+                min,
+                max,
+                ..
+            } if !self.hidden.contains(id) => {
+                let mut value = match self.values.get(id) {
+                    Some(WidgetValue::Number(value)) => *value,
+                    _ => *min,
+                };
+                let slider = Slider::new(&mut value, *min..=*max).text(label);
+                if ui
+                    .add_enabled(!self.disabled.contains(id), slider)
+                    .changed()
+                {
+                    self.values.insert(id.clone(), WidgetValue::Number(value));
+                    self.fired.push((id.clone(), "change"));
+                }
+            }
+            UIWidget::Checkbox { id, label, .. } if !self.hidden.contains(id) => {
+                let mut checked = matches!(self.values.get(id), Some(WidgetValue::Bool(true)));
+                let checkbox = Checkbox::new(&mut checked, label);
+                if ui
+                    .add_enabled(!self.disabled.contains(id), checkbox)
+                    .changed()
+                {
+                    self.values.insert(id.clone(), WidgetValue::Bool(checked));
+                    self.fired.push((id.clone(), "change"));
+                }
+            }
+            UIWidget::Horizontal { children } => {
+                ui.horizontal(|ui| self.show_widgets(ui, children, resource));
+            }
+            UIWidget::Vertical { children } => {
+                ui.vertical(|ui| self.show_widgets(ui, children, resource));
+            }
+            UIWidget::Grid {
+                id,
+                columns,
+                spacing,
+                children,
+            } if !self.hidden.contains(id) => {
+                Grid::new(id)
+                    .spacing([spacing.width, spacing.height])
+                    .show(ui, |ui| {
+                        for (i, child) in children.iter().enumerate() {
+                            self.show_widget(ui, child, resource);
+                            if (i + 1) % (*columns).max(1) == 0 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+            }
+            UIWidget::Space { width, height } => {
+                let _ = ui.allocate_space(Vec2 {
+                    x: *width,
+                    y: *height,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct LuaUIForm {
+    path: String,
+    layout: UILayout,
+    state: FormState,
+    callbacks: HashMap<(String, String), Function>,
+    resource: ResourceManager,
+    #[cfg(feature = "debug")]
+    modified: Option<std::time::SystemTime>,
+}
+
+impl LuaUIForm {
+    pub fn load(path: &str, resource: &ResourceManager) -> anyhow::Result<Self> {
+        let layout = Self::read_layout(path, resource)?;
+        let mut values = HashMap::new();
+        FormState::from_widgets(&layout.widgets, &mut values);
+        Ok(Self {
+            path: path.to_string(),
+            layout,
+            state: FormState {
+                values,
+                ..Default::default()
+            },
+            callbacks: Default::default(),
+            resource: resource.clone(),
+            #[cfg(feature = "debug")]
+            modified: Self::modified_time(path, resource),
+        })
+    }
+    fn read_layout(path: &str, resource: &ResourceManager) -> anyhow::Result<UILayout> {
+        let content = resource.raw_resource.get(path)?.to_string()?;
+        let layout = UILayout::parse(path, &content)?;
+        let mut images = Vec::new();
+        UILayout::images(&layout.widgets, &mut images);
+        for img in images {
+            let _texture = resource
+                .get_ui_texture(&img)
+                .map_err(|err| anyhow::anyhow!("{}: image {} not found: {}", path, img, err))?;
+        }
+        Ok(layout)
+    }
+    #[cfg(feature = "debug")]
+    fn modified_time(path: &str, resource: &ResourceManager) -> Option<std::time::SystemTime> {
+        resource
+            .assets_path
+            .join(path)
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+    }
+    // hot reload the layout file when it changes on disk, keeping widget values
+    #[cfg(feature = "debug")]
+    fn reload_if_changed(&mut self) {
+        let modified = Self::modified_time(&self.path, &self.resource);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        self.resource.raw_resource.remove(&self.path);
+        match Self::read_layout(&self.path, &self.resource) {
+            Ok(layout) => {
+                let mut values = HashMap::new();
+                FormState::from_widgets(&layout.widgets, &mut values);
+                for (id, value) in values.iter_mut() {
+                    if let Some(old) = self.state.values.get(id) {
+                        if std::mem::discriminant(old) == std::mem::discriminant(value) {
+                            *value = old.clone();
+                        }
+                    }
+                }
+                self.state.values = values;
+                self.layout = layout;
+                log::debug!("ui layout {} reloaded", self.path);
+            }
+            Err(err) => log::error!("reload ui layout failed: {}", err),
+        }
+    }
+    fn show(&mut self, context: &EguiContext) -> Vec<(Function, Option<WidgetValue>)> {
+        #[cfg(feature = "debug")]
+        self.reload_if_changed();
+        let state = &mut self.state;
+        let widgets = &self.layout.widgets;
+        let resource = &self.resource;
+        show_window(self.layout.window.clone(), context, |ui| {
+            state.show_widgets(ui, widgets, resource)
+        });
+        std::mem::take(&mut self.state.fired)
+            .into_iter()
+            .filter_map(|(id, event)| {
+                let callback = self.callbacks.get(&(id.clone(), event.to_string()))?;
+                Some((callback.clone(), self.state.values.get(&id).cloned()))
+            })
+            .collect()
+    }
+    fn set(&mut self, id: String, prop: &str, value: Value) -> mlua::Result<()> {
+        match (prop, value) {
+            ("text", Value::String(text)) => {
+                self.state
+                    .values
+                    .insert(id, WidgetValue::Text(text.to_str()?.to_string()));
+            }
+            ("value", Value::Number(n)) => {
+                self.state.values.insert(id, WidgetValue::Number(n));
+            }
+            ("value", Value::Integer(n)) => {
+                self.state.values.insert(id, WidgetValue::Number(n as f64));
+            }
+            ("checked", Value::Boolean(b)) => {
+                self.state.values.insert(id, WidgetValue::Bool(b));
+            }
+            ("visible", Value::Boolean(b)) => {
+                if b {
+                    self.state.hidden.remove(&id);
+                } else {
+                    self.state.hidden.insert(id);
+                }
+            }
+            ("enabled", Value::Boolean(b)) => {
+                if b {
+                    self.state.disabled.remove(&id);
+                } else {
+                    self.state.disabled.insert(id);
+                }
+            }
+            (prop, value) => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "can not set {}.{} to {}",
+                    id,
+                    prop,
+                    value.type_name()
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl UserData for LuaUIForm {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // callbacks run after the form is released so they can call form:set
+        methods.add_function(
+            "show",
+            |_lua, (form, context): (AnyUserData, UserDataRef<EguiContext>)| {
+                let callbacks = form.borrow_mut::<Self>()?.show(&context);
+                for (callback, value) in callbacks {
+                    callback.call::<()>((form.clone(), value))?;
+                }
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "on",
+            |_lua, this, (id, event, callback): (String, String, Function)| {
+                this.callbacks.insert((id, event), callback);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "set",
+            |_lua, this, (id, prop, value): (String, String, Value)| this.set(id, &prop, value),
+        );
+        methods.add_method("get", |_lua, this, id: String| {
+            Ok(this.state.values.get(&id).cloned())
+        });
+        methods.add_method_mut("reload", |_lua, this, ()| {
+            this.resource.raw_resource.remove(&this.path);
+            this.layout = map2lua_error!(
+                Self::read_layout(&this.path, &this.resource),
+                "reload ui layout"
+            )?;
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn test_layout_validate() {
+    let ok = r#"
+        [window]
+        title = "menu"
+        [[widgets]]
+        type = "button"
+        id = "start_button"
+        text = "Start"
+        [[widgets]]
+        type = "horizontal"
+        children = [{ type = "slider", id = "volume_slider", max = 1.0 }]
+    "#;
+    let layout = UILayout::parse("ui/menu.toml", ok).unwrap();
+    assert_eq!(layout.widgets.len(), 2);
+
+    let unknown = r#"{"window": {"title": "menu"}, "widgets": [{"type": "knob", "id": "k"}]}"#;
+    let err = UILayout::parse("ui/menu.json", unknown).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("widgets[0] has unknown widget type `knob`"));
+
+    let missing_id = r#"
+        [window]
+        title = "menu"
+        [[widgets]]
+        type = "vertical"
+        children = [{ type = "label", text = "no id" }]
+    "#;
+    let err = UILayout::parse("ui/menu.toml", missing_id).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("widgets[0].children[0]: label widget is missing an id"));
+}
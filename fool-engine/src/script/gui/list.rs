@@ -0,0 +1,306 @@
+use super::LuaUiContext;
+use crate::engine::ResourceManager;
+use egui::{vec2, Align, Id, Layout, Modifiers, Sense, Ui, UiBuilder};
+use egui_extras::{Column, TableBuilder};
+use mlua::{FromLua, Function, Lua, Table, Value};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectMode {
+    #[default]
+    None,
+    Single,
+    /// shift selects a range from the last click, ctrl toggles a row
+    Multi,
+}
+
+impl SelectMode {
+    fn from_name(name: Option<String>) -> mlua::Result<Self> {
+        match name.as_deref() {
+            None | Some("none") => Ok(SelectMode::None),
+            Some("single") => Ok(SelectMode::Single),
+            Some("multi") => Ok(SelectMode::Multi),
+            Some(other) => Err(mlua::Error::RuntimeError(format!(
+                "unknown select mode {}, expect none, single or multi",
+                other
+            ))),
+        }
+    }
+}
+
+/// selection of a virtual list or table, kept in egui memory by list id
+#[derive(Debug, Clone, Default)]
+pub struct ListSelection {
+    pub selected: BTreeSet<usize>,
+    anchor: Option<usize>,
+}
+
+impl ListSelection {
+    pub fn click(&mut self, index: usize, mode: SelectMode, modifiers: Modifiers) {
+        match mode {
+            SelectMode::None => {}
+            SelectMode::Multi if modifiers.shift => {
+                let anchor = self.anchor.unwrap_or(index);
+                if !modifiers.command {
+                    self.selected.clear();
+                }
+                self.selected.extend(anchor.min(index)..=anchor.max(index));
+            }
+            SelectMode::Multi if modifiers.command => {
+                if !self.selected.remove(&index) {
+                    self.selected.insert(index);
+                }
+                self.anchor = Some(index);
+            }
+            SelectMode::Single | SelectMode::Multi => {
+                self.selected.clear();
+                self.selected.insert(index);
+                self.anchor = Some(index);
+            }
+        }
+    }
+    /// rows past the end are dropped when the data shrinks
+    fn truncate(&mut self, total: usize) {
+        self.selected.retain(|i| *i < total);
+        self.anchor = self.anchor.filter(|i| *i < total);
+    }
+}
+
+fn selection_id(id: &str) -> Id {
+    Id::new(("fool_list_selection", id))
+}
+fn sort_id(id: &str) -> Id {
+    Id::new(("fool_table_sort", id))
+}
+
+pub fn list_selection(ui: &Ui, id: &str) -> ListSelection {
+    ui.ctx()
+        .data(|d| d.get_temp::<ListSelection>(selection_id(id)))
+        .unwrap_or_default()
+}
+fn store_selection(ui: &Ui, id: &str, selection: ListSelection) {
+    ui.ctx()
+        .data_mut(|d| d.insert_temp(selection_id(id), selection));
+}
+
+pub struct VirtualListConfig {
+    pub id: String,
+    pub total: usize,
+    pub row_height: f32,
+    pub render: Function,
+    pub select: SelectMode,
+}
+
+impl FromLua for VirtualListConfig {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        let table = Table::from_lua(value, lua)?;
+        Ok(Self {
+            id: table.get("id")?,
+            total: table.get("total")?,
+            row_height: table.get::<Option<f32>>("row_height")?.unwrap_or(24.0),
+            render: table.get("render")?,
+            select: SelectMode::from_name(table.get("select")?)?,
+        })
+    }
+}
+
+pub struct TableColumn {
+    pub title: String,
+    pub width: Option<f32>,
+}
+
+pub struct VirtualTableConfig {
+    pub id: String,
+    pub total: usize,
+    pub row_height: f32,
+    pub columns: Vec<TableColumn>,
+    pub cell: Function,
+    pub sort: Option<Function>,
+    pub select: SelectMode,
+}
+
+impl FromLua for VirtualTableConfig {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        let table = Table::from_lua(value, lua)?;
+        let columns = table
+            .get::<Table>("columns")?
+            .sequence_values::<Table>()
+            .map(|col| {
+                let col = col?;
+                Ok(TableColumn {
+                    title: col.get::<Option<String>>("title")?.unwrap_or_default(),
+                    width: col.get("width")?,
+                })
+            })
+            .collect::<mlua::Result<Vec<_>>>()?;
+        Ok(Self {
+            id: table.get("id")?,
+            total: table.get("total")?,
+            row_height: table.get::<Option<f32>>("row_height")?.unwrap_or(24.0),
+            columns,
+            cell: table.get("cell")?,
+            sort: table.get("sort")?,
+            select: SelectMode::from_name(table.get("select")?)?,
+        })
+    }
+}
+
+fn call_with_ui(
+    lua: &Lua,
+    ui: &mut Ui,
+    resource: &ResourceManager,
+    func: &Function,
+    args: impl mlua::IntoLuaMulti,
+) -> mlua::Result<()> {
+    lua.scope(|scope| {
+        let ctx = scope.create_userdata(LuaUiContext {
+            ui,
+            resource: resource.clone(),
+        })?;
+        let mut args = args.into_lua_multi(lua)?;
+        args.push_front(Value::UserData(ctx));
+        func.call::<()>(args)
+    })
+}
+
+/// only the rows inside the viewport call `render`, one lua scope per row
+pub fn virtual_list(
+    lua: &Lua,
+    ui: &mut Ui,
+    resource: &ResourceManager,
+    config: VirtualListConfig,
+) -> mlua::Result<()> {
+    let mut selection = list_selection(ui, &config.id);
+    selection.truncate(config.total);
+    let modifiers = ui.input(|i| i.modifiers);
+    let mut result = Ok(());
+    egui::ScrollArea::vertical()
+        .id_salt(("fool_virtual_list", &config.id))
+        .auto_shrink([false, false])
+        .show_rows(ui, config.row_height, config.total, |ui, rows| {
+            for row in rows {
+                let size = vec2(ui.available_width(), config.row_height);
+                let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+                if selection.selected.contains(&row) {
+                    ui.painter()
+                        .rect_filled(rect, 0.0, ui.visuals().selection.bg_fill);
+                }
+                let mut row_ui = ui.new_child(
+                    UiBuilder::new()
+                        .id_salt(row)
+                        .max_rect(rect)
+                        .layout(Layout::left_to_right(Align::Center)),
+                );
+                if let Err(err) = call_with_ui(lua, &mut row_ui, resource, &config.render, row + 1)
+                {
+                    result = Err(err);
+                    break;
+                }
+                if response.clicked() {
+                    selection.click(row, config.select, modifiers);
+                }
+            }
+        });
+    store_selection(ui, &config.id, selection);
+    result
+}
+
+/// headers sort through `sort(col, ascending)`, the lua side reorders its data
+pub fn virtual_table(
+    lua: &Lua,
+    ui: &mut Ui,
+    resource: &ResourceManager,
+    config: VirtualTableConfig,
+) -> mlua::Result<()> {
+    let mut selection = list_selection(ui, &config.id);
+    selection.truncate(config.total);
+    let modifiers = ui.input(|i| i.modifiers);
+    let sort_key = sort_id(&config.id);
+    let sort_state = ui.ctx().data(|d| d.get_temp::<(usize, bool)>(sort_key));
+    let mut sort_clicked = None;
+    let mut result = Ok(());
+    let mut builder = TableBuilder::new(ui)
+        .id_salt(("fool_virtual_table", &config.id))
+        .striped(true)
+        .sense(Sense::click())
+        .auto_shrink([false, false])
+        .cell_layout(Layout::left_to_right(Align::Center));
+    for column in &config.columns {
+        builder = builder.column(match column.width {
+            Some(width) => Column::initial(width).resizable(true),
+            None => Column::remainder(),
+        });
+    }
+    builder
+        .header(config.row_height, |mut header| {
+            for (col, column) in config.columns.iter().enumerate() {
+                header.col(|ui| {
+                    let arrow = match sort_state {
+                        Some((c, true)) if c == col => " ▲",
+                        Some((c, false)) if c == col => " ▼",
+                        _ => "",
+                    };
+                    let title = format!("{}{}", column.title, arrow);
+                    if config.sort.is_some() && ui.button(title).clicked() {
+                        sort_clicked = Some(col);
+                    } else if config.sort.is_none() {
+                        ui.strong(&column.title);
+                    }
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(config.row_height, config.total, |mut row| {
+                let index = row.index();
+                row.set_selected(selection.selected.contains(&index));
+                for col in 0..config.columns.len() {
+                    row.col(|ui| {
+                        if result.is_ok() {
+                            result =
+                                call_with_ui(lua, ui, resource, &config.cell, (index + 1, col + 1));
+                        }
+                    });
+                }
+                if row.response().clicked() {
+                    selection.click(index, config.select, modifiers);
+                }
+            });
+        });
+    if let (Some(col), Some(sort)) = (sort_clicked, &config.sort) {
+        let ascending = !matches!(sort_state, Some((c, true)) if c == col);
+        ui.ctx()
+            .data_mut(|d| d.insert_temp(sort_key, (col, ascending)));
+        // row indices point at other data once sorted
+        selection = ListSelection::default();
+        sort.call::<()>((col + 1, ascending))?;
+    }
+    store_selection(ui, &config.id, selection);
+    result
+}
+
+#[test]
+fn test_list_selection() {
+    let none = Modifiers::NONE;
+    let shift = Modifiers::SHIFT;
+    let ctrl = Modifiers::COMMAND;
+    let mut selection = ListSelection::default();
+    selection.click(3, SelectMode::Single, shift);
+    selection.click(5, SelectMode::Single, ctrl);
+    assert_eq!(selection.selected, BTreeSet::from([5]));
+
+    let mut selection = ListSelection::default();
+    selection.click(2, SelectMode::Multi, none);
+    selection.click(5, SelectMode::Multi, shift);
+    assert_eq!(selection.selected, BTreeSet::from([2, 3, 4, 5]));
+    selection.click(4, SelectMode::Multi, ctrl);
+    assert_eq!(selection.selected, BTreeSet::from([2, 3, 5]));
+    // the anchor moved to the ctrl clicked row
+    selection.click(6, SelectMode::Multi, shift);
+    assert_eq!(selection.selected, BTreeSet::from([4, 5, 6]));
+    selection.click(9, SelectMode::Multi, none);
+    assert_eq!(selection.selected, BTreeSet::from([9]));
+    selection.truncate(5);
+    assert!(selection.selected.is_empty());
+    selection.click(1, SelectMode::None, none);
+    assert!(selection.selected.is_empty());
+}
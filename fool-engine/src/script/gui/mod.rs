@@ -1,12 +1,20 @@
 pub use super::graphics::types::LuaColor;
 use mlua::{Function, Lua, UserData};
 pub mod binding;
+pub mod layout;
+pub mod list;
+pub mod toast;
 pub mod types;
 pub mod utils;
+pub mod workspace;
 use crate::engine::ResourceManager;
+use crate::map2lua_error;
 pub use binding::LuaUiContext;
-use egui::{pos2, vec2, Context, Visuals};
+use egui::{pos2, vec2, Context, Ui, Visuals};
+pub use layout::LuaUIForm;
+pub use toast::{ToastTheme, Toasts};
 pub use types::{LuaGuiStyle, LuaUIConfig};
+pub use workspace::{GuiWorkspace, WindowLayout};
 
 #[derive(Clone)]
 pub struct EguiContext {
@@ -14,6 +22,8 @@ pub struct EguiContext {
     pub width: f32,
     pub heigth: f32,
     pub resource: ResourceManager,
+    pub workspace: GuiWorkspace,
+    pub toasts: Toasts,
 }
 impl EguiContext {
     pub fn resize(&mut self, w: u32, h: u32) {
@@ -57,6 +67,9 @@ impl UserData for EguiContext {
                 style.visuals.widgets.open.fg_stroke.color = color.into();
             }
             style.animation_time = ui_style.animation_time;
+            if let Some(theme) = &ui_style.toast {
+                this.toasts.set_theme(theme.clone());
+            }
             style.wrap_mode = ui_style.wrap;
             context.set_style(style);
             Ok(())
@@ -67,6 +80,33 @@ impl UserData for EguiContext {
                 create_window(lua, config, this.clone(), func)
             },
         );
+        methods.add_method("load_form", |_lua, this, path: String| {
+            map2lua_error!(LuaUIForm::load(&path, &this.resource), "load ui layout")
+        });
+        methods.add_method("reset_layout", |_lua, this, ()| {
+            this.workspace.reset();
+            Ok(())
+        });
+        methods.add_method("save_layout", |_lua, this, name: String| {
+            map2lua_error!(this.workspace.save_layout(Some(&name)), "save gui layout")
+        });
+        methods.add_method("load_layout", |_lua, this, name: String| {
+            map2lua_error!(this.workspace.load_layout(Some(&name)), "load gui layout")
+        });
+        methods.add_method(
+            "set_viewport_margin",
+            |_lua, this, (left, top, right, bottom): (f64, f64, f64, f64)| {
+                this.workspace.set_viewport_margin(left, top, right, bottom);
+                Ok(())
+            },
+        );
+        // gameplay skips the keys and clicks a focused text field or a window takes
+        methods.add_method("wants_keyboard", |_lua, this, ()| {
+            Ok(this.context.wants_keyboard_input())
+        });
+        methods.add_method("wants_pointer", |_lua, this, ()| {
+            Ok(this.context.wants_pointer_input())
+        });
     }
 }
 
@@ -76,16 +116,46 @@ pub fn create_window(
     context: EguiContext,
     func: Function,
 ) -> mlua::Result<()> {
+    let resource = context.resource.clone();
+    let res = show_window(config, &context, |ui| {
+        lua.scope(|scope| {
+            let ui_ctx = scope.create_userdata(LuaUiContext { ui, resource })?;
+            func.call::<()>(ui_ctx)
+        })
+    });
+    if let Some(e) = res {
+        e?
+    }
+    Ok(())
+}
+
+pub fn show_window<R>(
+    config: LuaUIConfig,
+    context: &EguiContext,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> Option<R> {
     let x_c = context.width / 2.0 + config.x - config.w / 2.0;
     let y_c = context.heigth / 2.0 + config.y - config.h / 2.0;
-    let pos = pos2(x_c, y_c);
-    let size = vec2(config.w, config.h);
-    let resource = context.resource.clone();
+    let mut pos = pos2(x_c, y_c);
+    let mut size = vec2(config.w, config.h);
+    let mut default_open = config.default_open;
+    let id = config.id;
+    if let Some(layout) = id.as_deref().and_then(|id| context.workspace.restored(id)) {
+        let layout = layout.clamped(context.width, context.heigth);
+        pos = layout.pos.into();
+        size = layout.size.into();
+        default_open = !layout.collapsed;
+    }
     let texture = config.bg_img;
-    let res = egui::containers::Window::new(config.title)
+    let mut window = egui::containers::Window::new(config.title);
+    if let Some(id) = &id {
+        window = window.id(context.workspace.window_id(id));
+    }
+    let mut content_size = None;
+    let res = window
         .collapsible(config.collapsible)
         .constrain(config.constrain)
-        .default_open(config.default_open)
+        .default_open(default_open)
         .default_pos(pos)
         .default_size(size)
         .enabled(true)
@@ -120,13 +190,90 @@ pub fn create_window(
                     Err(err) => log::error!("load texture failed: {}", err),
                 }
             };
-            lua.scope(|scope| {
-                let ui_ctx = scope.create_userdata(LuaUiContext { ui, resource })?;
-                func.call::<()>(ui_ctx)
-            })
+            content_size = Some(ui.max_rect().size());
+            add_contents(ui)
         });
-    if let Some(e) = res.and_then(|s| s.inner) {
-        e?
+    if let (Some(id), Some(res)) = (&id, &res) {
+        // a collapsed window keeps the size it had open
+        let size = content_size
+            .map(Into::into)
+            .or_else(|| context.workspace.current(id).map(|layout| layout.size))
+            .unwrap_or(size.into());
+        let layout = WindowLayout {
+            pos: res.response.rect.min.into(),
+            size,
+            collapsed: res.inner.is_none(),
+        };
+        context.workspace.record(id, layout);
     }
+    res.and_then(|s| s.inner)
+}
+
+#[test]
+fn test_wants_keyboard() -> anyhow::Result<()> {
+    use crate::resource::AssetSource;
+    use crate::save::SaveManager;
+    use fool_graphics::canvas::SceneGraph;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    let dir = std::env::temp_dir().join("test_wants_keyboard");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let workspace = GuiWorkspace::new(
+        SaveManager::new(&dir),
+        Arc::new(RwLock::new(SceneGraph::default())),
+    );
+    let context = EguiContext {
+        context: Default::default(),
+        width: 800.0,
+        heigth: 600.0,
+        resource: ResourceManager::new(AssetSource::Folder(dir.clone()))?,
+        workspace,
+        toasts: Default::default(),
+    };
+    let lua = Lua::new();
+    lua.globals().set("gui", context.clone())?;
+    lua.load(
+        r#"
+        moves = 0
+        function on_key()
+            if gui:wants_keyboard() then
+                return
+            end
+            moves = moves + 1
+        end
+        "#,
+    )
+    .exec()?;
+    let on_key: Function = lua.globals().get("on_key")?;
+    let mut name = String::new();
+    let mut frame = |focus: bool| {
+        let _ = context.context.run(Default::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.text_edit_singleline(&mut name);
+                if focus {
+                    response.request_focus();
+                } else {
+                    response.surrender_focus();
+                }
+            });
+        });
+    };
+    frame(false);
+    on_key.call::<()>(())?;
+    assert!(!lua.load("return gui:wants_keyboard()").eval::<bool>()?);
+
+    // typing into the field, the key is not a move
+    frame(true);
+    frame(true);
+    assert!(context.context.wants_keyboard_input());
+    on_key.call::<()>(())?;
+    assert!(lua.load("return gui:wants_keyboard()").eval::<bool>()?);
+
+    frame(false);
+    on_key.call::<()>(())?;
+    assert_eq!(lua.globals().get::<i32>("moves")?, 2);
+    // nothing under the pointer
+    assert!(!lua.load("return gui:wants_pointer()").eval::<bool>()?);
     Ok(())
 }
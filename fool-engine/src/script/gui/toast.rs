@@ -0,0 +1,482 @@
+use super::LuaColor;
+use crate::engine::ResourceManager;
+use crate::map2anyhow_error;
+use egui::{vec2, Align2, Area, Context, Frame, Id, Image, Order, RichText, Sense, Vec2};
+use mlua::{DeserializeOptions, Function, LuaSerdeExt, Table, UserData, UserDataMethods, Value};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// screen points between the toasts and the screen edge
+const EDGE_MARGIN: f32 = 8.0;
+// how far a toast slides while it fades
+const SLIDE: f32 = 40.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToastKind {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToastPosition {
+    TopLeft,
+    Top,
+    #[default]
+    TopRight,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ToastPosition {
+    fn align(self) -> Align2 {
+        match self {
+            ToastPosition::TopLeft => Align2::LEFT_TOP,
+            ToastPosition::Top => Align2::CENTER_TOP,
+            ToastPosition::TopRight => Align2::RIGHT_TOP,
+            ToastPosition::BottomLeft => Align2::LEFT_BOTTOM,
+            ToastPosition::Bottom => Align2::CENTER_BOTTOM,
+            ToastPosition::BottomRight => Align2::RIGHT_BOTTOM,
+        }
+    }
+    /// away from the screen center, where it slides in from and out to
+    fn outward(self) -> Vec2 {
+        match self {
+            ToastPosition::TopLeft | ToastPosition::BottomLeft => vec2(-1.0, 0.0),
+            ToastPosition::TopRight | ToastPosition::BottomRight => vec2(1.0, 0.0),
+            ToastPosition::Top => vec2(0.0, -1.0),
+            ToastPosition::Bottom => vec2(0.0, 1.0),
+        }
+    }
+    fn is_bottom(self) -> bool {
+        matches!(
+            self,
+            ToastPosition::BottomLeft | ToastPosition::Bottom | ToastPosition::BottomRight
+        )
+    }
+}
+
+fn default_duration() -> f64 {
+    3.0
+}
+
+/// what `toast:show` takes, `on_click` is read from the same table
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToastOptions {
+    pub text: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// seconds fully shown, the fades come on top
+    #[serde(default = "default_duration")]
+    pub duration: f64,
+    #[serde(default)]
+    pub kind: ToastKind,
+    #[serde(default)]
+    pub position: ToastPosition,
+    /// stays until clicked or dismissed, for errors the player has to see
+    #[serde(default)]
+    pub persistent: bool,
+}
+
+/// the `toast` part of `LuaGuiStyle`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToastTheme {
+    pub info: LuaColor,
+    pub warning: LuaColor,
+    pub error: LuaColor,
+    pub text_color: LuaColor,
+    pub font_size: f32,
+    pub max_width: f32,
+    /// the rest wait until one of these is gone
+    pub max_visible: usize,
+    pub spacing: f32,
+}
+
+impl Default for ToastTheme {
+    fn default() -> Self {
+        Self {
+            info: LuaColor {
+                r: 36,
+                g: 40,
+                b: 48,
+                a: 235,
+            },
+            warning: LuaColor {
+                r: 140,
+                g: 100,
+                b: 16,
+                a: 235,
+            },
+            error: LuaColor {
+                r: 140,
+                g: 32,
+                b: 32,
+                a: 235,
+            },
+            text_color: LuaColor {
+                r: 240,
+                g: 240,
+                b: 240,
+                a: 255,
+            },
+            font_size: 14.0,
+            max_width: 320.0,
+            max_visible: 4,
+            spacing: 6.0,
+        }
+    }
+}
+
+impl ToastTheme {
+    fn fill(&self, kind: ToastKind) -> LuaColor {
+        match kind {
+            ToastKind::Info => self.info,
+            ToastKind::Warning => self.warning,
+            ToastKind::Error => self.error,
+        }
+    }
+}
+
+struct Toast {
+    id: u64,
+    options: ToastOptions,
+    on_click: Option<Function>,
+    /// shown again while it was up, drawn as a badge
+    count: u32,
+    /// seconds on screen, a queued toast stays at 0
+    age: f64,
+    /// the age it started fading out at
+    closing: Option<f64>,
+    /// of the last frame, for stacking the ones after it
+    height: f32,
+}
+
+impl Toast {
+    fn opacity(&self, fade: f64) -> f32 {
+        if fade <= 0.0 {
+            return 1.0;
+        }
+        let fade_in = self.age / fade;
+        let fade_out = self
+            .closing
+            .map_or(1.0, |closing| 1.0 - (self.age - closing) / fade);
+        fade_in.min(fade_out).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[derive(Default)]
+struct ToastState {
+    // in the order they were shown, the first `max_visible` are on screen
+    toasts: Vec<Toast>,
+    theme: ToastTheme,
+    next_id: u64,
+    last_time: Option<f64>,
+    // the animation time of the last frame drawn
+    fade: f64,
+    // callbacks of the toasts clicked, run by `poll`
+    clicked: Vec<(u64, Function)>,
+}
+
+/// transient notifications over the game, queued, stacked per screen corner and
+/// faded with the gui style's animation time. They are drawn every frame whatever
+/// the engine status, so they keep going while the game is paused
+#[derive(Clone, Default)]
+pub struct Toasts {
+    state: Arc<Mutex<ToastState>>,
+}
+
+impl Toasts {
+    /// the same text and kind still up shows a counter instead of another toast,
+    /// returns the id of the toast it went to
+    pub fn show(&self, options: ToastOptions, on_click: Option<Function>) -> u64 {
+        let mut state = self.state.lock();
+        let fade = state.fade;
+        if let Some(toast) = state.toasts.iter_mut().find(|toast| {
+            toast.closing.is_none()
+                && toast.options.kind == options.kind
+                && toast.options.text == options.text
+        }) {
+            toast.count += 1;
+            // starts its time over, without fading in again
+            toast.age = toast.age.min(fade);
+            toast.options.duration = options.duration;
+            toast.options.persistent |= options.persistent;
+            if on_click.is_some() {
+                toast.on_click = on_click;
+            }
+            return toast.id;
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        state.toasts.push(Toast {
+            id,
+            options,
+            on_click,
+            count: 1,
+            age: 0.0,
+            closing: None,
+            height: 0.0,
+        });
+        id
+    }
+    /// fades it out, a queued one is dropped right away
+    pub fn dismiss(&self, id: u64) -> bool {
+        let mut state = self.state.lock();
+        let max_visible = state.theme.max_visible;
+        let Some(index) = state.toasts.iter().position(|toast| toast.id == id) else {
+            return false;
+        };
+        if index >= max_visible {
+            state.toasts.remove(index);
+        } else {
+            let toast = &mut state.toasts[index];
+            toast.closing.get_or_insert(toast.age);
+        }
+        true
+    }
+    pub fn dismiss_all(&self) {
+        let mut state = self.state.lock();
+        let max_visible = state.theme.max_visible;
+        state.toasts.truncate(max_visible);
+        for toast in &mut state.toasts {
+            toast.closing.get_or_insert(toast.age);
+        }
+    }
+    /// shown and queued
+    pub fn len(&self) -> usize {
+        self.state.lock().toasts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn set_theme(&self, theme: ToastTheme) {
+        self.state.lock().theme = theme;
+    }
+    /// runs the `on_click` of the toasts clicked since the last call
+    pub fn poll(&self) -> anyhow::Result<()> {
+        // no lock is held, the callback may show another toast
+        let clicked = std::mem::take(&mut self.state.lock().clicked);
+        for (id, callback) in clicked {
+            map2anyhow_error!(callback.call::<()>(id), "run toast on_click")?;
+        }
+        Ok(())
+    }
+    /// draws into the running egui pass, ages them by the egui clock
+    pub fn draw(&self, ctx: &Context, resource: &ResourceManager) {
+        let mut state = self.state.lock();
+        let now = ctx.input(|input| input.time);
+        let dt = state.last_time.map_or(0.0, |last| (now - last).max(0.0));
+        state.last_time = Some(now);
+        if state.toasts.is_empty() {
+            return;
+        }
+        let fade = ctx.style().animation_time as f64;
+        state.fade = fade;
+        let state = &mut *state;
+        let max_visible = state.theme.max_visible;
+        for toast in state.toasts.iter_mut().take(max_visible) {
+            toast.age += dt;
+            let shown = fade + toast.options.duration;
+            if !toast.options.persistent && toast.closing.is_none() && toast.age >= shown {
+                toast.closing = Some(toast.age);
+            }
+        }
+        state.toasts.retain(|toast| {
+            toast
+                .closing
+                .is_none_or(|closing| toast.age - closing < fade.max(f64::EPSILON))
+        });
+        // per position, how far down or up the next one goes
+        let mut stacked = [0.0f32; 6];
+        let theme = state.theme.clone();
+        for toast in state.toasts.iter_mut().take(max_visible) {
+            let opacity = toast.opacity(fade);
+            let position = toast.options.position;
+            let align = position.align();
+            let along = &mut stacked[position as usize];
+            let x = match align.x() {
+                egui::Align::Min => EDGE_MARGIN,
+                egui::Align::Center => 0.0,
+                egui::Align::Max => -EDGE_MARGIN,
+            };
+            let y = if position.is_bottom() {
+                -(EDGE_MARGIN + *along)
+            } else {
+                EDGE_MARGIN + *along
+            };
+            let slide = position.outward() * SLIDE * (1.0 - opacity);
+            // the ones below move up smoothly while one fades out
+            *along += (toast.height + theme.spacing) * opacity;
+            // clicks only, never focusable so typing stays where it was
+            let area = Area::new(Id::new(("fool_toast", toast.id)))
+                .order(Order::Foreground)
+                .anchor(align, vec2(x, y) + slide)
+                .movable(false)
+                .sense(Sense::CLICK)
+                .show(ctx, |ui| {
+                    ui.multiply_opacity(opacity);
+                    // selectable text would take the click from the toast
+                    ui.style_mut().interaction.selectable_labels = false;
+                    ui.set_max_width(theme.max_width);
+                    let fill = theme.fill(toast.options.kind);
+                    Frame::popup(ui.style()).fill(fill.into()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            if let Some(icon) = &toast.options.icon {
+                                match resource.get_ui_texture(icon) {
+                                    Ok(texture) => {
+                                        let size = Vec2::splat(theme.font_size * 1.5);
+                                        ui.add(Image::new((texture.id(), size)));
+                                    }
+                                    Err(err) => {
+                                        log::error!("load toast icon {} failed: {}", icon, err);
+                                        toast.options.icon = None;
+                                    }
+                                }
+                            }
+                            let text = RichText::new(&toast.options.text)
+                                .size(theme.font_size)
+                                .color(theme.text_color);
+                            ui.label(text);
+                            if toast.count > 1 {
+                                let badge = RichText::new(format!("x{}", toast.count))
+                                    .size(theme.font_size * 0.85)
+                                    .color(theme.text_color)
+                                    .strong();
+                                ui.label(badge);
+                            }
+                        });
+                    });
+                });
+            toast.height = area.response.rect.height();
+            if area.response.clicked() && toast.closing.is_none() {
+                toast.closing = Some(toast.age);
+                if let Some(callback) = &toast.on_click {
+                    state.clicked.push((toast.id, callback.clone()));
+                }
+            }
+        }
+        // fading needs the next frame even when nothing else changes
+        ctx.request_repaint();
+    }
+}
+
+impl UserData for Toasts {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("show", |lua, this, options: Table| {
+            let on_click = options.get::<Option<Function>>("on_click")?;
+            let options: ToastOptions = lua.from_value_with(
+                Value::Table(options),
+                DeserializeOptions::new().deny_unsupported_types(false),
+            )?;
+            Ok(this.show(options, on_click))
+        });
+        methods.add_method("dismiss", |_lua, this, id: u64| Ok(this.dismiss(id)));
+        methods.add_method("dismiss_all", |_lua, this, ()| {
+            this.dismiss_all();
+            Ok(())
+        });
+        methods.add_method("count", |_lua, this, ()| Ok(this.len()));
+    }
+}
+
+#[test]
+fn test_toasts() -> anyhow::Result<()> {
+    use crate::resource::AssetSource;
+    use egui::{Event, PointerButton, RawInput};
+    let dir = std::env::temp_dir().join("test_toasts");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let resource = ResourceManager::new(AssetSource::Folder(dir))?;
+    let ctx = Context::default();
+    ctx.style_mut(|style| style.animation_time = 0.2);
+    let toasts = Toasts::default();
+    toasts.set_theme(ToastTheme {
+        max_visible: 2,
+        ..Default::default()
+    });
+    let lua = mlua::Lua::new();
+    lua.globals().set("toast", toasts.clone())?;
+    lua.load(
+        r#"
+        clicked = {}
+        saved = toast:show({ text = "Game saved" })
+        assert(toast:show({ text = "Game saved" }) == saved)
+        lost = toast:show({
+            text = "Connection lost",
+            kind = "error",
+            position = "bottom_right",
+            persistent = true,
+            on_click = function(id) table.insert(clicked, id) end,
+        })
+        unlocked = toast:show({ text = "Achievement unlocked", duration = 1.0 })
+        "#,
+    )
+    .exec()?;
+    let id = |name: &str| lua.globals().get::<u64>(name);
+    let (saved, lost, unlocked) = (id("saved")?, id("lost")?, id("unlocked")?);
+    let mut time = 0.0;
+    let mut frame = |dt: f64, events: Vec<Event>| {
+        time += dt;
+        let input = RawInput {
+            time: Some(time),
+            events,
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| toasts.draw(ctx, &resource));
+    };
+    let rect = |id: u64| ctx.memory(|memory| memory.area_rect(Id::new(("fool_toast", id))));
+    // the repeat went into a counter, only two fit on screen
+    assert_eq!(toasts.len(), 3);
+    frame(0.0, vec![]);
+    frame(0.1, vec![]);
+    assert!(rect(saved).is_some() && rect(lost).is_some());
+    assert!(rect(unlocked).is_none());
+
+    // timed out and faded, the queued one takes its place
+    for _ in 0..36 {
+        frame(0.1, vec![]);
+    }
+    assert_eq!(toasts.len(), 2);
+    assert!(rect(unlocked).is_some());
+    for _ in 0..16 {
+        frame(0.1, vec![]);
+    }
+    // the error stays until the player deals with it
+    assert_eq!(toasts.len(), 1);
+
+    let pos = rect(lost).expect("shown").center();
+    frame(0.1, vec![Event::PointerMoved(pos)]);
+    let button = |pressed| Event::PointerButton {
+        pos,
+        button: PointerButton::Primary,
+        pressed,
+        modifiers: Default::default(),
+    };
+    frame(0.0, vec![button(true)]);
+    frame(0.05, vec![button(false)]);
+    // clicking it never took the keyboard from a text field
+    assert!(!ctx.wants_keyboard_input());
+    toasts.poll()?;
+    let clicked: Vec<u64> = lua.globals().get("clicked")?;
+    assert_eq!(clicked, [lost]);
+    for _ in 0..3 {
+        frame(0.1, vec![]);
+    }
+    assert!(toasts.is_empty());
+
+    lua.load("for i = 1, 5 do toast:show({ text = 'note ' .. i }) end")
+        .exec()?;
+    frame(0.1, vec![]);
+    toasts.dismiss_all();
+    assert_eq!(toasts.len(), 2);
+    frame(0.1, vec![]);
+    frame(0.2, vec![]);
+    assert!(toasts.is_empty());
+    Ok(())
+}
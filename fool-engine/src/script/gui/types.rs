@@ -1,6 +1,7 @@
 use crate::script::types::LuaPoint;
 
 use super::super::graphics::types::LuaColor;
+use super::toast::ToastTheme;
 use egui::{epaint::text::TextWrapMode, FontId, TextStyle};
 use egui::{
     epaint::{CornerRadius, Margin},
@@ -61,6 +62,9 @@ impl Into<Frame> for LuaFrame {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct LuaUIConfig {
+    /// windows with one keep where the player left them between runs
+    #[serde(default)]
+    pub id: Option<String>,
     pub title: String,
     #[serde(default)]
     pub collapsible: bool,
@@ -120,6 +124,8 @@ pub struct LuaGuiStyle {
     pub inactive_fg_color: Option<LuaColor>,
     #[serde(default)]
     pub open_fg_color: Option<LuaColor>,
+    #[serde(default)]
+    pub toast: Option<ToastTheme>,
 }
 impl LuaGuiStyle {
     pub fn text_style(&self) -> BTreeMap<TextStyle, FontId> {
@@ -0,0 +1,204 @@
+use crate::save::SaveManager;
+use egui::Id;
+use fool_graphics::canvas::SceneGraph;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// reserved, the presets are kept next to it as `__gui_layout.<name>`
+const SAVE_SLOT: &str = "__gui_layout";
+
+/// where an identified window was, in screen points
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowLayout {
+    /// left top corner of the window frame
+    pub pos: [f32; 2],
+    /// size of the contents, what `default_size` takes
+    pub size: [f32; 2],
+    pub collapsed: bool,
+}
+
+impl WindowLayout {
+    /// moved back onto a `width` x `height` screen, shrunk when it doesn't fit
+    pub fn clamped(self, width: f32, height: f32) -> Self {
+        let size = [
+            self.size[0].min(width).max(0.0),
+            self.size[1].min(height).max(0.0),
+        ];
+        let pos = [
+            self.pos[0].clamp(0.0, (width - size[0]).max(0.0)),
+            self.pos[1].clamp(0.0, (height - size[1]).max(0.0)),
+        ];
+        Self {
+            pos,
+            size,
+            collapsed: self.collapsed,
+        }
+    }
+}
+
+/// remembers the windows drawn with an `id` and puts them back where they were
+#[derive(Clone)]
+pub struct GuiWorkspace {
+    // what the identified windows look like now
+    current: Arc<RwLock<BTreeMap<String, WindowLayout>>>,
+    // what they open with, until the next load or reset
+    restored: Arc<RwLock<BTreeMap<String, WindowLayout>>>,
+    // egui keeps a window's rect by its id, a new one makes it take the restored layout
+    generation: Arc<AtomicU64>,
+    save: SaveManager,
+    scene_graph: Arc<RwLock<SceneGraph>>,
+}
+
+impl GuiWorkspace {
+    pub fn new(save: SaveManager, scene_graph: Arc<RwLock<SceneGraph>>) -> Self {
+        Self {
+            current: Default::default(),
+            restored: Default::default(),
+            generation: Default::default(),
+            save,
+            scene_graph,
+        }
+    }
+    fn slot(name: Option<&str>) -> String {
+        match name {
+            Some(name) => format!("{}.{}", SAVE_SLOT, name),
+            None => SAVE_SLOT.to_owned(),
+        }
+    }
+    pub fn window_id(&self, id: &str) -> Id {
+        Id::new(("fool_window", id, self.generation.load(Ordering::Relaxed)))
+    }
+    pub fn restored(&self, id: &str) -> Option<WindowLayout> {
+        self.restored.read().get(id).copied()
+    }
+    pub fn current(&self, id: &str) -> Option<WindowLayout> {
+        self.current.read().get(id).copied()
+    }
+    pub fn record(&self, id: &str, layout: WindowLayout) {
+        self.current.write().insert(id.to_owned(), layout);
+    }
+    pub fn layout(&self) -> BTreeMap<String, WindowLayout> {
+        self.current.read().clone()
+    }
+    /// the windows move there on their next frame
+    pub fn apply(&self, layout: BTreeMap<String, WindowLayout>) {
+        *self.current.write() = layout.clone();
+        *self.restored.write() = layout;
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+    /// back to the positions the scripts give
+    pub fn reset(&self) {
+        self.apply(BTreeMap::new());
+    }
+    /// None is the layout kept between runs
+    pub fn save_layout(&self, name: Option<&str>) -> anyhow::Result<()> {
+        let data = bson::to_bson(&self.layout())?;
+        self.save.save(Some(Self::slot(name)), true, data)
+    }
+    pub fn load_layout(&self, name: Option<&str>) -> anyhow::Result<()> {
+        let entry = self.save.load(Self::slot(name))?;
+        self.apply(bson::from_bson(entry.data)?);
+        Ok(())
+    }
+    /// restores the last run's layout, nothing when there is none
+    pub fn load(&self) {
+        if let Err(err) = self.load_layout(None) {
+            log::debug!("no saved gui layout: {}", err);
+        }
+    }
+    /// shrinks the scene's viewport so tool windows can sit along the edges
+    pub fn set_viewport_margin(&self, left: f64, top: f64, right: f64, bottom: f64) {
+        self.scene_graph
+            .write()
+            .set_viewport_margin(left, top, right, bottom);
+    }
+}
+
+#[test]
+fn test_gui_workspace() -> anyhow::Result<()> {
+    use super::{show_window, EguiContext, LuaUIConfig};
+    use crate::engine::ResourceManager;
+    use crate::resource::AssetSource;
+    let dir = std::env::temp_dir().join("test_gui_workspace");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let save = SaveManager::new(&dir);
+    let scene_graph = Arc::new(RwLock::new(SceneGraph::default()));
+    let workspace = GuiWorkspace::new(save.clone(), scene_graph.clone());
+    let context = EguiContext {
+        context: Default::default(),
+        width: 800.0,
+        heigth: 600.0,
+        resource: ResourceManager::new(AssetSource::Folder(dir.clone()))?,
+        workspace: workspace.clone(),
+        toasts: Default::default(),
+    };
+    let config = LuaUIConfig {
+        id: Some("inspector".into()),
+        title: "inspector".into(),
+        collapsible: true,
+        default_open: true,
+        x: -200.0,
+        w: 200.0,
+        h: 100.0,
+        ..Default::default()
+    };
+    let frame = || {
+        let _ = context.context.run(Default::default(), |_| {
+            show_window(config.clone(), &context, |ui| ui.label("entities"));
+        });
+    };
+    frame();
+    let first = workspace.current("inspector").expect("recorded");
+    assert!(!first.collapsed);
+    assert!(first.pos[0] < 400.0, "{:?}", first);
+
+    // the player dragged it into the corner, the next run opens it there
+    let moved = WindowLayout {
+        pos: [10.0, 20.0],
+        ..first
+    };
+    workspace.apply(BTreeMap::from([("inspector".to_owned(), moved)]));
+    workspace.save_layout(None)?;
+    workspace.save_layout(Some("debugging"))?;
+    let restored = GuiWorkspace::new(save.clone(), scene_graph.clone());
+    restored.load();
+    assert_eq!(restored.restored("inspector"), Some(moved));
+    frame();
+    assert_eq!(
+        workspace.current("inspector").map(|l| l.pos),
+        Some([10.0, 20.0])
+    );
+
+    // off screen after the window got smaller
+    let lost = WindowLayout {
+        pos: [5000.0, -40.0],
+        size: [1000.0, 100.0],
+        collapsed: true,
+    };
+    assert_eq!(
+        lost.clamped(800.0, 600.0),
+        WindowLayout {
+            pos: [0.0, 0.0],
+            size: [800.0, 100.0],
+            collapsed: true,
+        }
+    );
+    workspace.reset();
+    assert!(workspace.restored("inspector").is_none());
+    workspace.load_layout(Some("debugging"))?;
+    assert_eq!(workspace.restored("inspector"), Some(moved));
+    assert!(workspace.load_layout(Some("missing")).is_err());
+    // the presets are the engine's, not the scripts' saves
+    assert!(save
+        .list()?
+        .iter()
+        .all(|entry| entry.name.as_deref().is_some_and(crate::save::is_reserved)));
+
+    workspace.set_viewport_margin(300.0, 0.0, 0.0, 0.0);
+    assert_eq!(scene_graph.read().viewport_margin, (300.0, 0.0, 0.0, 0.0));
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use crate::map2anyhow_error;
+use mlua::{Debug, DebugEvent, Function, HookTriggers, IntoLuaMulti, Lua, VmState};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type HookFn = dyn Fn(&Lua, Debug) -> mlua::Result<VmState> + Send + Sync;
+
+// mlua can't hand the installed hook back, the one set through `set_hook` is kept here
+#[derive(Clone)]
+struct LuaHook {
+    triggers: HookTriggers,
+    callback: Arc<HookFn>,
+}
+
+/// sets the global lua hook, a debugger or profiler sets it through here so
+/// the hook bounding `on_exit` can run alongside and put it back after
+pub fn set_hook(
+    lua: &Lua,
+    triggers: HookTriggers,
+    callback: impl Fn(&Lua, Debug) -> mlua::Result<VmState> + Send + Sync + 'static,
+) {
+    install(
+        lua,
+        LuaHook {
+            triggers,
+            callback: Arc::new(callback),
+        },
+    );
+}
+pub fn remove_hook(lua: &Lua) {
+    lua.remove_app_data::<LuaHook>();
+    lua.remove_hook();
+}
+fn install(lua: &Lua, hook: LuaHook) {
+    let callback = hook.callback.clone();
+    lua.set_hook(hook.triggers, move |lua, debug| callback(lua, debug));
+    lua.set_app_data(hook);
+}
+
+/// runs the lua `on_exit` global once on shutdown, bounded by `timeout`
+#[derive(Debug, Clone)]
+pub struct ExitHook {
+    timeout: Duration,
+    done: bool,
+}
+impl ExitHook {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            done: false,
+        }
+    }
+    pub fn run(&mut self, lua: &Lua, args: impl IntoLuaMulti) -> anyhow::Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+        let Ok(on_exit) = lua.globals().get::<Function>("on_exit") else {
+            return Ok(());
+        };
+        let deadline = Instant::now() + self.timeout;
+        let timeout = self.timeout;
+        let previous = lua.app_data_ref::<LuaHook>().map(|hook| hook.clone());
+        let mut triggers = previous
+            .as_ref()
+            .map(|hook| hook.triggers)
+            .unwrap_or_default();
+        let counted = triggers.every_nth_instruction.is_some();
+        triggers.every_nth_instruction.get_or_insert(1000);
+        let chained = previous.clone();
+        // only interrupts lua code, a blocking native call still runs to the end
+        lua.set_hook(triggers, move |lua, debug| {
+            if Instant::now() > deadline {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "on_exit timed out after {:?}",
+                    timeout
+                )));
+            }
+            match &chained {
+                // the count events are ours unless the previous hook asked for them
+                Some(hook) if counted || !matches!(debug.event(), DebugEvent::Count) => {
+                    (hook.callback)(lua, debug)
+                }
+                _ => Ok(VmState::Continue),
+            }
+        });
+        let result = on_exit.call::<()>(args);
+        match previous {
+            Some(hook) => install(lua, hook),
+            None => lua.remove_hook(),
+        }
+        map2anyhow_error!(result, "run lua on_exit")
+    }
+}
+
+#[test]
+fn test_exit_hook() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    lua.load("exit_count = 0 function on_exit() exit_count = exit_count + 1 end")
+        .exec()?;
+    let mut hook = ExitHook::new(Duration::from_secs(1));
+    hook.run(&lua, ())?;
+    hook.run(&lua, ())?;
+    assert_eq!(lua.globals().get::<u32>("exit_count")?, 1);
+
+    lua.load("function on_exit() while true do end end")
+        .exec()?;
+    let mut hook = ExitHook::new(Duration::from_millis(50));
+    assert!(hook.run(&lua, ()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_exit_hook_keeps_hook() -> anyhow::Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let lua = Lua::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    set_hook(&lua, HookTriggers::new().on_calls(), move |_, _| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(VmState::Continue)
+    });
+    lua.load("function f() end function on_exit() f() end")
+        .exec()?;
+    let before = calls.load(Ordering::Relaxed);
+    ExitHook::new(Duration::from_secs(1)).run(&lua, ())?;
+    // the previous hook still saw the calls in on_exit
+    let during = calls.load(Ordering::Relaxed) - before;
+    assert!(during >= 2, "{}", during);
+    // and it is back afterwards
+    lua.load("f()").exec()?;
+    assert!(calls.load(Ordering::Relaxed) > before + during);
+
+    // the deadline holds with a hook that takes no count events
+    lua.load("function on_exit() while true do end end")
+        .exec()?;
+    assert!(ExitHook::new(Duration::from_millis(50))
+        .run(&lua, ())
+        .is_err());
+    remove_hook(&lua);
+    let after = calls.load(Ordering::Relaxed);
+    lua.load("f()").exec()?;
+    assert_eq!(calls.load(Ordering::Relaxed), after);
+    Ok(())
+}
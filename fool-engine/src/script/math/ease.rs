@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+
+/// easing curves, `t` is clamped to 0..=1,
+/// quad/cubic/quart are the same curves as kira's `InPowi(2..=4)` family used by audio tweens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ease {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InQuart,
+    OutQuart,
+    InOutQuart,
+    InSine,
+    OutSine,
+    InOutSine,
+    InExpo,
+    OutExpo,
+    InOutExpo,
+    InBack,
+    OutBack,
+    OutElastic,
+    OutBounce,
+}
+
+impl Ease {
+    pub const ALL: [(&'static str, Ease); 20] = [
+        ("linear", Ease::Linear),
+        ("in_quad", Ease::InQuad),
+        ("out_quad", Ease::OutQuad),
+        ("in_out_quad", Ease::InOutQuad),
+        ("in_cubic", Ease::InCubic),
+        ("out_cubic", Ease::OutCubic),
+        ("in_out_cubic", Ease::InOutCubic),
+        ("in_quart", Ease::InQuart),
+        ("out_quart", Ease::OutQuart),
+        ("in_out_quart", Ease::InOutQuart),
+        ("in_sine", Ease::InSine),
+        ("out_sine", Ease::OutSine),
+        ("in_out_sine", Ease::InOutSine),
+        ("in_expo", Ease::InExpo),
+        ("out_expo", Ease::OutExpo),
+        ("in_out_expo", Ease::InOutExpo),
+        ("in_back", Ease::InBack),
+        ("out_back", Ease::OutBack),
+        ("out_elastic", Ease::OutElastic),
+        ("out_bounce", Ease::OutBounce),
+    ];
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, ease)| *ease)
+    }
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        let in_out = |f: fn(f64) -> f64| {
+            if t < 0.5 {
+                f(t * 2.0) / 2.0
+            } else {
+                1.0 - f((1.0 - t) * 2.0) / 2.0
+            }
+        };
+        const BACK: f64 = 1.70158;
+        match self {
+            Ease::Linear => t,
+            Ease::InQuad => t.powi(2),
+            Ease::OutQuad => 1.0 - (1.0 - t).powi(2),
+            Ease::InOutQuad => in_out(|t| t.powi(2)),
+            Ease::InCubic => t.powi(3),
+            Ease::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Ease::InOutCubic => in_out(|t| t.powi(3)),
+            Ease::InQuart => t.powi(4),
+            Ease::OutQuart => 1.0 - (1.0 - t).powi(4),
+            Ease::InOutQuart => in_out(|t| t.powi(4)),
+            Ease::InSine => 1.0 - (t * PI / 2.0).cos(),
+            Ease::OutSine => (t * PI / 2.0).sin(),
+            Ease::InOutSine => -((PI * t).cos() - 1.0) / 2.0,
+            Ease::InExpo if t == 0.0 => 0.0,
+            Ease::InExpo => 2f64.powf(10.0 * t - 10.0),
+            Ease::OutExpo if t == 1.0 => 1.0,
+            Ease::OutExpo => 1.0 - 2f64.powf(-10.0 * t),
+            Ease::InOutExpo if t == 0.0 || t == 1.0 => t,
+            Ease::InOutExpo => in_out(|t| 2f64.powf(10.0 * t - 10.0)),
+            Ease::InBack => (BACK + 1.0) * t.powi(3) - BACK * t.powi(2),
+            Ease::OutBack => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Ease::OutElastic if t == 0.0 || t == 1.0 => t,
+            Ease::OutElastic => {
+                2f64.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+            }
+            Ease::OutBounce => out_bounce(t),
+        }
+    }
+}
+
+fn out_bounce(t: f64) -> f64 {
+    const N: f64 = 7.5625;
+    const D: f64 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
@@ -0,0 +1,114 @@
+use super::vec2::LuaVec2;
+use mlua::{FromLua, IntoLua, Lua, MetaMethod, UserData, UserDataMethods, Value};
+use vello::kurbo::{Affine, Point, Vec2};
+
+/// 2d affine transform, the coefficients are kurbo's `[a, b, c, d, e, f]`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LuaMat(pub Affine);
+
+impl LuaMat {
+    pub fn transform(&self, v: LuaVec2) -> LuaVec2 {
+        let p = self.0 * Point::new(v.x, v.y);
+        LuaVec2::new(p.x, p.y)
+    }
+    /// leaves out the translation, for directions
+    pub fn transform_vector(&self, v: LuaVec2) -> LuaVec2 {
+        let [a, b, c, d, _, _] = self.0.as_coeffs();
+        LuaVec2::new(a * v.x + c * v.y, b * v.x + d * v.y)
+    }
+    /// None when it squashes everything onto a line
+    pub fn inverse(&self) -> Option<Self> {
+        (self.0.determinant() != 0.0).then(|| Self(self.0.inverse()))
+    }
+}
+
+impl From<Affine> for LuaMat {
+    fn from(affine: Affine) -> Self {
+        Self(affine)
+    }
+}
+
+impl FromLua for LuaMat {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        match value {
+            Value::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            // the six coefficients
+            value => Ok(Self(Affine::new(<[f64; 6]>::from_lua(value, lua)?))),
+        }
+    }
+}
+
+impl UserData for LuaMat {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("mul", |_, this, other: LuaMat| Ok(Self(this.0 * other.0)));
+        methods.add_method("transform", |_, this, v: LuaVec2| Ok(this.transform(v)));
+        methods.add_method("transform_vector", |_, this, v: LuaVec2| {
+            Ok(this.transform_vector(v))
+        });
+        methods.add_method("inverse", |_, this, ()| Ok(this.inverse()));
+        methods.add_method("determinant", |_, this, ()| Ok(this.0.determinant()));
+        methods.add_method("translation", |_, this, ()| {
+            let t = this.0.translation();
+            Ok(LuaVec2::new(t.x, t.y))
+        });
+        methods.add_method("coeffs", |_, this, ()| Ok(this.0.as_coeffs()));
+        // applied after this transform
+        methods.add_method("then_translate", |_, this, (x, y): (f64, f64)| {
+            Ok(Self(this.0.then_translate(Vec2::new(x, y))))
+        });
+        methods.add_method("then_rotate", |_, this, angle: f64| {
+            Ok(Self(this.0.then_rotate(angle)))
+        });
+        methods.add_method("then_scale", |_, this, (sx, sy): (f64, Option<f64>)| {
+            Ok(Self(this.0.then_scale_non_uniform(sx, sy.unwrap_or(sx))))
+        });
+
+        // mat * mat chains, mat * vec2 transforms the point
+        methods.add_meta_function(MetaMethod::Mul, |lua, (a, b): (LuaMat, Value)| match b {
+            Value::UserData(ud) if ud.is::<LuaVec2>() => {
+                a.transform(*ud.borrow::<LuaVec2>()?).into_lua(lua)
+            }
+            b => Self(a.0 * LuaMat::from_lua(b, lua)?.0).into_lua(lua),
+        });
+        methods.add_meta_function(MetaMethod::Eq, |_, (a, b): (LuaMat, LuaMat)| Ok(a == b));
+        methods.add_meta_method(MetaMethod::ToString, |_, this, ()| {
+            let [a, b, c, d, e, f] = this.0.as_coeffs();
+            Ok(format!(
+                "Mat({:.2}, {:.2}, {:.2}, {:.2}, {:.2}, {:.2})",
+                a, b, c, d, e, f
+            ))
+        });
+    }
+}
+
+/// `mat.identity()`, `mat.translate(x, y)`, `mat.rotate(angle)`, `mat.scale(sx, sy)`
+pub fn create_mat_table(lua: &Lua) -> mlua::Result<mlua::Table> {
+    let mat = lua.create_table()?;
+    mat.set(
+        "new",
+        lua.create_function(|_, (a, b, c, d, e, f): (f64, f64, f64, f64, f64, f64)| {
+            Ok(LuaMat(Affine::new([a, b, c, d, e, f])))
+        })?,
+    )?;
+    mat.set(
+        "identity",
+        lua.create_function(|_, ()| Ok(LuaMat(Affine::IDENTITY)))?,
+    )?;
+    mat.set(
+        "translate",
+        lua.create_function(|_, (x, y): (f64, f64)| {
+            Ok(LuaMat(Affine::translate(Vec2::new(x, y))))
+        })?,
+    )?;
+    mat.set(
+        "rotate",
+        lua.create_function(|_, angle: f64| Ok(LuaMat(Affine::rotate(angle))))?,
+    )?;
+    mat.set(
+        "scale",
+        lua.create_function(|_, (sx, sy): (f64, Option<f64>)| {
+            Ok(LuaMat(Affine::scale_non_uniform(sx, sy.unwrap_or(sx))))
+        })?,
+    )?;
+    Ok(mat)
+}
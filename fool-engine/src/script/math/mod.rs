@@ -0,0 +1,256 @@
+use mlua::{IntoLua, Lua, Table, Value};
+use std::f64::consts::{PI, TAU};
+pub mod ease;
+pub mod mat;
+pub mod noise;
+pub mod rect;
+pub mod vec2;
+pub mod vec3;
+pub use ease::Ease;
+pub use mat::LuaMat;
+pub use rect::LuaRect;
+pub use vec2::LuaVec2;
+pub use vec3::LuaVec3;
+
+pub fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+/// maps `v` from `in_min..in_max` to `out_min..out_max` without clamping
+pub fn remap(v: f64, in_min: f64, in_max: f64, out_min: f64, out_max: f64) -> f64 {
+    if in_max == in_min {
+        return out_min;
+    }
+    lerp(out_min, out_max, (v - in_min) / (in_max - in_min))
+}
+pub fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge1 == edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+/// wraps an angle in radians into -PI..PI
+pub fn wrap_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+/// signed shortest rotation from `from` to `to`
+pub fn shortest_delta(from: f64, to: f64) -> f64 {
+    wrap_angle(to - from)
+}
+
+/// the `fool.math` module table
+pub fn create_module(lua: &Lua) -> mlua::Result<Value> {
+    let module = lua.create_table()?;
+    module.set(
+        "vec2",
+        lua.create_function(|_, (x, y): (Option<f64>, Option<f64>)| {
+            Ok(LuaVec2::new(x.unwrap_or(0.0), y.unwrap_or(0.0)))
+        })?,
+    )?;
+    module.set(
+        "vec3",
+        lua.create_function(|_, (x, y, z): (Option<f64>, Option<f64>, Option<f64>)| {
+            Ok(LuaVec3::new(
+                x.unwrap_or(0.0),
+                y.unwrap_or(0.0),
+                z.unwrap_or(0.0),
+            ))
+        })?,
+    )?;
+    module.set("mat", mat::create_mat_table(lua)?)?;
+    module.set(
+        "rect",
+        lua.create_function(|_, (x, y, width, height): (f64, f64, f64, f64)| {
+            Ok(LuaRect::new(x, y, width, height))
+        })?,
+    )?;
+    module.set(
+        "lerp",
+        lua.create_function(|_, (a, b, t): (f64, f64, f64)| Ok(lerp(a, b, t)))?,
+    )?;
+    module.set(
+        "clamp",
+        lua.create_function(|_, (v, min, max): (f64, f64, f64)| Ok(v.max(min).min(max)))?,
+    )?;
+    module.set(
+        "remap",
+        lua.create_function(|_, (v, a0, a1, b0, b1): (f64, f64, f64, f64, f64)| {
+            Ok(remap(v, a0, a1, b0, b1))
+        })?,
+    )?;
+    module.set(
+        "smoothstep",
+        lua.create_function(|_, (e0, e1, x): (f64, f64, f64)| Ok(smoothstep(e0, e1, x)))?,
+    )?;
+    module.set(
+        "wrap_angle",
+        lua.create_function(|_, angle: f64| Ok(wrap_angle(angle)))?,
+    )?;
+    module.set(
+        "shortest_delta",
+        lua.create_function(|_, (from, to): (f64, f64)| Ok(shortest_delta(from, to)))?,
+    )?;
+    module.set("ease", create_ease(lua)?)?;
+    let noise = lua.create_table()?;
+    noise.set(
+        "perlin2",
+        lua.create_function(|_, (x, y, seed): (f64, f64, Option<u32>)| {
+            Ok(noise::perlin2(x, y, seed.unwrap_or(0)))
+        })?,
+    )?;
+    noise.set(
+        "simplex2",
+        lua.create_function(|_, (x, y, seed): (f64, f64, Option<u32>)| {
+            Ok(noise::simplex2(x, y, seed.unwrap_or(0)))
+        })?,
+    )?;
+    module.set("noise", noise)?;
+    Ok(Value::Table(module))
+}
+
+/// the `vec` module, `vec.new(x, y)` is a vec2 and `vec.new(x, y, z)` a vec3
+pub fn create_vec_module(lua: &Lua) -> mlua::Result<Value> {
+    let module = lua.create_table()?;
+    module.set(
+        "new",
+        lua.create_function(|lua, (x, y, z): (Option<f64>, Option<f64>, Option<f64>)| {
+            let (x, y) = (x.unwrap_or(0.0), y.unwrap_or(0.0));
+            match z {
+                Some(z) => LuaVec3::new(x, y, z).into_lua(lua),
+                None => LuaVec2::new(x, y).into_lua(lua),
+            }
+        })?,
+    )?;
+    let math = create_module(lua)?;
+    let Value::Table(math) = math else {
+        unreachable!()
+    };
+    for name in ["vec2", "vec3", "mat"] {
+        module.set(name, math.get::<Value>(name)?)?;
+    }
+    Ok(Value::Table(module))
+}
+
+fn create_ease(lua: &Lua) -> mlua::Result<Table> {
+    let ease = lua.create_table()?;
+    for (name, curve) in Ease::ALL {
+        ease.set(
+            name,
+            lua.create_function(move |_, t: f64| Ok(curve.apply(t)))?,
+        )?;
+    }
+    // ease.apply("out_cubic", t) for curves chosen by name
+    ease.set(
+        "apply",
+        lua.create_function(|_, (name, t): (String, f64)| {
+            let curve = Ease::from_name(&name)
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown easing {}", name)))?;
+            Ok(curve.apply(t))
+        })?,
+    )?;
+    Ok(ease)
+}
+
+#[test]
+fn test_math_module() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    lua.globals().set("m", create_module(&lua)?)?;
+    lua.load(
+        r#"
+        local a = m.vec2(3, 4)
+        assert(a:length() == 5)
+        assert(a + m.vec2(1, 1) == m.vec2(4, 5))
+        assert(2 * a == a * 2 and (a * 2).x == 6)
+        assert((-a).y == -4)
+        -- plain tables work where a vec2 is expected
+        assert(a:dot({ x = 1, y = 0 }) == 3)
+        assert(math.abs(m.vec2(1, 0):rotate(math.pi / 2).y - 1) < 1e-9)
+        local r = m.rect(0, 0, 10, 10)
+        assert(r:contains(m.vec2(5, 5)) and not r:contains({ x = 11, y = 0 }))
+        assert(r:intersects(m.rect(5, 5, 10, 10)))
+        assert(not r:intersects(m.rect(10, 0, 5, 5)))
+        assert(r:union(m.rect(5, 5, 10, 10)).width == 15)
+        assert(m.ease.out_cubic(0) == 0 and m.ease.out_cubic(1) == 1)
+        assert(m.ease.apply("in_quad", 0.5) == 0.25)
+        assert(m.remap(5, 0, 10, 100, 200) == 150)
+        "#,
+    )
+    .exec()?;
+    for (_, curve) in Ease::ALL {
+        assert!(curve.apply(0.0).abs() < 1e-9, "{:?}", curve);
+        assert!((curve.apply(1.0) - 1.0).abs() < 1e-9, "{:?}", curve);
+    }
+    assert!((shortest_delta(PI - 0.1, -PI + 0.1) - 0.2).abs() < 1e-9);
+    assert!((wrap_angle(3.0 * PI) - -PI).abs() < 1e-9);
+    assert_eq!(noise::perlin2(3.0, 4.0, 1), 0.0);
+    assert_eq!(noise::perlin2(0.3, 0.7, 1), noise::perlin2(0.3, 0.7, 1));
+    assert_ne!(noise::simplex2(0.3, 0.7, 1), noise::simplex2(0.3, 0.7, 2));
+    for i in 0..1000 {
+        let (x, y) = (i as f64 * 0.37, i as f64 * 0.61);
+        assert!(noise::perlin2(x, y, 7).abs() <= 1.0);
+        assert!(noise::simplex2(x, y, 7).abs() <= 1.0);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_vec_module() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    lua.globals().set("vec", create_vec_module(&lua)?)?;
+    lua.load(
+        r#"
+        assert(vec.new(3, 4):length() == 5)
+        local mid = vec.new(0, 0):lerp(vec.new(10, 20), 0.5)
+        assert(mid == vec.new(5, 10))
+        local a, b = vec.new(1, 0, 0), vec.new(0, 1, 0)
+        assert(a:cross(b) == vec.new(0, 0, 1) and a:dot(b) == 0)
+        assert(a:lerp(b, 0.5) == vec.new(0.5, 0.5, 0))
+        assert((a + b) * 2 == vec.new(2, 2, 0) and (-a).x == -1)
+        assert(math.abs(vec.new(0, 3, 4):normalize():length() - 1) < 1e-12)
+        assert(vec.vec3(1, 2, 3):dot({ x = 1, y = 1, z = 1 }) == 6)
+
+        -- scale, then rotate a quarter turn, then move
+        local m = vec.mat.scale(2):then_rotate(math.pi / 2):then_translate(10, 0)
+        local p = m * vec.new(1, 0)
+        assert(math.abs(p.x - 10) < 1e-9 and math.abs(p.y - 2) < 1e-9)
+        local back = m:inverse() * p
+        assert(math.abs(back.x - 1) < 1e-9 and math.abs(back.y) < 1e-9)
+        assert(m:transform_vector(vec.new(1, 0)):length() == 2)
+        assert(vec.mat.translate(1, 2) * vec.mat.identity() == vec.mat.translate(1, 2))
+        assert(vec.mat.scale(0, 1):inverse() == nil)
+        assert(vec.mat.new(1, 0, 0, 1, 5, 6):translation() == vec.new(5, 6))
+        "#,
+    )
+    .exec()?;
+    Ok(())
+}
+
+// cargo test --release bench_vec2 -- --ignored --nocapture
+#[test]
+#[ignore = "benchmark"]
+fn bench_vec2() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    lua.globals().set("m", create_module(&lua)?)?;
+    let lua_vec = r#"
+        local V = {}
+        V.__index = V
+        local function new(x, y) return setmetatable({ x = x, y = y }, V) end
+        V.__add = function(a, b) return new(a.x + b.x, a.y + b.y) end
+        function V:length() return math.sqrt(self.x * self.x + self.y * self.y) end
+        function V:normalize() local l = self:length() return new(self.x / l, self.y / l) end
+        local acc = new(0, 0)
+        for i = 1, 100000 do acc = (acc + new(i, 1)):normalize() end
+        return acc.x
+    "#;
+    let rust_vec = r#"
+        local acc = m.vec2(0, 0)
+        for i = 1, 100000 do acc = (acc + m.vec2(i, 1)):normalize() end
+        return acc.x
+    "#;
+    for (name, code) in [("lua table", lua_vec), ("fool.math vec2", rust_vec)] {
+        let start = std::time::Instant::now();
+        lua.load(code).eval::<f64>()?;
+        println!("{}: 100k ops in {:?}", name, start.elapsed());
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// gradient noise with hashed gradients, no permutation table so any seed is cheap
+use std::f64::consts::FRAC_1_SQRT_2;
+
+fn hash(x: i64, y: i64, seed: u32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x27d4_eb2d)
+        ^ (y as u32).wrapping_mul(0x1656_67b1)
+        ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^ (h >> 15)
+}
+
+const GRADIENTS: [(f64, f64); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (-FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+    (-FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+];
+
+fn grad(ix: i64, iy: i64, seed: u32, dx: f64, dy: f64) -> f64 {
+    let (gx, gy) = GRADIENTS[(hash(ix, iy, seed) & 7) as usize];
+    gx * dx + gy * dy
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// classic perlin noise, roughly in -1..=1 and 0 on integer coordinates
+pub fn perlin2(x: f64, y: f64, seed: u32) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (dx, dy) = (x - x0, y - y0);
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let n00 = grad(ix, iy, seed, dx, dy);
+    let n10 = grad(ix + 1, iy, seed, dx - 1.0, dy);
+    let n01 = grad(ix, iy + 1, seed, dx, dy - 1.0);
+    let n11 = grad(ix + 1, iy + 1, seed, dx - 1.0, dy - 1.0);
+    let (u, v) = (fade(dx), fade(dy));
+    // max of 2d perlin with unit gradients is sqrt(0.5)
+    lerp(lerp(n00, n10, u), lerp(n01, n11, u), v) * std::f64::consts::SQRT_2
+}
+
+/// 2d simplex noise, roughly in -1..=1
+pub fn simplex2(x: f64, y: f64, seed: u32) -> f64 {
+    const F2: f64 = 0.366_025_403_784_438_6; // (sqrt(3) - 1) / 2
+    const G2: f64 = 0.211_324_865_405_187_1; // (3 - sqrt(3)) / 6
+    let s = (x + y) * F2;
+    let (i, j) = ((x + s).floor(), (y + s).floor());
+    let t = (i + j) * G2;
+    let (x0, y0) = (x - (i - t), y - (j - t));
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+    let (x1, y1) = (x0 - i1 as f64 + G2, y0 - j1 as f64 + G2);
+    let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+    let (ii, jj) = (i as i64, j as i64);
+    let corner = |ix: i64, iy: i64, dx: f64, dy: f64| {
+        let t = 0.5 - dx * dx - dy * dy;
+        if t < 0.0 {
+            0.0
+        } else {
+            t.powi(4) * grad(ix, iy, seed, dx, dy)
+        }
+    };
+    let n =
+        corner(ii, jj, x0, y0) + corner(ii + i1, jj + j1, x1, y1) + corner(ii + 1, jj + 1, x2, y2);
+    // peak with unit gradients is about 0.01
+    99.0 * n
+}
@@ -0,0 +1,100 @@
+use super::vec2::LuaVec2;
+use mlua::{FromLua, Lua, LuaSerdeExt, MetaMethod, UserData, UserDataMethods, Value};
+use serde::{Deserialize, Serialize};
+
+/// axis aligned rect, `x`/`y` is the top left corner
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LuaRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl LuaRect {
+    pub const fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+    pub fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+    pub fn center(&self) -> LuaVec2 {
+        LuaVec2::new(self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+    pub fn contains(&self, p: LuaVec2) -> bool {
+        p.x >= self.x && p.x <= self.right() && p.y >= self.y && p.y <= self.bottom()
+    }
+    /// touching edges don't count as intersecting
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        Some(Self::new(
+            x,
+            y,
+            self.right().min(other.right()) - x,
+            self.bottom().min(other.bottom()) - y,
+        ))
+    }
+    pub fn union(&self, other: &Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+}
+
+impl FromLua for LuaRect {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        match value {
+            Value::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            value => lua.from_value(value),
+        }
+    }
+}
+
+impl UserData for LuaRect {
+    fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("x", |_, this| Ok(this.x));
+        fields.add_field_method_get("y", |_, this| Ok(this.y));
+        fields.add_field_method_get("width", |_, this| Ok(this.width));
+        fields.add_field_method_get("height", |_, this| Ok(this.height));
+    }
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("contains", |_, this, p: LuaVec2| Ok(this.contains(p)));
+        methods.add_method("intersects", |_, this, other: LuaRect| {
+            Ok(this.intersects(&other))
+        });
+        methods.add_method("intersection", |_, this, other: LuaRect| {
+            Ok(this.intersection(&other))
+        });
+        methods.add_method("union", |_, this, other: LuaRect| Ok(this.union(&other)));
+        methods.add_method("center", |_, this, ()| Ok(this.center()));
+        methods.add_meta_method(MetaMethod::ToString, |_, this, ()| {
+            Ok(format!(
+                "Rect(x={:.2}, y={:.2}, width={:.2}, height={:.2})",
+                this.x, this.y, this.width, this.height
+            ))
+        });
+    }
+}
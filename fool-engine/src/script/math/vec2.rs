@@ -0,0 +1,145 @@
+use crate::script::types::LuaPoint;
+use mlua::{FromLua, Lua, LuaSerdeExt, MetaMethod, UserData, UserDataMethods, Value};
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Sub};
+
+/// 2d vector userdata, also accepts `{x, y}` tables where a vec2 is expected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LuaVec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl LuaVec2 {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+    pub fn scale(self, s: f64) -> Self {
+        Self::new(self.x * s, self.y * s)
+    }
+    pub fn dot(self, other: Self) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+    pub fn cross(self, other: Self) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+    /// zero length vectors stay zero
+    pub fn normalize(self) -> Self {
+        let len = self.length();
+        if len == 0.0 {
+            self
+        } else {
+            self.scale(1.0 / len)
+        }
+    }
+    pub fn rotate(self, angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self).scale(t)
+    }
+    pub fn angle(self) -> f64 {
+        self.y.atan2(self.x)
+    }
+}
+
+impl Add for LuaVec2 {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+impl Sub for LuaVec2 {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl From<LuaPoint<f32>> for LuaVec2 {
+    fn from(p: LuaPoint<f32>) -> Self {
+        Self::new(p.x as f64, p.y as f64)
+    }
+}
+impl From<LuaVec2> for LuaPoint<f32> {
+    fn from(v: LuaVec2) -> Self {
+        LuaPoint {
+            x: v.x as f32,
+            y: v.y as f32,
+        }
+    }
+}
+
+impl FromLua for LuaVec2 {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        match value {
+            Value::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            value => lua.from_value(value),
+        }
+    }
+}
+
+impl UserData for LuaVec2 {
+    fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("x", |_, this| Ok(this.x));
+        fields.add_field_method_get("y", |_, this| Ok(this.y));
+        fields.add_field_method_set("x", |_, this, x: f64| {
+            this.x = x;
+            Ok(())
+        });
+        fields.add_field_method_set("y", |_, this, y: f64| {
+            this.y = y;
+            Ok(())
+        });
+    }
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("add", |_, this, other: LuaVec2| Ok(*this + other));
+        methods.add_method("sub", |_, this, other: LuaVec2| Ok(*this - other));
+        methods.add_method("scale", |_, this, s: f64| Ok(this.scale(s)));
+        methods.add_method("dot", |_, this, other: LuaVec2| Ok(this.dot(other)));
+        methods.add_method("cross", |_, this, other: LuaVec2| Ok(this.cross(other)));
+        methods.add_method("length", |_, this, ()| Ok(this.length()));
+        methods.add_method("length_sq", |_, this, ()| Ok(this.dot(*this)));
+        methods.add_method("normalize", |_, this, ()| Ok(this.normalize()));
+        methods.add_method("rotate", |_, this, angle: f64| Ok(this.rotate(angle)));
+        methods.add_method("angle", |_, this, ()| Ok(this.angle()));
+        methods.add_method("distance", |_, this, other: LuaVec2| {
+            Ok((*this - other).length())
+        });
+        methods.add_method("lerp", |_, this, (other, t): (LuaVec2, f64)| {
+            Ok(this.lerp(other, t))
+        });
+        methods.add_method("unpack", |_, this, ()| Ok((this.x, this.y)));
+        // plain table, usable where a LuaPoint is expected
+        methods.add_method("to_point", |lua, this, ()| lua.to_value(this));
+
+        methods.add_meta_function(MetaMethod::Add, |_, (a, b): (LuaVec2, LuaVec2)| Ok(a + b));
+        methods.add_meta_function(MetaMethod::Sub, |_, (a, b): (LuaVec2, LuaVec2)| Ok(a - b));
+        methods.add_meta_function(MetaMethod::Mul, |lua, (a, b): (Value, Value)| {
+            match (a, b) {
+                (s @ (Value::Number(_) | Value::Integer(_)), v)
+                | (v, s @ (Value::Number(_) | Value::Integer(_))) => {
+                    let s = f64::from_lua(s, lua)?;
+                    Ok(LuaVec2::from_lua(v, lua)?.scale(s))
+                }
+                (a, b) => {
+                    // component wise
+                    let (a, b) = (LuaVec2::from_lua(a, lua)?, LuaVec2::from_lua(b, lua)?);
+                    Ok(LuaVec2::new(a.x * b.x, a.y * b.y))
+                }
+            }
+        });
+        methods.add_meta_function(MetaMethod::Div, |_, (a, s): (LuaVec2, f64)| {
+            Ok(a.scale(1.0 / s))
+        });
+        methods.add_meta_function(MetaMethod::Unm, |_, a: LuaVec2| Ok(a.scale(-1.0)));
+        methods.add_meta_function(MetaMethod::Eq, |_, (a, b): (LuaVec2, LuaVec2)| Ok(a == b));
+        methods.add_meta_method(MetaMethod::ToString, |_, this, ()| {
+            Ok(format!("Vec2(x={:.2}, y={:.2})", this.x, this.y))
+        });
+    }
+}
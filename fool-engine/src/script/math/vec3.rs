@@ -0,0 +1,132 @@
+use mlua::{FromLua, Lua, LuaSerdeExt, MetaMethod, UserData, UserDataMethods, Value};
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Sub};
+
+/// 3d vector userdata, also accepts `{x, y, z}` tables where a vec3 is expected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LuaVec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl LuaVec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+    pub fn scale(self, s: f64) -> Self {
+        Self::new(self.x * s, self.y * s, self.z * s)
+    }
+    pub fn dot(self, other: Self) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+    /// zero length vectors stay zero
+    pub fn normalize(self) -> Self {
+        let len = self.length();
+        if len == 0.0 {
+            self
+        } else {
+            self.scale(1.0 / len)
+        }
+    }
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self).scale(t)
+    }
+}
+
+impl Add for LuaVec3 {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+impl Sub for LuaVec3 {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl FromLua for LuaVec3 {
+    fn from_lua(value: Value, lua: &Lua) -> mlua::Result<Self> {
+        match value {
+            Value::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            value => lua.from_value(value),
+        }
+    }
+}
+
+impl UserData for LuaVec3 {
+    fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("x", |_, this| Ok(this.x));
+        fields.add_field_method_get("y", |_, this| Ok(this.y));
+        fields.add_field_method_get("z", |_, this| Ok(this.z));
+        fields.add_field_method_set("x", |_, this, x: f64| {
+            this.x = x;
+            Ok(())
+        });
+        fields.add_field_method_set("y", |_, this, y: f64| {
+            this.y = y;
+            Ok(())
+        });
+        fields.add_field_method_set("z", |_, this, z: f64| {
+            this.z = z;
+            Ok(())
+        });
+    }
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("add", |_, this, other: LuaVec3| Ok(*this + other));
+        methods.add_method("sub", |_, this, other: LuaVec3| Ok(*this - other));
+        methods.add_method("scale", |_, this, s: f64| Ok(this.scale(s)));
+        methods.add_method("dot", |_, this, other: LuaVec3| Ok(this.dot(other)));
+        methods.add_method("cross", |_, this, other: LuaVec3| Ok(this.cross(other)));
+        methods.add_method("length", |_, this, ()| Ok(this.length()));
+        methods.add_method("length_sq", |_, this, ()| Ok(this.dot(*this)));
+        methods.add_method("normalize", |_, this, ()| Ok(this.normalize()));
+        methods.add_method("distance", |_, this, other: LuaVec3| {
+            Ok((*this - other).length())
+        });
+        methods.add_method("lerp", |_, this, (other, t): (LuaVec3, f64)| {
+            Ok(this.lerp(other, t))
+        });
+        methods.add_method("unpack", |_, this, ()| Ok((this.x, this.y, this.z)));
+
+        methods.add_meta_function(MetaMethod::Add, |_, (a, b): (LuaVec3, LuaVec3)| Ok(a + b));
+        methods.add_meta_function(MetaMethod::Sub, |_, (a, b): (LuaVec3, LuaVec3)| Ok(a - b));
+        methods.add_meta_function(MetaMethod::Mul, |lua, (a, b): (Value, Value)| {
+            match (a, b) {
+                (s @ (Value::Number(_) | Value::Integer(_)), v)
+                | (v, s @ (Value::Number(_) | Value::Integer(_))) => {
+                    let s = f64::from_lua(s, lua)?;
+                    Ok(LuaVec3::from_lua(v, lua)?.scale(s))
+                }
+                (a, b) => {
+                    // component wise
+                    let (a, b) = (LuaVec3::from_lua(a, lua)?, LuaVec3::from_lua(b, lua)?);
+                    Ok(LuaVec3::new(a.x * b.x, a.y * b.y, a.z * b.z))
+                }
+            }
+        });
+        methods.add_meta_function(MetaMethod::Div, |_, (a, s): (LuaVec3, f64)| {
+            Ok(a.scale(1.0 / s))
+        });
+        methods.add_meta_function(MetaMethod::Unm, |_, a: LuaVec3| Ok(a.scale(-1.0)));
+        methods.add_meta_function(MetaMethod::Eq, |_, (a, b): (LuaVec3, LuaVec3)| Ok(a == b));
+        methods.add_meta_method(MetaMethod::ToString, |_, this, ()| {
+            Ok(format!(
+                "Vec3(x={:.2}, y={:.2}, z={:.2})",
+                this.x, this.y, this.z
+            ))
+        });
+    }
+}
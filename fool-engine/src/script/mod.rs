@@ -2,38 +2,54 @@ pub mod audio;
 pub mod engine;
 pub mod graphics;
 pub mod gui;
+pub mod hook;
+pub mod math;
+pub mod resource;
+pub mod rng;
+pub mod time;
 pub mod types;
+use crate::engine::BootProgress;
 use crate::event::InputEvent;
 use crate::{map2anyhow_error, physics::LuaPhysics};
-pub use engine::{LuaEngine, LuaWindow};
-use fool_script::FoolScript;
+pub use engine::{LuaEngine, LuaEngineServices, LuaWindow};
+use fool_script::{call_traced, FoolScript, ScriptError};
 use fool_window::WinEvent;
 pub use gui::EguiContext;
+pub use hook::ExitHook;
 use lazy_static::lazy_static;
 use mlua::{Function, Lua, Value};
 use parking_lot::Mutex;
 use std::time::Instant;
+use time::{FrameClock, FrameTime};
 lazy_static! {
-    static ref last_time: Mutex<Instant> = Mutex::new(Instant::now());
+    static ref frame_clock: Mutex<FrameClock> = Mutex::new(FrameClock::new(Instant::now()));
 }
 
-pub fn time_peer_frame() -> f64 {
-    let mut lt = last_time.lock();
-    let now = Instant::now();
-    let dt = now.duration_since(*lt).as_secs_f64();
-    *lt = now;
-    dt
+/// ticked before anything else in the frame, every script call sees the same time
+pub fn time_peer_frame(paused: bool) -> FrameTime {
+    let mut clock = frame_clock.lock();
+    clock.tick(Instant::now(), paused);
+    clock.time()
+}
+/// the clock `time_peer_frame` ticks, as of the current frame
+pub fn frame_time() -> FrameTime {
+    frame_clock.lock().time()
+}
+/// the time scale audio should follow, once per change
+pub fn take_audio_time_scale() -> Option<f64> {
+    frame_clock.lock().take_audio_scale()
 }
 
 pub fn run_init_fn(lua: &Lua, lua_win: &LuaEngine) -> anyhow::Result<()> {
+    frame_clock.lock().reset(Instant::now());
     match lua.globals().get::<Function>("init") {
         Ok(init_fn) => {
-            map2anyhow_error!(
+            script_result(
                 lua.scope(|_| {
                     let window = lua.create_userdata(lua_win.clone())?;
-                    init_fn.call::<()>(window)
+                    Ok(call_traced::<()>(lua, &init_fn, window)?)
                 }),
-                "run_init_fn"
+                "run_init_fn",
             )?;
             Ok(())
         }
@@ -44,55 +60,132 @@ pub fn run_init_fn(lua: &Lua, lua_win: &LuaEngine) -> anyhow::Result<()> {
     }
 }
 
-pub fn run_fn(lua: &Lua, lua_win: &LuaEngine, events: &Vec<WinEvent>) -> anyhow::Result<()> {
-    let elapsed = time_peer_frame();
-    map2anyhow_error!(
+// keeps the script error, with its traceback, under `context`
+fn script_result<T>(result: mlua::Result<T>, context: &'static str) -> anyhow::Result<T> {
+    result.map_err(|err| anyhow::Error::new(ScriptError::from(err)).context(context))
+}
+
+/// the optional `preload(boot)` of main.lua, run behind the splash before `init`
+pub fn run_preload_fn(lua: &Lua, progress: &BootProgress) -> anyhow::Result<()> {
+    let Ok(preload_fn) = lua.globals().get::<Function>("preload") else {
+        return Ok(());
+    };
+    call_traced::<()>(lua, &preload_fn, progress.clone())
+        .map_err(|err| anyhow::Error::new(err).context("run_preload_fn"))
+}
+
+/// the optional `required_assets` list main.lua declares
+pub fn required_assets(lua: &Lua) -> anyhow::Result<Vec<String>> {
+    let assets = map2anyhow_error!(
+        lua.globals().get::<Option<Vec<String>>>("required_assets"),
+        "read required_assets"
+    )?;
+    Ok(assets.unwrap_or_default())
+}
+
+pub fn update_fn(
+    lua: &Lua,
+    lua_win: &LuaEngine,
+    events: &Vec<WinEvent>,
+    dt: f64,
+) -> anyhow::Result<()> {
+    // update is optional, scripts without it only use run
+    let Ok(lua_update_fn) = lua.globals().get::<Function>("update") else {
+        return Ok(());
+    };
+    // the presses since the last step, not the frame's
+    let queue = lua_win.input_queue.stepped();
+    script_result(
+        lua.scope(|scope| {
+            let window = scope.create_userdata(lua_win.clone())?;
+            let input_event = InputEvent {
+                events,
+                actions: &lua_win.actions,
+                text: &lua_win.text_input,
+                queue: &queue,
+            };
+            let input_event = scope.create_userdata(input_event)?;
+            call_traced::<()>(lua, &lua_update_fn, (window, input_event, dt))?;
+            Ok(())
+        }),
+        "update_fn failed",
+    )
+}
+
+pub fn run_fn(
+    lua: &Lua,
+    lua_win: &LuaEngine,
+    events: &Vec<WinEvent>,
+    alpha: f64,
+) -> anyhow::Result<()> {
+    let elapsed = frame_time().scaled_delta;
+    script_result(
         lua.scope(|scope| {
             let window = scope.create_userdata(lua_win.clone())?;
-            let input_event = InputEvent { events };
+            let input_event = InputEvent {
+                events,
+                actions: &lua_win.actions,
+                text: &lua_win.text_input,
+                queue: &lua_win.input_queue,
+            };
             let input_event = scope.create_userdata(input_event)?;
             let lua_view_fn: Function = lua.globals().get("run")?;
-            lua_view_fn.call::<()>((window, input_event, elapsed))?;
+            call_traced::<()>(lua, &lua_view_fn, (window, input_event, elapsed, alpha))?;
             Ok(())
         }),
-        "run_frame_fn failed"
+        "run_frame_fn failed",
     )
 }
 pub fn exit_fn(lua: &Lua, lua_win: &LuaEngine, events: &Vec<WinEvent>) -> anyhow::Result<()> {
-    let elapsed = time_peer_frame();
-    map2anyhow_error!(
+    // menus keep running on real time
+    let elapsed = frame_time().delta;
+    script_result(
         lua.scope(|scope| {
             let window = scope.create_userdata(lua_win.clone())?;
-            let input_event = InputEvent { events };
+            let input_event = InputEvent {
+                events,
+                actions: &lua_win.actions,
+                text: &lua_win.text_input,
+                queue: &lua_win.input_queue,
+            };
             let input_event = scope.create_userdata(input_event)?;
             let lua_view_fn: Function = lua.globals().get("exit")?;
-            lua_view_fn.call::<()>((window, input_event, elapsed))?;
+            call_traced::<()>(lua, &lua_view_fn, (window, input_event, elapsed))?;
             Ok(())
         }),
-        "exit_fn failed"
+        "exit_fn failed",
     )
 }
 
 pub fn pause_fn(lua: &Lua, lua_win: &LuaEngine, events: &Vec<WinEvent>) -> anyhow::Result<()> {
-    let elapsed = time_peer_frame();
-    map2anyhow_error!(
+    // menus keep running on real time
+    let elapsed = frame_time().delta;
+    script_result(
         lua.scope(|scope| {
             let window = scope.create_userdata(lua_win.clone())?;
-            let input_event = InputEvent { events };
+            let input_event = InputEvent {
+                events,
+                actions: &lua_win.actions,
+                text: &lua_win.text_input,
+                queue: &lua_win.input_queue,
+            };
             let input_event = scope.create_userdata(input_event)?;
             let lua_view_fn: Function = lua.globals().get("pause")?;
-            lua_view_fn.call::<()>((window, input_event, elapsed))?;
+            call_traced::<()>(lua, &lua_view_fn, (window, input_event, elapsed))?;
             Ok(())
         }),
-        "pause_fn failed"
+        "pause_fn failed",
     )
 }
 pub fn setup_modules(lua: &FoolScript) -> anyhow::Result<()> {
     lua.register_user_mod("Physics", |lua: &Lua| {
-        let lua_phy_new = lua.create_function(|_, (x, y): (f32, f32)| Ok(LuaPhysics::new(x, y)))?;
+        let lua_phy_new =
+            lua.create_function(|lua, (x, y): (f32, f32)| LuaPhysics::create(lua, x, y))?;
         let lua_phy = lua.create_table()?;
         lua_phy.set("new", lua_phy_new)?;
         Ok(Value::Table(lua_phy))
     })?;
+    lua.register_user_mod("fool.math", math::create_module)?;
+    lua.register_user_mod("vec", math::create_vec_module)?;
     Ok(())
 }
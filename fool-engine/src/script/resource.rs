@@ -0,0 +1,204 @@
+use super::graphics::types::LuaColor;
+use crate::engine::ResourceManager;
+use crate::resource::imageops::{parse_filter, Atlas, ImageOp};
+use crate::{map2anyhow_error, map2lua_error};
+use crossbeam_channel::{Receiver, Sender};
+use mlua::{Function, IntoLua, Lua, LuaSerdeExt, UserData, UserDataMethods, Value};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// scoped loading around level transitions
+#[derive(Clone)]
+pub struct LuaResource(pub ResourceManager);
+
+impl UserData for LuaResource {
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("push_scope", |_lua, this, name: String| {
+            this.0.push_scope(name);
+            Ok(())
+        });
+        methods.add_method("pop_scope", |_lua, this, ()| Ok(this.0.pop_scope()));
+        methods.add_method("release_scope", |_lua, this, name: String| {
+            Ok(this.0.release_scope(&name))
+        });
+        methods.add_method("scopes", |_lua, this, ()| Ok(this.0.scopes()));
+        methods.add_method("pin", |_lua, this, name: String| {
+            this.0.pin(&name);
+            Ok(())
+        });
+        methods.add_method("memory_report", |lua, this, ()| {
+            lua.to_value(&this.0.memory_report())
+        });
+    }
+}
+
+enum Derived {
+    Key(String),
+    Atlas(Atlas),
+}
+
+impl IntoLua for Derived {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        match self {
+            Derived::Key(key) => key.into_lua(lua),
+            Derived::Atlas(atlas) => lua.to_value(&atlas),
+        }
+    }
+}
+
+type DerivedResult = (u64, anyhow::Result<Derived>);
+
+/// images derived at runtime, cached in `raw_image` under a key made from the
+/// source and the operation, with a callback the work runs on a thread of its own
+#[derive(Clone)]
+pub struct LuaImageOps {
+    resource: ResourceManager,
+    callbacks: Arc<Mutex<HashMap<u64, Function>>>,
+    next_id: Arc<AtomicU64>,
+    done: (Sender<DerivedResult>, Receiver<DerivedResult>),
+}
+
+impl LuaImageOps {
+    pub fn new(resource: ResourceManager) -> Self {
+        Self {
+            resource,
+            callbacks: Default::default(),
+            next_id: Default::default(),
+            done: crossbeam_channel::unbounded(),
+        }
+    }
+    // the result right away without a callback, nil and `callback(result, err)`
+    // from `poll` once it is done with one
+    fn run(
+        &self,
+        label: String,
+        callback: Option<Function>,
+        job: impl FnOnce(&ResourceManager) -> anyhow::Result<Derived> + Send + 'static,
+    ) -> mlua::Result<Option<Derived>> {
+        let Some(callback) = callback else {
+            return map2lua_error!(job(&self.resource), label).map(Some);
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.callbacks.lock().insert(id, callback);
+        let (resource, done) = (self.resource.clone(), self.done.0.clone());
+        let spawned = std::thread::Builder::new()
+            .name("fool-image".into())
+            .spawn(move || {
+                let result = job(&resource).map_err(|err| err.context(label));
+                let _ = done.send((id, result));
+            });
+        if let Err(err) = spawned {
+            self.callbacks.lock().remove(&id);
+            return Err(mlua::Error::external(err));
+        }
+        Ok(None)
+    }
+    fn derive(
+        &self,
+        source: String,
+        op: ImageOp,
+        callback: Option<Function>,
+    ) -> mlua::Result<Option<Derived>> {
+        let label = format!("derive {} from {}", op.key(&source), source);
+        self.run(label, callback, move |resource| {
+            resource.derive_image(&source, &op).map(Derived::Key)
+        })
+    }
+    /// runs the callbacks of the finished work, once per frame
+    pub fn poll(&self) -> anyhow::Result<()> {
+        for (id, result) in self.done.1.try_iter() {
+            let Some(callback) = self.callbacks.lock().remove(&id) else {
+                continue;
+            };
+            let called = match result {
+                Ok(derived) => callback.call::<()>((derived, Value::Nil)),
+                Err(err) => callback.call::<()>((Value::Nil, format!("{:#}", err))),
+            };
+            map2anyhow_error!(called, "run image callback")?;
+        }
+        Ok(())
+    }
+}
+
+impl UserData for LuaImageOps {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // filter is nearest, triangle, catmull_rom, gaussian or lanczos3, triangle by default
+        methods.add_method(
+            "resize",
+            |_,
+             this,
+             (key, width, height, filter, callback): (
+                String,
+                u32,
+                u32,
+                Option<String>,
+                Option<Function>,
+            )| {
+                let filter = filter.as_deref().unwrap_or("triangle");
+                let filter = map2lua_error!(parse_filter(filter), format!("resize {}", key))?;
+                let op = ImageOp::Resize {
+                    width,
+                    height,
+                    filter,
+                };
+                this.derive(key, op, callback)
+            },
+        );
+        methods.add_method(
+            "crop",
+            |_,
+             this,
+             (key, x, y, width, height, callback): (
+                String,
+                u32,
+                u32,
+                u32,
+                u32,
+                Option<Function>,
+            )| {
+                let op = ImageOp::Crop {
+                    x,
+                    y,
+                    width,
+                    height,
+                };
+                this.derive(key, op, callback)
+            },
+        );
+        methods.add_method(
+            "grayscale",
+            |_, this, (key, callback): (String, Option<Function>)| {
+                this.derive(key, ImageOp::Grayscale, callback)
+            },
+        );
+        methods.add_method(
+            "tint",
+            |_, this, (key, color, callback): (String, LuaColor, Option<Function>)| {
+                let op = ImageOp::Tint([color.r, color.g, color.b, color.a]);
+                this.derive(key, op, callback)
+            },
+        );
+        methods.add_method(
+            "flip",
+            |_, this, (key, horizontal, callback): (String, bool, Option<Function>)| {
+                this.derive(key, ImageOp::Flip { horizontal }, callback)
+            },
+        );
+        methods.add_method(
+            "pack_atlas",
+            |_, this, (keys, max_size, callback): (Vec<String>, u32, Option<Function>)| {
+                let label = format!(
+                    "pack atlas of {} in {}x{}",
+                    keys.join(", "),
+                    max_size,
+                    max_size
+                );
+                this.run(label, callback, move |resource| {
+                    resource.pack_atlas(&keys, max_size).map(Derived::Atlas)
+                })
+            },
+        );
+    }
+}
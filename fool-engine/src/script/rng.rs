@@ -0,0 +1,172 @@
+use mlua::{Table, UserData, Value};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MULTIPLIER: u64 = 6364136223846793005;
+// the default pcg32 stream
+const INCREMENT: u64 = 1442695040888963407;
+
+/// pcg32 (XSH RR), small enough to keep here so a seed plays the same
+/// whatever version of a rand crate is around
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pcg32 {
+    state: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+    /// uniform in `min..=max`, None when `min > max`
+    pub fn int(&mut self, min: i64, max: i64) -> Option<i64> {
+        if min > max {
+            return None;
+        }
+        let range = (max.wrapping_sub(min) as u64).wrapping_add(1);
+        // the whole i64 range
+        if range == 0 {
+            return Some(self.next_u64() as i64);
+        }
+        // rejects the low values that would make the modulo biased
+        let threshold = range.wrapping_neg() % range;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return Some(min.wrapping_add((x % range) as i64));
+            }
+        }
+    }
+    /// uniform in `0..1`
+    pub fn float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// fisher yates
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.int(0, i as i64).unwrap_or_default() as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RngState {
+    seed: u64,
+    rng: Pcg32,
+}
+
+/// the engine's generator, apart from `math.random` so replays can reseed it,
+/// every run starts from a fresh seed that is logged
+#[derive(Debug, Clone)]
+pub struct LuaRng(Arc<Mutex<RngState>>);
+
+impl Default for LuaRng {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        log::info!("rng seed: {}", seed);
+        Self::new(seed)
+    }
+}
+
+impl LuaRng {
+    pub fn new(seed: u64) -> Self {
+        Self(Arc::new(Mutex::new(RngState {
+            seed,
+            rng: Pcg32::new(seed),
+        })))
+    }
+    /// restarts the sequence of `seed`
+    pub fn reseed(&self, seed: u64) {
+        let mut state = self.0.lock();
+        state.seed = seed;
+        state.rng = Pcg32::new(seed);
+    }
+    /// the seed the current sequence started from
+    pub fn seed(&self) -> u64 {
+        self.0.lock().seed
+    }
+}
+
+impl UserData for LuaRng {
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        // lua integers are signed, the bits are kept
+        methods.add_method("seed", |_, this, seed: i64| {
+            this.reseed(seed as u64);
+            Ok(())
+        });
+        methods.add_method("get_seed", |_, this, ()| Ok(this.seed() as i64));
+        methods.add_method("int", |_, this, (min, max): (i64, i64)| {
+            this.0.lock().rng.int(min, max).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("rng int: min {} > max {}", min, max))
+            })
+        });
+        methods.add_method("float", |_, this, ()| Ok(this.0.lock().rng.float()));
+        // in place, the table is returned for chaining
+        methods.add_method("shuffle", |_, this, array: Table| {
+            let mut items = array
+                .sequence_values::<Value>()
+                .collect::<mlua::Result<Vec<_>>>()?;
+            this.0.lock().rng.shuffle(&mut items);
+            for (i, item) in items.into_iter().enumerate() {
+                array.raw_set(i + 1, item)?;
+            }
+            Ok(array)
+        });
+    }
+}
+
+#[test]
+fn test_rng() -> mlua::Result<()> {
+    let (mut a, mut b) = (Pcg32::new(42), Pcg32::new(42));
+    let sequence = (0..64).map(|_| a.next_u64()).collect::<Vec<_>>();
+    assert_eq!(sequence, (0..64).map(|_| b.next_u64()).collect::<Vec<_>>());
+    assert_ne!(sequence[0], Pcg32::new(43).next_u64());
+    for _ in 0..1000 {
+        assert!((-3..=3).contains(&a.int(-3, 3).unwrap()));
+        assert!((0.0..1.0).contains(&a.float()));
+    }
+    assert_eq!(a.int(5, 5), Some(5));
+    assert_eq!(a.int(1, 0), None);
+    a.int(i64::MIN, i64::MAX).unwrap();
+
+    let mut items = (0..50).collect::<Vec<_>>();
+    a.shuffle(&mut items);
+    assert_ne!(items, (0..50).collect::<Vec<_>>());
+    items.sort();
+    assert_eq!(items, (0..50).collect::<Vec<_>>());
+
+    let lua = mlua::Lua::new();
+    let rng = LuaRng::new(7);
+    lua.globals().set("rng", rng.clone())?;
+    let first: Vec<i64> = lua
+        .load("local t = {1, 2, 3, 4, 5, 6, 7, 8} rng:shuffle(t) return t")
+        .eval()?;
+    let mut sorted = first.clone();
+    sorted.sort();
+    assert_eq!(sorted, (1..=8).collect::<Vec<_>>());
+    // a reseed replays the same shuffle
+    let replay: Vec<i64> = lua
+        .load("rng:seed(7) return rng:shuffle({1, 2, 3, 4, 5, 6, 7, 8})")
+        .eval()?;
+    assert_eq!(first, replay);
+    assert_eq!(rng.seed(), 7);
+    assert!(lua.load("rng:int(2, 1)").exec().is_err());
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use mlua::UserData;
+use std::time::{Duration, Instant};
+
+// weight of the newest frame in the smoothed fps
+const FPS_SMOOTHING: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTime {
+    /// seconds since init
+    pub total: f64,
+    /// seconds the last frame took
+    pub delta: f64,
+    pub fps: f64,
+    /// the time scale of this frame, 0 while paused or in a hitstop
+    pub scale: f64,
+    /// `delta` times `scale`
+    pub scaled_delta: f64,
+    /// scaled seconds since init
+    pub scaled_total: f64,
+}
+
+/// wall clock ticked once at the start of every rendered frame
+#[derive(Debug, Clone, Copy)]
+pub struct FrameClock {
+    start: Instant,
+    last: Instant,
+    delta: f64,
+    fps: f64,
+    // what the current frame runs at
+    scale: f64,
+    // set from scripts, picked up by the next tick
+    target: f64,
+    hitstop: Option<Duration>,
+    hitstop_until: Option<Instant>,
+    scaled_delta: f64,
+    scaled_total: f64,
+    // forward `target` to the time scaled audio groups
+    audio: bool,
+    audio_scale: f64,
+    audio_pending: Option<f64>,
+}
+
+impl FrameClock {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            start: now,
+            last: now,
+            delta: 0.0,
+            fps: 0.0,
+            scale: 1.0,
+            target: 1.0,
+            hitstop: None,
+            hitstop_until: None,
+            scaled_delta: 0.0,
+            scaled_total: 0.0,
+            audio: false,
+            audio_scale: 1.0,
+            audio_pending: None,
+        }
+    }
+    /// starts counting again, the time spent loading is not a frame
+    pub fn reset(&mut self, now: Instant) {
+        *self = Self::new(now);
+    }
+    /// the seconds since the last tick, the scale set since then applies from here on,
+    /// `paused` freezes scaled time whatever the scale
+    pub fn tick(&mut self, now: Instant, paused: bool) -> f64 {
+        let dt = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.delta = dt;
+        if dt > 0.0 {
+            self.fps = match self.fps > 0.0 {
+                true => self.fps + (1.0 / dt - self.fps) * FPS_SMOOTHING,
+                false => 1.0 / dt,
+            };
+        }
+        if let Some(hitstop) = self.hitstop.take() {
+            let until = now + hitstop;
+            self.hitstop_until = Some(self.hitstop_until.map_or(until, |u| u.max(until)));
+        }
+        let frozen = self.hitstop_until.is_some_and(|until| now < until);
+        if !frozen {
+            self.hitstop_until = None;
+        }
+        self.scale = match paused || frozen {
+            true => 0.0,
+            false => self.target,
+        };
+        self.scaled_delta = dt * self.scale;
+        self.scaled_total += self.scaled_delta;
+        // hitstops and pauses are too short or handled elsewhere, audio only follows the scale
+        let audio_scale = if self.audio { self.target } else { 1.0 };
+        if audio_scale != self.audio_scale {
+            self.audio_scale = audio_scale;
+            self.audio_pending = Some(audio_scale);
+        }
+        dt
+    }
+    pub fn time(&self) -> FrameTime {
+        FrameTime {
+            total: self.last.duration_since(self.start).as_secs_f64(),
+            delta: self.delta,
+            fps: self.fps,
+            scale: self.scale,
+            scaled_delta: self.scaled_delta,
+            scaled_total: self.scaled_total,
+        }
+    }
+    /// takes effect on the next frame, 0 freezes scaled time
+    pub fn set_scale(&mut self, scale: f64) {
+        self.target = scale.max(0.0);
+    }
+    /// the scale the next frame runs at, hitstops aside
+    pub fn target_scale(&self) -> f64 {
+        self.target
+    }
+    /// freezes scaled time for `duration` from the next frame,
+    /// overlapping hitstops end with the longest
+    pub fn hitstop(&mut self, duration: Duration) {
+        self.hitstop = Some(self.hitstop.map_or(duration, |d| d.max(duration)));
+    }
+    pub fn set_scale_audio(&mut self, enabled: bool) {
+        self.audio = enabled;
+    }
+    /// the audio time scale to apply, once per change
+    pub fn take_audio_scale(&mut self) -> Option<f64> {
+        self.audio_pending.take()
+    }
+}
+
+/// the frame clock as seen from scripts
+#[derive(Debug, Clone, Copy)]
+pub struct LuaTime;
+
+impl UserData for LuaTime {
+    fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("total", |_, _| Ok(super::frame_time().total));
+        fields.add_field_method_get("delta", |_, _| Ok(super::frame_time().delta));
+        fields.add_field_method_get("fps", |_, _| Ok(super::frame_time().fps));
+    }
+    fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("dt", |_, _, ()| Ok(super::frame_time().scaled_delta));
+        methods.add_method("real_dt", |_, _, ()| Ok(super::frame_time().delta));
+        methods.add_method("scaled_total", |_, _, ()| {
+            Ok(super::frame_time().scaled_total)
+        });
+        methods.add_method("scale", |_, _, ()| {
+            Ok(super::frame_clock.lock().target_scale())
+        });
+        methods.add_method("set_scale", |_, _, scale: f64| {
+            super::frame_clock.lock().set_scale(scale);
+            Ok(())
+        });
+        methods.add_method("hitstop", |_, _, ms: u64| {
+            super::frame_clock.lock().hitstop(Duration::from_millis(ms));
+            Ok(())
+        });
+        methods.add_method("scale_audio", |_, _, enabled: bool| {
+            super::frame_clock.lock().set_scale_audio(enabled);
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn test_frame_clock() {
+    let start = Instant::now();
+    let mut clock = FrameClock::new(start);
+    let mut now = start;
+    // a slow first frame, then a steady 60 fps with some jitter
+    now += Duration::from_millis(100);
+    clock.tick(now, false);
+    assert_eq!(clock.time().fps, 10.0);
+    for i in 0..120 {
+        now += Duration::from_micros(if i % 2 == 0 { 15_667 } else { 17_667 });
+        clock.tick(now, false);
+    }
+    let time = clock.time();
+    assert!((time.fps - 60.0).abs() < 1.0, "fps {}", time.fps);
+    assert_eq!(time.delta, 0.017667);
+    assert_eq!(time.total, now.duration_since(start).as_secs_f64());
+    // summed frame by frame, unscaled it only drifts by rounding
+    assert!(
+        (time.scaled_total - time.total).abs() < 1e-9,
+        "scaled_total {} total {}",
+        time.scaled_total,
+        time.total
+    );
+    clock.reset(now);
+    assert_eq!(clock.time().total, 0.0);
+}
+
+#[test]
+fn test_time_scale() {
+    let frame = Duration::from_millis(10);
+    let mut now = Instant::now();
+    let mut clock = FrameClock::new(now);
+    let mut tick = |clock: &mut FrameClock, paused: bool| {
+        now += frame;
+        clock.tick(now, paused);
+        clock.time()
+    };
+    clock.set_scale(0.5);
+    clock.set_scale_audio(true);
+    // the whole frame runs at the scale it started with
+    assert_eq!(clock.time().scale, 1.0);
+    let time = tick(&mut clock, false);
+    assert_eq!((time.scale, time.scaled_delta), (0.5, 0.005));
+    assert_eq!(clock.take_audio_scale(), Some(0.5));
+    assert_eq!(clock.take_audio_scale(), None);
+    // pause wins over the scale
+    assert_eq!(tick(&mut clock, true).scaled_delta, 0.0);
+    assert_eq!(tick(&mut clock, false).scale, 0.5);
+
+    clock.hitstop(Duration::from_millis(25));
+    clock.hitstop(Duration::from_millis(15));
+    let before = clock.time().scaled_total;
+    for _ in 0..3 {
+        assert_eq!(tick(&mut clock, false).scale, 0.0);
+    }
+    let time = tick(&mut clock, false);
+    assert_eq!((time.scale, time.scaled_total), (0.5, before + 0.005));
+    assert!(time.total > time.scaled_total);
+    // a hitstop does not reach the audio
+    assert_eq!(clock.take_audio_scale(), None);
+    clock.set_scale_audio(false);
+    tick(&mut clock, false);
+    assert_eq!(clock.take_audio_scale(), Some(1.0));
+}
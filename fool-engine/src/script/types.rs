@@ -1,3 +1,4 @@
+use super::math::LuaVec2;
 use egui::Pos2;
 use mlua::{FromLua, IntoLua, Lua, LuaSerdeExt, Result as LuaResult, UserData};
 use rapier2d::prelude::*;
@@ -29,6 +30,11 @@ pub struct LuaPoint<T> {
     pub x: T,
     pub y: T,
 }
+impl From<Point<f32>> for LuaPoint<f32> {
+    fn from(p: Point<f32>) -> Self {
+        Self { x: p.x, y: p.y }
+    }
+}
 impl Into<Point<f32>> for LuaPoint<f32> {
     fn into(self) -> Point<f32> {
         Point::new(self.x, self.y)
@@ -42,7 +48,14 @@ impl Into<egui::Pos2> for LuaPoint<f32> {
 }
 impl<T: Sized + DeserializeOwned> FromLua for LuaPoint<T> {
     fn from_lua(value: mlua::Value, lua: &Lua) -> LuaResult<Self> {
-        lua.from_value(value)
+        match value {
+            // fool.math vec2 is accepted wherever a point is
+            mlua::Value::UserData(ud) if ud.is::<LuaVec2>() => {
+                let vec = *ud.borrow::<LuaVec2>()?;
+                lua.from_value(lua.to_value(&vec)?)
+            }
+            value => lua.from_value(value),
+        }
     }
 }
 impl<T: IntoLua + Copy> UserData for LuaPoint<T> {
@@ -1,4 +1,4 @@
-use super::{FontManager, ImageManager, Style};
+use super::{FontManager, ImageManager, Style, SvgManager};
 use std::fmt::Debug;
 use vello::{Scene, kurbo::Shape};
 pub trait Drawable: DrawableClone + Debug {
@@ -8,6 +8,7 @@ pub trait Drawable: DrawableClone + Debug {
         style: &Style,
         font_mgr: FontManager,
         img_mgr: ImageManager,
+        svg_mgr: SvgManager,
     ) -> anyhow::Result<()>;
 }
 
@@ -18,6 +19,7 @@ impl<T: Shape + Sized + Debug + Clone + 'static> Drawable for T {
         style: &Style,
         _font_mgr: FontManager,
         img_mgr: ImageManager,
+        _svg_mgr: SvgManager,
     ) -> anyhow::Result<()> {
         if !style.visible {
             return Ok(());
@@ -0,0 +1,570 @@
+use super::style::{CustomBrush, CustomExtend, CustomGradient, CustomGradientKind};
+use super::utils::light_mask_path;
+use super::{SceneGraph, SceneNode, SceneNodeKind, Style, TextDrawable};
+use kurbo::{
+    Affine, Arc, BezPath, Cap, CubicBez, Ellipse, Join, Point, QuadBez, Rect, RoundedRect, Shape,
+    Size, Triangle,
+};
+use peniko::{Fill, Image};
+use std::fmt::Write;
+
+// flattening tolerance for the shapes written as paths
+const PATH_TOLERANCE: f64 = 0.1;
+
+impl SceneGraph {
+    /// the node tree as an svg document, placed and styled the way `draw` would,
+    /// images are referenced by their resource name and sprite frames embedded as png
+    pub fn to_svg(&self) -> String {
+        let mut writer = SvgWriter {
+            graph: self,
+            defs: String::new(),
+            body: String::new(),
+            next_id: 0,
+        };
+        let mut style = self.style.clone();
+        style.translation = self.transform();
+        writer.node(&self.root, &style);
+        let (width, height) = self.default_size;
+        let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg""#);
+        if width > 0.0 && height > 0.0 {
+            let _ = write!(
+                svg,
+                r#" width="{width}" height="{height}" viewBox="0 0 {width} {height}""#
+            );
+        }
+        svg.push_str(">\n");
+        if !writer.defs.is_empty() {
+            let _ = write!(svg, "<defs>\n{}</defs>\n", writer.defs);
+        }
+        svg.push_str(&writer.body);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+struct SvgWriter<'a> {
+    graph: &'a SceneGraph,
+    defs: String,
+    body: String,
+    next_id: usize,
+}
+
+impl SvgWriter<'_> {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    // the same walk as `SceneGraph::draw_node`
+    fn node(&mut self, node: &SceneNode, parent_style: &Style) {
+        if let Some(clip) = &node.style.clip {
+            let transform = if node.apply_parent_style {
+                parent_style.translation * node.style.translation
+            } else {
+                node.style.translation
+            };
+            let id = self.id("clip");
+            let _ = writeln!(
+                self.defs,
+                r#"<clipPath id="{}"><rect{}{}/></clipPath>"#,
+                id,
+                rect_attrs(clip),
+                transform_attr(transform)
+            );
+            let _ = writeln!(self.body, r#"<g clip-path="url(#{})">"#, id);
+        }
+        let mut current_style = parent_style.clone();
+        if let Some(kind) = &node.drawable {
+            let built = kind.build(&node.style);
+            current_style = if node.apply_parent_style {
+                parent_style * &built.style
+            } else {
+                built.style.clone()
+            };
+            self.drawable(kind, &node.style, &current_style);
+        }
+        let mut children_refs: Vec<&SceneNode> = node.children.iter().collect();
+        children_refs.sort_by_key(|c| c.style.z_index);
+        for child in children_refs {
+            self.node(child, &current_style);
+        }
+        if node.style.clip.is_some() {
+            self.body.push_str("</g>\n");
+        }
+    }
+
+    fn drawable(&mut self, kind: &SceneNodeKind, node_style: &Style, style: &Style) {
+        if !style.visible {
+            return;
+        }
+        let element = match kind {
+            SceneNodeKind::Circle { center, radius, .. } => {
+                format!(
+                    r#"<circle cx="{}" cy="{}" r="{}""#,
+                    center.x, center.y, radius
+                )
+            }
+            SceneNodeKind::Point { pos } => {
+                format!(r#"<circle cx="{}" cy="{}" r="0.5""#, pos.x, pos.y)
+            }
+            SceneNodeKind::Ellipse {
+                center,
+                radii,
+                rotation,
+            } => match *rotation == 0.0 {
+                true => format!(
+                    r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}""#,
+                    center.x, center.y, radii.x, radii.y
+                ),
+                false => path_element(Ellipse::new(*center, *radii, *rotation)),
+            },
+            SceneNodeKind::Line { p0, p1 } => format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}""#,
+                p0.x, p0.y, p1.x, p1.y
+            ),
+            SceneNodeKind::Rect { p0, size } => {
+                format!("<rect{}", rect_attrs(&Rect::from_center_size(*p0, *size)))
+            }
+            SceneNodeKind::RoundedRect { p0, size, radii } => {
+                let rect = Rect::from_center_size(*p0, *size);
+                match radii.as_single_radius() {
+                    Some(r) => format!(r#"<rect{} rx="{}""#, rect_attrs(&rect), r),
+                    None => path_element(RoundedRect::from_rect(rect, *radii)),
+                }
+            }
+            SceneNodeKind::Triangle { a, b, c } => path_element(Triangle::new(*a, *b, *c)),
+            SceneNodeKind::QuadBez { a, b, c } => path_element(QuadBez::new(*a, *b, *c)),
+            SceneNodeKind::CubicBez { a, b, c, d } => path_element(CubicBez::new(*a, *b, *c, *d)),
+            SceneNodeKind::BezPath { elements } => {
+                path_element(BezPath::from_vec(elements.clone()))
+            }
+            SceneNodeKind::Arc {
+                center,
+                radii,
+                start_angle,
+                sweep_angle,
+                rotation,
+            } => path_element(Arc::new(
+                *center,
+                *radii,
+                *start_angle,
+                *sweep_angle,
+                *rotation,
+            )),
+            SceneNodeKind::Ring { .. } | SceneNodeKind::Polygon { .. } => {
+                path_element(kind.outline().unwrap_or_default())
+            }
+            SceneNodeKind::PointLight { center, radius, .. } => {
+                format!(
+                    r#"<circle cx="{}" cy="{}" r="{}""#,
+                    center.x, center.y, radius
+                )
+            }
+            SceneNodeKind::LightMask {
+                screen_size,
+                lights,
+                ..
+            } => path_element(light_mask_path(*screen_size, lights)),
+            SceneNodeKind::Text { position, text } => {
+                return self.text(*position, text, node_style, style);
+            }
+            SceneNodeKind::Image { position, image } => {
+                let size = self
+                    .graph
+                    .img_mgr
+                    .try_get(image.as_str())
+                    .map(|img| image_size(&img));
+                return self.image(&escape(image), *position, size, style.translation);
+            }
+            SceneNodeKind::SpriteImage { position, image } => {
+                return self.image(
+                    &png_data_uri(image),
+                    *position,
+                    Some(image_size(image)),
+                    style.translation,
+                );
+            }
+            SceneNodeKind::TiledImage {
+                image,
+                dest,
+                tile_size,
+            } => {
+                let id = self.id("tile");
+                let _ = writeln!(
+                    self.defs,
+                    r#"<pattern id="{}" patternUnits="userSpaceOnUse" x="{}" y="{}" width="{}" height="{}"><image href="{}" width="{}" height="{}" preserveAspectRatio="none"/></pattern>"#,
+                    id,
+                    dest.x0,
+                    dest.y0,
+                    tile_size.width,
+                    tile_size.height,
+                    escape(image),
+                    tile_size.width,
+                    tile_size.height
+                );
+                let _ = writeln!(
+                    self.body,
+                    r#"<rect{} fill="url(#{})"{}/>"#,
+                    rect_attrs(dest),
+                    id,
+                    transform_attr(style.translation)
+                );
+                return;
+            }
+            SceneNodeKind::Instanced { image, transforms } => {
+                let size = self
+                    .graph
+                    .img_mgr
+                    .try_get(image.as_str())
+                    .map(|img| image_size(&img));
+                let href = escape(image);
+                for transform in transforms {
+                    self.image(&href, Point::ZERO, size, style.translation * *transform);
+                }
+                return;
+            }
+            SceneNodeKind::Svg { svg } => {
+                let size = self.graph.svg_mgr.size(svg).ok();
+                let (width, height) = size.map_or((0.0, 0.0), |s| (s.width, s.height));
+                let _ = writeln!(
+                    self.body,
+                    r#"<image href="{}" x="{}" y="{}"{}{}{}/>"#,
+                    escape(svg),
+                    -width / 2.0,
+                    -height / 2.0,
+                    size_attrs(size),
+                    opacity_attr(style.opacity),
+                    transform_attr(style.translation)
+                );
+                return;
+            }
+        };
+        let paint = self.paint(style);
+        let _ = writeln!(
+            self.body,
+            "{}{}{}/>",
+            element,
+            paint,
+            transform_attr(style.translation)
+        );
+    }
+
+    // positioned like `ImageDrawable`, centered on `position`
+    fn image(&mut self, href: &str, position: Point, size: Option<Size>, transform: Affine) {
+        let (width, height) = size.map_or((0.0, 0.0), |s| (s.width, s.height));
+        let _ = writeln!(
+            self.body,
+            r#"<image href="{}" x="{}" y="{}"{}{}/>"#,
+            href,
+            position.x - width / 2.0,
+            position.y - height / 2.0,
+            size_attrs(size),
+            transform_attr(transform)
+        );
+    }
+
+    // every character placed where the text layout puts its glyph
+    fn text(&mut self, position: Point, text: &str, node_style: &Style, style: &Style) {
+        if !node_style.visible {
+            return;
+        }
+        let font_name = node_style.font.clone().unwrap_or_default();
+        let font = self.graph.font_mgr.get(&font_name);
+        let size = node_style.font_size.unwrap_or(16.0);
+        let layout = TextDrawable::layout(
+            &font,
+            text,
+            size,
+            node_style.vertical.unwrap_or(false),
+            node_style.align.unwrap_or_default(),
+            node_style.line_spacing.unwrap_or(1.0),
+        );
+        let transform =
+            node_style.translation * style.translation * Affine::translate(position.to_vec2());
+        let mut element = format!(r#"<text font-size="{}""#, size);
+        if !font_name.is_empty() {
+            let _ = write!(element, r#" font-family="{}""#, escape(&font_name));
+        }
+        // text is only filled, without one it is transparent
+        let mut text_style = node_style.clone().with_stoke(None);
+        text_style.opacity *= style.opacity;
+        element.push_str(&self.paint(&text_style));
+        element.push_str(&transform_attr(transform));
+        let _ = writeln!(self.body, "{}>", element);
+        let mut glyphs = layout.runs.iter().flatten();
+        for line in text.lines() {
+            let chars: String = line.chars().filter(|c| !c.is_control()).collect();
+            let placed: Vec<_> = glyphs.by_ref().take(chars.chars().count()).collect();
+            if placed.is_empty() {
+                continue;
+            }
+            let xs: Vec<String> = placed.iter().map(|g| g.x.to_string()).collect();
+            let ys: Vec<String> = placed.iter().map(|g| g.y.to_string()).collect();
+            let _ = writeln!(
+                self.body,
+                r#"<tspan x="{}" y="{}">{}</tspan>"#,
+                xs.join(" "),
+                ys.join(" "),
+                escape(&chars)
+            );
+        }
+        self.body.push_str("</text>\n");
+    }
+
+    fn paint(&mut self, style: &Style) -> String {
+        let mut attrs = match &style.fill {
+            Some(brush) => self.brush("fill", brush),
+            None => r#" fill="none""#.to_string(),
+        };
+        if style.fill_rule == Fill::EvenOdd {
+            attrs.push_str(r#" fill-rule="evenodd""#);
+        }
+        if let Some(stoke) = &style.stoke {
+            let stroke = &stoke.stroke;
+            attrs.push_str(&self.brush("stroke", &stoke.brush));
+            let join = match stroke.join {
+                Join::Bevel => "bevel",
+                Join::Miter => "miter",
+                Join::Round => "round",
+            };
+            let cap = match stroke.start_cap {
+                Cap::Butt => "butt",
+                Cap::Square => "square",
+                Cap::Round => "round",
+            };
+            let _ = write!(
+                attrs,
+                r#" stroke-width="{}" stroke-linejoin="{}" stroke-linecap="{}" stroke-miterlimit="{}""#,
+                stroke.width, join, cap, stroke.miter_limit
+            );
+            if !stroke.dash_pattern.is_empty() {
+                let dashes: Vec<String> = stroke.dash_pattern.iter().map(f64::to_string).collect();
+                let _ = write!(
+                    attrs,
+                    r#" stroke-dasharray="{}" stroke-dashoffset="{}""#,
+                    dashes.join(" "),
+                    stroke.dash_offset
+                );
+            }
+        }
+        attrs.push_str(&opacity_attr(style.opacity));
+        attrs
+    }
+
+    fn brush(&mut self, attr: &str, brush: &CustomBrush) -> String {
+        match brush {
+            CustomBrush::Color(color) => {
+                let mut paint = format!(
+                    r##" {}="#{:02x}{:02x}{:02x}""##,
+                    attr, color.r, color.g, color.b
+                );
+                if color.a < 255 {
+                    let _ = write!(paint, r#" {}-opacity="{}""#, attr, color.a as f32 / 255.0);
+                }
+                paint
+            }
+            CustomBrush::Gradient(gradient) => {
+                let id = self.gradient(gradient);
+                format!(r#" {}="url(#{})""#, attr, id)
+            }
+            CustomBrush::Image(image) => {
+                let size = self
+                    .graph
+                    .img_mgr
+                    .try_get(image.as_str())
+                    .map(|img| image_size(&img));
+                let (width, height) = size.map_or((0.0, 0.0), |s| (s.width, s.height));
+                let id = self.id("pattern");
+                let _ = writeln!(
+                    self.defs,
+                    r#"<pattern id="{}" patternUnits="userSpaceOnUse" width="{}" height="{}"><image href="{}"{}/></pattern>"#,
+                    id,
+                    width,
+                    height,
+                    escape(image),
+                    size_attrs(size)
+                );
+                format!(r#" {}="url(#{})""#, attr, id)
+            }
+        }
+    }
+
+    // in the shape's own units, like `CustomGradient::to_vello`
+    fn gradient(&mut self, gradient: &CustomGradient) -> String {
+        let id = self.id("gradient");
+        let spread = match gradient.extend {
+            CustomExtend::Pad => "pad",
+            CustomExtend::Repeat => "repeat",
+            CustomExtend::Reflect => "reflect",
+        };
+        let tag = match gradient.kind {
+            CustomGradientKind::Linear => "linearGradient",
+            CustomGradientKind::Radial => "radialGradient",
+        };
+        let geometry = match gradient.kind {
+            CustomGradientKind::Linear => format!(
+                r#"x1="{}" y1="{}" x2="{}" y2="{}""#,
+                gradient.start.x, gradient.start.y, gradient.end.x, gradient.end.y
+            ),
+            CustomGradientKind::Radial => format!(
+                r#"cx="{}" cy="{}" r="{}""#,
+                gradient.center.x, gradient.center.y, gradient.radius
+            ),
+        };
+        let _ = write!(
+            self.defs,
+            r#"<{} id="{}" gradientUnits="userSpaceOnUse" spreadMethod="{}" {}>"#,
+            tag, id, spread, geometry
+        );
+        for (offset, color) in &gradient.colors {
+            let _ = write!(
+                self.defs,
+                r##"<stop offset="{}" stop-color="#{:02x}{:02x}{:02x}" stop-opacity="{}"/>"##,
+                offset,
+                color.r,
+                color.g,
+                color.b,
+                color.a as f32 / 255.0
+            );
+        }
+        let _ = writeln!(self.defs, "</{}>", tag);
+        id
+    }
+}
+
+fn path_element(shape: impl Shape) -> String {
+    format!(r#"<path d="{}""#, shape.to_path(PATH_TOLERANCE).to_svg())
+}
+
+fn rect_attrs(rect: &Rect) -> String {
+    format!(
+        r#" x="{}" y="{}" width="{}" height="{}""#,
+        rect.x0,
+        rect.y0,
+        rect.width(),
+        rect.height()
+    )
+}
+
+fn size_attrs(size: Option<Size>) -> String {
+    size.map_or(String::new(), |s| {
+        format!(r#" width="{}" height="{}""#, s.width, s.height)
+    })
+}
+
+fn transform_attr(transform: Affine) -> String {
+    if transform == Affine::IDENTITY {
+        return String::new();
+    }
+    let [a, b, c, d, e, f] = transform.as_coeffs();
+    format!(
+        r#" transform="matrix({} {} {} {} {} {})""#,
+        a, b, c, d, e, f
+    )
+}
+
+fn opacity_attr(opacity: f32) -> String {
+    match opacity < 1.0 {
+        true => format!(r#" opacity="{}""#, opacity),
+        false => String::new(),
+    }
+}
+
+fn image_size(image: &Image) -> Size {
+    Size::new(image.width as f64, image.height as f64)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn png_data_uri(image: &Image) -> String {
+    let mut png = Vec::new();
+    let encoded = image::RgbaImage::from_raw(image.width, image.height, image.data.data().to_vec())
+        .map(|rgba| rgba.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png));
+    if !matches!(encoded, Some(Ok(()))) {
+        log::warn!(
+            "sprite frame {}x{} could not be encoded as png",
+            image.width,
+            image.height
+        );
+        return String::new();
+    }
+    format!("data:image/png;base64,{}", base64(&png))
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+#[test]
+fn test_scene_to_svg() {
+    use super::StokeStyle;
+    use super::style::SimpleColor;
+    let red = Style::default().with_fill(Some(CustomBrush::Color(SimpleColor {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    })));
+    let outlined = Style::default()
+        .with_fill(None)
+        .with_stoke(Some(StokeStyle {
+            stroke: kurbo::Stroke::new(2.0),
+            brush: CustomBrush::default(),
+        }));
+    let mut root = SceneNode::circle(Point::new(10.0, 20.0), 5.0, 0.0, &red);
+    root.add_child(&SceneNode::rect(
+        Point::new(-10.0, 0.0),
+        Size::new(20.0, 10.0),
+        &outlined,
+    ));
+    root.add_child(&SceneNode::line(
+        Point::ZERO,
+        Point::new(1.0, 1.0),
+        &Style::default().with_visible(false),
+    ));
+    let mut graph = SceneGraph::default();
+    graph.set_root(root);
+    let svg = graph.to_svg();
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg">"#));
+    assert!(svg.contains(r##"<circle cx="10" cy="20" r="5" fill="#ff0000"/>"##));
+    // the rect is given by its center
+    assert!(svg.contains(
+        r##"<rect x="-20" y="-5" width="20" height="10" fill="none" stroke="#ffffff" stroke-width="2""##
+    ));
+    assert!(!svg.contains("<line"));
+
+    // the camera offset ends up in the transforms
+    graph.center_with_screen_size(800.0, 600.0);
+    let svg = graph.to_svg();
+    assert!(svg.contains(r#"viewBox="0 0 800 600""#));
+    assert!(svg.contains(r##"r="5" fill="#ff0000" transform="matrix(1 0 0 1 400 300)"/>"##));
+    assert_eq!(base64(b"fool"), "Zm9vbA==");
+}
@@ -1,9 +1,14 @@
 #![allow(unused_imports)]
 mod types;
-use fool_resource::{Fallback, Resource, SharedData};
-pub use types::{FontGlyph, FontGlyphId, VelloFont};
+use super::{TextAlign, TextDrawable};
+use fool_resource::{EntryUsage, Fallback, Resource, ScopeStack, SharedData};
+use std::sync::Arc;
+pub use types::{FontGlyph, FontGlyphId, TextLayout, TextLayoutId, VelloFont};
 use vello::peniko::Blob;
 
+// what text nodes are drawn at without a `font_size`
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+
 #[repr(transparent)]
 #[derive(Debug, Clone)]
 pub struct FontManager {
@@ -28,13 +33,44 @@ impl FontManager {
     pub fn get(&self, name: &str) -> VelloFont {
         self.cache.get(name).unwrap_or(self.default_font())
     }
+    /// like `get` but without falling back to the default font
+    pub fn try_get(&self, name: &str) -> Option<VelloFont> {
+        self.cache.get(name).ok()
+    }
+    pub fn supports_char(&self, name: &str, ch: char) -> bool {
+        self.try_get(name)
+            .map(|font| font.supports_char(ch))
+            .unwrap_or(false)
+    }
     pub fn put(&self, name: &str, data: SharedData) {
-        let font = VelloFont::new(Blob::from(data.to_vec()));
+        let font = VelloFont::new(Blob::new(Arc::new(data)));
         self.cache.load(name.to_string(), font);
     }
     pub fn exists(&self, name: &str) -> bool {
         self.cache.exists(name)
     }
+    pub fn set_scopes(&self, scopes: ScopeStack) {
+        self.cache.set_scopes(scopes);
+    }
+    pub fn release_scope(&self, scope: &str) -> Vec<String> {
+        self.cache.release_scope(scope)
+    }
+    /// looks up the glyphs of `chars` at the default text size ahead of the first draw,
+    /// loading the font when it isn't yet
+    pub fn warm(&self, name: &str, chars: &str) {
+        self.get(name).glyph(&chars.to_string(), DEFAULT_FONT_SIZE);
+    }
+    /// width and height `text` takes when drawn left aligned at `size`, without drawing it,
+    /// the layout is kept for the draw
+    pub fn measure(&self, name: &str, text: &str, size: f32) -> (f32, f32) {
+        let font = self.get(name);
+        let layout = TextDrawable::layout(&font, text, size, false, TextAlign::Left, 1.0);
+        (layout.bounds.width() as f32, layout.bounds.height() as f32)
+    }
+    /// loaded fonts by the size of their font file
+    pub fn usage(&self) -> Vec<EntryUsage<String>> {
+        self.cache.usage(|font| font.font.data.len())
+    }
 }
 
 #[repr(transparent)]
@@ -57,6 +93,35 @@ impl Fallback for VelloFontFallback {
     type V = VelloFont;
     fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
         let data = self.resource.get(key)?;
-        Ok(VelloFont::new(Blob::from(data.to_vec())))
+        Ok(VelloFont::new(Blob::new(Arc::new(data))))
     }
 }
+
+#[test]
+fn test_font_coverage() {
+    let mgr = FontManager::default();
+    assert!(mgr.try_get("missing").is_none());
+    assert!(!mgr.supports_char("missing", 'A'));
+    mgr.put(
+        "roboto",
+        SharedData::from_static(include_bytes!("../../../Roboto_SemiCondensed-Bold.ttf")),
+    );
+    assert!(mgr.try_get("roboto").is_some());
+    assert!(mgr.supports_char("roboto", 'A'));
+    assert!(!mgr.supports_char("roboto", '\u{4e2d}'));
+}
+
+#[test]
+fn test_font_measure() {
+    let mgr = FontManager::default();
+    mgr.warm("", "0123456789");
+    let (short, height) = mgr.measure("", "Play", 16.0);
+    let (long, same) = mgr.measure("", "Play again", 16.0);
+    assert!(long > short, "{} {}", long, short);
+    assert_eq!(height, same);
+    let (_, two_lines) = mgr.measure("", "Play\nagain", 16.0);
+    assert!(two_lines > height);
+    let (bigger, _) = mgr.measure("", "Play", 32.0);
+    assert!(bigger > short);
+    assert_eq!(mgr.measure("", "", 16.0).0, 0.0);
+}
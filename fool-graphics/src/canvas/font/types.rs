@@ -1,3 +1,4 @@
+use crate::canvas::TextAlign;
 use ordered_float::OrderedFloat;
 use parking_lot::RwLock;
 use skrifa::{
@@ -6,8 +7,15 @@ use skrifa::{
     metrics::{BoundingBox, Metrics},
     raw::{FileRef, FontRef},
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+use vello::Glyph;
+use vello::kurbo::Rect;
 use vello::peniko::{Blob, Font};
+// laid out strings kept per font, dropped all at once past this
+const MAX_CACHED_LAYOUTS: usize = 512;
 fn to_font_ref(font: &Font) -> Option<FontRef<'_>> {
     let file_ref = FileRef::new(font.data.as_ref()).ok()?;
     match file_ref {
@@ -68,18 +76,39 @@ impl FontGlyph {
         self.gid != GlyphId::NOTDEF
     }
 }
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextLayoutId {
+    pub text: String,
+    pub font_size: OrderedFloat<f32>,
+    pub line_spacing: OrderedFloat<f32>,
+    pub align: TextAlign,
+    pub vertical: bool,
+}
+
+/// positioned glyphs of a string, one run per draw call
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    pub font_size: f32,
+    pub runs: Vec<Vec<Glyph>>,
+    pub bounds: Rect,
+}
+
 const DEFAULT_FALLBACK_FONT: &[u8] = include_bytes!("../../../Roboto_SemiCondensed-Bold.ttf");
+// shared so the default font keeps its caches between frames
+static DEFAULT_FONT: LazyLock<VelloFont> = LazyLock::new(|| VelloFont {
+    font: Font::new(Blob::new(Arc::new(DEFAULT_FALLBACK_FONT)), 0),
+    glyph: Default::default(),
+    layouts: Default::default(),
+});
 #[derive(Clone, Debug)]
 pub struct VelloFont {
     pub font: Font,
     glyph: Arc<RwLock<HashMap<FontGlyphId, Arc<FontGlyph>>>>,
+    layouts: Arc<RwLock<HashMap<TextLayoutId, Arc<TextLayout>>>>,
 }
 impl Default for VelloFont {
     fn default() -> Self {
-        Self {
-            font: Font::new(Blob::new(Arc::new(DEFAULT_FALLBACK_FONT)), 0),
-            glyph: Default::default(),
-        }
+        DEFAULT_FONT.clone()
     }
 }
 impl VelloFont {
@@ -87,7 +116,30 @@ impl VelloFont {
         Self {
             font: Font::new(Blob::new(Arc::new(data)), 0),
             glyph: Default::default(),
+            layouts: Default::default(),
+        }
+    }
+    /// the cached layout for `id`, laid out with `build` the first time
+    pub fn layout(
+        &self,
+        id: TextLayoutId,
+        build: impl FnOnce(&Self) -> TextLayout,
+    ) -> Arc<TextLayout> {
+        if let Some(layout) = self.layouts.read().get(&id) {
+            return layout.clone();
+        }
+        let layout = Arc::new(build(self));
+        let mut layouts = self.layouts.write();
+        if layouts.len() >= MAX_CACHED_LAYOUTS {
+            layouts.clear();
         }
+        layouts.insert(id, layout.clone());
+        layout
+    }
+    pub fn supports_char(&self, ch: char) -> bool {
+        to_font_ref(&self.font)
+            .and_then(|f| f.charmap().map(ch))
+            .is_some_and(|gid| gid != GlyphId::NOTDEF)
     }
     pub fn glyph(&self, text: &String, size: f32) -> Vec<Arc<FontGlyph>> {
         let mut lock = self.glyph.write();
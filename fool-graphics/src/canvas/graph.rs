@@ -1,6 +1,8 @@
 use super::ImageManager;
-use super::{Affine, FontManager, SceneNode, Style};
+use super::{Affine, FontManager, SceneNode, Style, SvgManager};
 pub use vello::Scene;
+use vello::kurbo::Point;
+use vello::peniko::Mix;
 
 #[derive(Debug, Default, Clone)]
 pub struct SceneGraph {
@@ -8,8 +10,15 @@ pub struct SceneGraph {
     pub style: Style,
     pub font_mgr: FontManager,
     pub img_mgr: ImageManager,
+    pub svg_mgr: SvgManager,
     pub default_size: (f64, f64),
     pub scale: Option<f64>,
+    /// the accessibility ui scale, not applied here, for world space ui to follow
+    pub ui_scale: Option<f64>,
+    /// screen edges kept for tool windows, left, top, right, bottom
+    pub viewport_margin: (f64, f64, f64, f64),
+    /// for pixel art, the nodes land on whole pixels and images sample their nearest pixel
+    pub pixel_perfect: bool,
 }
 impl SceneGraph {
     pub fn center_with_screen_size(&mut self, w: f64, h: f64) {
@@ -22,17 +31,47 @@ impl SceneGraph {
         self.scale = scale;
     }
 
+    pub fn set_pixel_perfect(&mut self, enabled: bool) {
+        self.pixel_perfect = enabled;
+    }
+
+    pub fn set_ui_scale(&mut self, scale: Option<f64>) {
+        self.ui_scale = scale;
+    }
+    pub fn ui_scale(&self) -> f64 {
+        self.ui_scale.unwrap_or(1.0)
+    }
+    pub fn set_viewport_margin(&mut self, left: f64, top: f64, right: f64, bottom: f64) {
+        self.viewport_margin = (left.max(0.0), top.max(0.0), right.max(0.0), bottom.max(0.0));
+    }
+
     pub fn set_root(&mut self, root: SceneNode) {
         self.root.children.clear();
         self.root.add_child(&root);
     }
-    pub fn draw(&self, scene: &mut Scene) -> anyhow::Result<()> {
-        let mut style = self.style.clone();
+    /// scene to screen transform, the scene origin sits at the viewport center
+    pub fn transform(&self) -> Affine {
         let scale = self.scale.unwrap_or(1.0);
         let (win_w, win_h) = (self.default_size.0, self.default_size.1);
+        let (left, top, right, bottom) = self.viewport_margin;
         let scaling = Affine::scale(scale);
-        let to_screen_center = Affine::translate((win_w / 2.0, win_h / 2.0));
-        let transform = to_screen_center * scaling;
+        let to_screen_center = Affine::translate((
+            left + (win_w - left - right) / 2.0,
+            top + (win_h - top - bottom) / 2.0,
+        ));
+        to_screen_center * scaling
+    }
+    /// maps a screen position, e.g. the mouse, back into scene coordinates
+    pub fn screen_to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        let p = self.transform().inverse() * Point::new(x, y);
+        (p.x, p.y)
+    }
+    pub fn draw(&self, scene: &mut Scene) -> anyhow::Result<()> {
+        self.draw_with(scene, self.transform())
+    }
+    /// draws with another scene to screen transform, e.g. the camera of a `View`
+    pub fn draw_with(&self, scene: &mut Scene, transform: Affine) -> anyhow::Result<()> {
+        let mut style = self.style.clone();
         style.translation = transform;
         self.draw_node(&self.root, scene, &style)
     }
@@ -43,6 +82,15 @@ impl SceneGraph {
         scene: &mut Scene,
         parent_style: &Style,
     ) -> anyhow::Result<()> {
+        // nested clip layers intersect with the ones already pushed
+        if let Some(clip) = &node.style.clip {
+            let transform = if node.apply_parent_style {
+                parent_style.translation * node.style.translation
+            } else {
+                node.style.translation
+            };
+            scene.push_layer(Mix::Clip, 1.0, transform, clip);
+        }
         let mut current_style = parent_style.clone();
         if let Some(drawable) = &node.drawable {
             let d = drawable.build(&node.style);
@@ -51,18 +99,217 @@ impl SceneGraph {
             } else {
                 d.style.clone()
             };
+            // snapped where the node lands on the screen, the children follow from there
+            if self.pixel_perfect {
+                current_style.translation = Style::snap_translation(current_style.translation);
+                current_style.pixel_perfect = true;
+            }
             d.drawable.draw(
                 scene,
                 &current_style,
                 self.font_mgr.clone(),
                 self.img_mgr.clone(),
+                self.svg_mgr.clone(),
             )?;
         }
         let mut children_refs: Vec<&SceneNode> = node.children.iter().collect();
         children_refs.sort_by_key(|c| c.style.z_index);
-        for child in &children_refs {
-            self.draw_node(child, scene, &current_style)?;
+        let result = children_refs
+            .iter()
+            .try_for_each(|child| self.draw_node(child, scene, &current_style));
+        if node.style.clip.is_some() {
+            scene.pop_layer();
         }
-        Ok(())
+        result
     }
 }
+
+#[test]
+fn test_clip() -> anyhow::Result<()> {
+    use kurbo::{Point, Rect, Size};
+    let parent_style = Style::default().with_clip(Some(Rect::new(0.0, 0.0, 50.0, 50.0)));
+    let child_style = Style::default().with_clip(Some(Rect::new(25.0, 25.0, 100.0, 100.0)));
+    let mut parent = SceneNode::rect(Point::ZERO, Size::new(100.0, 100.0), &parent_style);
+    let child = SceneNode::rect(
+        Point::new(25.0, 25.0),
+        Size::new(100.0, 100.0),
+        &child_style,
+    );
+    parent.add_child(&child);
+    let mut graph = SceneGraph::default();
+    graph.set_root(parent);
+    let mut scene = Scene::new();
+    graph.draw(&mut scene)?;
+    let encoding = scene.encoding();
+    // begin and end of both clips are encoded, none left open
+    assert_eq!(encoding.n_clips, 4);
+    assert_eq!(encoding.n_open_clips, 0);
+    Ok(())
+}
+
+#[test]
+fn test_screen_to_world() {
+    let mut graph = SceneGraph::default();
+    graph.center_with_screen_size(800.0, 600.0);
+    // the camera offset is the screen center
+    assert_eq!(graph.screen_to_world(400.0, 300.0), (0.0, 0.0));
+    assert_eq!(graph.screen_to_world(500.0, 250.0), (100.0, -50.0));
+    graph.set_scale(Some(2.0));
+    assert_eq!(graph.screen_to_world(500.0, 250.0), (50.0, -25.0));
+    // a 200 wide panel on the left moves the center into what is left
+    graph.set_viewport_margin(200.0, 0.0, 0.0, 100.0);
+    assert_eq!(graph.screen_to_world(500.0, 250.0), (0.0, 0.0));
+}
+
+#[test]
+fn test_instanced() -> anyhow::Result<()> {
+    use peniko::{Blob, Image, ImageFormat};
+    use std::sync::Arc;
+    let graph = |root: SceneNode| {
+        let mut graph = SceneGraph::default();
+        graph.center_with_screen_size(100.0, 100.0);
+        let tile = Image::new(
+            Blob::new(Arc::new(vec![255u8; 16])),
+            ImageFormat::Rgba8,
+            2,
+            2,
+        );
+        graph.img_mgr.load("tile", Arc::new(tile));
+        graph.set_root(root);
+        graph
+    };
+    let transforms = vec![
+        Affine::translate((0.0, 0.0)),
+        Affine::translate((16.0, 0.0)),
+        Affine::translate((32.0, 8.0)) * Affine::rotate(0.5),
+    ];
+    let instanced = graph(SceneNode::instanced("tile".into(), transforms.clone()));
+    // an image takes its place from the parent, an empty rect here
+    let mut separate = SceneNode::empty();
+    for transform in &transforms {
+        let style = Style::default()
+            .with_fill(None)
+            .with_translation(*transform);
+        let mut node = SceneNode::rect(Point::ZERO, kurbo::Size::ZERO, &style);
+        node.add_child(&SceneNode::image(Point::ZERO, "tile".into()));
+        separate.add_child(&node);
+    }
+    let separate = graph(separate);
+
+    let (mut a, mut b) = (Scene::new(), Scene::new());
+    instanced.draw(&mut a)?;
+    separate.draw(&mut b)?;
+    let (a, b) = (a.encoding(), b.encoding());
+    // three image fills either way, placed the same
+    assert_eq!(a.n_paths, 3);
+    assert_eq!(a.n_paths, b.n_paths);
+    assert!(a.draw_tags == b.draw_tags);
+    assert_eq!(a.transforms, b.transforms);
+    assert_eq!(instanced.to_svg().matches("<image").count(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_render_order() -> anyhow::Result<()> {
+    use super::style::{CustomBrush, SimpleColor};
+    use vello::peniko::color::{AlphaColor, Srgb};
+    use vello_encoding::DrawColor;
+    let colors = [(255, 0, 0), (0, 255, 0), (0, 0, 255)];
+    let child = |(r, g, b): (u8, u8, u8), z_index: i32, y: f64| {
+        let style = Style::default()
+            .with_fill(Some(CustomBrush::Color(SimpleColor { r, g, b, a: 255 })))
+            .with_z_index(z_index)
+            .with_translation(Affine::translate((0.0, y)));
+        SceneNode::rect(Point::ZERO, kurbo::Size::new(10.0, 10.0), &style)
+    };
+    // the colors in the order they are filled
+    let drawn = |root: &SceneNode| -> anyhow::Result<Vec<(u8, u8, u8)>> {
+        let mut graph = SceneGraph::default();
+        graph.set_root(root.clone());
+        let mut scene = Scene::new();
+        graph.draw(&mut scene)?;
+        let data = &scene.encoding().draw_data;
+        Ok(colors
+            .iter()
+            .filter_map(|&(r, g, b)| {
+                let rgba = DrawColor::from(AlphaColor::<Srgb>::from_rgba8(r, g, b, 255)).rgba;
+                Some((data.iter().position(|word| *word == rgba)?, (r, g, b)))
+            })
+            .collect::<std::collections::BTreeMap<_, _>>()
+            .into_values()
+            .collect())
+    };
+    let mut root = SceneNode::empty();
+    root.add_child(&child(colors[0], 2, 30.0));
+    root.add_child(&child(colors[1], 0, 10.0));
+    root.add_child(&child(colors[2], 1, 20.0));
+    // lowest z first, whatever the order they were added in
+    assert_eq!(drawn(&root)?, [colors[1], colors[2], colors[0]]);
+
+    // the same z draws in child order, sorting by y moves the lower ones in front
+    for child in &mut root.children {
+        child.style.z_index = 0;
+    }
+    assert_eq!(drawn(&root)?, colors);
+    root.sort_children_by(|child| -child.style.translation.translation().y);
+    assert_eq!(drawn(&root)?, [colors[0], colors[2], colors[1]]);
+    root.sort_children_by(|child| child.style.translation.translation().y);
+    assert_eq!(drawn(&root)?, [colors[1], colors[2], colors[0]]);
+    Ok(())
+}
+
+#[test]
+fn test_gradient_fill() -> anyhow::Result<()> {
+    use super::style::SimpleColor;
+    use vello_encoding::DrawTag;
+    let color = |r, b| SimpleColor { r, g: 0, b, a: 255 };
+    let stops = vec![(0.0, color(255, 0)), (1.0, color(0, 255))];
+    // the raw tag values, DrawTag has no Debug
+    let tags = |style: Style| -> anyhow::Result<Vec<u32>> {
+        let mut graph = SceneGraph::default();
+        let size = kurbo::Size::new(100.0, 20.0);
+        graph.set_root(SceneNode::rect(Point::ZERO, size, &style));
+        let mut scene = Scene::new();
+        graph.draw(&mut scene)?;
+        Ok(scene.encoding().draw_tags.iter().map(|tag| tag.0).collect())
+    };
+    let linear = Style::default().with_linear_gradient((0.0, 0.0), (100.0, 0.0), stops.clone());
+    assert_eq!(tags(linear)?, [DrawTag::LINEAR_GRADIENT.0]);
+    let radial = Style::default().with_radial_gradient((50.0, 10.0), 50.0, stops);
+    assert_eq!(tags(radial)?, [DrawTag::RADIAL_GRADIENT.0]);
+    assert_eq!(tags(Style::default())?, [DrawTag::COLOR.0]);
+    Ok(())
+}
+
+#[test]
+fn test_pixel_perfect() -> anyhow::Result<()> {
+    let translations = |pixel_perfect: bool| -> anyhow::Result<Vec<[f32; 2]>> {
+        let mut graph = SceneGraph::default();
+        // an odd window puts the center on half a pixel
+        graph.center_with_screen_size(101.0, 100.0);
+        graph.set_pixel_perfect(pixel_perfect);
+        let style = Style::default().with_translation(Affine::translate((10.3, -20.6)));
+        graph.set_root(SceneNode::rect(
+            Point::ZERO,
+            kurbo::Size::new(8.0, 8.0),
+            &style,
+        ));
+        let mut scene = Scene::new();
+        graph.draw(&mut scene)?;
+        Ok(scene
+            .encoding()
+            .transforms
+            .iter()
+            .map(|transform| transform.translation)
+            .collect())
+    };
+    assert!(translations(false)?.contains(&[60.8, 29.4]));
+    let snapped = translations(true)?;
+    assert!(snapped.contains(&[61.0, 29.0]));
+    assert!(
+        snapped
+            .iter()
+            .all(|[x, y]| x.fract() == 0.0 && y.fract() == 0.0)
+    );
+    Ok(())
+}
@@ -1,12 +1,21 @@
 use super::ImageManager;
 use kurbo::{Affine, Point, Rect, Size};
-use peniko::Image;
+use peniko::{Extend, Image, ImageQuality};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 use crate::{
     canvas::{Drawable, Style},
     graph_vec2,
 };
+// nearest sampling keeps pixel art crisp when scaled
+fn sampled<'a>(img: &'a Image, style: &Style) -> Cow<'a, Image> {
+    match style.pixel_perfect {
+        true => Cow::Owned(img.clone().with_quality(ImageQuality::Low)),
+        false => Cow::Borrowed(img),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VelloImage {
     Path(String),
@@ -29,7 +38,7 @@ impl VelloImage {
                 scene.fill(
                     peniko::Fill::NonZero,
                     Affine::IDENTITY * style.translation,
-                    img,
+                    sampled(img, style).as_ref(),
                     Some(tex_to_rect),
                     &rect,
                 );
@@ -53,7 +62,7 @@ impl VelloImage {
                 scene.fill(
                     peniko::Fill::NonZero,
                     Affine::IDENTITY * style.translation,
-                    img.as_ref(),
+                    sampled(&img, style).as_ref(),
                     Some(tex_to_rect),
                     &rect,
                 );
@@ -86,8 +95,172 @@ impl Drawable for ImageDrawable {
         style: &super::Style,
         _font_mgr: super::FontManager,
         img_mgr: ImageManager,
+        _svg_mgr: super::SvgManager,
     ) -> anyhow::Result<()> {
         self.image
             .draw(self.position.x, self.position.y, scene, style, img_mgr)
     }
 }
+
+/// the same image under many transforms, looked up once and filled once per transform
+#[derive(Debug, Clone)]
+pub struct InstancedImageDrawable {
+    pub image: String,
+    pub transforms: Vec<Affine>,
+}
+impl Drawable for InstancedImageDrawable {
+    fn draw(
+        &self,
+        scene: &mut vello::Scene,
+        style: &super::Style,
+        _font_mgr: super::FontManager,
+        img_mgr: ImageManager,
+        _svg_mgr: super::SvgManager,
+    ) -> anyhow::Result<()> {
+        let img = img_mgr.get(&self.image)?;
+        let (width, height) = (img.width as f64, img.height as f64);
+        // centered on the origin of every transform, like an `ImageDrawable` at 0, 0
+        let rect = Rect::from_center_size(Point::ZERO, Size::new(width, height));
+        let tex_to_rect = Affine::translate(graph_vec2!(-width / 2.0, -height / 2.0));
+        let brush = sampled(&img, style);
+        for transform in &self.transforms {
+            let transform = match style.pixel_perfect {
+                true => Style::snap_translation(style.translation * *transform),
+                false => style.translation * *transform,
+            };
+            scene.fill(
+                peniko::Fill::NonZero,
+                Affine::IDENTITY * transform,
+                brush.as_ref(),
+                Some(tex_to_rect),
+                &rect,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TiledImageDrawable {
+    pub image: String,
+    pub dest: Rect,
+    pub tile_size: Size,
+}
+impl TiledImageDrawable {
+    /// tile rects covering `dest`, the last row/column clipped to it
+    pub fn tiles(&self) -> Vec<Rect> {
+        let mut tiles = Vec::new();
+        if self.tile_size.width <= 0.0 || self.tile_size.height <= 0.0 {
+            return tiles;
+        }
+        let mut y = self.dest.y0;
+        while y < self.dest.y1 {
+            let mut x = self.dest.x0;
+            while x < self.dest.x1 {
+                let tile = Rect::from_origin_size(Point::new(x, y), self.tile_size);
+                tiles.push(tile.intersect(self.dest));
+                x += self.tile_size.width;
+            }
+            y += self.tile_size.height;
+        }
+        tiles
+    }
+}
+impl Drawable for TiledImageDrawable {
+    fn draw(
+        &self,
+        scene: &mut vello::Scene,
+        style: &super::Style,
+        _font_mgr: super::FontManager,
+        img_mgr: ImageManager,
+        _svg_mgr: super::SvgManager,
+    ) -> anyhow::Result<()> {
+        let img = img_mgr.get(&self.image)?;
+        if img.width == 0 || img.height == 0 {
+            return Ok(());
+        }
+        // one fill with a repeating brush, the dest rect clips partial tiles
+        let brush = sampled(&img, style)
+            .into_owned()
+            .with_extend(Extend::Repeat);
+        let tex_to_rect = Affine::translate(graph_vec2!(self.dest.x0, self.dest.y0))
+            * Affine::scale_non_uniform(
+                self.tile_size.width / img.width as f64,
+                self.tile_size.height / img.height as f64,
+            );
+        scene.fill(
+            peniko::Fill::NonZero,
+            Affine::IDENTITY * style.translation,
+            &brush,
+            Some(tex_to_rect),
+            &self.dest,
+        );
+        #[cfg(feature = "graph_debug")]
+        {
+            let bg = peniko::Color::from_rgba8(255, 0, 0, 255);
+            for tile in self.tiles() {
+                scene.stroke(
+                    &Default::default(),
+                    Affine::IDENTITY * style.translation,
+                    &bg,
+                    None,
+                    &tile,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_tiled_image() -> anyhow::Result<()> {
+    use peniko::{Blob, ImageFormat};
+    use std::sync::Arc;
+    use vello_encoding::{DrawTag, Patch};
+    let tiled = TiledImageDrawable {
+        image: "tile".to_string(),
+        dest: Rect::new(10.0, 20.0, 110.0, 120.0),
+        tile_size: Size::new(32.0, 32.0),
+    };
+    let tiles = tiled.tiles();
+    // 3 full tiles and one clipped 4px tile per row/column
+    assert_eq!(tiles.len(), 16);
+    let area: f64 = tiles.iter().map(|t| t.area()).sum();
+    assert_eq!(area, 100.0 * 100.0);
+    let last = tiles.last().unwrap();
+    assert_eq!(last.size(), Size::new(4.0, 4.0));
+    assert!(tiles.iter().all(|t| tiled.dest.contains_rect(*t)));
+
+    let img_mgr = ImageManager::default();
+    let tile = Image::new(
+        Blob::new(Arc::new(vec![255u8; 16])),
+        ImageFormat::Rgba8,
+        2,
+        2,
+    );
+    img_mgr.load("tile", Arc::new(tile));
+    let mut scene = vello::Scene::new();
+    tiled.draw(
+        &mut scene,
+        &Style::default(),
+        super::FontManager::default(),
+        img_mgr,
+        super::SvgManager::default(),
+    )?;
+    let encoding = scene.encoding();
+    // a single repeating image fill, not a fill per tile
+    assert_eq!(encoding.n_paths, 1);
+    assert!(encoding.draw_tags == [DrawTag::IMAGE]);
+    let [Patch::Image { image, .. }] = encoding.resources.patches.as_slice() else {
+        panic!("expect one image patch");
+    };
+    assert_eq!(
+        (image.x_extend, image.y_extend),
+        (Extend::Repeat, Extend::Repeat)
+    );
+    // the 2px image stretched to a 32px tile from the dest corner
+    let brush = encoding.transforms.last().unwrap();
+    assert_eq!(brush.matrix, [16.0, 0.0, 0.0, 16.0]);
+    assert_eq!(brush.translation, [10.0, 20.0]);
+    Ok(())
+}
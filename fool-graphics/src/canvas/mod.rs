@@ -1,4 +1,5 @@
 mod draw;
+mod export;
 mod font;
 mod graph;
 mod image;
@@ -6,19 +7,26 @@ mod node;
 mod node_kind;
 mod sprite;
 mod style;
+mod svg;
 mod text;
 mod utils;
+mod view;
 pub use draw::Drawable;
-pub use font::{FontManager, VelloFont, VelloFontFallback};
+pub use font::{FontManager, TextLayout, VelloFont, VelloFontFallback};
 use fool_resource::Resource;
 pub use graph::{Scene, SceneGraph};
-pub use image::{ImageDrawable, VelloImage};
+pub use image::{ImageDrawable, InstancedImageDrawable, TiledImageDrawable, VelloImage};
 pub use node::SceneNode;
 pub use node_kind::SceneNodeKind;
 pub use peniko::{Blob, Image, ImageFormat};
-pub use sprite::{Animation, Frame, Sprite};
+pub use sprite::{
+    Animation, AnimationController, CompareOp, Condition, ControllerDef, ControllerEvent, Frame,
+    ParamValue, Sprite, StateDef, TransitionDef,
+};
 use std::sync::Arc;
-pub use style::{Affine, Color, StokeStyle, Stroke, Style};
+pub use style::{Affine, Color, StokeStyle, Stroke, Style, TextOutline, TextShadow};
+pub use svg::{SvgDrawable, SvgFragment, SvgManager};
 pub use text::{FontName, TextAlign, TextDrawable};
 pub use utils::load_image_from_file;
+pub use view::{Camera, View, view_at};
 pub type ImageManager = Resource<String, Arc<Image>>;
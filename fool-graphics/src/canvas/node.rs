@@ -1,8 +1,9 @@
 use crate::canvas::style::SimpleColor;
 
 use super::{SceneNodeKind, Style};
-use kurbo::{PathEl, Point, RoundedRectRadii, Size, Vec2};
+use kurbo::{Affine, PathEl, Point, Rect, RoundedRectRadii, Size, Vec2};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 
 const fn default_apply_parent_style() -> bool {
     true
@@ -30,9 +31,23 @@ impl SceneNode {
     pub fn clear_children(&mut self) {
         self.children.clear();
     }
+    /// reorders the children by `key` once, e.g. by y so lower sprites draw in front
+    /// in a top down game, equal keys keep their order and `z_index` still goes
+    /// first when drawn
+    pub fn sort_children_by<K: PartialOrd>(&mut self, mut key: impl FnMut(&SceneNode) -> K) {
+        self.children
+            .sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal));
+    }
     pub fn empty() -> Self {
         Self::default()
     }
+    /// the drawables of this node and its children, see `SceneNodeKind::validate`
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(drawable) = &self.drawable {
+            drawable.validate()?;
+        }
+        self.children.iter().try_for_each(SceneNode::validate)
+    }
 }
 impl SceneNode {
     pub fn image(position: Point, image: String) -> Self {
@@ -41,6 +56,28 @@ impl SceneNode {
             &Default::default(),
         )
     }
+    pub fn tiled_image(image: String, dest: Rect, tile_size: Size) -> Self {
+        Self::new(
+            SceneNodeKind::TiledImage {
+                image,
+                dest,
+                tile_size,
+            },
+            &Default::default(),
+        )
+    }
+    /// `image` centered on the origin of each transform, many tiles or sprites of
+    /// the same image in one node instead of a node each
+    pub fn instanced(image: String, transforms: Vec<Affine>) -> Self {
+        Self::new(
+            SceneNodeKind::Instanced { image, transforms },
+            &Default::default(),
+        )
+    }
+    /// centered on the origin, position, scale and opacity come from the style
+    pub fn svg(svg: impl Into<String>) -> Self {
+        Self::new(SceneNodeKind::Svg { svg: svg.into() }, &Default::default())
+    }
     pub fn text(position: Point, text: String, style: Style) -> Self {
         Self::new(SceneNodeKind::Text { position, text }, &style)
     }
@@ -117,6 +154,39 @@ impl SceneNode {
             style,
         )
     }
+    /// a cooldown donut, `start_angle` and `sweep_angle` in radians clockwise from +x
+    pub fn ring(
+        center: Point,
+        outer_radius: f64,
+        inner_radius: f64,
+        start_angle: f64,
+        sweep_angle: f64,
+        style: &Style,
+    ) -> Self {
+        Self::new(
+            SceneNodeKind::Ring {
+                center,
+                outer_radius,
+                inner_radius,
+                start_angle,
+                sweep_angle,
+            },
+            style,
+        )
+    }
+    /// a filled arc, the stroked one is `arc`
+    pub fn pie(
+        center: Point,
+        radius: f64,
+        start_angle: f64,
+        sweep_angle: f64,
+        style: &Style,
+    ) -> Self {
+        Self::ring(center, radius, 0.0, start_angle, sweep_angle, style)
+    }
+    pub fn polygon(points: Vec<Point>, closed: bool, style: &Style) -> Self {
+        Self::new(SceneNodeKind::Polygon { points, closed }, style)
+    }
     pub fn point_light(
         center: Point,
         radius: f64,
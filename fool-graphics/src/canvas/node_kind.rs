@@ -1,12 +1,15 @@
 use crate::canvas::style::{CustomBrush, CustomGradient, SimpleColor};
 
-use super::utils::add_circle_to_path;
-use super::{Drawable, ImageDrawable, Style, TextDrawable};
+use super::utils::{light_mask_path, normalize_sweep, polygon_path, ring_path};
+use super::{
+    Drawable, ImageDrawable, InstancedImageDrawable, Style, SvgDrawable, TextDrawable,
+    TiledImageDrawable,
+};
 use kurbo::{
-    Arc, BezPath, CubicBez, Ellipse, Line, PathEl, Point, QuadBez, Rect, RoundedRect,
-    RoundedRectRadii, Size, Triangle, Vec2,
+    Affine, Arc, BezPath, CubicBez, Ellipse, Line, PathEl, Point, QuadBez, Rect, RoundedRect,
+    RoundedRectRadii, Shape, Size, Triangle, Vec2,
 };
-use peniko::Image;
+use peniko::{Fill, Image};
 use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SceneNodeKind {
@@ -62,6 +65,20 @@ pub enum SceneNodeKind {
         sweep_angle: f64,
         rotation: f64,
     },
+    /// the part of a ring from `start_angle` over `sweep_angle`, e.g. a cooldown,
+    /// a pie slice when `inner_radius` is 0
+    Ring {
+        center: Point,
+        outer_radius: f64,
+        inner_radius: f64,
+        start_angle: f64,
+        sweep_angle: f64,
+    },
+    /// filled even-odd when closed, only stroked when open
+    Polygon {
+        points: Vec<Point>,
+        closed: bool,
+    },
     PointLight {
         center: Point,
         radius: f64,
@@ -85,6 +102,18 @@ pub enum SceneNodeKind {
         position: Point,
         image: Image,
     },
+    TiledImage {
+        image: String,
+        dest: Rect,
+        tile_size: Size,
+    },
+    Instanced {
+        image: String,
+        transforms: Vec<Affine>,
+    },
+    Svg {
+        svg: String,
+    },
 }
 impl SceneNodeKind {
     pub(crate) fn build(&self, style: &Style) -> BuiltDrawable {
@@ -99,6 +128,15 @@ impl SceneNodeKind {
                 super::VelloImage::Path(image.clone()),
                 Default::default(),
             ),
+            SceneNodeKind::TiledImage {
+                image,
+                dest,
+                tile_size,
+            } => BuiltDrawable::tiled_image(image.clone(), *dest, *tile_size),
+            SceneNodeKind::Instanced { image, transforms } => {
+                BuiltDrawable::instanced(image.clone(), transforms.clone())
+            }
+            SceneNodeKind::Svg { svg } => BuiltDrawable::svg(svg.clone(), style),
             SceneNodeKind::Text { position, text } => {
                 BuiltDrawable::text(*position, text.clone(), style.clone())
             }
@@ -149,6 +187,27 @@ impl SceneNodeKind {
                 style,
             ),
 
+            SceneNodeKind::Ring {
+                center,
+                outer_radius,
+                inner_radius,
+                start_angle,
+                sweep_angle,
+            } => BuiltDrawable::path(
+                ring_path(
+                    *center,
+                    *outer_radius,
+                    *inner_radius,
+                    *start_angle,
+                    *sweep_angle,
+                ),
+                style,
+            ),
+
+            SceneNodeKind::Polygon { points, closed } => {
+                BuiltDrawable::polygon(points, *closed, style)
+            }
+
             SceneNodeKind::PointLight {
                 center,
                 radius,
@@ -165,6 +224,196 @@ impl SceneNodeKind {
     }
 }
 
+// flattening tolerance of the outlines
+const OUTLINE_TOLERANCE: f64 = 0.1;
+
+fn check_radius(kind: &str, name: &str, radius: f64) -> anyhow::Result<()> {
+    match radius.is_finite() && radius >= 0.0 {
+        true => Ok(()),
+        false => Err(anyhow::anyhow!(
+            "{} {} must be a number >= 0, got {}",
+            kind,
+            name,
+            radius
+        )),
+    }
+}
+
+fn check_finite(kind: &str, name: &str, value: f64) -> anyhow::Result<()> {
+    match value.is_finite() {
+        true => Ok(()),
+        false => Err(anyhow::anyhow!(
+            "{} {} must be a number, got {}",
+            kind,
+            name,
+            value
+        )),
+    }
+}
+
+impl SceneNodeKind {
+    /// errors name the parameter it can't be drawn with, sweeps past a full turn
+    /// are fine, they draw the full turn
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Ellipse { radii, .. } => {
+                check_radius("Ellipse", "radii.x", radii.x)?;
+                check_radius("Ellipse", "radii.y", radii.y)
+            }
+            Self::Circle { radius, .. } => check_radius("Circle", "radius", *radius),
+            Self::Rect { size, .. } => {
+                check_radius("Rect", "size.width", size.width)?;
+                check_radius("Rect", "size.height", size.height)
+            }
+            Self::RoundedRect { size, radii, .. } => {
+                check_radius("RoundedRect", "size.width", size.width)?;
+                check_radius("RoundedRect", "size.height", size.height)?;
+                for (name, radius) in [
+                    ("radii.top_left", radii.top_left),
+                    ("radii.top_right", radii.top_right),
+                    ("radii.bottom_right", radii.bottom_right),
+                    ("radii.bottom_left", radii.bottom_left),
+                ] {
+                    check_radius("RoundedRect", name, radius)?;
+                }
+                Ok(())
+            }
+            Self::Arc {
+                radii,
+                start_angle,
+                sweep_angle,
+                ..
+            } => {
+                check_radius("Arc", "radii.x", radii.x)?;
+                check_radius("Arc", "radii.y", radii.y)?;
+                check_finite("Arc", "start_angle", *start_angle)?;
+                check_finite("Arc", "sweep_angle", *sweep_angle)
+            }
+            Self::Ring {
+                outer_radius,
+                inner_radius,
+                start_angle,
+                sweep_angle,
+                ..
+            } => {
+                check_radius("Ring", "outer_radius", *outer_radius)?;
+                check_radius("Ring", "inner_radius", *inner_radius)?;
+                if inner_radius > outer_radius {
+                    return Err(anyhow::anyhow!(
+                        "Ring inner_radius {} is larger than outer_radius {}",
+                        inner_radius,
+                        outer_radius
+                    ));
+                }
+                check_finite("Ring", "start_angle", *start_angle)?;
+                check_finite("Ring", "sweep_angle", *sweep_angle)
+            }
+            Self::Polygon { points, closed } => {
+                let least = if *closed { 3 } else { 2 };
+                if points.len() < least {
+                    return Err(anyhow::anyhow!(
+                        "Polygon points needs at least {} points when closed is {}, got {}",
+                        least,
+                        closed,
+                        points.len()
+                    ));
+                }
+                match points.iter().position(|p| !p.is_finite()) {
+                    Some(index) => Err(anyhow::anyhow!(
+                        "Polygon points[{}] must be numbers, got {:?}",
+                        index + 1,
+                        points[index]
+                    )),
+                    None => Ok(()),
+                }
+            }
+            Self::PointLight { radius, .. } => check_radius("PointLight", "radius", *radius),
+            _ => Ok(()),
+        }
+    }
+    /// the shape before the style transform and without the stroke, None for
+    /// text, images, svgs and lights
+    pub fn outline(&self) -> Option<BezPath> {
+        let path = match self {
+            Self::Ellipse {
+                center,
+                radii,
+                rotation,
+            } => Ellipse::new(*center, *radii, *rotation).to_path(OUTLINE_TOLERANCE),
+            Self::Circle { center, radius, .. } => {
+                Ellipse::new(*center, Vec2::new(*radius, *radius), 0.0).to_path(OUTLINE_TOLERANCE)
+            }
+            Self::Line { p0, p1 } => Line::new(*p0, *p1).to_path(OUTLINE_TOLERANCE),
+            Self::Rect { p0, size } => {
+                Rect::from_center_size(*p0, *size).to_path(OUTLINE_TOLERANCE)
+            }
+            Self::RoundedRect { p0, size, radii } => {
+                RoundedRect::from_rect(Rect::from_center_size(*p0, *size), *radii)
+                    .to_path(OUTLINE_TOLERANCE)
+            }
+            Self::Triangle { a, b, c } => Triangle::new(*a, *b, *c).to_path(OUTLINE_TOLERANCE),
+            Self::QuadBez { a, b, c } => QuadBez::new(*a, *b, *c).to_path(OUTLINE_TOLERANCE),
+            Self::CubicBez { a, b, c, d } => {
+                CubicBez::new(*a, *b, *c, *d).to_path(OUTLINE_TOLERANCE)
+            }
+            Self::BezPath { elements } => BezPath::from_vec(elements.clone()),
+            Self::Point { pos } => {
+                Ellipse::new(*pos, Vec2::new(0.5, 0.5), 0.0).to_path(OUTLINE_TOLERANCE)
+            }
+            Self::Arc {
+                center,
+                radii,
+                start_angle,
+                sweep_angle,
+                rotation,
+            } => Arc::new(
+                *center,
+                *radii,
+                *start_angle,
+                normalize_sweep(*sweep_angle),
+                *rotation,
+            )
+            .to_path(OUTLINE_TOLERANCE),
+            Self::Ring {
+                center,
+                outer_radius,
+                inner_radius,
+                start_angle,
+                sweep_angle,
+            } => ring_path(
+                *center,
+                *outer_radius,
+                *inner_radius,
+                *start_angle,
+                *sweep_angle,
+            ),
+            Self::Polygon { points, closed } => polygon_path(points, *closed),
+            _ => return None,
+        };
+        Some(path)
+    }
+    /// what the outline covers, for culling and picking
+    pub fn bounds(&self) -> Option<Rect> {
+        self.outline().map(|outline| outline.bounding_box())
+    }
+    /// whether the fill covers `point`, closed polygons are always even-odd
+    pub fn contains(&self, point: Point, fill_rule: Fill) -> bool {
+        let fill_rule = match self {
+            Self::Polygon { closed: false, .. } => return false,
+            Self::Polygon { .. } => Fill::EvenOdd,
+            _ => fill_rule,
+        };
+        let Some(outline) = self.outline() else {
+            return false;
+        };
+        let winding = outline.winding(point);
+        match fill_rule {
+            Fill::NonZero => winding != 0,
+            Fill::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
 pub(crate) struct BuiltDrawable {
     pub(crate) style: Style,
     pub(crate) drawable: Box<dyn Drawable>,
@@ -178,6 +427,31 @@ impl BuiltDrawable {
         }
     }
     #[inline]
+    pub fn tiled_image(image: String, dest: Rect, tile_size: Size) -> Self {
+        Self {
+            style: Default::default(),
+            drawable: Box::new(TiledImageDrawable {
+                image,
+                dest,
+                tile_size,
+            }),
+        }
+    }
+    #[inline]
+    pub fn instanced(image: String, transforms: Vec<Affine>) -> Self {
+        Self {
+            style: Default::default(),
+            drawable: Box::new(InstancedImageDrawable { image, transforms }),
+        }
+    }
+    #[inline]
+    pub fn svg(svg: String, style: &Style) -> Self {
+        Self {
+            style: style.clone(),
+            drawable: Box::new(SvgDrawable { svg }),
+        }
+    }
+    #[inline]
     pub fn text(position: Point, text: String, style: Style) -> Self {
         Self {
             style: Default::default(),
@@ -274,7 +548,31 @@ impl BuiltDrawable {
     ) -> Self {
         Self {
             style: style.clone(),
-            drawable: Box::new(Arc::new(center, radii, start_angle, sweep_angle, rotation)),
+            drawable: Box::new(Arc::new(
+                center,
+                radii,
+                start_angle,
+                normalize_sweep(sweep_angle),
+                rotation,
+            )),
+        }
+    }
+    #[inline]
+    pub fn path(path: BezPath, style: &Style) -> Self {
+        Self {
+            style: style.clone(),
+            drawable: Box::new(path),
+        }
+    }
+    #[inline]
+    pub fn polygon(points: &[Point], closed: bool, style: &Style) -> Self {
+        let style = match closed {
+            true => style.clone().with_fill_rule(Fill::EvenOdd),
+            false => style.clone().with_fill(None),
+        };
+        Self {
+            style,
+            drawable: Box::new(polygon_path(points, closed)),
         }
     }
     #[inline]
@@ -284,11 +582,7 @@ impl BuiltDrawable {
         rotation: f64,
         color: &Vec<(f32, SimpleColor)>,
     ) -> Self {
-        let gradient = CustomGradient {
-            kind: super::style::CustomGradientKind::Radial,
-            extend: super::style::CustomExtend::Pad,
-            colors: color.to_vec(),
-        };
+        let gradient = CustomGradient::radial(Point::ZERO, 1.0, color.to_vec());
         let brush = CustomBrush::Gradient(gradient);
         Self {
             style: Style::default().with_fill(Some(brush)),
@@ -297,17 +591,7 @@ impl BuiltDrawable {
     }
     #[inline]
     pub fn light_mask(screen_size: Size, lights: &[(Point, f64)], darkness_alpha: u8) -> Self {
-        let mut path = BezPath::new();
-        let rect = Rect::from_origin_size(Point::ORIGIN, screen_size);
-        path.move_to(rect.origin());
-        path.line_to(Point::new(rect.x1, rect.y0));
-        path.line_to(Point::new(rect.x1, rect.y1));
-        path.line_to(Point::new(rect.x0, rect.y1));
-        path.close_path();
-        for &(center, radius) in lights {
-            add_circle_to_path(&mut path, center, radius);
-        }
-
+        let path = light_mask_path(screen_size, lights);
         let style = Style {
             fill: Some(CustomBrush::Color(SimpleColor {
                 r: 0,
@@ -325,3 +609,230 @@ impl BuiltDrawable {
         }
     }
 }
+
+#[test]
+fn test_primitives() {
+    use std::f64::consts::{FRAC_PI_2, PI, TAU};
+    // '#' where the fill covers the pixel, 12 x 12 pixels around the origin
+    let raster = |kind: &SceneNodeKind| -> String {
+        let rows: Vec<String> = (0..12)
+            .map(|y| {
+                (0..12)
+                    .map(|x| {
+                        let pixel = Point::new(x as f64 - 5.5, y as f64 - 5.5);
+                        match kind.contains(pixel, Fill::NonZero) {
+                            true => '#',
+                            false => '.',
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        rows.join("\n")
+    };
+    let rounded = SceneNodeKind::RoundedRect {
+        p0: Point::ZERO,
+        size: Size::new(12.0, 8.0),
+        radii: RoundedRectRadii::new(4.0, 0.0, 4.0, 0.0),
+    };
+    let ellipse = SceneNodeKind::Ellipse {
+        center: Point::ZERO,
+        radii: Vec2::new(6.0, 3.0),
+        rotation: FRAC_PI_2,
+    };
+    let arc = SceneNodeKind::Arc {
+        center: Point::ZERO,
+        radii: Vec2::new(6.0, 6.0),
+        start_angle: 0.0,
+        sweep_angle: PI,
+        rotation: 0.0,
+    };
+    let pie = SceneNodeKind::Ring {
+        center: Point::ZERO,
+        outer_radius: 6.0,
+        inner_radius: 0.0,
+        start_angle: -FRAC_PI_2,
+        sweep_angle: FRAC_PI_2 * 3.0,
+    };
+    let ring = |sweep_angle| SceneNodeKind::Ring {
+        center: Point::ZERO,
+        outer_radius: 6.0,
+        inner_radius: 3.0,
+        start_angle: 0.0,
+        sweep_angle,
+    };
+    // a star through its own inside, the middle is left open
+    let star: Vec<Point> = (0..5)
+        .map(|i| Point::ORIGIN + Vec2::from_angle(-FRAC_PI_2 + i as f64 * 2.0 * TAU / 5.0) * 6.0)
+        .collect();
+    let polygon = SceneNodeKind::Polygon {
+        points: star.clone(),
+        closed: true,
+    };
+    // per corner radii, top left and bottom right
+    assert_eq!(
+        raster(&rounded),
+        "............\n\
+         ............\n\
+         ..##########\n\
+         .###########\n\
+         ############\n\
+         ############\n\
+         ############\n\
+         ############\n\
+         ###########.\n\
+         ##########..\n\
+         ............\n\
+         ............"
+    );
+    // turned upright
+    assert_eq!(
+        raster(&ellipse),
+        ".....##.....\n\
+         ....####....\n\
+         ....####....\n\
+         ...######...\n\
+         ...######...\n\
+         ...######...\n\
+         ...######...\n\
+         ...######...\n\
+         ...######...\n\
+         ....####....\n\
+         ....####....\n\
+         .....##....."
+    );
+    // filled it is cut along the chord
+    assert_eq!(
+        raster(&arc),
+        "............\n\
+         ............\n\
+         ............\n\
+         ............\n\
+         ............\n\
+         ............\n\
+         ############\n\
+         ############\n\
+         .##########.\n\
+         .##########.\n\
+         ..########..\n\
+         ....####...."
+    );
+    // three quarters from the top, clockwise
+    assert_eq!(
+        raster(&pie),
+        "......##....\n\
+         ......####..\n\
+         ......#####.\n\
+         ......#####.\n\
+         ......######\n\
+         ......######\n\
+         ############\n\
+         ############\n\
+         .##########.\n\
+         .##########.\n\
+         ..########..\n\
+         ....####...."
+    );
+    // a full turn keeps the hole
+    assert_eq!(
+        raster(&ring(TAU)),
+        "....####....\n\
+         ..########..\n\
+         .##########.\n\
+         .###....###.\n\
+         ###......###\n\
+         ###......###\n\
+         ###......###\n\
+         ###......###\n\
+         .###....###.\n\
+         .##########.\n\
+         ..########..\n\
+         ....####...."
+    );
+    // even-odd leaves the middle of the star open
+    assert_eq!(
+        raster(&polygon),
+        "............\n\
+         ............\n\
+         .....##.....\n\
+         .....##.....\n\
+         .####..####.\n\
+         ..##....##..\n\
+         ............\n\
+         ....#..#....\n\
+         ...######...\n\
+         ...#....#...\n\
+         ............\n\
+         ............"
+    );
+    // past a full turn is the full turn
+    assert_eq!(raster(&ring(10.0)), raster(&ring(TAU)));
+    let open = SceneNodeKind::Polygon {
+        points: star,
+        closed: false,
+    };
+    assert!(!raster(&open).contains('#'));
+
+    // bounds of the geometry, for culling and picking
+    let near = |a: Rect, b: Rect| {
+        [a.x0 - b.x0, a.y0 - b.y0, a.x1 - b.x1, a.y1 - b.y1]
+            .iter()
+            .all(|d| d.abs() < 1e-6)
+    };
+    assert!(near(pie.bounds().unwrap(), Rect::new(-6.0, -6.0, 6.0, 6.0)));
+    assert!(near(arc.bounds().unwrap(), Rect::new(-6.0, 0.0, 6.0, 6.0)));
+    assert!(near(
+        ellipse.bounds().unwrap(),
+        Rect::new(-3.0, -6.0, 3.0, 6.0)
+    ));
+    assert!(near(
+        rounded.bounds().unwrap(),
+        Rect::new(-6.0, -4.0, 6.0, 4.0)
+    ));
+    let text = SceneNodeKind::Text {
+        position: Point::ZERO,
+        text: "a".into(),
+    };
+    assert_eq!(text.bounds(), None);
+
+    // errors name what is wrong
+    let err = |kind: SceneNodeKind| kind.validate().unwrap_err().to_string();
+    assert!(
+        err(SceneNodeKind::Ring {
+            center: Point::ZERO,
+            outer_radius: 2.0,
+            inner_radius: 3.0,
+            start_angle: 0.0,
+            sweep_angle: 1.0,
+        })
+        .contains("inner_radius")
+    );
+    assert!(
+        err(SceneNodeKind::Circle {
+            center: Point::ZERO,
+            radius: -1.0,
+            rotation: 0.0,
+        })
+        .contains("Circle radius")
+    );
+    let mut bad = RoundedRectRadii::from_single_radius(1.0);
+    bad.bottom_left = f64::NAN;
+    assert!(
+        err(SceneNodeKind::RoundedRect {
+            p0: Point::ZERO,
+            size: Size::new(4.0, 4.0),
+            radii: bad,
+        })
+        .contains("radii.bottom_left")
+    );
+    assert!(
+        err(SceneNodeKind::Polygon {
+            points: vec![Point::ZERO, Point::new(1.0, 0.0)],
+            closed: true,
+        })
+        .contains("Polygon points")
+    );
+    for kind in [rounded, ellipse, arc, pie, ring(10.0), polygon] {
+        kind.validate().unwrap();
+    }
+}
@@ -29,6 +29,15 @@ impl Animation {
     pub const fn current(&self) -> usize {
         self.current
     }
+    /// seconds one pass through the frames takes
+    pub fn duration(&self) -> f64 {
+        self.count as f64 * self.scheduler.interval()
+    }
+    /// back to the first frame
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.scheduler.restart();
+    }
     pub fn next(&mut self) {
         if !self.running {
             return;
@@ -40,12 +49,39 @@ impl Animation {
             }
         }
     }
+    /// moves forward by `dt` seconds instead of by the wall clock
+    pub fn advance(&mut self, dt: f64) {
+        if !self.running || self.count == 0 {
+            return;
+        }
+        let frames = self.scheduler.step(dt) as usize;
+        self.current = (self.current + frames) % self.count;
+    }
     pub fn to_node(&self, x: f64, y: f64) -> SceneNode {
-        if !self.running && self.on_pause.is_some() {
-            self.on_pause.as_ref().map(|i| i.to_node(x, y)).unwrap()
-        } else {
-            let frame = &self.frames[self.current];
-            frame.to_node(x, y)
+        self.frame().to_node(x, y)
+    }
+    /// the frame drawn now
+    pub fn frame(&self) -> &Frame {
+        match &self.on_pause {
+            Some(frame) if !self.running => frame,
+            _ => &self.frames[self.current],
         }
     }
 }
+
+#[test]
+fn test_animation_advance() {
+    let img = image::DynamicImage::new_rgba8(1, 1);
+    let frames = (0..4).map(|_| Frame::from_image(&img)).collect::<Vec<_>>();
+    let mut animation = Animation::new(frames, 10);
+    animation.advance(0.05);
+    assert_eq!(animation.current(), 0);
+    animation.advance(0.06);
+    assert_eq!(animation.current(), 1);
+    // a long frame skips ahead and wraps around
+    animation.advance(0.45);
+    assert_eq!(animation.current(), 1);
+    animation.scheduler.pause();
+    animation.advance(1.0);
+    assert_eq!(animation.current(), 1);
+}
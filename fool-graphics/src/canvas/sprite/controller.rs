@@ -0,0 +1,414 @@
+use super::{Animation, FrameId, Sprite};
+use crate::canvas::SceneNode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// what `from` takes to leave every state at once
+pub const ANY_STATE: &str = "any";
+
+const fn default_speed() -> f64 {
+    1.0
+}
+
+/// the graph a designer edits, states by name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerDef {
+    pub initial: String,
+    pub states: BTreeMap<String, StateDef>,
+    /// checked in order, the `any` ones before the current state's
+    #[serde(default)]
+    pub transitions: Vec<TransitionDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateDef {
+    /// the name of an animation of the sprite
+    pub animation: String,
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionDef {
+    /// a state or `any`
+    pub from: String,
+    pub to: String,
+    /// all have to hold, none always does
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// seconds both states are drawn crossfading, 0 snaps
+    #[serde(default)]
+    pub duration: f64,
+    /// the normalized time the state has to reach first, 1 is one pass
+    #[serde(default)]
+    pub exit_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Bool(bool),
+    Number(f64),
+}
+
+impl Default for ParamValue {
+    fn default() -> Self {
+        Self::Bool(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CompareOp {
+    #[default]
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+}
+
+/// `param op value`, `{ param = "grounded" }` alone is a bool or trigger being set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    pub param: String,
+    #[serde(default)]
+    pub op: CompareOp,
+    #[serde(default)]
+    pub value: ParamValue,
+}
+
+impl Condition {
+    /// false for parameters never set, or of the other kind
+    fn holds(&self, params: &HashMap<String, ParamValue>) -> bool {
+        let Some(param) = params.get(&self.param) else {
+            return false;
+        };
+        match (*param, self.value) {
+            (ParamValue::Bool(a), ParamValue::Bool(b)) => match self.op {
+                CompareOp::Eq => a == b,
+                CompareOp::Ne => a != b,
+                _ => false,
+            },
+            (ParamValue::Number(a), ParamValue::Number(b)) => match self.op {
+                CompareOp::Eq => a == b,
+                CompareOp::Ne => a != b,
+                CompareOp::Gt => a > b,
+                CompareOp::Ge => a >= b,
+                CompareOp::Lt => a < b,
+                CompareOp::Le => a <= b,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControllerEvent {
+    Enter(String),
+    Exit(String),
+}
+
+#[derive(Debug, Clone)]
+struct Blend {
+    from: String,
+    elapsed: f64,
+    duration: f64,
+}
+
+/// a state machine over the animations of a sprite, stepped by the caller's clock
+#[derive(Debug, Clone)]
+pub struct AnimationController {
+    def: ControllerDef,
+    animations: HashMap<String, Animation>,
+    params: HashMap<String, ParamValue>,
+    // set until a transition that needs them is taken
+    triggers: HashSet<String>,
+    current: String,
+    // seconds in the current state, by its speed
+    time: f64,
+    blend: Option<Blend>,
+    events: Vec<ControllerEvent>,
+}
+
+impl AnimationController {
+    /// fails listing every state and transition naming something that isn't there
+    pub fn new<Id: FrameId>(def: ControllerDef, sprite: &Sprite<Id>) -> anyhow::Result<Self> {
+        let mut errors = Vec::new();
+        let mut animations = HashMap::new();
+        for (name, state) in &def.states {
+            match sprite.get_animation(&state.animation) {
+                Ok(animation) => {
+                    animations.insert(name.clone(), animation);
+                }
+                Err(_) => errors.push(format!(
+                    "state {}: animation {} not found",
+                    name, state.animation
+                )),
+            }
+        }
+        if !def.states.contains_key(&def.initial) {
+            errors.push(format!("initial state {} not found", def.initial));
+        }
+        for transition in &def.transitions {
+            if transition.from != ANY_STATE && !def.states.contains_key(&transition.from) {
+                errors.push(format!(
+                    "transition {} -> {}: state {} not found",
+                    transition.from, transition.to, transition.from
+                ));
+            }
+            if !def.states.contains_key(&transition.to) {
+                errors.push(format!(
+                    "transition {} -> {}: state {} not found",
+                    transition.from, transition.to, transition.to
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            anyhow::bail!("invalid animation controller:\n{}", errors.join("\n"));
+        }
+        let current = def.initial.clone();
+        let mut this = Self {
+            def,
+            animations,
+            params: HashMap::new(),
+            triggers: HashSet::new(),
+            current: current.clone(),
+            time: 0.0,
+            blend: None,
+            events: Vec::new(),
+        };
+        this.enter(current);
+        Ok(this)
+    }
+    pub fn set(&mut self, param: impl Into<String>, value: f64) {
+        self.params.insert(param.into(), ParamValue::Number(value));
+    }
+    pub fn set_bool(&mut self, param: impl Into<String>, value: bool) {
+        self.params.insert(param.into(), ParamValue::Bool(value));
+    }
+    /// true until a transition checking it is taken
+    pub fn trigger(&mut self, param: impl Into<String>) {
+        let param = param.into();
+        self.params.insert(param.clone(), ParamValue::Bool(true));
+        self.triggers.insert(param);
+    }
+    pub fn get(&self, param: &str) -> Option<ParamValue> {
+        self.params.get(param).copied()
+    }
+    pub fn state(&self) -> &str {
+        &self.current
+    }
+    /// passes through the current state's animation, 1.5 is halfway through the second
+    pub fn normalized_time(&self) -> f64 {
+        let duration = self.animations[&self.current].duration();
+        match duration > 0.0 {
+            true => self.time / duration,
+            false => 0.0,
+        }
+    }
+    /// 0..1 through the crossfade, None while not blending
+    pub fn blend(&self) -> Option<f64> {
+        self.blend
+            .as_ref()
+            .map(|blend| (blend.elapsed / blend.duration).min(1.0))
+    }
+    /// the states entered and left since the last call, in order
+    pub fn take_events(&mut self) -> Vec<ControllerEvent> {
+        std::mem::take(&mut self.events)
+    }
+    fn speed(&self, state: &str) -> f64 {
+        self.def.states[state].speed
+    }
+    fn enter(&mut self, state: String) {
+        if let Some(animation) = self.animations.get_mut(&state) {
+            animation.restart();
+        }
+        self.events.push(ControllerEvent::Enter(state.clone()));
+        self.current = state;
+        self.time = 0.0;
+    }
+    /// the first transition that can be taken now
+    fn pick(&self) -> Option<usize> {
+        let normalized = self.normalized_time();
+        let any = self
+            .def
+            .transitions
+            .iter()
+            .enumerate()
+            // interrupts don't restart the state they lead to
+            .filter(|(_, t)| t.from == ANY_STATE && t.to != self.current);
+        let own = self
+            .def
+            .transitions
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.from == self.current);
+        any.chain(own)
+            .find(|(_, t)| {
+                t.exit_time.is_none_or(|exit| normalized >= exit)
+                    && t.conditions.iter().all(|c| c.holds(&self.params))
+            })
+            .map(|(index, _)| index)
+    }
+    /// moves the animations on by `dt` seconds, then takes at most one transition
+    pub fn step(&mut self, dt: f64) {
+        let dt = dt.max(0.0);
+        let speed = self.speed(&self.current);
+        self.time += dt * speed;
+        if let Some(animation) = self.animations.get_mut(&self.current) {
+            animation.advance(dt * speed);
+        }
+        if let Some(blend) = self.blend.as_mut() {
+            blend.elapsed += dt;
+            let from = blend.from.clone();
+            let done = blend.elapsed >= blend.duration;
+            let speed = self.speed(&from);
+            if let Some(animation) = self.animations.get_mut(&from) {
+                animation.advance(dt * speed);
+            }
+            if done {
+                self.blend = None;
+            }
+        }
+        let Some(index) = self.pick() else {
+            return;
+        };
+        let transition = self.def.transitions[index].clone();
+        for condition in &transition.conditions {
+            if self.triggers.remove(&condition.param) {
+                self.params.remove(&condition.param);
+            }
+        }
+        let from = self.current.clone();
+        self.events.push(ControllerEvent::Exit(from.clone()));
+        self.blend = (transition.duration > 0.0).then_some(Blend {
+            from,
+            elapsed: 0.0,
+            duration: transition.duration,
+        });
+        self.enter(transition.to);
+    }
+    /// the current frame, crossfaded with the state being left while blending
+    pub fn to_node(&self, x: f64, y: f64) -> SceneNode {
+        let current = self.animations[&self.current].frame();
+        let Some(blend) = &self.blend else {
+            return current.to_node(x, y);
+        };
+        let weight = self.blend().unwrap_or(1.0) as f32;
+        let mut node = SceneNode::empty();
+        node.add_child(&self.animations[&blend.from].frame().to_node_with_alpha(
+            x,
+            y,
+            1.0 - weight,
+        ));
+        node.add_child(&current.to_node_with_alpha(x, y, weight));
+        node
+    }
+}
+
+#[test]
+fn test_animation_controller() -> anyhow::Result<()> {
+    use crate::canvas::SceneNodeKind;
+    use std::sync::Arc;
+    let image = Arc::new(image::DynamicImage::new_rgba8(8, 2));
+    let mut sprite = Sprite::from_image(image, 2, 2, 0..4usize);
+    sprite.create_animation("idle", [0, 1].into_iter(), 10)?;
+    sprite.create_animation("run", [2, 3].into_iter(), 10)?;
+    sprite.create_animation("hurt", [0].into_iter(), 10)?;
+    let def: ControllerDef = serde_json::from_str(
+        r#"{
+            "initial": "idle",
+            "states": {
+                "idle": { "animation": "idle" },
+                "run": { "animation": "run", "speed": 2 },
+                "hurt": { "animation": "hurt" }
+            },
+            "transitions": [
+                { "from": "idle", "to": "run", "duration": 0.2,
+                  "conditions": [{ "param": "speed", "op": ">", "value": 0.1 },
+                                 { "param": "grounded" }] },
+                { "from": "run", "to": "idle",
+                  "conditions": [{ "param": "speed", "op": "<=", "value": 0.1 }] },
+                { "from": "any", "to": "hurt", "conditions": [{ "param": "hurt" }] },
+                { "from": "hurt", "to": "idle", "exit_time": 1 }
+            ]
+        }"#,
+    )?;
+    let mut controller = AnimationController::new(def.clone(), &sprite)?;
+    assert_eq!(controller.state(), "idle");
+    assert_eq!(
+        controller.take_events(),
+        [ControllerEvent::Enter("idle".into())]
+    );
+    controller.set("speed", 3.2);
+    controller.step(0.05);
+    // not grounded yet
+    assert_eq!(controller.state(), "idle");
+    assert!((controller.normalized_time() - 0.25).abs() < 1e-9);
+    controller.set_bool("grounded", true);
+    controller.step(0.05);
+    assert_eq!(controller.state(), "run");
+    assert_eq!(
+        controller.take_events(),
+        [
+            ControllerEvent::Exit("idle".into()),
+            ControllerEvent::Enter("run".into())
+        ]
+    );
+    // both frames are drawn while crossfading
+    controller.step(0.05);
+    assert!((controller.blend().unwrap() - 0.25).abs() < 1e-9);
+    assert!((controller.normalized_time() - 0.5).abs() < 1e-9);
+    let node = controller.to_node(0.0, 0.0);
+    let alphas: Vec<f32> = node
+        .children
+        .iter()
+        .map(|child| match &child.drawable {
+            Some(SceneNodeKind::SpriteImage { image, .. }) => image.alpha,
+            _ => panic!("not a sprite frame"),
+        })
+        .collect();
+    assert_eq!(alphas, [0.75, 0.25]);
+    controller.step(0.2);
+    assert!(controller.blend().is_none());
+    assert!(controller.to_node(0.0, 0.0).drawable.is_some());
+
+    // the trigger interrupts from any state once, then the clip plays out
+    controller.trigger("hurt");
+    controller.step(0.0);
+    assert_eq!(controller.state(), "hurt");
+    controller.step(0.05);
+    assert_eq!(controller.state(), "hurt");
+    controller.step(0.06);
+    assert_eq!(controller.state(), "idle");
+    assert!(controller.get("hurt").is_none());
+
+    let mut broken = def;
+    broken.states.get_mut("run").unwrap().animation = "sprint".into();
+    broken.transitions[0].to = "jump".into();
+    let err = AnimationController::new(broken, &sprite)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("state run: animation sprint not found"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("transition idle -> jump: state jump not found"),
+        "{}",
+        err
+    );
+    Ok(())
+}
@@ -37,9 +37,15 @@ impl Frame {
         Self::from_image(&img)
     }
     pub fn to_node(&self, x: f64, y: f64) -> SceneNode {
+        self.to_node_with_alpha(x, y, 1.0)
+    }
+    /// drawn with `alpha` times its own opacity, images leave the node opacity alone
+    pub fn to_node_with_alpha(&self, x: f64, y: f64, alpha: f32) -> SceneNode {
+        let mut image = self.img.as_ref().clone();
+        image.alpha *= alpha;
         let node = SceneNodeKind::SpriteImage {
             position: graph_pt2!(x, y),
-            image: self.img.as_ref().clone(),
+            image,
         };
         SceneNode {
             style: Default::default(),
@@ -6,9 +6,14 @@ use std::{
     sync::Arc,
 };
 mod animation;
+mod controller;
 mod frame;
 mod scheduler;
 pub use animation::Animation;
+pub use controller::{
+    AnimationController, CompareOp, Condition, ControllerDef, ControllerEvent, ParamValue,
+    StateDef, TransitionDef,
+};
 pub use frame::Frame;
 pub use scheduler::Scheduler;
 pub trait FrameId: Hash + Clone + Eq + PartialEq + Display + Debug + Send + Sync {}
@@ -6,6 +6,8 @@ pub struct Scheduler {
     frame_interval: Duration,
     pub next_frame_time: Instant,
     pub running: bool,
+    // seconds fed through `step` not used up by a frame yet
+    elapsed: f64,
 }
 impl Default for Scheduler {
     fn default() -> Self {
@@ -20,9 +22,18 @@ impl Scheduler {
             frame_interval,
             next_frame_time: now + frame_interval,
             running: true,
+            elapsed: 0.0,
         }
     }
 
+    pub fn interval(&self) -> f64 {
+        self.frame_interval.as_secs_f64()
+    }
+    /// back to the start of a frame, dropping what `step` carried over
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+        self.reset();
+    }
     fn advance(&mut self) {
         self.next_frame_time += self.frame_interval;
     }
@@ -51,4 +62,16 @@ impl Scheduler {
         }
         return redraw;
     }
+    /// how many frames `dt` seconds of the caller's clock move forward,
+    /// for animations that follow a scaled clock instead of the wall clock
+    pub fn step(&mut self, dt: f64) -> u32 {
+        if !self.running {
+            return 0;
+        }
+        let interval = self.frame_interval.as_secs_f64();
+        self.elapsed += dt.max(0.0);
+        let frames = (self.elapsed / interval).floor();
+        self.elapsed -= frames * interval;
+        frames as u32
+    }
 }
@@ -1,11 +1,12 @@
 use super::ImageManager;
 use super::text::{FontName, TextAlign};
+use crate::graph_vec2;
 use peniko::Brush;
 use serde::{Deserialize, Serialize};
 pub use vello::{
-    kurbo::{Affine, Stroke},
+    kurbo::{Affine, Point, Rect, Stroke},
     peniko::{
-        Color, ColorStop, ColorStops, Extend, Fill, Gradient, GradientKind,
+        Color, Extend, Fill, Gradient,
         color::{ColorSpaceTag, HueDirection},
     },
 };
@@ -24,6 +25,12 @@ const fn default_opacity() -> f32 {
 const fn default_visible() -> bool {
     true
 }
+const fn default_gradient_end() -> Point {
+    Point::new(1.0, 0.0)
+}
+const fn default_gradient_radius() -> f64 {
+    1.0
+}
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct SimpleColor {
@@ -43,6 +50,34 @@ impl Default for SimpleColor {
     }
 }
 
+impl SimpleColor {
+    pub fn to_color(&self) -> Color {
+        Color::from_rgba8(self.r, self.g, self.b, self.a)
+    }
+}
+
+/// glyph outlines stroked beneath the fill, `width` is how far it reaches past the glyph edge
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+pub struct TextOutline {
+    pub width: f64,
+    #[serde(default)]
+    pub color: SimpleColor,
+}
+
+/// the text drawn again offset by `dx`, `dy` in its own units beneath everything else,
+/// `blur` spreads it over a few jittered passes
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+pub struct TextShadow {
+    #[serde(default)]
+    pub dx: f64,
+    #[serde(default)]
+    pub dy: f64,
+    #[serde(default)]
+    pub blur: f64,
+    #[serde(default)]
+    pub color: SimpleColor,
+}
+
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub enum CustomBrush {
     Color(SimpleColor),
@@ -81,48 +116,75 @@ pub enum CustomExtend {
     Repeat,
     Reflect,
 }
+/// geometry in the node's own units, before its translation
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct CustomGradient {
     pub kind: CustomGradientKind,
     pub extend: CustomExtend,
     pub colors: Vec<(f32, SimpleColor)>,
+    /// where a linear one runs from and to
+    #[serde(default)]
+    pub start: Point,
+    #[serde(default = "default_gradient_end")]
+    pub end: Point,
+    /// where a radial one spreads from and how far
+    #[serde(default)]
+    pub center: Point,
+    #[serde(default = "default_gradient_radius")]
+    pub radius: f64,
 }
 impl CustomGradient {
+    pub fn linear(
+        start: impl Into<Point>,
+        end: impl Into<Point>,
+        stops: Vec<(f32, SimpleColor)>,
+    ) -> Self {
+        Self {
+            kind: CustomGradientKind::Linear,
+            extend: CustomExtend::Pad,
+            colors: stops,
+            start: start.into(),
+            end: end.into(),
+            center: Point::ZERO,
+            radius: default_gradient_radius(),
+        }
+    }
+    pub fn radial(center: impl Into<Point>, radius: f64, stops: Vec<(f32, SimpleColor)>) -> Self {
+        Self {
+            kind: CustomGradientKind::Radial,
+            extend: CustomExtend::Pad,
+            colors: stops,
+            start: Point::ZERO,
+            end: default_gradient_end(),
+            center: center.into(),
+            radius,
+        }
+    }
+    pub fn with_extend(mut self, extend: CustomExtend) -> Self {
+        self.extend = extend;
+        self
+    }
+    /// the stops are interpolated in srgb between their offsets, by vello
     pub fn to_vello(&self) -> peniko::Gradient {
-        let kind = match self.kind {
-            CustomGradientKind::Linear => GradientKind::Linear {
-                start: (0.0, 0.0).into(),
-                end: (1.0, 0.0).into(),
-            },
-            CustomGradientKind::Radial => GradientKind::Radial {
-                start_center: (0.0, 0.0).into(),
-                end_center: (0.0, 0.0).into(),
-                start_radius: 0.0,
-                end_radius: 1.0,
-            },
+        let gradient = match self.kind {
+            CustomGradientKind::Linear => Gradient::new_linear(self.start, self.end),
+            CustomGradientKind::Radial => Gradient::new_radial(self.center, self.radius as f32),
         };
-
         let extend = match self.extend {
             CustomExtend::Pad => Extend::Pad,
             CustomExtend::Repeat => Extend::Repeat,
             CustomExtend::Reflect => Extend::Reflect,
         };
-        let colors = self
+        let stops = self
             .colors
             .iter()
-            .map(|(pos, color)| ColorStop {
-                offset: *pos,
-                color: Color::from_rgba8(color.r, color.g, color.b, color.a).into(),
-            })
-            .collect();
-        let stops = ColorStops(colors);
-        Gradient {
-            kind,
-            extend,
-            interpolation_cs: ColorSpaceTag::Srgb,
-            hue_direction: HueDirection::Shorter,
-            stops,
-        }
+            .map(|(offset, color)| (*offset, color.to_color()))
+            .collect::<Vec<_>>();
+        gradient
+            .with_extend(extend)
+            .with_interpolation_cs(ColorSpaceTag::Srgb)
+            .with_hue_direction(HueDirection::Shorter)
+            .with_stops(stops.as_slice())
     }
 }
 
@@ -157,6 +219,16 @@ pub struct Style {
     pub line_spacing: Option<f32>,
     #[serde(default)]
     pub vertical: Option<bool>,
+    #[serde(default)]
+    pub outline: Option<TextOutline>,
+    #[serde(default)]
+    pub shadow: Option<TextShadow>,
+    // clip children to this rect, in node local space
+    #[serde(default)]
+    pub clip: Option<Rect>,
+    // set by the graph while drawing, images sample their nearest pixel
+    #[serde(skip)]
+    pub pixel_perfect: bool,
 }
 impl Default for Style {
     fn default() -> Self {
@@ -175,6 +247,10 @@ impl Default for Style {
             align: None,
             line_spacing: None,
             vertical: Some(false),
+            outline: None,
+            shadow: None,
+            clip: None,
+            pixel_perfect: false,
         }
     }
 }
@@ -192,9 +268,13 @@ impl Style {
         let font = child.font.clone().or_else(|| self.font.clone());
         let font_size = child.font_size.or_else(|| self.font_size.clone());
         let hint = child.hint.or_else(|| self.hint.clone());
-        let align = child.align.clone().or_else(|| self.align.clone());
+        let align = child.align.or(self.align);
         let line_spacing = child.line_spacing.or_else(|| self.line_spacing.clone());
         let vertical = child.vertical.or_else(|| self.vertical);
+        let outline = child.outline.clone().or_else(|| self.outline.clone());
+        let shadow = child.shadow.clone().or_else(|| self.shadow.clone());
+        let clip = child.clip;
+        let pixel_perfect = self.pixel_perfect || child.pixel_perfect;
         Style {
             font,
             font_size,
@@ -210,8 +290,17 @@ impl Style {
             z_index,
             tag,
             vertical,
+            outline,
+            shadow,
+            clip,
+            pixel_perfect,
         }
     }
+    /// the translation rounded to whole pixels, the scale and rotation kept
+    pub fn snap_translation(transform: Affine) -> Affine {
+        let translation = transform.translation();
+        transform.with_translation(graph_vec2!(translation.x.round(), translation.y.round()))
+    }
 }
 
 impl std::ops::Mul for Style {
@@ -237,6 +326,24 @@ impl Style {
         self
     }
 
+    pub fn with_linear_gradient(
+        self,
+        start: impl Into<Point>,
+        end: impl Into<Point>,
+        stops: Vec<(f32, SimpleColor)>,
+    ) -> Self {
+        let gradient = CustomGradient::linear(start, end, stops);
+        self.with_fill(Some(CustomBrush::Gradient(gradient)))
+    }
+    pub fn with_radial_gradient(
+        self,
+        center: impl Into<Point>,
+        radius: f64,
+        stops: Vec<(f32, SimpleColor)>,
+    ) -> Self {
+        let gradient = CustomGradient::radial(center, radius, stops);
+        self.with_fill(Some(CustomBrush::Gradient(gradient)))
+    }
     pub fn with_fill_rule(mut self, fill_rule: Fill) -> Self {
         self.fill_rule = fill_rule;
         self
@@ -295,4 +402,16 @@ impl Style {
         self.vertical = vertical;
         self
     }
+    pub fn with_outline(mut self, outline: Option<TextOutline>) -> Self {
+        self.outline = outline;
+        self
+    }
+    pub fn with_shadow(mut self, shadow: Option<TextShadow>) -> Self {
+        self.shadow = shadow;
+        self
+    }
+    pub fn with_clip(mut self, clip: Option<Rect>) -> Self {
+        self.clip = clip;
+        self
+    }
 }
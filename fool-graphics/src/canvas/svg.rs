@@ -0,0 +1,185 @@
+use super::{Drawable, FontManager, ImageManager, Style};
+use fool_resource::{EntryUsage, Resource, ScopeStack, SharedData};
+use kurbo::{Affine, Point, Rect, Size};
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+use vello::Scene;
+use vello::peniko::Mix;
+use vello_svg::usvg;
+
+/// a parsed svg encoded once into a vello scene, appended wherever it is drawn
+#[derive(Clone)]
+pub struct SvgFragment {
+    scene: Scene,
+    size: Size,
+    // the bytes it was parsed from, a reloaded resource comes with new ones
+    source: SharedData,
+}
+impl Debug for SvgFragment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SvgFragment")
+            .field("size", &self.size)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl SvgFragment {
+    pub fn parse(name: &str, data: SharedData) -> anyhow::Result<Self> {
+        let tree = usvg::Tree::from_data(&data, &usvg::Options::default())
+            .map_err(|err| anyhow::anyhow!("parse svg {} failed: {}", name, err))?;
+        let mut unsupported = BTreeSet::new();
+        find_unsupported(tree.root(), &mut unsupported);
+        // parsed once per load, so this is not repeated every frame
+        if !unsupported.is_empty() {
+            log::warn!(
+                "svg {} uses unsupported features, they are skipped: {:?}",
+                name,
+                unsupported
+            );
+        }
+        let size = Size::new(tree.size().width() as f64, tree.size().height() as f64);
+        Ok(Self {
+            scene: vello_svg::render_tree(&tree),
+            size,
+            source: data,
+        })
+    }
+    /// the svg viewport size in its own units
+    pub fn size(&self) -> Size {
+        self.size
+    }
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+    fn is_from(&self, data: &SharedData) -> bool {
+        self.source.as_ptr() == data.as_ptr() && self.source.len() == data.len()
+    }
+}
+
+fn find_unsupported(group: &usvg::Group, found: &mut BTreeSet<&'static str>) {
+    if !group.filters().is_empty() {
+        found.insert("filter");
+    }
+    if group.mask().is_some() {
+        found.insert("mask");
+    }
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => find_unsupported(group, found),
+            usvg::Node::Image(image) => match image.kind() {
+                usvg::ImageKind::PNG(_) | usvg::ImageKind::JPEG(_) => {}
+                usvg::ImageKind::SVG(tree) => find_unsupported(tree.root(), found),
+                _ => {
+                    found.insert("raster image other than png or jpeg");
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// svg fragments by resource name, parsed on first use from the raw resource
+#[derive(Debug, Clone, Default)]
+pub struct SvgManager {
+    fragments: Resource<String, Arc<SvgFragment>>,
+    raw: Resource<String, SharedData>,
+}
+
+impl SvgManager {
+    pub fn from_resource(raw: Resource<String, SharedData>) -> Self {
+        Self {
+            fragments: Resource::empty(),
+            raw,
+        }
+    }
+    pub fn get(&self, name: &str) -> anyhow::Result<Arc<SvgFragment>> {
+        let data = self.raw.get(name)?;
+        if let Some(fragment) = self.fragments.try_get(name).filter(|f| f.is_from(&data)) {
+            return Ok(fragment);
+        }
+        let fragment = Arc::new(SvgFragment::parse(name, data)?);
+        self.fragments.load(name, fragment.clone());
+        Ok(fragment)
+    }
+    pub fn size(&self, name: &str) -> anyhow::Result<Size> {
+        Ok(self.get(name)?.size())
+    }
+    pub fn set_scopes(&self, scopes: ScopeStack) {
+        self.fragments.set_scopes(scopes);
+    }
+    pub fn release_scope(&self, scope: &str) -> Vec<String> {
+        self.fragments.release_scope(scope)
+    }
+    /// parsed fragments by the size of their encoded scene
+    pub fn usage(&self) -> Vec<EntryUsage<String>> {
+        self.fragments.usage(|fragment| {
+            let encoding = fragment.scene.encoding();
+            encoding.path_data.len() + encoding.draw_data.len() + encoding.path_tags.len()
+        })
+    }
+}
+
+/// drawn centered on the node origin, the style transform places and scales it
+#[derive(Debug, Clone)]
+pub struct SvgDrawable {
+    pub svg: String,
+}
+impl Drawable for SvgDrawable {
+    fn draw(
+        &self,
+        scene: &mut Scene,
+        style: &Style,
+        _font_mgr: FontManager,
+        _img_mgr: ImageManager,
+        svg_mgr: SvgManager,
+    ) -> anyhow::Result<()> {
+        if !style.visible {
+            return Ok(());
+        }
+        let fragment = svg_mgr.get(&self.svg)?;
+        let size = fragment.size();
+        let transform =
+            style.translation * Affine::translate((-size.width / 2.0, -size.height / 2.0));
+        if style.opacity < 1.0 {
+            let bounds = Rect::from_origin_size(Point::ORIGIN, size);
+            scene.push_layer(Mix::Normal, style.opacity, transform, &bounds);
+            scene.append(fragment.scene(), Some(transform));
+            scene.pop_layer();
+        } else {
+            scene.append(fragment.scene(), Some(transform));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_svg_fragment() {
+    let raw = Resource::<String, SharedData>::empty();
+    let svgs = SvgManager::from_resource(raw.clone());
+    raw.load(
+        "icons/box.svg",
+        SharedData::from_static(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="16">
+                <rect x="2" y="2" width="12" height="12" fill="red"/>
+                <circle cx="24" cy="8" r="6" fill="blue"/>
+            </svg>"#,
+        ),
+    );
+    let fragment = svgs.get("icons/box.svg").unwrap();
+    assert_eq!(fragment.size(), Size::new(32.0, 16.0));
+    assert!(fragment.scene().encoding().n_paths >= 2);
+    // cached until the resource is loaded again
+    assert!(Arc::ptr_eq(&fragment, &svgs.get("icons/box.svg").unwrap()));
+    raw.load(
+        "icons/box.svg",
+        SharedData::from(r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"/>"#),
+    );
+    assert_eq!(svgs.size("icons/box.svg").unwrap(), Size::new(8.0, 8.0));
+
+    raw.load("icons/broken.svg", SharedData::from_static(b"<svg><rect"));
+    let err = svgs.get("icons/broken.svg").unwrap_err();
+    assert!(err.to_string().contains("icons/broken.svg"));
+    assert!(svgs.get("icons/box.svg").is_ok());
+}
@@ -1,14 +1,18 @@
 use super::ImageManager;
-use super::{Drawable, FontManager, Style, VelloFont};
+use super::font::TextLayoutId;
+use super::{Drawable, FontManager, Style, TextLayout, VelloFont};
 use crate::graph_pt2;
 use kurbo::{Point, Size};
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
-use vello::kurbo::{Affine, Rect};
-use vello::peniko::{BrushRef, StyleRef};
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::sync::Arc;
+use vello::kurbo::{Affine, Join, Rect, Stroke, Vec2};
+use vello::peniko::{BrushRef, Mix, StyleRef};
 use vello::{Glyph, Scene};
 pub type FontName = String;
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TextAlign {
     #[default]
     Left,
@@ -16,6 +20,18 @@ pub enum TextAlign {
     Right,
 }
 
+// the directions a blurred shadow is jittered in, around the undisplaced pass
+const SHADOW_JITTER: [(f64, f64); 8] = [
+    (1.0, 0.0),
+    (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (0.0, 1.0),
+    (-FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (-1.0, 0.0),
+    (-FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+    (0.0, -1.0),
+    (FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+];
+
 #[derive(Debug, Clone)]
 pub struct TextDrawable {
     pub position: Point,
@@ -31,42 +47,91 @@ impl TextDrawable {
         font_mgr: FontManager,
         img_res: ImageManager,
     ) -> anyhow::Result<()> {
+        if !(self.style.visible && style.visible) {
+            return Ok(());
+        }
         let final_transform =
             self.style.translation * style.translation * Affine::translate(self.position.to_vec2());
         let font = font_mgr.get(&self.style.font.clone().unwrap_or_default());
+        let size = self.style.font_size.unwrap_or(16.0);
+        let hint = self.style.hint.unwrap_or_default();
+        let layout = Self::layout(
+            &font,
+            &self.text,
+            size,
+            self.style.vertical.unwrap_or(false),
+            self.style.align.unwrap_or_default(),
+            self.style.line_spacing.unwrap_or(1.0),
+        );
+        let opacity = self.style.opacity * style.opacity;
+        let outline = self.style.outline.as_ref().filter(|o| o.width > 0.0);
+        let shadow = self.style.shadow.as_ref();
+        // overlapping passes fade as one, not each on its own
+        let group = opacity < 1.0 && (outline.is_some() || shadow.is_some());
+        if group {
+            let mut bounds = layout.bounds.inflate(size as f64, size as f64);
+            if let Some(shadow) = shadow {
+                let reach = shadow.blur + outline.map_or(0.0, |o| o.width);
+                bounds = bounds
+                    .union(bounds + Vec2::new(shadow.dx, shadow.dy))
+                    .inflate(reach, reach);
+            }
+            scene.push_layer(Mix::Normal, opacity, final_transform, &bounds);
+        }
+        let alpha = if group { 1.0 } else { opacity };
+        let outline_stroke = outline.map(|o| Stroke::new(o.width * 2.0).with_join(Join::Round));
+        if let Some(shadow) = shadow {
+            let passes = match shadow.blur > 0.0 {
+                true => SHADOW_JITTER.len() + 1,
+                false => 1,
+            };
+            // the passes pile up to the shadow color where they all overlap
+            let color = shadow.color.to_color().multiply_alpha(alpha);
+            let pass_alpha = 1.0 - (1.0 - color.components[3]).powf(1.0 / passes as f32);
+            let color = color.with_alpha(pass_alpha);
+            let offsets = std::iter::once((0.0, 0.0))
+                .chain(SHADOW_JITTER)
+                .take(passes);
+            for (jx, jy) in offsets {
+                let transform = final_transform
+                    * Affine::translate((
+                        shadow.dx + jx * shadow.blur,
+                        shadow.dy + jy * shadow.blur,
+                    ));
+                if let Some(stroke) = &outline_stroke {
+                    Self::draw_layout(scene, &font, &layout, color, transform, stroke, hint);
+                }
+                Self::draw_layout(
+                    scene,
+                    &font,
+                    &layout,
+                    color,
+                    transform,
+                    self.style.fill_rule,
+                    hint,
+                );
+            }
+        }
+        if let (Some(outline), Some(stroke)) = (outline, &outline_stroke) {
+            let color = outline.color.to_color().multiply_alpha(alpha);
+            Self::draw_layout(scene, &font, &layout, color, final_transform, stroke, hint);
+        }
         let brush = match &self.style.fill {
             Some(b) => b.build(img_res)?,
             None => Default::default(),
         };
-        let _rect = if self.style.vertical.unwrap_or(false) {
-            Self::draw_glyphs_vertical(
-                scene,
-                &font,
-                self.style.font_size.unwrap_or(16.0),
-                &brush,
-                final_transform,
-                None,
-                self.style.fill_rule,
-                &self.text,
-                self.style.hint.unwrap_or_default(),
-                self.style.align.clone().unwrap_or_default(),
-                self.style.line_spacing.unwrap_or(1.0),
-            )
-        } else {
-            Self::draw_glyphs_horizontal(
-                scene,
-                &font,
-                self.style.font_size.unwrap_or(16.0),
-                &brush,
-                final_transform,
-                None,
-                self.style.fill_rule,
-                &self.text,
-                self.style.hint.unwrap_or_default(),
-                self.style.align.clone().unwrap_or_default(),
-                self.style.line_spacing.unwrap_or(1.0),
-            )
-        };
+        Self::draw_layout(
+            scene,
+            &font,
+            &layout,
+            &brush.multiply_alpha(alpha),
+            final_transform,
+            self.style.fill_rule,
+            hint,
+        );
+        if group {
+            scene.pop_layer();
+        }
 
         #[cfg(feature = "graph_debug")]
         {
@@ -77,12 +142,57 @@ impl TextDrawable {
                 &Brush::Solid(Color::from_rgba8(255, 0, 0, 255)),
                 // Some(final_transform),
                 None,
-                &_rect,
+                &layout.bounds,
             );
         }
         Ok(())
     }
 
+    /// the glyphs of `text` placed once per font, size and alignment,
+    /// later draws of the same string only encode them
+    pub fn layout(
+        font: &VelloFont,
+        text: &str,
+        size: f32,
+        vertical: bool,
+        align: TextAlign,
+        line_spacing: f32,
+    ) -> Arc<TextLayout> {
+        let id = TextLayoutId {
+            text: text.to_string(),
+            font_size: OrderedFloat(size),
+            line_spacing: OrderedFloat(line_spacing),
+            align,
+            vertical,
+        };
+        font.layout(id, |font| match vertical {
+            true => Self::layout_vertical(font, text, size, align, line_spacing),
+            false => Self::layout_horizontal(font, text, size, align, line_spacing),
+        })
+    }
+
+    fn draw_layout<'a>(
+        scene: &mut Scene,
+        font: &VelloFont,
+        layout: &TextLayout,
+        brush: impl Into<BrushRef<'a>>,
+        transform: Affine,
+        style: impl Into<StyleRef<'a>>,
+        hint: bool,
+    ) {
+        let brush = brush.into();
+        let style = style.into();
+        for run in &layout.runs {
+            scene
+                .draw_glyphs(&font.font)
+                .font_size(layout.font_size)
+                .transform(transform)
+                .brush(brush)
+                .hint(hint)
+                .draw(style, run.iter().copied());
+        }
+    }
+
     pub fn draw_glyphs_vertical<'a>(
         scene: &mut Scene,
         font: &VelloFont,
@@ -91,14 +201,35 @@ impl TextDrawable {
         transform: Affine,
         glyph_transform: Option<Affine>,
         style: impl Into<StyleRef<'a>>,
-        text: &String,
+        text: &str,
         hint: bool,
         align: TextAlign,
         line_spacing: f32,
     ) -> Rect {
-        let glyphs = font.glyph(text, size);
+        let layout = Self::layout(font, text, size, true, align, line_spacing);
         let brush = brush.into();
         let style = style.into();
+        for run in &layout.runs {
+            scene
+                .draw_glyphs(&font.font)
+                .font_size(size)
+                .transform(transform)
+                .glyph_transform(glyph_transform)
+                .brush(brush)
+                .hint(hint)
+                .draw(style, run.iter().copied());
+        }
+        layout.bounds
+    }
+
+    fn layout_vertical(
+        font: &VelloFont,
+        text: &str,
+        size: f32,
+        align: TextAlign,
+        line_spacing: f32,
+    ) -> TextLayout {
+        let glyphs = font.glyph(&text.to_string(), size);
         let mut glyphs_index = 0usize;
 
         let (line_height, baseline_offset) = glyphs
@@ -156,40 +287,27 @@ impl TextDrawable {
             pen_x += col_width;
         }
 
-        scene
-            .draw_glyphs(&font.font)
-            .font_size(size)
-            .transform(transform)
-            .glyph_transform(glyph_transform)
-            .brush(brush)
-            .hint(hint)
-            .draw(style, glyph_iter.into_iter());
-
         let width = (max_x - min_x + size) as f64;
         let height = max_y as f64;
-        Rect::from_origin_size(
-            graph_pt2!(min_x as _, 0.0),
-            Size::new(width as _, height as _),
-        )
+        TextLayout {
+            font_size: size,
+            runs: vec![glyph_iter],
+            bounds: Rect::from_origin_size(
+                graph_pt2!(min_x as _, 0.0),
+                Size::new(width as _, height as _),
+            ),
+        }
     }
 
-    fn draw_glyphs_horizontal<'a>(
-        scene: &mut Scene,
+    fn layout_horizontal(
         font: &VelloFont,
+        text: &str,
         size: f32,
-        brush: impl Into<BrushRef<'a>>,
-        transform: Affine,
-        glyph_transform: Option<Affine>,
-        style: impl Into<StyleRef<'a>>,
-        text: &String,
-        hint: bool,
         align: TextAlign,
         line_spacing: f32,
-    ) -> Rect {
-        let glyphs = font.glyph(text, size);
+    ) -> TextLayout {
+        let glyphs = font.glyph(&text.to_string(), size);
         let mut glyphs_index = 0usize;
-        let brush = brush.into();
-        let style = style.into();
         let (line_height, mut pen_y) = glyphs
             .get(0)
             .and_then(|gly| {
@@ -203,6 +321,7 @@ impl TextDrawable {
         let mut max_width = 0.0f32;
         let mut num_lines = 0;
         let mut min_x = 0.0f32;
+        let mut runs = Vec::new();
         for line in lines {
             let mut line_width = 0.0;
             num_lines += 1;
@@ -233,20 +352,20 @@ impl TextDrawable {
                 glyphs_index += 1;
             }
             max_width = max_width.max(line_width);
-            scene
-                .draw_glyphs(&font.font)
-                .font_size(size)
-                .transform(transform)
-                .glyph_transform(glyph_transform)
-                .brush(brush.clone())
-                .hint(hint)
-                .draw(style, glyph_iter.into_iter());
+            runs.push(glyph_iter);
             pen_y += line_height;
             // \n
             glyphs_index += 1;
         }
         let height = num_lines as f32 * line_height;
-        Rect::from_origin_size(graph_pt2!(0.0, 0.0), Size::new(max_width as _, height as _))
+        TextLayout {
+            font_size: size,
+            runs,
+            bounds: Rect::from_origin_size(
+                graph_pt2!(0.0, 0.0),
+                Size::new(max_width as _, height as _),
+            ),
+        }
     }
 }
 
@@ -272,8 +391,190 @@ impl Drawable for TextDrawable {
         style: &super::Style,
         font_mgr: FontManager,
         img_res: ImageManager,
+        _svg_mgr: super::SvgManager,
     ) -> anyhow::Result<()> {
         self.draw_text(scene, style, font_mgr, img_res)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+fn draw_test_text(text: &str, style: Style) -> Scene {
+    let mut scene = Scene::new();
+    TextDrawable {
+        position: Point::new(8.0, 8.0),
+        text: text.to_string(),
+        style,
+    }
+    .draw_text(
+        &mut scene,
+        &Style::default(),
+        FontManager::default(),
+        ImageManager::default(),
+    )
+    .unwrap();
+    scene
+}
+
+#[test]
+fn test_text_effects() {
+    use super::style::{SimpleColor, TextOutline, TextShadow};
+    let runs = |style: Style| {
+        draw_test_text("Hello\nWorld!", style)
+            .encoding()
+            .resources
+            .glyph_runs
+            .len()
+    };
+    let black = SimpleColor {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    let outline = TextOutline {
+        width: 2.0,
+        color: black.clone(),
+    };
+    let shadow = TextShadow {
+        dx: 2.0,
+        dy: 3.0,
+        blur: 0.0,
+        color: black,
+    };
+    // one run per line
+    assert_eq!(runs(Style::default()), 2);
+    assert_eq!(
+        runs(Style::default().with_outline(Some(outline.clone()))),
+        4
+    );
+    // the shadow has the outline in its silhouette too
+    let both = Style::default()
+        .with_outline(Some(outline))
+        .with_shadow(Some(shadow.clone()));
+    assert_eq!(runs(both.clone()), 8);
+    let blurred = TextShadow {
+        blur: 1.5,
+        ..shadow
+    };
+    assert_eq!(runs(Style::default().with_shadow(Some(blurred))), 20);
+    assert_eq!(runs(both.clone().with_visible(false)), 0);
+    assert_eq!(runs(both.with_opacity(0.5)), 8);
+
+    // laid out once, the default font keeps its cache between lookups
+    let font = FontManager::default().get("");
+    let first = TextDrawable::layout(&font, "Hello", 16.0, false, TextAlign::Left, 1.0);
+    let font = FontManager::default().get("");
+    let again = TextDrawable::layout(&font, "Hello", 16.0, false, TextAlign::Left, 1.0);
+    assert!(Arc::ptr_eq(&first, &again));
+    assert_eq!(first.runs[0].len(), 5);
+    let other = TextDrawable::layout(&font, "Hello", 16.0, false, TextAlign::Center, 1.0);
+    assert!(!Arc::ptr_eq(&first, &other));
+}
+
+#[test]
+#[ignore = "needs a gpu adapter"]
+fn test_text_outline_legible() {
+    use super::style::{CustomBrush, SimpleColor, TextOutline};
+    use pollster::FutureExt;
+    use vello::peniko::Color;
+    use vello::{AaConfig, AaSupport, RenderParams, Renderer, RendererOptions};
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .block_on()
+        .expect("no adapter found");
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .block_on()
+        .unwrap();
+    let mut renderer = Renderer::new(
+        &device,
+        RendererOptions {
+            use_cpu: false,
+            antialiasing_support: AaSupport::area_only(),
+            num_init_threads: std::num::NonZero::new(1),
+            pipeline_cache: None,
+        },
+    )
+    .unwrap();
+    let (width, height) = (256u32, 64u32);
+    // the darkest channel drawn, on a white background
+    let mut darkest = |style: Style| {
+        let scene = draw_test_text("Legible", style);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let params = RenderParams {
+            base_color: Color::WHITE,
+            width,
+            height,
+            antialiasing_method: AaConfig::Area,
+        };
+        renderer
+            .render_to_texture(&device, &queue, &scene, &view, &params)
+            .unwrap();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (width * height * 4) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    // 1024 bytes, already row aligned
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        queue.submit([encoder.finish()]);
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+        let pixels = slice.get_mapped_range();
+        pixels
+            .chunks_exact(4)
+            .map(|px| px[0].min(px[1]).min(px[2]))
+            .min()
+            .unwrap()
+    };
+    let light = Style::default()
+        .with_font_size(Some(32.0))
+        .with_fill(Some(CustomBrush::Color(SimpleColor {
+            r: 240,
+            g: 240,
+            b: 240,
+            a: 255,
+        })));
+    // light text alone all but vanishes on white
+    assert!(darkest(light.clone()) >= 200);
+    let outlined = light.with_outline(Some(TextOutline {
+        width: 2.0,
+        color: SimpleColor {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        },
+    }));
+    assert!(darkest(outlined) < 64);
+}
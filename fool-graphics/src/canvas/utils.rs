@@ -1,5 +1,6 @@
 use image::GenericImageView;
-use kurbo::{BezPath, Point};
+use kurbo::{Arc, BezPath, Point, Rect, Size, Vec2};
+use std::f64::consts::TAU;
 use vello::peniko::{Blob, Image as VelloImage, ImageFormat};
 
 pub fn add_circle_to_path(path: &mut BezPath, center: Point, radius: f64) {
@@ -33,6 +34,71 @@ pub fn add_circle_to_path(path: &mut BezPath, center: Point, radius: f64) {
     path.close_path();
 }
 
+/// a screen sized rect with a hole cut for every light, filled even-odd
+pub fn light_mask_path(screen_size: Size, lights: &[(Point, f64)]) -> BezPath {
+    let mut path = BezPath::new();
+    let rect = Rect::from_origin_size(Point::ORIGIN, screen_size);
+    path.move_to(rect.origin());
+    path.line_to(Point::new(rect.x1, rect.y0));
+    path.line_to(Point::new(rect.x1, rect.y1));
+    path.line_to(Point::new(rect.x0, rect.y1));
+    path.close_path();
+    for &(center, radius) in lights {
+        add_circle_to_path(&mut path, center, radius);
+    }
+    path
+}
+
+/// more than a full turn draws the full turn
+pub fn normalize_sweep(sweep_angle: f64) -> f64 {
+    sweep_angle.clamp(-TAU, TAU)
+}
+
+/// the part of a ring from `start_angle` over `sweep_angle`, a pie slice when
+/// `inner_radius` is 0, the inner edge runs back so a full ring keeps its hole
+pub fn ring_path(
+    center: Point,
+    outer_radius: f64,
+    inner_radius: f64,
+    start_angle: f64,
+    sweep_angle: f64,
+) -> BezPath {
+    let sweep_angle = normalize_sweep(sweep_angle);
+    let end_angle = start_angle + sweep_angle;
+    let at = |radius: f64, angle: f64| center + Vec2::from_angle(angle) * radius;
+    let arc = |radius: f64, start: f64, sweep: f64| {
+        Arc::new(center, Vec2::new(radius, radius), start, sweep, 0.0)
+    };
+    let mut path = BezPath::new();
+    path.move_to(at(outer_radius, start_angle));
+    path.extend(arc(outer_radius, start_angle, sweep_angle).append_iter(0.1));
+    match inner_radius > 0.0 {
+        true => {
+            path.line_to(at(inner_radius, end_angle));
+            path.extend(arc(inner_radius, end_angle, -sweep_angle).append_iter(0.1));
+        }
+        false => path.line_to(center),
+    }
+    path.close_path();
+    path
+}
+
+/// straight lines through `points`, back to the first one when `closed`
+pub fn polygon_path(points: &[Point], closed: bool) -> BezPath {
+    let mut path = BezPath::new();
+    let mut points = points.iter();
+    if let Some(first) = points.next() {
+        path.move_to(*first);
+    }
+    for point in points {
+        path.line_to(*point);
+    }
+    if closed && !path.is_empty() {
+        path.close_path();
+    }
+    path
+}
+
 pub fn load_image_from_file(path: &str) -> VelloImage {
     let img = image::open(path).expect("Failed to open image");
     let rgba = img.to_rgba8();
@@ -0,0 +1,149 @@
+use super::{Affine, Scene, SceneGraph};
+use vello::kurbo::{Point, Rect};
+
+/// what a view looks at, the position in scene units ends up in the center of its rect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub x: f64,
+    pub y: f64,
+    pub zoom: f64,
+    /// radians, the scene turns the other way
+    pub rotation: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+/// a region of the window showing a graph through its own camera, e.g. one player of
+/// a split screen. The views are encoded one after another into the same scene, each
+/// inside a clip layer with its camera transform, so the whole window is still one
+/// vello render and one blit, no texture per view
+#[derive(Debug, Clone, Copy)]
+pub struct View<'a> {
+    /// in window pixels
+    pub rect: Rect,
+    pub camera: Camera,
+    /// several views may show the same graph
+    pub graph: &'a SceneGraph,
+}
+
+impl<'a> View<'a> {
+    /// `rect` as x, y, width and height in 0..1 of the window `size`,
+    /// built again after a resize
+    pub fn normalized(
+        rect: (f64, f64, f64, f64),
+        size: (f64, f64),
+        camera: Camera,
+        graph: &'a SceneGraph,
+    ) -> Self {
+        let (x, y, w, h) = rect;
+        let (win_w, win_h) = size;
+        Self {
+            // snapped to whole pixels, two views sharing an edge don't overlap or leave a gap
+            rect: Rect::new(
+                (x * win_w).round(),
+                (y * win_h).round(),
+                ((x + w) * win_w).round(),
+                ((y + h) * win_h).round(),
+            ),
+            camera,
+            graph,
+        }
+    }
+    /// scene to window transform, the graph scale multiplies the zoom
+    pub fn transform(&self) -> Affine {
+        let camera = &self.camera;
+        let zoom = self.graph.scale.unwrap_or(1.0) * camera.zoom;
+        Affine::translate(self.rect.center().to_vec2())
+            * Affine::scale(zoom)
+            * Affine::rotate(-camera.rotation)
+            * Affine::translate((-camera.x, -camera.y))
+    }
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        self.rect.contains(Point::new(x, y))
+    }
+    pub fn screen_to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        let p = self.transform().inverse() * Point::new(x, y);
+        (p.x, p.y)
+    }
+    /// encodes the graph clipped to the rect, `overlay` draws more in the same space
+    /// before the clip is popped, e.g. debug shapes
+    pub fn draw(
+        &self,
+        scene: &mut Scene,
+        overlay: impl FnOnce(&mut Scene, Affine),
+    ) -> anyhow::Result<()> {
+        let transform = self.transform();
+        scene.push_layer(vello::peniko::Mix::Clip, 1.0, Affine::IDENTITY, &self.rect);
+        let result = self.graph.draw_with(scene, transform);
+        overlay(scene, transform);
+        scene.pop_layer();
+        result
+    }
+}
+
+/// the index of the view under a pointer, the later views are drawn over the earlier ones
+pub fn view_at(views: &[View], x: f64, y: f64) -> Option<usize> {
+    views.iter().rposition(|view| view.contains(x, y))
+}
+
+#[test]
+fn test_views() -> anyhow::Result<()> {
+    let mut graph = SceneGraph::default();
+    graph.set_root(super::SceneNode::rect(
+        Point::ZERO,
+        vello::kurbo::Size::new(10.0, 10.0),
+        &Default::default(),
+    ));
+    let size = (801.0, 600.0);
+    let left = Camera {
+        x: 100.0,
+        y: 50.0,
+        ..Default::default()
+    };
+    let right = Camera {
+        x: -20.0,
+        zoom: 2.0,
+        rotation: std::f64::consts::FRAC_PI_2,
+        ..Default::default()
+    };
+    let views = [
+        View::normalized((0.0, 0.0, 0.5, 1.0), size, left, &graph),
+        View::normalized((0.5, 0.0, 0.5, 1.0), size, right, &graph),
+    ];
+    // the odd width is split on a whole pixel shared by both
+    assert_eq!(views[0].rect, Rect::new(0.0, 0.0, 401.0, 600.0));
+    assert_eq!(views[1].rect, Rect::new(401.0, 0.0, 801.0, 600.0));
+    // each camera position sits in the center of its view
+    let round = |(x, y): (f64, f64)| ((x * 1e6).round() / 1e6, (y * 1e6).round() / 1e6);
+    assert_eq!(views[0].screen_to_world(200.5, 300.0), (100.0, 50.0));
+    assert_eq!(round(views[1].screen_to_world(601.0, 300.0)), (-20.0, 0.0));
+    // zoomed in twice and turned a quarter, right on the screen is down in the scene
+    assert_eq!(round(views[1].screen_to_world(621.0, 300.0)), (-20.0, 10.0));
+
+    assert_eq!(view_at(&views, 10.0, 10.0), Some(0));
+    assert_eq!(view_at(&views, 401.0, 10.0), Some(1));
+    assert_eq!(view_at(&views, 900.0, 10.0), None);
+
+    let mut scene = Scene::new();
+    let mut overlays = 0;
+    for view in &views {
+        view.draw(&mut scene, |_, _| overlays += 1)?;
+    }
+    assert_eq!(overlays, 2);
+    let encoding = scene.encoding();
+    // the same rect twice, each in its own clip
+    assert_eq!(encoding.n_clips, 4);
+    assert_eq!(encoding.n_open_clips, 0);
+    // the fill and stroke of the rect and the clip of each view
+    assert_eq!(encoding.n_paths, 6);
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use crate::render::FrameContext;
@@ -13,6 +15,8 @@ pub struct EguiRenderer {
     screen_descriptor: ScreenDescriptor,
     window: Arc<Window>,
     need_repaint: bool,
+    // digest of the last tessellated output
+    last_output: u64,
 }
 
 impl EguiRenderer {
@@ -48,14 +52,35 @@ impl EguiRenderer {
             },
             window: window,
             need_repaint: false,
+            last_output: 0,
         }
     }
+    /// a renderer for a new device, the font atlas is queued for upload again
+    pub fn rebuild(&mut self, device: &Device, output_color_format: TextureFormat) {
+        self.renderer = Renderer::new(device, output_color_format, None, 1, true);
+        let ctx = self.state.egui_ctx();
+        let font_image = ctx.fonts(|fonts| fonts.image());
+        let delta = egui::epaint::ImageDelta::full(
+            font_image,
+            egui::epaint::TextureAtlas::texture_options(),
+        );
+        ctx.tex_manager()
+            .write()
+            .set(egui::TextureId::default(), delta);
+    }
     pub fn resize(&mut self, width: u32, height: u32) {
         self.screen_descriptor.size_in_pixels = [width, height];
     }
+    /// gui points per window pixel, from the next frame on
+    pub fn set_ui_scale(&mut self, scale: f32) {
+        if scale != self.screen_descriptor.pixels_per_point {
+            self.screen_descriptor.pixels_per_point = scale;
+            self.need_repaint = true;
+        }
+    }
     pub fn handle_event(&mut self, event: &WindowEvent) {
         let response = self.state.on_window_event(&self.window, event);
-        self.need_repaint = response.repaint
+        self.need_repaint |= response.repaint
     }
     pub fn begin_frame(&mut self) {
         let raw_input = self.state.take_egui_input(&self.window);
@@ -63,7 +88,8 @@ impl EguiRenderer {
         ctx.begin_pass(raw_input);
     }
 
-    pub fn end_frame(&mut self, ctx: &mut FrameContext) {
+    /// true when egui asked for a repaint or draws something else than last frame
+    pub fn end_frame(&mut self, ctx: &mut FrameContext) -> bool {
         self.context()
             .set_pixels_per_point(self.screen_descriptor.pixels_per_point);
 
@@ -72,10 +98,19 @@ impl EguiRenderer {
         self.state
             .handle_platform_output(&self.window, full_output.platform_output);
 
+        let repaint = std::mem::take(&mut self.need_repaint)
+            || !full_output.textures_delta.is_empty()
+            || full_output
+                .viewport_output
+                .values()
+                .any(|viewport| viewport.repaint_delay.is_zero());
         let tris = self
             .state
             .egui_ctx()
             .tessellate(full_output.shapes, self.state.egui_ctx().pixels_per_point());
+        let output = output_digest(&tris);
+        let changed = repaint || output.is_none() || output != Some(self.last_output);
+        self.last_output = output.unwrap_or_default();
         for (id, image_delta) in &full_output.textures_delta.set {
             self.renderer
                 .update_texture(&ctx.device, &ctx.queue, *id, image_delta);
@@ -108,5 +143,26 @@ impl EguiRenderer {
         for x in &full_output.textures_delta.free {
             self.renderer.free_texture(x)
         }
+        changed
+    }
+}
+
+/// None for paint callbacks, they may draw anything and always count as a change
+fn output_digest(primitives: &[egui::ClippedPrimitive]) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    for primitive in primitives {
+        let rect = primitive.clip_rect;
+        [rect.min.x, rect.min.y, rect.max.x, rect.max.y]
+            .map(f32::to_bits)
+            .hash(&mut hasher);
+        match &primitive.primitive {
+            egui::epaint::Primitive::Mesh(mesh) => {
+                mesh.texture_id.hash(&mut hasher);
+                mesh.indices.hash(&mut hasher);
+                bytemuck::cast_slice::<_, u8>(&mesh.vertices).hash(&mut hasher);
+            }
+            egui::epaint::Primitive::Callback(_) => return None,
+        }
     }
+    Some(hasher.finish())
 }
@@ -2,18 +2,35 @@ pub mod canvas;
 pub mod gui;
 pub mod render;
 pub mod scheduler;
-use crate::render::FrameContext;
+pub mod video;
+use crate::canvas::{Affine, View};
+use crate::render::{FrameContext, FrameStatus};
 use gui::EguiRenderer;
+use parking_lot::Mutex;
 use render::VelloRender;
-pub use scheduler::Scheduler;
+pub use render::{
+    AdaptiveQuality, EffectStack, GpuInfo, QUALITY_LADDER, QualityLevel, parse_quality,
+};
+pub use scheduler::{FixedStep, Scheduler};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
+pub use vello::AaConfig;
+use vello::peniko::Color;
 use winit::event::WindowEvent;
 use winit::window::Window;
 pub struct GraphRender {
     vello: VelloRender,
     egui: EguiRenderer,
     frame: Option<FrameContext>,
+    on_device_recreated: Vec<Box<dyn FnMut() + Send>>,
+    effects: Arc<Mutex<EffectStack>>,
+    last_frame: Instant,
+    quality: Arc<Mutex<AdaptiveQuality>>,
+    // the rung of the ladder the renderer is set to
+    quality_rung: Option<usize>,
+    // the aa to go back to while pixel perfect
+    pixel_perfect: Option<AaConfig>,
 }
 
 impl GraphRender {
@@ -29,30 +46,158 @@ impl GraphRender {
             vello,
             egui,
             frame: None,
+            on_device_recreated: Vec::new(),
+            effects: Default::default(),
+            last_frame: Instant::now(),
+            quality: Default::default(),
+            quality_rung: None,
+            pixel_perfect: None,
         })
     }
 
     pub fn draw_scene(&mut self, scene: &vello::Scene) -> anyhow::Result<()> {
         self.vello.draw_scene(scene)
     }
+    /// one scene with every view clipped to its rect, see `View`,
+    /// a single full window view is cheaper drawn by the graph itself
+    pub fn render_views(&mut self, views: &[View]) -> anyhow::Result<()> {
+        self.render_views_with(views, |_, _, _| {})
+    }
+    /// `overlay` draws more into each view with its transform, e.g. debug shapes
+    pub fn render_views_with(
+        &mut self,
+        views: &[View],
+        mut overlay: impl FnMut(usize, &mut vello::Scene, Affine),
+    ) -> anyhow::Result<()> {
+        let mut scene = vello::Scene::new();
+        let drawn = views.iter().enumerate().try_for_each(|(index, view)| {
+            view.draw(&mut scene, |scene, transform| {
+                overlay(index, scene, transform)
+            })
+        });
+        // the views drawn before a failed one still show
+        self.draw_scene(&scene)?;
+        drawn
+    }
 
-    pub fn begin_frame(&mut self) -> anyhow::Result<()> {
-        self.frame.replace(self.vello.begin_frame()?);
+    /// returns false when the frame is skipped, e.g. the window is minimized
+    /// or the gpu device is being recovered
+    pub fn begin_frame(&mut self) -> anyhow::Result<bool> {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        let status = {
+            let mut effects = self.effects.lock();
+            effects.advance(dt);
+            self.vello.begin_frame(Some(&effects))?
+        };
+        if self.vello.take_recreated() {
+            self.egui
+                .rebuild(&self.vello.device_handle().device, self.vello.format());
+            for callback in &mut self.on_device_recreated {
+                callback();
+            }
+        }
+        let FrameStatus::Ready(frame) = status else {
+            self.quality.lock().frame_skipped();
+            return Ok(false);
+        };
+        self.frame.replace(frame);
         self.egui.begin_frame();
-        Ok(())
+        Ok(true)
     }
-    pub fn end_frame(&mut self, capture_to: Option<impl Into<PathBuf>>) -> anyhow::Result<()> {
+    /// Ok(false) when neither the scene, the effects nor the gui changed
+    /// and the frame was not presented
+    pub fn end_frame(&mut self, capture_to: Option<impl Into<PathBuf>>) -> anyhow::Result<bool> {
         if let Some(mut frame_ctx) = self.frame.take() {
-            self.egui.end_frame(&mut frame_ctx);
-            self.vello.end_frame(frame_ctx, capture_to);
-            Ok(())
+            let gui_changed = self.egui.end_frame(&mut frame_ctx);
+            let presented = self.vello.end_frame(frame_ctx, capture_to, gui_changed);
+            self.update_quality(presented);
+            Ok(presented)
         } else {
             Err(anyhow::anyhow!("call begin_frame first!"))
         }
     }
+    /// called after a lost device was rebuilt, textures uploaded before are gone
+    pub fn on_device_recreated(&mut self, callback: impl FnMut() + Send + 'static) {
+        self.on_device_recreated.push(Box::new(callback));
+    }
+    /// the post processing effects, shared with the scripts
+    pub fn effects(&self) -> Arc<Mutex<EffectStack>> {
+        self.effects.clone()
+    }
+    /// the adaptive quality controller, shared with the scripts
+    pub fn quality(&self) -> Arc<Mutex<AdaptiveQuality>> {
+        self.quality.clone()
+    }
+    // an unchanged frame that was not presented says nothing about the gpu
+    fn update_quality(&mut self, presented: bool) {
+        let mut quality = self.quality.lock();
+        match presented {
+            true => {
+                quality.frame_presented(Instant::now());
+            }
+            false => quality.frame_skipped(),
+        }
+        // the floor may have been raised from a script
+        if quality.enabled() && self.quality_rung != Some(quality.rung()) {
+            self.quality_rung = Some(quality.rung());
+            self.vello.set_quality(quality.level());
+            // the render scale may still go down, the edges stay hard
+            if self.pixel_perfect.is_some() {
+                self.vello.set_aa(AaConfig::Area);
+            }
+        }
+    }
+    /// frames dropped because nothing changed
+    pub fn skipped_frames(&self) -> u64 {
+        self.vello.skipped_frames()
+    }
+    /// the anti-aliasing of the scene from the next frame on,
+    /// unsupported methods fall back to a supported one which is returned,
+    /// the adaptive quality overrides it on its next step
+    pub fn set_aa(&mut self, mode: AaConfig) -> AaConfig {
+        self.vello.set_aa(mode)
+    }
+    pub fn aa(&self) -> AaConfig {
+        self.vello.aa()
+    }
+    /// for pixel art, the cheapest aa instead of msaa, the one set before comes back
+    /// when turned off. Nearest sampling and whole pixel translations are drawn by
+    /// the graph, see `SceneGraph::set_pixel_perfect`
+    pub fn set_pixel_perfect(&mut self, enabled: bool) {
+        match (enabled, self.pixel_perfect) {
+            (true, None) => {
+                self.pixel_perfect = Some(self.vello.aa());
+                self.vello.set_aa(AaConfig::Area);
+            }
+            (false, Some(aa)) => {
+                self.pixel_perfect = None;
+                self.vello.set_aa(aa);
+            }
+            _ => {}
+        }
+    }
+    pub fn pixel_perfect(&self) -> bool {
+        self.pixel_perfect.is_some()
+    }
+    /// what the scene is drawn over, black until set
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.vello.set_clear_color(color);
+    }
+    pub fn clear_color(&self) -> Color {
+        self.vello.clear_color()
+    }
+    pub fn gpu_info(&self) -> GpuInfo {
+        self.vello.gpu_info()
+    }
     pub fn gui_context(&self) -> &egui::Context {
         self.egui.context()
     }
+    /// scales the gui, 1.0 draws a point per window pixel
+    pub fn set_ui_scale(&mut self, scale: f32) {
+        self.egui.set_ui_scale(scale);
+    }
     pub fn resize(&mut self, w: u32, h: u32) {
         self.vello.resize(w, h);
         self.egui.resize(w, h);
@@ -0,0 +1,94 @@
+use vello::{AaConfig, AaSupport};
+
+// cheapest first
+const METHODS: [AaConfig; 3] = [AaConfig::Area, AaConfig::Msaa8, AaConfig::Msaa16];
+
+/// "area", "msaa8" or "msaa16"
+pub fn parse_aa(name: &str) -> Option<AaConfig> {
+    match name.to_ascii_lowercase().as_str() {
+        "area" => Some(AaConfig::Area),
+        "msaa8" => Some(AaConfig::Msaa8),
+        "msaa16" => Some(AaConfig::Msaa16),
+        _ => None,
+    }
+}
+pub fn aa_name(config: AaConfig) -> &'static str {
+    match config {
+        AaConfig::Area => "area",
+        AaConfig::Msaa8 => "msaa8",
+        AaConfig::Msaa16 => "msaa16",
+    }
+}
+
+/// the anti-aliasing method of the scene, limited to the ones the renderer was built for
+#[derive(Debug, Clone, Copy)]
+pub struct Antialiasing {
+    support: AaSupport,
+    config: AaConfig,
+}
+
+impl Antialiasing {
+    pub fn new(support: AaSupport) -> Self {
+        let mut aa = Self {
+            support,
+            config: AaConfig::Msaa16,
+        };
+        aa.set(AaConfig::Msaa16);
+        aa
+    }
+    pub fn config(&self) -> AaConfig {
+        self.config
+    }
+    pub fn supports(&self, config: AaConfig) -> bool {
+        match config {
+            AaConfig::Area => self.support.area,
+            AaConfig::Msaa8 => self.support.msaa8,
+            AaConfig::Msaa16 => self.support.msaa16,
+        }
+    }
+    /// returns the method used, an unsupported one falls back to the closest cheaper one,
+    /// or the cheapest there is
+    pub fn set(&mut self, config: AaConfig) -> AaConfig {
+        let wanted = METHODS.iter().position(|m| *m == config).unwrap_or(0);
+        let cheaper = METHODS[..=wanted].iter().rev();
+        let fallback = cheaper.chain(&METHODS[wanted + 1..]);
+        match fallback.copied().find(|m| self.supports(*m)) {
+            Some(used) => {
+                if used != config {
+                    log::warn!(
+                        "antialiasing {} is not supported, use {}",
+                        aa_name(config),
+                        aa_name(used)
+                    );
+                }
+                self.config = used;
+            }
+            None => log::warn!("renderer supports no antialiasing method"),
+        }
+        self.config
+    }
+}
+
+#[test]
+fn test_antialiasing() {
+    let mut aa = Antialiasing::new(AaSupport::all());
+    assert_eq!(aa.config(), AaConfig::Msaa16);
+    for name in ["area", "msaa8", "MSAA16"] {
+        let config = parse_aa(name).unwrap();
+        assert_eq!(aa.set(config), config);
+        assert_eq!(aa.config(), config);
+        assert_eq!(aa_name(config), name.to_lowercase());
+    }
+    assert_eq!(parse_aa("fxaa"), None);
+
+    let mut aa = Antialiasing::new(AaSupport::area_only());
+    assert_eq!(aa.config(), AaConfig::Area);
+    assert_eq!(aa.set(AaConfig::Msaa8), AaConfig::Area);
+    let mut aa = Antialiasing::new(AaSupport {
+        area: false,
+        msaa8: true,
+        msaa16: false,
+    });
+    assert_eq!(aa.set(AaConfig::Area), AaConfig::Msaa8);
+    assert_eq!(aa.set(AaConfig::Msaa16), AaConfig::Msaa8);
+}
@@ -0,0 +1,167 @@
+use super::PostFxUniforms;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
+use vello::Scene;
+use vello::peniko::Style;
+use vello_encoding::Patch;
+
+/// hashes everything the renderer reads from an encoded scene,
+/// images and fonts by their blob id rather than their bytes
+pub fn scene_digest(scene: &Scene) -> u64 {
+    let encoding = scene.encoding();
+    let mut hasher = DefaultHasher::new();
+    bytemuck::cast_slice::<_, u8>(&encoding.path_tags).hash(&mut hasher);
+    encoding.path_data.hash(&mut hasher);
+    bytemuck::cast_slice::<_, u8>(&encoding.draw_tags).hash(&mut hasher);
+    encoding.draw_data.hash(&mut hasher);
+    bytemuck::cast_slice::<_, u8>(&encoding.transforms).hash(&mut hasher);
+    bytemuck::cast_slice::<_, u8>(&encoding.styles).hash(&mut hasher);
+    let resources = &encoding.resources;
+    for patch in &resources.patches {
+        match patch {
+            Patch::Ramp {
+                draw_data_offset,
+                stops,
+                extend,
+            } => (0u8, draw_data_offset, stops, discriminant(extend)).hash(&mut hasher),
+            Patch::GlyphRun { index } => (1u8, index).hash(&mut hasher),
+            Patch::Image {
+                draw_data_offset,
+                image,
+            } => {
+                (2u8, draw_data_offset, image.data.id()).hash(&mut hasher);
+                (image.width, image.height, image.alpha.to_bits()).hash(&mut hasher);
+                discriminant(&image.format).hash(&mut hasher);
+                discriminant(&image.quality).hash(&mut hasher);
+                discriminant(&image.x_extend).hash(&mut hasher);
+                discriminant(&image.y_extend).hash(&mut hasher);
+            }
+        }
+    }
+    for stop in &resources.color_stops {
+        stop.offset.to_bits().hash(&mut hasher);
+        stop.color.components.map(f32::to_bits).hash(&mut hasher);
+        discriminant(&stop.color.cs).hash(&mut hasher);
+    }
+    for glyph in &resources.glyphs {
+        (glyph.id, glyph.x.to_bits(), glyph.y.to_bits()).hash(&mut hasher);
+    }
+    for run in &resources.glyph_runs {
+        (run.font.data.id(), run.font.index, run.font_size.to_bits()).hash(&mut hasher);
+        (run.hint, &run.glyphs, &run.normalized_coords).hash(&mut hasher);
+        bytemuck::bytes_of(&run.transform).hash(&mut hasher);
+        run.glyph_transform
+            .as_ref()
+            .map(bytemuck::bytes_of)
+            .hash(&mut hasher);
+        match &run.style {
+            Style::Fill(fill) => (0u8, *fill as u8).hash(&mut hasher),
+            Style::Stroke(stroke) => {
+                (1u8, stroke.width.to_bits(), stroke.miter_limit.to_bits()).hash(&mut hasher);
+                discriminant(&stroke.join).hash(&mut hasher);
+                discriminant(&stroke.start_cap).hash(&mut hasher);
+                discriminant(&stroke.end_cap).hash(&mut hasher);
+                stroke.dash_offset.to_bits().hash(&mut hasher);
+                for dash in stroke.dash_pattern.iter() {
+                    dash.to_bits().hash(&mut hasher);
+                }
+            }
+        }
+    }
+    resources.normalized_coords.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// what ends up on screen, a frame with the same key as the presented one is not submitted
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameKey {
+    pub scene: u64,
+    pub effects: Option<PostFxUniforms>,
+    pub size: (u32, u32),
+}
+
+#[derive(Debug, Default)]
+pub struct FrameTracker {
+    presented: Option<FrameKey>,
+    pending: FrameKey,
+    skipped: u64,
+}
+
+impl FrameTracker {
+    pub fn begin(&mut self, size: (u32, u32), effects: Option<PostFxUniforms>) {
+        self.pending = FrameKey {
+            size,
+            effects,
+            ..Default::default()
+        };
+    }
+    /// false when the render target still holds this scene from the presented frame
+    pub fn set_scene(&mut self, scene: &Scene) -> bool {
+        self.pending.scene = scene_digest(scene);
+        !self
+            .presented
+            .is_some_and(|p| p.scene == self.pending.scene && p.size == self.pending.size)
+    }
+    pub fn is_unchanged(&self) -> bool {
+        self.presented == Some(self.pending)
+    }
+    /// whether the frame gets submitted, `force` for gui changes and captures
+    pub fn finish(&mut self, force: bool) -> bool {
+        if !force && self.is_unchanged() {
+            self.skipped += 1;
+            return false;
+        }
+        self.presented = Some(self.pending);
+        true
+    }
+    /// the render target was lost, the next frame is drawn in full
+    pub fn invalidate(&mut self) {
+        self.presented = None;
+    }
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+#[test]
+fn test_frame_tracker() {
+    use crate::canvas::{SceneGraph, SceneNode, Style};
+    use kurbo::{Point, Size};
+    let build = |size: f64| {
+        let mut graph = SceneGraph::default();
+        graph.set_root(SceneNode::rect(
+            Point::ZERO,
+            Size::new(size, size),
+            &Style::default(),
+        ));
+        let mut scene = Scene::new();
+        graph.draw(&mut scene).unwrap();
+        scene
+    };
+    let mut tracker = FrameTracker::default();
+    tracker.begin((800, 600), None);
+    assert!(tracker.set_scene(&build(10.0)));
+    assert!(tracker.finish(false));
+    // an identical build is neither rendered nor submitted
+    tracker.begin((800, 600), None);
+    assert!(!tracker.set_scene(&build(10.0)));
+    assert!(tracker.is_unchanged());
+    assert!(!tracker.finish(false));
+    assert_eq!(tracker.skipped(), 1);
+    // a gui change still submits, reusing the rendered scene
+    tracker.begin((800, 600), None);
+    assert!(!tracker.set_scene(&build(10.0)));
+    assert!(tracker.finish(true));
+
+    tracker.begin((800, 600), None);
+    assert!(tracker.set_scene(&build(20.0)));
+    assert!(tracker.finish(false));
+    tracker.begin((640, 480), None);
+    assert!(tracker.set_scene(&build(20.0)));
+    assert!(tracker.finish(false));
+    tracker.invalidate();
+    tracker.begin((640, 480), None);
+    assert!(tracker.set_scene(&build(20.0)));
+    assert!(!tracker.is_unchanged());
+}
@@ -1,4 +1,6 @@
+use super::quality::scaled_size;
 use pollster::FutureExt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{num::NonZero, sync::Arc};
 use vello::{
     AaSupport, Renderer, RendererOptions,
@@ -11,6 +13,45 @@ pub struct ContextRender {
     pub context: RenderContext,
     pub renderer: Renderer,
     pub surface: Box<RenderSurface<'static>>,
+    /// the anti-aliasing methods the renderer has pipelines for
+    pub aa_support: AaSupport,
+    window: Arc<Window>,
+    size: SurfaceSize,
+    device_lost: Arc<AtomicBool>,
+    // the target texture is this fraction of the surface, the blit scales it up
+    render_scale: f64,
+}
+
+pub fn is_empty_size(width: u32, height: u32) -> bool {
+    width == 0 || height == 0
+}
+
+/// the size the surface is configured at, a minimized window's zero size can't be
+/// configured, the last real one is kept and the frames suspended until restored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceSize {
+    pub width: u32,
+    pub height: u32,
+    pub suspended: bool,
+}
+
+impl SurfaceSize {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            suspended: is_empty_size(width, height),
+        }
+    }
+    /// the size to configure the surface with, None while suspended
+    pub fn resize(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        self.suspended = is_empty_size(width, height);
+        if self.suspended {
+            return None;
+        }
+        (self.width, self.height) = (width, height);
+        Some((width, height))
+    }
 }
 
 impl ContextRender {
@@ -39,28 +80,113 @@ impl ContextRender {
             desired_maximum_frame_latency: 2,
         };
         surface.config = copyable_config;
+        let aa_support = AaSupport::all();
         let renderer = Renderer::new(
             &context.devices[surface.dev_id].device,
             RendererOptions {
                 use_cpu: false,
-                antialiasing_support: AaSupport::all(),
+                antialiasing_support: aa_support,
                 num_init_threads: NonZero::new(1),
                 pipeline_cache: None,
             },
         )
         .expect("Failed to create renderer");
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost = device_lost.clone();
+        context.devices[surface.dev_id]
+            .device
+            .set_device_lost_callback(move |reason, msg| {
+                log::error!("gpu device lost({:?}): {}", reason, msg);
+                lost.store(true, Ordering::Release);
+            });
         Ok(Self {
             context,
             renderer,
             surface: Box::new(surface),
+            aa_support,
+            window,
+            size: SurfaceSize::new(size.width, size.height),
+            device_lost,
+            render_scale: 1.0,
         })
     }
+    /// a new device, renderer and surface for the same window
+    pub fn rebuild(&self) -> anyhow::Result<Self> {
+        let mut context = Self::new(self.window.clone())?;
+        context.set_render_scale(self.render_scale);
+        Ok(context)
+    }
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+    pub fn mark_device_lost(&self) {
+        self.device_lost.store(true, Ordering::Release);
+    }
     pub fn format(&self) -> TextureFormat {
         self.surface.format
     }
+    /// a zero sized surface can't be configured, keep the old one until restored
     pub fn resize(&mut self, width: u32, height: u32) {
+        let Some((width, height)) = self.size.resize(width, height) else {
+            return;
+        };
+        self.context
+            .resize_surface(&mut self.surface, width, height);
+        self.create_targets();
+    }
+    pub fn is_suspended(&self) -> bool {
+        self.size.suspended
+    }
+    /// reconfigure with the current size after the surface got lost or outdated
+    pub fn reconfigure(&mut self) {
+        let (width, height) = (self.surface.config.width, self.surface.config.height);
         self.context
             .resize_surface(&mut self.surface, width, height);
+        self.create_targets();
+    }
+    /// returns false when it was already at `scale`
+    pub fn set_render_scale(&mut self, scale: f64) -> bool {
+        let scale = scale.clamp(0.1, 1.0);
+        if scale == self.render_scale {
+            return false;
+        }
+        self.render_scale = scale;
+        self.create_targets();
+        true
+    }
+    pub fn render_scale(&self) -> f64 {
+        self.render_scale
+    }
+    /// the size the scene is rendered at
+    pub fn target_size(&self) -> (u32, u32) {
+        let config = &self.surface.config;
+        scaled_size(config.width, config.height, self.render_scale)
+    }
+    // resize_surface creates the targets at the full size, what vello does with the scaled size
+    fn create_targets(&mut self) {
+        let (width, height) = self.target_size();
+        let size = self.surface.target_texture.size();
+        if (size.width, size.height) == (width, height) {
+            return;
+        }
+        let device = &self.context.devices[self.surface.dev_id].device;
+        let target_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            format: TextureFormat::Rgba8Unorm,
+            view_formats: &[],
+        });
+        self.surface.target_view =
+            target_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.surface.target_texture = target_texture;
     }
     pub fn device_handle(&self) -> &DeviceHandle {
         &self.context.devices[self.surface.dev_id]
@@ -0,0 +1,37 @@
+use serde::Serialize;
+
+/// adapter details for diagnostics and bug reports
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GpuInfo {
+    pub name: String,
+    pub backend: String,
+    pub driver: String,
+    pub driver_info: String,
+    pub device_type: String,
+}
+
+impl From<wgpu::AdapterInfo> for GpuInfo {
+    fn from(info: wgpu::AdapterInfo) -> Self {
+        Self {
+            name: info.name,
+            backend: info.backend.to_str().to_owned(),
+            driver: info.driver,
+            driver_info: info.driver_info,
+            device_type: format!("{:?}", info.device_type),
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs a gpu adapter"]
+fn test_gpu_info() {
+    use pollster::FutureExt;
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .block_on()
+        .expect("no adapter found");
+    let info = GpuInfo::from(adapter.get_info());
+    assert!(!info.name.is_empty());
+    assert!(!info.backend.is_empty());
+}
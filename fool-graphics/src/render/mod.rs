@@ -2,29 +2,122 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 use vello::AaConfig;
+use vello::kurbo::Affine;
+use vello::peniko::Color;
 use vello::{Scene, peniko::color::palette, util::DeviceHandle};
 use winit::window::Window;
+mod aa;
 mod capture;
+mod change;
 mod context;
 mod frame;
+mod info;
+mod postfx;
+mod quality;
+pub use aa::{Antialiasing, aa_name, parse_aa};
 use capture::FrameCapture;
+use change::FrameTracker;
 use context::ContextRender;
+#[cfg(test)]
+use context::{SurfaceSize, is_empty_size};
 pub use frame::FrameContext;
+pub use info::GpuInfo;
+pub use postfx::{
+    ChromaticAberration, ColorGrade, EffectStack, Fade, PostFxUniforms, PostProcess, Vignette,
+};
+pub use quality::{AdaptiveQuality, QUALITY_LADDER, QualityLevel, parse_quality};
+/// lost and outdated surfaces come back after a reconfigure
+fn is_recoverable(err: &wgpu::SurfaceError) -> bool {
+    matches!(err, wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SurfaceAction {
+    Reconfigure,
+    Skip,
+    RebuildDevice,
+    Fatal,
+}
+
+fn classify(err: &wgpu::SurfaceError) -> SurfaceAction {
+    match err {
+        err if is_recoverable(err) => SurfaceAction::Reconfigure,
+        wgpu::SurfaceError::Timeout => SurfaceAction::Skip,
+        // generic failures are usually a lost device, the error callback has the details
+        wgpu::SurfaceError::Other => SurfaceAction::RebuildDevice,
+        _ => SurfaceAction::Fatal,
+    }
+}
+
+/// what `begin_frame` does before asking the surface for a texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameGate {
+    Skip,
+    RebuildDevice,
+    Acquire,
+}
+
+/// nothing is drawn while minimized, a lost device is rebuilt once restored
+fn frame_gate(suspended: bool, device_lost: bool) -> FrameGate {
+    match (suspended, device_lost) {
+        (true, _) => FrameGate::Skip,
+        (false, true) => FrameGate::RebuildDevice,
+        (false, false) => FrameGate::Acquire,
+    }
+}
+
+/// what `begin_frame` got from the surface
+pub enum FrameStatus {
+    Ready(FrameContext),
+    /// nothing to draw to this frame, not an error
+    Skipped,
+}
+
 pub struct VelloRender {
     context: ContextRender,
     frame_buffer: Option<FrameCapture>,
+    recreated: bool,
+    // built on the first frame with effects
+    postfx: Option<PostProcess>,
+    frames: FrameTracker,
+    aa: Antialiasing,
+    // what the scene is drawn over
+    clear_color: Color,
+    // the scene scaled down to a reduced render scale
+    scaled: Scene,
 }
 
 impl VelloRender {
     pub fn new(window: Arc<Window>) -> anyhow::Result<Self> {
         let context = ContextRender::new(window)
             .map_err(|err| anyhow::anyhow!("Failed to create vello surface:{}", err))?;
+        let aa = Antialiasing::new(context.aa_support);
         Ok(Self {
             context,
             frame_buffer: None,
+            recreated: false,
+            postfx: None,
+            frames: FrameTracker::default(),
+            aa,
+            clear_color: palette::css::BLACK,
+            scaled: Scene::new(),
         })
     }
+    /// an unchanged scene is still in the target texture and not rendered again
     pub fn draw_scene(&mut self, scene: &Scene) -> anyhow::Result<()> {
+        if self.context.is_device_lost() || !self.frames.set_scene(scene) {
+            return Ok(());
+        }
+        let params = self.render_params();
+        let scale = self.context.render_scale();
+        let scene = match scale < 1.0 {
+            true => {
+                self.scaled.reset();
+                self.scaled.append(scene, Some(Affine::scale(scale)));
+                &self.scaled
+            }
+            false => scene,
+        };
         let context = &mut self.context;
         let surface = &mut context.surface;
         let device_handle = &context.context.devices[surface.dev_id];
@@ -33,20 +126,39 @@ impl VelloRender {
             &device_handle.queue,
             scene,
             &surface.target_view,
-            &vello::RenderParams {
-                base_color: palette::css::BLACK,
-                width: surface.config.width,
-                height: surface.config.height,
-                antialiasing_method: AaConfig::Msaa16,
-            },
+            &params,
         )?;
         Ok(())
     }
-    pub fn begin_frame(&mut self) -> anyhow::Result<FrameContext> {
+    /// what the next `draw_scene` renders with
+    pub fn render_params(&self) -> vello::RenderParams {
+        let (width, height) = self.context.target_size();
+        vello::RenderParams {
+            base_color: self.clear_color,
+            width,
+            height,
+            antialiasing_method: self.aa.config(),
+        }
+    }
+    /// skipped while minimized, on acquire timeouts and while recovering the device,
+    /// `effects` replaces the plain blit with the post processing pass
+    pub fn begin_frame(&mut self, effects: Option<&EffectStack>) -> anyhow::Result<FrameStatus> {
+        match frame_gate(self.context.is_suspended(), self.context.is_device_lost()) {
+            FrameGate::Skip => return Ok(FrameStatus::Skipped),
+            FrameGate::RebuildDevice => {
+                self.rebuild_device()?;
+                return Ok(FrameStatus::Skipped);
+            }
+            FrameGate::Acquire => {}
+        }
+        let surface_texture = match self.acquire(true)? {
+            Some(texture) => texture,
+            None => return Ok(FrameStatus::Skipped),
+        };
+        let postfx = &mut self.postfx;
         let context = &mut self.context;
         let surface = &mut context.surface;
         let device_handle = &context.context.devices[surface.dev_id];
-        let surface_texture = surface.surface.get_current_texture()?;
 
         let final_view = surface_texture
             .texture
@@ -57,21 +169,91 @@ impl VelloRender {
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("Main Encoder"),
                 });
-        surface.blitter.copy(
-            &device_handle.device,
-            &mut encoder,
-            &surface.target_view,
-            &final_view,
-        );
-        Ok(FrameContext {
+        let (width, height) = (surface.config.width, surface.config.height);
+        let effects = effects
+            .filter(|effects| !effects.is_empty())
+            .map(|effects| effects.uniforms(width, height));
+        self.frames.begin((width, height), effects);
+        match effects {
+            Some(uniforms) => {
+                postfx
+                    .get_or_insert_with(|| PostProcess::new(&device_handle.device, surface.format))
+                    .draw(
+                        &device_handle.device,
+                        &device_handle.queue,
+                        &mut encoder,
+                        &surface.target_view,
+                        &final_view,
+                        &uniforms,
+                    );
+            }
+            None => surface.blitter.copy(
+                &device_handle.device,
+                &mut encoder,
+                &surface.target_view,
+                &final_view,
+            ),
+        }
+        Ok(FrameStatus::Ready(FrameContext {
             encoder,
             device: device_handle.device.clone(),
             queue: device_handle.queue.clone(),
             target_view: final_view,
             surface_texture,
-        })
+        }))
+    }
+    fn acquire(&mut self, retry: bool) -> anyhow::Result<Option<wgpu::SurfaceTexture>> {
+        let err = match self.context.surface.surface.get_current_texture() {
+            Ok(texture) => return Ok(Some(texture)),
+            Err(err) => err,
+        };
+        match classify(&err) {
+            SurfaceAction::Reconfigure if retry => {
+                log::warn!("surface {}, reconfigure and retry", err);
+                self.context.reconfigure();
+                self.acquire(false)
+            }
+            // still lost after a reconfigure, try again next frame
+            SurfaceAction::Reconfigure | SurfaceAction::Skip => {
+                log::warn!("surface {}, frame skipped", err);
+                Ok(None)
+            }
+            SurfaceAction::RebuildDevice => {
+                log::warn!("surface {}, rebuild device", err);
+                self.context.mark_device_lost();
+                Ok(None)
+            }
+            SurfaceAction::Fatal => Err(err.into()),
+        }
     }
-    pub fn end_frame(&mut self, ctx: FrameContext, capture_to: Option<impl Into<PathBuf>>) {
+    fn rebuild_device(&mut self) -> anyhow::Result<()> {
+        log::warn!("rebuilding gpu device and renderer");
+        self.frame_buffer = None;
+        self.postfx = None;
+        self.frames.invalidate();
+        self.context = self
+            .context
+            .rebuild()
+            .map_err(|err| anyhow::anyhow!("Failed to rebuild vello surface:{}", err))?;
+        self.recreated = true;
+        Ok(())
+    }
+    /// true once after the device got rebuilt, everything uploaded to the old one is gone
+    pub fn take_recreated(&mut self) -> bool {
+        std::mem::take(&mut self.recreated)
+    }
+    /// returns false when the frame matched the presented one and was dropped,
+    /// `force` submits it anyway, e.g. when the gui changed
+    pub fn end_frame(
+        &mut self,
+        ctx: FrameContext,
+        capture_to: Option<impl Into<PathBuf>>,
+        force: bool,
+    ) -> bool {
+        if !self.frames.finish(force || capture_to.is_some()) {
+            log::trace!("frame unchanged, not presented");
+            return false;
+        }
         let mut ctx = ctx;
         if let Some(capture_to) = capture_to {
             let config = &self.context.surface.config;
@@ -89,6 +271,47 @@ impl VelloRender {
                 frame.finish(&device);
             });
         }
+        true
+    }
+    /// frames dropped because nothing changed
+    pub fn skipped_frames(&self) -> u64 {
+        self.frames.skipped()
+    }
+    /// applies from the next scene, returns the method actually used
+    pub fn set_aa(&mut self, config: AaConfig) -> AaConfig {
+        let previous = self.aa.config();
+        if self.aa.set(config) != previous {
+            // the same scene renders differently now
+            self.frames.invalidate();
+        }
+        self.aa.config()
+    }
+    pub fn aa(&self) -> AaConfig {
+        self.aa.config()
+    }
+    /// renders the scene at `scale` of the window size, upscaled when presented
+    pub fn set_render_scale(&mut self, scale: f64) {
+        if self.context.set_render_scale(scale) {
+            self.frames.invalidate();
+        }
+    }
+    pub fn render_scale(&self) -> f64 {
+        self.context.render_scale()
+    }
+    /// a rung of the quality ladder, aa and render scale at once
+    pub fn set_quality(&mut self, level: QualityLevel) {
+        self.set_aa(level.aa);
+        self.set_render_scale(level.scale);
+    }
+    /// the background from the next scene on
+    pub fn set_clear_color(&mut self, color: Color) {
+        if self.clear_color != color {
+            self.clear_color = color;
+            self.frames.invalidate();
+        }
+    }
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
     }
 
     pub fn resize(&mut self, w: u32, h: u32) {
@@ -101,4 +324,100 @@ impl VelloRender {
     pub fn format(&self) -> wgpu::TextureFormat {
         self.context.format()
     }
+    pub fn gpu_info(&self) -> GpuInfo {
+        self.device_handle().adapter().get_info().into()
+    }
+}
+
+#[test]
+fn test_surface_recover() {
+    assert!(is_recoverable(&wgpu::SurfaceError::Lost));
+    assert!(is_recoverable(&wgpu::SurfaceError::Outdated));
+    assert!(!is_recoverable(&wgpu::SurfaceError::Timeout));
+    assert!(!is_recoverable(&wgpu::SurfaceError::OutOfMemory));
+    assert_eq!(
+        classify(&wgpu::SurfaceError::Outdated),
+        SurfaceAction::Reconfigure
+    );
+    assert_eq!(classify(&wgpu::SurfaceError::Timeout), SurfaceAction::Skip);
+    assert_eq!(
+        classify(&wgpu::SurfaceError::Other),
+        SurfaceAction::RebuildDevice
+    );
+    assert_eq!(
+        classify(&wgpu::SurfaceError::OutOfMemory),
+        SurfaceAction::Fatal
+    );
+    // resize(0, 0) suspends the frame instead of configuring the surface
+    assert!(is_empty_size(0, 0));
+    assert!(is_empty_size(800, 0));
+    assert!(!is_empty_size(800, 600));
+    let mut size = SurfaceSize::new(800, 600);
+    assert_eq!(frame_gate(size.suspended, false), FrameGate::Acquire);
+    // minimized, the next frame is skipped and the configured size kept
+    assert_eq!(size.resize(0, 0), None);
+    assert_eq!(frame_gate(size.suspended, false), FrameGate::Skip);
+    assert_eq!((size.width, size.height), (800, 600));
+    // a device lost meanwhile waits for the window to come back
+    assert_eq!(frame_gate(size.suspended, true), FrameGate::Skip);
+    assert_eq!(size.resize(640, 0), None);
+    assert_eq!(size.resize(640, 480), Some((640, 480)));
+    assert_eq!(frame_gate(size.suspended, true), FrameGate::RebuildDevice);
+    assert_eq!(frame_gate(size.suspended, false), FrameGate::Acquire);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[ignore = "needs a display and a gpu adapter"]
+#[allow(deprecated)]
+fn test_resize_to_zero_and_back() -> anyhow::Result<()> {
+    use winit::platform::x11::EventLoopBuilderExtX11;
+    let event_loop = winit::event_loop::EventLoop::builder()
+        .with_any_thread(true)
+        .build()?;
+    let window = Arc::new(event_loop.create_window(Window::default_attributes())?);
+    let mut render = VelloRender::new(window)?;
+    // minimize, restore, a lost surface and a one sided collapse
+    for (w, h) in [(0, 0), (800, 600), (800, 0), (640, 480)] {
+        render.resize(w, h);
+        render.context.reconfigure();
+        match render.begin_frame(None)? {
+            FrameStatus::Ready(frame) => {
+                assert!(!is_empty_size(w, h));
+                render.end_frame(frame, None::<PathBuf>, true);
+            }
+            FrameStatus::Skipped => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[ignore = "needs a display and a gpu adapter"]
+#[allow(deprecated)]
+fn test_clear_color() -> anyhow::Result<()> {
+    use winit::platform::x11::EventLoopBuilderExtX11;
+    let event_loop = winit::event_loop::EventLoop::builder()
+        .with_any_thread(true)
+        .build()?;
+    let window = Arc::new(event_loop.create_window(Window::default_attributes())?);
+    let mut render = VelloRender::new(window)?;
+    let scene = Scene::new();
+    let FrameStatus::Ready(frame) = render.begin_frame(None)? else {
+        return Ok(());
+    };
+    render.draw_scene(&scene)?;
+    render.end_frame(frame, None::<PathBuf>, false);
+    assert_eq!(render.render_params().base_color, palette::css::BLACK);
+    let blue = Color::from_rgb8(0x20, 0x40, 0x80);
+    render.set_clear_color(blue);
+    assert_eq!(render.render_params().base_color, blue);
+    // the same scene is rendered again over the new color
+    let FrameStatus::Ready(frame) = render.begin_frame(None)? else {
+        return Ok(());
+    };
+    render.draw_scene(&scene)?;
+    assert!(render.end_frame(frame, None::<PathBuf>, false));
+    Ok(())
 }
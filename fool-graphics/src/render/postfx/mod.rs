@@ -0,0 +1,272 @@
+use serde::Deserialize;
+mod pass;
+pub use pass::PostProcess;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ChromaticAberration {
+    /// red and blue offset at the screen edge in pixels
+    pub amount: f32,
+}
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { amount: 2.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ColorGrade {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    pub tint: [f32; 4],
+}
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            tint: [1.0; 4],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Vignette {
+    pub intensity: f32,
+    /// distance from the center the darkening starts, 1.0 is the corners
+    pub radius: f32,
+    pub softness: f32,
+    pub color: [f32; 4],
+}
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            radius: 0.6,
+            softness: 0.4,
+            color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Fade {
+    /// 0 shows the scene, 1 only `color`
+    pub amount: f32,
+    pub color: [f32; 4],
+}
+impl Default for Fade {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FadeTween {
+    from: f32,
+    to: f32,
+    duration: f32,
+    elapsed: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Shake {
+    amplitude: f32,
+    frequency: f32,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Shake {
+    /// decays linearly to nothing over `duration`
+    fn offset(&self) -> [f32; 2] {
+        let decay = 1.0 - (self.elapsed / self.duration).min(1.0);
+        let phase = std::f32::consts::TAU * self.frequency * self.elapsed;
+        let amplitude = self.amplitude * decay;
+        // different rates per axis so it does not move along a line
+        [
+            amplitude * phase.sin(),
+            amplitude * (phase * 1.37 + 1.7).sin(),
+        ]
+    }
+}
+
+/// full screen effects applied between the vello output and the surface,
+/// composed in field order: shake, chromatic aberration, color grade, vignette, fade, color matrix
+#[derive(Debug, Clone, Default)]
+pub struct EffectStack {
+    pub chromatic_aberration: Option<ChromaticAberration>,
+    pub color_grade: Option<ColorGrade>,
+    pub vignette: Option<Vignette>,
+    pub fade: Option<Fade>,
+    /// rgb transform of the final image, each row gives one channel,
+    /// a display setting rather than an effect so `clear` keeps it
+    pub color_matrix: Option<[[f32; 3]; 3]>,
+    fade_tween: Option<FadeTween>,
+    shake: Option<Shake>,
+    // fades finished since the last `take_fades_done`
+    fades_done: u32,
+}
+
+impl EffectStack {
+    /// nothing to draw, the post processing pass is skipped
+    pub fn is_empty(&self) -> bool {
+        self.chromatic_aberration.is_none()
+            && self.color_grade.is_none()
+            && self.vignette.is_none()
+            && self.fade.is_none_or(|fade| fade.amount <= 0.0)
+            && self.color_matrix.is_none()
+            && self.fade_tween.is_none()
+            && self.shake.is_none()
+    }
+    /// removes every effect, a running fade is dropped without finishing
+    pub fn clear(&mut self) {
+        *self = Self {
+            fades_done: self.fades_done,
+            color_matrix: self.color_matrix,
+            ..Default::default()
+        };
+    }
+    /// fades from the current amount to `target` over `duration` seconds
+    pub fn fade_to(&mut self, target: f32, duration: f32) {
+        let fade = self.fade.get_or_insert_with(Default::default);
+        self.fade_tween = Some(FadeTween {
+            from: fade.amount,
+            to: target.clamp(0.0, 1.0),
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+        });
+    }
+    pub fn is_fading(&self) -> bool {
+        self.fade_tween.is_some()
+    }
+    /// `amplitude` in pixels, `duration` in seconds, replaces a running shake
+    pub fn shake(&mut self, amplitude: f32, frequency: f32, duration: f32) {
+        self.shake = (duration > 0.0).then_some(Shake {
+            amplitude,
+            frequency,
+            duration,
+            elapsed: 0.0,
+        });
+    }
+    /// shake offset in pixels
+    pub fn shake_offset(&self) -> [f32; 2] {
+        self.shake.map(|shake| shake.offset()).unwrap_or_default()
+    }
+    /// moves fades and shakes forward by `dt` seconds
+    pub fn advance(&mut self, dt: f32) {
+        if let Some(tween) = &mut self.fade_tween {
+            tween.elapsed += dt;
+            let t = match tween.duration > 0.0 {
+                true => (tween.elapsed / tween.duration).min(1.0),
+                false => 1.0,
+            };
+            let fade = self.fade.get_or_insert_with(Default::default);
+            fade.amount = tween.from + (tween.to - tween.from) * t;
+            if t >= 1.0 {
+                self.fade_tween = None;
+                self.fades_done += 1;
+            }
+        }
+        if let Some(shake) = &mut self.shake {
+            shake.elapsed += dt;
+            if shake.elapsed >= shake.duration {
+                self.shake = None;
+            }
+        }
+    }
+    /// how many fades finished since the last call
+    pub fn take_fades_done(&mut self) -> u32 {
+        std::mem::take(&mut self.fades_done)
+    }
+    /// the shader parameters for a `width` x `height` target
+    pub fn uniforms(&self, width: u32, height: u32) -> PostFxUniforms {
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        let [x, y] = self.shake_offset();
+        let chroma = self
+            .chromatic_aberration
+            .map(|c| c.amount / width)
+            .unwrap_or(0.0);
+        let (grade, tint) = match self.color_grade {
+            Some(g) => ([g.brightness, g.contrast, g.saturation, 1.0], g.tint),
+            None => ([0.0, 1.0, 1.0, 0.0], [1.0; 4]),
+        };
+        let (vignette, vignette_color) = match self.vignette {
+            Some(v) => ([v.intensity, v.radius, v.softness, 1.0], v.color),
+            None => ([0.0; 4], [0.0; 4]),
+        };
+        let fade = self.fade.unwrap_or_default();
+        let [r, g, b, a] = fade.color;
+        // the w of the first row switches it on
+        let [m0, m1, m2] = match self.color_matrix {
+            Some([r0, r1, r2]) => [
+                [r0[0], r0[1], r0[2], 1.0],
+                [r1[0], r1[1], r1[2], 0.0],
+                [r2[0], r2[1], r2[2], 0.0],
+            ],
+            None => [[0.0; 4]; 3],
+        };
+        PostFxUniforms([
+            [x / width, y / height, chroma, 0.0],
+            grade,
+            tint,
+            vignette,
+            vignette_color,
+            [r, g, b, a * fade.amount.clamp(0.0, 1.0)],
+            m0,
+            m1,
+            m2,
+        ])
+    }
+}
+
+/// laid out like `Params` in postfx.wgsl
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostFxUniforms(pub [[f32; 4]; 9]);
+
+#[test]
+fn test_effect_stack() {
+    let mut stack = EffectStack::default();
+    assert!(stack.is_empty());
+    stack.fade_to(1.0, 0.5);
+    assert!(!stack.is_empty());
+    stack.advance(0.25);
+    assert_eq!(stack.fade.unwrap().amount, 0.5);
+    assert_eq!(stack.take_fades_done(), 0);
+    stack.advance(0.5);
+    assert_eq!(stack.fade.unwrap().amount, 1.0);
+    assert!(!stack.is_fading());
+    assert_eq!(stack.take_fades_done(), 1);
+    assert_eq!(stack.take_fades_done(), 0);
+    // faded back out it costs nothing again
+    stack.fade_to(0.0, 0.0);
+    stack.advance(0.016);
+    assert!(stack.is_empty());
+
+    stack.shake(8.0, 10.0, 0.2);
+    stack.advance(0.01);
+    let [x, y] = stack.shake_offset();
+    assert!(x.abs() <= 8.0 && y.abs() <= 8.0 && (x, y) != (0.0, 0.0));
+    stack.advance(0.2);
+    assert_eq!(stack.shake_offset(), [0.0, 0.0]);
+    assert!(stack.is_empty());
+
+    stack.vignette = Some(Vignette::default());
+    let uniforms = stack.uniforms(800, 600);
+    assert_eq!(uniforms.0[3], [0.5, 0.6, 0.4, 1.0]);
+    stack.clear();
+    assert!(stack.is_empty());
+    // survives clearing the game's effects
+    stack.color_matrix = Some([[0.5, 0.5, 0.0], [0.5, 0.5, 0.0], [0.0, 0.0, 1.0]]);
+    stack.clear();
+    assert!(!stack.is_empty());
+    assert_eq!(stack.uniforms(800, 600).0[6], [0.5, 0.5, 0.0, 1.0]);
+}
@@ -0,0 +1,85 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+struct Params {
+    // xy shake offset and z chromatic aberration, in uv
+    shake_chroma: vec4<f32>,
+    // brightness, contrast, saturation, enabled
+    grade: vec4<f32>,
+    tint: vec4<f32>,
+    // intensity, radius, softness, enabled
+    vignette: vec4<f32>,
+    vignette_color: vec4<f32>,
+    // rgb and the fade amount in a
+    fade: vec4<f32>,
+    // rows of a color matrix, enabled by the w of the first
+    matrix_r: vec4<f32>,
+    matrix_g: vec4<f32>,
+    matrix_b: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vi: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = vec2<f32>(
+        f32((vi << 1u) & 2u),
+        f32(vi & 2u),
+    );
+    out.position = vec4<f32>(out.tex_coords * 2.0 - 1.0, 0.0, 1.0);
+    out.tex_coords.y = 1.0 - out.tex_coords.y;
+    return out;
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+fn sample(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(source, source_sampler, uv, 0.0);
+}
+
+@fragment
+fn fs_main(vs: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = vs.tex_coords + params.shake_chroma.xy;
+    var color = sample(uv);
+
+    let chroma = params.shake_chroma.z;
+    if chroma > 0.0 {
+        let dir = (uv - vec2<f32>(0.5)) * 2.0 * chroma;
+        color.r = sample(uv + dir).r;
+        color.b = sample(uv - dir).b;
+    }
+
+    if params.grade.w > 0.0 {
+        var rgb = color.rgb + vec3<f32>(params.grade.x);
+        rgb = (rgb - 0.5) * params.grade.y + 0.5;
+        let luma = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        rgb = mix(vec3<f32>(luma), rgb, params.grade.z) * params.tint.rgb;
+        color = vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+    }
+
+    if params.vignette.w > 0.0 {
+        // 0 at the center, 1 in the corners
+        let dist = distance(vs.tex_coords, vec2<f32>(0.5)) * 1.41421356;
+        let edge = smoothstep(params.vignette.y, params.vignette.y + params.vignette.z, dist);
+        let amount = edge * params.vignette.x * params.vignette_color.a;
+        color = vec4<f32>(mix(color.rgb, params.vignette_color.rgb, amount), color.a);
+    }
+
+    color = vec4<f32>(mix(color.rgb, params.fade.rgb, params.fade.a), color.a);
+
+    if params.matrix_r.w > 0.0 {
+        let rgb = vec3<f32>(
+            dot(params.matrix_r.xyz, color.rgb),
+            dot(params.matrix_g.xyz, color.rgb),
+            dot(params.matrix_b.xyz, color.rgb),
+        );
+        color = vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+    }
+    return color;
+}
@@ -0,0 +1,270 @@
+use super::aa_name;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use vello::AaConfig;
+
+/// one rung of the quality ladder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityLevel {
+    pub aa: AaConfig,
+    // the scene is rendered at this fraction of the window size and scaled up
+    pub scale: f64,
+}
+
+impl QualityLevel {
+    pub fn name(&self) -> String {
+        match self.scale < 1.0 {
+            true => format!("{}@{}", aa_name(self.aa), self.scale),
+            false => aa_name(self.aa).to_owned(),
+        }
+    }
+}
+
+/// best first
+pub const QUALITY_LADDER: [QualityLevel; 5] = [
+    QualityLevel {
+        aa: AaConfig::Msaa16,
+        scale: 1.0,
+    },
+    QualityLevel {
+        aa: AaConfig::Msaa8,
+        scale: 1.0,
+    },
+    QualityLevel {
+        aa: AaConfig::Area,
+        scale: 1.0,
+    },
+    QualityLevel {
+        aa: AaConfig::Area,
+        scale: 0.85,
+    },
+    QualityLevel {
+        aa: AaConfig::Area,
+        scale: 0.75,
+    },
+];
+
+/// the rung named like `QualityLevel::name`, "msaa16" to "area@0.75"
+pub fn parse_quality(name: &str) -> Option<usize> {
+    let name = name.to_ascii_lowercase();
+    QUALITY_LADDER.iter().position(|level| level.name() == name)
+}
+
+// the rolling average covers this much
+const AVERAGE_WINDOW: Duration = Duration::from_secs(1);
+// fewer frames than this in the window tell nothing yet
+const MIN_SAMPLES: usize = 10;
+// a gap this long is a stall or a hidden window, not a slow frame
+const MAX_SAMPLE: Duration = Duration::from_millis(500);
+// vsync jitter stays under this
+const OVER_BUDGET: f64 = 1.15;
+const HEADROOM: f64 = 1.05;
+// how long the average has to stay over budget before a step down
+const STEP_DOWN_AFTER: Duration = Duration::from_secs(3);
+// how long it has to stay in budget before a step up, doubled
+// each time a step up had to be taken back
+const STEP_UP_AFTER: Duration = Duration::from_secs(10);
+const MAX_STEP_UP_AFTER: Duration = Duration::from_secs(160);
+
+/// steps down the quality ladder while the frames take longer than the budget and back up
+/// once there is headroom again, timed by the time between presented frames since vello's
+/// device is created without timestamp queries
+#[derive(Debug, Clone)]
+pub struct AdaptiveQuality {
+    enabled: bool,
+    budget: Duration,
+    rung: usize,
+    // the lowest rung it may go down to
+    floor: usize,
+    samples: VecDeque<(Instant, Duration)>,
+    last_present: Option<Instant>,
+    over_since: Option<Instant>,
+    headroom_since: Option<Instant>,
+    step_up_after: Duration,
+    // a step down this soon after a step up takes the step up back
+    stepped_up_at: Option<Instant>,
+}
+
+impl Default for AdaptiveQuality {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            budget: Duration::from_secs_f64(1.0 / 60.0),
+            rung: 0,
+            floor: QUALITY_LADDER.len() - 1,
+            samples: VecDeque::new(),
+            last_present: None,
+            over_since: None,
+            headroom_since: None,
+            step_up_after: STEP_UP_AFTER,
+            stepped_up_at: None,
+        }
+    }
+}
+
+impl AdaptiveQuality {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.reset();
+    }
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    /// the time one frame may take, 1 / the target fps
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+        self.reset();
+    }
+    /// the lowest rung of `QUALITY_LADDER` it may pick, the current one is
+    /// returned when it is below the floor now
+    pub fn set_floor(&mut self, floor: usize) -> Option<QualityLevel> {
+        self.floor = floor.min(QUALITY_LADDER.len() - 1);
+        if self.rung <= self.floor {
+            return None;
+        }
+        log::info!(
+            "render quality {} -> {}, the floor was raised",
+            self.level().name(),
+            QUALITY_LADDER[self.floor].name()
+        );
+        self.rung = self.floor;
+        self.reset();
+        Some(self.level())
+    }
+    pub fn floor(&self) -> usize {
+        self.floor
+    }
+    pub fn rung(&self) -> usize {
+        self.rung
+    }
+    pub fn level(&self) -> QualityLevel {
+        QUALITY_LADDER[self.rung]
+    }
+    // the measurements belong to the old rung
+    fn reset(&mut self) {
+        self.samples.clear();
+        self.last_present = None;
+        self.over_since = None;
+        self.headroom_since = None;
+    }
+    /// a frame that was not presented, the next interval would include the idle time
+    pub fn frame_skipped(&mut self) {
+        self.last_present = None;
+    }
+    /// after every presented frame, returns the level to switch to on a change
+    pub fn frame_presented(&mut self, now: Instant) -> Option<QualityLevel> {
+        if !self.enabled {
+            return None;
+        }
+        if let Some(last) = self.last_present.replace(now) {
+            let interval = now.saturating_duration_since(last);
+            if interval < MAX_SAMPLE {
+                self.samples.push_back((now, interval));
+            }
+        }
+        while let Some((at, _)) = self.samples.front() {
+            match now.saturating_duration_since(*at) > AVERAGE_WINDOW {
+                true => self.samples.pop_front(),
+                false => break,
+            };
+        }
+        if self.samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let total: Duration = self.samples.iter().map(|(_, interval)| *interval).sum();
+        let average = total.as_secs_f64() / self.samples.len() as f64;
+        let budget = self.budget.as_secs_f64();
+        if average > budget * OVER_BUDGET {
+            self.headroom_since = None;
+            let since = *self.over_since.get_or_insert(now);
+            if now - since < STEP_DOWN_AFTER || self.rung >= self.floor {
+                return None;
+            }
+            let retaken = self
+                .stepped_up_at
+                .take()
+                .is_some_and(|at| now - at < self.step_up_after + STEP_DOWN_AFTER);
+            if retaken {
+                self.step_up_after = (self.step_up_after * 2).min(MAX_STEP_UP_AFTER);
+            }
+            return Some(self.step(self.rung + 1, average));
+        }
+        self.over_since = None;
+        if average > budget * HEADROOM {
+            self.headroom_since = None;
+            return None;
+        }
+        let since = *self.headroom_since.get_or_insert(now);
+        if now - since < self.step_up_after || self.rung == 0 {
+            return None;
+        }
+        self.stepped_up_at = Some(now);
+        Some(self.step(self.rung - 1, average))
+    }
+    fn step(&mut self, rung: usize, average: f64) -> QualityLevel {
+        log::info!(
+            "render quality {} -> {}, average frame {:.1}ms, budget {:.1}ms",
+            self.level().name(),
+            QUALITY_LADDER[rung].name(),
+            average * 1000.0,
+            self.budget.as_secs_f64() * 1000.0
+        );
+        self.rung = rung;
+        self.reset();
+        self.level()
+    }
+}
+
+/// the size of a target rendered at `scale` of the window, at least a pixel
+pub fn scaled_size(width: u32, height: u32, scale: f64) -> (u32, u32) {
+    let scaled = |size: u32| ((size as f64 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+#[test]
+fn test_adaptive_quality() {
+    let mut quality = AdaptiveQuality::default();
+    let start = Instant::now();
+    let mut now = start;
+    // frames of `ms` for `secs` seconds, the rungs it went through
+    let mut run = |quality: &mut AdaptiveQuality, ms: u64, secs: u64| {
+        let mut changes = Vec::new();
+        let end = now + Duration::from_secs(secs);
+        while now < end {
+            now += Duration::from_millis(ms);
+            if let Some(level) = quality.frame_presented(now) {
+                changes.push(level.name());
+            }
+        }
+        changes
+    };
+    // off by default
+    assert!(run(&mut quality, 50, 10).is_empty());
+    quality.set_enabled(true);
+    // 20 fps on a 60 fps budget, a rung every 3 seconds down to the floor
+    quality.set_floor(parse_quality("area@0.85").unwrap());
+    let changes = run(&mut quality, 50, 20);
+    assert_eq!(changes, ["msaa8", "area", "area@0.85"]);
+    // at 60 fps again, a step up only after 10 seconds of headroom
+    assert!(run(&mut quality, 16, 9).is_empty());
+    assert_eq!(run(&mut quality, 16, 2), ["area"]);
+    // which was too much, back down and the next try waits twice as long
+    assert_eq!(run(&mut quality, 25, 5), ["area@0.85"]);
+    assert!(run(&mut quality, 16, 19).is_empty());
+    assert_eq!(run(&mut quality, 16, 2), ["area"]);
+    // a little over the budget is vsync jitter, not a reason to step
+    assert!(run(&mut quality, 18, 10).is_empty());
+    // a raised floor takes effect right away
+    quality.set_enabled(true);
+    assert_eq!(
+        quality.set_floor(1).map(|level| level.name()),
+        Some("msaa8".into())
+    );
+    assert_eq!(quality.rung(), 1);
+
+    assert_eq!(parse_quality("MSAA16"), Some(0));
+    assert_eq!(parse_quality("area@0.75"), Some(4));
+    assert_eq!(parse_quality("fxaa"), None);
+    assert_eq!(scaled_size(1920, 1080, 0.75), (1440, 810));
+    assert_eq!(scaled_size(1, 1, 0.5), (1, 1));
+}
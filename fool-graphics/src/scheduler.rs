@@ -1,32 +1,108 @@
 #![allow(dead_code)]
 use std::time::{Duration, Instant};
 
-use winit::event_loop::{ActiveEventLoop, ControlFlow};
+use winit::event_loop::ControlFlow;
+
+// max fixed updates per iteration, avoids the spiral of death
+const MAX_UPDATE_STEPS: u32 = 5;
+
+/// fixed rate update clock, accumulates elapsed time into whole steps
+#[derive(Debug, Clone)]
+pub struct FixedStep {
+    step: Duration,
+    accumulator: Duration,
+    last_time: Instant,
+    max_steps: u32,
+}
+
+impl FixedStep {
+    pub fn new(hz: u32) -> Self {
+        Self {
+            step: Duration::from_secs_f64(1.0 / hz.max(1) as f64),
+            accumulator: Duration::ZERO,
+            last_time: Instant::now(),
+            max_steps: MAX_UPDATE_STEPS,
+        }
+    }
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+    pub fn set_hz(&mut self, hz: u32) {
+        self.step = Duration::from_secs_f64(1.0 / hz.max(1) as f64);
+        self.accumulator = Duration::ZERO;
+    }
+    /// how many fixed updates to run for the time elapsed until `now`
+    pub fn should_update(&mut self, now: Instant) -> u32 {
+        self.should_update_scaled(now, 1.0)
+    }
+    /// like `should_update` with the elapsed time multiplied by `scale`,
+    /// the step stays the same so slow motion runs fewer updates
+    pub fn should_update_scaled(&mut self, now: Instant, scale: f64) -> u32 {
+        let elapsed = now.saturating_duration_since(self.last_time);
+        self.accumulator += elapsed.mul_f64(scale.max(0.0));
+        self.last_time = now;
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if self.accumulator >= self.step {
+            log::trace!("fixed update fell behind by {:?}", self.accumulator);
+            self.accumulator =
+                Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+        }
+        steps
+    }
+    /// interpolation fraction between the last two updates
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.as_secs_f64() / self.step.as_secs_f64()
+    }
+    /// constant dt of a single update in seconds
+    pub fn dt(&self) -> f64 {
+        self.step.as_secs_f64()
+    }
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.last_time = Instant::now();
+    }
+}
 
 #[derive(Debug)]
 pub struct Scheduler {
-    frame_interval: Duration,
+    // None renders as fast as the display allows
+    frame_interval: Option<Duration>,
     pub next_frame_time: Instant,
     pub running: bool,
+    pub update: FixedStep,
 }
 
 impl Scheduler {
-    pub fn new(fps: u32) -> Self {
-        let frame_interval = Duration::from_secs_f64(1.0 / fps as f64);
+    pub fn new(update_hz: u32, render_fps: Option<u32>) -> Self {
+        let frame_interval = render_fps.map(|fps| Duration::from_secs_f64(1.0 / fps.max(1) as f64));
         let now = Instant::now();
         Self {
             frame_interval,
-            next_frame_time: now + frame_interval,
+            next_frame_time: now + frame_interval.unwrap_or_default(),
             running: true,
+            update: FixedStep::new(update_hz),
         }
     }
+    pub fn set_fps(&mut self, fps: Option<u32>) {
+        self.frame_interval = fps.map(|fps| Duration::from_secs_f64(1.0 / fps.max(1) as f64));
+        self.next_frame_time = Instant::now() + self.frame_interval.unwrap_or_default();
+    }
+    pub fn set_update_hz(&mut self, hz: u32) {
+        self.update.set_hz(hz);
+    }
 
     pub fn advance(&mut self) {
-        self.next_frame_time += self.frame_interval;
+        self.next_frame_time += self.frame_interval.unwrap_or_default();
     }
     pub fn reset(&mut self) {
         let now = Instant::now();
-        self.next_frame_time = now + self.frame_interval;
+        self.next_frame_time = now + self.frame_interval.unwrap_or_default();
+        self.update.reset();
     }
     pub fn pause(&mut self) {
         self.running = false;
@@ -37,23 +113,94 @@ impl Scheduler {
             self.reset();
         }
     }
-    pub fn trigger_redraw(&mut self, event_loop: &ActiveEventLoop) -> bool {
+    pub fn should_update(&mut self, now: Instant) -> u32 {
+        self.should_update_scaled(now, 1.0)
+    }
+    /// `time_scale` stretches the time the fixed updates catch up on
+    pub fn should_update_scaled(&mut self, now: Instant, time_scale: f64) -> u32 {
+        if !self.running {
+            return 0;
+        }
+        self.update.should_update_scaled(now, time_scale)
+    }
+    pub fn alpha(&self) -> f64 {
+        self.update.alpha()
+    }
+    pub fn update_dt(&self) -> f64 {
+        self.update.dt()
+    }
+    /// whether to redraw at `now` and the control flow to wait with until the next check,
+    /// uncapped frames poll only while running, a paused scheduler sleeps until resumed
+    pub fn next_frame(&mut self, now: Instant) -> (bool, ControlFlow) {
         if !self.running {
-            return false;
+            return (false, ControlFlow::Wait);
         }
+        let Some(frame_interval) = self.frame_interval else {
+            return (true, ControlFlow::Poll);
+        };
+        // frames missed while stalled are skipped, not drawn back to back
         let mut redraw = false;
-        let now = std::time::Instant::now();
-        if now >= self.next_frame_time {
+        while self.next_frame_time <= now {
+            self.next_frame_time += frame_interval;
             redraw = true;
-            self.advance();
         }
         let next = self.next_frame_time;
         let wait = if next > now {
             next
         } else {
-            now + std::time::Duration::from_millis(1)
+            now + Duration::from_millis(1)
         };
-        event_loop.set_control_flow(ControlFlow::WaitUntil(wait));
-        return redraw;
+        (redraw, ControlFlow::WaitUntil(wait))
     }
 }
+
+#[test]
+fn test_fixed_step() {
+    let mut clock = FixedStep::new(60);
+    let start = clock.last_time;
+    // 2.5 steps elapsed
+    let steps = clock.should_update(start + Duration::from_secs_f64(2.5 / 60.0));
+    assert_eq!(steps, 2);
+    assert!((clock.alpha() - 0.5).abs() < 1e-3);
+    // a long stall is capped and the remainder dropped
+    let steps = clock.should_update(start + Duration::from_secs(10));
+    assert_eq!(steps, MAX_UPDATE_STEPS);
+    assert!(clock.alpha() < 1.0);
+    // at quarter speed 20ms frames count as 5ms, the fourth makes a step
+    clock.reset();
+    let start = clock.last_time;
+    let frame = Duration::from_millis(20);
+    let steps = (1..=4)
+        .map(|i| clock.should_update_scaled(start + frame * i, 0.25))
+        .collect::<Vec<_>>();
+    assert_eq!(steps, [0, 0, 0, 1]);
+    assert_eq!(clock.should_update_scaled(start + frame * 8, 0.0), 0);
+}
+
+#[test]
+fn test_next_frame() {
+    let mut scheduler = Scheduler::new(60, None);
+    let now = Instant::now();
+    assert_eq!(scheduler.next_frame(now), (true, ControlFlow::Poll));
+    // paused, the poll is dropped for a wait
+    scheduler.pause();
+    assert_eq!(scheduler.next_frame(now), (false, ControlFlow::Wait));
+    scheduler.resume();
+    assert_eq!(scheduler.next_frame(now), (true, ControlFlow::Poll));
+
+    let mut scheduler = Scheduler::new(60, Some(50));
+    let next = scheduler.next_frame_time;
+    assert_eq!(
+        scheduler.next_frame(next - Duration::from_millis(5)),
+        (false, ControlFlow::WaitUntil(next))
+    );
+    let (redraw, _) = scheduler.next_frame(next);
+    assert!(redraw);
+    assert_eq!(scheduler.next_frame_time, next + Duration::from_millis(20));
+    // a stall of several frames redraws once and waits for the next one on time
+    let (redraw, _) = scheduler.next_frame(next + Duration::from_millis(70));
+    assert!(redraw);
+    assert_eq!(scheduler.next_frame_time, next + Duration::from_millis(80));
+    scheduler.pause();
+    assert_eq!(scheduler.next_frame(next), (false, ControlFlow::Wait));
+}
@@ -0,0 +1,412 @@
+use fool_resource::SharedData;
+use peniko::{Blob, Image, ImageFormat};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, SyncSender, TryRecvError};
+use std::sync::{Arc, mpsc};
+#[cfg(feature = "webm")]
+mod webm;
+#[cfg(feature = "webm")]
+pub use webm::WebmVideo;
+
+// decoded frames waiting to be shown, what a video holds in memory on top
+// of the frame on screen and the one being decoded
+const RING_FRAMES: usize = 4;
+
+/// `video.toml` in the folder of a video, the frames are png or jpeg images
+#[derive(Debug, Clone, Deserialize)]
+pub struct VideoManifest {
+    pub fps: f64,
+    /// the frame files in order, relative to the folder
+    #[serde(default)]
+    pub frames: Vec<String>,
+    /// one file with every frame back to back instead of a file each
+    #[serde(default)]
+    pub pack: Option<String>,
+    /// where each frame in `pack` starts, the last one ends with the file
+    #[serde(default)]
+    pub offsets: Vec<u64>,
+}
+
+impl VideoManifest {
+    pub fn frame_count(&self) -> usize {
+        match self.pack {
+            Some(_) => self.offsets.len(),
+            None => self.frames.len(),
+        }
+    }
+    pub fn duration(&self) -> f64 {
+        self.frame_count() as f64 / self.fps
+    }
+}
+
+/// the frames a `VideoPlayer` decodes on its thread
+pub trait VideoSource: Send + 'static {
+    fn fps(&self) -> f64;
+    fn frame_count(&self) -> usize;
+    /// decodes frame `index` to rgba8, between seeks the index only goes up
+    fn decode(&mut self, index: usize) -> anyhow::Result<Image>;
+}
+
+/// reads a file of the video folder, on the decode thread
+pub type ReadFrameFile = Box<dyn Fn(&str) -> anyhow::Result<SharedData> + Send>;
+
+/// the encoded frames of an image sequence, read one at a time
+pub struct ImageSequence {
+    manifest: VideoManifest,
+    read: ReadFrameFile,
+    // the pack is read once, on the first frame
+    pack: Option<SharedData>,
+}
+
+impl ImageSequence {
+    pub fn new(manifest: VideoManifest, read: ReadFrameFile) -> Self {
+        Self {
+            manifest,
+            read,
+            pack: None,
+        }
+    }
+    fn encoded(&mut self, index: usize) -> anyhow::Result<SharedData> {
+        let Some(pack_name) = &self.manifest.pack else {
+            return (self.read)(&self.manifest.frames[index]);
+        };
+        let pack = match &self.pack {
+            Some(pack) => pack.clone(),
+            None => self.pack.insert((self.read)(pack_name)?).clone(),
+        };
+        let start = self.manifest.offsets[index] as usize;
+        let end = match self.manifest.offsets.get(index + 1) {
+            Some(end) => *end as usize,
+            None => pack.len(),
+        };
+        match pack.get(start..end) {
+            Some(frame) => Ok(SharedData::from(frame)),
+            None => Err(anyhow::anyhow!(
+                "frame {} at {}..{} is outside {}, {} bytes",
+                index,
+                start,
+                end,
+                pack_name,
+                pack.len()
+            )),
+        }
+    }
+}
+
+impl VideoSource for ImageSequence {
+    fn fps(&self) -> f64 {
+        self.manifest.fps
+    }
+    fn frame_count(&self) -> usize {
+        self.manifest.frame_count()
+    }
+    fn decode(&mut self, index: usize) -> anyhow::Result<Image> {
+        let encoded = self.encoded(index)?;
+        let rgba = image::load_from_memory(&encoded)?.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        Ok(Image::new(
+            Blob::new(Arc::new(rgba.into_raw())),
+            ImageFormat::Rgba8,
+            width,
+            height,
+        ))
+    }
+}
+
+enum Command {
+    Seek { frame: usize, generation: u64 },
+}
+
+struct DecodedFrame {
+    generation: u64,
+    index: usize,
+    image: Image,
+}
+
+// decodes ahead of the clock until the ring is full, frames the clock already
+// passed are never decoded, ends when the player is dropped
+fn decode_frames(
+    mut source: impl VideoSource,
+    commands: Receiver<Command>,
+    frames: SyncSender<DecodedFrame>,
+    due: Arc<AtomicUsize>,
+) {
+    let count = source.frame_count();
+    let (mut next, mut generation) = (0, 0);
+    loop {
+        let command = match next < count {
+            true => match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            },
+            // nothing left to decode until a seek
+            false => match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            },
+        };
+        if let Some(Command::Seek {
+            frame,
+            generation: seek,
+        }) = command
+        {
+            (next, generation) = (frame, seek);
+            continue;
+        }
+        next = next.max(due.load(Ordering::Relaxed));
+        if next >= count {
+            continue;
+        }
+        match source.decode(next) {
+            Ok(image) => {
+                let frame = DecodedFrame {
+                    generation,
+                    index: next,
+                    image,
+                };
+                if frames.send(frame).is_err() {
+                    return;
+                }
+            }
+            Err(err) => log::warn!("decode video frame {} failed: {}", next, err),
+        }
+        next += 1;
+    }
+}
+
+/// plays a video source, decoded on a thread of its own, on a clock the
+/// game advances, frames decoded too late are skipped instead of slowing it down
+pub struct VideoPlayer {
+    fps: f64,
+    frame_count: usize,
+    clock: f64,
+    playing: bool,
+    looping: bool,
+    finished: bool,
+    generation: u64,
+    commands: Sender<Command>,
+    frames: Receiver<DecodedFrame>,
+    due: Arc<AtomicUsize>,
+    // decoded ahead of the clock, shown once it is due
+    pending: Option<DecodedFrame>,
+    shown: Option<usize>,
+    dropped: usize,
+}
+
+impl VideoPlayer {
+    pub fn new(source: impl VideoSource) -> anyhow::Result<Self> {
+        let (fps, frame_count) = (source.fps(), source.frame_count());
+        if fps.is_nan() || fps <= 0.0 {
+            return Err(anyhow::anyhow!("video fps {} must be above 0", fps));
+        }
+        if frame_count == 0 {
+            return Err(anyhow::anyhow!("video has no frames"));
+        }
+        let (commands, command_rx) = mpsc::channel();
+        let (frame_tx, frames) = mpsc::sync_channel(RING_FRAMES);
+        let due = Arc::new(AtomicUsize::new(0));
+        {
+            let due = due.clone();
+            std::thread::Builder::new()
+                .name("fool-video".into())
+                .spawn(move || decode_frames(source, command_rx, frame_tx, due))?;
+        }
+        Ok(Self {
+            fps,
+            frame_count,
+            clock: 0.0,
+            playing: false,
+            looping: false,
+            finished: false,
+            generation: 0,
+            commands,
+            frames,
+            due,
+            pending: None,
+            shown: None,
+            dropped: 0,
+        })
+    }
+    /// from the start again when it played to the end
+    pub fn play(&mut self) {
+        if self.clock >= self.duration() {
+            self.seek(0.0);
+        }
+        self.playing = true;
+    }
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+    pub fn looping(&self) -> bool {
+        self.looping
+    }
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+    pub fn duration(&self) -> f64 {
+        self.frame_count as f64 / self.fps
+    }
+    /// the clock in seconds
+    pub fn position(&self) -> f64 {
+        self.clock
+    }
+    /// the frame the clock is on
+    pub fn frame(&self) -> usize {
+        // a clock landing on a frame boundary belongs to that frame
+        let frame = (self.clock * self.fps + 1e-9).floor() as usize;
+        frame.min(self.frame_count - 1)
+    }
+    /// frames skipped because they were decoded too late
+    pub fn dropped_frames(&self) -> usize {
+        self.dropped
+    }
+    /// moves the clock to `position` seconds, what was decoded for the old one is dropped
+    pub fn seek(&mut self, position: f64) {
+        self.clock = position.clamp(0.0, self.duration());
+        self.finished = false;
+        self.generation += 1;
+        self.pending = None;
+        self.shown = None;
+        let frame = self.frame();
+        self.due.store(frame, Ordering::Relaxed);
+        let _ = self.commands.send(Command::Seek {
+            frame,
+            generation: self.generation,
+        });
+        // frees the ring, the decoder may be waiting on it
+        while self.frames.try_recv().is_ok() {}
+    }
+    /// stops at the last frame, as if it played through
+    pub fn skip(&mut self) {
+        self.seek(self.duration());
+        self.finish();
+    }
+    fn finish(&mut self) {
+        self.playing = false;
+        self.finished = true;
+    }
+    /// true once, after it played or was skipped to the end
+    pub fn take_finished(&mut self) -> bool {
+        std::mem::take(&mut self.finished)
+    }
+    /// advances the clock by `dt` seconds while playing, returns the frame to
+    /// show when it changed
+    pub fn update(&mut self, dt: f64) -> Option<Image> {
+        if self.playing {
+            self.clock += dt;
+            let duration = self.duration();
+            if self.clock >= duration {
+                match self.looping {
+                    true => self.seek(self.clock % duration),
+                    false => {
+                        self.clock = duration;
+                        self.finish();
+                    }
+                }
+            }
+        }
+        let due = self.frame();
+        self.due.store(due, Ordering::Relaxed);
+        let mut latest = None;
+        loop {
+            let frame = match self.pending.take() {
+                Some(frame) => frame,
+                None => match self.frames.try_recv() {
+                    Ok(frame) => frame,
+                    Err(_) => break,
+                },
+            };
+            if frame.generation != self.generation {
+                continue;
+            }
+            if frame.index > due {
+                self.pending = Some(frame);
+                break;
+            }
+            latest = Some(frame);
+        }
+        let frame = latest?;
+        if let Some(shown) = self.shown {
+            self.dropped += frame.index.saturating_sub(shown + 1);
+        }
+        self.shown = Some(frame.index);
+        Some(frame.image)
+    }
+}
+
+#[test]
+fn test_video_player() -> anyhow::Result<()> {
+    use std::time::{Duration, Instant};
+    // 10 frames of 2x1 pixels, the red channel is the frame number
+    let mut offsets = Vec::new();
+    let mut pack = Vec::new();
+    for n in 0..10u8 {
+        offsets.push(pack.len() as u64);
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::from_pixel(2, 1, image::Rgba([n, 0, 0, 255]))
+            .write_to(&mut png, image::ImageFormat::Png)?;
+        pack.extend(png.into_inner());
+    }
+    let manifest = VideoManifest {
+        fps: 10.0,
+        frames: Vec::new(),
+        pack: Some("frames.bin".into()),
+        offsets,
+    };
+    assert_eq!(manifest.duration(), 1.0);
+    let pack = SharedData::from(pack);
+    let source = ImageSequence::new(manifest, Box::new(move |_| Ok(pack.clone())));
+    let mut video = VideoPlayer::new(source)?;
+    // gives the decoder time to fill the ring, then waits for the frame
+    let next_frame = |video: &mut VideoPlayer, dt: f64| {
+        std::thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        let mut dt = dt;
+        loop {
+            if let Some(image) = video.update(dt) {
+                return Some(image.data.data()[0]);
+            }
+            dt = 0.0;
+            if start.elapsed() > Duration::from_secs(5) {
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    };
+    // paused, the first frame shows and the clock stands still
+    assert_eq!(next_frame(&mut video, 0.5), Some(0));
+    assert_eq!(video.position(), 0.0);
+    video.play();
+    assert_eq!(next_frame(&mut video, 0.1), Some(1));
+    // a long frame skips what it missed instead of falling behind
+    assert_eq!(next_frame(&mut video, 0.35), Some(4));
+    assert_eq!(video.frame(), 4);
+    assert_eq!(video.dropped_frames(), 2);
+    video.seek(0.7);
+    assert_eq!(next_frame(&mut video, 0.0), Some(7));
+    // to the end, the last frame stays up once it finished
+    assert_eq!(next_frame(&mut video, 0.5), Some(9));
+    assert!(!video.is_playing());
+    assert!(video.take_finished());
+    assert!(!video.take_finished());
+    // looping starts over instead
+    video.set_looping(true);
+    video.play();
+    assert_eq!(next_frame(&mut video, 0.1), Some(1));
+    video.seek(0.95);
+    assert_eq!(next_frame(&mut video, 0.0), Some(9));
+    assert_eq!(next_frame(&mut video, 0.1), Some(0));
+    assert!(video.is_playing() && !video.take_finished());
+    video.skip();
+    assert_eq!(video.position(), 1.0);
+    assert!(video.take_finished());
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use super::VideoSource;
+use fool_resource::SharedData;
+use matroska_demuxer::{Frame, MatroskaFile, TrackType};
+use peniko::{Blob, Image, ImageFormat};
+use re_rav1d::pixel::{MatrixCoefficients, YUVRange};
+use re_rav1d::{Decoder, Picture, PixelLayout, PlanarImageComponent, Settings};
+use std::io::Cursor;
+use std::sync::Arc;
+
+/// the av1 video track of a webm, demuxed and decoded in rust,
+/// vp8 and vp9 have no pure rust decoder and are refused
+pub struct WebmVideo {
+    data: SharedData,
+    file: MatroskaFile<Cursor<SharedData>>,
+    track: u64,
+    fps: f64,
+    // whether each frame of the track is a keyframe, decoding starts at one
+    keyframes: Vec<bool>,
+    decoder: Decoder,
+    // the frame the demuxer hands out next
+    next: usize,
+}
+
+impl WebmVideo {
+    pub fn open(data: SharedData) -> anyhow::Result<Self> {
+        let mut file = MatroskaFile::open(Cursor::new(data.clone()))?;
+        let track = file
+            .tracks()
+            .iter()
+            .find(|track| track.track_type() == TrackType::Video)
+            .ok_or_else(|| anyhow::anyhow!("webm has no video track"))?;
+        if track.codec_id() != "V_AV1" {
+            return Err(anyhow::anyhow!(
+                "webm video is {}, only V_AV1 can be decoded",
+                track.codec_id()
+            ));
+        }
+        let (number, frame_duration) = (track.track_number().get(), track.default_duration());
+        let scale = file.info().timestamp_scale().get() as f64;
+        // listed once up front, the frames of the other tracks are skipped
+        let mut keyframes = Vec::new();
+        let (mut first, mut last) = (None, 0);
+        let mut frame = Frame::default();
+        while file.next_frame(&mut frame)? {
+            if frame.track == number {
+                // block groups don't say, only the first frame is sure to be one
+                keyframes.push(frame.is_keyframe.unwrap_or(keyframes.is_empty()));
+                first.get_or_insert(frame.timestamp);
+                last = frame.timestamp;
+            }
+        }
+        let fps = match (frame_duration, first) {
+            (Some(duration), _) => 1e9 / duration.get() as f64,
+            (None, Some(first)) if last > first => {
+                (keyframes.len() - 1) as f64 / ((last - first) as f64 * scale / 1e9)
+            }
+            _ => return Err(anyhow::anyhow!("webm video has no frame rate")),
+        };
+        let mut settings = Settings::new();
+        // a picture out for every frame in, the decode thread already runs ahead
+        settings.set_max_frame_delay(1);
+        let decoder = Decoder::with_settings(&settings)?;
+        // past the end, the first decode starts over
+        let next = keyframes.len();
+        Ok(Self {
+            data,
+            file,
+            track: number,
+            fps,
+            keyframes,
+            decoder,
+            next,
+        })
+    }
+    // the decoder starts over at `keyframe`, the frames before it are read past,
+    // the seek of the demuxer misses the last cluster of files without cues
+    fn seek(&mut self, keyframe: usize) -> anyhow::Result<()> {
+        if keyframe < self.next {
+            self.file = MatroskaFile::open(Cursor::new(self.data.clone()))?;
+            self.next = 0;
+        }
+        let mut frame = Frame::default();
+        while self.next < keyframe {
+            if !self.file.next_frame(&mut frame)? {
+                return Err(anyhow::anyhow!("webm ended before frame {}", keyframe));
+            }
+            if frame.track == self.track {
+                self.next += 1;
+            }
+        }
+        self.decoder.flush();
+        while self.decoder.get_picture().is_ok() {}
+        Ok(())
+    }
+}
+
+impl VideoSource for WebmVideo {
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+    fn frame_count(&self) -> usize {
+        self.keyframes.len()
+    }
+    fn decode(&mut self, index: usize) -> anyhow::Result<Image> {
+        let keyframe = self.keyframes[..=index]
+            .iter()
+            .rposition(|keyframe| *keyframe)
+            .unwrap_or(0);
+        // back, or ahead past a keyframe, decoding from it is shorter
+        if index < self.next || keyframe > self.next {
+            self.seek(keyframe)?;
+        }
+        let mut frame = Frame::default();
+        loop {
+            // the frames before `index` are decoded for the ones after them, never converted
+            match self.decoder.get_picture() {
+                Ok(picture) if picture.timestamp() >= Some(index as i64) => {
+                    return to_rgba(&picture);
+                }
+                Ok(_) => continue,
+                Err(err) if err.is_again() => {}
+                Err(err) => return Err(err.into()),
+            }
+            // the decoder was full, what is left of the last frame goes in first
+            match self.decoder.send_pending_data() {
+                Ok(()) => {}
+                Err(err) if err.is_again() => continue,
+                Err(err) => return Err(err.into()),
+            }
+            if !self.file.next_frame(&mut frame)? {
+                return Err(anyhow::anyhow!("webm ended before frame {}", index));
+            }
+            if frame.track != self.track {
+                continue;
+            }
+            let data = std::mem::take(&mut frame.data);
+            let sent = self
+                .decoder
+                .send_data(data, None, Some(self.next as i64), None);
+            self.next += 1;
+            match sent {
+                Ok(()) => {}
+                Err(err) if err.is_again() => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+// yuv to rgba8 with the range and matrix of the stream, bt.601 unless it says bt.709
+fn to_rgba(picture: &Picture) -> anyhow::Result<Image> {
+    if picture.bit_depth() != 8 {
+        return Err(anyhow::anyhow!(
+            "{} bit av1 is not supported, only 8 bit",
+            picture.bit_depth()
+        ));
+    }
+    let (width, height) = (picture.width() as usize, picture.height() as usize);
+    let (kr, kb) = match picture.matrix_coefficients() {
+        MatrixCoefficients::BT709 => (0.2126, 0.0722),
+        _ => (0.299, 0.114),
+    };
+    let full = matches!(picture.color_range(), YUVRange::Full);
+    // how far the chroma planes are subsampled, a shift per axis
+    let (shift_x, shift_y) = match picture.pixel_layout() {
+        PixelLayout::I420 => (1, 1),
+        PixelLayout::I422 => (1, 0),
+        PixelLayout::I444 | PixelLayout::I400 => (0, 0),
+    };
+    let luma = picture.plane(PlanarImageComponent::Y);
+    let luma_stride = picture.stride(PlanarImageComponent::Y) as usize;
+    let chroma = match picture.pixel_layout() {
+        PixelLayout::I400 => None,
+        _ => Some((
+            picture.plane(PlanarImageComponent::U),
+            picture.plane(PlanarImageComponent::V),
+            picture.stride(PlanarImageComponent::U) as usize,
+        )),
+    };
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in 0..height {
+        for col in 0..width {
+            let y = luma[row * luma_stride + col] as f32;
+            let (u, v) = match &chroma {
+                Some((u, v, stride)) => {
+                    let index = (row >> shift_y) * stride + (col >> shift_x);
+                    (u[index] as f32 - 128.0, v[index] as f32 - 128.0)
+                }
+                None => (0.0, 0.0),
+            };
+            let (y, u, v) = match full {
+                true => (y, u, v),
+                false => (
+                    (y - 16.0) * 255.0 / 219.0,
+                    u * 255.0 / 224.0,
+                    v * 255.0 / 224.0,
+                ),
+            };
+            let r = y + 2.0 * (1.0 - kr) * v;
+            let b = y + 2.0 * (1.0 - kb) * u;
+            let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+            rgba.extend([r, g, b].map(|c| c.round().clamp(0.0, 255.0) as u8));
+            rgba.push(255);
+        }
+    }
+    Ok(Image::new(
+        Blob::new(Arc::new(rgba)),
+        ImageFormat::Rgba8,
+        width as u32,
+        height as u32,
+    ))
+}
+
+#[test]
+fn test_webm_video() -> anyhow::Result<()> {
+    // 6 flat grey 64x48 frames at 25 fps, keyframes at 0 and 3, with an audio
+    // track in between, the luma of frame n is 16 + 40n
+    let data = include_bytes!("../../testdata/grey.webm");
+    let mut video = WebmVideo::open(SharedData::from(&data[..]))?;
+    assert_eq!(video.frame_count(), 6);
+    assert_eq!(video.fps(), 25.0);
+    let grey = |n: usize| ((40 * n) as f32 * 255.0 / 219.0).round() as i32;
+    let check = |video: &mut WebmVideo, index: usize| -> anyhow::Result<()> {
+        let image = video.decode(index)?;
+        assert_eq!((image.width, image.height), (64, 48));
+        let pixel = &image.data.data()[..4];
+        for channel in &pixel[..3] {
+            assert!((*channel as i32 - grey(index)).abs() <= 4, "{:?}", pixel);
+        }
+        assert_eq!(pixel[3], 255);
+        Ok(())
+    };
+    // in order, then ahead past a keyframe, then back before it
+    check(&mut video, 0)?;
+    check(&mut video, 1)?;
+    check(&mut video, 5)?;
+    check(&mut video, 2)?;
+    check(&mut video, 3)?;
+    check(&mut video, 4)?;
+    Ok(())
+}
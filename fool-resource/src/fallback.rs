@@ -1,11 +1,37 @@
 use super::resource::{ResData, ResId};
 use dyn_clone::DynClone;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 pub trait Fallback: Send + Sync + DynClone + Debug {
     type K: ResId;
     type V: ResData;
 
     fn get(&self, key: &Self::K) -> anyhow::Result<Self::V>;
+    /// cheap existence probe, override when loading is expensive
+    fn exists(&self, key: &Self::K) -> bool {
+        self.get(key).is_ok()
+    }
+    /// the keys it can provide, for listings and diagnostics, empty when it can't tell
+    fn keys(&self) -> Vec<Self::K> {
+        Vec::new()
+    }
 }
 
 dyn_clone::clone_trait_object!(<K: ResId, V: ResData> Fallback<K = K, V = V>);
+
+/// every fallback of a chain failed, each with its own reason in the order tried
+#[derive(Debug)]
+pub struct FallbackErrors {
+    pub failures: Vec<(String, anyhow::Error)>,
+}
+
+impl Display for FallbackErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} fallbacks failed", self.failures.len())?;
+        for (fallback, err) in &self.failures {
+            write!(f, "; {}: {}", fallback, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FallbackErrors {}
@@ -1,7 +1,12 @@
 mod fallback;
+mod pool;
 mod resource;
+mod router;
+mod scope;
 mod types;
-pub use fallback::Fallback;
-pub use resource::Resource;
+pub use fallback::{Fallback, FallbackErrors};
+pub use resource::{AsyncLoad, Resource};
+pub use router::ExtensionRouter;
+pub use scope::{EntryUsage, GLOBAL_SCOPE, ScopeStack};
 pub use std::path::PathBuf;
 pub use types::SharedData;
@@ -0,0 +1,81 @@
+use parking_lot::Mutex;
+use std::sync::{
+    Arc, OnceLock,
+    mpsc::{Receiver, Sender},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// loads run in the background, more workers than this only fight over the disk
+const MAX_WORKERS: usize = 4;
+
+/// a few threads running queued jobs, shared by every `Resource`
+pub struct LoadPool {
+    jobs: Mutex<Sender<Job>>,
+}
+
+impl LoadPool {
+    fn new(workers: usize) -> Self {
+        let (jobs, queue) = std::sync::mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for n in 0..workers {
+            let queue = queue.clone();
+            // a worker that didn't start drops its end of the queue, with none
+            // left `run` fails instead of queueing jobs nobody runs
+            let spawned = std::thread::Builder::new()
+                .name("fool-resource".into())
+                .spawn(move || work(&queue));
+            if let Err(err) = spawned {
+                log::error!("start resource worker {} failed: {}", n, err);
+            }
+        }
+        Self {
+            jobs: Mutex::new(jobs),
+        }
+    }
+    pub fn global() -> &'static Self {
+        static POOL: OnceLock<LoadPool> = OnceLock::new();
+        POOL.get_or_init(|| {
+            let workers = std::thread::available_parallelism().map_or(2, |n| n.get());
+            Self::new(workers.clamp(1, MAX_WORKERS))
+        })
+    }
+    /// queued behind the jobs before it, false when no worker is left to run it
+    pub fn run(&self, job: impl FnOnce() + Send + 'static) -> bool {
+        self.jobs.lock().send(Box::new(job)).is_ok()
+    }
+}
+
+fn work(queue: &Mutex<Receiver<Job>>) {
+    loop {
+        // the lock is only held while taking the next job
+        let job = queue.lock().recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
+
+#[test]
+fn test_load_pool() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let pool = LoadPool::new(2);
+    let done = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = std::sync::mpsc::channel();
+    for _ in 0..50 {
+        let (done, sender) = (done.clone(), sender.clone());
+        assert!(pool.run(move || {
+            done.fetch_add(1, Ordering::Relaxed);
+            let _ = sender.send(std::thread::current().name().map(str::to_owned));
+        }));
+    }
+    drop(sender);
+    let names: Vec<_> = receiver.iter().collect();
+    assert_eq!(done.load(Ordering::Relaxed), 50);
+    assert!(
+        names
+            .iter()
+            .all(|name| name.as_deref() == Some("fool-resource"))
+    );
+}
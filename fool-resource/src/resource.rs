@@ -1,11 +1,17 @@
-use super::Fallback;
-use dashmap::DashMap;
+use super::pool::LoadPool;
+use super::scope::{EntryUsage, GLOBAL_SCOPE, ScopeStack};
+use super::{Fallback, FallbackErrors};
+use dashmap::{DashMap, mapref::entry::Entry};
 use parking_lot::RwLock;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{Debug, Display},
     hash::Hash,
-    sync::Arc,
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender, TryRecvError},
+    },
+    time::{Duration, Instant},
 };
 macro_rules! ResourceNotFound {
     ($name: expr) => {
@@ -13,11 +19,40 @@ macro_rules! ResourceNotFound {
     };
 }
 
+// repeated failures of one key are logged at most once per window
+const FAILURE_LOG_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+struct FailureLog {
+    last: Option<Instant>,
+    suppressed: u32,
+}
+
+/// a `Resource::get_async` in flight
+#[derive(Debug)]
+pub struct AsyncLoad<V> {
+    receiver: Receiver<anyhow::Result<V>>,
+}
+
+impl<V> AsyncLoad<V> {
+    /// None while it is still loading, the result is handed out once
+    pub fn poll(&self) -> Option<anyhow::Result<V>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow::anyhow!("load worker exited"))),
+        }
+    }
+}
+
 pub trait ResId: Hash + Eq + Clone + Default + Display + Debug {}
 pub trait ResData: Clone {}
 impl<T> ResId for T where T: Hash + Eq + Clone + Default + Display + Debug {}
 impl<T> ResData for T where T: Clone {}
 
+// a fallback of the chain, higher priorities are tried first
+type Ranked<K, V> = (i32, Box<dyn Fallback<K = K, V = V>>);
+
 #[derive(Clone, Debug)]
 pub struct Resource<K, V>
 where
@@ -25,7 +60,14 @@ where
     V: ResData,
 {
     data: Arc<DashMap<K, V>>,
-    fall_back: Arc<RwLock<Option<Box<dyn Fallback<K = K, V = V>>>>>,
+    // tried in order until one has the value, sorted by priority
+    fall_back: Arc<RwLock<Vec<Ranked<K, V>>>>,
+    failures: Arc<DashMap<K, FailureLog>>,
+    scopes: Arc<RwLock<ScopeStack>>,
+    // scope name -> references, an entry without tags left is dropped
+    tags: Arc<DashMap<K, BTreeMap<String, u32>>>,
+    // `get_async` loads in flight, the later requests of a key wait on the first
+    loading: Arc<DashMap<K, Vec<Sender<anyhow::Result<V>>>>>,
 }
 
 impl<K: ResId, V: ResData> Default for Resource<K, V> {
@@ -33,6 +75,10 @@ impl<K: ResId, V: ResData> Default for Resource<K, V> {
         Resource {
             data: Default::default(),
             fall_back: Default::default(),
+            failures: Default::default(),
+            scopes: Default::default(),
+            tags: Default::default(),
+            loading: Default::default(),
         }
     }
 }
@@ -44,61 +90,472 @@ where
     pub fn from_fallback(fall_back: impl Fallback<K = K, V = V> + 'static) -> Self {
         Self {
             data: Default::default(),
-            fall_back: Arc::new(RwLock::new(Some(Box::new(fall_back)))),
+            fall_back: Arc::new(RwLock::new(vec![(0, Box::new(fall_back))])),
+            ..Default::default()
         }
     }
     pub fn empty() -> Self {
         Self::default()
     }
+    /// replaces the whole chain with `fall_back`, at priority 0
     pub fn set_fall_back(&self, fall_back: impl Fallback<K = K, V = V> + 'static) {
-        self.fall_back.write().replace(Box::new(fall_back));
+        *self.fall_back.write() = vec![(0, Box::new(fall_back))];
+    }
+    /// higher priorities are tried first, after the ones of the same priority added before
+    pub fn push_fallback(&self, fall_back: impl Fallback<K = K, V = V> + 'static, priority: i32) {
+        let mut chain = self.fall_back.write();
+        let at = chain
+            .iter()
+            .position(|(other, _)| *other < priority)
+            .unwrap_or(chain.len());
+        chain.insert(at, (priority, Box::new(fall_back)));
+    }
+    /// tried before every fallback set so far, at the priority of the first one
+    pub fn prepend_fallback(&self, fall_back: impl Fallback<K = K, V = V> + 'static) {
+        let mut chain = self.fall_back.write();
+        let priority = chain.first().map_or(0, |(priority, _)| *priority);
+        chain.insert(0, (priority, Box::new(fall_back)));
+    }
+    /// follow a scope stack shared with other caches instead of this cache's own
+    pub fn set_scopes(&self, scopes: ScopeStack) {
+        *self.scopes.write() = scopes;
     }
     pub fn load_from_map<KK: Into<K>, VV: Into<V>>(&self, map: HashMap<KK, VV>) {
         for (k, v) in map {
             let key = k.into();
             let data = v.into();
+            self.tag(&key, true);
             self.data.insert(key, data);
         }
     }
     pub fn load(&self, name: impl Into<K>, data: impl Into<V>) {
-        self.data.insert(name.into(), data.into());
+        let name = name.into();
+        self.tag(&name, true);
+        self.data.insert(name, data.into());
     }
+    /// when every fallback failed the source is the error of the only one, or a
+    /// `FallbackErrors` with the reason of each
     pub fn get(&self, name: impl Into<K>) -> anyhow::Result<V> {
         let name = name.into();
-        match self.data.get(&name) {
-            Some(v) => Ok(v.value().clone()),
-            None => match &self.fall_back.read().as_ref() {
-                Some(fb) => match fb.get(&name) {
-                    Ok(data) => {
-                        let data = data;
-                        log::trace!("load {} from Fallback {:?} succeed!!", &name, fb);
-                        self.data.insert(name, data.clone());
-                        Ok(data)
+        match self.load_with_fallback(&name) {
+            Ok(data) => Ok(data),
+            Err(Some(err)) => {
+                self.log_failure(&name, &err);
+                Err(err.context(ResourceNotFound!(name)))
+            }
+            Err(None) => Err(ResourceNotFound!(name)),
+        }
+    }
+    /// `get` on the shared load workers so slow fallbacks don't block the frame, the
+    /// value is cached like `get` does, one already loaded is ready right away and
+    /// a key already loading is loaded once for every request of it
+    pub fn get_async(&self, name: impl Into<K>) -> AsyncLoad<V>
+    where
+        K: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        let name = name.into();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        if let Some(v) = self.data.get(&name).map(|v| v.value().clone()) {
+            self.tag(&name, false);
+            let _ = sender.send(Ok(v));
+            return AsyncLoad { receiver };
+        }
+        match self.loading.entry(name.clone()) {
+            Entry::Occupied(mut waiting) => {
+                waiting.get_mut().push(sender);
+                return AsyncLoad { receiver };
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(vec![sender]);
+            }
+        }
+        let resource = self.clone();
+        let key = name.clone();
+        let queued = LoadPool::global().run(move || {
+            // cached before the waiters are taken, a request after this hits the cache
+            let result = resource.get(key.clone());
+            let waiting = resource.loading.remove(&key).map(|(_, waiting)| waiting);
+            let mut waiting = waiting.unwrap_or_default().into_iter();
+            match result {
+                Ok(v) => waiting.for_each(|sender| {
+                    let _ = sender.send(Ok(v.clone()));
+                }),
+                Err(err) => {
+                    let first = waiting.next();
+                    for sender in waiting {
+                        let _ = sender.send(Err(anyhow::anyhow!("{:#}", err)));
                     }
-                    Err(err) => {
-                        log::trace!("load {} from Fallback {:?} failed: {}!", &name, fb, err);
-                        Err(ResourceNotFound!(name))
+                    // the first request gets the error itself
+                    if let Some(sender) = first {
+                        let _ = sender.send(Err(err));
                     }
-                },
-                None => Err(ResourceNotFound!(name)),
-            },
+                }
+            }
+        });
+        // the senders are dropped, `poll` reports it
+        if !queued {
+            log::error!("load {} failed: no resource worker running", name);
+            self.loading.remove(&name);
+        }
+        AsyncLoad { receiver }
+    }
+    /// like `get` but quiet, for probing optional resources
+    pub fn try_get(&self, name: impl Into<K>) -> Option<V> {
+        self.load_with_fallback(&name.into()).ok()
+    }
+    fn load_with_fallback(&self, name: &K) -> Result<V, Option<anyhow::Error>> {
+        if let Some(v) = self.data.get(name).map(|v| v.value().clone()) {
+            self.tag(name, false);
+            return Ok(v);
+        }
+        let mut failures = Vec::new();
+        for (_, fb) in self.fall_back.read().iter() {
+            match fb.get(name) {
+                Ok(data) => {
+                    log::trace!("load {} from Fallback {:?} succeed!!", name, fb);
+                    self.failures.remove(name);
+                    self.tag(name, true);
+                    self.data.insert(name.clone(), data.clone());
+                    return Ok(data);
+                }
+                Err(err) => failures.push((format!("{:?}", fb), err)),
+            }
+        }
+        match failures.len() {
+            0 => Err(None),
+            1 => Err(failures.pop().map(|(_, err)| err)),
+            _ => Err(Some(FallbackErrors { failures }.into())),
+        }
+    }
+    /// tags `name` with the innermost scope, a cache hit at the global level
+    /// leaves the tags alone so it does not pin entries of released scopes
+    fn tag(&self, name: &K, loaded: bool) {
+        self.scopes.read().with_current(|scope| {
+            let scope = match scope {
+                Some(scope) => scope,
+                None if loaded => GLOBAL_SCOPE,
+                None => return,
+            };
+            let tagged = self.tags.get(name).map(|tags| tags.contains_key(scope));
+            if tagged == Some(true) {
+                return;
+            }
+            let mut tags = self.tags.entry(name.clone()).or_default();
+            tags.entry(scope.to_owned()).or_insert(1);
+        })
+    }
+    /// one more reference from the innermost scope, false when `name` is not loaded
+    pub fn retain(&self, name: impl Into<K>) -> bool {
+        let name = name.into();
+        if !self.data.contains_key(&name) {
+            return false;
+        }
+        let scope = self.scopes.read().current();
+        let scope = scope.unwrap_or_else(|| GLOBAL_SCOPE.to_owned());
+        *self.tags.entry(name).or_default().entry(scope).or_insert(0) += 1;
+        true
+    }
+    /// drops one reference of the innermost scope, the entry goes with
+    /// its last reference, true when it was dropped
+    pub fn release(&self, name: impl Into<K>) -> bool {
+        let name = name.into();
+        let scope = self.scopes.read().current();
+        let scope = scope.as_deref().unwrap_or(GLOBAL_SCOPE);
+        let drop = match self.tags.get_mut(&name) {
+            Some(mut tags) => {
+                if let Some(count) = tags.get_mut(scope) {
+                    *count = count.saturating_sub(1);
+                    if *count == 0 {
+                        tags.remove(scope);
+                    }
+                }
+                tags.is_empty()
+            }
+            None => false,
+        };
+        if drop {
+            self.remove(name);
+        }
+        drop
+    }
+    /// kept until removed explicitly, whatever scope loaded it
+    pub fn pin(&self, name: impl Into<K>) {
+        let name = name.into();
+        let mut tags = self.tags.entry(name).or_default();
+        tags.entry(GLOBAL_SCOPE.to_owned()).or_insert(1);
+    }
+    /// drops every entry only `scope` held, entries another scope holds too survive
+    pub fn release_scope(&self, scope: &str) -> Vec<K> {
+        let mut released = Vec::new();
+        self.tags.retain(|name, tags| {
+            if tags.remove(scope).is_none() || !tags.is_empty() {
+                return true;
+            }
+            released.push(name.clone());
+            false
+        });
+        for name in &released {
+            self.data.remove(name);
+        }
+        if !released.is_empty() {
+            log::debug!("scope {} released {} resources", scope, released.len());
         }
+        released
+    }
+    /// every loaded entry with its scopes, `size_of` measures a value in bytes
+    pub fn usage(&self, size_of: impl Fn(&V) -> usize) -> Vec<EntryUsage<K>> {
+        self.data
+            .iter()
+            .map(|entry| EntryUsage {
+                name: entry.key().clone(),
+                scopes: self
+                    .tags
+                    .get(entry.key())
+                    .map(|tags| tags.keys().cloned().collect())
+                    .unwrap_or_default(),
+                bytes: size_of(entry.value()),
+            })
+            .collect()
+    }
+    fn log_failure(&self, name: &K, err: &anyhow::Error) {
+        let now = Instant::now();
+        let mut entry = self.failures.entry(name.clone()).or_insert(FailureLog {
+            last: None,
+            suppressed: 0,
+        });
+        if entry
+            .last
+            .is_some_and(|last| now.duration_since(last) < FAILURE_LOG_WINDOW)
+        {
+            entry.suppressed += 1;
+            return;
+        }
+        if entry.suppressed > 0 {
+            log::warn!(
+                "load {} failed: {} ({} repeated failures suppressed)",
+                name,
+                err,
+                entry.suppressed
+            );
+        } else {
+            log::warn!("load {} failed: {}", name, err);
+        }
+        entry.last = Some(now);
+        entry.suppressed = 0;
     }
     pub fn remove(&self, path: impl Into<K>) {
         let path = path.into();
         self.data.remove(&path);
+        self.tags.remove(&path);
         log::trace!("remove resource: {}", &path);
     }
+    /// drops every loaded value, the fallback loads them again on next use
+    pub fn clear(&self) {
+        self.data.clear();
+        self.failures.clear();
+        self.tags.clear();
+    }
     pub fn exists(&self, name: impl Into<K>) -> bool {
         self.data.contains_key(&name.into())
     }
+    /// loaded or available from the fallback
+    pub fn contains(&self, name: impl Into<K>) -> bool {
+        let name = name.into();
+        self.data.contains_key(&name)
+            || self.fall_back.read().iter().any(|(_, fb)| fb.exists(&name))
+    }
     pub fn list_names(&self) -> Vec<K> {
         self.data
             .iter()
             .map(|x| x.key().clone())
             .collect::<Vec<K>>()
     }
+    /// the loaded names and the ones the fallbacks can provide, without loading any
+    pub fn list_available(&self) -> Vec<K> {
+        let mut names: HashSet<K> = self.data.iter().map(|x| x.key().clone()).collect();
+        for (_, fallback) in self.fall_back.read().iter() {
+            names.extend(fallback.keys());
+        }
+        names.into_iter().collect()
+    }
     pub fn count(&self) -> usize {
         self.data.len()
     }
 }
+
+#[test]
+fn test_try_get() {
+    #[derive(Debug, Clone)]
+    struct Missing;
+    impl Fallback for Missing {
+        type K = String;
+        type V = u32;
+        fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+            Err(anyhow::anyhow!("{} missing", key))
+        }
+    }
+    let res = Resource::from_fallback(Missing);
+    res.load("a", 1u32);
+    assert_eq!(res.try_get("a"), Some(1));
+    assert_eq!(res.try_get("b"), None);
+    assert!(res.contains("a"));
+    assert!(!res.contains("b"));
+    // the first failure is logged, the rest within the window are counted
+    for _ in 0..3 {
+        assert!(res.get("b").is_err());
+    }
+    assert_eq!(res.failures.get("b").map(|f| f.suppressed), Some(2));
+}
+
+#[test]
+fn test_scoped_release() {
+    let scopes = ScopeStack::default();
+    let res = Resource::<String, u32>::empty();
+    res.set_scopes(scopes.clone());
+    res.load("ui", 1u32);
+    scopes.push("world");
+    res.load("map", 2u32);
+    scopes.push("forest");
+    res.load("tree", 3u32);
+    // used again by the inner scope, the outer one keeps it
+    assert_eq!(res.get("map").unwrap(), 2);
+    assert_eq!(res.get("ui").unwrap(), 1);
+    res.load("boss", 4u32);
+    res.pin("boss");
+    res.load("leaf", 5u32);
+    assert!(res.retain("leaf"));
+    assert!(!res.release("leaf"));
+    assert!(res.release("leaf"));
+    assert!(!res.exists("leaf"));
+
+    let usage = res.usage(|_| 4);
+    let map = usage.iter().find(|entry| entry.name == "map").unwrap();
+    assert_eq!(map.scopes, ["forest", "world"]);
+    assert_eq!(scopes.pop().as_deref(), Some("forest"));
+    assert_eq!(res.release_scope("forest"), ["tree"]);
+    assert!(res.exists("map") && res.exists("ui") && res.exists("boss"));
+    scopes.pop();
+    assert_eq!(res.release_scope("world"), ["map"]);
+    // a hit at the global level does not pin anything
+    assert_eq!(res.get("boss").unwrap(), 4);
+    assert_eq!(res.count(), 2);
+}
+
+#[test]
+fn test_fallback_chain() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[derive(Debug, Clone)]
+    struct Source(&'static str, Option<u32>, Arc<AtomicUsize>);
+    impl Fallback for Source {
+        type K = String;
+        type V = u32;
+        fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+            self.2.fetch_add(1, Ordering::Relaxed);
+            self.1
+                .ok_or_else(|| anyhow::anyhow!("{} not in {}", key, self.0))
+        }
+    }
+    let source = |name, value| Source(name, value, Default::default());
+    let (missing, pak) = (source("override", None), source("pak", Some(2)));
+    let res = Resource::from_fallback(missing.clone());
+    res.push_fallback(pak.clone(), 0);
+    for _ in 0..3 {
+        assert_eq!(res.get("a").unwrap(), 2);
+    }
+    // the first missed and the second hit once, the cache did the rest
+    assert_eq!(missing.2.load(Ordering::Relaxed), 1);
+    assert_eq!(pak.2.load(Ordering::Relaxed), 1);
+    assert!(res.contains("b"));
+    // a higher priority goes first, whenever it was added
+    res.push_fallback(source("patch", Some(3)), 10);
+    assert_eq!(res.get("c").unwrap(), 3);
+
+    res.set_fall_back(source("override", None));
+    let err = res.get("b").unwrap_err();
+    assert_eq!(err.root_cause().to_string(), "b not in override");
+    res.push_fallback(source("cdn", None), -1);
+    let err = res.get("b").unwrap_err();
+    assert_eq!(err.to_string(), "Resource b Not Found!");
+    // every fallback tried with its reason, in order
+    let failures = &err.downcast_ref::<FallbackErrors>().unwrap().failures;
+    let failures = failures
+        .iter()
+        .map(|(_, err)| err.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(failures, ["b not in override", "b not in cdn"]);
+    assert!(
+        err.root_cause()
+            .to_string()
+            .starts_with("2 fallbacks failed; Source(\"override\"")
+    );
+    // loaded by the replaced chain
+    assert_eq!(res.get("a").unwrap(), 2);
+}
+
+#[test]
+fn test_get_async() {
+    #[derive(Debug, Clone)]
+    struct Slow;
+    impl Fallback for Slow {
+        type K = String;
+        type V = u32;
+        fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+            std::thread::sleep(Duration::from_millis(20));
+            key.parse()
+                .map_err(|_| anyhow::anyhow!("{} is not a number", key))
+        }
+    }
+    let res = Resource::from_fallback(Slow);
+    let wait = |load: AsyncLoad<u32>| loop {
+        if let Some(result) = load.poll() {
+            return result;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    let load = res.get_async("7");
+    assert!(load.poll().is_none());
+    assert_eq!(wait(load).unwrap(), 7);
+    assert!(res.exists("7"));
+    // cached, ready without waiting
+    assert_eq!(res.get_async("7").poll().unwrap().unwrap(), 7);
+    assert!(wait(res.get_async("seven")).is_err());
+}
+
+#[test]
+fn test_get_async_shared() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[derive(Debug, Clone)]
+    struct Counted(Arc<AtomicUsize>);
+    impl Fallback for Counted {
+        type K = String;
+        type V = u32;
+        fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            std::thread::sleep(Duration::from_millis(50));
+            key.parse()
+                .map_err(|_| anyhow::anyhow!("{} is not a number", key))
+        }
+    }
+    let calls = Arc::new(AtomicUsize::new(0));
+    let res = Resource::from_fallback(Counted(calls.clone()));
+    let wait = |load: AsyncLoad<u32>| loop {
+        if let Some(result) = load.poll() {
+            return result;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    // the second request of a key waits on the load of the first
+    let (first, second) = (res.get_async("3"), res.get_async("3"));
+    assert_eq!(wait(first).unwrap(), 3);
+    assert_eq!(wait(second).unwrap(), 3);
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    // both get the failure
+    let (first, second) = (res.get_async("x"), res.get_async("x"));
+    let err = wait(first).unwrap_err();
+    assert_eq!(err.root_cause().to_string(), "x is not a number");
+    let err = wait(second).unwrap_err();
+    assert!(err.to_string().contains("x is not a number"));
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    assert!(res.loading.is_empty());
+}
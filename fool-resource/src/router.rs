@@ -0,0 +1,165 @@
+use super::Fallback;
+use super::resource::{ResData, ResId};
+use std::fmt::Debug;
+
+// the extensions of a route and where they are loaded from
+type Route<K, V> = (Vec<String>, Box<dyn Fallback<K = K, V = V>>);
+
+/// sends each key to the fallback of its extension, e.g. the scripts from one mount
+/// and the images from another, the keys without a route go to the default one
+#[derive(Clone)]
+pub struct ExtensionRouter<K: ResId, V: ResData> {
+    // lowercase extensions without the dot
+    routes: Vec<Route<K, V>>,
+    default: Option<Box<dyn Fallback<K = K, V = V>>>,
+}
+
+// named in the errors of a chain, without asking V to be Debug
+impl<K: ResId, V: ResData> Debug for ExtensionRouter<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtensionRouter")
+            .field("routes", &self.routes)
+            .field("default", &self.default)
+            .finish()
+    }
+}
+
+impl<K: ResId, V: ResData> Default for ExtensionRouter<K, V> {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            default: None,
+        }
+    }
+}
+
+impl<K: ResId, V: ResData> ExtensionRouter<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// `extensions` like "lua" or ".lua", matched without case, an earlier route
+    /// for the same extension wins
+    pub fn route(
+        mut self,
+        extensions: &[&str],
+        fallback: impl Fallback<K = K, V = V> + 'static,
+    ) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        self.routes.push((extensions, Box::new(fallback)));
+        self
+    }
+    /// for the keys no route takes
+    pub fn otherwise(mut self, fallback: impl Fallback<K = K, V = V> + 'static) -> Self {
+        self.default = Some(Box::new(fallback));
+        self
+    }
+    fn extension(key: &K) -> Option<String> {
+        let key = key.to_string();
+        let name = key.rsplit(['/', '\\']).next().unwrap_or(&key);
+        name.rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+    }
+    // the index of the route taking `key`
+    fn route_of(&self, key: &K) -> Option<usize> {
+        let ext = Self::extension(key)?;
+        self.routes
+            .iter()
+            .position(|(extensions, _)| extensions.contains(&ext))
+    }
+    fn fallback_of(&self, key: &K) -> Option<&dyn Fallback<K = K, V = V>> {
+        match self.route_of(key) {
+            Some(index) => Some(self.routes[index].1.as_ref()),
+            None => self.default.as_deref(),
+        }
+    }
+}
+
+impl<K, V> Fallback for ExtensionRouter<K, V>
+where
+    K: ResId + Send + Sync + 'static,
+    V: ResData + Send + Sync + 'static,
+{
+    type K = K;
+    type V = V;
+    fn get(&self, key: &K) -> anyhow::Result<V> {
+        match self.fallback_of(key) {
+            Some(fallback) => fallback.get(key),
+            None => Err(anyhow::anyhow!("no fallback routed for {}", key)),
+        }
+    }
+    fn exists(&self, key: &K) -> bool {
+        self.fallback_of(key)
+            .is_some_and(|fallback| fallback.exists(key))
+    }
+    /// each fallback only for the keys routed to it
+    fn keys(&self) -> Vec<K> {
+        let mut keys = Vec::new();
+        for (index, (_, fallback)) in self.routes.iter().enumerate() {
+            let routed = fallback.keys().into_iter();
+            keys.extend(routed.filter(|key| self.route_of(key) == Some(index)));
+        }
+        if let Some(default) = &self.default {
+            let rest = default.keys().into_iter();
+            keys.extend(rest.filter(|key| self.route_of(key).is_none()));
+        }
+        keys
+    }
+}
+
+#[test]
+fn test_extension_router() {
+    use super::Resource;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    // answers every key with its name and counts the loads
+    #[derive(Debug, Clone)]
+    struct Mount(&'static str, Arc<AtomicUsize>);
+    impl Fallback for Mount {
+        type K = String;
+        type V = String;
+        fn get(&self, _key: &String) -> anyhow::Result<String> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            Ok(self.0.to_owned())
+        }
+        fn keys(&self) -> Vec<String> {
+            vec![
+                "main.lua".to_owned(),
+                "image/a.PNG".to_owned(),
+                "font.ttf".to_owned(),
+            ]
+        }
+    }
+    let (scripts, textures, base) = (
+        Mount("scripts", Default::default()),
+        Mount("textures", Default::default()),
+        Mount("base", Default::default()),
+    );
+    let router = ExtensionRouter::new()
+        .route(&["lua", ".toml"], scripts.clone())
+        .route(&["png", "jpg"], textures.clone())
+        .otherwise(base.clone());
+    // each key listed once, by the fallback it is routed to
+    let mut keys = router.keys();
+    keys.sort();
+    assert_eq!(keys, ["font.ttf", "image/a.PNG", "main.lua"]);
+    let res = Resource::from_fallback(router);
+    for _ in 0..2 {
+        assert_eq!(res.get("main.lua").unwrap(), "scripts");
+        assert_eq!(res.get("config/game.toml").unwrap(), "scripts");
+        assert_eq!(res.get("image/a.PNG").unwrap(), "textures");
+        assert_eq!(res.get("font.ttf").unwrap(), "base");
+        // the dot of the folder is not an extension
+        assert_eq!(res.get("v1.2/readme").unwrap(), "base");
+    }
+    // cached on the first get of each key
+    assert_eq!(scripts.1.load(Ordering::Relaxed), 2);
+    assert_eq!(textures.1.load(Ordering::Relaxed), 1);
+    assert_eq!(base.1.load(Ordering::Relaxed), 2);
+
+    let unrouted = Resource::from_fallback(ExtensionRouter::new().route(&["lua"], scripts));
+    assert!(unrouted.get("a.png").is_err());
+    assert!(!unrouted.contains("a.png"));
+}
@@ -0,0 +1,42 @@
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// the tag of entries loaded outside any scope or pinned, no scope release drops them
+pub const GLOBAL_SCOPE: &str = "global";
+
+/// the stack of loading scopes, every cache sharing it tags what it loads
+/// with the innermost scope
+#[derive(Debug, Clone, Default)]
+pub struct ScopeStack(Arc<RwLock<Vec<String>>>);
+
+impl ScopeStack {
+    pub fn push(&self, name: impl Into<String>) {
+        self.0.write().push(name.into());
+    }
+    pub fn pop(&self) -> Option<String> {
+        self.0.write().pop()
+    }
+    /// the innermost scope, None at the global level
+    pub fn current(&self) -> Option<String> {
+        self.0.read().last().cloned()
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.read().iter().any(|scope| scope == name)
+    }
+    /// outermost first
+    pub fn names(&self) -> Vec<String> {
+        self.0.read().clone()
+    }
+    pub(crate) fn with_current<R>(&self, f: impl FnOnce(Option<&str>) -> R) -> R {
+        f(self.0.read().last().map(String::as_str))
+    }
+}
+
+/// one cached entry as seen by a memory report
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryUsage<K> {
+    pub name: K,
+    /// every scope holding it, sorted
+    pub scopes: Vec<String>,
+    pub bytes: usize,
+}
@@ -1,29 +1,70 @@
 pub use image::DynamicImage;
-use std::{ops::Deref, sync::Arc};
+use memmap2::Mmap;
+use std::{
+    fmt::Debug,
+    ops::{Deref, Range},
+    sync::Arc,
+};
 
-#[repr(transparent)]
-#[derive(Debug, Clone, Default)]
-pub struct SharedData(Arc<[u8]>);
+#[derive(Clone)]
+enum Backing {
+    Owned(Arc<[u8]>),
+    Mapped(Arc<Mmap>),
+}
+impl Backing {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Owned(data) => data,
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+/// cheap to clone view over owned or memory mapped bytes
+#[derive(Clone)]
+pub struct SharedData {
+    backing: Backing,
+    offset: usize,
+    len: usize,
+}
+impl Default for SharedData {
+    fn default() -> Self {
+        Self::from_arc(Arc::from(Vec::new().into_boxed_slice()))
+    }
+}
+impl Debug for SharedData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.backing {
+            Backing::Owned(_) => "Owned",
+            Backing::Mapped(_) => "Mapped",
+        };
+        write!(
+            f,
+            "SharedData({}, offset: {}, len: {})",
+            kind, self.offset, self.len
+        )
+    }
+}
 impl Deref for SharedData {
     type Target = [u8];
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.backing.bytes()[self.offset..self.offset + self.len]
     }
 }
 impl AsRef<[u8]> for SharedData {
     fn as_ref(&self) -> &[u8] {
-        &self.0
+        self
     }
 }
 
 impl From<&[u8]> for SharedData {
     fn from(value: &[u8]) -> Self {
-        SharedData(Arc::from(value.to_vec().into_boxed_slice()))
+        SharedData::from_arc(Arc::from(value.to_vec().into_boxed_slice()))
     }
 }
 impl From<&str> for SharedData {
     fn from(value: &str) -> Self {
-        SharedData(Arc::from(value.as_bytes()))
+        SharedData::from_arc(Arc::from(value.as_bytes()))
     }
 }
 impl From<Vec<u8>> for SharedData {
@@ -32,22 +73,75 @@ impl From<Vec<u8>> for SharedData {
     }
 }
 impl SharedData {
+    fn from_arc(data: Arc<[u8]>) -> Self {
+        let len = data.len();
+        Self {
+            backing: Backing::Owned(data),
+            offset: 0,
+            len,
+        }
+    }
     pub fn from_vec(data: Vec<u8>) -> Self {
-        Self(Arc::from(data.into_boxed_slice()))
+        Self::from_arc(Arc::from(data.into_boxed_slice()))
     }
     pub fn from_static(data: &'static [u8]) -> Self {
-        Self(Arc::from(data))
+        Self::from_arc(Arc::from(data))
+    }
+    /// view `range` of a mapped file without copying
+    pub fn from_mmap(map: Arc<Mmap>, range: Range<usize>) -> anyhow::Result<Self> {
+        if range.start > range.end || range.end > map.len() {
+            anyhow::bail!(
+                "range {:?} out of bounds of mapping with len {}",
+                range,
+                map.len()
+            );
+        }
+        Ok(Self {
+            backing: Backing::Mapped(map),
+            offset: range.start,
+            len: range.end - range.start,
+        })
+    }
+    /// sub view sharing the same backing buffer
+    pub fn slice(&self, range: Range<usize>) -> anyhow::Result<Self> {
+        if range.start > range.end || range.end > self.len {
+            anyhow::bail!(
+                "range {:?} out of bounds of data with len {}",
+                range,
+                self.len
+            );
+        }
+        Ok(Self {
+            backing: self.backing.clone(),
+            offset: self.offset + range.start,
+            len: range.end - range.start,
+        })
+    }
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.backing, Backing::Mapped(_))
     }
     pub fn to_image(&self) -> anyhow::Result<DynamicImage> {
-        Ok(image::load_from_memory(&self)?)
+        Ok(image::load_from_memory(self)?)
     }
     pub fn to_string(&self) -> anyhow::Result<String> {
-        Ok(String::from_utf8(self.0.to_vec())?)
+        Ok(std::str::from_utf8(self)?.to_string())
     }
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.len
     }
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.len == 0
     }
 }
+
+#[test]
+fn test_slice() -> anyhow::Result<()> {
+    let data = SharedData::from("hello world");
+    let world = data.slice(6..11)?;
+    assert_eq!(&*world, b"world");
+    assert_eq!(&*world.slice(1..3)?, b"or");
+    assert!(data.slice(6..12).is_err());
+    // slices share the backing buffer
+    assert_eq!(world.as_ptr(), data[6..].as_ptr());
+    Ok(())
+}
@@ -0,0 +1,335 @@
+use mlua::{FromLuaMulti, Function, IntoLuaMulti, Lua, MultiValue, Table, Value};
+use std::fmt::Display;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+const TRACEBACK_HEADER: &str = "stack traceback:";
+const TRACED_CALL_KEY: &str = "__fool_script_traced_call";
+
+/// a lua error with where it was raised, instead of one formatted line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+    /// the chunk it was raised in, e.g. `<@MemModule>/ui.menu.lua`
+    pub chunk: Option<String>,
+    pub line: Option<u32>,
+    /// the module being loaded, set by the searchers
+    pub module: Option<String>,
+    /// `stack traceback:` and a line per frame, innermost first
+    pub traceback: Option<String>,
+    /// the error this one was raised for, e.g. in the module a `require` failed to load
+    pub cause: Option<Box<ScriptError>>,
+    /// a rust panic in a function called from lua
+    pub panic: bool,
+}
+
+impl ScriptError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+    /// a message as lua formats it, `chunk:line: message` and maybe a traceback
+    pub fn from_message(message: &str) -> Self {
+        let (message, traceback) = match message.split_once(TRACEBACK_HEADER) {
+            Some((message, frames)) => (
+                message.trim_end(),
+                Some(format!("{}{}", TRACEBACK_HEADER, frames)),
+            ),
+            None => (message, None),
+        };
+        let err = match split_location(message) {
+            Some((chunk, line, message)) => Self {
+                chunk: Some(chunk.to_owned()),
+                line: Some(line),
+                ..Self::new(message)
+            },
+            None => Self::new(message),
+        };
+        err.with_traceback(traceback)
+    }
+    fn with_traceback(mut self, traceback: Option<String>) -> Self {
+        if traceback.is_some() {
+            self.traceback = traceback;
+        }
+        self.locate_from_traceback();
+        self
+    }
+    fn panic(message: String, traceback: String) -> Self {
+        Self {
+            panic: true,
+            ..Self::new(format!("rust panic: {}", message))
+        }
+        .with_traceback(Some(traceback))
+    }
+    /// `err` raised at the frames of `traceback`, an error of another script it came from
+    /// becomes the cause
+    fn traced(err: &mlua::Error, traceback: String) -> Self {
+        let traced = match find_script_error(err) {
+            Some(inner) => Self {
+                message: match &inner.module {
+                    Some(module) => format!("error loading module '{}'", module),
+                    None => inner.message.clone(),
+                },
+                cause: Some(Box::new(inner.clone())),
+                ..Default::default()
+            },
+            None => Self::convert(err),
+        };
+        traced.with_traceback(Some(traceback))
+    }
+    fn convert(err: &mlua::Error) -> Self {
+        match err {
+            mlua::Error::CallbackError { traceback, cause } => {
+                Self::traced(cause, traceback.clone())
+            }
+            mlua::Error::ExternalError(external) => match external.downcast_ref::<ScriptError>() {
+                Some(err) => err.clone(),
+                None => Self::new(external.to_string()),
+            },
+            mlua::Error::WithContext { context, cause } => {
+                let mut err = Self::convert(cause);
+                err.message = format!("{}: {}", context, err.message);
+                err
+            }
+            mlua::Error::SyntaxError { message, .. } => Self::from_message(message),
+            mlua::Error::RuntimeError(message) => Self::from_message(message),
+            err => Self::from_message(&err.to_string()),
+        }
+    }
+    // errors raised by rust functions carry no location, the innermost lua frame has it
+    fn locate_from_traceback(&mut self) {
+        if self.line.is_some() {
+            return;
+        }
+        let Some(traceback) = &self.traceback else {
+            return;
+        };
+        let frame = traceback
+            .lines()
+            .skip(1)
+            .find_map(|frame| split_location(frame.trim()));
+        if let Some((chunk, line, _)) = frame {
+            self.chunk = Some(chunk.to_owned());
+            self.line = Some(line);
+        }
+    }
+    /// the module it was raised in, when loaded by the memory searcher
+    pub fn in_module(mut self, module: &str) -> Self {
+        if self.module.is_none() {
+            self.module = Some(module.to_owned());
+        }
+        self
+    }
+    /// `module:line` or `chunk:line`
+    pub fn location(&self) -> Option<String> {
+        let name = self.module.as_ref().or(self.chunk.as_ref())?;
+        Some(match self.line {
+            Some(line) => format!("{}:{}", name, line),
+            None => name.clone(),
+        })
+    }
+    /// this error and every cause, outermost first
+    pub fn chain(&self) -> impl Iterator<Item = &ScriptError> {
+        std::iter::successors(Some(self), |err| err.cause.as_deref())
+    }
+    /// the innermost cause, where it went wrong first
+    pub fn root(&self) -> &ScriptError {
+        self.chain().last().unwrap_or(self)
+    }
+    fn write_one(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location() {
+            Some(location) => write!(f, "{}: {}", location, self.message)?,
+            None => write!(f, "{}", self.message)?,
+        }
+        if let (true, Some(traceback)) = (f.alternate(), &self.traceback) {
+            write!(f, "\n{}", traceback)?;
+        }
+        Ok(())
+    }
+}
+
+/// `{:#}` adds the tracebacks
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_one(f)?;
+        for cause in self.chain().skip(1) {
+            write!(f, "\ncaused by ")?;
+            cause.write_one(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(err: mlua::Error) -> Self {
+        Self::convert(&err)
+    }
+}
+
+impl From<ScriptError> for mlua::Error {
+    fn from(err: ScriptError) -> Self {
+        mlua::Error::external(err)
+    }
+}
+
+// the script error the searchers wrapped, through the callbacks it was passed up by
+fn find_script_error(err: &mlua::Error) -> Option<&ScriptError> {
+    match err {
+        mlua::Error::CallbackError { cause, .. } => find_script_error(cause),
+        mlua::Error::WithContext { cause, .. } => find_script_error(cause),
+        mlua::Error::ExternalError(external) => external.downcast_ref::<ScriptError>(),
+        _ => None,
+    }
+}
+
+// `[string "name"]:12: message` or `name:12: message`
+fn split_location(message: &str) -> Option<(&str, u32, &str)> {
+    for (index, _) in message.match_indices(':') {
+        let rest = &message[index + 1..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 || !rest[digits..].starts_with(':') {
+            continue;
+        }
+        let Ok(line) = rest[..digits].parse() else {
+            continue;
+        };
+        let chunk = &message[..index];
+        let chunk = chunk
+            .strip_prefix("[string \"")
+            .and_then(|chunk| chunk.strip_suffix("\"]"))
+            .unwrap_or(chunk);
+        return Some((chunk, line, rest[digits + 1..].trim_start()));
+    }
+    None
+}
+
+/// the stack from `level` on, formatted like lua's `debug.traceback`
+pub fn traceback(lua: &Lua, level: usize) -> String {
+    let mut traceback = String::from(TRACEBACK_HEADER);
+    let mut level = level;
+    while let Some(debug) = lua.inspect_stack(level) {
+        let source = debug.source();
+        let short_src = source.short_src.as_deref().unwrap_or("?");
+        let location = match (source.what, debug.curr_line()) {
+            ("C", _) => "[C]".to_owned(),
+            (_, line) if line > 0 => format!("{}:{}", short_src, line),
+            _ => short_src.to_owned(),
+        };
+        let function = match (debug.names().name, source.what) {
+            (Some(name), _) => format!("function '{}'", name),
+            (None, "main") => "main chunk".to_owned(),
+            (None, "C") => "?".to_owned(),
+            (None, _) => format!(
+                "function <{}:{}>",
+                short_src,
+                source.line_defined.unwrap_or_default()
+            ),
+        };
+        traceback.push_str(&format!("\n\t{}: in {}", location, function));
+        level += 1;
+    }
+    traceback
+}
+
+// `xpcall(f, handler, ...)`, the handler keeps the error as it is and adds the stack
+// from where it was raised, before it unwinds
+fn traced_call(lua: &Lua) -> mlua::Result<Function> {
+    if let Ok(traced) = lua.named_registry_value::<Function>(TRACED_CALL_KEY) {
+        return Ok(traced);
+    }
+    // 0 is this function, 1 the handler and 2 where the error was raised, the frames
+    // from xpcall on are the caller's
+    let stack = lua.create_function(|lua, ()| {
+        let mut traceback = traceback(lua, 2);
+        if let Some(end) = traceback.find("\n\t[C]: in function 'xpcall'\n\ttraced_call:") {
+            traceback.truncate(end);
+        }
+        Ok(traceback)
+    })?;
+    let traced: Function = lua
+        .load(
+            r#"
+            local stack = ...
+            local xpcall, tostring = xpcall, tostring
+            local function handler(err)
+                return { err = err, message = tostring(err), traceback = stack() }
+            end
+            return function(f, ...)
+                return xpcall(f, handler, ...)
+            end
+            "#,
+        )
+        .set_name("=traced_call")
+        .call(stack)?;
+    lua.set_named_registry_value(TRACED_CALL_KEY, &traced)?;
+    Ok(traced)
+}
+
+/// calls `func` with a traceback taken where an error is raised, a rust panic
+/// in a function it calls comes back as an error too
+pub fn call_traced<R: FromLuaMulti>(
+    lua: &Lua,
+    func: &Function,
+    args: impl IntoLuaMulti,
+) -> Result<R, ScriptError> {
+    let mut args = args.into_lua_multi(lua)?;
+    args.push_front(Value::Function(func.clone()));
+    let mut results = traced_call(lua)?.call::<MultiValue>(args)?;
+    if let Some(Value::Boolean(true)) = results.pop_front() {
+        return Ok(R::from_lua_multi(results, lua)?);
+    }
+    let failure: Table = match results.pop_front() {
+        Some(Value::Table(failure)) => failure,
+        // the handler itself failed, e.g. out of memory
+        other => return Err(ScriptError::new(format!("{:?}", other))),
+    };
+    let message: String = failure.get("message").unwrap_or_default();
+    let traceback: String = failure.get("traceback").unwrap_or_default();
+    // reading a panic back resumes it
+    match catch_unwind(AssertUnwindSafe(|| failure.get::<Value>("err"))) {
+        Ok(Ok(Value::Error(err))) => Err(ScriptError::traced(&err, traceback)),
+        Ok(_) => Err(ScriptError::from_message(&message).with_traceback(Some(traceback))),
+        Err(_) => Err(ScriptError::panic(message, traceback)),
+    }
+}
+
+#[test]
+fn test_split_location() {
+    assert_eq!(
+        split_location(r#"[string "<@MemModule>/ui.menu.lua"]:12: attempt to call a nil value"#),
+        Some((
+            "<@MemModule>/ui.menu.lua",
+            12,
+            "attempt to call a nil value"
+        ))
+    );
+    assert_eq!(
+        split_location("main:3: boom: again"),
+        Some(("main", 3, "boom: again"))
+    );
+    assert_eq!(split_location("no location: here"), None);
+}
+
+#[test]
+fn test_rust_panic() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    let explode = lua.create_function(|_, ()| -> mlua::Result<()> { panic!("exploded") })?;
+    lua.globals().set("explode", explode)?;
+    let func = lua
+        .load("local n = 1\nexplode()\nreturn n")
+        .set_name("=game")
+        .into_function()?;
+    let err = call_traced::<()>(&lua, &func, ()).unwrap_err();
+    assert!(err.panic, "{:#}", err);
+    assert!(err.message.contains("exploded"), "{}", err);
+    assert_eq!(err.location().as_deref(), Some("game:2"));
+    // the state is still usable
+    let n: i64 = lua.load("return 1 + 1").eval()?;
+    assert_eq!(n, 2);
+    Ok(())
+}
@@ -1,16 +1,22 @@
+mod error;
 mod macros;
 pub mod modules;
 pub mod thread;
 mod utils;
 use std::ops::Deref;
 
+pub use error::{ScriptError, call_traced, traceback};
 use fool_resource::{Resource, SharedData};
 use mlua::{AsChunk, FromLuaMulti, Function, IntoLuaMulti, Lua, LuaOptions, StdLib, Table, Value};
-use modules::{DSLModule, MemoryModule, Modules, UserMod, UserModConstructor, stdlib};
+use modules::{
+    DSLModule, DependencyGraph, MemoryModule, Modules, Sandbox, UserMod, UserModConstructor,
+    loader, require, schema, state, stdlib,
+};
 #[derive(Debug, Clone)]
 pub struct FoolScript {
     lua: Lua,
     pub modules: Modules,
+    dependencies: DependencyGraph,
 }
 impl Deref for FoolScript {
     type Target = Lua;
@@ -32,16 +38,37 @@ impl FoolScript {
             ),
             "init lua failed"
         )?;
+        let user_mod = UserMod::new();
+        user_mod.register("state", state::create_module);
+        user_mod.register("schema", schema::create_module);
+        let loader_resource = resource.clone();
+        user_mod.register("resource", move |lua: &Lua| {
+            loader::create_module(lua, &loader_resource)
+        });
         Ok(Self {
             lua: lua.clone(),
             modules: Modules {
                 mem_mod: MemoryModule::new(resource.clone()),
                 dsl_mod: DSLModule::new(),
-                user_mod: UserMod::new(),
+                user_mod,
+                sandbox: Sandbox::default(),
+                origins: Default::default(),
             },
+            dependencies: Default::default(),
         })
     }
     pub fn setup(&mut self) -> anyhow::Result<()> {
+        // the sandbox hands untrusted modules the wrapped require
+        map2anyhow_error!(
+            require::init(&self.lua, &self.modules, &self.dependencies),
+            "setup require failed"
+        )?;
+        map2anyhow_error!(
+            self.modules
+                .sandbox
+                .init(&self.lua, &self.modules.mem_mod, &self.modules.user_mod),
+            "setup sandbox failed"
+        )?;
         let mem_loader = map2anyhow_error!(
             self.modules.mem_mod.init(&self.lua),
             "setup mem loader failed"
@@ -50,7 +77,7 @@ impl FoolScript {
             self.modules.user_mod.init(&self.lua),
             "setup fs loader failed"
         )?;
-        self.register_module_searcher(&[mem_loader, user_loader])?;
+        self.register_module_searcher(&[("memory", mem_loader), ("user", user_loader)])?;
         map2anyhow_error!(
             self.modules.dsl_mod.init(&self.lua),
             "setup_dsl_lua failed: {}"
@@ -59,15 +86,42 @@ impl FoolScript {
         stdlib::enable_debug(&self.lua)?;
         Ok(())
     }
-    fn register_module_searcher(&self, searchers: &[Function]) -> anyhow::Result<()> {
+    /// every require that succeeded in this state, by the module that made it
+    pub fn dependency_graph(&self) -> &DependencyGraph {
+        &self.dependencies
+    }
+    /// the names in `package.loaded`, sorted, `debug_modules()` tells where each came from
+    pub fn loaded_modules(&self) -> Vec<String> {
+        let loaded = self
+            .lua
+            .globals()
+            .get::<Table>("package")
+            .and_then(|package| package.get::<Table>("loaded"));
+        let mut names = match loaded {
+            Ok(loaded) => loaded
+                .pairs::<String, Value>()
+                .filter_map(|pair| pair.ok().map(|(name, _)| name))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names
+    }
+    fn register_module_searcher(
+        &self,
+        searchers: &[(&'static str, Function)],
+    ) -> anyhow::Result<()> {
         let package: Table =
             map2anyhow_error!(self.lua.globals().get("package"), "get lua module package ")?;
         let new_searchers = map2anyhow_error!(self.lua.create_table(), "lua create table")?;
-        for (index, searcher) in searchers.iter().enumerate() {
-            map2anyhow_error!(
-                new_searchers.set(index + 1, searcher.clone()),
-                "lua set searcher!"
+        for (index, (origin, searcher)) in searchers.iter().enumerate() {
+            let searcher = map2anyhow_error!(
+                self.modules
+                    .origins
+                    .track(&self.lua, origin, searcher.clone()),
+                "track searcher"
             )?;
+            map2anyhow_error!(new_searchers.set(index + 1, searcher), "lua set searcher!")?;
         }
         map2anyhow_error!(
             package.set("searchers", new_searchers),
@@ -78,44 +132,45 @@ impl FoolScript {
 }
 
 impl FoolScript {
-    pub fn run<'a>(&self, code: impl AsChunk<'a>, name: impl Into<String>) -> anyhow::Result<()> {
-        map2anyhow_error!(
-            self.lua.load(code).set_name(name).exec(),
-            "run lua code failed"
-        )?;
-        Ok(())
+    pub fn run<'a>(
+        &self,
+        code: impl AsChunk<'a>,
+        name: impl Into<String>,
+    ) -> Result<(), ScriptError> {
+        let func = self.lua.load(code).set_name(name).into_function()?;
+        call_traced(&self.lua, &func, ())
     }
 
-    pub fn load_main(&self) -> anyhow::Result<()> {
-        map2anyhow_error!(
-            self.lua.load("require(\"main\")").exec(),
-            "run require(\"main\") failed"
-        )
+    pub fn load_main(&self) -> Result<(), ScriptError> {
+        self.run("require(\"main\")", "=boot")
     }
     pub fn run_fun<R: FromLuaMulti>(
         &self,
         name: &str,
         args: impl IntoLuaMulti,
-    ) -> anyhow::Result<R> {
+    ) -> Result<R, ScriptError> {
         let func: Function =
-            map2anyhow_error!(self.lua.globals().get(name), "get main func failed:")?;
-        map2anyhow_error!(func.call::<R>(args), "run main func")
+            self.lua.globals().get(name).map_err(|err| {
+                ScriptError::new(format!("get function {} failed: {}", name, err))
+            })?;
+        call_traced(&self.lua, &func, args)
     }
     pub fn run_module_fun<R: FromLuaMulti>(
         &self,
         mod_name: &str,
         func_name: &str,
         args: impl IntoLuaMulti,
-    ) -> anyhow::Result<R> {
-        let globals = self.lua.globals();
-        let require: mlua::Function =
-            map2anyhow_error!(globals.get("require"), "get require failed:")?;
-        let module: mlua::Table = map2anyhow_error!(
-            require.call(mod_name),
-            format!("require module {} failed:", mod_name)
-        )?;
-        let func: mlua::Function = module.get(func_name)?;
-        Ok(func.call::<R>(args)?)
+    ) -> Result<R, ScriptError> {
+        let require: Function = self.lua.globals().get("require")?;
+        let module: Table = call_traced(&self.lua, &require, mod_name)?;
+        let func: Function = module.get(func_name).map_err(|err| {
+            ScriptError::new(format!(
+                "get function {}.{} failed: {}",
+                mod_name, func_name, err
+            ))
+            .in_module(mod_name)
+        })?;
+        call_traced(&self.lua, &func, args)
     }
     pub fn get_module_var(&self, mod_name: &str, var_name: &str) -> anyhow::Result<Value> {
         let globals = self.lua.globals();
@@ -138,6 +193,24 @@ impl FoolScript {
         self.modules.user_mod.register(mod_path, module);
         Ok(())
     }
+    /// like `register_user_mod`, but untrusted modules can not require it
+    pub fn register_trusted_user_mod(
+        &self,
+        mod_path: &str,
+        module: impl UserModConstructor + 'static,
+    ) -> anyhow::Result<()> {
+        self.modules.user_mod.register_trusted(mod_path, module);
+        Ok(())
+    }
+    /// scripts under `dir` run sandboxed, they can not change the globals
+    /// or the engine modules, call before `setup`
+    pub fn mount_untrusted(&self, dir: &str) {
+        self.modules.mem_mod.mount_untrusted(dir);
+    }
+    /// a global table untrusted modules may change, `mods.shared` always is
+    pub fn allow_shared(&self, path: &str) {
+        self.modules.sandbox.allow_shared(path);
+    }
 }
 
 impl FoolScript {
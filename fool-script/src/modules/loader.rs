@@ -0,0 +1,116 @@
+use fool_resource::{AsyncLoad, Resource, SharedData};
+use mlua::{Either, Function, Lua, Result, Table, Thread, ThreadStatus, Value};
+
+// the script loader the module is written in, `load` yields the running coroutine
+const LOAD_SOURCE: &str = r#"
+local module = ...
+function module.load(key)
+    local co, main = coroutine.running()
+    if main then
+        error("resource.load must be called from a coroutine, use load_async", 2)
+    end
+    module.load_async(key, co)
+    return coroutine.yield()
+end
+return module
+"#;
+
+struct Pending {
+    key: String,
+    load: AsyncLoad<SharedData>,
+    waiter: Either<Function, Thread>,
+}
+
+/// the loads started by one lua state, only that state may call their callbacks
+#[derive(Default)]
+struct PendingLoads(Vec<Pending>);
+
+/// `require("resource")`, loads run on threads of their own and finish in `poll`
+pub fn create_module(lua: &Lua, resource: &Resource<String, SharedData>) -> Result<Value> {
+    if lua.app_data_ref::<PendingLoads>().is_none() {
+        lua.set_app_data(PendingLoads::default());
+    }
+    let module = lua.create_table()?;
+    let loader = resource.clone();
+    module.set(
+        "load_async",
+        lua.create_function(
+            move |lua, (key, waiter): (String, Either<Function, Thread>)| {
+                let load = loader.get_async(key.clone());
+                let Some(mut pending) = lua.app_data_mut::<PendingLoads>() else {
+                    return Err(mlua::Error::RuntimeError(
+                        "resource module is not set up".to_owned(),
+                    ));
+                };
+                pending.0.push(Pending { key, load, waiter });
+                Ok(())
+            },
+        )?,
+    )?;
+    let loaded = resource.clone();
+    module.set(
+        "is_loaded",
+        lua.create_function(move |_, key: String| Ok(loaded.exists(key)))?,
+    )?;
+    lua.load(LOAD_SOURCE)
+        .set_name("=resource")
+        .call::<Table>(module)
+        .map(Value::Table)
+}
+
+/// hands finished loads to their callbacks as `(data, err)`, data is the raw bytes
+/// in a lua string, returns how many finished. Every callback runs even when one
+/// fails, the first error is returned
+pub fn poll(lua: &Lua) -> Result<usize> {
+    // taken out first, a callback may start another load
+    let done = match lua.app_data_mut::<PendingLoads>() {
+        Some(mut pending) => {
+            let mut done = Vec::new();
+            let mut waiting = Vec::new();
+            for load in pending.0.drain(..) {
+                match load.load.poll() {
+                    Some(result) => done.push((load.key, result, load.waiter)),
+                    None => waiting.push(load),
+                }
+            }
+            pending.0 = waiting;
+            done
+        }
+        None => return Ok(0),
+    };
+    let count = done.len();
+    let mut first_err = Ok(());
+    for (key, result, waiter) in done {
+        let args = match result {
+            Ok(data) => (Value::String(lua.create_string(&*data)?), Value::Nil),
+            Err(err) => {
+                log::debug!("load {} failed: {}", key, err);
+                (
+                    Value::Nil,
+                    Value::String(lua.create_string(format!("{:#}", err))?),
+                )
+            }
+        };
+        let called = match waiter {
+            Either::Left(callback) => callback.call::<()>(args),
+            Either::Right(co) if co.status() == ThreadStatus::Resumable => co.resume::<()>(args),
+            Either::Right(_) => Err(mlua::Error::RuntimeError(format!(
+                "coroutine waiting for {} is not resumable",
+                key
+            ))),
+        };
+        if let Err(err) = called {
+            log::error!("resource {} callback failed: {}", key, err);
+            if first_err.is_ok() {
+                first_err = Err(err);
+            }
+        }
+    }
+    first_err.map(|_| count)
+}
+
+/// loads still running
+pub fn pending(lua: &Lua) -> usize {
+    lua.app_data_ref::<PendingLoads>()
+        .map_or(0, |pending| pending.0.len())
+}
@@ -1,22 +1,107 @@
+use crate::{ScriptError, call_traced};
 use fool_resource::{Resource, SharedData};
-use mlua::{Function, Lua, Value};
+use mlua::{ChunkMode, Function, Lua, Value};
 use parking_lot::RwLock;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{collections::HashMap, fmt::Debug, path::Path};
+
+fn chunk_name(modname: &str) -> String {
+    format!("<@MemModule>/{}.lua", modname)
+}
+
+#[derive(Debug)]
+pub struct ModuleSource {
+    pub source: String,
+    /// key of the compiled chunk
+    pub hash: u64,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct MemoryModule {
-    modules: Arc<RwLock<HashMap<String, Arc<String>>>>,
+    modules: Arc<RwLock<HashMap<String, Arc<ModuleSource>>>>,
+    // bytecode dumped by the first state that compiled a source, functions are bound
+    // to their state so every state built from these modules loads its own copy
+    chunks: Arc<RwLock<HashMap<u64, Arc<Vec<u8>>>>>,
+    hashed: Arc<AtomicUsize>,
+    // folders whose modules run sandboxed
+    untrusted: Arc<RwLock<Vec<String>>>,
     pub resource: Resource<String, SharedData>,
 }
 impl MemoryModule {
     pub fn new(resource: Resource<String, SharedData>) -> Self {
         Self {
-            modules: Default::default(),
             resource,
+            ..Default::default()
+        }
+    }
+    /// modules under `dir` run in their own environment, see `Sandbox`
+    pub fn mount_untrusted(&self, dir: &str) {
+        let dir = dir.trim_end_matches('/').to_owned() + "/";
+        let mut untrusted = self.untrusted.write();
+        if !untrusted.contains(&dir) {
+            untrusted.push(dir);
+        }
+    }
+    pub fn is_untrusted(&self, modname: &str) -> bool {
+        let file_path = modname.replace('.', "/") + ".lua";
+        self.untrusted
+            .read()
+            .iter()
+            .any(|dir| file_path.starts_with(dir.as_str()))
+    }
+    /// every module the resource can provide, by the name `require` takes
+    pub fn module_names(&self) -> Vec<String> {
+        self.resource
+            .list_available()
+            .into_iter()
+            .filter_map(|path| path.strip_suffix(".lua").map(|name| name.replace('/', ".")))
+            .collect()
+    }
+    /// how many sources were hashed, each is hashed once when first read
+    pub fn hashed_count(&self) -> usize {
+        self.hashed.load(Ordering::Relaxed)
+    }
+    /// the chunk function in `lua`, parsed only if no state compiled this source yet
+    pub fn compile(&self, lua: &Lua, module: &ModuleSource, name: &str) -> mlua::Result<Function> {
+        let chunk = { self.chunks.read().get(&module.hash).cloned() };
+        if let Some(chunk) = chunk {
+            return lua
+                .load(chunk.as_slice())
+                .set_name(name)
+                .set_mode(ChunkMode::Binary)
+                .into_function();
         }
+        // a module that is a single expression evaluates to it, like `Chunk::eval`,
+        // binary chunks shipped as scripts are never loaded
+        let text = |source: String| lua.load(source).set_name(name).set_mode(ChunkMode::Text);
+        let function = match text(format!("return {}", module.source)).into_function() {
+            Ok(function) => function,
+            Err(_) => text(module.source.clone()).into_function()?,
+        };
+        self.chunks
+            .write()
+            .insert(module.hash, Arc::new(function.dump(false)));
+        Ok(function)
     }
-    pub fn get_or_insert(&self, modname: &String) -> mlua::Result<Arc<String>> {
+    /// compiles every module ahead of its first `require`, returns the ones that don't,
+    /// they fail again when required
+    pub fn precompile(&self, lua: &Lua) -> Vec<(String, mlua::Error)> {
+        let mut failed = Vec::new();
+        for modname in self.module_names() {
+            let compiled = self
+                .get_or_insert(&modname)
+                .and_then(|module| self.compile(lua, &module, &chunk_name(&modname)));
+            if let Err(err) = compiled {
+                failed.push((modname, err));
+            }
+        }
+        failed
+    }
+    pub fn get_or_insert(&self, modname: &String) -> mlua::Result<Arc<ModuleSource>> {
         let module = { self.modules.read().get(modname).cloned() };
         match module {
             Some(content) => Ok(content),
@@ -24,12 +109,19 @@ impl MemoryModule {
                 let file_path = modname.replace('.', "/") + ".lua";
                 match self.resource.get(&file_path) {
                     Ok(content) => {
-                        let script = Arc::new(content.to_string().map_err(|err| {
+                        let source = content.to_string().map_err(|err| {
                             mlua::Error::RuntimeError(format!(
                                 "{} not a Correct lua script: {}",
                                 file_path, err
                             ))
-                        })?);
+                        })?;
+                        let mut hasher = DefaultHasher::new();
+                        source.hash(&mut hasher);
+                        self.hashed.fetch_add(1, Ordering::Relaxed);
+                        let script = Arc::new(ModuleSource {
+                            hash: hasher.finish(),
+                            source,
+                        });
                         self.modules.write().insert(modname.clone(), script.clone());
                         log::trace!("module {} is load from from {}!", modname, file_path);
                         Ok(script)
@@ -45,20 +137,26 @@ impl MemoryModule {
             }
         }
     }
+    // runs the chunk of `modname`, an error keeps the module and line it was raised at
+    fn load(&self, lua: &Lua, script: &ModuleSource, modname: &str) -> Result<Value, ScriptError> {
+        let function = self.compile(lua, script, &chunk_name(modname))?;
+        if self.is_untrusted(modname) {
+            function.set_environment(super::sandbox::environment(lua, modname)?)?;
+        }
+        let value = call_traced::<Value>(lua, &function, ())?;
+        Ok(crate::utils::set_module_name(value, modname, lua)?)
+    }
     pub fn init(&self, lua: &Lua) -> mlua::Result<Function> {
         let resource = self.clone();
         let memory_searcher = {
             lua.create_function(move |lua, modname: String| {
                 if let Ok(script) = resource.get_or_insert(&modname) {
-                    let script = script.to_owned();
+                    let modules = resource.clone();
                     let modname_cloned = modname.clone();
                     let loader = lua.create_function(move |lua, _: ()| {
-                        let value = lua
-                            .load(script.as_ref())
-                            .set_name(format!("<@MemModule>/{}.lua", modname_cloned))
-                            .eval::<Value>()?;
-                        let value = crate::utils::set_module_name(value, &modname_cloned, lua)?;
-                        Ok(value)
+                        modules
+                            .load(lua, &script, &modname_cloned)
+                            .map_err(|err| mlua::Error::external(err.in_module(&modname_cloned)))
                     })?;
                     log::trace!("lua module {} found!", modname);
                     Ok((
@@ -67,12 +165,13 @@ impl MemoryModule {
                     ))
                 } else {
                     log::trace!("lua module {} not found!", modname);
+                    // require lists it with the other searchers' messages
                     Ok((
-                        Value::Nil,
                         Value::String(lua.create_string(format!(
-                            "Module {} not found in MemoryModule Loader!",
-                            modname
+                            "memory loader: no script {}.lua, the dots in the name are folders",
+                            modname.replace('.', "/")
                         ))?),
+                        Value::Nil,
                     ))
                 }
             })?
@@ -80,3 +179,108 @@ impl MemoryModule {
         Ok(memory_searcher)
     }
 }
+
+#[test]
+fn test_shared_module_chunks() -> anyhow::Result<()> {
+    use super::Modules;
+    use crate::FoolScript;
+    let resource = Resource::<String, SharedData>::empty();
+    resource.load(
+        "shared/counter.lua",
+        SharedData::from("local n = 0\nreturn { next = function() n = n + 1 return n end }"),
+    );
+    let modules = Modules {
+        mem_mod: MemoryModule::new(resource),
+        ..Default::default()
+    };
+    let first = FoolScript::setup_from_modules(&modules)?;
+    let second = FoolScript::setup_from_modules(&modules)?;
+    for script in [&first, &second] {
+        let n: i64 = script
+            .load("local c = require('shared.counter') c.next() return c.next()")
+            .eval()
+            .map_err(|err| anyhow::anyhow!("{}", err))?;
+        // every state runs its own copy of the module
+        assert_eq!(n, 2);
+    }
+    assert_eq!(modules.mem_mod.hashed_count(), 1);
+    assert_eq!(modules.mem_mod.chunks.read().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_precompile() -> anyhow::Result<()> {
+    use crate::FoolScript;
+    let resource = Resource::<String, SharedData>::empty();
+    resource.load(
+        "main.lua",
+        SharedData::from("return require('ui.menu').title"),
+    );
+    resource.load("ui/menu.lua", SharedData::from("return { title = 'menu' }"));
+    resource.load("ui/broken.lua", SharedData::from("return {"));
+    let mut script = FoolScript::new(resource)?;
+    script.setup()?;
+    let mem_mod = &script.modules.mem_mod;
+    let failed = mem_mod.precompile(&script);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].0, "ui.broken");
+    assert_eq!(mem_mod.chunks.read().len(), 2);
+    // required later, without parsing again
+    let title: String = script.load("return require('main')").eval()?;
+    assert_eq!(title, "menu");
+    assert_eq!(mem_mod.hashed_count(), 3);
+    assert_eq!(mem_mod.chunks.read().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_error_lines() -> anyhow::Result<()> {
+    use super::Modules;
+    use crate::FoolScript;
+    let resource = Resource::<String, SharedData>::empty();
+    resource.load(
+        "main.lua",
+        SharedData::from("local x = 1\nrequire('ui.menu')"),
+    );
+    resource.load(
+        "ui/menu.lua",
+        SharedData::from("local menu = {}\n\nerror('boom')\nreturn menu"),
+    );
+    let modules = Modules {
+        mem_mod: MemoryModule::new(resource),
+        ..Default::default()
+    };
+    let first = FoolScript::setup_from_modules(&modules)?;
+    assert!(modules.mem_mod.precompile(&first).is_empty());
+    // the first state parsed the sources, the second loads their bytecode
+    for script in [&first, &FoolScript::setup_from_modules(&modules)?] {
+        let err = match script.load_main() {
+            Ok(_) => panic!("main should fail"),
+            Err(err) => err,
+        };
+        // boot requires main, which requires the menu
+        let chain: Vec<_> = err.chain().collect();
+        assert_eq!(chain.len(), 3, "{:#}", err);
+        assert_eq!(chain[0].location().as_deref(), Some("boot:1"));
+        assert_eq!(chain[1].module.as_deref(), Some("main"));
+        assert_eq!(chain[1].line, Some(2));
+        assert_eq!(chain[1].message, "error loading module 'ui.menu'");
+        let root = err.root();
+        assert_eq!(root.module.as_deref(), Some("ui.menu"));
+        assert_eq!(root.chunk.as_deref(), Some("<@MemModule>/ui.menu.lua"));
+        assert_eq!(root.line, Some(3));
+        assert_eq!(root.message, "boom");
+        assert!(
+            root.traceback
+                .as_ref()
+                .is_some_and(|t| t.contains("menu.lua\"]:3:"))
+        );
+        assert!(!format!("{:#}", err).contains("traced_call"), "{:#}", err);
+        assert_eq!(root.location().as_deref(), Some("ui.menu:3"));
+        // and it goes through anyhow without losing them
+        let err = anyhow::Error::from(err);
+        let err = err.downcast_ref::<ScriptError>().unwrap();
+        assert_eq!(err.root().line, Some(3));
+    }
+    Ok(())
+}
@@ -1,13 +1,20 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 mod dsl;
+pub mod loader;
 mod memory;
+pub(crate) mod require;
+mod sandbox;
+pub mod schema;
 pub mod ser;
+pub mod state;
 pub mod stdlib;
 mod userdata;
 pub use dsl::{DSLContent, DSLID, DSLModule};
 use fool_resource::{Resource, SharedData};
 pub use memory::MemoryModule;
+pub use require::{DependencyGraph, ModuleOrigins};
+pub use sandbox::Sandbox;
 pub use userdata::{UserMod, UserModConstructor};
 
 #[derive(Debug, Clone, Default)]
@@ -15,4 +22,6 @@ pub struct Modules {
     pub mem_mod: MemoryModule,
     pub dsl_mod: DSLModule,
     pub user_mod: UserMod,
+    pub sandbox: Sandbox,
+    pub origins: ModuleOrigins,
 }
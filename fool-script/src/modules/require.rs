@@ -0,0 +1,287 @@
+use super::Modules;
+use mlua::{Function, Lua, MultiValue, Table, Value};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+// how many near misses a failed require suggests
+const SUGGESTIONS: usize = 3;
+
+/// the modules one state required, `importer -> imported`
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    edges: Arc<RwLock<BTreeMap<String, BTreeSet<String>>>>,
+}
+
+impl DependencyGraph {
+    fn record(&self, importer: Option<&String>, module: &str) {
+        let mut edges = self.edges.write();
+        if !edges.contains_key(module) {
+            edges.insert(module.to_owned(), BTreeSet::new());
+        }
+        if let Some(importer) = importer {
+            edges
+                .entry(importer.clone())
+                .or_default()
+                .insert(module.to_owned());
+        }
+    }
+    /// every required module with the modules it required, leaves included
+    pub fn edges(&self) -> BTreeMap<String, BTreeSet<String>> {
+        self.edges.read().clone()
+    }
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph require {\n");
+        for (module, imports) in self.edges.read().iter() {
+            dot.push_str(&format!("    {:?};\n", module));
+            for import in imports {
+                dot.push_str(&format!("    {:?} -> {:?};\n", module, import));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    /// `{"module": ["imported", ...]}`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&*self.edges.read()).unwrap_or_default()
+    }
+    pub fn to_lua(&self, lua: &Lua) -> mlua::Result<Table> {
+        let table = lua.create_table()?;
+        for (module, imports) in self.edges.read().iter() {
+            table.set(
+                module.as_str(),
+                lua.create_sequence_from(imports.iter().cloned())?,
+            )?;
+        }
+        Ok(table)
+    }
+}
+
+/// the searcher that first found each module, shared by the states built from the same modules
+#[derive(Debug, Clone, Default)]
+pub struct ModuleOrigins {
+    origins: Arc<RwLock<HashMap<String, &'static str>>>,
+}
+
+impl ModuleOrigins {
+    /// `memory`, `user`, None for modules no searcher loaded, like the lua libraries
+    pub fn get(&self, module: &str) -> Option<&'static str> {
+        self.origins.read().get(module).copied()
+    }
+    /// `searcher` noting itself as the origin of the modules it finds
+    pub(crate) fn track(
+        &self,
+        lua: &Lua,
+        origin: &'static str,
+        searcher: Function,
+    ) -> mlua::Result<Function> {
+        let origins = self.clone();
+        lua.create_function(move |_, name: String| {
+            let found = searcher.call::<MultiValue>(name.as_str())?;
+            if let Some(Value::Function(_)) = found.front() {
+                origins.origins.write().entry(name).or_insert(origin);
+            }
+            Ok(found)
+        })
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// the names closest to `name`, nearest first
+fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let mut scored: Vec<(usize, &String)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored
+        .into_iter()
+        .take(SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// wraps the global `require` to catch cycles, name near misses and record the graph,
+/// installed before anything captures `require`
+pub(crate) fn init(lua: &Lua, modules: &Modules, graph: &DependencyGraph) -> mlua::Result<()> {
+    let globals = lua.globals();
+    let real_require = globals.get::<Function>("require")?;
+    let (mem_mod, user_mod) = (modules.mem_mod.clone(), modules.user_mod.clone());
+    let tracked = graph.clone();
+    // modules being loaded, outermost first
+    let loading = Arc::new(Mutex::new(Vec::<String>::new()));
+    let require = lua.create_function(move |_, name: String| {
+        let importer = {
+            let mut loading = loading.lock();
+            if let Some(start) = loading.iter().position(|module| *module == name) {
+                let mut cycle = loading[start..].to_vec();
+                cycle.push(name);
+                return Err(mlua::Error::RuntimeError(format!(
+                    "circular require: {}",
+                    cycle.join(" -> ")
+                )));
+            }
+            let importer = loading.last().cloned();
+            loading.push(name.clone());
+            importer
+        };
+        let result = real_require.call::<MultiValue>(name.as_str());
+        loading.lock().pop();
+        match result {
+            Ok(values) => {
+                tracked.record(importer.as_ref(), &name);
+                Ok(values)
+            }
+            // not found by any searcher, not an error raised while loading it
+            Err(mlua::Error::RuntimeError(message))
+                if message.starts_with(&format!("module '{}' not found", name)) =>
+            {
+                let mut known = mem_mod.module_names();
+                known.extend(user_mod.names());
+                // the searchers' messages, the traceback is added again for this call
+                let message = message
+                    .split("\nstack traceback:")
+                    .next()
+                    .unwrap_or_default();
+                let suggestions = closest(&name, &known);
+                Err(mlua::Error::RuntimeError(match suggestions.is_empty() {
+                    true => message.to_owned(),
+                    false => format!("{}\n\tdid you mean: {}", message, suggestions.join(", ")),
+                }))
+            }
+            Err(err) => Err(err),
+        }
+    })?;
+    globals.set("require", require)?;
+    // every loaded module with where it came from, `builtin` for the lua libraries
+    let origins = modules.origins.clone();
+    let debug_modules = lua.create_function(move |lua, ()| {
+        let loaded = lua
+            .globals()
+            .get::<Table>("package")?
+            .get::<Table>("loaded")?;
+        let modules = lua.create_table()?;
+        for pair in loaded.pairs::<String, Value>() {
+            let (name, _) = pair?;
+            let origin = origins.get(&name).unwrap_or("builtin");
+            modules.set(name, origin)?;
+        }
+        Ok(modules)
+    })?;
+    globals.set("debug_modules", debug_modules)?;
+    // the adjacency table, for looking into module coupling while developing
+    #[cfg(debug_assertions)]
+    {
+        let graph = graph.clone();
+        let graph = lua.create_function(move |lua, ()| graph.to_lua(lua))?;
+        globals.get::<Table>("package")?.set("graph", graph)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_require_diagnostics() -> anyhow::Result<()> {
+    use crate::FoolScript;
+    use fool_resource::{Resource, SharedData};
+    let resource = Resource::<String, SharedData>::empty();
+    for (path, source) in [
+        (
+            "ui/menu.lua",
+            "local theme = require('ui.theme') return { theme = theme }",
+        ),
+        ("ui/theme.lua", "return { color = 'red' }"),
+        (
+            "ui/hud.lua",
+            "require('ui.menu') return require('ui.theme')",
+        ),
+        ("cycle/a.lua", "return require('cycle.b')"),
+        ("cycle/b.lua", "return require('cycle.c')"),
+        ("cycle/c.lua", "return require('cycle.a')"),
+    ] {
+        resource.load(path, SharedData::from(source));
+    }
+    let mut script = FoolScript::new(resource)?;
+    script.setup()?;
+    let error = |code: &str| -> String {
+        match script.load(code).exec() {
+            Ok(_) => panic!("{} should fail", code),
+            Err(err) => err.to_string(),
+        }
+    };
+    let err = error("require('ui.thme')");
+    assert!(err.contains("did you mean: ui.theme"), "{}", err);
+    // both searchers say what they looked for
+    assert!(
+        err.contains("ui/thme.lua") && err.contains("user module loader"),
+        "{}",
+        err
+    );
+    let err = error("require('cycle.a')");
+    assert!(
+        err.contains("circular require: cycle.a -> cycle.b -> cycle.c -> cycle.a"),
+        "{}",
+        err
+    );
+    // the failed loads left nothing half loaded behind
+    assert!(error("require('cycle.b')").contains("circular require: cycle.b -> cycle.c"));
+
+    script.load("require('ui.hud')").exec()?;
+    let edges = script.dependency_graph().edges();
+    let imports = |module: &str| edges[module].iter().cloned().collect::<Vec<_>>();
+    assert_eq!(imports("ui.hud"), ["ui.menu", "ui.theme"]);
+    assert_eq!(imports("ui.menu"), ["ui.theme"]);
+    assert!(imports("ui.theme").is_empty());
+    assert!(!edges.contains_key("cycle.a"));
+    assert!(
+        script
+            .dependency_graph()
+            .to_dot()
+            .contains(r#""ui.hud" -> "ui.menu";"#)
+    );
+    let json: serde_json::Value = serde_json::from_str(&script.dependency_graph().to_json())?;
+    assert_eq!(json["ui.menu"][0], "ui.theme");
+    #[cfg(debug_assertions)]
+    {
+        let first: String = script.load("return package.graph()['ui.hud'][1]").eval()?;
+        assert_eq!(first, "ui.menu");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_loaded_modules() -> anyhow::Result<()> {
+    use crate::FoolScript;
+    use fool_resource::{Resource, SharedData};
+    let resource = Resource::<String, SharedData>::empty();
+    resource.load("ui/theme.lua", SharedData::from("return { color = 'red' }"));
+    let mut script = FoolScript::new(resource)?;
+    script.setup()?;
+    assert!(!script.loaded_modules().contains(&"ui.theme".to_owned()));
+    script.load("require('ui.theme') require('state')").exec()?;
+    let loaded = script.loaded_modules();
+    assert!(loaded.contains(&"ui.theme".to_owned()), "{:?}", loaded);
+    assert_eq!(script.modules.origins.get("ui.theme"), Some("memory"));
+    assert_eq!(script.modules.origins.get("state"), Some("user"));
+    let origins: HashMap<String, String> = script.load("return debug_modules()").eval()?;
+    assert_eq!(origins["ui.theme"], "memory");
+    assert_eq!(origins["state"], "user");
+    assert_eq!(origins["string"], "builtin");
+    Ok(())
+}
@@ -0,0 +1,407 @@
+use super::{MemoryModule, UserMod};
+use mlua::{Function, Lua, MetaMethod, MultiValue, Table, UserData, UserDataMethods, Value};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+// go through the metamethods, mlua only indexes userdata by its registered fields
+const HELPERS: &str = r#"
+return function(t, k) return t[k] end,
+    function(t) return #t end,
+    function(t, ...) return t(...) end
+"#;
+
+/// modules from untrusted mounts get their own environment, the globals read through
+/// read only wrappers and new globals stay in the module,
+/// `shared` lists the global tables they may change, by dotted path
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    shared: Arc<RwLock<Vec<String>>>,
+}
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            shared: Arc::new(RwLock::new(vec!["mods.shared".to_owned()])),
+        }
+    }
+}
+
+// what the wrappers of one state need, kept in its app data
+struct SandboxState {
+    globals: Table,
+    shared: Vec<String>,
+    // target to wrapper, weak keys
+    wrappers: Table,
+    index: Function,
+    len: Function,
+    call: Function,
+    next: Function,
+    tostring: Function,
+    load: Function,
+    getmetatable: Function,
+    require: Function,
+}
+
+fn state(lua: &Lua) -> mlua::Result<Arc<SandboxState>> {
+    lua.app_data_ref::<Arc<SandboxState>>()
+        .map(|state| state.clone())
+        .ok_or_else(|| mlua::Error::RuntimeError("sandbox is not set up".to_owned()))
+}
+
+fn protected(path: &str) -> mlua::Error {
+    mlua::Error::RuntimeError(format!("cannot set protected global '{}'", path))
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.to_string_lossy(),
+        key => key
+            .to_string()
+            .unwrap_or_else(|_| key.type_name().to_owned()),
+    }
+}
+
+fn child_path(parent: &str, key: &Value) -> String {
+    match parent {
+        "_G" => key_name(key),
+        parent => format!("{}.{}", parent, key_name(key)),
+    }
+}
+
+impl Sandbox {
+    /// lets untrusted modules change the global table at `path`, created if missing
+    pub fn allow_shared(&self, path: &str) {
+        let mut shared = self.shared.write();
+        if !shared.iter().any(|p| p == path) {
+            shared.push(path.to_owned());
+        }
+    }
+    pub(crate) fn init(
+        &self,
+        lua: &Lua,
+        mem_mod: &MemoryModule,
+        user_mod: &UserMod,
+    ) -> mlua::Result<()> {
+        let globals = lua.globals();
+        let shared = self.shared.read().clone();
+        for path in &shared {
+            let mut table = globals.clone();
+            for part in path.split('.') {
+                table = match table.raw_get::<Value>(part)? {
+                    Value::Table(child) => child,
+                    _ => {
+                        let child = lua.create_table()?;
+                        table.raw_set(part, child.clone())?;
+                        child
+                    }
+                };
+            }
+        }
+        let (index, len, call) =
+            lua.load(HELPERS)
+                .set_name("<@Sandbox>")
+                .eval::<(Function, Function, Function)>()?;
+        let wrappers = lua.create_table()?;
+        let weak = lua.create_table()?;
+        weak.raw_set("__mode", "k")?;
+        wrappers.set_metatable(Some(weak));
+        let real_getmetatable = globals.get::<Function>("getmetatable")?;
+        // the string metatable and the like are shared with trusted code
+        let getmetatable = lua.create_function(move |lua, value: Value| {
+            let meta = real_getmetatable.call::<Value>(value.clone())?;
+            match (value, &meta) {
+                (Value::Table(_), _) | (_, Value::Nil) => Ok(meta),
+                _ => wrap(lua, &*state(lua)?, meta, "metatable", true),
+            }
+        })?;
+        let (mem_mod, user_mod) = (mem_mod.clone(), user_mod.clone());
+        let real_require = globals.get::<Function>("require")?;
+        let require = lua.create_function(move |lua, name: String| {
+            if user_mod.is_trusted_only(&name) {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "module '{}' is not available to untrusted modules",
+                    name
+                )));
+            }
+            let value = real_require.call::<Value>(name.as_str())?;
+            match mem_mod.is_untrusted(&name) {
+                true => Ok(value),
+                false => wrap(lua, &*state(lua)?, value, &name, true),
+            }
+        })?;
+        let state = SandboxState {
+            shared,
+            wrappers,
+            index,
+            len,
+            call,
+            next: globals.get("next")?,
+            tostring: globals.get("tostring")?,
+            load: globals.get("load")?,
+            getmetatable,
+            require,
+            globals,
+        };
+        lua.set_app_data(Arc::new(state));
+        Ok(())
+    }
+}
+
+/// tables and userdata as read only wrappers, the rest as is
+fn wrap(
+    lua: &Lua,
+    state: &SandboxState,
+    value: Value,
+    path: &str,
+    cache: bool,
+) -> mlua::Result<Value> {
+    if state.shared.iter().any(|shared| shared == path) {
+        return Ok(value);
+    }
+    match &value {
+        Value::UserData(data) if data.is::<ReadOnly>() => Ok(value),
+        Value::Table(_) | Value::UserData(_) => {
+            if !cache {
+                return ReadOnly::create(lua, value, path);
+            }
+            let cached = state.wrappers.raw_get::<Value>(value.clone())?;
+            if !cached.is_nil() {
+                return Ok(cached);
+            }
+            let wrapper = ReadOnly::create(lua, value.clone(), path)?;
+            state.wrappers.raw_set(value, wrapper.clone())?;
+            Ok(wrapper)
+        }
+        _ => Ok(value),
+    }
+}
+
+fn unwrap(value: Value) -> Value {
+    match &value {
+        Value::UserData(data) => match data.borrow::<ReadOnly>() {
+            Ok(read_only) => read_only.target.clone(),
+            Err(_) => value,
+        },
+        _ => value,
+    }
+}
+
+// a method of wrapped userdata gets the real one as self, userdata it returns is wrapped too
+fn wrap_method(lua: &Lua, method: Function, path: String) -> mlua::Result<Value> {
+    let method = lua.create_function(move |lua, args: MultiValue| {
+        let args = args.into_iter().map(unwrap).collect::<MultiValue>();
+        let state = state(lua)?;
+        method
+            .call::<MultiValue>(args)?
+            .into_iter()
+            .map(|value| match value {
+                Value::UserData(_) => wrap(lua, &state, value, &path, false),
+                value => Ok(value),
+            })
+            .collect::<mlua::Result<MultiValue>>()
+    })?;
+    Ok(Value::Function(method))
+}
+
+/// a global table or engine userdata as seen from an untrusted module
+struct ReadOnly {
+    target: Value,
+    path: String,
+}
+
+impl ReadOnly {
+    fn create(lua: &Lua, target: Value, path: &str) -> mlua::Result<Value> {
+        let path = path.to_owned();
+        Ok(Value::UserData(lua.create_userdata(Self { target, path })?))
+    }
+}
+
+impl UserData for ReadOnly {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(MetaMethod::Index, |lua, this, key: Value| {
+            let state = state(lua)?;
+            let path = child_path(&this.path, &key);
+            let value = state.index.call::<Value>((this.target.clone(), key))?;
+            match (&this.target, value) {
+                (Value::UserData(_), Value::Function(method)) => wrap_method(lua, method, path),
+                (_, value) => wrap(lua, &state, value, &path, true),
+            }
+        });
+        methods.add_meta_method(MetaMethod::NewIndex, |_, this, (key, _): (Value, Value)| {
+            Err::<(), _>(protected(&child_path(&this.path, &key)))
+        });
+        methods.add_meta_method(MetaMethod::Len, |lua, this, ()| {
+            state(lua)?.len.call::<Value>(this.target.clone())
+        });
+        methods.add_meta_method(MetaMethod::Call, |lua, this, args: MultiValue| {
+            let mut args = args;
+            // the wrapper itself comes first
+            args.pop_front();
+            args.push_front(this.target.clone());
+            state(lua)?.call.call::<MultiValue>(args)
+        });
+        methods.add_meta_method(MetaMethod::ToString, |lua, this, ()| {
+            state(lua)?.tostring.call::<Value>(this.target.clone())
+        });
+        methods.add_meta_method(MetaMethod::Pairs, |lua, this, ()| {
+            let (target, path) = (this.target.clone(), this.path.clone());
+            let next = lua.create_function(move |lua, (_, key): (Value, Value)| {
+                let state = state(lua)?;
+                let (key, value) = state.next.call::<(Value, Value)>((target.clone(), key))?;
+                let value = match key.is_nil() {
+                    true => value,
+                    false => wrap(lua, &state, value, &child_path(&path, &key), true)?,
+                };
+                Ok((key, value))
+            })?;
+            Ok((next, Value::Nil, Value::Nil))
+        });
+    }
+}
+
+/// the environment of the untrusted module `modname`
+pub(crate) fn environment(lua: &Lua, modname: &str) -> mlua::Result<Table> {
+    let modname = modname.to_owned();
+    // text only, a chunk without an environment gets a sandboxed one of its own
+    let load = lua.create_function(
+        move |lua, (chunk, name, _, env): (Value, Option<String>, Value, Option<Table>)| {
+            let env = match env {
+                Some(env) => env,
+                None => environment(lua, &modname)?,
+            };
+            state(lua)?.load.call::<MultiValue>((chunk, name, "t", env))
+        },
+    )?;
+    let override_of = move |state: &SandboxState, key: &Value| -> Option<Value> {
+        let Value::String(key) = key else {
+            return None;
+        };
+        match key.to_str().ok()?.as_ref() {
+            "require" => Some(Value::Function(state.require.clone())),
+            "getmetatable" => Some(Value::Function(state.getmetatable.clone())),
+            "load" => Some(Value::Function(load.clone())),
+            _ => None,
+        }
+    };
+    let override_of = Arc::new(override_of);
+    let meta = lua.create_table()?;
+    let overrides = override_of.clone();
+    meta.raw_set(
+        "__index",
+        lua.create_function(move |lua, (_, key): (Table, Value)| {
+            let state = state(lua)?;
+            if let Some(value) = overrides(&state, &key) {
+                return Ok(value);
+            }
+            let value = state.globals.raw_get::<Value>(key.clone())?;
+            wrap(lua, &state, value, &key_name(&key), true)
+        })?,
+    )?;
+    meta.raw_set(
+        "__newindex",
+        lua.create_function(move |lua, (env, key, value): (Table, Value, Value)| {
+            let state = state(lua)?;
+            let global = state.globals.raw_get::<Value>(key.clone())?;
+            if override_of(&state, &key).is_some() || !global.is_nil() {
+                return Err(protected(&key_name(&key)));
+            }
+            env.raw_set(key, value)
+        })?,
+    )?;
+    meta.raw_set("__metatable", false)?;
+    let env = lua.create_table()?;
+    env.set_metatable(Some(meta));
+    Ok(env)
+}
+
+#[test]
+fn test_untrusted_modules() -> anyhow::Result<()> {
+    use crate::FoolScript;
+    use fool_resource::{Resource, SharedData};
+    let resource = Resource::<String, SharedData>::empty();
+    resource.load(
+        "mods/hostile.lua",
+        SharedData::from(
+            r#"
+            local results = {}
+            local function try(name, f)
+                local ok, err = pcall(f)
+                results[name] = ok and "ok" or tostring(err)
+            end
+            try("require", function() require = nil end)
+            try("print", function() print = function() end end)
+            try("physics", function() require("Physics").new = nil end)
+            try("string", function() string.format = nil end)
+            try("rawset", function() rawset(_G, "print", nil) end)
+            try("load", function() load("print = nil")() end)
+            try("metatable", function() getmetatable("").__index = {} end)
+            try("secrets", function() return require("secrets") end)
+            try("engine", function() engine:set_name("mod") end)
+            -- new globals stay in the mod, shared ones are writable
+            helper = "mine"
+            mods.shared.score = 10
+            return { results = results, helper = helper, physics = require("Physics").new(1) }
+        "#,
+        ),
+    );
+    #[derive(Clone)]
+    struct Engine(Arc<RwLock<String>>);
+    impl UserData for Engine {
+        fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+            methods.add_method("name", |_, this, ()| Ok(this.0.read().clone()));
+            methods.add_method("set_name", |_, this, name: String| {
+                *this.0.write() = name;
+                Ok(())
+            });
+        }
+    }
+    let mut script = FoolScript::new(resource)?;
+    script.mount_untrusted("mods");
+    script.register_user_mod("Physics", |lua: &Lua| {
+        let physics = lua.create_table()?;
+        physics.set("new", lua.create_function(|_, n: i64| Ok(n * 2))?)?;
+        Ok(Value::Table(physics))
+    })?;
+    script
+        .register_trusted_user_mod("secrets", |lua: &Lua| Ok(Value::Table(lua.create_table()?)))?;
+    script.setup()?;
+    let engine = Engine(Arc::new(RwLock::new("game".to_owned())));
+    script.globals().set("engine", engine.clone())?;
+    let module = script
+        .load("return require('mods.hostile')")
+        .eval::<Table>()
+        .map_err(|err| anyhow::anyhow!("{}", err))?;
+    let results = module.get::<Table>("results")?;
+    for global in ["require", "print", "Physics.new", "string.format"] {
+        let key = match global {
+            "Physics.new" => "physics",
+            "string.format" => "string",
+            global => global,
+        };
+        let err = results.get::<String>(key)?;
+        assert!(
+            err.contains(&format!("cannot set protected global '{}'", global)),
+            "{}: {}",
+            key,
+            err
+        );
+    }
+    for key in ["rawset", "load", "metatable", "secrets"] {
+        assert_ne!(results.get::<String>(key)?, "ok", "{}", key);
+    }
+    // methods of engine userdata still work through the wrapper
+    assert_eq!(results.get::<String>("engine")?, "ok");
+    assert_eq!(*engine.0.read(), "mod");
+    assert_eq!(module.get::<String>("helper")?, "mine");
+    assert_eq!(module.get::<i64>("physics")?, 2);
+    // trusted code is untouched
+    let (helper, score, physics, formatted): (Value, i64, i64, String) = script
+        .load(
+            r#"print("still here")
+            return helper, mods.shared.score, require("Physics").new(2), string.format("%d", 3)"#,
+        )
+        .eval()
+        .map_err(|err| anyhow::anyhow!("{}", err))?;
+    assert!(helper.is_nil());
+    assert_eq!((score, physics, formatted.as_str()), (10, 4, "3"));
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use mlua::{Error as LuaError, IntoLuaMulti, Lua, Result, Table, Value};
+use std::fmt::{Display, Write as _};
+
+// deeper schemas are most likely cycles
+const MAX_SCHEMA_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+/// the first place a value doesn't match its schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// "player.tags[2]", "value" for the value itself
+    pub path: String,
+    pub expected: String,
+    pub got: &'static str,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.path, self.expected, self.got
+        )
+    }
+}
+
+fn path_string(path: &[Segment]) -> String {
+    if path.is_empty() {
+        return "value".to_owned();
+    }
+    let mut out = String::new();
+    for segment in path {
+        let _ = match segment {
+            Segment::Field(name) if out.is_empty() => write!(out, "{}", name),
+            Segment::Field(name) => write!(out, ".{}", name),
+            Segment::Index(index) => write!(out, "[{}]", index),
+        };
+    }
+    out
+}
+
+// integers are numbers to the scripts
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Integer(_) => "number",
+        value => value.type_name(),
+    }
+}
+
+fn matches_type(value: &Value, name: &str) -> Result<bool> {
+    Ok(match name {
+        "any" => !value.is_nil(),
+        "string" => value.is_string(),
+        "number" => value.is_number() || value.is_integer(),
+        "integer" => match value {
+            Value::Integer(_) => true,
+            Value::Number(n) => n.fract() == 0.0,
+            _ => false,
+        },
+        "boolean" => value.is_boolean(),
+        "table" => value.is_table(),
+        "function" => value.is_function(),
+        "userdata" => value.is_userdata(),
+        _ => {
+            return Err(LuaError::RuntimeError(format!(
+                "unknown schema type {}, expect string, number, integer, boolean, table, function, userdata or any",
+                name
+            )));
+        }
+    })
+}
+
+/// `{ "string" }`, a single item describing every item of an array
+fn array_item(schema: &Table) -> Result<Option<Value>> {
+    if schema.raw_len() == 1 && schema.pairs::<Value, Value>().count() == 1 {
+        return schema.raw_get(1).map(Some);
+    }
+    Ok(None)
+}
+
+/// checks `value` against `schema`, Ok(None) when it matches. A schema is a type name
+/// like "string", "integer" or "any", one ending in `?` also takes nil, an array
+/// `{ schema }` for every item, or a table of fields each with its own schema.
+/// Fields the schema doesn't name are allowed. Errors on a malformed schema
+pub fn validate(value: &Value, schema: &Value) -> Result<Option<Mismatch>> {
+    check(value, schema, &mut Vec::new())
+}
+
+fn check(value: &Value, schema: &Value, path: &mut Vec<Segment>) -> Result<Option<Mismatch>> {
+    if path.len() > MAX_SCHEMA_DEPTH {
+        return Err(LuaError::RuntimeError(
+            "schema nested too deep, cyclic?".to_owned(),
+        ));
+    }
+    let mismatch = |path: &[Segment], expected: String| Mismatch {
+        path: path_string(path),
+        expected,
+        got: type_name(value),
+    };
+    match schema {
+        Value::String(name) => {
+            let name = name.to_str()?;
+            let (name, optional) = match name.strip_suffix('?') {
+                Some(name) => (name, true),
+                None => (&*name, false),
+            };
+            if (optional && value.is_nil()) || matches_type(value, name)? {
+                return Ok(None);
+            }
+            Ok(Some(mismatch(path, name.to_owned())))
+        }
+        Value::Table(schema) => {
+            let Value::Table(table) = value else {
+                let expected = match array_item(schema)? {
+                    Some(_) => "array",
+                    None => "table",
+                };
+                return Ok(Some(mismatch(path, expected.to_owned())));
+            };
+            if let Some(item) = array_item(schema)? {
+                for (index, value) in table.sequence_values::<Value>().enumerate() {
+                    path.push(Segment::Index(index + 1));
+                    let found = check(&value?, &item, path)?;
+                    path.pop();
+                    if found.is_some() {
+                        return Ok(found);
+                    }
+                }
+                return Ok(None);
+            }
+            // sorted, the first mismatch is the same on every run
+            let mut fields = schema
+                .pairs::<Value, Value>()
+                .map(|pair| {
+                    let (key, field) = pair?;
+                    match key {
+                        Value::String(key) => Ok((key.to_str()?.to_owned(), field)),
+                        key => Err(LuaError::RuntimeError(format!(
+                            "schema field names must be strings, got {}",
+                            key.type_name()
+                        ))),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, field) in fields {
+                let value = table.raw_get::<Value>(key.as_str())?;
+                path.push(Segment::Field(key));
+                let found = check(&value, &field, path)?;
+                path.pop();
+                if found.is_some() {
+                    return Ok(found);
+                }
+            }
+            Ok(None)
+        }
+        schema => Err(LuaError::RuntimeError(format!(
+            "a schema is a type name or a table, got {}",
+            schema.type_name()
+        ))),
+    }
+}
+
+/// `require("schema")`, `schema.validate(value, schema)` returns true,
+/// or false and the first mismatch
+pub fn create_module(lua: &Lua) -> Result<Value> {
+    let module = lua.create_table()?;
+    module.set(
+        "validate",
+        lua.create_function(|lua, (value, schema): (Value, Value)| {
+            match validate(&value, &schema)? {
+                None => (true, Value::Nil).into_lua_multi(lua),
+                Some(mismatch) => (false, mismatch.to_string()).into_lua_multi(lua),
+            }
+        })?,
+    )?;
+    Ok(Value::Table(module))
+}
+
+#[test]
+fn test_schema() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    lua.globals().set("schema", create_module(&lua)?)?;
+    let schema: Value = lua
+        .load(r#"{ name = "string", hp = "number", tags = { "string" }, pet = { name = "string?" } }"#)
+        .eval()?;
+    let value = |source: &str| lua.load(source).eval::<Value>();
+
+    let player =
+        value(r#"{ name = "ada", hp = 10, tags = { "a", "b" }, pet = {}, extra = true }"#)?;
+    assert_eq!(validate(&player, &schema)?, None);
+    let wrong = value(r#"{ name = "ada", hp = "10", tags = {} , pet = {} }"#)?;
+    let mismatch = validate(&wrong, &schema)?.unwrap();
+    assert_eq!(
+        (
+            mismatch.path.as_str(),
+            mismatch.expected.as_str(),
+            mismatch.got
+        ),
+        ("hp", "number", "string")
+    );
+    let item = value(r#"{ name = "ada", hp = 1, tags = { "a", 2 }, pet = { name = false } }"#)?;
+    assert_eq!(
+        validate(&item, &schema)?.unwrap().to_string(),
+        "pet.name: expected string, got boolean"
+    );
+    let missing = value(r#"{ hp = 1, tags = { "a", 2 }, pet = {} }"#)?;
+    assert_eq!(
+        validate(&missing, &schema)?.unwrap().to_string(),
+        "name: expected string, got nil"
+    );
+    let array = value(r#"{ name = "ada", hp = 1, tags = { "a", 2 }, pet = {} }"#)?;
+    assert_eq!(validate(&array, &schema)?.unwrap().path, "tags[2]");
+    assert_eq!(
+        validate(&Value::Integer(3), &schema)?.unwrap().to_string(),
+        "value: expected table, got number"
+    );
+
+    lua.load(
+        r#"
+        assert(schema.validate({ level = 3 }, { level = "integer" }))
+        local ok, err = schema.validate({ level = 3.5 }, { level = "integer" })
+        assert(not ok and err == "level: expected integer, got number")
+        -- a broken schema is an error, not a mismatch
+        assert(not pcall(schema.validate, {}, { level = "int" }))
+        assert(not pcall(schema.validate, {}, { [1] = "string", [2] = "number" }))
+        "#,
+    )
+    .exec()?;
+    Ok(())
+}
@@ -8,7 +8,14 @@ pub fn lua_value_to_bson(value: Value) -> mlua::Result<Bson> {
         Value::Boolean(b) => Bson::Boolean(b),
         Value::Integer(i) => Bson::Int64(i),
         Value::Number(f) => Bson::Double(f),
-        Value::String(s) => Bson::String(s.to_str()?.to_owned()),
+        // not utf8, e.g. a serialized physics world
+        Value::String(s) => match s.to_str() {
+            Ok(s) => Bson::String(s.to_owned()),
+            Err(_) => Bson::Binary(bson::Binary {
+                subtype: bson::spec::BinarySubtype::Generic,
+                bytes: s.as_bytes().to_vec(),
+            }),
+        },
         Value::Table(t) => {
             if is_array_table(&t)? {
                 let mut arr = Vec::new();
@@ -60,6 +67,7 @@ pub fn bson_to_lua_value(lua: &Lua, value: &Bson) -> mlua::Result<Value> {
         Bson::Int64(i) => Value::Integer(*i),
         Bson::Double(f) => Value::Number(*f),
         Bson::String(s) => Value::String(lua.create_string(&s)?),
+        Bson::Binary(b) => Value::String(lua.create_string(&b.bytes)?),
         Bson::Array(arr) => {
             let table = lua.create_table()?;
             for (i, item) in arr.into_iter().enumerate() {
@@ -79,3 +87,20 @@ fn bson_doc_to_lua_table<'lua>(lua: &'lua Lua, doc: &Document) -> mlua::Result<T
     }
     Ok(table)
 }
+
+#[test]
+fn test_binary_string() -> mlua::Result<()> {
+    let lua = Lua::new();
+    let bytes = lua.create_string([0xff, 0x00, 0xfe])?;
+    let bson = lua_value_to_bson(Value::String(bytes))?;
+    assert!(matches!(bson, Bson::Binary(_)));
+    let Value::String(back) = bson_to_lua_value(&lua, &bson)? else {
+        panic!("not a string");
+    };
+    assert_eq!(back.as_bytes().as_ref(), [0xff, 0x00, 0xfe]);
+    assert!(matches!(
+        lua_value_to_bson(Value::String(lua.create_string("text")?))?,
+        Bson::String(_)
+    ));
+    Ok(())
+}
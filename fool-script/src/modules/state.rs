@@ -0,0 +1,191 @@
+use super::stdlib::deep_copy;
+use mlua::{
+    AnyUserData, Error as LuaError, Function, Lua, Result, Table, UserData, UserDataMethods, Value,
+};
+
+/// a table whose writes go through `set` so subscribers see every change,
+/// values are copied in so the old value handed to subscribers stays intact
+pub struct StateHandle {
+    data: Table,
+    subscribers: Vec<(u64, Function)>,
+    next_id: u64,
+}
+
+/// "player.items.1" -> ["player", "items", 1]
+fn path_keys(lua: &Lua, path: &str) -> Result<Vec<Value>> {
+    path.split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.parse::<i64>() {
+            Ok(index) => Ok(Value::Integer(index)),
+            Err(_) => lua.create_string(part).map(Value::String),
+        })
+        .collect()
+}
+
+pub fn create(lua: &Lua, initial: Option<Table>) -> Result<StateHandle> {
+    let data = match initial {
+        Some(initial) => match deep_copy(lua, Value::Table(initial), 0)? {
+            Value::Table(data) => data,
+            _ => unreachable!(),
+        },
+        None => lua.create_table()?,
+    };
+    Ok(StateHandle {
+        data,
+        subscribers: Vec::new(),
+        next_id: 1,
+    })
+}
+
+/// nil for missing paths, an empty path returns the whole table
+pub fn get(lua: &Lua, handle: &AnyUserData, path: Option<String>) -> Result<Value> {
+    let mut current = Value::Table(handle.borrow::<StateHandle>()?.data.clone());
+    for key in path_keys(lua, path.as_deref().unwrap_or(""))? {
+        current = match current {
+            Value::Table(table) => table.raw_get(key)?,
+            _ => return Ok(Value::Nil),
+        };
+    }
+    Ok(current)
+}
+
+/// stores `value` at `path`, creating missing tables on the way,
+/// then calls every subscriber with `(path, value, old)` and returns the old value
+pub fn set(lua: &Lua, handle: &AnyUserData, path: &str, value: Value) -> Result<Value> {
+    let keys = path_keys(lua, path)?;
+    let Some((last, parents)) = keys.split_last() else {
+        return Err(LuaError::RuntimeError("state path is empty".to_owned()));
+    };
+    // released before calling out, subscribers may read the state again
+    let (mut table, subscribers) = {
+        let state = handle.borrow::<StateHandle>()?;
+        let subscribers = state
+            .subscribers
+            .iter()
+            .map(|(_, func)| func.clone())
+            .collect::<Vec<_>>();
+        (state.data.clone(), subscribers)
+    };
+    for key in parents {
+        table = match table.raw_get::<Value>(key.clone())? {
+            Value::Table(child) => child,
+            Value::Nil => {
+                let child = lua.create_table()?;
+                table.raw_set(key.clone(), child.clone())?;
+                child
+            }
+            _ => {
+                return Err(LuaError::RuntimeError(format!(
+                    "state path {} crosses a non table value",
+                    path
+                )));
+            }
+        };
+    }
+    let value = deep_copy(lua, value, 0)?;
+    let old = table.raw_get::<Value>(last.clone())?;
+    table.raw_set(last.clone(), value.clone())?;
+    for subscriber in subscribers {
+        subscriber.call::<()>((path, value.clone(), old.clone()))?;
+    }
+    Ok(old)
+}
+
+/// returns an id for `unsubscribe`
+pub fn subscribe(handle: &AnyUserData, func: Function) -> Result<u64> {
+    let mut state = handle.borrow_mut::<StateHandle>()?;
+    let id = state.next_id;
+    state.next_id += 1;
+    state.subscribers.push((id, func));
+    Ok(id)
+}
+
+pub fn unsubscribe(handle: &AnyUserData, id: u64) -> Result<bool> {
+    let mut state = handle.borrow_mut::<StateHandle>()?;
+    let count = state.subscribers.len();
+    state.subscribers.retain(|(sub, _)| *sub != id);
+    Ok(state.subscribers.len() != count)
+}
+
+impl UserData for StateHandle {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_function("get", |lua, (this, path): (AnyUserData, Option<String>)| {
+            get(lua, &this, path)
+        });
+        methods.add_function(
+            "set",
+            |lua, (this, path, value): (AnyUserData, String, Value)| set(lua, &this, &path, value),
+        );
+        methods.add_function("subscribe", |_, (this, func): (AnyUserData, Function)| {
+            subscribe(&this, func)
+        });
+        methods.add_function("unsubscribe", |_, (this, id): (AnyUserData, u64)| {
+            unsubscribe(&this, id)
+        });
+    }
+}
+
+/// `require("state")`, every function also works as a method on the handle
+pub fn create_module(lua: &Lua) -> Result<Value> {
+    let module = lua.create_table()?;
+    module.set(
+        "create",
+        lua.create_function(|lua, initial: Option<Table>| create(lua, initial))?,
+    )?;
+    module.set(
+        "get",
+        lua.create_function(|lua, (handle, path): (AnyUserData, Option<String>)| {
+            get(lua, &handle, path)
+        })?,
+    )?;
+    module.set(
+        "set",
+        lua.create_function(|lua, (handle, path, value): (AnyUserData, String, Value)| {
+            set(lua, &handle, &path, value)
+        })?,
+    )?;
+    module.set(
+        "subscribe",
+        lua.create_function(|_, (handle, func): (AnyUserData, Function)| subscribe(&handle, func))?,
+    )?;
+    module.set(
+        "unsubscribe",
+        lua.create_function(|_, (handle, id): (AnyUserData, u64)| unsubscribe(&handle, id))?,
+    )?;
+    Ok(Value::Table(module))
+}
+
+#[test]
+fn test_state_subscribe() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    lua.globals().set("state", create_module(&lua)?)?;
+    lua.load(
+        r#"
+        local initial = { player = { hp = 10 } }
+        local s = state.create(initial)
+        local changes = {}
+        local id = state.subscribe(s, function(path, value, old)
+            table.insert(changes, { path = path, value = value, old = old })
+            -- the state is readable from inside a subscriber
+            assert(state.get(s, path) == value)
+        end)
+        assert(state.set(s, "player.hp", 7) == 10)
+        assert(#changes == 1 and changes[1].path == "player.hp")
+        assert(changes[1].value == 7 and changes[1].old == 10)
+        assert(initial.player.hp == 10)
+
+        s:set("player.items.1.name", "sword")
+        assert(changes[2].path == "player.items.1.name" and changes[2].value == "sword")
+        assert(changes[2].old == nil)
+        assert(s:get("player.items")[1].name == "sword")
+        assert(s:get("player.missing.deeper") == nil)
+        assert(not pcall(state.set, s, "player.hp.value", 1))
+
+        assert(state.unsubscribe(s, id))
+        s:set("player.hp", 1)
+        assert(#changes == 2)
+        "#,
+    )
+    .exec()?;
+    Ok(())
+}
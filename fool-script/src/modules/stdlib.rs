@@ -10,6 +10,7 @@ use mlua::{
 };
 use parking_lot::Mutex;
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
@@ -17,6 +18,131 @@ use std::time::{Instant, SystemTime, UNIX_EPOCH};
 lazy_static! {
     static ref start_time: Instant = Instant::now();
 }
+// deeper tables are most likely cycles
+const MAX_MERGE_DEPTH: usize = 64;
+
+/// sequences with keys 1..=n only, empty tables count as maps
+fn is_array(table: &Table) -> bool {
+    let len = table.raw_len();
+    len > 0 && table.pairs::<Value, Value>().count() == len
+}
+
+pub(crate) fn deep_copy(lua: &Lua, value: Value, depth: usize) -> Result<Value> {
+    let Value::Table(table) = value else {
+        return Ok(value);
+    };
+    if depth > MAX_MERGE_DEPTH {
+        return Err(LuaError::RuntimeError(
+            "table nested too deep, cyclic?".to_owned(),
+        ));
+    }
+    let copy = lua.create_table()?;
+    for pair in table.pairs::<Value, Value>() {
+        let (k, v) = pair?;
+        copy.raw_set(k, deep_copy(lua, v, depth + 1)?)?;
+    }
+    Ok(Value::Table(copy))
+}
+
+/// a new table with `over` merged into `base`, maps merge recursively,
+/// scalars from `over` win and arrays replace unless `concat_arrays`
+pub fn deep_merge(lua: &Lua, base: &Table, over: &Table, concat_arrays: bool) -> Result<Table> {
+    merge_tables(lua, base, over, concat_arrays, 0)
+}
+
+fn merge_tables(
+    lua: &Lua,
+    base: &Table,
+    over: &Table,
+    concat_arrays: bool,
+    depth: usize,
+) -> Result<Table> {
+    if depth > MAX_MERGE_DEPTH {
+        return Err(LuaError::RuntimeError(
+            "table nested too deep, cyclic?".to_owned(),
+        ));
+    }
+    if is_array(base) && is_array(over) {
+        let merged = lua.create_table()?;
+        let src = if concat_arrays {
+            vec![base, over]
+        } else {
+            vec![over]
+        };
+        for table in src {
+            for v in table.sequence_values::<Value>() {
+                merged.raw_push(deep_copy(lua, v?, depth + 1)?)?;
+            }
+        }
+        return Ok(merged);
+    }
+    let Value::Table(merged) = deep_copy(lua, Value::Table(base.clone()), depth)? else {
+        unreachable!()
+    };
+    for pair in over.pairs::<Value, Value>() {
+        let (k, v) = pair?;
+        let value = match (merged.raw_get::<Value>(k.clone())?, v) {
+            (Value::Table(b), Value::Table(o)) => {
+                Value::Table(merge_tables(lua, &b, &o, concat_arrays, depth + 1)?)
+            }
+            (_, v) => deep_copy(lua, v, depth + 1)?,
+        };
+        merged.raw_set(k, value)?;
+    }
+    Ok(merged)
+}
+
+/// a `log.event` record, the fields follow the message as `key=value` pairs
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEvent {
+    pub level: log::Level,
+    pub message: String,
+    /// the values as json, sorted by key
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LogEvent {
+    pub fn from_lua(level: &str, message: String, fields: Option<Table>) -> Result<Self> {
+        let level = log::Level::from_str(level).unwrap_or(log::Level::Trace);
+        let mut fields = match fields {
+            Some(fields) => serde_json::to_value(Value::Table(fields)).map_err(|err| {
+                LuaError::RuntimeError(format!("log fields are not serializable: {}", err))
+            })?,
+            None => serde_json::Value::Null,
+        };
+        // lua tables have no order, sorted the same record reads the same every time
+        fields.sort_all_objects();
+        let fields = match fields {
+            serde_json::Value::Object(fields) => fields,
+            // an empty table is an array to serde
+            serde_json::Value::Null => Default::default(),
+            serde_json::Value::Array(items) if items.is_empty() => Default::default(),
+            _ => {
+                return Err(LuaError::RuntimeError(
+                    "log fields must be a table with string keys".to_owned(),
+                ));
+            }
+        };
+        Ok(Self {
+            level,
+            message,
+            fields,
+        })
+    }
+    pub fn log(&self) {
+        log::log!(self.level, "{}", self);
+    }
+}
+
+impl Display for LogEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for (key, value) in &self.fields {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
 
 pub fn init_stdlib(lua: &Lua) -> Result<()> {
     let os_table = lua.create_table()?;
@@ -71,6 +197,29 @@ pub fn init_stdlib(lua: &Lua) -> Result<()> {
     os_table.set("rename", Value::Nil)?;
     os_table.set("setlocale", Value::Nil)?;
     lua.globals().set("os", os_table)?;
+
+    // table.deep_merge(base, override, { arrays = "concat" })
+    let table: Table = lua.globals().get("table")?;
+    let merge = lua.create_function(
+        |lua, (base, over, options): (Table, Table, Option<Table>)| {
+            let arrays = match options {
+                Some(options) => options.get::<Option<String>>("arrays")?,
+                None => None,
+            };
+            let concat = match arrays.as_deref() {
+                None | Some("replace") => false,
+                Some("concat") => true,
+                Some(other) => {
+                    return Err(LuaError::RuntimeError(format!(
+                        "unknown arrays option {}, expect replace or concat",
+                        other
+                    )));
+                }
+            };
+            deep_merge(lua, &base, &over, concat)
+        },
+    )?;
+    table.set("deep_merge", merge)?;
     Ok(())
 }
 
@@ -119,6 +268,19 @@ pub fn enable_debug(lua: &Lua) -> anyhow::Result<()> {
         lua.globals().set("__logger", log_print),
         "globals set __logger"
     )?;
+    // log.event("info", "hit", { damage = 5 }) logs `hit damage=5`
+    let log_event = map2anyhow_error!(
+        lua.create_function(
+            move |_, (level, message, fields): (String, String, Option<Table>)| {
+                LogEvent::from_lua(&level, message, fields)?.log();
+                Ok(())
+            }
+        ),
+        "create_function log_event"
+    )?;
+    let log_table = map2anyhow_error!(lua.create_table(), "create_table log")?;
+    map2anyhow_error!(log_table.set("event", log_event), "log set event")?;
+    map2anyhow_error!(lua.globals().set("log", log_table), "globals set log")?;
     let print = map2anyhow_error!(
         lua.create_function(move |_, value: Variadic<Value>| {
             log::debug!("{}", values_to_string(&value)?.join(", "));
@@ -140,3 +302,75 @@ pub fn enable_debug(lua: &Lua) -> anyhow::Result<()> {
     )?;
     Ok(())
 }
+
+#[test]
+fn test_deep_merge() -> anyhow::Result<()> {
+    let lua = Lua::new();
+    init_stdlib(&lua)?;
+    lua.load(
+        r#"
+        local base = {
+            window = { width = 800, height = 600, title = "fool" },
+            audio = { volume = 0.5, groups = { "music", "sfx" } },
+            keep = { deep = { value = 1 } },
+        }
+        local over = {
+            window = { width = 1024, fullscreen = true },
+            audio = { groups = { "voice" } },
+        }
+        local merged = table.deep_merge(base, over)
+        assert(merged.window.width == 1024 and merged.window.fullscreen)
+        assert(merged.window.height == 600 and merged.window.title == "fool")
+        assert(merged.keep.deep.value == 1)
+        assert(#merged.audio.groups == 1 and merged.audio.groups[1] == "voice")
+        assert(merged.audio.volume == 0.5)
+        -- inputs are left alone
+        assert(base.window.width == 800 and base.window.fullscreen == nil)
+        merged.keep.deep.value = 2
+        assert(base.keep.deep.value == 1)
+        local concat = table.deep_merge(base, over, { arrays = "concat" })
+        assert(#concat.audio.groups == 3 and concat.audio.groups[3] == "voice")
+        assert(not pcall(table.deep_merge, base, over, { arrays = "zip" }))
+        "#,
+    )
+    .exec()?;
+    Ok(())
+}
+
+#[test]
+fn test_log_event() -> anyhow::Result<()> {
+    struct Capture(Mutex<Vec<(log::Level, String)>>);
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            self.0
+                .lock()
+                .push((record.level(), record.args().to_string()));
+        }
+        fn flush(&self) {}
+    }
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+    log::set_logger(&CAPTURE).map_err(|err| anyhow::anyhow!("{}", err))?;
+    log::set_max_level(log::LevelFilter::Trace);
+    let lua = Lua::new();
+    enable_debug(&lua)?;
+    lua.load(
+        r#"
+        log.event("info", "hit", { damage = 5 })
+        log.event("warn", "spawn", { kind = "orc", at = { x = 1.5, y = 2 } })
+        log.event("debug", "tick")
+        assert(not pcall(log.event, "info", "bad", { 1, 2 }))
+        "#,
+    )
+    .exec()?;
+    let records = CAPTURE.0.lock().clone();
+    assert!(records.contains(&(log::Level::Info, "hit damage=5".to_owned())));
+    assert!(records.contains(&(
+        log::Level::Warn,
+        r#"spawn at={"x":1.5,"y":2} kind="orc""#.to_owned()
+    )));
+    assert!(records.contains(&(log::Level::Debug, "tick".to_owned())));
+    Ok(())
+}
@@ -29,6 +29,8 @@ impl Clone for Box<dyn UserModConstructor> {
 struct ModNode {
     children: HashMap<String, ModNode>,
     constructor: Option<Box<dyn UserModConstructor>>,
+    // hidden from untrusted modules
+    trusted_only: bool,
 }
 
 impl Debug for ModNode {
@@ -63,6 +65,32 @@ impl UserMod {
     where
         F: UserModConstructor + 'static,
     {
+        self.insert(path, Box::new(constructor), false);
+    }
+    /// required from trusted modules only
+    pub fn register_trusted<F>(&self, path: &str, constructor: F)
+    where
+        F: UserModConstructor + 'static,
+    {
+        self.insert(path, Box::new(constructor), true);
+    }
+    /// true when `path`, a module above or one in its tree is trusted only
+    pub fn is_trusted_only(&self, path: &str) -> bool {
+        fn any_below(node: &ModNode) -> bool {
+            node.trusted_only || node.children.values().any(any_below)
+        }
+        let root = self.root.read();
+        let mut node = &*root;
+        for part in path.split('.') {
+            match node.children.get(part) {
+                Some(child) if child.trusted_only => return true,
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+        any_below(node)
+    }
+    fn insert(&self, path: &str, constructor: Box<dyn UserModConstructor>, trusted_only: bool) {
         let parts = path.split('.').collect::<Vec<_>>();
         let mut node = &mut *self.root.write();
 
@@ -70,10 +98,29 @@ impl UserMod {
             node = node.children.entry(part.to_string()).or_default();
         }
 
-        node.children
+        let node = node
+            .children
             .entry(parts.last().unwrap().to_string())
-            .or_default()
-            .constructor = Some(Box::new(constructor));
+            .or_default();
+        node.constructor = Some(constructor);
+        node.trusted_only = trusted_only;
+    }
+
+    /// the registered module paths, parents of registered modules included
+    pub fn names(&self) -> Vec<String> {
+        fn collect(node: &ModNode, prefix: &str, names: &mut Vec<String>) {
+            for (name, child) in &node.children {
+                let path = match prefix {
+                    "" => name.clone(),
+                    prefix => format!("{}.{}", prefix, name),
+                };
+                collect(child, &path, names);
+                names.push(path);
+            }
+        }
+        let mut names = Vec::new();
+        collect(&self.root.read(), "", &mut names);
+        names
     }
 
     fn build_module_tree(lua: &Lua, node: &ModNode, parent_name: &str) -> mlua::Result<Value> {
@@ -128,11 +175,13 @@ impl UserMod {
                 Ok(module) => {
                     let loader = lua.create_function(move |_, ()| Ok(module.clone()))?;
                     log::trace!("lua module {} found!", modname);
-                    Ok((Value::Function(loader), lua.create_string(&modname)?))
+                    let name = lua.create_string(&modname)?;
+                    Ok((Value::Function(loader), Value::String(name)))
                 }
                 Err(e) => {
                     log::error!("lua module {} not found: {}!", modname, e);
-                    Ok((Value::Nil, lua.create_string("not found")?))
+                    let message = lua.create_string(format!("user module loader: {}", e))?;
+                    Ok((Value::String(message), Value::Nil))
                 }
             }
         })
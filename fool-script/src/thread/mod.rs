@@ -1,4 +1,4 @@
 pub mod fullchannel;
 mod task;
 mod thread;
-pub use thread::{AsyncScheduler, LuaTask};
+pub use thread::{AsyncScheduler, LuaTask, WorkerHealth, WorkerPanic, WorkerState};
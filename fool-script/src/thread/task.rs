@@ -103,7 +103,7 @@ impl LuaTask {
             },
             Err(e) => ThreadResponse {
                 id: self.id.clone(),
-                content: Err(e),
+                content: Err(e.into()),
             },
         }
     }
@@ -122,7 +122,7 @@ impl LuaTask {
             Err(e) => {
                 return ThreadResponse {
                     id: self.id.clone(),
-                    content: Err(e),
+                    content: Err(e.into()),
                 };
             }
         };
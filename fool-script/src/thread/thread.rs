@@ -1,13 +1,20 @@
 pub use super::task::{LuaTask, ThreadResponse};
-use crate::FoolScript;
-use crate::modules::{DSLID, Modules};
+use crate::modules::{DSLID, DSLModule, Modules, loader};
 use crate::thread::fullchannel::FullChannel;
+use crate::{FoolScript, ScriptError};
 use bson::Bson;
+use serde::Serialize;
+use std::any::Any;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::panic::{AssertUnwindSafe, catch_unwind};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 pub type StateMap = Arc<HashMap<DSLID, Bson>>;
+/// how long `stop_all` waits for workers before abandoning them
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Debug)]
 pub enum ThreadControl {
@@ -15,10 +22,65 @@ pub enum ThreadControl {
     Start(StateMap, u64),
     Stop,
 }
+
+/// a job panicked on the rust side, the worker got a fresh lua state and keeps running
+#[derive(Debug, Clone)]
+pub struct WorkerPanic {
+    pub id: DSLID,
+    pub message: String,
+}
+impl Display for WorkerPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "dsl module {} panicked: {}", self.id.name, self.message)
+    }
+}
+impl std::error::Error for WorkerPanic {}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerState {
+    Idle,
+    Busy,
+    /// the thread is gone, it is respawned on the next update
+    Dead,
+}
+
+#[derive(Debug, Default)]
+struct WorkerStatus {
+    busy: AtomicBool,
+    dead: AtomicBool,
+    restarts: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerHealth {
+    pub id: String,
+    pub state: WorkerState,
+    pub restarts: usize,
+}
+
+#[derive(Debug)]
+struct Worker {
+    task: LuaTask,
+    handle: JoinHandle<()>,
+    channel: FullChannel<ThreadControl, ThreadResponse>,
+    status: Arc<WorkerStatus>,
+}
+
 #[derive(Debug)]
 pub struct AsyncScheduler {
     modules: Modules,
-    task_map: HashMap<DSLID, (JoinHandle<()>, FullChannel<ThreadControl, ThreadResponse>)>,
+    task_map: HashMap<DSLID, Worker>,
     running: AtomicBool,
 }
 
@@ -30,53 +92,140 @@ impl AsyncScheduler {
             running: AtomicBool::new(false),
         }
     }
-    pub(crate) fn runner(
+    /// runs `job`, a panic becomes a failed response instead of killing the thread,
+    /// also one lua caught in a function it called
+    fn guarded(task: &LuaTask, job: impl FnOnce() -> ThreadResponse) -> (ThreadResponse, bool) {
+        let message = match catch_unwind(AssertUnwindSafe(job)) {
+            Ok(res) => match &res.content {
+                Err(err) if err.downcast_ref::<ScriptError>().is_some_and(|e| e.panic) => {
+                    err.to_string()
+                }
+                _ => return (res, false),
+            },
+            Err(payload) => panic_message(&payload),
+        };
+        let panic = WorkerPanic {
+            id: task.id.clone(),
+            message,
+        };
+        log::error!("{}", panic);
+        let res = ThreadResponse {
+            id: task.id.clone(),
+            content: Err(anyhow::Error::new(panic)),
+        };
+        (res, true)
+    }
+    fn setup_worker(
+        task: &LuaTask,
+        modules: &Modules,
+    ) -> anyhow::Result<(FoolScript, ThreadResponse)> {
+        // requiring the module registers it again, into a map of this lua state only,
+        // the shared one keeps pointing at the main state
+        let modules = Modules {
+            dsl_mod: DSLModule::new(),
+            ..modules.clone()
+        };
+        let script = FoolScript::setup_from_modules(&modules)?;
+        let (res, _) = Self::guarded(task, || task.run_init(&script));
+        Ok((script, res))
+    }
+    fn runner(
         task: LuaTask,
         modules: Modules,
         slaver: FullChannel<ThreadResponse, ThreadControl>,
+        status: Arc<WorkerStatus>,
     ) {
         let mut slaver = slaver;
-        match FoolScript::setup_from_modules(&modules) {
-            Ok(script) => {
-                let _ = slaver.sender().send(task.run_init(&script));
-                loop {
-                    if let Ok(control) = slaver.receiver().recv() {
-                        match control {
-                            ThreadControl::Start(state_map, frame_id) => {
-                                let res = if frame_id % task.frames_interval == 0 {
-                                    task.run_update(&script, &state_map)
-                                } else {
-                                    ThreadResponse {
-                                        id: task.id.clone(),
-                                        content: Ok(None),
-                                    }
-                                };
-                                let _ = slaver.sender().send(res);
+        let mut script = match Self::setup_worker(&task, &modules) {
+            Ok((script, res)) => {
+                let _ = slaver.sender().send(res);
+                script
+            }
+            Err(err) => {
+                log::error!("setup FoolScript env for {}, failed: {}", task.id, err);
+                let _ = slaver.sender().send(ThreadResponse {
+                    id: task.id.clone(),
+                    content: Err(err),
+                });
+                status.dead.store(true, Ordering::Release);
+                return;
+            }
+        };
+        // a closed channel means the scheduler is gone
+        while let Ok(control) = slaver.receiver().recv() {
+            match control {
+                ThreadControl::Start(state_map, frame_id) => {
+                    status.busy.store(true, Ordering::Release);
+                    // loads a worker started finish on its own lua state
+                    if let Err(err) = loader::poll(&script) {
+                        log::error!("dsl module {} resource callback failed: {}", task.id, err);
+                    }
+                    let (res, panicked) = if frame_id % task.frames_interval == 0 {
+                        Self::guarded(&task, || task.run_update(&script, &state_map))
+                    } else {
+                        let res = ThreadResponse {
+                            id: task.id.clone(),
+                            content: Ok(None),
+                        };
+                        (res, false)
+                    };
+                    if panicked {
+                        // the lua state may be half way through a call, start over
+                        status.restarts.fetch_add(1, Ordering::AcqRel);
+                        match Self::setup_worker(&task, &modules) {
+                            Ok((new_script, init)) => {
+                                if let Err(err) = init.content {
+                                    log::error!("restart {} init failed: {}", task.id, err);
+                                }
+                                script = new_script;
                             }
-                            ThreadControl::Stop => {
-                                log::debug!("stop thread {}", task.id);
-                                break;
+                            Err(err) => {
+                                log::error!("restart {} failed: {}", task.id, err);
+                                status.dead.store(true, Ordering::Release);
+                                let _ = slaver.sender().send(res);
+                                return;
                             }
                         }
                     }
+                    status.busy.store(false, Ordering::Release);
+                    let _ = slaver.sender().send(res);
+                }
+                ThreadControl::Stop => {
+                    log::debug!("stop thread {}", task.id);
+                    break;
                 }
-            }
-            Err(err) => {
-                log::error!("setup FoolScript env for {}, failed: {}", task.id, err);
             }
         }
     }
     pub(crate) fn start_thread(&mut self, task: LuaTask, modules: Modules) -> anyhow::Result<()> {
         let (master, slave) = FullChannel::<ThreadControl, ThreadResponse>::new(1);
         let task_cloneed = task.clone();
+        // a respawned worker keeps counting restarts
+        let restarts = self
+            .task_map
+            .get(&task.id)
+            .map_or(0, |w| w.status.restarts.load(Ordering::Acquire));
+        let status = Arc::new(WorkerStatus {
+            restarts: AtomicUsize::new(restarts),
+            ..Default::default()
+        });
+        let status_cloned = status.clone();
         let res = std::thread::Builder::new()
             .name(format!("Fool-Script"))
             .spawn(move || {
-                Self::runner(task_cloneed, modules, slave);
+                Self::runner(task_cloneed, modules, slave, status_cloned);
             });
         match res {
             Ok(h) => {
-                self.task_map.insert(task.id, (h, master));
+                self.task_map.insert(
+                    task.id.clone(),
+                    Worker {
+                        task,
+                        handle: h,
+                        channel: master,
+                        status,
+                    },
+                );
                 Ok(())
             }
             Err(err) => {
@@ -85,6 +234,21 @@ impl AsyncScheduler {
             }
         }
     }
+    /// replaces a worker whose thread is gone, its init response is consumed here
+    fn respawn(&mut self, id: &DSLID) -> anyhow::Result<()> {
+        let Some(task) = self.task_map.get(id).map(|w| w.task.clone()) else {
+            return Ok(());
+        };
+        log::warn!("dsl module {} worker died, respawning", id);
+        if let Some(worker) = self.task_map.get(id) {
+            worker.status.restarts.fetch_add(1, Ordering::AcqRel);
+        }
+        self.start_thread(task, self.modules.clone())?;
+        if let Some(worker) = self.task_map.get_mut(id) {
+            worker.channel.receiver().recv()?.content?;
+        }
+        Ok(())
+    }
     pub(crate) fn state_map(&self) -> StateMap {
         Arc::new(
             self.modules
@@ -105,8 +269,8 @@ impl AsyncScheduler {
                 continue;
             }
             self.start_thread(t.clone(), modules.clone())?;
-            if let Some((_j, c)) = self.task_map.get_mut(&t.id) {
-                let res = c.receiver().recv()?;
+            if let Some(worker) = self.task_map.get_mut(&t.id) {
+                let res = worker.channel.receiver().recv()?;
                 if res.is_error() {
                     self.stop_all();
                     return res.content.map(|_| ());
@@ -115,31 +279,108 @@ impl AsyncScheduler {
         }
         Ok(())
     }
-    pub fn stop_all(&mut self) {
-        for (id, (j, control)) in self.task_map.drain() {
-            let _ = control.sender().send(ThreadControl::Stop);
-            let _ = j.join();
-            log::trace!("stop dsl module {}", id)
+    /// signals every worker, waits up to `timeout` and returns the ones left running
+    pub fn stop_all_timeout(&mut self, timeout: Duration) -> Vec<DSLID> {
+        self.running.store(false, Ordering::SeqCst);
+        for worker in self.task_map.values() {
+            let _ = worker.channel.sender().send(ThreadControl::Stop);
         }
+        let deadline = Instant::now() + timeout;
+        let mut abandoned = Vec::new();
+        for (id, worker) in self.task_map.drain() {
+            while !worker.handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            if worker.handle.is_finished() {
+                let _ = worker.handle.join();
+                log::trace!("stop dsl module {}", id)
+            } else {
+                log::warn!("dsl module {} did not stop in {:?}, abandoned", id, timeout);
+                abandoned.push(id);
+            }
+        }
+        abandoned
+    }
+    pub fn stop_all(&mut self) -> Vec<DSLID> {
+        self.stop_all_timeout(STOP_TIMEOUT)
+    }
+    pub fn health(&self) -> Vec<WorkerHealth> {
+        let mut health = self
+            .task_map
+            .iter()
+            .map(|(id, worker)| {
+                let state =
+                    if worker.status.dead.load(Ordering::Acquire) || worker.handle.is_finished() {
+                        WorkerState::Dead
+                    } else if worker.status.busy.load(Ordering::Acquire) {
+                        WorkerState::Busy
+                    } else {
+                        WorkerState::Idle
+                    };
+                WorkerHealth {
+                    id: id.name.clone(),
+                    state,
+                    restarts: worker.status.restarts.load(Ordering::Acquire),
+                }
+            })
+            .collect::<Vec<_>>();
+        health.sort_by(|a, b| a.id.cmp(&b.id));
+        health
     }
     pub fn start_update(&mut self, frame_id: u64) {
+        let dead = self
+            .task_map
+            .iter()
+            .filter(|(_, w)| w.handle.is_finished())
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        for id in dead {
+            if let Err(err) = self.respawn(&id) {
+                log::error!("respawn dsl module {} failed: {}", id, err);
+                self.task_map.remove(&id);
+            }
+        }
         self.running.store(true, Ordering::SeqCst);
         let state_map = self.state_map();
-        for (id, (_j, control)) in &mut self.task_map {
+        for (id, worker) in &mut self.task_map {
             log::trace!("start {} update function at frame {}", id, frame_id);
-            let _ = control
+            let _ = worker
+                .channel
                 .sender()
                 .send(ThreadControl::Start(state_map.clone(), frame_id));
         }
     }
+    /// finishes the `resource.load_async` calls of the main lua state, a failed
+    /// callback is logged and does not stop anything
+    pub fn poll_loads(&self, lua: &FoolScript) -> usize {
+        match loader::poll(lua) {
+            Ok(count) => count,
+            Err(err) => {
+                log::error!("resource callback failed: {}", err);
+                0
+            }
+        }
+    }
+    /// a `WorkerPanic` error leaves the workers running, any other error stops them
     pub fn fetch_result(&mut self, lua: &FoolScript, frame_id: u64) -> anyhow::Result<()> {
-        if !self.running.load(Ordering::Relaxed) {
+        self.poll_loads(lua);
+        if !self.running.swap(false, Ordering::SeqCst) {
             return Ok(());
         }
         let mut is_error = Ok(());
         let mut result_map = Vec::new();
-        for (id, (_j, control)) in &mut self.task_map {
-            let res = control.receiver().recv()?;
+        // every worker is drained so none is left a response behind
+        for (id, worker) in &mut self.task_map {
+            let res = match worker.channel.receiver().recv() {
+                Ok(res) => res,
+                Err(_) => ThreadResponse {
+                    id: id.clone(),
+                    content: Err(anyhow::Error::new(WorkerPanic {
+                        id: id.clone(),
+                        message: "worker thread exited".to_owned(),
+                    })),
+                },
+            };
             match res.content {
                 Ok(d) => {
                     log::trace!(
@@ -157,17 +398,19 @@ impl AsyncScheduler {
                         err,
                         frame_id
                     );
-                    is_error = Err(err);
-                    break;
+                    if is_error.is_ok() {
+                        is_error = Err(err);
+                    }
                 }
             }
         }
-        if is_error.is_err() {
+        if let Err(err) = &is_error
+            && !err.is::<WorkerPanic>()
+        {
             self.stop_all();
             return is_error;
         }
         let mut modules_lock = self.modules.dsl_mod.modules.write();
-        self.running.store(false, Ordering::SeqCst);
         for res in result_map {
             if let Some(m) = modules_lock.get_mut(&res.0) {
                 match res.1 {
@@ -179,6 +422,139 @@ impl AsyncScheduler {
                 }
             }
         }
-        Ok(())
+        is_error
+    }
+}
+
+#[test]
+fn test_worker_panic() -> anyhow::Result<()> {
+    use fool_resource::{Resource, SharedData};
+    let res = Resource::<String, SharedData>::empty();
+    res.load(
+        "worker.lua".to_owned(),
+        SharedData::from_vec(
+            r#"
+            local worker = {
+                name = "worker",
+                frames_interval = 1,
+                enabled = true,
+                shared_state = { count = 0 },
+                init = function() end,
+                update = function(ctx)
+                    ctx.shared_state.count = ctx.shared_state.count + 1
+                    if ctx.shared_state.count >= 2 then
+                        require("boom").explode()
+                    end
+                end,
+            }
+            register_threaded_module(worker)
+            return worker
+            "#
+            .as_bytes()
+            .to_vec(),
+        ),
+    );
+    let mut script = FoolScript::new(res)?;
+    script.setup()?;
+    // panics once, the restarted worker gets through
+    static EXPLODED: AtomicBool = AtomicBool::new(false);
+    script.register_user_mod("boom", |lua: &mlua::Lua| {
+        let boom = lua.create_table()?;
+        boom.set(
+            "explode",
+            lua.create_function(|_, ()| {
+                if !EXPLODED.swap(true, Ordering::SeqCst) {
+                    panic!("user module unwrap");
+                }
+                Ok(())
+            })?,
+        )?;
+        Ok(mlua::Value::Table(boom))
+    })?;
+    script.run("require(\"worker\")", "main")?;
+    let mut scheduler = AsyncScheduler::new(script.modules.clone());
+    scheduler.init()?;
+    let mut panics = 0;
+    for frame in 0..4 {
+        scheduler.start_update(frame);
+        if let Err(err) = scheduler.fetch_result(&script, frame) {
+            let panic = err.downcast_ref::<WorkerPanic>().expect("not a panic");
+            assert!(panic.message.contains("user module unwrap"));
+            panics += 1;
+        }
+        // the main thread lua state is untouched
+        assert_eq!(script.load("return 1 + 1").eval::<i32>()?, 2);
+    }
+    assert_eq!(panics, 1);
+    let health = scheduler.health();
+    assert_eq!(health.len(), 1);
+    assert_eq!(health[0].restarts, 1);
+    assert_ne!(health[0].state, WorkerState::Dead);
+    assert!(scheduler.stop_all().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_load_async() -> anyhow::Result<()> {
+    use fool_resource::{Fallback, Resource, SharedData};
+    #[derive(Debug, Clone)]
+    struct Slow;
+    impl Fallback for Slow {
+        type K = String;
+        type V = SharedData;
+        fn get(&self, key: &Self::K) -> anyhow::Result<Self::V> {
+            std::thread::sleep(Duration::from_millis(30));
+            match key.as_str() {
+                "level.txt" | "intro.txt" => Ok(SharedData::from(key.as_str())),
+                _ => anyhow::bail!("no {}", key),
+            }
+        }
+    }
+    let res = Resource::<String, SharedData>::from_fallback(Slow);
+    let mut script = FoolScript::new(res.clone())?;
+    script.setup()?;
+    script.run(
+        r#"
+        local resource = require("resource")
+        done = {}
+        resource.load_async("level.txt", function(data, err)
+            done.level = data
+            done.level_loaded = resource.is_loaded("level.txt")
+        end)
+        resource.load_async("missing.txt", function(data, err)
+            done.missing = err
+        end)
+        -- the same from a coroutine
+        intro = coroutine.wrap(function()
+            done.intro = resource.load("intro.txt")
+        end)
+        intro()
+        assert(not resource.is_loaded("level.txt"))
+        assert(not pcall(resource.load, "level.txt"))
+        "#,
+        "main",
+    )?;
+    let mut scheduler = AsyncScheduler::new(script.modules.clone());
+    let finished = || {
+        script
+            .load("return done.level and done.missing and done.intro")
+            .eval::<bool>()
+    };
+    let mut frame = 0;
+    while !finished()? {
+        assert!(frame < 200, "loads never finished");
+        scheduler.fetch_result(&script, frame)?;
+        std::thread::sleep(Duration::from_millis(5));
+        frame += 1;
     }
+    // loaded in the background, not in the first tick
+    assert!(frame > 1);
+    let done: mlua::Table = script.globals().get("done")?;
+    assert_eq!(done.get::<String>("level")?, "level.txt");
+    assert!(done.get::<bool>("level_loaded")?);
+    assert_eq!(done.get::<String>("intro")?, "intro.txt");
+    assert!(done.get::<String>("missing")?.contains("missing.txt"));
+    assert!(res.exists("level.txt") && !res.exists("missing.txt"));
+    assert_eq!(crate::modules::loader::pending(&script), 0);
+    Ok(())
 }
@@ -1,4 +1,5 @@
 mod window;
 pub use window::{
-    AppEvent, Application, CustomEvent, EventProxy, FoolWindow, WinEvent, WindowCursor,
+    AppEvent, Application, CustomEvent, EventProxy, FoolWindow, ImeState, LoopMode, MonitorArea,
+    WinEvent, WindowCursor, WindowState,
 };
@@ -3,6 +3,7 @@ use super::input::WinEvent;
 use downcast_rs::{Downcast, impl_downcast};
 use dyn_clone::{DynClone, clone_trait_object};
 use std::{fmt::Debug, sync::Arc};
+use winit::event_loop::ControlFlow;
 pub use winit::{event::WindowEvent, window::Window};
 
 pub trait CustomEvent: Downcast + DynClone + Send + Sync + Debug + 'static {}
@@ -11,8 +12,11 @@ impl_downcast!(CustomEvent);
 impl<T: Downcast + DynClone + Send + Sync + Debug + 'static> CustomEvent for T {}
 pub trait Application {
     fn init(&mut self, window: Arc<Window>, proxy: &EventProxy);
-    fn update(&mut self);
+    /// when the next frame is due, the event loop sleeps no longer than this
+    fn update(&mut self) -> ControlFlow;
     fn event(&mut self, event: &WinEvent, raw_event: &WindowEvent);
     fn exiting(&mut self);
     fn user_event(&mut self, _event: Box<dyn CustomEvent>) {}
+    /// called when the window gets occluded/minimized or restored
+    fn visibility_changed(&mut self, _visible: bool) {}
 }
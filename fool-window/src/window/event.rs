@@ -1,7 +1,7 @@
 use super::CustomEvent;
 use image::DynamicImage;
 use winit::{
-    event_loop::{ActiveEventLoop, ControlFlow},
+    event_loop::ActiveEventLoop,
     window::{Cursor, CursorIcon, CustomCursor},
 };
 #[derive(Debug, Clone, Default)]
@@ -9,8 +9,9 @@ pub enum AppEvent {
     #[default]
     None,
     SetCursor(WindowCursor),
-    ControlFlow(ControlFlow),
     Exit,
+    /// forgets the stored window state, the next start uses the config again
+    ResetWindowState,
     CustomEvent(Box<dyn CustomEvent>),
 }
 
@@ -0,0 +1,219 @@
+use super::Application;
+use std::time::{Duration, Instant};
+use winit::event_loop::{ActiveEventLoop, ControlFlow};
+
+/// how the event loop waits between frames once the events are handled,
+/// the longest it may sleep before the app's next frame is due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    /// sleeps until the next event or the frame the app asks for, an idle app
+    /// sleeps until input comes, easy on the battery and fine for menus
+    #[default]
+    Wait,
+    /// never sleeps, frames keep flowing for animation heavy games
+    Poll,
+    /// wakes up at least once per interval, usually the frame interval
+    WaitUntil(Duration),
+}
+
+impl LoopMode {
+    /// the control flow to set now, a `WaitUntil` deadline is counted from `now`
+    /// so it is set again before every wait
+    pub fn control_flow(&self, now: Instant) -> ControlFlow {
+        match self {
+            Self::Wait => ControlFlow::Wait,
+            Self::Poll => ControlFlow::Poll,
+            Self::WaitUntil(interval) => ControlFlow::WaitUntil(now + *interval),
+        }
+    }
+    /// `wanted` is when the app wants its next frame, the loop wakes for
+    /// whichever of it and the mode comes first
+    pub fn bound(&self, now: Instant, wanted: ControlFlow) -> ControlFlow {
+        match (self.control_flow(now), wanted) {
+            (ControlFlow::Poll, _) | (_, ControlFlow::Poll) => ControlFlow::Poll,
+            (ControlFlow::Wait, wanted) => wanted,
+            (bound, ControlFlow::Wait) => bound,
+            (ControlFlow::WaitUntil(bound), ControlFlow::WaitUntil(wanted)) => {
+                ControlFlow::WaitUntil(bound.min(wanted))
+            }
+        }
+    }
+}
+
+/// where the control flow is set, the running event loop
+pub trait FlowTarget {
+    fn set_control_flow(&self, flow: ControlFlow);
+}
+impl FlowTarget for ActiveEventLoop {
+    fn set_control_flow(&self, flow: ControlFlow) {
+        ActiveEventLoop::set_control_flow(self, flow)
+    }
+}
+
+/// the loop mode of the window and whether it is shown, set again before every wait,
+/// the only place the control flow of the event loop is set
+#[derive(Debug, Clone, Copy)]
+pub struct LoopFlow {
+    pub mode: LoopMode,
+    pub visible: bool,
+}
+impl Default for LoopFlow {
+    fn default() -> Self {
+        Self {
+            mode: LoopMode::default(),
+            visible: true,
+        }
+    }
+}
+impl LoopFlow {
+    /// `wanted` is what the app's update returned
+    pub fn apply(&self, target: &impl FlowTarget, now: Instant, wanted: ControlFlow) {
+        // a hidden window has no frames to keep flowing
+        let flow = match self.visible {
+            true => self.mode.bound(now, wanted),
+            false => ControlFlow::Wait,
+        };
+        target.set_control_flow(flow);
+    }
+    /// runs the app's update while shown and sets the flow to wait with after it
+    pub fn update(&self, app: &mut dyn Application, target: &impl FlowTarget, now: Instant) {
+        let wanted = match self.visible {
+            true => app.update(),
+            // nothing to present while hidden, wait for the restore event
+            false => ControlFlow::Wait,
+        };
+        self.apply(target, now, wanted);
+    }
+}
+
+#[test]
+fn test_loop_mode() {
+    let now = Instant::now();
+    assert_eq!(LoopMode::default().control_flow(now), ControlFlow::Wait);
+    assert_eq!(LoopMode::Poll.control_flow(now), ControlFlow::Poll);
+    let interval = Duration::from_millis(16);
+    assert_eq!(
+        LoopMode::WaitUntil(interval).control_flow(now),
+        ControlFlow::WaitUntil(now + interval)
+    );
+    // a later wait waits from then
+    let later = now + Duration::from_secs(1);
+    assert_eq!(
+        LoopMode::WaitUntil(interval).control_flow(later),
+        ControlFlow::WaitUntil(later + interval)
+    );
+}
+
+#[test]
+fn test_loop_flow() {
+    use std::cell::Cell;
+    struct Recorder(Cell<Option<ControlFlow>>);
+    impl FlowTarget for Recorder {
+        fn set_control_flow(&self, flow: ControlFlow) {
+            self.0.set(Some(flow));
+        }
+    }
+    let target = Recorder(Cell::new(None));
+    let now = Instant::now();
+    let interval = Duration::from_millis(16);
+    let mut flow = LoopFlow {
+        mode: LoopMode::Poll,
+        ..Default::default()
+    };
+    flow.apply(&target, now, ControlFlow::Wait);
+    assert_eq!(target.0.get(), Some(ControlFlow::Poll));
+    // minimized, the loop sleeps whatever the mode
+    flow.visible = false;
+    flow.apply(&target, now, ControlFlow::Wait);
+    assert_eq!(target.0.get(), Some(ControlFlow::Wait));
+    flow.mode = LoopMode::WaitUntil(interval);
+    flow.apply(&target, now, ControlFlow::Wait);
+    assert_eq!(target.0.get(), Some(ControlFlow::Wait));
+    // restored, the deadline counts from the wait it is set for
+    flow.visible = true;
+    let later = now + Duration::from_secs(1);
+    flow.apply(&target, later, ControlFlow::Wait);
+    assert_eq!(
+        target.0.get(),
+        Some(ControlFlow::WaitUntil(later + interval))
+    );
+}
+
+#[test]
+fn test_loop_mode_bound() {
+    let now = Instant::now();
+    let frame = now + Duration::from_millis(20);
+    let interval = Duration::from_millis(16);
+    // Wait leaves it to the app, an idle one sleeps until the next event
+    assert_eq!(
+        LoopMode::Wait.bound(now, ControlFlow::Wait),
+        ControlFlow::Wait
+    );
+    assert_eq!(
+        LoopMode::Wait.bound(now, ControlFlow::WaitUntil(frame)),
+        ControlFlow::WaitUntil(frame)
+    );
+    assert_eq!(
+        LoopMode::Wait.bound(now, ControlFlow::Poll),
+        ControlFlow::Poll
+    );
+    // WaitUntil wakes up no later than the interval
+    let mode = LoopMode::WaitUntil(interval);
+    assert_eq!(
+        mode.bound(now, ControlFlow::Wait),
+        ControlFlow::WaitUntil(now + interval)
+    );
+    assert_eq!(
+        mode.bound(now, ControlFlow::WaitUntil(frame)),
+        ControlFlow::WaitUntil(now + interval)
+    );
+    let soon = now + Duration::from_millis(5);
+    assert_eq!(
+        mode.bound(now, ControlFlow::WaitUntil(soon)),
+        ControlFlow::WaitUntil(soon)
+    );
+    assert_eq!(
+        LoopMode::Poll.bound(now, ControlFlow::Wait),
+        ControlFlow::Poll
+    );
+}
+
+#[test]
+fn test_wait_with_idle_app() {
+    use super::{EventProxy, WinEvent, WindowEvent};
+    use std::{cell::RefCell, sync::Arc};
+    use winit::window::Window;
+    // like the engine with a paused scheduler, nothing to draw until resumed
+    struct Idle(u32);
+    impl Application for Idle {
+        fn init(&mut self, _window: Arc<Window>, _proxy: &EventProxy) {}
+        fn update(&mut self) -> ControlFlow {
+            self.0 += 1;
+            ControlFlow::Wait
+        }
+        fn event(&mut self, _event: &WinEvent, _raw_event: &WindowEvent) {}
+        fn exiting(&mut self) {}
+    }
+    struct Recorder(RefCell<Vec<ControlFlow>>);
+    impl FlowTarget for Recorder {
+        fn set_control_flow(&self, flow: ControlFlow) {
+            self.0.borrow_mut().push(flow);
+        }
+    }
+    let target = Recorder(RefCell::new(Vec::new()));
+    let mut app = Idle(0);
+    let flow = LoopFlow::default();
+    assert_eq!(flow.mode, LoopMode::Wait);
+    flow.update(&mut app, &target, Instant::now());
+    // the loop sleeps and nothing was sent that would wake it up again
+    assert_eq!(app.0, 1);
+    assert_eq!(*target.0.borrow(), [ControlFlow::Wait]);
+    // hidden, the app is not even asked
+    let hidden = LoopFlow {
+        visible: false,
+        mode: LoopMode::Poll,
+    };
+    hidden.update(&mut app, &target, Instant::now());
+    assert_eq!(app.0, 1);
+    assert_eq!(*target.0.borrow(), [ControlFlow::Wait, ControlFlow::Wait]);
+}
@@ -51,41 +51,9 @@ impl CurrentInput {
 
     pub fn handle_event(&mut self, event: &WindowEvent) {
         match event {
-            WindowEvent::KeyboardInput { event, .. } => match event.state {
-                ElementState::Pressed => {
-                    let logical_key = &event.logical_key;
-                    if !self.key_held.contains(logical_key) {
-                        self.key_actions
-                            .push(KeyAction::Pressed(logical_key.clone()));
-                    }
-
-                    self.key_held.push(logical_key.clone());
-                    self.key_actions
-                        .push(KeyAction::PressedOs(logical_key.clone()));
-                    self.text.push(logical_key.clone());
-
-                    let physical_key = &event.physical_key;
-                    if !self.scancode_held.contains(physical_key) {
-                        self.scancode_actions
-                            .push(ScanCodeAction::Pressed(*physical_key));
-                        self.scancode_held.push(*physical_key);
-                    }
-
-                    self.scancode_actions
-                        .push(ScanCodeAction::PressedOs(*physical_key));
-                }
-                ElementState::Released => {
-                    let logical_key = &event.logical_key;
-                    self.key_held.retain(|x| x != logical_key);
-                    self.key_actions
-                        .push(KeyAction::Released(logical_key.clone()));
-
-                    let physical_key = &event.physical_key;
-                    self.scancode_held.retain(|x| x != physical_key);
-                    self.scancode_actions
-                        .push(ScanCodeAction::Released(*physical_key));
-                }
-            },
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.handle_key(&event.physical_key, &event.logical_key, event.state)
+            }
             WindowEvent::CursorMoved { position, .. } => {
                 self.cursor_point = Some((position.x as f32, position.y as f32));
             }
@@ -129,6 +97,46 @@ impl CurrentInput {
         }
     }
 
+    /// the logical key is what the layout types, the physical one where the key sits
+    pub fn handle_key(
+        &mut self,
+        physical_key: &PhysicalKey,
+        logical_key: &Key,
+        state: ElementState,
+    ) {
+        match state {
+            ElementState::Pressed => {
+                if !self.key_held.contains(logical_key) {
+                    self.key_actions
+                        .push(KeyAction::Pressed(logical_key.clone()));
+                }
+
+                self.key_held.push(logical_key.clone());
+                self.key_actions
+                    .push(KeyAction::PressedOs(logical_key.clone()));
+                self.text.push(logical_key.clone());
+
+                if !self.scancode_held.contains(physical_key) {
+                    self.scancode_actions
+                        .push(ScanCodeAction::Pressed(*physical_key));
+                    self.scancode_held.push(*physical_key);
+                }
+
+                self.scancode_actions
+                    .push(ScanCodeAction::PressedOs(*physical_key));
+            }
+            ElementState::Released => {
+                self.key_held.retain(|x| x != logical_key);
+                self.key_actions
+                    .push(KeyAction::Released(logical_key.clone()));
+
+                self.scancode_held.retain(|x| x != physical_key);
+                self.scancode_actions
+                    .push(ScanCodeAction::Released(*physical_key));
+            }
+        }
+    }
+
     pub fn handle_device_event(&mut self, event: &DeviceEvent) {
         if let DeviceEvent::MouseMotion { delta, .. } = event {
             match self.mouse_diff {
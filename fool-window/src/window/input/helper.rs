@@ -1,9 +1,10 @@
 use winit::dpi::PhysicalSize;
-use winit::event::{DeviceEvent, DeviceId, Event, Ime, MouseButton, WindowEvent};
+use winit::event::{DeviceEvent, DeviceId, ElementState, Event, Ime, MouseButton, WindowEvent};
 use winit::keyboard::{Key, KeyCode, PhysicalKey};
 
 use super::current::{CurrentInput, KeyAction, MouseAction, ScanCodeAction, mouse_button_to_int};
-use std::collections::HashSet;
+use super::ime::ImeState;
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 use std::{path::PathBuf, time::Duration};
 /// The main struct of the API.
@@ -22,6 +23,9 @@ use std::{path::PathBuf, time::Duration};
 #[derive(Clone, Debug)]
 pub struct WinEvent {
     current: Option<CurrentInput>,
+    ime: ImeState,
+    // what each physical key typed last, kept across steps and focus changes
+    key_labels: HashMap<KeyCode, Key>,
     dropped_file: Option<PathBuf>,
     window_resized: Option<PhysicalSize<u32>>,
     window_size: Option<(u32, u32)>,
@@ -30,6 +34,8 @@ pub struct WinEvent {
     destroyed: bool,
     close_requested: bool,
     focused: bool,
+    occluded: bool,
+    minimized: bool,
     active_cursors: HashSet<DeviceId>,
     must_redraw: bool,
     step_start: Option<Instant>,
@@ -46,6 +52,8 @@ impl WinEvent {
     pub fn new() -> WinEvent {
         WinEvent {
             current: Some(CurrentInput::new()),
+            ime: ImeState::default(),
+            key_labels: HashMap::new(),
             dropped_file: None,
             window_resized: None,
             window_size: None,
@@ -54,6 +62,8 @@ impl WinEvent {
             destroyed: false,
             close_requested: false,
             focused: false,
+            occluded: false,
+            minimized: false,
             must_redraw: false,
             step_start: None,
             step_duration: None,
@@ -115,6 +125,7 @@ impl WinEvent {
         self.step_start.get_or_insert(Instant::now());
         self.step_duration = None;
         self.must_redraw = false;
+        self.ime.step();
         if let Some(current) = &mut self.current {
             current.step();
         }
@@ -127,6 +138,7 @@ impl WinEvent {
             WindowEvent::Focused(false) => {
                 self.current = None;
                 self.focused = false;
+                self.ime.unfocus();
             }
             WindowEvent::Focused(true) => {
                 self.focused = true;
@@ -134,8 +146,11 @@ impl WinEvent {
                     self.current = Some(CurrentInput::new())
                 }
             }
+            WindowEvent::Occluded(occluded) => self.occluded = *occluded,
             WindowEvent::DroppedFile(path) => self.dropped_file = Some(path.clone()),
             WindowEvent::Resized(size) => {
+                // most platforms report minimizing as a zero sized resize
+                self.minimized = size.width == 0 || size.height == 0;
                 self.window_resized = Some(*size);
                 self.window_size = Some((*size).into());
             }
@@ -150,6 +165,10 @@ impl WinEvent {
                 self.active_cursors.remove(device_id);
             }
             WindowEvent::RedrawRequested => self.must_redraw = true,
+            WindowEvent::Ime(ime) => self.ime.handle(ime),
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.learn_label(&event.physical_key, &event.logical_key)
+            }
             _ => {}
         }
         if let Some(current) = &mut self.current {
@@ -157,6 +176,25 @@ impl WinEvent {
         }
     }
 
+    /// Feeds a key event without a `winit::event::KeyEvent`, those can't be built outside winit.
+    pub fn process_key(
+        &mut self,
+        physical_key: PhysicalKey,
+        logical_key: Key,
+        state: ElementState,
+    ) {
+        self.learn_label(&physical_key, &logical_key);
+        if let Some(current) = &mut self.current {
+            current.handle_key(&physical_key, &logical_key, state);
+        }
+    }
+
+    fn learn_label(&mut self, physical_key: &PhysicalKey, logical_key: &Key) {
+        if let PhysicalKey::Code(code) = physical_key {
+            self.key_labels.insert(*code, logical_key.clone());
+        }
+    }
+
     fn process_device_event(&mut self, event: &DeviceEvent) {
         if let Some(ref mut current) = self.current {
             current.handle_device_event(event);
@@ -464,9 +502,28 @@ impl WinEvent {
             None
         }
     }
+    /// Returns what the physical key typed the last time it was pressed, e.g. `z` for `KeyW` on AZERTY.
+    /// Returns `None` for keys not pressed yet, winit can't ask the layout for them.
+    pub fn key_label(&self, keycode: KeyCode) -> Option<&Key> {
+        self.key_labels.get(&keycode)
+    }
+
+    /// Returns every label learned so far, see `key_label`.
+    pub fn key_labels(&self) -> &HashMap<KeyCode, Key> {
+        &self.key_labels
+    }
+
+    /// Returns the composition kept across steps and what the last step committed.
+    pub fn ime_state(&self) -> &ImeState {
+        &self.ime
+    }
     pub fn focused(&self) -> bool {
         self.focused
     }
+    /// Returns false while the window is occluded or minimized.
+    pub fn visible(&self) -> bool {
+        !(self.occluded || self.minimized)
+    }
     pub fn is_cursor_active(&self) -> bool {
         !self.active_cursors.is_empty()
     }
@@ -479,3 +536,86 @@ impl WinEvent {
         self.step_duration
     }
 }
+
+#[test]
+fn test_occluded_visible() {
+    let mut input = WinEvent::new();
+    assert!(input.visible());
+    input.step_with_window_events(&[&WindowEvent::Occluded(true)]);
+    assert!(!input.visible());
+    input.step_with_window_events(&[&WindowEvent::Occluded(false)]);
+    assert!(input.visible());
+    input.step_with_window_events(&[&WindowEvent::Resized(PhysicalSize::new(0, 0))]);
+    assert!(!input.visible());
+    input.step_with_window_events(&[&WindowEvent::Resized(PhysicalSize::new(800, 600))]);
+    assert!(input.visible());
+}
+
+#[test]
+fn test_ime_state() {
+    let mut input = WinEvent::new();
+    input.step_with_window_events(&[&WindowEvent::Ime(Ime::Enabled)]);
+    assert!(input.ime_state().enabled);
+    let preedit = WindowEvent::Ime(Ime::Preedit("ni".into(), Some((2, 2))));
+    input.step_with_window_events(&[&preedit]);
+    assert_eq!(
+        input.ime_state().preedit,
+        Some(("ni".to_owned(), Some((2, 2))))
+    );
+    // still composing on the next step without new events
+    input.step_with_window_events(&[]);
+    assert_eq!(input.ime_state().preedit.as_ref().unwrap().0, "ni");
+    assert!(input.ime_state().commit.is_empty());
+
+    let clear = WindowEvent::Ime(Ime::Preedit(String::new(), None));
+    let commit = WindowEvent::Ime(Ime::Commit("你".into()));
+    let next = WindowEvent::Ime(Ime::Preedit("h".into(), Some((1, 1))));
+    input.step_with_window_events(&[&clear, &commit, &next]);
+    let state = input.ime_state();
+    assert_eq!(state.commit, "你");
+    assert_eq!(state.preedit, Some(("h".to_owned(), Some((1, 1)))));
+    assert_eq!(state.events().len(), 3);
+    // only the last event is what `ime` reports
+    assert_eq!(input.ime(), Some(Ime::Preedit("h".into(), Some((1, 1)))));
+
+    input.step_with_window_events(&[&WindowEvent::Ime(Ime::Disabled)]);
+    let state = input.ime_state();
+    assert!(!state.enabled && state.preedit.is_none() && state.commit.is_empty());
+}
+
+#[test]
+fn test_physical_logical_keys() {
+    let mut input = WinEvent::new();
+    // the W position on an AZERTY keyboard types z
+    input.step();
+    input.process_key(
+        PhysicalKey::Code(KeyCode::KeyW),
+        Key::Character("z".into()),
+        ElementState::Pressed,
+    );
+    input.end_step();
+    assert!(input.key_pressed(KeyCode::KeyW) && input.key_held(KeyCode::KeyW));
+    assert!(!input.key_pressed(KeyCode::KeyZ));
+    assert!(input.key_pressed_logical(Key::Character("z")));
+    assert!(!input.key_pressed_logical(Key::Character("w")));
+    assert_eq!(
+        input.key_label(KeyCode::KeyW),
+        Some(&Key::Character("z".into()))
+    );
+    assert_eq!(input.key_label(KeyCode::KeyA), None);
+
+    input.step_with_window_events(&[]);
+    assert!(!input.key_pressed(KeyCode::KeyW) && input.key_held_logical(Key::Character("z")));
+    input.step();
+    input.process_key(
+        PhysicalKey::Code(KeyCode::KeyW),
+        Key::Character("z".into()),
+        ElementState::Released,
+    );
+    input.end_step();
+    assert!(input.key_released(KeyCode::KeyW));
+    assert!(input.key_released_logical(Key::Character("z")));
+    assert!(!input.key_held(KeyCode::KeyW) && !input.key_held_logical(Key::Character("z")));
+    // the label outlives the press
+    assert!(input.key_label(KeyCode::KeyW).is_some());
+}
@@ -0,0 +1,46 @@
+use winit::event::Ime;
+
+/// what the input method composes, the preedit outlives the step it came in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImeState {
+    pub enabled: bool,
+    /// the composition not committed yet, (content, cursor byte range)
+    pub preedit: Option<(String, Option<(usize, usize)>)>,
+    /// everything committed during the step
+    pub commit: String,
+    // all of the step's events in order, a commit is often followed by a preedit
+    events: Vec<Ime>,
+}
+
+impl ImeState {
+    pub fn step(&mut self) {
+        self.commit.clear();
+        self.events.clear();
+    }
+    pub fn handle(&mut self, ime: &Ime) {
+        match ime {
+            Ime::Enabled => self.enabled = true,
+            Ime::Disabled => {
+                self.enabled = false;
+                self.preedit = None;
+            }
+            // an empty preedit is how the composition gets cleared
+            Ime::Preedit(content, cursor) => {
+                self.preedit = (!content.is_empty()).then(|| (content.clone(), *cursor));
+            }
+            Ime::Commit(text) => {
+                self.preedit = None;
+                self.commit.push_str(text);
+            }
+        }
+        self.events.push(ime.clone());
+    }
+    /// the composition is dropped with the focus, the input method doesn't always say so
+    pub fn unfocus(&mut self) {
+        self.enabled = false;
+        self.preedit = None;
+    }
+    pub fn events(&self) -> &[Ime] {
+        &self.events
+    }
+}
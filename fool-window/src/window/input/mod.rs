@@ -1,3 +1,5 @@
 mod current;
 mod helper;
+mod ime;
 pub use helper::WinEvent;
+pub use ime::ImeState;
@@ -1,12 +1,17 @@
 mod app;
 pub mod event;
+mod flow;
 pub mod input;
 pub mod proxy;
+pub mod state;
 pub use app::{Application, CustomEvent};
 pub use event::{AppEvent, WindowCursor};
-pub use input::WinEvent;
+use flow::LoopFlow;
+pub use flow::LoopMode;
+pub use input::{ImeState, WinEvent};
 pub use proxy::EventProxy;
-use std::sync::Arc;
+pub use state::{MonitorArea, WindowState};
+use std::{path::PathBuf, sync::Arc, time::Instant};
 use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
@@ -26,6 +31,11 @@ pub struct FoolWindow {
     owned_display_handle: Option<Arc<OwnedDisplayHandle>>,
     system_theme: Option<Arc<Theme>>,
     input: WinEvent,
+    // saved on exit when set
+    state_file: Option<PathBuf>,
+    // the monitor the restored window was on
+    state_monitor: Option<String>,
+    flow: LoopFlow,
 }
 impl FoolWindow {
     pub fn new(
@@ -48,8 +58,47 @@ impl FoolWindow {
             owned_display_handle: None,
             system_theme: None,
             input: WinEvent::new(),
+            state_file: None,
+            state_monitor: None,
+            flow: LoopFlow::default(),
         })
     }
+    /// saves the window state to `path` on exit, `restored` is what the
+    /// window attributes were built from
+    pub fn remember_state(&mut self, path: PathBuf, restored: Option<&WindowState>) {
+        self.state_monitor = restored.and_then(|state| state.monitor.clone());
+        self.state_file = Some(path);
+    }
+    /// applied from the next wait of the event loop on
+    pub fn set_loop_mode(&mut self, mode: LoopMode) {
+        self.flow.mode = mode;
+    }
+    pub fn loop_mode(&self) -> LoopMode {
+        self.flow.mode
+    }
+    // the stored position may be on a monitor that is gone or got smaller
+    fn fit_to_monitors(&mut self, event_loop: &ActiveEventLoop) {
+        let monitors = event_loop
+            .available_monitors()
+            .map(|m| MonitorArea::from(&m))
+            .collect::<Vec<_>>();
+        let primary = event_loop.primary_monitor().map(|m| MonitorArea::from(&m));
+        state::fit_attributes(
+            &mut self.window_attr,
+            self.state_monitor.as_deref(),
+            &monitors,
+            primary.as_ref(),
+        );
+    }
+    fn save_state(&self) {
+        let (Some(path), Some(window)) = (&self.state_file, &self.window) else {
+            return;
+        };
+        match WindowState::capture(window).save(path) {
+            Ok(_) => log::debug!("window state saved to {}", path.display()),
+            Err(err) => log::warn!("save window state {} failed: {}", path.display(), err),
+        }
+    }
     pub fn init(&mut self, window: Arc<Window>, event_loop: &ActiveEventLoop) {
         if self.window.is_some() {
             return;
@@ -72,6 +121,9 @@ impl FoolWindow {
         }
         Ok(())
     }
+    pub fn is_visible(&self) -> bool {
+        self.flow.visible
+    }
     pub fn set_cursor(&mut self, icon: Cursor) {
         self.cursor = Some(icon);
     }
@@ -80,6 +132,7 @@ impl FoolWindow {
 impl ApplicationHandler<AppEvent> for FoolWindow {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
+            self.fit_to_monitors(event_loop);
             match event_loop.create_window(self.window_attr.clone()) {
                 Ok(window) => {
                     let window = Arc::new(window);
@@ -92,7 +145,9 @@ impl ApplicationHandler<AppEvent> for FoolWindow {
                 }
             }
         }
-        event_loop.set_control_flow(ControlFlow::Wait);
+        // the first frame follows in about_to_wait
+        self.flow
+            .apply(event_loop, Instant::now(), ControlFlow::Wait);
     }
     fn window_event(
         &mut self,
@@ -102,12 +157,19 @@ impl ApplicationHandler<AppEvent> for FoolWindow {
     ) {
         self.input.step_with_window_events(&[&event]);
         self.app.event(&self.input, &event);
+        if self.input.visible() != self.flow.visible {
+            self.flow.visible = self.input.visible();
+            log::debug!("window visible: {}", self.flow.visible);
+            self.app.visibility_changed(self.flow.visible);
+        }
     }
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         if let (Some(window), Some(cursor)) = (&self.window, &self.cursor) {
             window.set_cursor(cursor.clone());
         }
-        self.app.update();
+        // a WaitUntil deadline moves on with every wait
+        self.flow
+            .update(self.app.as_mut(), event_loop, Instant::now());
     }
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: AppEvent) {
         match event {
@@ -119,6 +181,15 @@ impl ApplicationHandler<AppEvent> for FoolWindow {
                 Err(err) => log::error!("set cursor failed: {}", err),
             },
             AppEvent::Exit => event_loop.exit(),
+            AppEvent::ResetWindowState => {
+                if let Some(path) = self.state_file.take() {
+                    match std::fs::remove_file(&path) {
+                        Ok(_) => log::info!("window state {} removed", path.display()),
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(err) => log::warn!("remove {} failed: {}", path.display(), err),
+                    }
+                }
+            }
             AppEvent::CustomEvent(ev) => {
                 self.app.user_event(ev);
             }
@@ -126,6 +197,7 @@ impl ApplicationHandler<AppEvent> for FoolWindow {
         }
     }
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.save_state();
         self.app.exiting();
     }
 }
@@ -1,6 +1,5 @@
 use super::{AppEvent, Arc, EventLoopProxy};
 use crate::window::event::WindowCursor;
-use winit::event_loop::ControlFlow;
 #[repr(transparent)]
 #[derive(Debug, Clone)]
 pub struct EventProxy {
@@ -14,16 +13,10 @@ impl EventProxy {
     pub fn exit(&self) -> anyhow::Result<()> {
         self.send(AppEvent::Exit)
     }
+    pub fn reset_window_state(&self) -> anyhow::Result<()> {
+        self.send(AppEvent::ResetWindowState)
+    }
     pub fn set_cursor(&self, cursor: WindowCursor) -> anyhow::Result<()> {
         self.send(AppEvent::SetCursor(cursor))
     }
-    pub fn wait(&self) -> anyhow::Result<()> {
-        self.send(AppEvent::ControlFlow(ControlFlow::Wait))
-    }
-    pub fn poll(&self) -> anyhow::Result<()> {
-        self.send(AppEvent::ControlFlow(ControlFlow::Poll))
-    }
-    pub fn wait_util(&self, instant: std::time::Instant) -> anyhow::Result<()> {
-        self.send(AppEvent::ControlFlow(ControlFlow::WaitUntil(instant)))
-    }
 }
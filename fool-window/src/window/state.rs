@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window, WindowAttributes},
+};
+
+// a window smaller than this is not worth restoring
+const MIN_SIZE: u32 = 64;
+
+/// where the window was when the game closed, in physical pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    /// outer position, None where the platform does not tell, e.g. wayland
+    pub position: Option<(i32, i32)>,
+    /// inner size
+    pub size: (u32, u32),
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// name of the monitor the window was on
+    pub monitor: Option<String>,
+}
+
+impl WindowState {
+    pub fn capture(window: &Window) -> Self {
+        let size = window.inner_size();
+        Self {
+            position: window.outer_position().ok().map(|p| (p.x, p.y)),
+            size: (size.width, size.height),
+            maximized: window.is_maximized(),
+            fullscreen: window.fullscreen().is_some(),
+            monitor: window.current_monitor().and_then(|m| m.name()),
+        }
+    }
+    /// None when nothing was stored or the file is broken
+    pub fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read_to_string(path).ok()?;
+        match toml::from_str(&data) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                log::warn!("ignore window state {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+    /// merges the stored state over `attributes`
+    pub fn apply(&self, mut attributes: WindowAttributes) -> WindowAttributes {
+        let (width, height) = self.size;
+        if width >= MIN_SIZE && height >= MIN_SIZE {
+            attributes.inner_size = Some(Size::Physical(PhysicalSize::new(width, height)));
+        }
+        if let Some((x, y)) = self.position {
+            attributes.position = Some(Position::Physical(PhysicalPosition::new(x, y)));
+        }
+        attributes.maximized = self.maximized;
+        attributes.fullscreen = self.fullscreen.then_some(Fullscreen::Borderless(None));
+        attributes
+    }
+}
+
+/// the bounds of a connected monitor in physical pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorArea {
+    pub name: Option<String>,
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+    pub scale_factor: f64,
+}
+impl From<&MonitorHandle> for MonitorArea {
+    fn from(monitor: &MonitorHandle) -> Self {
+        let (position, size) = (monitor.position(), monitor.size());
+        Self {
+            name: monitor.name(),
+            position: (position.x, position.y),
+            size: (size.width, size.height),
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+}
+impl MonitorArea {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        let (left, top) = self.position;
+        let (right, bottom) = (left + self.size.0 as i32, top + self.size.1 as i32);
+        (left..right).contains(&x) && (top..bottom).contains(&y)
+    }
+    fn center(&self, (width, height): (u32, u32)) -> (i32, i32) {
+        let x = self.position.0 + (self.size.0.saturating_sub(width) / 2) as i32;
+        let y = self.position.1 + (self.size.1.saturating_sub(height) / 2) as i32;
+        (x, y)
+    }
+}
+
+/// keeps a window of `size` at `position` on a connected monitor, (position, size),
+/// a position off every monitor is centered on `preferred` or the primary one,
+/// the size is clamped to the monitor, winit does not report the work area
+pub fn fit_to_monitors(
+    position: Option<(i32, i32)>,
+    size: (u32, u32),
+    preferred: Option<&str>,
+    monitors: &[MonitorArea],
+    primary: Option<&MonitorArea>,
+) -> (Option<(i32, i32)>, (u32, u32)) {
+    let on_monitor = position.and_then(|p| monitors.iter().find(|m| m.contains(p)));
+    let named =
+        preferred.and_then(|name| monitors.iter().find(|m| m.name.as_deref() == Some(name)));
+    let Some(monitor) = on_monitor.or(named).or(primary).or(monitors.first()) else {
+        // nothing known about the monitors, leave it to the platform
+        return (position, size);
+    };
+    let size = (size.0.min(monitor.size.0), size.1.min(monitor.size.1));
+    let position = match (position, on_monitor) {
+        // pulled back in so the whole window is on the monitor
+        (Some((x, y)), Some(_)) => {
+            let (right, bottom) = (
+                monitor.position.0 + (monitor.size.0 - size.0) as i32,
+                monitor.position.1 + (monitor.size.1 - size.1) as i32,
+            );
+            Some((x.min(right), y.min(bottom)))
+        }
+        (Some(_), None) => Some(monitor.center(size)),
+        (None, _) => None,
+    };
+    (position, size)
+}
+
+/// `fit_to_monitors` for the position and size of `attributes`
+pub fn fit_attributes(
+    attributes: &mut WindowAttributes,
+    preferred: Option<&str>,
+    monitors: &[MonitorArea],
+    primary: Option<&MonitorArea>,
+) {
+    let Some(monitor) = primary.or(monitors.first()) else {
+        return;
+    };
+    let scale = monitor.scale_factor;
+    let position = attributes.position.map(|p| {
+        let p = p.to_physical::<i32>(scale);
+        (p.x, p.y)
+    });
+    let size = attributes.inner_size.map(|s| {
+        let s = s.to_physical::<u32>(scale);
+        (s.width, s.height)
+    });
+    let (position, fitted) = fit_to_monitors(
+        position,
+        size.unwrap_or((MIN_SIZE, MIN_SIZE)),
+        preferred,
+        monitors,
+        primary,
+    );
+    if let Some((x, y)) = position {
+        attributes.position = Some(Position::Physical(PhysicalPosition::new(x, y)));
+    }
+    if size.is_some() {
+        let (width, height) = fitted;
+        attributes.inner_size = Some(Size::Physical(PhysicalSize::new(width, height)));
+    }
+}
+
+#[test]
+fn test_fit_to_monitors() {
+    let monitor = |name: &str, x: i32, width: u32| MonitorArea {
+        name: Some(name.to_owned()),
+        position: (x, 0),
+        size: (width, 1080),
+        scale_factor: 1.0,
+    };
+    let left = monitor("left", 0, 1920);
+    let right = monitor("right", 1920, 1280);
+    let monitors = [left.clone(), right.clone()];
+    // still connected, kept where it was
+    assert_eq!(
+        fit_to_monitors(Some((100, 50)), (800, 600), None, &monitors, Some(&left)),
+        (Some((100, 50)), (800, 600))
+    );
+    // hanging off the right monitor, pulled back in and shrunk to it
+    assert_eq!(
+        fit_to_monitors(Some((2900, 900)), (1600, 600), None, &monitors, Some(&left)),
+        (Some((1920, 480)), (1280, 600))
+    );
+    // the right monitor got unplugged, centered on the primary
+    assert_eq!(
+        fit_to_monitors(
+            Some((2000, 100)),
+            (800, 600),
+            None,
+            std::slice::from_ref(&left),
+            Some(&left)
+        ),
+        (Some((560, 240)), (800, 600))
+    );
+    // off screen but the monitor it was on is still there by name
+    assert_eq!(
+        fit_to_monitors(Some((-5000, 0)), (800, 600), Some("right"), &monitors, None),
+        (Some((2160, 240)), (800, 600))
+    );
+    // no position to keep, only the size is clamped
+    assert_eq!(
+        fit_to_monitors(None, (4000, 600), None, &monitors, Some(&left)),
+        (None, (1920, 600))
+    );
+    assert_eq!(
+        fit_to_monitors(Some((-5000, 0)), (800, 600), None, &[], None),
+        (Some((-5000, 0)), (800, 600))
+    );
+}
+
+#[test]
+fn test_window_state_file() -> anyhow::Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("fool-window-{}", std::process::id()))
+        .join("window.toml");
+    let state = WindowState {
+        position: Some((-20, 40)),
+        size: (1280, 720),
+        maximized: false,
+        fullscreen: true,
+        monitor: Some("DP-1".to_owned()),
+    };
+    state.save(&path)?;
+    assert_eq!(WindowState::load(&path), Some(state.clone()));
+    let attributes = state.apply(WindowAttributes::default());
+    assert_eq!(
+        attributes.inner_size,
+        Some(Size::Physical(PhysicalSize::new(1280, 720)))
+    );
+    assert!(attributes.fullscreen.is_some());
+    std::fs::write(&path, "size = 3")?;
+    assert_eq!(WindowState::load(&path), None);
+    std::fs::remove_dir_all(path.parent().unwrap())?;
+    assert_eq!(WindowState::load(&path), None);
+    Ok(())
+}
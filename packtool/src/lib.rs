@@ -1,25 +1,41 @@
+mod manifest;
+mod patch;
 mod tee;
+mod volume;
 use bincode::{Decode, Encode, config::standard};
 use chrono::{DateTime, TimeZone, Utc};
+pub use manifest::{Diff, Manifest};
+use memmap2::Mmap;
+pub use patch::{Patch, PatchFile};
 use path_slash::PathExt;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 #[cfg(target_os = "linux")]
 use std::os::unix::fs::MetadataExt;
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
     fs::File,
     io::{BufWriter, Read, Seek, SeekFrom, Write},
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 use tee::{TeeReader, TeeWriter, WriteCounter};
+pub use volume::{VolumeReader, VolumeWriter, volume_base, volume_path, volume_paths};
 use zstd::stream::{Decoder, Encoder};
 const MAGIC: &[u8; 4] = b"GPAC";
-const VERSION: [u8; 4] = [0, 0, 0, 1];
+// 0.0.0.2 added volumes, the header flags after it are read as all off by
+// older readers so they did not bump it
+const VERSION: [u8; 4] = [0, 0, 0, 2];
 const FOOTER_MAGIC: &[u8; 7] = b"GPACEND";
 const FOOTER_LEN: usize = size_of::<u64>() * 2 + FOOTER_MAGIC.len();
-type Sha256Digest = [u8; 32];
-pub type MemResource = HashMap<String, Vec<u8>>;
+pub type Sha256Digest = [u8; 32];
+// volume, offset and length of stored entry data
+type DataLocation = (u32, u64, u64);
+// deduplicated entries share one buffer
+pub type MemResource = HashMap<String, Arc<[u8]>>;
 
 #[derive(Encode, Decode, Debug, Serialize, Deserialize, Default, Clone)]
 pub struct TimestampUtc(i64);
@@ -46,8 +62,51 @@ pub struct PackageHeader {
     // pub timestamp: DateTime<Utc>,
     pub timestamp: TimestampUtc,
     pub resource_id: String,
+    // 1 for an unsplit pak
+    pub volume_count: u32,
+    // has to stay last, see `HeaderFlags`
+    #[serde(default)]
+    pub flags: HeaderFlags,
 }
 
+const FLAG_DEDUP: u8 = 1;
+
+/// one byte at the end of the header, readers that predate it stop decoding
+/// before it and paks that predate it read as all off
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderFlags {
+    // identical files are stored once, their entries share offset and length
+    pub dedup: bool,
+}
+
+impl Encode for HeaderFlags {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        let mut bits = 0u8;
+        if self.dedup {
+            bits |= FLAG_DEDUP;
+        }
+        bits.encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for HeaderFlags {
+    fn decode<D: bincode::de::Decoder<Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        match u8::decode(decoder) {
+            Ok(bits) => Ok(Self {
+                dedup: bits & FLAG_DEDUP != 0,
+            }),
+            Err(bincode::error::DecodeError::UnexpectedEnd { .. }) => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+}
+bincode::impl_borrow_decode!(HeaderFlags);
+
 impl PackageHeader {
     pub fn version_string(&self) -> String {
         format!(
@@ -60,6 +119,11 @@ impl PackageHeader {
 #[derive(Serialize, Deserialize, Debug, Encode, Decode)]
 pub struct FileEntry {
     pub path: String,
+    // index of the volume the data starts in, entries bigger than a volume
+    // continue at the start of the following ones
+    pub volume: u32,
+    // relative to the start of `volume`, entries with the same data share
+    // it when the pak is deduplicated
     pub data_offset: u64,
     pub data_length: u64,
     pub hash: Sha256Digest,
@@ -68,11 +132,19 @@ pub struct FileEntry {
 #[derive(Default, Debug)]
 pub struct ResourcePackage {
     pub header: PackageHeader,
-    files: HashMap<String, PathBuf>,
+    // sorted, the entries are written in path order
+    files: BTreeMap<String, PathBuf>,
     pub entrys: Vec<FileEntry>,
     pub input: PathBuf,
     pub output: PathBuf,
     pub total_size: u64,
+    // volume size cap, `None` writes a single file
+    pub volume_size: Option<u64>,
+    // stamped instead of the current time, identical inputs then pack
+    // to identical bytes
+    pub source_date_epoch: Option<DateTime<Utc>>,
+    // store the data of identical files once
+    pub dedup: bool,
 }
 
 impl Display for ResourcePackage {
@@ -86,12 +158,39 @@ impl Display for ResourcePackage {
     }
 }
 
+// where the data of an entry is read from when writing a pak
+enum EntrySource<'a> {
+    File(&'a Path),
+    Memory(Cow<'a, [u8]>),
+}
+
+impl EntrySource<'_> {
+    fn open(&self) -> anyhow::Result<Box<dyn Read + '_>> {
+        Ok(match self {
+            EntrySource::File(path) => Box::new(File::open(path)?),
+            EntrySource::Memory(data) => Box::new(data.as_ref()),
+        })
+    }
+    fn len(&self) -> anyhow::Result<u64> {
+        Ok(match self {
+            EntrySource::File(path) => std::fs::metadata(path)?.len(),
+            EntrySource::Memory(data) => data.len() as u64,
+        })
+    }
+    fn hash(&self) -> anyhow::Result<Sha256Digest> {
+        let mut tee_reader = TeeReader::new(self.open()?);
+        std::io::copy(&mut tee_reader, &mut std::io::sink())?;
+        Ok(tee_reader.finalize())
+    }
+}
+
 impl ResourcePackage {
     pub fn create_pak(
         input: impl Into<PathBuf>,
         output: impl Into<PathBuf>,
         compress: bool,
         compress_level: i32,
+        volume_size: Option<u64>,
     ) -> Self {
         let resource_id: PathBuf = input.into();
         Self {
@@ -110,13 +209,37 @@ impl ResourcePackage {
                         "pak_{}",
                         chrono::Local::now().format("%Y%m%d_%H%M%S")
                     )),
+                volume_count: 1,
+                flags: Default::default(),
             },
             input: resource_id.clone(),
             output: output.into(),
             total_size: 0,
             entrys: Default::default(),
+            volume_size,
+            source_date_epoch: None,
+            dedup: true,
         }
     }
+    /// reproducible output, see `source_date_epoch`
+    pub fn with_source_date_epoch(mut self, timestamp: Option<DateTime<Utc>>) -> Self {
+        self.source_date_epoch = timestamp;
+        self
+    }
+    /// on by default, off stores every file on its own
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+    /// stored bytes the deduplicated entries share with an earlier one
+    pub fn dedup_saved(&self) -> u64 {
+        let mut stored = HashSet::new();
+        self.entrys
+            .iter()
+            .filter(|entry| !stored.insert((entry.volume, entry.data_offset, entry.data_length)))
+            .map(|entry| entry.data_length)
+            .sum()
+    }
 
     fn add_folder(&mut self) -> anyhow::Result<()> {
         let base: PathBuf = self.input.clone();
@@ -134,21 +257,71 @@ impl ResourcePackage {
 
     pub fn pack(&mut self) -> anyhow::Result<()> {
         self.add_folder()?;
+        let files = std::mem::take(&mut self.files);
+        let sources = files
+            .iter()
+            .map(|(rel_path, real_path)| Ok((rel_path.clone(), EntrySource::File(real_path))));
+        let result = self.write_entries(sources);
+        self.files = files;
+        result
+    }
+    // the write path shared by packing a folder and applying a patch, `sources`
+    // have to come in path order
+    fn write_entries<'a>(
+        &mut self,
+        sources: impl IntoIterator<Item = anyhow::Result<(String, EntrySource<'a>)>>,
+    ) -> anyhow::Result<()> {
         let path = self.output.clone();
         if let Some(p) = path.parent() {
             if !p.exists() {
                 std::fs::create_dir_all(&p)?;
             }
         }
-        let mut out_file = File::create(&path)?;
+        let mut out_file = VolumeWriter::create(&path, self.volume_size)?;
         let mut entries = Vec::new();
-        {
-            let mut offset = 0u64;
-
-            for (rel_path, real_path) in &self.files {
+        // only a file as long as one written before is hashed up front
+        let mut stored_lens = HashSet::new();
+        let mut stored: HashMap<Sha256Digest, DataLocation> = HashMap::new();
+        for source in sources {
+            let (rel_path, source) = source?;
+            let len = source.len()?;
+            if self.dedup && stored_lens.contains(&len) {
+                let hash = source.hash()?;
+                if let Some(&(volume, data_offset, data_length)) = stored.get(&hash) {
+                    log::debug!(
+                        "add {} to pack as a duplicate, volume: {}, offset: {}",
+                        rel_path,
+                        volume,
+                        data_offset
+                    );
+                    entries.push(FileEntry {
+                        path: rel_path,
+                        volume,
+                        data_offset,
+                        data_length,
+                        hash,
+                    });
+                    continue;
+                }
+            }
+            let mut tee_reader = TeeReader::new(source.open()?);
+            let (volume, data_offset, data_length);
+            if self.header.compress && out_file.is_split() {
+                // the compressed size decides which volume the entry goes to
+                let mut staged = Vec::new();
+                let mut encoder = Encoder::new(&mut staged, self.header.compress_level)?;
+                std::io::copy(&mut tee_reader, &mut encoder)?;
+                encoder.finish()?;
+                (volume, data_offset) = out_file.reserve(staged.len() as u64)?;
+                out_file.write_all(&staged)?;
+                data_length = staged.len() as u64;
+            } else {
+                let size = match self.header.compress {
+                    true => 0,
+                    false => len,
+                };
+                (volume, data_offset) = out_file.reserve(size)?;
                 let mut out = WriteCounter::new(&mut out_file);
-                let file = File::open(real_path)?;
-                let mut tee_reader = TeeReader::new(file);
                 if self.header.compress {
                     let mut encoder = Encoder::new(&mut out, self.header.compress_level)?;
                     std::io::copy(&mut tee_reader, &mut encoder)?;
@@ -156,26 +329,41 @@ impl ResourcePackage {
                 } else {
                     std::io::copy(&mut tee_reader, &mut out)?;
                 };
-                let entry = FileEntry {
-                    path: rel_path.clone(),
-                    data_offset: offset,
-                    data_length: out.bytes_written(),
-                    hash: tee_reader.finalize(),
-                };
-                log::debug!(
-                    "add {} to pack size: {}, offset: {}",
-                    rel_path,
-                    out.bytes_written(),
-                    offset
-                );
-                offset += out.bytes_written();
-                entries.push(entry);
+                data_length = out.bytes_written();
             }
+            log::debug!(
+                "add {} to pack size: {}, volume: {}, offset: {}",
+                rel_path,
+                data_length,
+                volume,
+                data_offset
+            );
+            let hash = tee_reader.finalize();
+            if self.dedup {
+                stored_lens.insert(len);
+                stored.insert(hash, (volume, data_offset, data_length));
+            }
+            entries.push(FileEntry {
+                path: rel_path,
+                volume,
+                data_offset,
+                data_length,
+                hash,
+            });
         }
         self.header.file_count = entries.len() as u32;
-        self.header.timestamp = Utc::now().into();
+        self.header.flags.dedup = self.dedup;
+        self.header.timestamp = self.source_date_epoch.unwrap_or_else(Utc::now).into();
 
         let entry_bytes = bincode::encode_to_vec(&entries, standard())?;
+        // the index is kept whole in the last volume, sized with one volume
+        // more than needed as varints only grow with the count
+        self.header.volume_count = out_file.volume_count() + 1;
+        let index_len = bincode::encode_to_vec(&self.header, standard())?.len()
+            + entry_bytes.len()
+            + FOOTER_LEN;
+        out_file.reserve(index_len as u64)?;
+        self.header.volume_count = out_file.volume_count();
         let header_bytes = bincode::encode_to_vec(&self.header, standard())?;
 
         out_file.write_all(&header_bytes)?;
@@ -191,19 +379,18 @@ impl ResourcePackage {
             f
         };
         out_file.write_all(&footer)?;
-
-        out_file.sync_data()?;
-        #[cfg(target_os = "linux")]
-        {
-            self.total_size = out_file.metadata()?.size();
+        if out_file.is_split() {
+            log::debug!("pak split into {} volumes", out_file.volume_count());
         }
+        self.total_size = out_file.finish()?;
+        let manifest_path = Manifest::path_for(&path);
+        Manifest::from_entries(&entries).save(&manifest_path)?;
+        log::debug!("manifest written to {}", manifest_path.display());
         Ok(())
     }
 }
 impl ResourcePackage {
-    pub fn from_pak(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
-        let path = path.into();
-        let mut file = File::open(&path)?;
+    fn read_index<R: Read + Seek>(file: &mut R) -> anyhow::Result<(PackageHeader, Vec<FileEntry>)> {
         let mut footer = [0u8; FOOTER_LEN];
         file.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
         file.read_exact(&mut footer)?;
@@ -223,17 +410,101 @@ impl ResourcePackage {
             bincode::decode_from_slice(&buf[..header_len as usize], standard())?;
         let (entries, _): (Vec<FileEntry>, usize) =
             bincode::decode_from_slice(&buf[header_len as usize..], standard())?;
-        #[cfg(target_os = "linux")]
-        let size = file.metadata().and_then(|s| Ok(s.size())).unwrap_or(0);
-        #[cfg(not(target_os = "linux"))]
-        let size = 0;
+        Ok((header, entries))
+    }
+    pub fn from_pak(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        // a split set is read through its `.NNN` volumes
+        let mut file = VolumeReader::open(&path)?;
+        let (header, entries) = Self::read_index(&mut file)?;
+        Self::check_volumes(&header, &file)?;
         Ok(Self {
-            files: HashMap::new(),
+            files: BTreeMap::new(),
             entrys: entries,
+            dedup: header.flags.dedup,
             header,
             input: path,
             output: PathBuf::new(),
-            total_size: size,
+            total_size: file.size(),
+            volume_size: None,
+            source_date_epoch: None,
+        })
+    }
+    fn check_volumes(header: &PackageHeader, file: &VolumeReader) -> anyhow::Result<()> {
+        if header.volume_count != file.volume_count() {
+            anyhow::bail!(
+                "Invalid package: expect {} volumes, found {}",
+                header.volume_count,
+                file.volume_count()
+            );
+        }
+        Ok(())
+    }
+    /// reads a single entry, opening only the volumes it is stored in
+    pub fn get_file(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+        let entry = self
+            .entrys
+            .iter()
+            .find(|entry| entry.path == path)
+            .ok_or_else(|| anyhow::anyhow!("{} not found in package", path))?;
+        let mut file = VolumeReader::open(&self.input)?;
+        file.seek_volume(entry.volume, entry.data_offset)?;
+        let mut mem = Vec::new();
+        let hash = {
+            let mut writer = TeeWriter::new(&mut mem);
+            let mut sized_file = file.take(entry.data_length);
+            if self.header.compress {
+                let mut decoder = Decoder::new(&mut sized_file)?;
+                std::io::copy(&mut decoder, &mut writer)?;
+            } else {
+                std::io::copy(&mut sized_file, &mut writer)?;
+            }
+            writer.flush()?;
+            writer.finalize()
+        };
+        if entry.hash != hash {
+            anyhow::bail!("SHA256 checksum mismatch for file: {}", entry.path);
+        }
+        Ok(mem)
+    }
+    /// memory map the pak, entries are read on demand instead of up front
+    pub fn open_mapped(path: impl Into<PathBuf>) -> anyhow::Result<MappedPackage> {
+        let path = path.into();
+        let mut file = VolumeReader::open(&path)?;
+        let (header, entries) = Self::read_index(&mut file)?;
+        Self::check_volumes(&header, &file)?;
+        let volumes = file
+            .paths()
+            .map(|path| {
+                let volume = File::open(path)?;
+                // the pak is read only, it must not be modified while the engine runs
+                Ok(Arc::new(unsafe { Mmap::map(&volume)? }))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let starts = volumes
+            .iter()
+            .scan(0u64, |start, volume| {
+                let current = *start;
+                *start += volume.len() as u64;
+                Some(current)
+            })
+            .collect::<Vec<_>>();
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                let end = starts
+                    .get(entry.volume as usize)
+                    .map(|start| start + entry.data_offset + entry.data_length);
+                if end.is_none_or(|end| end > file.size()) {
+                    anyhow::bail!("Invalid package: entry {} out of bounds", entry.path);
+                }
+                Ok((entry.path.clone(), entry))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        Ok(MappedPackage {
+            header,
+            entries,
+            volumes,
         })
     }
     pub fn unpack2dir(&self, output: impl Into<PathBuf>) -> anyhow::Result<()> {
@@ -242,7 +513,7 @@ impl ResourcePackage {
             std::fs::create_dir_all(&out)?;
         }
 
-        let mut file = File::open(&self.input)?;
+        let mut file = VolumeReader::open(&self.input)?;
         for entry in &self.entrys {
             let full_path = out.join(&entry.path);
             if let Some(p) = full_path.parent() {
@@ -250,7 +521,7 @@ impl ResourcePackage {
                     std::fs::create_dir_all(p)?;
                 }
             }
-            file.seek(SeekFrom::Start(entry.data_offset))?;
+            file.seek_volume(entry.volume, entry.data_offset)?;
             let mut f = std::fs::File::options()
                 .write(true)
                 .create(true)
@@ -278,11 +549,22 @@ impl ResourcePackage {
 
         Ok(())
     }
-    pub fn unpack2memory(&self) -> anyhow::Result<HashMap<String, Vec<u8>>> {
-        let mut file = File::open(&self.input)?;
+    /// deduplicated entries share the buffer read for the first of them
+    pub fn unpack2memory(&self) -> anyhow::Result<MemResource> {
+        let mut file = VolumeReader::open(&self.input)?;
         let mut resource = HashMap::default();
+        let mut shared: HashMap<DataLocation, (Sha256Digest, Arc<[u8]>)> = HashMap::new();
         for entry in &self.entrys {
-            file.seek(SeekFrom::Start(entry.data_offset))?;
+            let key = (entry.volume, entry.data_offset, entry.data_length);
+            if let Some((hash, buffer)) = shared.get(&key) {
+                if entry.hash != *hash {
+                    anyhow::bail!("SHA256 checksum mismatch for file: {}", entry.path);
+                }
+                log::debug!("unpack file: {}, shared: {}", entry.path, buffer.len());
+                resource.insert(entry.path.clone(), buffer.clone());
+                continue;
+            }
+            file.seek_volume(entry.volume, entry.data_offset)?;
             let mut mem = BufWriter::new(Vec::new());
             let hash = {
                 let mut writer = TeeWriter::new(&mut mem);
@@ -296,11 +578,14 @@ impl ResourcePackage {
                 writer.flush()?;
                 writer.finalize()
             };
-            let buffer = mem.into_inner()?;
+            let buffer: Arc<[u8]> = mem.into_inner()?.into();
             if !(entry.hash == hash) {
                 anyhow::bail!("SHA256 checksum mismatch for file: {}", entry.path);
             }
             log::debug!("unpack file: {}, size: {}", entry.path, buffer.len());
+            if self.header.flags.dedup {
+                shared.insert(key, (hash, buffer.clone()));
+            }
             resource.insert(entry.path.clone(), buffer);
         }
 
@@ -309,4 +594,437 @@ impl ResourcePackage {
     pub fn info(&self) -> &PackageHeader {
         &self.header
     }
+    /// entries added, removed and changed from `old` to `new` by path and hash,
+    /// nothing is read besides the indexes
+    pub fn diff(old: &Self, new: &Self) -> Diff {
+        Manifest::from_entries(&new.entrys).diff(&Manifest::from_entries(&old.entrys))
+    }
+    /// writes `base` with `patch` applied to `output` as a single volume, compressed
+    /// like `base`, the result is read back and every entry checked against the hash
+    /// it should end up with
+    pub fn apply_patch(
+        base: &Self,
+        patch: &Patch,
+        output: impl Into<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let output = output.into();
+        let mut expected = Manifest::from_entries(&base.entrys);
+        for path in &patch.removed {
+            if expected.entries.remove(path).is_none() {
+                anyhow::bail!("patch removes {}, not in {}", path, base.input.display());
+            }
+        }
+        for (path, file) in &patch.files {
+            file.verify(path)?;
+            expected.insert(path.clone(), file.hash);
+        }
+        let mut pak = Self::create_pak(
+            PathBuf::new(),
+            &output,
+            base.header.compress,
+            base.header.compress_level,
+            None,
+        );
+        pak.header.resource_id = base.header.resource_id.clone();
+        let sources = expected.entries.keys().map(|path| {
+            let data = match patch.files.get(path) {
+                Some(file) => Cow::Borrowed(file.data.as_slice()),
+                None => Cow::Owned(base.get_file(path)?),
+            };
+            Ok((path.clone(), EntrySource::Memory(data)))
+        });
+        pak.write_entries(sources)?;
+
+        let patched = Self::from_pak(&output)?;
+        let written = Manifest::from_entries(&patched.entrys);
+        if written != expected {
+            let diff = written.diff(&expected);
+            anyhow::bail!(
+                "patched {} does not match, added: {:?}, removed: {:?}, changed: {:?}",
+                output.display(),
+                diff.added,
+                diff.removed,
+                diff.changed
+            );
+        }
+        Ok(patched)
+    }
+}
+
+pub struct MappedPackage {
+    pub header: PackageHeader,
+    entries: HashMap<String, FileEntry>,
+    volumes: Vec<Arc<Mmap>>,
+}
+impl std::fmt::Debug for MappedPackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MappedPackage({})", self.header.resource_id)
+    }
+}
+impl MappedPackage {
+    pub fn entry(&self, path: &str) -> Option<&FileEntry> {
+        self.entries.get(path)
+    }
+    pub fn entries(&self) -> impl Iterator<Item = &FileEntry> {
+        self.entries.values()
+    }
+    pub fn compressed(&self) -> bool {
+        self.header.compress
+    }
+    pub fn volume_count(&self) -> usize {
+        self.volumes.len()
+    }
+    /// mapping and range of the stored (maybe compressed) entry data,
+    /// `None` for entries spanning volumes
+    pub fn mapped_range(&self, entry: &FileEntry) -> Option<(Arc<Mmap>, Range<usize>)> {
+        let mmap = self.volumes.get(entry.volume as usize)?;
+        let start = entry.data_offset as usize;
+        let end = start + entry.data_length as usize;
+        (end <= mmap.len()).then(|| (mmap.clone(), start..end))
+    }
+    /// borrowed from the mapping, entries spanning volumes are joined
+    pub fn raw(&self, entry: &FileEntry) -> Cow<'_, [u8]> {
+        let volumes = &self.volumes[entry.volume as usize..];
+        let start = entry.data_offset as usize;
+        let end = start + entry.data_length as usize;
+        if end <= volumes[0].len() {
+            return Cow::Borrowed(&volumes[0][start..end]);
+        }
+        let mut joined = Vec::with_capacity(entry.data_length as usize);
+        let mut offset = start;
+        for mmap in volumes {
+            let left = entry.data_length as usize - joined.len();
+            let end = mmap.len().min(offset + left);
+            joined.extend_from_slice(&mmap[offset.min(end)..end]);
+            offset = 0;
+            if joined.len() == entry.data_length as usize {
+                break;
+            }
+        }
+        Cow::Owned(joined)
+    }
+    /// decompress a compressed entry, verifying its checksum
+    pub fn decompress(&self, entry: &FileEntry) -> anyhow::Result<Vec<u8>> {
+        let mut mem = Vec::new();
+        let hash = {
+            let mut writer = TeeWriter::new(&mut mem);
+            let raw = self.raw(entry);
+            let mut decoder = Decoder::new(raw.as_ref())?;
+            std::io::copy(&mut decoder, &mut writer)?;
+            writer.flush()?;
+            writer.finalize()
+        };
+        if entry.hash != hash {
+            anyhow::bail!("SHA256 checksum mismatch for file: {}", entry.path);
+        }
+        Ok(mem)
+    }
+    pub fn check_header(&self) -> anyhow::Result<()> {
+        if &self.header.magic != MAGIC {
+            anyhow::bail!("Invalid package: wrong header magic");
+        }
+        if self.header.version != VERSION {
+            anyhow::bail!(
+                "Invalid package: unsupported version {}",
+                self.header.version_string()
+            );
+        }
+        if self.header.file_count as usize != self.entries.len() {
+            anyhow::bail!(
+                "Invalid package: header lists {} files, index has {}",
+                self.header.file_count,
+                self.entries.len()
+            );
+        }
+        Ok(())
+    }
+    /// verify the checksum of an entry whether compressed or not
+    pub fn verify_entry(&self, entry: &FileEntry) -> anyhow::Result<()> {
+        if self.compressed() {
+            self.decompress(entry).map(|_| ())
+        } else {
+            self.verify(entry)
+        }
+    }
+    /// checks `sample` evenly spread entries, or all of them when `None`,
+    /// returns every failure instead of stopping at the first
+    pub fn verify_entries(&self, sample: Option<usize>) -> Vec<(String, anyhow::Error)> {
+        let mut paths: Vec<&String> = self.entries.keys().collect();
+        paths.sort();
+        let stride = match sample {
+            Some(n) if n > 0 && n < paths.len() => paths.len().div_ceil(n),
+            _ => 1,
+        };
+        paths
+            .into_iter()
+            .step_by(stride)
+            .filter_map(|path| {
+                let entry = &self.entries[path];
+                self.verify_entry(entry)
+                    .err()
+                    .map(|err| (path.clone(), err))
+            })
+            .collect()
+    }
+    /// verify an uncompressed entry in place
+    pub fn verify(&self, entry: &FileEntry) -> anyhow::Result<()> {
+        let hash: Sha256Digest = Sha256::digest(self.raw(entry)).into();
+        if entry.hash != hash {
+            anyhow::bail!("SHA256 checksum mismatch for file: {}", entry.path);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_split_volumes() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("packtool_test_split_volumes");
+    let input = dir.join("assets");
+    std::fs::create_dir_all(input.join("image"))?;
+    let big: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    std::fs::write(input.join("image/big.bin"), &big)?;
+    std::fs::write(input.join("main.lua"), "return {}")?;
+    let output = dir.join("assets.pak");
+    std::fs::write(input.join("zz.txt"), "after the big one")?;
+    let mut pak = ResourcePackage::create_pak(&input, &output, false, 0, Some(4096));
+    pak.pack()?;
+    let volumes = volume_paths(&output);
+    assert!(!output.exists() && volumes.len() >= 3);
+    assert_eq!(volumes[0], volume_path(&output, 0));
+    assert!(volumes[0].to_string_lossy().ends_with(".pak.001"));
+    for volume in &volumes {
+        assert!(std::fs::metadata(volume)?.len() <= 4096);
+    }
+
+    // opened from the first volume, the siblings are found next to it
+    let pak = ResourcePackage::from_pak(&volumes[0])?;
+    assert_eq!(pak.header.volume_count as usize, volumes.len());
+    assert_eq!(pak.get_file("zz.txt")?, b"after the big one");
+    assert_eq!(pak.get_file("image/big.bin")?, big);
+    let files = pak.unpack2memory()?;
+    assert_eq!(*files["main.lua"], *b"return {}");
+    pak.unpack2dir(dir.join("out"))?;
+    assert_eq!(std::fs::read(dir.join("out/image/big.bin"))?, big);
+    let mapped = ResourcePackage::open_mapped(&output)?;
+    assert!(mapped.verify_entries(None).is_empty());
+    // only the entry bigger than a volume spans
+    for small in ["main.lua", "zz.txt"] {
+        assert!(mapped.mapped_range(mapped.entry(small).unwrap()).is_some());
+    }
+    let big_entry = mapped.entry("image/big.bin").unwrap();
+    assert!(mapped.mapped_range(big_entry).is_none());
+    assert_eq!(mapped.raw(big_entry), big.as_slice());
+
+    // packing again unsplit removes the stale volumes
+    ResourcePackage::create_pak(&input, &output, true, 3, None).pack()?;
+    assert_eq!(volume_paths(&output), std::slice::from_ref(&output));
+    assert!(!volume_path(&output, 1).exists());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_reproducible_pack() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("packtool_test_reproducible_pack");
+    let input = dir.join("assets");
+    for name in ["b/two.txt", "a/one.txt", "c.lua", "a/z/three.bin"] {
+        let path = input.join(name);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, name.repeat(64))?;
+    }
+    let epoch = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let pack = |name: &str, compress: bool| -> anyhow::Result<Vec<u8>> {
+        let output = dir.join(name);
+        let mut pak = ResourcePackage::create_pak(&input, &output, compress, 3, None)
+            .with_source_date_epoch(Some(epoch));
+        pak.pack()?;
+        Ok(std::fs::read(output)?)
+    };
+    for compress in [false, true] {
+        let first = pack("first/assets.pak", compress)?;
+        let second = pack("second/assets.pak", compress)?;
+        assert!(first == second, "compress: {}", compress);
+    }
+    let pak = ResourcePackage::from_pak(dir.join("first/assets.pak"))?;
+    assert_eq!(DateTime::<Utc>::from(pak.header.timestamp.clone()), epoch);
+    let paths: Vec<&str> = pak.entrys.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["a/one.txt", "a/z/three.bin", "b/two.txt", "c.lua"]);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_diff() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("packtool_test_diff");
+    let build = |name: &str, files: &[(&str, &str)]| -> anyhow::Result<ResourcePackage> {
+        let input = dir.join(name);
+        for (path, data) in files {
+            let path = input.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, data)?;
+        }
+        let output = dir.join(format!("{}.pak", name));
+        ResourcePackage::create_pak(&input, &output, true, 3, None).pack()?;
+        ResourcePackage::from_pak(&output)
+    };
+    let old = build(
+        "old",
+        &[
+            ("main.lua", "return {}"),
+            ("image/hero.png", "hero v1"),
+            ("sound/gone.ogg", "removed"),
+        ],
+    )?;
+    let new = build(
+        "new",
+        &[
+            ("main.lua", "return {}"),
+            ("image/hero.png", "hero v2"),
+            ("image/new.png", "added"),
+        ],
+    )?;
+    let diff = ResourcePackage::diff(&old, &new);
+    assert_eq!(diff.added, ["image/new.png"]);
+    assert_eq!(diff.removed, ["sound/gone.ogg"]);
+    assert_eq!(diff.changed, ["image/hero.png"]);
+    // the other way round swaps added and removed
+    let back = ResourcePackage::diff(&new, &old);
+    assert_eq!((back.added, back.removed), (diff.removed, diff.added));
+    assert!(ResourcePackage::diff(&new, &new).is_empty());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_apply_patch() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("packtool_test_apply_patch");
+    let build = |name: &str, files: &[(&str, &str)]| -> anyhow::Result<ResourcePackage> {
+        let input = dir.join(name);
+        for (path, data) in files {
+            let path = input.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, data)?;
+        }
+        let output = dir.join(format!("{}.pak", name));
+        ResourcePackage::create_pak(&input, &output, true, 3, None).pack()?;
+        ResourcePackage::from_pak(&output)
+    };
+    let base = build(
+        "base",
+        &[
+            ("main.lua", "return {}"),
+            ("image/hero.png", "hero v1"),
+            ("sound/gone.ogg", "removed"),
+        ],
+    )?;
+    let target = build(
+        "target",
+        &[
+            ("main.lua", "return {}"),
+            ("image/hero.png", "hero v2"),
+            ("image/new.png", "added"),
+        ],
+    )?;
+    let patch = Patch::create(&base, &target)?;
+    // only what changed is shipped
+    let shipped: Vec<&str> = patch.files.keys().map(String::as_str).collect();
+    assert_eq!(shipped, ["image/hero.png", "image/new.png"]);
+    assert_eq!(patch.removed, ["sound/gone.ogg"]);
+    patch.save(dir.join("update.patch"), 3)?;
+    let patch = Patch::load(dir.join("update.patch"))?;
+
+    let patched = ResourcePackage::apply_patch(&base, &patch, dir.join("patched.pak"))?;
+    assert_eq!(
+        Manifest::from_entries(&patched.entrys),
+        Manifest::from_entries(&target.entrys)
+    );
+    assert!(ResourcePackage::diff(&target, &patched).is_empty());
+    assert_eq!(patched.get_file("image/hero.png")?, b"hero v2");
+    assert_eq!(patched.get_file("main.lua")?, b"return {}");
+    assert_eq!(patched.header.resource_id, base.header.resource_id);
+
+    // a patch made for another base or with broken data is refused
+    assert!(ResourcePackage::apply_patch(&patched, &patch, dir.join("twice.pak")).is_err());
+    let mut broken = patch.clone();
+    broken.files.get_mut("image/new.png").unwrap().data = b"other".to_vec();
+    assert!(ResourcePackage::apply_patch(&base, &broken, dir.join("broken.pak")).is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_dedup() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join("packtool_test_dedup");
+    let _ = std::fs::remove_dir_all(&dir);
+    let input = dir.join("assets");
+    let blank: Vec<u8> = (0..4096u32).map(|i| (i % 7) as u8).collect();
+    let tileset: Vec<u8> = (0..3000u32).map(|i| (i * 13 % 251) as u8).collect();
+    let mut files = vec![("main.lua".to_owned(), b"return {}".to_vec())];
+    for i in 0..30 {
+        files.push((format!("normal/{:02}.png", i), blank.clone()));
+    }
+    for level in ["one", "two", "three"] {
+        files.push((format!("level/{}/tiles.png", level), tileset.clone()));
+    }
+    // as long as the tileset but not the same
+    let mut other = tileset.clone();
+    other[0] ^= 1;
+    files.push(("level/other.png".to_owned(), other));
+    for (path, data) in &files {
+        let path = input.join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, data)?;
+    }
+    for compress in [false, true] {
+        let pack = |name: &str, dedup: bool| -> anyhow::Result<ResourcePackage> {
+            let output = dir.join(name);
+            ResourcePackage::create_pak(&input, &output, compress, 3, None)
+                .with_dedup(dedup)
+                .pack()?;
+            ResourcePackage::from_pak(&output)
+        };
+        let full = pack("full.pak", false)?;
+        let pak = pack("dedup.pak", true)?;
+        assert!(pak.header.flags.dedup && !full.header.flags.dedup);
+        assert_eq!(full.dedup_saved(), 0);
+        // 29 blank maps and 2 tilesets are not stored again, the indexes only
+        // differ in the offsets
+        let unshared = pak.total_size + pak.dedup_saved();
+        assert!(unshared.abs_diff(full.total_size) < 64);
+        if !compress {
+            assert_eq!(pak.dedup_saved(), 29 * 4096 + 2 * 3000);
+        }
+        assert_eq!(pak.entrys.len(), files.len());
+        let mapped = ResourcePackage::open_mapped(dir.join("dedup.pak"))?;
+        assert!(mapped.verify_entries(None).is_empty());
+
+        let memory = pak.unpack2memory()?;
+        let out = dir.join("out");
+        let _ = std::fs::remove_dir_all(&out);
+        pak.unpack2dir(&out)?;
+        for (path, data) in &files {
+            assert_eq!(*memory[path], *data.as_slice(), "{}", path);
+            assert_eq!(std::fs::read(out.join(path))?, *data, "{}", path);
+        }
+        // one buffer behind all the blank maps
+        assert!(Arc::ptr_eq(
+            &memory["normal/00.png"],
+            &memory["normal/29.png"]
+        ));
+        assert!(!Arc::ptr_eq(
+            &memory["level/one/tiles.png"],
+            &memory["level/other.png"]
+        ));
+    }
+    // headers from before the flags read with them off
+    let header = PackageHeader {
+        flags: HeaderFlags { dedup: true },
+        ..Default::default()
+    };
+    let bytes = bincode::encode_to_vec(&header, standard())?;
+    let (old, _): (PackageHeader, usize) =
+        bincode::decode_from_slice(&bytes[..bytes.len() - 1], standard())?;
+    assert!(!old.flags.dedup);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
 }
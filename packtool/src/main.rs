@@ -1,6 +1,6 @@
 use clap::Parser;
 use log::LevelFilter;
-use packtool::ResourcePackage;
+use packtool::{Diff, Patch, ResourcePackage};
 use prettytable::{Attr, Cell, Row, Table, color, row};
 use std::str::FromStr;
 #[derive(Parser, Debug, Clone)]
@@ -18,6 +18,16 @@ pub struct PackArgs {
     /// compress level
     #[arg(short = 'p', long, default_value_t = 10)]
     compress_level: u32,
+    /// split the output into volumes of at most this size, 512MiB, 1GiB ...
+    #[arg(short = 's', long, value_parser = parse_size)]
+    volume_size: Option<u64>,
+    /// unix seconds stamped instead of now for reproducible output,
+    /// falls back to the SOURCE_DATE_EPOCH environment variable
+    #[arg(short = 'e', long)]
+    source_date_epoch: Option<i64>,
+    /// store identical files separately, for comparing sizes
+    #[arg(long, default_value_t = false)]
+    no_dedup: bool,
     /// off, error, warn, info, debug, trace,
     #[arg(short = 'l', long, default_value = "info")]
     log_level: String,
@@ -50,11 +60,58 @@ pub struct UnPackArgs {
     #[arg(short = 's', long, default_value_t = false)]
     show: bool,
 }
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct DiffArgs {
+    /// the previous build
+    old: String,
+    /// the build to compare it with
+    new: String,
+    /// also write a patch turning old into new, see apply
+    #[arg(short = 'o', long)]
+    patch: Option<String>,
+    /// compress level of the patch
+    #[arg(short = 'p', long, default_value_t = 10)]
+    compress_level: u32,
+}
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct ApplyArgs {
+    /// the pak the patch was made from
+    base: String,
+    /// written by diff
+    patch: String,
+    /// patched Assets output
+    #[arg(short = 'o', long, default_value = "./assets.pak")]
+    output: String,
+}
 #[derive(Parser, Debug)]
 #[allow(non_camel_case_types)]
 pub enum Args {
     pack(PackArgs),
     unpack(UnPackArgs),
+    /// list the entries added, removed and changed between two paks
+    diff(DiffArgs),
+    /// write a pak with a patch applied to its base
+    apply(ApplyArgs),
+}
+fn parse_size(s: &str) -> Result<u64, String> {
+    byte_unit::Byte::parse_str(s, true)
+        .map(|byte| byte.as_u64())
+        .map_err(|err| err.to_string())
+}
+fn source_date_epoch(arg: Option<i64>) -> anyhow::Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let secs = match (arg, std::env::var("SOURCE_DATE_EPOCH")) {
+        (Some(secs), _) => secs,
+        (None, Ok(var)) => var
+            .trim()
+            .parse()
+            .map_err(|err| anyhow::anyhow!("invalid SOURCE_DATE_EPOCH {}: {}", var, err))?,
+        (None, Err(_)) => return Ok(None),
+    };
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(Some)
+        .ok_or_else(|| anyhow::anyhow!("source date epoch {} out of range", secs))
 }
 fn main() -> anyhow::Result<()> {
     match Args::parse() {
@@ -67,7 +124,10 @@ fn main() -> anyhow::Result<()> {
                 args.output,
                 args.compress,
                 args.compress_level as i32,
-            );
+                args.volume_size,
+            )
+            .with_source_date_epoch(source_date_epoch(args.source_date_epoch)?)
+            .with_dedup(!args.no_dedup);
             gp.pack()?;
             dump_info(&gp);
         }
@@ -84,6 +144,21 @@ fn main() -> anyhow::Result<()> {
                 gp.unpack2dir(args.out_put)?;
             }
         }
+        Args::diff(args) => {
+            let old = ResourcePackage::from_pak(&args.old)?;
+            let new = ResourcePackage::from_pak(&args.new)?;
+            dump_diff(&ResourcePackage::diff(&old, &new));
+            if let Some(path) = args.patch {
+                Patch::create(&old, &new)?.save(&path, args.compress_level as i32)?;
+                println!("patch written to {}", path);
+            }
+        }
+        Args::apply(args) => {
+            let base = ResourcePackage::from_pak(&args.base)?;
+            let patch = Patch::load(&args.patch)?;
+            let gp = ResourcePackage::apply_patch(&base, &patch, args.output)?;
+            dump_info(&gp);
+        }
     }
     Ok(())
 }
@@ -91,6 +166,8 @@ fn main() -> anyhow::Result<()> {
 pub fn dump_info(gp: &ResourcePackage) {
     let byte = byte_unit::Byte::from_u64(gp.total_size);
     let adjusted_byte = byte.get_appropriate_unit(byte_unit::UnitType::Binary);
+    let saved = byte_unit::Byte::from_u64(gp.dedup_saved())
+        .get_appropriate_unit(byte_unit::UnitType::Binary);
     let info = gp.info();
     let mut table = Table::new();
     table.set_titles(row![
@@ -99,6 +176,7 @@ pub fn dump_info(gp: &ResourcePackage) {
         "compressed",
         "level",
         "size",
+        "dedup saved",
         "date",
         "id"
     ]);
@@ -112,6 +190,8 @@ pub fn dump_info(gp: &ResourcePackage) {
             .with_style(Attr::ForegroundColor(color::BRIGHT_YELLOW)),
         Cell::new(format!("{:0.2}", adjusted_byte).as_str())
             .with_style(Attr::ForegroundColor(color::BRIGHT_YELLOW)),
+        Cell::new(format!("{:0.2}", saved).as_str())
+            .with_style(Attr::ForegroundColor(color::BRIGHT_GREEN)),
         Cell::new(
             format!(
                 "{}",
@@ -130,12 +210,14 @@ pub fn dump_info(gp: &ResourcePackage) {
 
 pub fn dump_files(gp: &ResourcePackage) {
     let mut table = Table::new();
-    table.set_titles(row!["path", "length", "sha256"]);
+    table.set_titles(row!["path", "volume", "length", "sha256"]);
     for entry in &gp.entrys {
         let byte = byte_unit::Byte::from_u64(entry.data_length);
         let adjusted_byte = byte.get_appropriate_unit(byte_unit::UnitType::Binary);
         table.add_row(Row::new(vec![
             Cell::new(entry.path.as_str()).with_style(Attr::ForegroundColor(color::WHITE)),
+            Cell::new(format!("{:03}", entry.volume + 1).as_str())
+                .with_style(Attr::ForegroundColor(color::WHITE)),
             Cell::new(format!("{:0.2}", adjusted_byte).as_str())
                 .with_style(Attr::ForegroundColor(color::BRIGHT_GREEN)),
             Cell::new(format!("{}", hex::encode(entry.hash)).as_str())
@@ -144,3 +226,32 @@ pub fn dump_files(gp: &ResourcePackage) {
     }
     table.printstd();
 }
+
+pub fn dump_diff(diff: &Diff) {
+    if diff.is_empty() {
+        println!("no changes");
+        return;
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["change", "path"]);
+    let lists = [
+        ("added", &diff.added, color::BRIGHT_GREEN),
+        ("removed", &diff.removed, color::BRIGHT_RED),
+        ("changed", &diff.changed, color::BRIGHT_YELLOW),
+    ];
+    for (change, paths, color) in lists {
+        for path in paths {
+            table.add_row(Row::new(vec![
+                Cell::new(change).with_style(Attr::ForegroundColor(color)),
+                Cell::new(path.as_str()).with_style(Attr::ForegroundColor(color::WHITE)),
+            ]));
+        }
+    }
+    table.printstd();
+    println!(
+        "{} added, {} removed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+}
@@ -0,0 +1,104 @@
+use crate::{FileEntry, Sha256Digest};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// every packed path with its sha256, written next to the pak in the
+/// `sha256sum` format so it can also be checked by hand
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub entries: BTreeMap<String, Sha256Digest>,
+}
+
+impl Manifest {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a FileEntry>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry.hash))
+                .collect(),
+        }
+    }
+    /// `assets.pak` -> `assets.manifest`
+    pub fn path_for(pak: impl AsRef<Path>) -> PathBuf {
+        pak.as_ref().with_extension("manifest")
+    }
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+    pub fn insert(&mut self, path: impl Into<String>, hash: Sha256Digest) {
+        self.entries.insert(path.into(), hash);
+    }
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+    /// what changed going from `old` to `self`
+    pub fn diff(&self, old: &Manifest) -> Diff {
+        let mut diff = Diff::default();
+        for (path, hash) in &self.entries {
+            match old.entries.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(old_hash) if old_hash != hash => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .entries
+            .keys()
+            .filter(|path| !self.contains(path))
+            .cloned()
+            .collect();
+        diff
+    }
+}
+
+/// entry paths that differ between two builds, each list in path order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// in both with a different sha256
+    pub changed: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for Manifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (path, hash) in &self.entries {
+            writeln!(f, "{}  {}", hex::encode(hash), path)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut manifest = Manifest::default();
+        for (index, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (hash, path) = line.split_once("  ").ok_or_else(|| {
+                anyhow::anyhow!("manifest line {}: expect `hash  path`", index + 1)
+            })?;
+            let mut digest = Sha256Digest::default();
+            hex::decode_to_slice(hash, &mut digest)
+                .map_err(|err| anyhow::anyhow!("manifest line {}: {}", index + 1, err))?;
+            manifest.insert(path, digest);
+        }
+        Ok(manifest)
+    }
+}
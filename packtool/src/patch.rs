@@ -0,0 +1,74 @@
+use crate::{ResourcePackage, Sha256Digest};
+use bincode::{Decode, Encode, config::standard};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, path::Path};
+
+const PATCH_MAGIC: &[u8; 4] = b"GPAT";
+const PATCH_VERSION: [u8; 4] = [0, 0, 0, 1];
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct PatchFile {
+    pub hash: Sha256Digest,
+    pub data: Vec<u8>,
+}
+
+impl PatchFile {
+    pub fn verify(&self, path: &str) -> anyhow::Result<()> {
+        let hash: Sha256Digest = Sha256::digest(&self.data).into();
+        if self.hash != hash {
+            anyhow::bail!("SHA256 checksum mismatch for patched file: {}", path);
+        }
+        Ok(())
+    }
+}
+
+/// what turns one build into another, the data of the added and changed entries
+/// and the paths removed, see `ResourcePackage::apply_patch`
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct Patch {
+    pub files: BTreeMap<String, PatchFile>,
+    pub removed: Vec<String>,
+}
+
+impl Patch {
+    /// reads the added and changed entries out of `new`
+    pub fn create(old: &ResourcePackage, new: &ResourcePackage) -> anyhow::Result<Self> {
+        let diff = ResourcePackage::diff(old, new);
+        let mut files = BTreeMap::new();
+        for path in diff.added.into_iter().chain(diff.changed) {
+            let data = new.get_file(&path)?;
+            let hash = Sha256::digest(&data).into();
+            files.insert(path, PatchFile { hash, data });
+        }
+        Ok(Self {
+            files,
+            removed: diff.removed,
+        })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.removed.is_empty()
+    }
+    /// zstd compressed with `level`
+    pub fn save(&self, path: impl AsRef<Path>, level: i32) -> anyhow::Result<()> {
+        let mut out = Vec::from(*PATCH_MAGIC);
+        out.extend(PATCH_VERSION);
+        let body = bincode::encode_to_vec(self, standard())?;
+        out.extend(zstd::encode_all(body.as_slice(), level)?);
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read(path)?;
+        let (magic, rest) = data.split_at_checked(4).unwrap_or_default();
+        if magic != PATCH_MAGIC {
+            anyhow::bail!("Invalid patch: missing magic");
+        }
+        let (version, body) = rest.split_at_checked(4).unwrap_or_default();
+        if version != PATCH_VERSION {
+            anyhow::bail!("Unsupported patch version: {:?}", version);
+        }
+        let body = zstd::decode_all(body)?;
+        let (patch, _) = bincode::decode_from_slice(&body, standard())?;
+        Ok(patch)
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// `assets.pak` -> `assets.pak.001`, volume 0 is `.001`
+pub fn volume_path(base: &Path, volume: u32) -> PathBuf {
+    let mut name = OsString::from(base.as_os_str());
+    name.push(format!(".{:03}", volume + 1));
+    PathBuf::from(name)
+}
+
+/// `assets.pak.001` -> `assets.pak`, other paths are returned as they are
+pub fn volume_base(path: &Path) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.len() == 3 && ext.bytes().all(|b| b.is_ascii_digit()) => {
+            path.with_extension("")
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// the single pak, or every `.NNN` volume in order
+pub fn volume_paths(base: &Path) -> Vec<PathBuf> {
+    if base.is_file() {
+        return vec![base.to_path_buf()];
+    }
+    (0..)
+        .map(|volume| volume_path(base, volume))
+        .take_while(|path| path.is_file())
+        .collect()
+}
+
+/// writes entries into volumes of at most `volume_size` bytes,
+/// an entry only spans volumes when it alone is bigger than the cap
+pub struct VolumeWriter {
+    base: PathBuf,
+    volume_size: Option<u64>,
+    current: File,
+    volume: u32,
+    written: u64,
+    total: u64,
+}
+
+impl VolumeWriter {
+    pub fn create(base: impl Into<PathBuf>, volume_size: Option<u64>) -> anyhow::Result<Self> {
+        let base = base.into();
+        if volume_size == Some(0) {
+            anyhow::bail!("volume size must be greater than 0");
+        }
+        // leftovers of an earlier pack would be read as part of this one
+        for stale in volume_paths(&base) {
+            std::fs::remove_file(stale)?;
+        }
+        let current = match volume_size {
+            Some(_) => File::create(volume_path(&base, 0))?,
+            None => File::create(&base)?,
+        };
+        Ok(Self {
+            base,
+            volume_size,
+            current,
+            volume: 0,
+            written: 0,
+            total: 0,
+        })
+    }
+    fn next_volume(&mut self) -> io::Result<()> {
+        self.current.sync_data()?;
+        self.volume += 1;
+        self.current = File::create(volume_path(&self.base, self.volume))?;
+        self.written = 0;
+        Ok(())
+    }
+    /// starts a new volume when `len` more bytes do not fit the current one,
+    /// returns the volume and offset the next write lands at
+    pub fn reserve(&mut self, len: u64) -> io::Result<(u32, u64)> {
+        if let Some(volume_size) = self.volume_size {
+            // a full volume is left even for empty entries, offsets stay inside it
+            if self.written > 0 && (self.written + len > volume_size || self.written >= volume_size)
+            {
+                self.next_volume()?;
+            }
+        }
+        Ok((self.volume, self.written))
+    }
+    pub fn is_split(&self) -> bool {
+        self.volume_size.is_some()
+    }
+    pub fn volume_count(&self) -> u32 {
+        self.volume + 1
+    }
+    /// syncs the last volume, returns the size of all volumes
+    pub fn finish(self) -> anyhow::Result<u64> {
+        self.current.sync_data()?;
+        Ok(self.total)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = match self.volume_size {
+            Some(volume_size) => {
+                // continuation of an entry bigger than a whole volume
+                if self.written >= volume_size {
+                    self.next_volume()?;
+                }
+                buf.len().min((volume_size - self.written) as usize)
+            }
+            None => buf.len(),
+        };
+        let n = self.current.write(&buf[..len])?;
+        self.written += n as u64;
+        self.total += n as u64;
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.current.flush()
+    }
+}
+
+struct Volume {
+    path: PathBuf,
+    // offset of the volume when all volumes are read back to back
+    start: u64,
+    size: u64,
+    file: Option<File>,
+}
+
+/// reads a single pak or a set of volumes as one stream,
+/// volume files are only opened once read from
+pub struct VolumeReader {
+    volumes: Vec<Volume>,
+    pos: u64,
+    size: u64,
+}
+
+impl VolumeReader {
+    /// `path` is the pak or the first of its volumes
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let paths = volume_paths(&volume_base(path));
+        if paths.is_empty() {
+            anyhow::bail!(
+                "{} not found, neither as a file nor as volumes",
+                path.display()
+            );
+        }
+        let mut volumes = Vec::with_capacity(paths.len());
+        let mut size = 0;
+        for path in paths {
+            let len = std::fs::metadata(&path)?.len();
+            volumes.push(Volume {
+                path,
+                start: size,
+                size: len,
+                file: None,
+            });
+            size += len;
+        }
+        Ok(Self {
+            volumes,
+            pos: 0,
+            size,
+        })
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn volume_count(&self) -> u32 {
+        self.volumes.len() as u32
+    }
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.volumes.iter().map(|volume| volume.path.as_path())
+    }
+    /// moves to `offset` inside `volume`
+    pub fn seek_volume(&mut self, volume: u32, offset: u64) -> anyhow::Result<()> {
+        let start = self
+            .volumes
+            .get(volume as usize)
+            .map(|volume| volume.start)
+            .ok_or_else(|| anyhow::anyhow!("volume {:03} is missing", volume + 1))?;
+        self.pos = start + offset;
+        Ok(())
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let pos = self.pos;
+        let Some(volume) = self
+            .volumes
+            .iter_mut()
+            .find(|volume| pos >= volume.start && pos < volume.start + volume.size)
+        else {
+            return Ok(0);
+        };
+        if volume.file.is_none() {
+            volume.file = Some(File::open(&volume.path)?);
+        }
+        let file = volume.file.as_mut().unwrap();
+        let offset = pos - volume.start;
+        let len = buf.len().min((volume.size - offset) as usize);
+        file.seek(SeekFrom::Start(offset))?;
+        let n = file.read(&mut buf[..len])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the package",
+            )),
+        }
+    }
+}
@@ -1,4 +1,6 @@
+mod recent;
 mod startuproll;
+mod trigger;
 use log::{LevelFilter, SetLoggerError};
 use log4rs::{
     append::{
@@ -9,58 +11,199 @@ use log4rs::{
     encode::pattern::PatternEncoder,
     filter::threshold::ThresholdFilter,
 };
-use startuproll::{ModuleFilter, StartupRollTrigger};
+pub use recent::{RECENT_LINES, RecentAppender, RecentLines, recent_lines};
+pub use startuproll::{ModuleFilter, StartupRollTrigger};
+pub use trigger::{AnyTrigger, IntervalRollTrigger, SizeRollTrigger};
 
-const LOG_FILE_COUNT: u32 = 7;
+/// archives kept by default, older ones are deleted
+pub const LOG_FILE_COUNT: u32 = 7;
 const FORMAT: &str = "{h({d(%+)(utc)} [{f}:{L}:{T}] {l:<6} {M} {m})}{n}";
-pub fn log_init(
-    level: LevelFilter,
-    console: bool,
-    path: &str,
-    allow_modules: &[&str],
-) -> anyhow::Result<(), SetLoggerError> {
-    let stdout = ConsoleAppender::builder()
-        .target(Target::Stdout)
-        .encoder(Box::new(PatternEncoder::new(FORMAT)))
-        .build();
-    let module_filter = ModuleFilter::new(allow_modules);
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub level: LevelFilter,
+    /// to stdout instead of the file
+    pub console: bool,
+    pub path: String,
+    /// roll once the file reached this size
+    pub max_size_mb: Option<u64>,
+    /// roll on the first record after utc midnight
+    pub daily: bool,
+    /// archives kept, rolled into `<path>.0.gz` with the older ones shifted up
+    pub keep_files: u32,
+    /// module path prefixes logged, nothing is logged when empty
+    pub modules: Vec<String>,
+    /// also keep the last lines in `recent_lines`, wherever the log goes
+    pub recent_lines: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            console: false,
+            path: "./log.log".to_owned(),
+            max_size_mb: None,
+            daily: false,
+            keep_files: LOG_FILE_COUNT,
+            modules: Vec::new(),
+            recent_lines: true,
+        }
+    }
+}
+
+impl LogConfig {
+    /// the size and daily triggers, the last run's file is rolled on startup when
+    /// neither is set, with one set a crash loop keeps appending to the same file
+    fn trigger(&self) -> AnyTrigger {
+        let mut trigger = AnyTrigger::new();
+        if let Some(mb) = self.max_size_mb {
+            trigger = trigger.with(SizeRollTrigger::new(mb * 1024 * 1024));
+        }
+        if self.daily {
+            trigger = trigger.with(IntervalRollTrigger::daily(&self.path));
+        }
+        if trigger.is_empty() {
+            trigger = trigger.with(StartupRollTrigger::new(self.path.clone()));
+        }
+        trigger
+    }
+}
+
+pub fn build_config(log: &LogConfig) -> anyhow::Result<Config> {
+    let modules = log.modules.iter().map(String::as_str).collect::<Vec<_>>();
+    let module_filter = ModuleFilter::new(&modules);
     let config = Config::builder();
-    let config = match console {
-        true => config.appender(
-            Appender::builder()
-                .filter(Box::new(ThresholdFilter::new(level)))
-                .filter(Box::new(module_filter))
-                .build("stdout", Box::new(stdout)),
-        ),
+    let config = match log.console {
+        true => {
+            let stdout = ConsoleAppender::builder()
+                .target(Target::Stdout)
+                .encoder(Box::new(PatternEncoder::new(FORMAT)))
+                .build();
+            config.appender(
+                Appender::builder()
+                    .filter(Box::new(ThresholdFilter::new(log.level)))
+                    .filter(Box::new(module_filter))
+                    .build("stdout", Box::new(stdout)),
+            )
+        }
         false => {
-            let archive_pattern = format!("{}.{{}}.gz", path);
+            // the same names whatever rolled it, tools glob `*.gz`
+            let archive_pattern = format!("{}.{{}}.gz", log.path);
             let roller = FixedWindowRoller::builder()
                 .base(0)
-                .build(archive_pattern.as_str(), LOG_FILE_COUNT)
-                .unwrap();
-            let policy = CompoundPolicy::new(
-                Box::new(StartupRollTrigger::new(path.to_string())),
-                Box::new(roller),
-            );
+                .build(archive_pattern.as_str(), log.keep_files.max(1))?;
+            let policy = CompoundPolicy::new(Box::new(log.trigger()), Box::new(roller));
             let logfile = log4rs::append::rolling_file::RollingFileAppender::builder()
                 .encoder(Box::new(PatternEncoder::new(FORMAT)))
-                .build(path, Box::new(policy))
-                .unwrap();
+                .build(&log.path, Box::new(policy))?;
             config.appender(
                 Appender::builder()
-                    .filter(Box::new(ThresholdFilter::new(level)))
+                    .filter(Box::new(ThresholdFilter::new(log.level)))
                     .filter(Box::new(module_filter))
                     .build("logfile", Box::new(logfile)),
             )
         }
     };
-    let root = Root::builder();
-    let root = match console {
+    let (config, root) = match log.recent_lines {
+        true => (
+            config.appender(
+                Appender::builder()
+                    .filter(Box::new(ThresholdFilter::new(log.level)))
+                    .filter(Box::new(ModuleFilter::new(&modules)))
+                    .build(
+                        "recent",
+                        Box::new(RecentAppender::new(Box::new(PatternEncoder::new(FORMAT)))),
+                    ),
+            ),
+            Root::builder().appender("recent"),
+        ),
+        false => (config, Root::builder()),
+    };
+    let root = match log.console {
         true => root.appender("stdout"),
         false => root.appender("logfile"),
     }
-    .build(level);
-    let config = config.build(root).unwrap();
+    .build(log.level);
+    Ok(config.build(root)?)
+}
+
+pub fn log_init_with_config(config: &LogConfig) -> anyhow::Result<()> {
+    log4rs::init_config(build_config(config)?)?;
+    Ok(())
+}
+
+pub fn log_init(
+    level: LevelFilter,
+    console: bool,
+    path: &str,
+    allow_modules: &[&str],
+) -> anyhow::Result<(), SetLoggerError> {
+    let config = build_config(&LogConfig {
+        level,
+        console,
+        path: path.to_owned(),
+        modules: allow_modules.iter().map(|m| m.to_string()).collect(),
+        ..Default::default()
+    })
+    .unwrap();
     log4rs::init_config(config)?;
     Ok(())
 }
+
+#[test]
+fn test_size_roll() -> anyhow::Result<()> {
+    use log::Log;
+    let dir = std::env::temp_dir().join("test_size_roll");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("game.log").to_string_lossy().into_owned();
+    let config = LogConfig {
+        path: path.clone(),
+        max_size_mb: Some(1),
+        keep_files: 2,
+        modules: vec!["rolllog".to_owned()],
+        ..Default::default()
+    };
+    let logger = log4rs::Logger::new(build_config(&config)?);
+    let line = "x".repeat(1000);
+    let write = |count: usize, module: &str| {
+        for _ in 0..count {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("{}", line))
+                    .level(log::Level::Info)
+                    .module_path(Some(module))
+                    .build(),
+            );
+        }
+    };
+    let archives = || -> anyhow::Result<Vec<String>> {
+        let mut names = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".gz"))
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    };
+    let size = || std::fs::metadata(&path).map(|meta| meta.len());
+    // under the limit nothing rolls
+    write(500, "rolllog");
+    assert!(archives()?.is_empty());
+    // filtered out, nothing written
+    let before = size()?;
+    write(2000, "other");
+    assert_eq!(size()?, before);
+
+    // past the limit once, the active file starts over
+    write(700, "rolllog");
+    assert_eq!(archives()?, ["game.log.0.gz"]);
+    assert!(size()? < 1024 * 1024 / 2);
+
+    // rolled three times, only the two newest are kept
+    write(2200, "rolllog");
+    assert_eq!(archives()?, ["game.log.0.gz", "game.log.1.gz"]);
+    assert!(size()? < 1024 * 1024);
+    Ok(())
+}
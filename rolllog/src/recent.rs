@@ -0,0 +1,98 @@
+use log4rs::{
+    append::Append,
+    encode::{Encode, writer::simple::SimpleWriter},
+};
+use std::sync::{
+    Mutex, OnceLock, TryLockError,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// lines kept by `recent_lines`
+pub const RECENT_LINES: usize = 200;
+
+/// the last lines logged, for crash reports. A writer only locks the slot it fills,
+/// `lines` never waits on a lock, so a panic while one is held can't hang it
+#[derive(Debug)]
+pub struct RecentLines {
+    slots: Box<[Mutex<String>]>,
+    // lines pushed so far, the next one goes to `next % slots.len()`
+    next: AtomicUsize,
+}
+
+impl RecentLines {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| Mutex::default()).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+    pub fn push(&self, line: String) {
+        if self.slots.is_empty() {
+            return;
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.slots.len();
+        let mut slot = self.slots[index]
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        *slot = line;
+    }
+    /// oldest first, a line being written right now is left out
+    pub fn lines(&self) -> Vec<String> {
+        let next = self.next.load(Ordering::Relaxed);
+        (next.saturating_sub(self.slots.len())..next)
+            .filter_map(|index| {
+                let slot = &self.slots[index % self.slots.len()];
+                match slot.try_lock() {
+                    Ok(slot) => Some(slot.clone()),
+                    Err(TryLockError::Poisoned(err)) => Some(err.into_inner().clone()),
+                    Err(TryLockError::WouldBlock) => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// the lines every logger built with `LogConfig::recent_lines` writes to
+pub fn recent_lines() -> &'static RecentLines {
+    static RECENT: OnceLock<RecentLines> = OnceLock::new();
+    RECENT.get_or_init(|| RecentLines::new(RECENT_LINES))
+}
+
+/// formats like the other appenders, without colors
+#[derive(Debug)]
+pub struct RecentAppender {
+    encoder: Box<dyn Encode>,
+}
+
+impl RecentAppender {
+    pub fn new(encoder: Box<dyn Encode>) -> Self {
+        Self { encoder }
+    }
+}
+
+impl Append for RecentAppender {
+    fn append(&self, record: &log::Record) -> anyhow::Result<()> {
+        let mut writer = SimpleWriter(Vec::new());
+        self.encoder.encode(&mut writer, record)?;
+        let line = String::from_utf8_lossy(&writer.0);
+        recent_lines().push(line.trim_end().to_owned());
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn test_recent_lines() {
+    let recent = RecentLines::new(3);
+    assert!(recent.lines().is_empty());
+    for line in ["one", "two", "three", "four"] {
+        recent.push(line.to_owned());
+    }
+    assert_eq!(recent.lines(), ["two", "three", "four"]);
+    // held by a writer, e.g. one that panicked, the others are still read
+    let held = recent.slots[1].lock().unwrap();
+    assert_eq!(recent.lines(), ["three", "four"]);
+    drop(held);
+    RecentLines::new(0).push("nowhere".to_owned());
+}
@@ -0,0 +1,107 @@
+use log4rs::append::rolling_file::{LogFile, policy::compound::trigger::Trigger};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// rolls once the active file reached `limit` bytes, checked before each record
+/// is written so the file ends up at most one record over
+#[derive(Debug)]
+pub struct SizeRollTrigger {
+    limit: u64,
+}
+
+impl SizeRollTrigger {
+    pub fn new(limit: u64) -> Self {
+        Self { limit }
+    }
+}
+
+impl Trigger for SizeRollTrigger {
+    fn is_pre_process(&self) -> bool {
+        true
+    }
+
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        Ok(self.limit > 0 && file.len_estimate() >= self.limit)
+    }
+}
+
+/// rolls on the first record of a new period, periods count from the unix epoch so
+/// a day of 86400 seconds rolls at utc midnight, the same clock the log lines use
+#[derive(Debug)]
+pub struct IntervalRollTrigger {
+    period: u64,
+    // the period the active file was last written in
+    current: AtomicU64,
+}
+
+impl IntervalRollTrigger {
+    /// a file left from an earlier period is rolled on the first record
+    pub fn new(log_path: &str, period: Duration) -> Self {
+        let period = period.as_secs().max(1);
+        let written = std::fs::metadata(Path::new(log_path))
+            .and_then(|meta| meta.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        Self {
+            period,
+            current: AtomicU64::new(secs_of(written) / period),
+        }
+    }
+    pub fn daily(log_path: &str) -> Self {
+        Self::new(log_path, Duration::from_secs(24 * 60 * 60))
+    }
+}
+
+fn secs_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Trigger for IntervalRollTrigger {
+    fn is_pre_process(&self) -> bool {
+        true
+    }
+
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        let now = secs_of(SystemTime::now()) / self.period;
+        let last = self.current.swap(now, Ordering::Relaxed);
+        // nothing to roll in an empty file
+        Ok(last != now && file.len_estimate() > 0)
+    }
+}
+
+/// rolls when any of its triggers fires, all of them are asked every time so each
+/// keeps its own state current
+#[derive(Debug, Default)]
+pub struct AnyTrigger {
+    triggers: Vec<Box<dyn Trigger>>,
+}
+
+impl AnyTrigger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with(mut self, trigger: impl Trigger) -> Self {
+        self.triggers.push(Box::new(trigger));
+        self
+    }
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+}
+
+impl Trigger for AnyTrigger {
+    // checked before the record, every trigger here works that way
+    fn is_pre_process(&self) -> bool {
+        true
+    }
+
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        let mut roll = false;
+        for trigger in &self.triggers {
+            roll |= trigger.trigger(file)?;
+        }
+        Ok(roll)
+    }
+}